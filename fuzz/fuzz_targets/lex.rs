@@ -0,0 +1,54 @@
+#![no_main]
+
+//! lexes arbitrary input to the end under `CheckedLexer`, which asserts the position
+//! invariants after every token. run with `cargo fuzz run lex`, seeding the corpus from
+//! `progs/` helps it get past the first few bytes.
+
+use libfuzzer_sys::fuzz_target;
+use mumbo_lang::lexer::{ControlBytePolicy, Lexer, LexerLimits, LexerOptions};
+use mumbo_lang::source_code::SourceCode;
+use mumbo_lang::test_util::checked_lexer::CheckedLexer;
+
+/// the first byte picks the lexer options, so every configuration gets fuzzed.
+fn options(flags: u8) -> LexerOptions {
+    let control_bytes = match flags & 0b11 {
+        0 => ControlBytePolicy::Accept,
+        1 => ControlBytePolicy::Warn,
+        _ => ControlBytePolicy::Reject,
+    };
+    let mut options = LexerOptions::new()
+        .control_bytes(control_bytes)
+        .error_recovery(flags & 0b100 != 0)
+        .unicode_identifiers(flags & 0b1000 != 0);
+    if flags & 0b1_0000 != 0 {
+        let limits = LexerLimits::new().max_token_len(32).max_string_len(16).max_comment_len(16).max_tokens(64);
+        options = options.limits(limits);
+    }
+    options
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&flags, data)) = data.split_first() else {
+        return;
+    };
+    let text = String::from_utf8_lossy(data);
+    let source = SourceCode::new(&text);
+    let mut lexer = CheckedLexer::from_lexer(Lexer::with_options(source.clone(), options(flags)), source);
+
+    // every token and every error except `Eof` makes progress, so this many calls always suffice
+    for _ in 0..=text.len() {
+        match lexer.lex_single_token() {
+            Ok(token) if token.is_identifier_extractable() => {
+                lexer.extract_literal().expect("extractable token without a literal");
+            }
+            Ok(_) => {}
+            Err(e) if e.is_eof() => {
+                assert!(lexer.is_at_end());
+                return;
+            }
+            Err(e) if e.is_limit_exceeded() => return,
+            Err(_) => {}
+        }
+    }
+    panic!("no `Eof` after {} calls\n\t{}", text.len() + 1, lexer.get_lexer_debug_state());
+});
@@ -0,0 +1,154 @@
+//! a global allocator that counts what goes through it. the library never installs it, that
+//! is up to the binary: `mumbo` does with the `alloc-profiling` feature. until something
+//! installs it every [`snapshot`] is all zeroes.
+
+use core::fmt::Display;
+use core::ops::Sub;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::alloc::{GlobalAlloc, Layout, System};
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static REALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static DEALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static BYTES_ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// forwards to [`System`] while counting every call, install it with `#[global_allocator]`.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    #[inline]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES_ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        // SAFETY: forwarded verbatim from our caller
+        unsafe { System.alloc(layout) }
+    }
+
+    #[inline]
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES_ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        // SAFETY: forwarded verbatim from our caller
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    #[inline]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        DEALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        // SAFETY: forwarded verbatim from our caller
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    #[inline]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        REALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        // only growth counts as newly allocated bytes
+        BYTES_ALLOCATED.fetch_add(new_size.saturating_sub(layout.size()), Ordering::Relaxed);
+        // SAFETY: forwarded verbatim from our caller
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+/// allocator counters at a point in time. subtract two snapshots to get the
+/// numbers for the stage in between.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct AllocationStats {
+    pub allocations: usize,
+    pub reallocations: usize,
+    pub deallocations: usize,
+    pub bytes_allocated: usize,
+}
+
+#[inline]
+pub fn snapshot() -> AllocationStats {
+    AllocationStats {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        reallocations: REALLOCATIONS.load(Ordering::Relaxed),
+        deallocations: DEALLOCATIONS.load(Ordering::Relaxed),
+        bytes_allocated: BYTES_ALLOCATED.load(Ordering::Relaxed),
+    }
+}
+
+impl Sub for AllocationStats {
+    type Output = AllocationStats;
+
+    #[inline]
+    fn sub(self, rhs: AllocationStats) -> AllocationStats {
+        AllocationStats {
+            allocations: self.allocations - rhs.allocations,
+            reallocations: self.reallocations - rhs.reallocations,
+            deallocations: self.deallocations - rhs.deallocations,
+            bytes_allocated: self.bytes_allocated - rhs.bytes_allocated,
+        }
+    }
+}
+
+impl Display for AllocationStats {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} allocs, {} reallocs, {} deallocs, {:.2} MB",
+            self.allocations,
+            self.reallocations,
+            self.deallocations,
+            self.bytes_allocated as f64 / 1000000.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::{GlobalAlloc, Layout};
+
+    use super::{AllocationStats, CountingAllocator, snapshot};
+
+    #[test]
+    fn snapshots_subtract_per_field() {
+        let before = AllocationStats {
+            allocations: 1,
+            reallocations: 2,
+            deallocations: 3,
+            bytes_allocated: 4,
+        };
+        let after = AllocationStats {
+            allocations: 11,
+            reallocations: 12,
+            deallocations: 13,
+            bytes_allocated: 14,
+        };
+        assert_eq!(
+            after - before,
+            AllocationStats {
+                allocations: 10,
+                reallocations: 10,
+                deallocations: 10,
+                bytes_allocated: 10,
+            }
+        );
+    }
+
+    #[test]
+    fn counts_what_goes_through_it() {
+        // not installed in the test binary, so only these calls are counted
+        let layout = Layout::new::<[u8; 64]>();
+        let before = snapshot();
+        // SAFETY: the layout isn't zero sized and the pointers come from the same allocator
+        unsafe {
+            let ptr = CountingAllocator.alloc(layout);
+            assert!(!ptr.is_null());
+            let ptr = CountingAllocator.realloc(ptr, layout, 128);
+            assert!(!ptr.is_null());
+            CountingAllocator.dealloc(ptr, Layout::new::<[u8; 128]>());
+        }
+        let delta = snapshot() - before;
+        assert_eq!(
+            delta,
+            AllocationStats {
+                allocations: 1,
+                reallocations: 1,
+                deallocations: 1,
+                bytes_allocated: 128,
+            }
+        );
+    }
+}
@@ -0,0 +1,750 @@
+//! the syntax tree built by the `parser`. identifiers and literals borrow from the source,
+//! nested nodes are allocated in an `AstArena`, both for `'source`. every node carries the
+//! span of the text it was parsed from.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::literal_value::Literal;
+#[cfg(feature = "serde")]
+use crate::parser::Parsed;
+use crate::types::{Span, Spanned, Token};
+
+pub mod arena;
+pub mod pretty;
+pub mod visit;
+pub mod visit_mut;
+
+/// a name together with where it was written.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ident<'source> {
+    pub name: &'source str,
+    pub span: Span,
+}
+
+impl<'source> Ident<'source> {
+    #[inline]
+    pub const fn new(name: &'source str, span: Span) -> Self {
+        Ident { name, span }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Expr<'source> {
+    pub kind: ExprKind<'source>,
+    pub span: Span,
+}
+
+impl<'source> Expr<'source> {
+    #[inline]
+    pub const fn new(kind: ExprKind<'source>, span: Span) -> Self {
+        Expr { kind, span }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum ExprKind<'source> {
+    /// a literal token other than an identifier, escapes are not resolved yet.
+    Literal(Literal<'source>),
+    Identifier(&'source str),
+    /// `Enum::Variant`, a variant named through its enum.
+    Path {
+        enum_name: Ident<'source>,
+        variant: Ident<'source>,
+    },
+    /// `-x`, `!x`, `~x` or `*x`.
+    Unary {
+        op: UnaryOp,
+        expr: &'source mut Expr<'source>,
+    },
+    Binary {
+        op: BinaryOp,
+        lhs: &'source mut Expr<'source>,
+        rhs: &'source mut Expr<'source>,
+    },
+    /// `target = value`, or `target op= value` when `op` is set.
+    Assign {
+        op: Option<BinaryOp>,
+        target: &'source mut Expr<'source>,
+        value: &'source mut Expr<'source>,
+    },
+    /// `callee(arg, ...)`. a method call `obj.method(x)` is the call of a `Field`.
+    Call {
+        callee: &'source mut Expr<'source>,
+        args: Vec<Expr<'source>>,
+    },
+    /// `expr.field`, the field of a tuple is its index, `t.0`.
+    Field {
+        expr: &'source mut Expr<'source>,
+        field: Ident<'source>,
+    },
+    /// `expr[index]`
+    Index {
+        expr: &'source mut Expr<'source>,
+        index: &'source mut Expr<'source>,
+    },
+    /// `Name { field: value, ... }`, a struct literal or, with one field, a union literal.
+    Struct {
+        name: Ident<'source>,
+        fields: Vec<FieldInit<'source>>,
+    },
+    /// `expr cast ty`.
+    Cast {
+        expr: &'source mut Expr<'source>,
+        ty: &'source mut TypeExpr<'source>,
+    },
+    /// `{ ... }`, `compiletime { ... }` or `runtime { ... }`.
+    Block(&'source mut Block<'source>),
+    /// `if cond { ... } else ...`, the else branch is a block or another `if`.
+    If {
+        cond: &'source mut Expr<'source>,
+        then_branch: &'source mut Block<'source>,
+        else_branch: Option<&'source mut Expr<'source>>,
+    },
+    /// `@label while cond { ... }`, the label is optional.
+    While {
+        label: Option<Ident<'source>>,
+        cond: &'source mut Expr<'source>,
+        body: &'source mut Block<'source>,
+    },
+    /// `@label loop { ... }`, only left through `break` or `return`.
+    Loop {
+        label: Option<Ident<'source>>,
+        body: &'source mut Block<'source>,
+    },
+    /// `break @label value`, both parts are optional. the value is what a `loop` evaluates to.
+    Break {
+        label: Option<Ident<'source>>,
+        value: Option<&'source mut Expr<'source>>,
+    },
+    /// `continue @label`
+    Continue { label: Option<Ident<'source>> },
+    /// `match scrutinee { pattern => body, ... }`, arms are tried in order.
+    Match {
+        scrutinee: &'source mut Expr<'source>,
+        arms: Vec<MatchArm<'source>>,
+    },
+    /// `fn name(...) -> ty { ... }` used as a value.
+    Fn(&'source mut FnDecl<'source>),
+    Return(Option<&'source mut Expr<'source>>),
+}
+
+/// when the statements of a block run.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockKind {
+    Plain,
+    /// evaluated while compiling, the block is replaced by its value.
+    Compiletime,
+    Runtime,
+}
+
+/// statements followed by an optional value, `{ let a = 1; a + 1 }`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Block<'source> {
+    pub kind: BlockKind,
+    pub stmts: Vec<Stmt<'source>>,
+    /// the trailing expression without a `;`, the value of the block.
+    pub tail: Option<&'source mut Expr<'source>>,
+    pub span: Span,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Stmt<'source> {
+    pub kind: StmtKind<'source>,
+    pub span: Span,
+}
+
+impl<'source> Stmt<'source> {
+    #[inline]
+    pub const fn new(kind: StmtKind<'source>, span: Span) -> Self {
+        Stmt { kind, span }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum StmtKind<'source> {
+    /// `let name: ty = value;`
+    Let {
+        name: Ident<'source>,
+        ty: Option<TypeExpr<'source>>,
+        value: Expr<'source>,
+    },
+    /// an expression followed by `;`, assignments included.
+    Expr(Expr<'source>),
+    Item(Item<'source>),
+}
+
+/// a declaration, allowed at the top level and inside blocks. the span starts at the first
+/// attribute.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Item<'source> {
+    pub attrs: Vec<Attribute<'source>>,
+    pub kind: ItemKind<'source>,
+    pub span: Span,
+}
+
+impl<'source> Item<'source> {
+    #[inline]
+    pub const fn new(attrs: Vec<Attribute<'source>>, kind: ItemKind<'source>, span: Span) -> Self {
+        Item { attrs, kind, span }
+    }
+
+    /// the first attribute called `name`.
+    #[inline]
+    pub fn attr(&self, name: &str) -> Option<&Attribute<'source>> {
+        self.attrs.iter().find(|attr| attr.name.name == name)
+    }
+
+    /// the declared name, for imports the name they bring into scope.
+    #[inline]
+    pub const fn name(&self) -> Ident<'source> {
+        match &self.kind {
+            ItemKind::Fn(decl) => decl.name,
+            ItemKind::Struct(adt) | ItemKind::Union(adt) => adt.name,
+            ItemKind::Enum(decl) => decl.name,
+            ItemKind::Mod(decl) => decl.name,
+            ItemKind::Import(decl) => decl.name,
+        }
+    }
+}
+
+/// `#[name]` or `#[name(arg, ...)]` in front of an item. the parser accepts any name, passes
+/// look up the ones they understand with `Item::attr`. keywords are names too, `#[extern("C")]`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Attribute<'source> {
+    pub name: Ident<'source>,
+    /// empty without parentheses.
+    pub args: Vec<AttrArg<'source>>,
+    pub span: Span,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttrArg<'source> {
+    Literal(Spanned<Literal<'source>>),
+    Ident(Ident<'source>),
+}
+
+impl AttrArg<'_> {
+    #[inline]
+    pub const fn span(&self) -> Span {
+        match self {
+            AttrArg::Literal(literal) => literal.span,
+            AttrArg::Ident(ident) => ident.span,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum ItemKind<'source> {
+    Fn(FnDecl<'source>),
+    Struct(AdtDecl<'source>),
+    /// same shape as a struct, but every field lives at the same address.
+    Union(AdtDecl<'source>),
+    Enum(EnumDecl<'source>),
+    Mod(ModDecl<'source>),
+    Import(ImportDecl<'source>),
+}
+
+/// `extern fn name(let a: ty, ...) -> ty { ... }`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct FnDecl<'source> {
+    pub is_extern: bool,
+    pub name: Ident<'source>,
+    pub params: Vec<Param<'source>>,
+    pub ret: Option<TypeExpr<'source>>,
+    /// `None` for declarations ending in `;`, which are only meaningful for `extern` functions.
+    pub body: Option<Block<'source>>,
+    pub span: Span,
+}
+
+/// `let name: ty` in a parameter list.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Param<'source> {
+    pub name: Ident<'source>,
+    pub ty: TypeExpr<'source>,
+    pub span: Span,
+}
+
+/// the body of a `struct` or `union`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct AdtDecl<'source> {
+    pub name: Ident<'source>,
+    pub fields: Vec<Field<'source>>,
+    pub span: Span,
+}
+
+/// `name: ty`
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Field<'source> {
+    pub name: Ident<'source>,
+    pub ty: TypeExpr<'source>,
+    pub span: Span,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EnumDecl<'source> {
+    pub name: Ident<'source>,
+    pub variants: Vec<Variant<'source>>,
+    pub span: Span,
+}
+
+/// what every enum has besides its variants, written like one: `E::variant_count` is the
+/// number of variants, a `usize`, and `E::ALL` an array of them in order of declaration. a
+/// variant with the same name hides them.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EnumIntrinsic {
+    VariantCount,
+    All,
+}
+
+impl EnumIntrinsic {
+    pub const ALL: [EnumIntrinsic; 2] = [EnumIntrinsic::VariantCount, EnumIntrinsic::All];
+
+    /// the intrinsic written `E::name`.
+    #[inline]
+    pub fn named(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|intrinsic| intrinsic.name() == name)
+    }
+
+    #[inline]
+    pub const fn name(self) -> &'static str {
+        match self {
+            EnumIntrinsic::VariantCount => "variant_count",
+            EnumIntrinsic::All => "ALL",
+        }
+    }
+}
+
+/// `mod name;`, the module is the file `name.mumbo` next to the one declaring it, see
+/// `SourceMap::find_module`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModDecl<'source> {
+    pub name: Ident<'source>,
+    pub span: Span,
+}
+
+/// `import a.b.name;`, brings `name` from module `a.b` into scope.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImportDecl<'source> {
+    /// the modules leading to `name`, outermost first. never empty.
+    pub path: Vec<Ident<'source>>,
+    pub name: Ident<'source>,
+    pub span: Span,
+}
+
+/// a variant without a payload, the only kind there is so far.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Variant<'source> {
+    pub name: Ident<'source>,
+    pub span: Span,
+}
+
+/// a type annotation, `anymut static u8`. qualifiers are kept in source order.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct TypeExpr<'source> {
+    pub qualifiers: Vec<Spanned<Qualifier>>,
+    pub kind: TypeKind<'source>,
+    pub span: Span,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Qualifier {
+    Mut,
+    Const,
+    Anymut,
+    Static,
+    Runtime,
+    Compiletime,
+}
+
+impl Qualifier {
+    #[inline]
+    pub const fn token(self) -> Token {
+        match self {
+            Qualifier::Mut => Token::KwMut,
+            Qualifier::Const => Token::KwConst,
+            Qualifier::Anymut => Token::KwAnymut,
+            Qualifier::Static => Token::KwStatic,
+            Qualifier::Runtime => Token::KwRuntime,
+            Qualifier::Compiletime => Token::KwCompiletime,
+        }
+    }
+
+    #[inline]
+    pub const fn from_token(token: Token) -> Option<Self> {
+        Some(match token {
+            Token::KwMut => Qualifier::Mut,
+            Token::KwConst => Qualifier::Const,
+            Token::KwAnymut => Qualifier::Anymut,
+            Token::KwStatic => Qualifier::Static,
+            Token::KwRuntime => Qualifier::Runtime,
+            Token::KwCompiletime => Qualifier::Compiletime,
+            _ => return None,
+        })
+    }
+
+    /// `mut`, `const` or `anymut`.
+    #[inline]
+    pub const fn is_mutability(self) -> bool {
+        matches!(self, Qualifier::Mut | Qualifier::Const | Qualifier::Anymut)
+    }
+
+    /// whether the two can't qualify the same type: a qualifier repeated, two mutabilities, or
+    /// `compiletime` together with `runtime` or `static`.
+    #[inline]
+    pub const fn conflicts_with(self, other: Qualifier) -> bool {
+        match (self, other) {
+            (Qualifier::Compiletime, Qualifier::Runtime | Qualifier::Static)
+            | (Qualifier::Runtime | Qualifier::Static, Qualifier::Compiletime) => true,
+            _ if self.is_mutability() && other.is_mutability() => true,
+            _ => self as u8 == other as u8,
+        }
+    }
+}
+
+/// writes the keyword, `mut` for `Mut`.
+impl fmt::Display for Qualifier {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.token().source_repr())
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum TypeKind<'source> {
+    /// primitives like `u8` and `literal` as well as user defined types.
+    Named(&'source str),
+    /// the `type` keyword, the type of types.
+    Type,
+    /// `*ty`
+    Pointer(&'source mut TypeExpr<'source>),
+    /// `[len ty]`
+    Array {
+        len: &'source mut Expr<'source>,
+        elem: &'source mut TypeExpr<'source>,
+    },
+    /// `(ty, ...)`
+    Tuple(Vec<TypeExpr<'source>>),
+    /// `fn(ty, ...) -> ty`
+    Fn {
+        params: Vec<TypeExpr<'source>>,
+        ret: Option<&'source mut TypeExpr<'source>>,
+    },
+}
+
+/// `name: value` in a struct literal.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct FieldInit<'source> {
+    pub name: Ident<'source>,
+    pub value: Expr<'source>,
+    pub span: Span,
+}
+
+/// `pattern => body`
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct MatchArm<'source> {
+    pub pattern: Pattern<'source>,
+    pub body: Expr<'source>,
+    pub span: Span,
+}
+
+/// what a `match` arm compares against.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pattern<'source> {
+    pub kind: PatternKind<'source>,
+    pub span: Span,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PatternKind<'source> {
+    /// `_`
+    Wildcard,
+    Literal(Literal<'source>),
+    /// binds the value, or names an enum variant once names are resolved, like `__variant1`.
+    Identifier(&'source str),
+    /// `Enum::Variant`, always a variant.
+    Path {
+        enum_name: Ident<'source>,
+        variant: Ident<'source>,
+    },
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl BinaryOp {
+    pub const ALL: [BinaryOp; 16] = [
+        BinaryOp::Add,
+        BinaryOp::Sub,
+        BinaryOp::Mul,
+        BinaryOp::Div,
+        BinaryOp::Rem,
+        BinaryOp::BitAnd,
+        BinaryOp::BitOr,
+        BinaryOp::BitXor,
+        BinaryOp::Shl,
+        BinaryOp::Shr,
+        BinaryOp::Eq,
+        BinaryOp::Ne,
+        BinaryOp::Lt,
+        BinaryOp::Le,
+        BinaryOp::Gt,
+        BinaryOp::Ge,
+    ];
+
+    /// the operator token, `PuncPlus` for `Add`.
+    #[inline]
+    pub const fn token(self) -> Token {
+        match self {
+            BinaryOp::Add => Token::PuncPlus,
+            BinaryOp::Sub => Token::PuncMinus,
+            BinaryOp::Mul => Token::PuncStar,
+            BinaryOp::Div => Token::PuncSlash,
+            BinaryOp::Rem => Token::PuncModulo,
+            BinaryOp::BitAnd => Token::PuncAnd,
+            BinaryOp::BitOr => Token::PuncOr,
+            BinaryOp::BitXor => Token::PuncXor,
+            BinaryOp::Shl => Token::PuncShl,
+            BinaryOp::Shr => Token::PuncShr,
+            BinaryOp::Eq => Token::PuncEqEq,
+            BinaryOp::Ne => Token::PuncBangEq,
+            BinaryOp::Lt => Token::PuncLt,
+            BinaryOp::Le => Token::PuncLtEq,
+            BinaryOp::Gt => Token::PuncGt,
+            BinaryOp::Ge => Token::PuncGtEq,
+        }
+    }
+
+    /// the compound assignment token, `PuncPlusEq` for `Add`. comparisons have none.
+    #[inline]
+    pub const fn assign_token(self) -> Option<Token> {
+        Some(match self {
+            BinaryOp::Add => Token::PuncPlusEq,
+            BinaryOp::Sub => Token::PuncMinusEq,
+            BinaryOp::Mul => Token::PuncStarEq,
+            BinaryOp::Div => Token::PuncSlashEq,
+            BinaryOp::Rem => Token::PuncModuloEq,
+            BinaryOp::BitAnd => Token::PuncAndEq,
+            BinaryOp::BitOr => Token::PuncOrEq,
+            BinaryOp::BitXor => Token::PuncXorEq,
+            BinaryOp::Shl => Token::PuncShlEq,
+            BinaryOp::Shr => Token::PuncShrEq,
+            BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => return None,
+        })
+    }
+
+    #[inline]
+    pub const fn from_token(token: Token) -> Option<Self> {
+        Some(match token {
+            Token::PuncPlus => BinaryOp::Add,
+            Token::PuncMinus => BinaryOp::Sub,
+            Token::PuncStar => BinaryOp::Mul,
+            Token::PuncSlash => BinaryOp::Div,
+            Token::PuncModulo => BinaryOp::Rem,
+            Token::PuncAnd => BinaryOp::BitAnd,
+            Token::PuncOr => BinaryOp::BitOr,
+            Token::PuncXor => BinaryOp::BitXor,
+            Token::PuncShl => BinaryOp::Shl,
+            Token::PuncShr => BinaryOp::Shr,
+            Token::PuncEqEq => BinaryOp::Eq,
+            Token::PuncBangEq => BinaryOp::Ne,
+            Token::PuncLt => BinaryOp::Lt,
+            Token::PuncLtEq => BinaryOp::Le,
+            Token::PuncGt => BinaryOp::Gt,
+            Token::PuncGtEq => BinaryOp::Ge,
+            _ => return None,
+        })
+    }
+
+    /// the operator of a compound assignment token, `Add` for `PuncPlusEq`.
+    #[inline]
+    pub const fn from_assign_token(token: Token) -> Option<Self> {
+        Some(match token {
+            Token::PuncPlusEq => BinaryOp::Add,
+            Token::PuncMinusEq => BinaryOp::Sub,
+            Token::PuncStarEq => BinaryOp::Mul,
+            Token::PuncSlashEq => BinaryOp::Div,
+            Token::PuncModuloEq => BinaryOp::Rem,
+            Token::PuncAndEq => BinaryOp::BitAnd,
+            Token::PuncOrEq => BinaryOp::BitOr,
+            Token::PuncXorEq => BinaryOp::BitXor,
+            Token::PuncShlEq => BinaryOp::Shl,
+            Token::PuncShrEq => BinaryOp::Shr,
+            _ => return None,
+        })
+    }
+
+    #[inline]
+    pub const fn is_comparison(self) -> bool {
+        self.assign_token().is_none()
+    }
+}
+
+/// writes the operator, `+` for `Add`.
+impl fmt::Display for BinaryOp {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.token().source_repr())
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnaryOp {
+    /// `-x`
+    Neg,
+    /// `!x`, logical not.
+    Not,
+    /// `~x`, bitwise not.
+    BitNot,
+    /// `*x`, the value a pointer points to.
+    Deref,
+}
+
+impl UnaryOp {
+    pub const ALL: [UnaryOp; 4] = [UnaryOp::Neg, UnaryOp::Not, UnaryOp::BitNot, UnaryOp::Deref];
+
+    /// the operator token, `PuncMinus` for `Neg`.
+    #[inline]
+    pub const fn token(self) -> Token {
+        match self {
+            UnaryOp::Neg => Token::PuncMinus,
+            UnaryOp::Not => Token::PuncBang,
+            UnaryOp::BitNot => Token::PuncTilde,
+            UnaryOp::Deref => Token::PuncStar,
+        }
+    }
+
+    #[inline]
+    pub const fn from_token(token: Token) -> Option<Self> {
+        Some(match token {
+            Token::PuncMinus => UnaryOp::Neg,
+            Token::PuncBang => UnaryOp::Not,
+            Token::PuncTilde => UnaryOp::BitNot,
+            Token::PuncStar => UnaryOp::Deref,
+            _ => return None,
+        })
+    }
+}
+
+/// writes the operator, `-` for `Neg`.
+impl fmt::Display for UnaryOp {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.token().source_repr())
+    }
+}
+
+/// `parsed` as a single line of JSON: `{"stmts":[...],"errors":[...],"lexer_errors":[...]}`.
+/// enums are externally tagged, literals look like `{"token":"LitInteger","raw":"1"}`.
+#[cfg(feature = "serde")]
+pub fn dump_ast_json(parsed: &Parsed<'_>) -> alloc::string::String {
+    serde_json::to_string(parsed).expect("syntax trees have string keys and utf-8 literals")
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use super::{BinaryOp, Qualifier, UnaryOp};
+    use crate::types::Token;
+
+    #[test]
+    fn operators_map_to_their_tokens() {
+        for op in BinaryOp::ALL {
+            assert_eq!(BinaryOp::from_token(op.token()), Some(op));
+            match op.assign_token() {
+                Some(token) => {
+                    assert_eq!(BinaryOp::from_assign_token(token), Some(op));
+                    assert_eq!(token.source_repr(), format!("{op}="));
+                }
+                None => assert!(op.is_comparison()),
+            }
+        }
+        assert_eq!(BinaryOp::from_token(Token::PuncEq), None);
+        assert_eq!(BinaryOp::from_assign_token(Token::PuncEqEq), None);
+
+        for op in UnaryOp::ALL {
+            assert_eq!(UnaryOp::from_token(op.token()), Some(op));
+        }
+        assert_eq!(UnaryOp::Deref.to_string(), "*");
+        assert_eq!(UnaryOp::from_token(Token::PuncPlus), None);
+    }
+
+    #[test]
+    fn qualifiers_map_to_their_keywords() {
+        let qualifiers: Vec<Qualifier> = Token::ALL.iter().filter_map(|t| Qualifier::from_token(*t)).collect();
+        assert_eq!(qualifiers.len(), 6);
+        for qualifier in qualifiers {
+            assert_eq!(Qualifier::from_token(qualifier.token()), Some(qualifier));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn parse_results_dump_as_json() {
+        use super::arena::AstArena;
+        use super::dump_ast_json;
+        use crate::parser::parse;
+
+        let arena = AstArena::new();
+        assert_eq!(
+            dump_ast_json(&parse("x = 1;", &arena)),
+            r#"{"stmts":[{"kind":{"Expr":{"kind":{"Assign":{"op":null,"target":{"kind":{"Identifier":"x"},"#.to_owned()
+                + r#""span":{"start":0,"end":1}},"value":{"kind":{"Literal":{"token":"LitInteger","raw":"1"}},"#
+                + r#""span":{"start":4,"end":5}}}},"span":{"start":0,"end":5}}},"span":{"start":0,"end":6}}],"#
+                + r#""errors":[],"lexer_errors":[]}"#
+        );
+
+        let dumped = dump_ast_json(&parse("let a: mut u8 = ;", &arena));
+        assert!(dumped.starts_with(r#"{"stmts":[],"errors":[{"kind":{"Unexpected":"#), "{dumped}");
+        assert!(dumped.contains(r#""found":"PuncSemi""#), "{dumped}");
+    }
+}
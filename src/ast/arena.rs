@@ -0,0 +1,436 @@
+//! storage for the boxed nodes of a syntax tree.
+//!
+//! the parser allocates every expression, block, type and function declaration that another
+//! node points to in an `AstArena`, so building a tree costs a few large allocations instead
+//! of one per node, and dropping it frees them all at once. nodes refer to each other with
+//! `&'source mut` references, which keeps the tree rewritable in place by a `MutVisitor`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt;
+
+use crate::ast::{
+    AdtDecl, Block, Expr, ExprKind, Field, FieldInit, FnDecl, Item, ItemKind, MatchArm, Param, Stmt, StmtKind, TypeExpr,
+    TypeKind,
+};
+
+/// capacity of the first chunk of a `TypedArena`, later chunks double.
+const FIRST_CHUNK: usize = 64;
+
+/// bump allocator for values of a single type.
+struct TypedArena<T> {
+    chunks: RefCell<Chunks<T>>,
+}
+
+struct Chunks<T> {
+    /// only ever pushed to while below capacity, so it never reallocates.
+    current: Vec<T>,
+    full: Vec<Vec<T>>,
+}
+
+impl<T> TypedArena<T> {
+    const fn new() -> Self {
+        TypedArena {
+            chunks: RefCell::new(Chunks {
+                current: Vec::new(),
+                full: Vec::new(),
+            }),
+        }
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    fn alloc(&self, value: T) -> &mut T {
+        let mut chunks = self.chunks.borrow_mut();
+        if chunks.current.len() == chunks.current.capacity() {
+            let capacity = (chunks.current.capacity() * 2).max(FIRST_CHUNK);
+            let full = core::mem::replace(&mut chunks.current, Vec::with_capacity(capacity));
+            if full.capacity() > 0 {
+                chunks.full.push(full);
+            }
+        }
+        let index = chunks.current.len();
+        chunks.current.push(value);
+        // SAFETY: the push above stayed within capacity, so the buffer did not move and the
+        // earlier references into it stay valid. moving a full chunk into `full` doesn't move
+        // its buffer either, and values are only dropped together with the arena. each call
+        // hands out a different slot, so the `&mut` is unique.
+        unsafe { &mut *chunks.current.as_mut_ptr().add(index) }
+    }
+
+    fn len(&self) -> usize {
+        let chunks = self.chunks.borrow();
+        chunks.current.len() + chunks.full.iter().map(Vec::len).sum::<usize>()
+    }
+}
+
+/// owns the nodes of the trees parsed with it. it has to outlive them, which the borrow
+/// checker enforces through the `'source` lifetime every node carries.
+pub struct AstArena<'source> {
+    exprs: TypedArena<Expr<'source>>,
+    blocks: TypedArena<Block<'source>>,
+    types: TypedArena<TypeExpr<'source>>,
+    fns: TypedArena<FnDecl<'source>>,
+    /// text of literals made up by passes, which has no source to borrow from.
+    strs: TypedArena<String>,
+    /// like `strs`, for bytes that don't have to be utf-8.
+    bytes: TypedArena<Vec<u8>>,
+}
+
+impl<'source> AstArena<'source> {
+    #[inline]
+    pub const fn new() -> Self {
+        AstArena {
+            exprs: TypedArena::new(),
+            blocks: TypedArena::new(),
+            types: TypedArena::new(),
+            fns: TypedArena::new(),
+            strs: TypedArena::new(),
+            bytes: TypedArena::new(),
+        }
+    }
+
+    #[inline]
+    pub fn alloc_expr(&'source self, expr: Expr<'source>) -> &'source mut Expr<'source> {
+        self.exprs.alloc(expr)
+    }
+
+    #[inline]
+    pub fn alloc_block(&'source self, block: Block<'source>) -> &'source mut Block<'source> {
+        self.blocks.alloc(block)
+    }
+
+    #[inline]
+    pub fn alloc_type(&'source self, ty: TypeExpr<'source>) -> &'source mut TypeExpr<'source> {
+        self.types.alloc(ty)
+    }
+
+    #[inline]
+    pub fn alloc_fn(&'source self, decl: FnDecl<'source>) -> &'source mut FnDecl<'source> {
+        self.fns.alloc(decl)
+    }
+
+    /// keeps `text` alive for `'source`, for nodes a pass creates, like a computed literal.
+    #[inline]
+    pub fn alloc_str(&'source self, text: String) -> &'source str {
+        self.strs.alloc(text)
+    }
+    /// like `alloc_str`, for the bytes of a string value, which don't have to be utf-8.
+    /// like `alloc_str`, for bytes that may not be utf-8, like a string with `\\xff` in it.
+    #[inline]
+    pub fn alloc_bytes(&'source self, bytes: Vec<u8>) -> &'source [u8] {
+        self.bytes.alloc(bytes)
+    }
+
+    /// number of nodes allocated so far, strings not included.
+    pub fn len(&self) -> usize {
+        self.exprs.len() + self.blocks.len() + self.types.len() + self.fns.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for AstArena<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for AstArena<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AstArena")
+            .field("exprs", &self.exprs.len())
+            .field("blocks", &self.blocks.len())
+            .field("types", &self.types.len())
+            .field("fns", &self.fns.len())
+            .field("strs", &self.strs.len())
+            .finish()
+    }
+}
+
+/// deep copy of a node, the copies of the nodes it points to are allocated in `arena`.
+/// nodes can't be `Clone` because they hold `&mut` references to their children.
+pub trait CloneIn<'source> {
+    fn clone_in(&self, arena: &'source AstArena<'source>) -> Self;
+}
+
+impl<'source, T: CloneIn<'source>> CloneIn<'source> for Option<T> {
+    #[inline]
+    fn clone_in(&self, arena: &'source AstArena<'source>) -> Self {
+        self.as_ref().map(|value| value.clone_in(arena))
+    }
+}
+
+impl<'source, T: CloneIn<'source>> CloneIn<'source> for Vec<T> {
+    fn clone_in(&self, arena: &'source AstArena<'source>) -> Self {
+        self.iter().map(|value| value.clone_in(arena)).collect()
+    }
+}
+
+impl<'source> CloneIn<'source> for &'source mut Expr<'source> {
+    #[inline]
+    fn clone_in(&self, arena: &'source AstArena<'source>) -> Self {
+        arena.alloc_expr((**self).clone_in(arena))
+    }
+}
+
+impl<'source> CloneIn<'source> for &'source mut Block<'source> {
+    #[inline]
+    fn clone_in(&self, arena: &'source AstArena<'source>) -> Self {
+        arena.alloc_block((**self).clone_in(arena))
+    }
+}
+
+impl<'source> CloneIn<'source> for &'source mut TypeExpr<'source> {
+    #[inline]
+    fn clone_in(&self, arena: &'source AstArena<'source>) -> Self {
+        arena.alloc_type((**self).clone_in(arena))
+    }
+}
+
+impl<'source> CloneIn<'source> for &'source mut FnDecl<'source> {
+    #[inline]
+    fn clone_in(&self, arena: &'source AstArena<'source>) -> Self {
+        arena.alloc_fn((**self).clone_in(arena))
+    }
+}
+
+impl<'source> CloneIn<'source> for Expr<'source> {
+    fn clone_in(&self, arena: &'source AstArena<'source>) -> Self {
+        let kind = match &self.kind {
+            ExprKind::Literal(literal) => ExprKind::Literal(*literal),
+            ExprKind::Identifier(name) => ExprKind::Identifier(name),
+            ExprKind::Path { enum_name, variant } => ExprKind::Path {
+                enum_name: *enum_name,
+                variant: *variant,
+            },
+            ExprKind::Unary { op, expr } => ExprKind::Unary {
+                op: *op,
+                expr: expr.clone_in(arena),
+            },
+            ExprKind::Binary { op, lhs, rhs } => ExprKind::Binary {
+                op: *op,
+                lhs: lhs.clone_in(arena),
+                rhs: rhs.clone_in(arena),
+            },
+            ExprKind::Assign { op, target, value } => ExprKind::Assign {
+                op: *op,
+                target: target.clone_in(arena),
+                value: value.clone_in(arena),
+            },
+            ExprKind::Call { callee, args } => ExprKind::Call {
+                callee: callee.clone_in(arena),
+                args: args.clone_in(arena),
+            },
+            ExprKind::Struct { name, fields } => ExprKind::Struct {
+                name: *name,
+                fields: fields.clone_in(arena),
+            },
+            ExprKind::Field { expr, field } => ExprKind::Field {
+                expr: expr.clone_in(arena),
+                field: *field,
+            },
+            ExprKind::Index { expr, index } => ExprKind::Index {
+                expr: expr.clone_in(arena),
+                index: index.clone_in(arena),
+            },
+            ExprKind::Cast { expr, ty } => ExprKind::Cast {
+                expr: expr.clone_in(arena),
+                ty: ty.clone_in(arena),
+            },
+            ExprKind::Block(block) => ExprKind::Block(block.clone_in(arena)),
+            ExprKind::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => ExprKind::If {
+                cond: cond.clone_in(arena),
+                then_branch: then_branch.clone_in(arena),
+                else_branch: else_branch.clone_in(arena),
+            },
+            ExprKind::While { label, cond, body } => ExprKind::While {
+                label: *label,
+                cond: cond.clone_in(arena),
+                body: body.clone_in(arena),
+            },
+            ExprKind::Loop { label, body } => ExprKind::Loop {
+                label: *label,
+                body: body.clone_in(arena),
+            },
+            ExprKind::Break { label, value } => ExprKind::Break {
+                label: *label,
+                value: value.clone_in(arena),
+            },
+            ExprKind::Continue { label } => ExprKind::Continue { label: *label },
+            ExprKind::Match { scrutinee, arms } => ExprKind::Match {
+                scrutinee: scrutinee.clone_in(arena),
+                arms: arms.clone_in(arena),
+            },
+            ExprKind::Fn(decl) => ExprKind::Fn(decl.clone_in(arena)),
+            ExprKind::Return(value) => ExprKind::Return(value.clone_in(arena)),
+        };
+        Expr::new(kind, self.span)
+    }
+}
+
+impl<'source> CloneIn<'source> for Block<'source> {
+    fn clone_in(&self, arena: &'source AstArena<'source>) -> Self {
+        Block {
+            kind: self.kind,
+            stmts: self.stmts.clone_in(arena),
+            tail: self.tail.clone_in(arena),
+            span: self.span,
+        }
+    }
+}
+
+impl<'source> CloneIn<'source> for Stmt<'source> {
+    fn clone_in(&self, arena: &'source AstArena<'source>) -> Self {
+        let kind = match &self.kind {
+            StmtKind::Let { name, ty, value } => StmtKind::Let {
+                name: *name,
+                ty: ty.clone_in(arena),
+                value: value.clone_in(arena),
+            },
+            StmtKind::Expr(expr) => StmtKind::Expr(expr.clone_in(arena)),
+            StmtKind::Item(item) => StmtKind::Item(item.clone_in(arena)),
+        };
+        Stmt::new(kind, self.span)
+    }
+}
+
+impl<'source> CloneIn<'source> for Item<'source> {
+    fn clone_in(&self, arena: &'source AstArena<'source>) -> Self {
+        let kind = match &self.kind {
+            ItemKind::Fn(decl) => ItemKind::Fn(decl.clone_in(arena)),
+            ItemKind::Struct(adt) => ItemKind::Struct(adt.clone_in(arena)),
+            ItemKind::Union(adt) => ItemKind::Union(adt.clone_in(arena)),
+            ItemKind::Enum(decl) => ItemKind::Enum(decl.clone()),
+            ItemKind::Mod(decl) => ItemKind::Mod(*decl),
+            ItemKind::Import(decl) => ItemKind::Import(decl.clone()),
+        };
+        Item::new(self.attrs.clone(), kind, self.span)
+    }
+}
+
+impl<'source> CloneIn<'source> for FnDecl<'source> {
+    fn clone_in(&self, arena: &'source AstArena<'source>) -> Self {
+        FnDecl {
+            is_extern: self.is_extern,
+            name: self.name,
+            params: self.params.clone_in(arena),
+            ret: self.ret.clone_in(arena),
+            body: self.body.clone_in(arena),
+            span: self.span,
+        }
+    }
+}
+
+impl<'source> CloneIn<'source> for Param<'source> {
+    fn clone_in(&self, arena: &'source AstArena<'source>) -> Self {
+        Param {
+            name: self.name,
+            ty: self.ty.clone_in(arena),
+            span: self.span,
+        }
+    }
+}
+
+impl<'source> CloneIn<'source> for AdtDecl<'source> {
+    fn clone_in(&self, arena: &'source AstArena<'source>) -> Self {
+        AdtDecl {
+            name: self.name,
+            fields: self.fields.clone_in(arena),
+            span: self.span,
+        }
+    }
+}
+
+impl<'source> CloneIn<'source> for Field<'source> {
+    fn clone_in(&self, arena: &'source AstArena<'source>) -> Self {
+        Field {
+            name: self.name,
+            ty: self.ty.clone_in(arena),
+            span: self.span,
+        }
+    }
+}
+
+impl<'source> CloneIn<'source> for TypeExpr<'source> {
+    fn clone_in(&self, arena: &'source AstArena<'source>) -> Self {
+        let kind = match &self.kind {
+            TypeKind::Named(name) => TypeKind::Named(name),
+            TypeKind::Type => TypeKind::Type,
+            TypeKind::Pointer(pointee) => TypeKind::Pointer(pointee.clone_in(arena)),
+            TypeKind::Array { len, elem } => TypeKind::Array {
+                len: len.clone_in(arena),
+                elem: elem.clone_in(arena),
+            },
+            TypeKind::Tuple(elems) => TypeKind::Tuple(elems.clone_in(arena)),
+            TypeKind::Fn { params, ret } => TypeKind::Fn {
+                params: params.clone_in(arena),
+                ret: ret.clone_in(arena),
+            },
+        };
+        TypeExpr {
+            qualifiers: self.qualifiers.clone(),
+            kind,
+            span: self.span,
+        }
+    }
+}
+
+impl<'source> CloneIn<'source> for FieldInit<'source> {
+    fn clone_in(&self, arena: &'source AstArena<'source>) -> Self {
+        FieldInit {
+            name: self.name,
+            value: self.value.clone_in(arena),
+            span: self.span,
+        }
+    }
+}
+
+impl<'source> CloneIn<'source> for MatchArm<'source> {
+    fn clone_in(&self, arena: &'source AstArena<'source>) -> Self {
+        MatchArm {
+            pattern: self.pattern.clone(),
+            body: self.body.clone_in(arena),
+            span: self.span,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::vec::Vec;
+
+    use super::{AstArena, FIRST_CHUNK, TypedArena};
+    use crate::ast::{Expr, ExprKind};
+    use crate::types::Span;
+
+    #[test]
+    fn references_survive_new_chunks() {
+        let arena = TypedArena::new();
+        let values: Vec<&mut usize> = (0..FIRST_CHUNK * 5).map(|i| arena.alloc(i)).collect();
+        assert_eq!(arena.len(), FIRST_CHUNK * 5);
+        assert!(values.iter().enumerate().all(|(i, value)| **value == i));
+        assert_eq!(arena.chunks.borrow().full.len(), 2);
+    }
+
+    #[test]
+    fn nodes_can_point_into_the_same_arena() {
+        let arena = AstArena::new();
+        let leaf = arena.alloc_expr(Expr::new(ExprKind::Identifier("a"), Span::new(0, 1)));
+        let ret = arena.alloc_expr(Expr::new(ExprKind::Return(Some(leaf)), Span::new(0, 1)));
+        let ExprKind::Return(Some(inner)) = &mut ret.kind else { panic!() };
+        inner.kind = ExprKind::Identifier("b");
+        assert_eq!(format!("{ret}"), "return b");
+        assert_eq!(arena.alloc_str("made up".into()), "made up");
+        assert_eq!(format!("{arena:?}"), "AstArena { exprs: 2, blocks: 0, types: 0, fns: 0, strs: 1 }");
+    }
+}
@@ -0,0 +1,667 @@
+//! renders syntax trees back into canonical source text: one statement per line, four space
+//! indentation, a `;` after every statement except function declarations with a body, and
+//! parentheses only where precedence requires them.
+//!
+//! the `Display` impls of the node types go through here, `to_source` prints a whole program.
+
+use alloc::string::String;
+use core::fmt::{self, Write};
+
+use crate::ast::{
+    AttrArg, Attribute, Block, BlockKind, Expr, ExprKind, FnDecl, Ident, Item, ItemKind, Pattern, PatternKind, Stmt,
+    StmtKind, TypeExpr, TypeKind,
+};
+use crate::literal_value::Literal;
+use crate::parser::precedence::{Associativity, Precedence, infix_operator};
+use crate::types::Token;
+
+const INDENT: &str = "    ";
+
+/// `stmts` as source text, each statement followed by a newline.
+pub fn to_source(stmts: &[Stmt<'_>]) -> String {
+    let mut out = String::new();
+    let mut printer = Printer::new(&mut out);
+    for stmt in stmts {
+        printer.stmt(stmt).expect("formatting into a String can't fail");
+        out.push('\n');
+        printer = Printer::new(&mut out);
+    }
+    out
+}
+
+/// which operand of an infix operator an expression is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Left,
+    Right,
+}
+
+/// how an expression binds when it is an operand, see `needs_parens`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Binding {
+    /// `return a`, `break a`: the value reaches as far right as possible.
+    Jump,
+    Infix(Precedence, Associativity),
+    /// `a cast ty`
+    Postfix,
+    Atom,
+}
+
+fn binding(expr: &Expr<'_>) -> Binding {
+    let token = match &expr.kind {
+        ExprKind::Binary { op, .. } => op.token(),
+        ExprKind::Assign { op: None, .. } => Token::PuncEq,
+        ExprKind::Assign { op: Some(op), .. } => op.assign_token().unwrap_or(Token::PuncEq),
+        ExprKind::Cast { .. } => return Binding::Postfix,
+        ExprKind::Return(Some(_)) | ExprKind::Break { value: Some(_), .. } => return Binding::Jump,
+        _ => return Binding::Atom,
+    };
+    match infix_operator(token) {
+        Some(operator) => Binding::Infix(operator.precedence, operator.associativity),
+        None => Binding::Atom,
+    }
+}
+
+/// whether `expr` has a struct literal that isn't inside parentheses, brackets or braces,
+/// which would be taken for the body of an `if`, `while` or `match`.
+fn has_bare_struct_literal(expr: &Expr<'_>) -> bool {
+    match &expr.kind {
+        ExprKind::Struct { .. } => true,
+        ExprKind::Binary { lhs, rhs, .. } => has_bare_struct_literal(lhs) || has_bare_struct_literal(rhs),
+        ExprKind::Assign { target, value, .. } => has_bare_struct_literal(target) || has_bare_struct_literal(value),
+        ExprKind::Unary { expr, .. }
+        | ExprKind::Cast { expr, .. }
+        | ExprKind::Field { expr, .. }
+        | ExprKind::Index { expr, .. }
+        | ExprKind::Call { callee: expr, .. }
+        | ExprKind::Return(Some(expr))
+        | ExprKind::Break { value: Some(expr), .. } => has_bare_struct_literal(expr),
+        _ => false,
+    }
+}
+
+/// whether `operand` has to be wrapped in parentheses to parse back as the `side` operand of
+/// an infix operator with `precedence` and `associativity`.
+fn needs_parens(operand: &Expr<'_>, precedence: Precedence, associativity: Associativity, side: Side) -> bool {
+    match binding(operand) {
+        // a jump on the right takes the rest of the expression along anyway
+        Binding::Jump => side == Side::Left,
+        Binding::Infix(inner, _) if inner != precedence => inner < precedence,
+        Binding::Infix(..) => match associativity {
+            Associativity::Left => side == Side::Right,
+            Associativity::Right => side == Side::Left,
+            Associativity::None => true,
+        },
+        Binding::Postfix | Binding::Atom => false,
+    }
+}
+
+struct Printer<'w, W: Write> {
+    out: &'w mut W,
+    indent: usize,
+}
+
+impl<'w, W: Write> Printer<'w, W> {
+    fn new(out: &'w mut W) -> Self {
+        Printer { out, indent: 0 }
+    }
+
+    fn newline(&mut self) -> fmt::Result {
+        self.out.write_char('\n')?;
+        for _ in 0..self.indent {
+            self.out.write_str(INDENT)?;
+        }
+        Ok(())
+    }
+
+    fn token(&mut self, token: Token) -> fmt::Result {
+        self.out.write_str(token.source_repr())
+    }
+
+    /// `items` separated by `, `, without a trailing comma.
+    fn list<T>(&mut self, items: &[T], mut item: impl FnMut(&mut Self, &T) -> fmt::Result) -> fmt::Result {
+        for (index, value) in items.iter().enumerate() {
+            if index > 0 {
+                self.out.write_str(", ")?;
+            }
+            item(self, value)?;
+        }
+        Ok(())
+    }
+
+    fn label(&mut self, label: Option<Ident<'_>>) -> fmt::Result {
+        match label {
+            Some(label) => write!(self.out, "@{} ", label.name),
+            None => Ok(()),
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal<'_>) -> fmt::Result {
+        let raw = core::str::from_utf8(literal.raw()).map_err(|_| fmt::Error)?;
+        match literal.token() {
+            Token::LitStr => write!(self.out, "\"{}\"", raw),
+            Token::LitChar => write!(self.out, "'{}'", raw),
+            token if raw.is_empty() => self.token(token),
+            _ => self.out.write_str(raw),
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt<'_>) -> fmt::Result {
+        match &stmt.kind {
+            StmtKind::Let { name, ty, value } => {
+                write!(self.out, "let {}", name.name)?;
+                if let Some(ty) = ty {
+                    self.out.write_str(": ")?;
+                    self.ty(ty)?;
+                }
+                self.out.write_str(" = ")?;
+                self.expr(value)?;
+            }
+            StmtKind::Expr(expr) => self.expr(expr)?,
+            StmtKind::Item(item) => {
+                self.item(item)?;
+                if let ItemKind::Fn(FnDecl { body: Some(_), .. }) = item.kind {
+                    return Ok(());
+                }
+            }
+        }
+        self.out.write_char(';')
+    }
+
+    /// attributes go on their own lines in front of the item.
+    fn item(&mut self, item: &Item<'_>) -> fmt::Result {
+        for attr in &item.attrs {
+            self.attribute(attr)?;
+            self.newline()?;
+        }
+        match &item.kind {
+            ItemKind::Fn(decl) => self.fn_decl(decl),
+            ItemKind::Struct(adt) | ItemKind::Union(adt) => {
+                let keyword = match item.kind {
+                    ItemKind::Struct(_) => Token::KwAdtStruct,
+                    _ => Token::KwAdtUnion,
+                };
+                self.token(keyword)?;
+                write!(self.out, " {} {{", adt.name.name)?;
+                self.indent += 1;
+                for field in &adt.fields {
+                    self.newline()?;
+                    write!(self.out, "{}: ", field.name.name)?;
+                    self.ty(&field.ty)?;
+                    self.out.write_char(',')?;
+                }
+                self.close_brace(adt.fields.is_empty())
+            }
+            ItemKind::Enum(decl) => {
+                write!(self.out, "enum {} {{", decl.name.name)?;
+                self.indent += 1;
+                for variant in &decl.variants {
+                    self.newline()?;
+                    write!(self.out, "{},", variant.name.name)?;
+                }
+                self.close_brace(decl.variants.is_empty())
+            }
+            ItemKind::Mod(decl) => write!(self.out, "mod {}", decl.name.name),
+            ItemKind::Import(decl) => {
+                self.out.write_str("import ")?;
+                for module in &decl.path {
+                    write!(self.out, "{}.", module.name)?;
+                }
+                self.out.write_str(decl.name.name)
+            }
+        }
+    }
+
+    fn attribute(&mut self, attr: &Attribute<'_>) -> fmt::Result {
+        write!(self.out, "#[{}", attr.name.name)?;
+        if !attr.args.is_empty() {
+            self.out.write_char('(')?;
+            self.list(&attr.args, |p, arg| match arg {
+                AttrArg::Literal(literal) => p.literal(&literal.value),
+                AttrArg::Ident(ident) => p.out.write_str(ident.name),
+            })?;
+            self.out.write_char(')')?;
+        }
+        self.out.write_char(']')
+    }
+
+    /// closes a brace opened with `self.indent += 1`, on its own line unless nothing was in between.
+    fn close_brace(&mut self, empty: bool) -> fmt::Result {
+        self.indent -= 1;
+        if !empty {
+            self.newline()?;
+        }
+        self.out.write_char('}')
+    }
+
+    fn fn_decl(&mut self, decl: &FnDecl<'_>) -> fmt::Result {
+        if decl.is_extern {
+            self.out.write_str("extern ")?;
+        }
+        write!(self.out, "fn {}(", decl.name.name)?;
+        self.list(&decl.params, |p, param| {
+            write!(p.out, "let {}: ", param.name.name)?;
+            p.ty(&param.ty)
+        })?;
+        self.out.write_char(')')?;
+        if let Some(ret) = &decl.ret {
+            self.out.write_str(" -> ")?;
+            self.ty(ret)?;
+        }
+        match &decl.body {
+            Some(body) => {
+                self.out.write_char(' ')?;
+                self.block(body)
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn block(&mut self, block: &Block<'_>) -> fmt::Result {
+        match block.kind {
+            BlockKind::Plain => {}
+            BlockKind::Compiletime => self.out.write_str("compiletime ")?,
+            BlockKind::Runtime => self.out.write_str("runtime ")?,
+        }
+        self.out.write_char('{')?;
+        self.indent += 1;
+        for stmt in &block.stmts {
+            self.newline()?;
+            self.stmt(stmt)?;
+        }
+        if let Some(tail) = &block.tail {
+            self.newline()?;
+            self.expr(tail)?;
+        }
+        self.close_brace(block.stmts.is_empty() && block.tail.is_none())
+    }
+
+    fn operand(&mut self, operand: &Expr<'_>, parent: Token, side: Side) -> fmt::Result {
+        let parens = match infix_operator(parent) {
+            Some(operator) => needs_parens(operand, operator.precedence, operator.associativity, side),
+            None => false,
+        };
+        if parens {
+            self.out.write_char('(')?;
+            self.expr(operand)?;
+            self.out.write_char(')')
+        } else {
+            self.expr(operand)
+        }
+    }
+
+    /// the callee of a call or the expression a field or an index is taken from. only names,
+    /// literals and other postfix expressions go without parentheses.
+    /// an `if` or `while` condition or a `match` scrutinee.
+    fn cond(&mut self, cond: &Expr<'_>) -> fmt::Result {
+        if has_bare_struct_literal(cond) {
+            self.out.write_char('(')?;
+            self.expr(cond)?;
+            self.out.write_char(')')
+        } else {
+            self.expr(cond)
+        }
+    }
+
+    fn postfix_base(&mut self, base: &Expr<'_>) -> fmt::Result {
+        let parens = !matches!(
+            base.kind,
+            ExprKind::Literal(_)
+                | ExprKind::Identifier(_)
+                | ExprKind::Path { .. }
+                | ExprKind::Struct { .. }
+                | ExprKind::Call { .. }
+                | ExprKind::Field { .. }
+                | ExprKind::Index { .. }
+        );
+        if parens {
+            self.out.write_char('(')?;
+            self.expr(base)?;
+            self.out.write_char(')')
+        } else {
+            self.expr(base)
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr<'_>) -> fmt::Result {
+        match &expr.kind {
+            ExprKind::Literal(literal) => self.literal(literal),
+            ExprKind::Identifier(name) => self.out.write_str(name),
+            ExprKind::Path { enum_name, variant } => write!(self.out, "{}::{}", enum_name.name, variant.name),
+            ExprKind::Unary { op, expr } => {
+                write!(self.out, "{}", op)?;
+                // `-a cast u8` casts `-a`
+                if let Binding::Infix(..) | Binding::Jump | Binding::Postfix = binding(expr) {
+                    self.out.write_char('(')?;
+                    self.expr(expr)?;
+                    self.out.write_char(')')
+                } else {
+                    self.expr(expr)
+                }
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                self.operand(lhs, op.token(), Side::Left)?;
+                write!(self.out, " {} ", op)?;
+                self.operand(rhs, op.token(), Side::Right)
+            }
+            ExprKind::Assign { op, target, value } => {
+                let token = match op {
+                    Some(op) => op.assign_token().unwrap_or(Token::PuncEq),
+                    None => Token::PuncEq,
+                };
+                self.operand(target, token, Side::Left)?;
+                write!(self.out, " {} ", token)?;
+                self.operand(value, token, Side::Right)
+            }
+            ExprKind::Call { callee, args } => {
+                self.postfix_base(callee)?;
+                self.out.write_char('(')?;
+                self.list(args, |p, arg| p.expr(arg))?;
+                self.out.write_char(')')
+            }
+            ExprKind::Struct { name, fields } => {
+                write!(self.out, "{} {{", name.name)?;
+                if !fields.is_empty() {
+                    self.out.write_char(' ')?;
+                    self.list(fields, |p, field| {
+                        write!(p.out, "{}: ", field.name.name)?;
+                        p.expr(&field.value)
+                    })?;
+                    self.out.write_char(' ')?;
+                }
+                self.out.write_char('}')
+            }
+            ExprKind::Field { expr, field } => {
+                self.postfix_base(expr)?;
+                write!(self.out, ".{}", field.name)
+            }
+            ExprKind::Index { expr, index } => {
+                self.postfix_base(expr)?;
+                self.out.write_char('[')?;
+                self.expr(index)?;
+                self.out.write_char(']')
+            }
+            ExprKind::Cast { expr, ty } => {
+                if let Binding::Infix(..) | Binding::Jump = binding(expr) {
+                    self.out.write_char('(')?;
+                    self.expr(expr)?;
+                    self.out.write_char(')')?;
+                } else {
+                    self.expr(expr)?;
+                }
+                self.out.write_str(" cast ")?;
+                self.ty(ty)
+            }
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                self.out.write_str("if ")?;
+                self.cond(cond)?;
+                self.out.write_char(' ')?;
+                self.block(then_branch)?;
+                if let Some(else_branch) = else_branch {
+                    self.out.write_str(" else ")?;
+                    self.expr(else_branch)?;
+                }
+                Ok(())
+            }
+            ExprKind::While { label, cond, body } => {
+                self.label(*label)?;
+                self.out.write_str("while ")?;
+                self.cond(cond)?;
+                self.out.write_char(' ')?;
+                self.block(body)
+            }
+            ExprKind::Loop { label, body } => {
+                self.label(*label)?;
+                self.out.write_str("loop ")?;
+                self.block(body)
+            }
+            ExprKind::Break { label, value } => {
+                self.out.write_str("break")?;
+                if let Some(label) = label {
+                    write!(self.out, " @{}", label.name)?;
+                }
+                self.jump_value(value.as_deref())
+            }
+            ExprKind::Continue { label } => {
+                self.out.write_str("continue")?;
+                match label {
+                    Some(label) => write!(self.out, " @{}", label.name),
+                    None => Ok(()),
+                }
+            }
+            ExprKind::Return(value) => {
+                self.out.write_str("return")?;
+                self.jump_value(value.as_deref())
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                self.out.write_str("match ")?;
+                self.cond(scrutinee)?;
+                self.out.write_str(" {")?;
+                self.indent += 1;
+                for arm in arms {
+                    self.newline()?;
+                    self.pattern(&arm.pattern)?;
+                    self.out.write_str(" => ")?;
+                    self.expr(&arm.body)?;
+                    self.out.write_char(',')?;
+                }
+                self.close_brace(arms.is_empty())
+            }
+            ExprKind::Fn(decl) => self.fn_decl(decl),
+        }
+    }
+
+    fn jump_value(&mut self, value: Option<&Expr<'_>>) -> fmt::Result {
+        match value {
+            Some(value) => {
+                self.out.write_char(' ')?;
+                self.expr(value)
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn ty(&mut self, ty: &TypeExpr<'_>) -> fmt::Result {
+        for qualifier in &ty.qualifiers {
+            write!(self.out, "{} ", qualifier.value)?;
+        }
+        match &ty.kind {
+            TypeKind::Named(name) => self.out.write_str(name),
+            TypeKind::Type => self.token(Token::KwType),
+            TypeKind::Pointer(pointee) => {
+                self.out.write_char('*')?;
+                self.ty(pointee)
+            }
+            TypeKind::Array { len, elem } => {
+                self.out.write_char('[')?;
+                self.expr(len)?;
+                self.out.write_char(' ')?;
+                self.ty(elem)?;
+                self.out.write_char(']')
+            }
+            TypeKind::Tuple(elems) => {
+                self.out.write_char('(')?;
+                self.list(elems, |p, elem| p.ty(elem))?;
+                self.out.write_char(')')
+            }
+            TypeKind::Fn { params, ret } => {
+                self.out.write_str("fn(")?;
+                self.list(params, |p, param| p.ty(param))?;
+                self.out.write_char(')')?;
+                match ret {
+                    Some(ret) => {
+                        self.out.write_str(" -> ")?;
+                        self.ty(ret)
+                    }
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+
+    fn pattern(&mut self, pattern: &Pattern<'_>) -> fmt::Result {
+        match &pattern.kind {
+            PatternKind::Wildcard => self.out.write_char('_'),
+            PatternKind::Literal(literal) => self.literal(literal),
+            PatternKind::Identifier(name) => self.out.write_str(name),
+            PatternKind::Path { enum_name, variant } => write!(self.out, "{}::{}", enum_name.name, variant.name),
+        }
+    }
+}
+
+impl fmt::Display for Expr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::new(f).expr(self)
+    }
+}
+
+/// includes the trailing `;`.
+impl fmt::Display for Stmt<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::new(f).stmt(self)
+    }
+}
+
+/// without a trailing `;`.
+impl fmt::Display for Item<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::new(f).item(self)
+    }
+}
+
+impl fmt::Display for Attribute<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::new(f).attribute(self)
+    }
+}
+
+impl fmt::Display for TypeExpr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::new(f).ty(self)
+    }
+}
+
+impl fmt::Display for Pattern<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::new(f).pattern(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+
+    use super::to_source;
+    use crate::ast::arena::{AstArena, CloneIn};
+    use crate::ast::{BinaryOp, Expr, ExprKind, Ident};
+    use crate::parser::parse;
+    use crate::types::Span;
+
+    /// prints `text`, checks that the output parses back into the same text and returns it.
+    fn canonical(text: &str) -> String {
+        let arena = AstArena::new();
+        let parsed = parse(text, &arena);
+        assert!(parsed.errors.is_empty(), "{text:?}: {:?}", parsed.errors);
+        let printed = to_source(&parsed.stmts);
+        let reparsed = parse(&printed, &arena);
+        assert!(reparsed.errors.is_empty(), "{printed:?}: {:?}", reparsed.errors);
+        assert_eq!(to_source(&reparsed.stmts), printed);
+        printed
+    }
+
+    #[test]
+    fn statements_and_items() {
+        assert_eq!(
+            canonical("let  v2 :mut   u8=0;v2=1 ; struct Lol{ty:type,un:myunion}union U{} enum E{a,b,}"),
+            "let v2: mut u8 = 0;\nv2 = 1;\nstruct Lol {\n    ty: type,\n    un: myunion,\n};\nunion U {};\n\
+             enum E {\n    a,\n    b,\n};\n"
+        );
+        assert_eq!(
+            canonical("let t: (const usize, anymut *anymut u8, [5 u8], fn(u8) -> fn()) = uninit;"),
+            "let t: (const usize, anymut *anymut u8, [5 u8], fn(u8) -> fn()) = uninit;\n"
+        );
+        assert_eq!(canonical("mod adt ;import std . io.print;"), "mod adt;\nimport std.io.print;\n");
+    }
+
+    #[test]
+    fn attributes_and_functions() {
+        assert_eq!(
+            canonical("#[extern(\"C\")] #[inline]extern fn f(let a: u8,) ;fn g()->u8{#[x(1, y)]struct S{} 1}"),
+            "#[extern(\"C\")]\n#[inline]\nextern fn f(let a: u8);\n\
+             fn g() -> u8 {\n    #[x(1, y)]\n    struct S {};\n    1\n}\n"
+        );
+    }
+
+    #[test]
+    fn control_flow() {
+        assert_eq!(
+            canonical("if cond == true { mynum = mynum + 1; } else if x { } else { 2 };"),
+            "if cond == true {\n    mynum = mynum + 1;\n} else if x {} else {\n    2\n};\n"
+        );
+        assert_eq!(
+            canonical("@outer while i<10 { loop { break @outer 1; } continue @outer; };let w = compiletime { 3 };"),
+            "@outer while i < 10 {\n    loop {\n        break @outer 1;\n    };\n    continue @outer;\n};\n\
+             let w = compiletime {\n    3\n};\n"
+        );
+        assert_eq!(
+            canonical("match v { __variant1 => 1, 'c' => { 2 } _ => return, };"),
+            "match v {\n    __variant1 => 1,\n    'c' => {\n        2\n    },\n    _ => return,\n};\n"
+        );
+    }
+
+    #[test]
+    fn struct_literals() {
+        assert_eq!(canonical("let p=Point{x:1,y:a+1,};let e = Empty{};"), "let p = Point { x: 1, y: a + 1 };
+let e = Empty {};
+");
+        assert_eq!(canonical("f(P{x:1}.x);"), "f(P { x: 1 }.x);
+");
+        assert_eq!(
+            canonical("if (P { x: 1 }).x == a {} while a == (P { x: 1 }) {} match (P{x:1}) {};"),
+            "if (P { x: 1 }.x == a) {};
+while (a == P { x: 1 }) {};
+match (P { x: 1 }) {};
+"
+        );
+    }
+
+    #[test]
+    fn operators_keep_their_grouping() {
+        assert_eq!(canonical("a=b+=1*2-3<<4&5|6^7;"), "a = b += 1 * 2 - 3 << 4 & 5 | 6 ^ 7;\n");
+        assert_eq!(canonical("x = \"s\" == 'c';"), "x = \"s\" == 'c';\n");
+        assert_eq!(canonical("a.b [i]=f ( x,y+1, )* 2;"), "a.b[i] = f(x, y + 1) * 2;\n");
+        assert_eq!(canonical("((a+b))*(c-(d-e));"), "(a + b) * (c - (d - e));\n");
+        assert_eq!(canonical("*p = -(a+b).c * - -x + !~y;"), "*p = -(a + b).c * --x + !~y;\n");
+        assert_eq!(canonical("(-a).b;"), "(-a).b;\n");
+        assert_eq!(canonical("-a cast u8 + (b+1) cast *const i8;"), "-a cast u8 + (b + 1) cast *const i8;\n");
+        assert_eq!(canonical("-(a cast i8) cast u16;"), "-(a cast i8) cast u16;\n");
+
+        // trees the parser can't produce without parentheses
+        let arena = AstArena::new();
+        let leaf = |name| Expr::new(ExprKind::Identifier(name), Span::new(0, 0));
+        let binary = |op, lhs, rhs| {
+            let kind = ExprKind::Binary {
+                op,
+                lhs: arena.alloc_expr(lhs),
+                rhs: arena.alloc_expr(rhs),
+            };
+            Expr::new(kind, Span::new(0, 0))
+        };
+        let sum = binary(BinaryOp::Add, leaf("a"), leaf("b"));
+        assert_eq!(binary(BinaryOp::Mul, sum.clone_in(&arena), leaf("c")).to_string(), "(a + b) * c");
+        assert_eq!(binary(BinaryOp::Sub, leaf("c"), sum.clone_in(&arena)).to_string(), "c - (a + b)");
+        assert_eq!(binary(BinaryOp::Add, sum.clone_in(&arena), leaf("c")).to_string(), "a + b + c");
+        let field = ExprKind::Field {
+            expr: arena.alloc_expr(sum.clone_in(&arena)),
+            field: Ident::new("c", Span::new(0, 0)),
+        };
+        assert_eq!(Expr::new(field, Span::new(0, 0)).to_string(), "(a + b).c");
+        let less = binary(BinaryOp::Lt, leaf("a"), leaf("b"));
+        assert_eq!(binary(BinaryOp::Lt, less, leaf("c")).to_string(), "(a < b) < c");
+    }
+}
@@ -0,0 +1,370 @@
+//! read-only traversal of syntax trees.
+//!
+//! every `visit_*` method of `Visitor` defaults to the matching `walk_*` function, which
+//! visits the children of the node in source order. a pass overrides the methods for the
+//! nodes it cares about and calls `walk_*` from them to keep descending, or leaves it out to
+//! skip the children.
+
+use crate::ast::{
+    AdtDecl, Attribute, Block, EnumDecl, Expr, ExprKind, Field, FnDecl, Ident, ImportDecl, Item, ItemKind, MatchArm,
+    Param, Pattern, Stmt, StmtKind, TypeExpr, TypeKind, Variant,
+};
+
+/// nodes are borrowed for `'ast`, so a visitor can keep references to them.
+pub trait Visitor<'ast, 'source: 'ast> {
+    #[inline]
+    fn visit_stmt(&mut self, stmt: &'ast Stmt<'source>) {
+        walk_stmt(self, stmt);
+    }
+
+    #[inline]
+    fn visit_item(&mut self, item: &'ast Item<'source>) {
+        walk_item(self, item);
+    }
+
+    /// attributes are leaves, the names in them don't refer to anything.
+    #[inline]
+    fn visit_attribute(&mut self, _attr: &'ast Attribute<'source>) {}
+
+    #[inline]
+    fn visit_fn_decl(&mut self, decl: &'ast FnDecl<'source>) {
+        walk_fn_decl(self, decl);
+    }
+
+    #[inline]
+    fn visit_param(&mut self, param: &'ast Param<'source>) {
+        walk_param(self, param);
+    }
+
+    /// structs and unions.
+    #[inline]
+    fn visit_adt_decl(&mut self, adt: &'ast AdtDecl<'source>) {
+        walk_adt_decl(self, adt);
+    }
+
+    #[inline]
+    fn visit_field(&mut self, field: &'ast Field<'source>) {
+        walk_field(self, field);
+    }
+
+    #[inline]
+    fn visit_enum_decl(&mut self, decl: &'ast EnumDecl<'source>) {
+        walk_enum_decl(self, decl);
+    }
+
+    #[inline]
+    fn visit_variant(&mut self, variant: &'ast Variant<'source>) {
+        self.visit_ident(&variant.name);
+    }
+
+    /// the modules in `path` aren't visited, only the imported name.
+    #[inline]
+    fn visit_import_decl(&mut self, decl: &'ast ImportDecl<'source>) {
+        self.visit_ident(&decl.name);
+    }
+
+    #[inline]
+    fn visit_block(&mut self, block: &'ast Block<'source>) {
+        walk_block(self, block);
+    }
+
+    #[inline]
+    fn visit_expr(&mut self, expr: &'ast Expr<'source>) {
+        walk_expr(self, expr);
+    }
+
+    #[inline]
+    fn visit_match_arm(&mut self, arm: &'ast MatchArm<'source>) {
+        walk_match_arm(self, arm);
+    }
+
+    #[inline]
+    fn visit_pattern(&mut self, _pattern: &'ast Pattern<'source>) {}
+
+    #[inline]
+    fn visit_type(&mut self, ty: &'ast TypeExpr<'source>) {
+        walk_type(self, ty);
+    }
+
+    /// a name being declared: a `let`, an item, a parameter, a field or a variant.
+    #[inline]
+    fn visit_ident(&mut self, _ident: &'ast Ident<'source>) {}
+
+    /// a loop label, both where it is declared and where `break` or `continue` refers to it.
+    #[inline]
+    fn visit_label(&mut self, _label: &'ast Ident<'source>) {}
+}
+
+/// visits each statement of a program in order.
+pub fn walk_stmts<'ast, 'source, V: Visitor<'ast, 'source> + ?Sized>(visitor: &mut V, stmts: &'ast [Stmt<'source>]) {
+    for stmt in stmts {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<'ast, 'source, V: Visitor<'ast, 'source> + ?Sized>(visitor: &mut V, stmt: &'ast Stmt<'source>) {
+    match &stmt.kind {
+        StmtKind::Let { name, ty, value } => {
+            visitor.visit_ident(name);
+            if let Some(ty) = ty {
+                visitor.visit_type(ty);
+            }
+            visitor.visit_expr(value);
+        }
+        StmtKind::Expr(expr) => visitor.visit_expr(expr),
+        StmtKind::Item(item) => visitor.visit_item(item),
+    }
+}
+
+pub fn walk_item<'ast, 'source, V: Visitor<'ast, 'source> + ?Sized>(visitor: &mut V, item: &'ast Item<'source>) {
+    for attr in &item.attrs {
+        visitor.visit_attribute(attr);
+    }
+    match &item.kind {
+        ItemKind::Fn(decl) => visitor.visit_fn_decl(decl),
+        ItemKind::Struct(adt) | ItemKind::Union(adt) => visitor.visit_adt_decl(adt),
+        ItemKind::Enum(decl) => visitor.visit_enum_decl(decl),
+        ItemKind::Mod(decl) => visitor.visit_ident(&decl.name),
+        ItemKind::Import(decl) => visitor.visit_import_decl(decl),
+    }
+}
+
+pub fn walk_fn_decl<'ast, 'source, V: Visitor<'ast, 'source> + ?Sized>(visitor: &mut V, decl: &'ast FnDecl<'source>) {
+    visitor.visit_ident(&decl.name);
+    for param in &decl.params {
+        visitor.visit_param(param);
+    }
+    if let Some(ret) = &decl.ret {
+        visitor.visit_type(ret);
+    }
+    if let Some(body) = &decl.body {
+        visitor.visit_block(body);
+    }
+}
+
+pub fn walk_param<'ast, 'source, V: Visitor<'ast, 'source> + ?Sized>(visitor: &mut V, param: &'ast Param<'source>) {
+    visitor.visit_ident(&param.name);
+    visitor.visit_type(&param.ty);
+}
+
+pub fn walk_adt_decl<'ast, 'source, V: Visitor<'ast, 'source> + ?Sized>(visitor: &mut V, adt: &'ast AdtDecl<'source>) {
+    visitor.visit_ident(&adt.name);
+    for field in &adt.fields {
+        visitor.visit_field(field);
+    }
+}
+
+pub fn walk_field<'ast, 'source, V: Visitor<'ast, 'source> + ?Sized>(visitor: &mut V, field: &'ast Field<'source>) {
+    visitor.visit_ident(&field.name);
+    visitor.visit_type(&field.ty);
+}
+
+pub fn walk_enum_decl<'ast, 'source, V: Visitor<'ast, 'source> + ?Sized>(
+    visitor: &mut V,
+    decl: &'ast EnumDecl<'source>,
+) {
+    visitor.visit_ident(&decl.name);
+    for variant in &decl.variants {
+        visitor.visit_variant(variant);
+    }
+}
+
+pub fn walk_block<'ast, 'source, V: Visitor<'ast, 'source> + ?Sized>(visitor: &mut V, block: &'ast Block<'source>) {
+    walk_stmts(visitor, &block.stmts);
+    if let Some(tail) = &block.tail {
+        visitor.visit_expr(tail);
+    }
+}
+
+pub fn walk_expr<'ast, 'source, V: Visitor<'ast, 'source> + ?Sized>(visitor: &mut V, expr: &'ast Expr<'source>) {
+    match &expr.kind {
+        ExprKind::Literal(_) | ExprKind::Identifier(_) | ExprKind::Path { .. } => {}
+        ExprKind::Unary { expr, .. } => visitor.visit_expr(expr),
+        ExprKind::Binary { lhs, rhs, .. } => {
+            visitor.visit_expr(lhs);
+            visitor.visit_expr(rhs);
+        }
+        ExprKind::Assign { target, value, .. } => {
+            visitor.visit_expr(target);
+            visitor.visit_expr(value);
+        }
+        ExprKind::Call { callee, args } => {
+            visitor.visit_expr(callee);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        ExprKind::Struct { fields, .. } => {
+            for field in fields {
+                visitor.visit_expr(&field.value);
+            }
+        }
+        ExprKind::Field { expr, .. } => visitor.visit_expr(expr),
+        ExprKind::Index { expr, index } => {
+            visitor.visit_expr(expr);
+            visitor.visit_expr(index);
+        }
+        ExprKind::Cast { expr, ty } => {
+            visitor.visit_expr(expr);
+            visitor.visit_type(ty);
+        }
+        ExprKind::Block(block) => visitor.visit_block(block),
+        ExprKind::If {
+            cond,
+            then_branch,
+            else_branch,
+        } => {
+            visitor.visit_expr(cond);
+            visitor.visit_block(then_branch);
+            if let Some(else_branch) = else_branch {
+                visitor.visit_expr(else_branch);
+            }
+        }
+        ExprKind::While { label, cond, body } => {
+            if let Some(label) = label {
+                visitor.visit_label(label);
+            }
+            visitor.visit_expr(cond);
+            visitor.visit_block(body);
+        }
+        ExprKind::Loop { label, body } => {
+            if let Some(label) = label {
+                visitor.visit_label(label);
+            }
+            visitor.visit_block(body);
+        }
+        ExprKind::Break { label, value } => {
+            if let Some(label) = label {
+                visitor.visit_label(label);
+            }
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+        ExprKind::Continue { label } => {
+            if let Some(label) = label {
+                visitor.visit_label(label);
+            }
+        }
+        ExprKind::Match { scrutinee, arms } => {
+            visitor.visit_expr(scrutinee);
+            for arm in arms {
+                visitor.visit_match_arm(arm);
+            }
+        }
+        ExprKind::Fn(decl) => visitor.visit_fn_decl(decl),
+        ExprKind::Return(value) => {
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+    }
+}
+
+pub fn walk_match_arm<'ast, 'source, V: Visitor<'ast, 'source> + ?Sized>(
+    visitor: &mut V,
+    arm: &'ast MatchArm<'source>,
+) {
+    visitor.visit_pattern(&arm.pattern);
+    visitor.visit_expr(&arm.body);
+}
+
+pub fn walk_type<'ast, 'source, V: Visitor<'ast, 'source> + ?Sized>(visitor: &mut V, ty: &'ast TypeExpr<'source>) {
+    match &ty.kind {
+        TypeKind::Named(_) | TypeKind::Type => {}
+        TypeKind::Pointer(pointee) => visitor.visit_type(pointee),
+        TypeKind::Array { len, elem } => {
+            visitor.visit_expr(len);
+            visitor.visit_type(elem);
+        }
+        TypeKind::Tuple(elems) => {
+            for elem in elems {
+                visitor.visit_type(elem);
+            }
+        }
+        TypeKind::Fn { params, ret } => {
+            for param in params {
+                visitor.visit_type(param);
+            }
+            if let Some(ret) = ret {
+                visitor.visit_type(ret);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{Visitor, walk_expr, walk_stmts};
+    use crate::ast::arena::AstArena;
+    use crate::ast::{Block, Expr, ExprKind, Ident, TypeExpr};
+    use crate::parser::parse;
+
+    /// everything a name resolution pass would look at.
+    #[derive(Default)]
+    struct Names<'ast> {
+        declared: Vec<&'ast str>,
+        used: Vec<&'ast str>,
+        labels: Vec<&'ast str>,
+        types: usize,
+    }
+
+    impl<'ast, 'source: 'ast> Visitor<'ast, 'source> for Names<'ast> {
+        fn visit_expr(&mut self, expr: &'ast Expr<'source>) {
+            if let ExprKind::Identifier(name) = expr.kind {
+                self.used.push(name);
+            }
+            walk_expr(self, expr);
+        }
+
+        fn visit_type(&mut self, _ty: &'ast TypeExpr<'source>) {
+            // nested types are not walked
+            self.types += 1;
+        }
+
+        fn visit_ident(&mut self, ident: &'ast Ident<'source>) {
+            self.declared.push(ident.name);
+        }
+
+        fn visit_label(&mut self, label: &'ast Ident<'source>) {
+            self.labels.push(label.name);
+        }
+    }
+
+    #[test]
+    fn visits_every_node_in_source_order() {
+        let arena = AstArena::new();
+        let parsed = parse(
+            "struct S { a: *u8, b: (u8, u8) } enum E { x, y }
+            let v: [n u8] = a + b;
+            @outer while c { match d { e => f = g, _ => break @outer h } }",
+            &arena,
+        );
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let mut names = Names::default();
+        walk_stmts(&mut names, &parsed.stmts);
+        assert_eq!(names.declared, ["S", "a", "b", "E", "x", "y", "v"]);
+        assert_eq!(names.used, ["a", "b", "c", "d", "f", "g", "h"]);
+        assert_eq!(names.labels, ["outer", "outer"]);
+        assert_eq!(names.types, 3);
+    }
+
+    #[test]
+    fn overrides_can_skip_children() {
+        struct Blocks(usize);
+
+        impl<'ast, 'source: 'ast> Visitor<'ast, 'source> for Blocks {
+            fn visit_block(&mut self, _block: &'ast Block<'source>) {
+                self.0 += 1;
+            }
+        }
+
+        let arena = AstArena::new();
+        let parsed = parse("{ { 1 } }; if a { { 2 } } else { 3 };", &arena);
+        let mut blocks = Blocks(0);
+        walk_stmts(&mut blocks, &parsed.stmts);
+        assert_eq!(blocks.0, 3);
+    }
+}
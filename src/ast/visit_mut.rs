@@ -0,0 +1,374 @@
+//! in-place rewriting of syntax trees, the mutable counterpart of `visit`.
+//!
+//! `MutVisitor` works like `Visitor`, but hands out `&mut` nodes, so a pass can replace a
+//! node's `kind` while keeping its span, or swap in a whole new node. calling `walk_*_mut`
+//! before rewriting a node rewrites its children first.
+
+use crate::ast::arena::{AstArena, CloneIn};
+use crate::ast::{
+    AdtDecl, Attribute, Block, EnumDecl, Expr, ExprKind, Field, FnDecl, Ident, ImportDecl, Item, ItemKind, MatchArm,
+    Param, Pattern, Stmt, StmtKind, TypeExpr, TypeKind, Variant,
+};
+
+pub trait MutVisitor<'source> {
+    #[inline]
+    fn visit_stmt(&mut self, stmt: &mut Stmt<'source>) {
+        walk_stmt_mut(self, stmt);
+    }
+
+    #[inline]
+    fn visit_item(&mut self, item: &mut Item<'source>) {
+        walk_item_mut(self, item);
+    }
+
+    /// attributes are leaves, the names in them don't refer to anything.
+    #[inline]
+    fn visit_attribute(&mut self, _attr: &mut Attribute<'source>) {}
+
+    #[inline]
+    fn visit_fn_decl(&mut self, decl: &mut FnDecl<'source>) {
+        walk_fn_decl_mut(self, decl);
+    }
+
+    #[inline]
+    fn visit_param(&mut self, param: &mut Param<'source>) {
+        walk_param_mut(self, param);
+    }
+
+    /// structs and unions.
+    #[inline]
+    fn visit_adt_decl(&mut self, adt: &mut AdtDecl<'source>) {
+        walk_adt_decl_mut(self, adt);
+    }
+
+    #[inline]
+    fn visit_field(&mut self, field: &mut Field<'source>) {
+        walk_field_mut(self, field);
+    }
+
+    #[inline]
+    fn visit_enum_decl(&mut self, decl: &mut EnumDecl<'source>) {
+        walk_enum_decl_mut(self, decl);
+    }
+
+    #[inline]
+    fn visit_variant(&mut self, variant: &mut Variant<'source>) {
+        self.visit_ident(&mut variant.name);
+    }
+
+    /// the modules in `path` aren't visited, only the imported name.
+    #[inline]
+    fn visit_import_decl(&mut self, decl: &mut ImportDecl<'source>) {
+        self.visit_ident(&mut decl.name);
+    }
+
+    #[inline]
+    fn visit_block(&mut self, block: &mut Block<'source>) {
+        walk_block_mut(self, block);
+    }
+
+    #[inline]
+    fn visit_expr(&mut self, expr: &mut Expr<'source>) {
+        walk_expr_mut(self, expr);
+    }
+
+    #[inline]
+    fn visit_match_arm(&mut self, arm: &mut MatchArm<'source>) {
+        walk_match_arm_mut(self, arm);
+    }
+
+    #[inline]
+    fn visit_pattern(&mut self, _pattern: &mut Pattern<'source>) {}
+
+    #[inline]
+    fn visit_type(&mut self, ty: &mut TypeExpr<'source>) {
+        walk_type_mut(self, ty);
+    }
+
+    /// see `Visitor::visit_ident`.
+    #[inline]
+    fn visit_ident(&mut self, _ident: &mut Ident<'source>) {}
+
+    /// see `Visitor::visit_label`.
+    #[inline]
+    fn visit_label(&mut self, _label: &mut Ident<'source>) {}
+}
+
+pub fn walk_stmts_mut<'source, V: MutVisitor<'source> + ?Sized>(visitor: &mut V, stmts: &mut [Stmt<'source>]) {
+    for stmt in stmts {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt_mut<'source, V: MutVisitor<'source> + ?Sized>(visitor: &mut V, stmt: &mut Stmt<'source>) {
+    match &mut stmt.kind {
+        StmtKind::Let { name, ty, value } => {
+            visitor.visit_ident(name);
+            if let Some(ty) = ty {
+                visitor.visit_type(ty);
+            }
+            visitor.visit_expr(value);
+        }
+        StmtKind::Expr(expr) => visitor.visit_expr(expr),
+        StmtKind::Item(item) => visitor.visit_item(item),
+    }
+}
+
+pub fn walk_item_mut<'source, V: MutVisitor<'source> + ?Sized>(visitor: &mut V, item: &mut Item<'source>) {
+    for attr in &mut item.attrs {
+        visitor.visit_attribute(attr);
+    }
+    match &mut item.kind {
+        ItemKind::Fn(decl) => visitor.visit_fn_decl(decl),
+        ItemKind::Struct(adt) | ItemKind::Union(adt) => visitor.visit_adt_decl(adt),
+        ItemKind::Enum(decl) => visitor.visit_enum_decl(decl),
+        ItemKind::Mod(decl) => visitor.visit_ident(&mut decl.name),
+        ItemKind::Import(decl) => visitor.visit_import_decl(decl),
+    }
+}
+
+pub fn walk_fn_decl_mut<'source, V: MutVisitor<'source> + ?Sized>(visitor: &mut V, decl: &mut FnDecl<'source>) {
+    visitor.visit_ident(&mut decl.name);
+    for param in &mut decl.params {
+        visitor.visit_param(param);
+    }
+    if let Some(ret) = &mut decl.ret {
+        visitor.visit_type(ret);
+    }
+    if let Some(body) = &mut decl.body {
+        visitor.visit_block(body);
+    }
+}
+
+pub fn walk_param_mut<'source, V: MutVisitor<'source> + ?Sized>(visitor: &mut V, param: &mut Param<'source>) {
+    visitor.visit_ident(&mut param.name);
+    visitor.visit_type(&mut param.ty);
+}
+
+pub fn walk_adt_decl_mut<'source, V: MutVisitor<'source> + ?Sized>(visitor: &mut V, adt: &mut AdtDecl<'source>) {
+    visitor.visit_ident(&mut adt.name);
+    for field in &mut adt.fields {
+        visitor.visit_field(field);
+    }
+}
+
+pub fn walk_field_mut<'source, V: MutVisitor<'source> + ?Sized>(visitor: &mut V, field: &mut Field<'source>) {
+    visitor.visit_ident(&mut field.name);
+    visitor.visit_type(&mut field.ty);
+}
+
+pub fn walk_enum_decl_mut<'source, V: MutVisitor<'source> + ?Sized>(visitor: &mut V, decl: &mut EnumDecl<'source>) {
+    visitor.visit_ident(&mut decl.name);
+    for variant in &mut decl.variants {
+        visitor.visit_variant(variant);
+    }
+}
+
+pub fn walk_block_mut<'source, V: MutVisitor<'source> + ?Sized>(visitor: &mut V, block: &mut Block<'source>) {
+    walk_stmts_mut(visitor, &mut block.stmts);
+    if let Some(tail) = &mut block.tail {
+        visitor.visit_expr(tail);
+    }
+}
+
+pub fn walk_expr_mut<'source, V: MutVisitor<'source> + ?Sized>(visitor: &mut V, expr: &mut Expr<'source>) {
+    match &mut expr.kind {
+        ExprKind::Literal(_) | ExprKind::Identifier(_) | ExprKind::Path { .. } => {}
+        ExprKind::Unary { expr, .. } => visitor.visit_expr(expr),
+        ExprKind::Binary { lhs, rhs, .. } => {
+            visitor.visit_expr(lhs);
+            visitor.visit_expr(rhs);
+        }
+        ExprKind::Assign { target, value, .. } => {
+            visitor.visit_expr(target);
+            visitor.visit_expr(value);
+        }
+        ExprKind::Call { callee, args } => {
+            visitor.visit_expr(callee);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        ExprKind::Struct { fields, .. } => {
+            for field in fields {
+                visitor.visit_expr(&mut field.value);
+            }
+        }
+        ExprKind::Field { expr, .. } => visitor.visit_expr(expr),
+        ExprKind::Index { expr, index } => {
+            visitor.visit_expr(expr);
+            visitor.visit_expr(index);
+        }
+        ExprKind::Cast { expr, ty } => {
+            visitor.visit_expr(expr);
+            visitor.visit_type(ty);
+        }
+        ExprKind::Block(block) => visitor.visit_block(block),
+        ExprKind::If {
+            cond,
+            then_branch,
+            else_branch,
+        } => {
+            visitor.visit_expr(cond);
+            visitor.visit_block(then_branch);
+            if let Some(else_branch) = else_branch {
+                visitor.visit_expr(else_branch);
+            }
+        }
+        ExprKind::While { label, cond, body } => {
+            if let Some(label) = label {
+                visitor.visit_label(label);
+            }
+            visitor.visit_expr(cond);
+            visitor.visit_block(body);
+        }
+        ExprKind::Loop { label, body } => {
+            if let Some(label) = label {
+                visitor.visit_label(label);
+            }
+            visitor.visit_block(body);
+        }
+        ExprKind::Break { label, value } => {
+            if let Some(label) = label {
+                visitor.visit_label(label);
+            }
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+        ExprKind::Continue { label } => {
+            if let Some(label) = label {
+                visitor.visit_label(label);
+            }
+        }
+        ExprKind::Match { scrutinee, arms } => {
+            visitor.visit_expr(scrutinee);
+            for arm in arms {
+                visitor.visit_match_arm(arm);
+            }
+        }
+        ExprKind::Fn(decl) => visitor.visit_fn_decl(decl),
+        ExprKind::Return(value) => {
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+    }
+}
+
+pub fn walk_match_arm_mut<'source, V: MutVisitor<'source> + ?Sized>(visitor: &mut V, arm: &mut MatchArm<'source>) {
+    visitor.visit_pattern(&mut arm.pattern);
+    visitor.visit_expr(&mut arm.body);
+}
+
+pub fn walk_type_mut<'source, V: MutVisitor<'source> + ?Sized>(visitor: &mut V, ty: &mut TypeExpr<'source>) {
+    match &mut ty.kind {
+        TypeKind::Named(_) | TypeKind::Type => {}
+        TypeKind::Pointer(pointee) => visitor.visit_type(pointee),
+        TypeKind::Array { len, elem } => {
+            visitor.visit_expr(len);
+            visitor.visit_type(elem);
+        }
+        TypeKind::Tuple(elems) => {
+            for elem in elems {
+                visitor.visit_type(elem);
+            }
+        }
+        TypeKind::Fn { params, ret } => {
+            for param in params {
+                visitor.visit_type(param);
+            }
+            if let Some(ret) = ret {
+                visitor.visit_type(ret);
+            }
+        }
+    }
+}
+
+/// rewrites `a op= b` into `a = a op b`. the assignment keeps its span, the new binary
+/// expression spans from the target to the end of the value.
+#[derive(Debug, Clone, Copy)]
+pub struct DesugarCompoundAssign<'source> {
+    /// where the copy of the target and the new binary expression go.
+    pub arena: &'source AstArena<'source>,
+}
+
+impl<'source> MutVisitor<'source> for DesugarCompoundAssign<'source> {
+    fn visit_expr(&mut self, expr: &mut Expr<'source>) {
+        walk_expr_mut(self, expr);
+        if let ExprKind::Assign {
+            op: op @ Some(_),
+            target,
+            value,
+        } = &mut expr.kind
+        {
+            let rhs = Expr::new(core::mem::replace(&mut value.kind, ExprKind::Return(None)), value.span);
+            value.kind = ExprKind::Binary {
+                op: op.take().expect("matched `Some` above"),
+                lhs: target.clone_in(self.arena),
+                rhs: self.arena.alloc_expr(rhs),
+            };
+            value.span = target.span.merge(value.span);
+        }
+    }
+}
+
+/// shorthand for running `DesugarCompoundAssign` over a program.
+#[inline]
+pub fn desugar_compound_assignments<'source>(stmts: &mut [Stmt<'source>], arena: &'source AstArena<'source>) {
+    walk_stmts_mut(&mut DesugarCompoundAssign { arena }, stmts);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MutVisitor, desugar_compound_assignments, walk_expr_mut, walk_stmts_mut};
+    use crate::ast::arena::AstArena;
+    use crate::ast::pretty::to_source;
+    use crate::ast::{BinaryOp, Expr, ExprKind, StmtKind};
+    use crate::parser::parse;
+    use crate::types::{Span, Token};
+
+    #[test]
+    fn compound_assignments_become_plain_ones() {
+        let arena = AstArena::new();
+        let mut parsed = parse("a += 1; b <<= c *= 2; while x { y -= z; };", &arena);
+        assert!(parsed.errors.is_empty());
+        desugar_compound_assignments(&mut parsed.stmts, &arena);
+        assert_eq!(
+            to_source(&parsed.stmts),
+            "a = a + 1;\nb = b << (c = c * 2);\nwhile x {\n    y = y - z;\n};\n"
+        );
+
+        let StmtKind::Expr(assign) = &parsed.stmts[0].kind else { panic!() };
+        assert_eq!(assign.span, Span::new(0, 6));
+        let ExprKind::Assign { op: None, target, value } = &assign.kind else { panic!() };
+        assert_eq!(target.span, Span::new(0, 1));
+        assert_eq!(value.span, Span::new(0, 6));
+    }
+
+    #[test]
+    fn nodes_can_be_replaced_in_place() {
+        /// drops `* 1`, keeping the span of the expression it replaces.
+        struct TimesOne;
+
+        impl<'source> MutVisitor<'source> for TimesOne {
+            fn visit_expr(&mut self, expr: &mut Expr<'source>) {
+                walk_expr_mut(self, expr);
+                if let ExprKind::Binary { op: BinaryOp::Mul, lhs, rhs } = &mut expr.kind
+                    && let ExprKind::Literal(literal) = rhs.kind
+                    && literal.token() == Token::LitInteger
+                    && literal.raw() == b"1"
+                {
+                    expr.kind = core::mem::replace(&mut lhs.kind, ExprKind::Identifier(""));
+                }
+            }
+        }
+
+        let arena = AstArena::new();
+        let mut parsed = parse("let v = a * 1 + b * 1 * 1;", &arena);
+        walk_stmts_mut(&mut TimesOne, &mut parsed.stmts);
+        assert_eq!(to_source(&parsed.stmts), "let v = a + b;\n");
+        let StmtKind::Let { value, .. } = &parsed.stmts[0].kind else { panic!() };
+        assert_eq!(value.span, Span::new(8, 25));
+    }
+}
@@ -0,0 +1,1049 @@
+//! a backend emitting portable C99 from the `ir`: one `.c` file for the whole program that
+//! includes the small runtime header `RUNTIME_HEADER`, see `emit`.
+//!
+//! every body becomes a C function that declares its locals up front under their IR names,
+//! `_1`, and whose blocks are labels its terminators `goto`. extern functions keep their
+//! names so that they link, which makes a name C reserves an error. the others are prefixed
+//! with `mb_f_`, or with `mb_f` and their body id followed by `_` when an earlier function has
+//! the same name. the top-level code is the function `mb_top_level`, which `main` calls.
+//! structs and fields keep their names unless C reserves them, then they get an `mb_r_` prefix.
+//!
+//! integers are the `<stdint.h>` types, `usize` and `isize` are `uintptr_t` and `intptr_t`.
+//! structs and unions are C ones and an enum is its discriminant type. arrays of a known length
+//! and tuples are wrapped in structs so that they can be assigned, a pointer to an array of
+//! unknown length points to its first element. values of `()` and `!` aren't stored at all.
+//! unsuffixed values get the type `typeck` defaults them to, a string is a pointer to its bytes.
+//!
+//! arithmetic is C's: signed overflow, division by zero and shifts past the width aren't
+//! checked. `%` on floats is `fmod`, so the program links against the math library. `==` and
+//! `!=` compare structs, tuples and arrays member by member through generated `mb_eq`
+//! functions, unions can't be compared. casts from floats to integers saturate, the way
+//! `typeck` describes them. values C has no type for, like `literal` bindings, are reported as
+//! `CError`s and their code is left out.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::ast::BinaryOp;
+#[cfg(feature = "std")]
+use crate::emit_sink::EmitSink;
+use crate::ir::cfg::Cfg;
+use crate::ir::{BlockId, Body, BodyId, Constant, Operand, Place, Program, Projection, Rvalue, StatementKind};
+use crate::ir::{LocalId, TerminatorKind};
+use crate::literal_value::{Literal, decode_char_literal, parse_u128};
+use crate::ty::{FloatTy, IntTy, TyId, TyKind, TyTable};
+use crate::typeck::{Checked, StructLayout};
+use crate::types::{Span, Spanned, Token};
+
+/// the file name generated code includes the runtime as.
+pub const RUNTIME_HEADER_NAME: &str = "mumbo_rt.h";
+
+/// what generated code needs besides the standard headers of freestanding C and `<math.h>`. the
+/// only other library function it declares is `abort`, which extern functions can't be named
+/// like.
+pub const RUNTIME_HEADER: &str = "\
+/* runtime support for C generated by mumbo */
+#ifndef MUMBO_RT_H
+#define MUMBO_RT_H
+
+#include <math.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+void abort(void);
+
+/* the end of a `match` no arm matched */
+#define mb_unreachable() abort()
+
+static inline void mb_copy(void *dest, const void *src, size_t len) {
+    unsigned char *to = dest;
+    const unsigned char *from = src;
+    while (len--) {
+        *to++ = *from++;
+    }
+}
+
+/* the bytes of the union `src` read as the type of `dest`, see `cast` */
+#define mb_reinterpret(dest, src) \\
+    mb_copy(&(dest), &(src), sizeof(dest) < sizeof(src) ? sizeof(dest) : sizeof(src))
+
+#endif
+";
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CErrorKind {
+    /// a value C has no type for, like an array without a length.
+    Unrepresentable(TyId),
+    /// `uninit` used as anything but the whole value of an assignment.
+    UninitOperand,
+    /// an extern function named like something C or the runtime header declares, see
+    /// `is_reserved`.
+    ReservedExternName,
+    /// `==` or `!=` on a union, or on a value with a union in it.
+    UnionComparison(TyId),
+}
+
+impl CErrorKind {
+    /// stable identifier like `E0701`.
+    #[inline]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            CErrorKind::Unrepresentable(_) => "E0701",
+            CErrorKind::UninitOperand => "E0702",
+            CErrorKind::ReservedExternName => "E0703",
+            CErrorKind::UnionComparison(_) => "E0704",
+        }
+    }
+
+    pub fn message(&self, tys: &TyTable<'_>) -> String {
+        match self {
+            CErrorKind::Unrepresentable(ty) => {
+                format!("values of type `{}` can't be represented in C", tys.display(*ty))
+            }
+            CErrorKind::UninitOperand => "`uninit` can only be assigned".to_string(),
+            CErrorKind::ReservedExternName => "the name of this extern function is reserved in C".to_string(),
+            CErrorKind::UnionComparison(ty) => {
+                format!("`{}` can't be compared, a union doesn't know which member it holds", tys.display(*ty))
+            }
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CError {
+    pub kind: CErrorKind,
+    pub span: Span,
+}
+
+impl CError {
+    #[inline]
+    pub const fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+}
+
+/// what `emit` returns.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CSource {
+    /// the `.c` file, complete unless there are errors.
+    pub code: String,
+    pub errors: Vec<CError>,
+}
+
+impl CSource {
+    /// emits the program as `{name}.c` next to the runtime header.
+    #[cfg(feature = "std")]
+    pub fn write_to(&self, sink: &mut impl EmitSink, name: &str) -> std::io::Result<()> {
+        sink.emit_file(RUNTIME_HEADER_NAME, RUNTIME_HEADER.as_bytes())?;
+        sink.emit_file(&format!("{}.c", name), self.code.as_bytes())?;
+        sink.finish()
+    }
+}
+
+/// C keywords and the macros of `<stdbool.h>`.
+const KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else", "enum", "extern",
+    "float", "for", "goto", "if", "inline", "int", "long", "register", "restrict", "return", "short", "signed",
+    "sizeof", "static", "struct", "switch", "typedef", "union", "unsigned", "void", "volatile", "while", "_Bool",
+    "_Complex", "_Imaginary", "bool", "true", "false",
+];
+
+/// whether generated code can't use `name` as it is: keywords, names the standard headers the
+/// runtime includes declare or reserve, like `int32_t`, `SIZE_MAX` or `__x`, `main`, `abort`
+/// and everything starting with `mb_`, the prefix of the runtime and of generated names.
+fn is_reserved(name: &str) -> bool {
+    let limit = name.strip_prefix('U').unwrap_or(name).strip_prefix("INT");
+    KEYWORDS.contains(&name)
+        || matches!(name, "main" | "abort" | "NULL" | "offsetof" | "SIZE_MAX")
+        || name.starts_with("mb_")
+        || name.ends_with("_t")
+        || limit.is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit() || c == '_' || c == 'M' || c == 'P'))
+        || ["PTRDIFF_", "SIG_ATOMIC_", "WCHAR_", "WINT_"].iter().any(|prefix| name.starts_with(prefix))
+        || name.starts_with("__")
+        || name.strip_prefix('_').is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_uppercase()))
+}
+
+/// emits `program`, lowered from the program `checked` is the result for. like `lower`, it
+/// expects a program without errors.
+pub fn emit(program: &Program<'_>, checked: &Checked<'_>) -> CSource {
+    let mut emitter = Emitter {
+        checked,
+        fn_names: fn_names(program),
+        ty_names: alloc::vec![None; checked.tys.len()],
+        defined: alloc::vec![false; checked.tys.len()],
+        pending: Vec::new(),
+        eq_names: alloc::vec![None; checked.tys.len()],
+        forward: String::new(),
+        definitions: String::new(),
+        equalities: String::new(),
+        errors: Vec::new(),
+    };
+    for body in &program.bodies {
+        if let Some(name) = body.name
+            && body.is_extern
+            && is_reserved(name.name)
+        {
+            emitter.error(CErrorKind::ReservedExternName, name.span);
+        }
+    }
+    let mut prototypes = String::new();
+    let mut functions = String::new();
+    for (index, body) in program.bodies.iter().enumerate() {
+        let signature = emitter.signature(BodyId::new(index), body);
+        let _ = writeln!(prototypes, "{};", signature);
+        if !body.blocks.is_empty() {
+            let _ = writeln!(functions, "\n{} {{", signature);
+            emitter.body(body, &mut functions);
+            functions.push_str("}\n");
+        }
+    }
+    while let Some(ty) = emitter.pending.pop() {
+        emitter.define(ty);
+    }
+
+    let mut code = format!("#include \"{}\"\n\n", RUNTIME_HEADER_NAME);
+    for section in [&emitter.forward, &emitter.definitions, &emitter.equalities] {
+        if !section.is_empty() {
+            code.push_str(section);
+            code.push('\n');
+        }
+    }
+    code.push_str(&prototypes);
+    code.push_str(&functions);
+    let _ = write!(code, "\nint main(void) {{\n    {}();\n    return 0;\n}}\n", emitter.fn_names[0]);
+    CSource {
+        code,
+        errors: emitter.errors,
+    }
+}
+
+/// the C name of every body, see the module docs.
+fn fn_names(program: &Program<'_>) -> Vec<String> {
+    let mut names: Vec<String> = Vec::with_capacity(program.bodies.len());
+    for (index, body) in program.bodies.iter().enumerate() {
+        let name = match body.name {
+            None => "mb_top_level".to_string(),
+            Some(name) if body.is_extern => name.name.to_string(),
+            // the body id can't clash with a name, which comes after a `_`
+            Some(name) if names.iter().any(|other| other.strip_prefix("mb_f_") == Some(name.name)) => {
+                format!("mb_f{}_{}", index, name.name)
+            }
+            Some(name) => format!("mb_f_{}", name.name),
+        };
+        names.push(name);
+    }
+    names
+}
+
+/// `name` unless it `is_reserved`, then with a prefix that no name which isn't reserved has.
+fn c_ident(name: &str) -> String {
+    if is_reserved(name) { format!("mb_r_{}", name) } else { name.to_string() }
+}
+
+/// the declaration of `name` as a `ty`.
+fn declare(ty: &str, name: &str) -> String {
+    if ty.ends_with('*') { format!("{}{}", ty, name) } else { format!("{} {}", ty, name) }
+}
+
+const fn int_name(int: IntTy) -> &'static str {
+    match int {
+        IntTy::U8 => "uint8_t",
+        IntTy::U16 => "uint16_t",
+        IntTy::U32 => "uint32_t",
+        IntTy::U64 => "uint64_t",
+        IntTy::Usize => "uintptr_t",
+        IntTy::I8 => "int8_t",
+        IntTy::I16 => "int16_t",
+        IntTy::I32 => "int32_t",
+        IntTy::I64 => "int64_t",
+        IntTy::Isize => "intptr_t",
+    }
+}
+
+/// the `<stdint.h>` macros for the smallest and largest value of `int`.
+const fn int_limits(int: IntTy) -> (&'static str, &'static str) {
+    match int {
+        IntTy::U8 => ("0", "UINT8_MAX"),
+        IntTy::U16 => ("0", "UINT16_MAX"),
+        IntTy::U32 => ("0", "UINT32_MAX"),
+        IntTy::U64 => ("0", "UINT64_MAX"),
+        IntTy::Usize => ("0", "UINTPTR_MAX"),
+        IntTy::I8 => ("INT8_MIN", "INT8_MAX"),
+        IntTy::I16 => ("INT16_MIN", "INT16_MAX"),
+        IntTy::I32 => ("INT32_MIN", "INT32_MAX"),
+        IntTy::I64 => ("INT64_MIN", "INT64_MAX"),
+        IntTy::Isize => ("INTPTR_MIN", "INTPTR_MAX"),
+    }
+}
+
+/// `bytes` as a C string literal. octal escapes take at most three digits, so unlike hex
+/// ones they can't swallow a digit following them.
+fn c_string(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() + 2);
+    out.push('"');
+    for &byte in bytes {
+        match byte {
+            b'"' | b'\\' | b'?' => {
+                out.push('\\');
+                out.push(byte as char);
+            }
+            b' '..=b'~' => out.push(byte as char),
+            _ => {
+                let _ = write!(out, "\\{:03o}", byte);
+            }
+        }
+    }
+    out.push('"');
+    out
+}
+
+struct Emitter<'a, 'source> {
+    checked: &'a Checked<'source>,
+    fn_names: Vec<String>,
+    /// the C spelling of every type spelled so far, by the index of its id.
+    ty_names: Vec<Option<String>>,
+    /// the aggregates whose definition is emitted or underway.
+    defined: Vec<bool>,
+    /// aggregates that were spelled and still have to be defined.
+    pending: Vec<TyId>,
+    /// the `mb_eq` function of every aggregate compared so far, by the index of its id.
+    eq_names: Vec<Option<String>>,
+    /// a declaration of every aggregate, so that pointers to them work in any order.
+    forward: String,
+    /// function pointer typedefs and aggregates, each after the types it holds by value.
+    definitions: String,
+    /// the `mb_eq` functions, each after the ones it calls.
+    equalities: String,
+    errors: Vec<CError>,
+}
+
+impl<'a, 'source> Emitter<'a, 'source> {
+    fn error(&mut self, kind: CErrorKind, span: Span) {
+        let error = CError { kind, span };
+        if !self.errors.contains(&error) {
+            self.errors.push(error);
+        }
+    }
+
+    fn layout(&self, name: &str) -> Option<&'a StructLayout<'source>> {
+        self.checked.structs.iter().rev().find(|layout| layout.name.name == name)
+    }
+
+    /// `()` and `!`, which aren't stored.
+    fn is_void(&self, ty: TyId) -> bool {
+        match self.checked.tys.kind(ty) {
+            TyKind::Tuple(elems) => elems.is_empty(),
+            TyKind::Never => true,
+            _ => false,
+        }
+    }
+
+    /// `ty` as a C type, `void` for the types that aren't stored.
+    fn ty(&mut self, ty: TyId) -> Result<String, CErrorKind> {
+        if let Some(name) = &self.ty_names[ty.index()] {
+            return Ok(name.clone());
+        }
+        let unrepresentable = Err(CErrorKind::Unrepresentable(ty));
+        let name = match self.checked.tys.kind(ty).clone() {
+            TyKind::Int(int) => int_name(int).to_string(),
+            TyKind::Float(FloatTy::F32) => "float".to_string(),
+            TyKind::Float(FloatTy::F64) => "double".to_string(),
+            TyKind::Bool => "bool".to_string(),
+            TyKind::Unsuffixed(token) => match token {
+                Token::LitInteger => int_name(IntTy::I32).to_string(),
+                Token::LitFloat => "double".to_string(),
+                Token::LitChar => int_name(IntTy::U8).to_string(),
+                Token::LitBool => "bool".to_string(),
+                Token::LitStr => "uint8_t *".to_string(),
+                _ => return unrepresentable,
+            },
+            _ if self.is_void(ty) => "void".to_string(),
+            TyKind::Pointer { pointee, .. } => {
+                let pointee = match *self.checked.tys.kind(pointee) {
+                    TyKind::Array { len: None, elem } => self.ty(elem)?,
+                    _ => self.ty(pointee)?,
+                };
+                if pointee.ends_with('*') { format!("{}*", pointee) } else { format!("{} *", pointee) }
+            }
+            TyKind::Array { len: Some(_), elem } => {
+                self.ty(elem)?;
+                self.aggregate(ty, format!("mb_array{}", ty.index()))
+            }
+            TyKind::Tuple(elems) => {
+                for elem in elems {
+                    self.ty(elem)?;
+                }
+                self.aggregate(ty, format!("mb_tuple{}", ty.index()))
+            }
+            TyKind::Fn { params, ret } => {
+                let ret = self.ty(ret)?;
+                let mut params = params.iter().map(|&param| self.value_ty(param)).collect::<Result<Vec<_>, _>>()?;
+                if params.is_empty() {
+                    params.push("void".to_string());
+                }
+                let name = format!("mb_fn{}", ty.index());
+                let _ = writeln!(self.definitions, "typedef {} (*{})({});", ret, name, params.join(", "));
+                name
+            }
+            TyKind::Adt(name) => {
+                if let Some(layout) = self.checked.enums.iter().rev().find(|layout| layout.name.name == name) {
+                    int_name(layout.repr).to_string()
+                } else if let Some(layout) = self.layout(name) {
+                    let keyword = if layout.is_union { "union" } else { "struct" };
+                    let _ = writeln!(self.forward, "{} {};", keyword, c_ident(name));
+                    self.pending.push(ty);
+                    format!("{} {}", keyword, c_ident(name))
+                } else {
+                    return unrepresentable;
+                }
+            }
+            TyKind::Array { len: None, .. }
+            | TyKind::Literal
+            | TyKind::Type
+            | TyKind::Never
+            | TyKind::Error
+            | TyKind::Infer { .. } => return unrepresentable,
+        };
+        self.ty_names[ty.index()] = Some(name.clone());
+        Ok(name)
+    }
+
+    /// like `ty`, but for a value that has to be stored.
+    fn value_ty(&mut self, ty: TyId) -> Result<String, CErrorKind> {
+        if self.is_void(ty) {
+            return Err(CErrorKind::Unrepresentable(ty));
+        }
+        self.ty(ty)
+    }
+
+    /// the wrapper struct `name` of an array or tuple.
+    fn aggregate(&mut self, ty: TyId, name: String) -> String {
+        let _ = writeln!(self.forward, "typedef struct {} {};", name, name);
+        self.pending.push(ty);
+        name
+    }
+
+    /// emits the definition of an aggregate after the ones it holds by value.
+    fn define(&mut self, ty: TyId) {
+        if core::mem::replace(&mut self.defined[ty.index()], true) {
+            return;
+        }
+        let Some(head) = self.ty_names[ty.index()].clone() else { return };
+        let members: Vec<(String, TyId, Span)> = match self.checked.tys.kind(ty) {
+            TyKind::Array { len: Some(0), .. } => Vec::new(),
+            TyKind::Array { len: Some(len), elem } => alloc::vec![(format!("e[{}]", len), *elem, Span::default())],
+            TyKind::Tuple(elems) => {
+                elems.iter().enumerate().map(|(index, &elem)| (format!("_{}", index), elem, Span::default())).collect()
+            }
+            TyKind::Adt(name) => match self.layout(name) {
+                Some(layout) => {
+                    layout.fields.iter().map(|(field, ty)| (c_ident(field.name), *ty, field.span)).collect()
+                }
+                None => Vec::new(),
+            },
+            _ => return,
+        };
+        let head = if head.starts_with("mb_") { format!("struct {}", head) } else { head };
+        let mut definition = format!("{} {{\n", head);
+        for (name, member, span) in &members {
+            match self.value_ty(*member) {
+                Ok(member_ty) => {
+                    if self.pending.contains(member) {
+                        self.define(*member);
+                    }
+                    let _ = writeln!(definition, "    {};", declare(&member_ty, name));
+                }
+                Err(kind) => self.error(kind, *span),
+            }
+        }
+        if members.is_empty() {
+            // C has no empty structs
+            definition.push_str("    char mb_empty;\n");
+        }
+        definition.push_str("};\n");
+        self.definitions.push_str(&definition);
+    }
+
+    /// structs, unions, tuples and arrays of a known length, which C can't compare with `==`.
+    fn is_aggregate(&self, ty: TyId) -> bool {
+        match self.checked.tys.kind(ty) {
+            TyKind::Array { len: Some(_), .. } | TyKind::Tuple(_) => !self.is_void(ty),
+            TyKind::Adt(name) => self.layout(name).is_some(),
+            _ => false,
+        }
+    }
+
+    /// the function comparing two values of the aggregate `ty` member by member.
+    fn equality(&mut self, ty: TyId) -> Result<String, CErrorKind> {
+        if let Some(name) = &self.eq_names[ty.index()] {
+            return Ok(name.clone());
+        }
+        let c_ty = self.value_ty(ty)?;
+        let members: Vec<(String, TyId)> = match self.checked.tys.kind(ty) {
+            TyKind::Array { len: Some(len), elem } => {
+                let (len, elem) = (*len, *elem);
+                let elem = self.member_eq(elem, "a.e[i]", "b.e[i]")?;
+                let name = format!("mb_eq{}", ty.index());
+                let _ = write!(
+                    self.equalities,
+                    "static bool {name}({ty} a, {ty} b) {{\n    for (size_t i = 0; i < {len}; i++) {{\n        \
+                     if (!({elem})) return false;\n    }}\n    return true;\n}}\n",
+                    name = name,
+                    ty = c_ty,
+                    len = len,
+                    elem = elem
+                );
+                self.eq_names[ty.index()] = Some(name.clone());
+                return Ok(name);
+            }
+            TyKind::Tuple(elems) => {
+                elems.iter().enumerate().map(|(index, &elem)| (format!("_{}", index), elem)).collect()
+            }
+            TyKind::Adt(name) => match self.layout(name) {
+                Some(layout) if !layout.is_union => {
+                    layout.fields.iter().map(|(field, ty)| (c_ident(field.name), *ty)).collect()
+                }
+                _ => return Err(CErrorKind::UnionComparison(ty)),
+            },
+            _ => return Err(CErrorKind::Unrepresentable(ty)),
+        };
+        let mut compared = Vec::with_capacity(members.len());
+        for (member, member_ty) in members {
+            let (a, b) = (format!("a.{}", member), format!("b.{}", member));
+            compared.push(self.member_eq(member_ty, &a, &b)?);
+        }
+        if compared.is_empty() {
+            compared.push("true".to_string());
+        }
+        let name = format!("mb_eq{}", ty.index());
+        let _ = write!(
+            self.equalities,
+            "static bool {}({ty} a, {ty} b) {{\n    return {};\n}}\n",
+            name,
+            compared.join(" && "),
+            ty = c_ty
+        );
+        self.eq_names[ty.index()] = Some(name.clone());
+        Ok(name)
+    }
+
+    /// whether the members `a` and `b` of type `ty` are equal, as a C expression.
+    fn member_eq(&mut self, ty: TyId, a: &str, b: &str) -> Result<String, CErrorKind> {
+        Ok(if self.is_aggregate(ty) {
+            format!("{}({}, {})", self.equality(ty)?, a, b)
+        } else {
+            format!("{} == {}", a, b)
+        })
+    }
+
+    fn signature(&mut self, id: BodyId, body: &Body<'_>) -> String {
+        let ret = self.ty(body.ret()).unwrap_or_else(|kind| {
+            self.error(kind, body.span);
+            "void".to_string()
+        });
+        let mut params = Vec::with_capacity(body.param_count);
+        for param in body.params() {
+            let local = body.local(param);
+            match self.value_ty(local.ty) {
+                Ok(ty) => params.push(declare(&ty, &param.to_string())),
+                Err(kind) => self.error(kind, local.span),
+            }
+        }
+        if params.is_empty() {
+            params.push("void".to_string());
+        }
+        let storage = if body.is_extern { "" } else { "static " };
+        format!("{}{}", storage, declare(&ret, &format!("{}({})", self.fn_names[id.index()], params.join(", "))))
+    }
+
+    fn body(&mut self, body: &Body<'_>, out: &mut String) {
+        let returns_value = !self.is_void(body.ret());
+        for (index, local) in body.locals.iter().enumerate() {
+            if (1..=body.param_count).contains(&index) || self.is_void(local.ty) {
+                continue;
+            }
+            match self.ty(local.ty) {
+                Ok(ty) => {
+                    let _ = write!(out, "    {};", declare(&ty, &LocalId::new(index).to_string()));
+                    match local.name {
+                        Some(name) => {
+                            let _ = writeln!(out, " // {}", name);
+                        }
+                        None => out.push('\n'),
+                    }
+                }
+                Err(kind) => self.error(kind, local.span),
+            }
+        }
+
+        // unreachable blocks are left out, and a jump to the block emitted next falls through
+        let cfg = Cfg::new(body);
+        let order: Vec<BlockId> = (0..cfg.len()).map(BlockId::new).filter(|&block| cfg.is_reachable(block)).collect();
+        let mut labeled = alloc::vec![false; cfg.len()];
+        for (position, &block) in order.iter().enumerate() {
+            for &target in cfg.successors(block) {
+                if order.get(position + 1) != Some(&target) {
+                    labeled[target.index()] = true;
+                }
+            }
+        }
+        for (position, &block) in order.iter().enumerate() {
+            if labeled[block.index()] {
+                let _ = writeln!(out, "{}:", block);
+            }
+            let block_data = body.block(block);
+            for statement in &block_data.statements {
+                match self.statement(body, &statement.kind) {
+                    Ok(Some(code)) => {
+                        let _ = writeln!(out, "    {};", code);
+                    }
+                    Ok(None) => {}
+                    Err(kind) => self.error(kind, statement.span),
+                }
+            }
+            let next = order.get(position + 1).copied();
+            let terminator = &block_data.terminator;
+            let code = match &terminator.kind {
+                TerminatorKind::Goto(target) if Some(*target) == next => continue,
+                TerminatorKind::Goto(target) => format!("goto {};", target),
+                TerminatorKind::Branch {
+                    cond,
+                    then_block,
+                    else_block,
+                } => {
+                    let cond = match self.operand(body, cond) {
+                        Ok(cond) => cond,
+                        Err(kind) => {
+                            self.error(kind, terminator.span);
+                            continue;
+                        }
+                    };
+                    match (Some(*then_block) == next, Some(*else_block) == next) {
+                        (true, true) => continue,
+                        (false, true) => format!("if ({}) goto {};", cond, then_block),
+                        (true, false) => format!("if (!{}) goto {};", cond, else_block),
+                        (false, false) => format!("if ({}) goto {}; else goto {};", cond, then_block, else_block),
+                    }
+                }
+                TerminatorKind::Return if returns_value => format!("return {};", LocalId::RETURN),
+                TerminatorKind::Return => "return;".to_string(),
+                TerminatorKind::Unreachable => "mb_unreachable();".to_string(),
+            };
+            let _ = writeln!(out, "    {}", code);
+        }
+    }
+
+    /// the statement without its `;`, `None` when there is nothing to do.
+    fn statement(&mut self, body: &Body<'_>, kind: &StatementKind<'_>) -> Result<Option<String>, CErrorKind> {
+        let (place, rvalue) = match kind {
+            StatementKind::Assign(place, rvalue) => (place, rvalue),
+            StatementKind::Eval(rvalue) => return self.rvalue(body, rvalue, TyId::UNIT).map(Some),
+        };
+        let (dest, ty) = self.place(body, place)?;
+        if self.is_void(ty) {
+            return match rvalue {
+                Rvalue::Call { .. } => self.rvalue(body, rvalue, ty).map(Some),
+                _ => Ok(None),
+            };
+        }
+        self.value_ty(ty)?;
+        let code = match rvalue {
+            Rvalue::Use(Operand::Const(Constant::Literal(literal))) if literal.token() == Token::LitUninit => {
+                return Ok(None);
+            }
+            // a string initializing a byte array, as much of it as fits
+            Rvalue::Use(Operand::Const(Constant::Literal(literal)))
+                if literal.token() == Token::LitStr
+                    && let TyKind::Array { len: Some(len), .. } = *self.checked.tys.kind(ty) =>
+            {
+                let bytes = literal.cooked();
+                let len = bytes.len().min(len as usize);
+                format!("mb_copy(&{}, {}, {})", dest, c_string(&bytes), len)
+            }
+            Rvalue::Cast(Operand::Copy(source), _) if let Some(union) = self.union_read(body, source)? => {
+                format!("mb_reinterpret({}, {})", dest, union)
+            }
+            _ => format!("{} = {}", dest, self.rvalue(body, rvalue, ty)?),
+        };
+        Ok(Some(code))
+    }
+
+    /// the union `place` is a member of, see `typeck` for how members are read.
+    fn union_read(&self, body: &Body<'_>, place: &Place<'_>) -> Result<Option<String>, CErrorKind> {
+        let Some((Projection::Field(_), projection)) = place.projection.split_last() else { return Ok(None) };
+        let union = Place {
+            local: place.local,
+            projection: projection.to_vec(),
+        };
+        let (union, ty) = self.place(body, &union)?;
+        Ok(match self.checked.tys.kind(ty) {
+            TyKind::Adt(name) if self.layout(name).is_some_and(|layout| layout.is_union) => Some(union),
+            _ => None,
+        })
+    }
+
+    /// `rvalue` as an expression, `ty` is the type of where it is stored.
+    fn rvalue(&mut self, body: &Body<'_>, rvalue: &Rvalue<'_>, ty: TyId) -> Result<String, CErrorKind> {
+        Ok(match rvalue {
+            Rvalue::Use(operand) => self.operand(body, operand)?,
+            Rvalue::Unary(op, operand) => format!("{}{}", op.token().source_repr(), self.operand(body, operand)?),
+            Rvalue::Binary(op, lhs, rhs) => {
+                // the operands have the same type except for shifts, only one of them is a literal
+                let (lhs_ty, rhs_ty) = (self.operand_ty(body, lhs)?, self.operand_ty(body, rhs)?);
+                let ty = if self.is_aggregate(lhs_ty) { lhs_ty } else { rhs_ty };
+                if matches!(op, BinaryOp::Eq | BinaryOp::Ne) && self.is_aggregate(ty) {
+                    let not = if *op == BinaryOp::Ne { "!" } else { "" };
+                    let (lhs, rhs) = (self.aggregate_operand(body, lhs, ty)?, self.aggregate_operand(body, rhs, ty)?);
+                    return Ok(format!("{}{}({}, {})", not, self.equality(ty)?, lhs, rhs));
+                }
+                let (lhs, rhs) = (self.operand(body, lhs)?, self.operand(body, rhs)?);
+                let is_float = |ty: TyId| ty.as_float().is_some() || ty == TyId::LIT_FLOAT;
+                match op {
+                    BinaryOp::Rem if is_float(lhs_ty) || is_float(rhs_ty) => {
+                        let f32 = lhs_ty == TyId::F32 || rhs_ty == TyId::F32;
+                        format!("{}({}, {})", if f32 { "fmodf" } else { "fmod" }, lhs, rhs)
+                    }
+                    _ => format!("{} {} {}", lhs, op.token().source_repr(), rhs),
+                }
+            }
+            Rvalue::Cast(operand, to) => self.cast(body, operand, *to)?,
+            Rvalue::Call { callee, args } => {
+                let callee = self.operand(body, callee)?;
+                let args = args.iter().map(|arg| self.operand(body, arg)).collect::<Result<Vec<_>, _>>()?;
+                format!("{}({})", callee, args.join(", "))
+            }
+            Rvalue::Struct { fields, .. } => {
+                let ty = self.value_ty(ty)?;
+                let mut inits = Vec::with_capacity(fields.len());
+                for (field, value) in fields {
+                    inits.push(format!(".{} = {}", c_ident(field), self.operand(body, value)?));
+                }
+                format!("({}){{ {} }}", ty, inits.join(", "))
+            }
+        })
+    }
+
+    /// a plain C cast, except from floats to integers, which saturate instead of being undefined
+    /// out of range. the operand is a constant or a place, evaluating it repeatedly is fine.
+    fn cast(&mut self, body: &Body<'_>, operand: &Operand<'_>, to: TyId) -> Result<String, CErrorKind> {
+        let value = self.operand(body, operand)?;
+        let target = self.value_ty(to)?;
+        let from = self.operand_ty(body, operand)?;
+        let is_float = from.as_float().is_some() || from == TyId::LIT_FLOAT;
+        Ok(match to.as_int() {
+            Some(int) if is_float => {
+                let (min, max) = int_limits(int);
+                format!(
+                    "({v} != {v} ? 0 : {v} <= {min} ? {min} : {v} >= {max} ? {max} : ({t}){v})",
+                    v = value,
+                    min = min,
+                    max = max,
+                    t = target
+                )
+            }
+            _ => format!("({}){}", target, value),
+        })
+    }
+
+    /// `operand` as a value of the aggregate `ty`, a string compared with a byte array becomes an
+    /// array of as many of its bytes as fit.
+    fn aggregate_operand(&mut self, body: &Body<'_>, operand: &Operand<'_>, ty: TyId) -> Result<String, CErrorKind> {
+        match (operand, self.checked.tys.kind(ty)) {
+            (Operand::Const(Constant::Literal(literal)), &TyKind::Array { len: Some(len), .. })
+                if literal.token() == Token::LitStr =>
+            {
+                let mut bytes = literal.cooked().into_owned();
+                bytes.resize(len as usize, 0);
+                let bytes: Vec<String> = bytes.iter().map(u8::to_string).collect();
+                Ok(format!("({}){{ {{ {} }} }}", self.value_ty(ty)?, bytes.join(", ")))
+            }
+            _ => self.operand(body, operand),
+        }
+    }
+
+    /// the type of `operand`, variants and functions are `{error}`.
+    fn operand_ty(&self, body: &Body<'_>, operand: &Operand<'_>) -> Result<TyId, CErrorKind> {
+        Ok(match operand {
+            Operand::Copy(place) => self.place(body, place)?.1,
+            Operand::Const(Constant::Literal(literal)) => TyId::unsuffixed(literal.token()).unwrap_or(TyId::ERROR),
+            Operand::Const(Constant::Bool(_)) => TyId::BOOL,
+            Operand::Const(Constant::Usize(_)) => TyId::USIZE,
+            Operand::Const(_) => TyId::ERROR,
+        })
+    }
+
+    fn operand(&mut self, body: &Body<'_>, operand: &Operand<'_>) -> Result<String, CErrorKind> {
+        match operand {
+            Operand::Copy(place) => {
+                let (place, ty) = self.place(body, place)?;
+                self.value_ty(ty)?;
+                Ok(place)
+            }
+            Operand::Const(Constant::Literal(literal)) => literal_value(literal),
+            Operand::Const(Constant::Bool(value)) => Ok(value.to_string()),
+            Operand::Const(Constant::Variant { discriminant, .. }) => Ok(discriminant.to_string()),
+            Operand::Const(Constant::Usize(value)) => Ok(format!("(uintptr_t){}", value)),
+            Operand::Const(Constant::Fn(id)) => Ok(self.fn_names[id.index()].clone()),
+        }
+    }
+
+    /// `place` as an lvalue, and its type.
+    fn place(&self, body: &Body<'_>, place: &Place<'_>) -> Result<(String, TyId), CErrorKind> {
+        let mut ty = body.local(place.local).ty;
+        let mut out = place.local.to_string();
+        let tys = &self.checked.tys;
+        for projection in &place.projection {
+            let unrepresentable = CErrorKind::Unrepresentable(ty);
+            (out, ty) = match (*projection, tys.kind(ty)) {
+                (Projection::Field(field), TyKind::Tuple(elems)) => {
+                    let elem = field.parse::<usize>().ok().and_then(|index| elems.get(index));
+                    (format!("{}._{}", out, field), *elem.ok_or(unrepresentable)?)
+                }
+                (Projection::Field(field), TyKind::Adt(name)) => {
+                    let field_ty = self.layout(name).and_then(|layout| layout.field(field));
+                    (format!("{}.{}", out, c_ident(field)), field_ty.ok_or(unrepresentable)?)
+                }
+                (Projection::Index(index), TyKind::Array { len: Some(_), elem }) => {
+                    (format!("{}.e[{}]", out, index), *elem)
+                }
+                // behind a pointer to an array of unknown length, which points to its first element
+                (Projection::Index(index), TyKind::Array { len: None, elem }) => (format!("{}[{}]", out, index), *elem),
+                (Projection::Index(index), TyKind::Pointer { pointee, .. }) => {
+                    (format!("{}[{}]", out, index), *pointee)
+                }
+                (Projection::Deref, TyKind::Pointer { pointee, .. }) => match tys.kind(*pointee) {
+                    TyKind::Array { len: None, .. } => (out, *pointee),
+                    _ => (format!("(*{})", out), *pointee),
+                },
+                _ => return Err(unrepresentable),
+            };
+        }
+        match tys.kind(ty) {
+            TyKind::Array { len: None, .. } => Err(CErrorKind::Unrepresentable(ty)),
+            _ => Ok((out, ty)),
+        }
+    }
+}
+
+/// a literal as a C constant.
+fn literal_value(literal: &Literal<'_>) -> Result<String, CErrorKind> {
+    let raw = literal.raw();
+    let unrepresentable = CErrorKind::Unrepresentable(TyId::unsuffixed(literal.token()).unwrap_or(TyId::ERROR));
+    Ok(match literal.token() {
+        Token::LitInteger => {
+            let value = parse_u128(Spanned::new(raw, Span::default())).map_err(|_| unrepresentable)?;
+            match value {
+                // unsuffixed decimal constants are signed
+                _ if value <= i64::MAX as u128 => value.to_string(),
+                _ if value <= u64::MAX as u128 => format!("{}u", value),
+                _ => return Err(unrepresentable),
+            }
+        }
+        Token::LitFloat => raw.iter().filter(|&&byte| byte != b'_').map(|&byte| byte as char).collect(),
+        Token::LitChar => (decode_char_literal(raw).map_err(|_| unrepresentable)? as u32).to_string(),
+        Token::LitStr => format!("(uint8_t *){}", c_string(&literal.cooked())),
+        Token::LitBool => (raw == b"true").to_string(),
+        Token::LitUninit => return Err(CErrorKind::UninitOperand),
+        _ => return Err(unrepresentable),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{CSource, emit};
+    #[cfg(feature = "std")]
+    use super::{RUNTIME_HEADER, RUNTIME_HEADER_NAME};
+    #[cfg(feature = "std")]
+    use crate::emit_sink::{DirectorySink, MemorySink};
+    use crate::ir::lower;
+    use crate::parser::tests::parse_ok;
+    use crate::typeck::check;
+    use crate::types::Span;
+
+    fn c(text: &str) -> CSource {
+        let stmts = parse_ok(text);
+        let checked = check(stmts);
+        assert!(checked.errors.is_empty(), "{:?}", checked.errors);
+        emit(&lower(stmts, &checked), &checked)
+    }
+
+    #[test]
+    fn functions_and_control_flow() {
+        let text = "extern fn puts(let s: *u8) -> i32;\n\
+                    fn max(let a: i32, let b: i32) -> i32 { if a > b { a } else { b } }\n\
+                    fn count(let n: mut u8) -> u8 {\n\
+                        let steps: mut u8 = 0; while n > 0 { n -= 1; steps += 1; } steps\n\
+                    }\n\
+                    let m = max(1, 2);\nputs(\"hi\\n\");";
+        let source = c(text);
+        assert_eq!(source.errors, []);
+        assert_eq!(source.code, r#"#include "mumbo_rt.h"
+
+static void mb_top_level(void);
+int32_t puts(uint8_t *_1);
+static int32_t mb_f_max(int32_t _1, int32_t _2);
+static uint8_t mb_f_count(uint8_t _1);
+
+static void mb_top_level(void) {
+    int32_t _1; // m
+    _1 = mb_f_max(1, 2);
+    puts((uint8_t *)"hi\012");
+    return;
+}
+
+static int32_t mb_f_max(int32_t _1, int32_t _2) {
+    int32_t _0;
+    bool _3;
+    _3 = _1 > _2;
+    if (!_3) goto bb2;
+    _0 = _1;
+    goto bb3;
+bb2:
+    _0 = _2;
+bb3:
+    return _0;
+}
+
+static uint8_t mb_f_count(uint8_t _1) {
+    uint8_t _0;
+    uint8_t _2; // steps
+    bool _3;
+    _2 = 0;
+bb1:
+    _3 = _1 > 0;
+    if (!_3) goto bb3;
+    _1 = _1 - 1;
+    _2 = _2 + 1;
+    goto bb1;
+bb3:
+    _0 = _2;
+    return _0;
+}
+
+int main(void) {
+    mb_top_level();
+    return 0;
+}
+"#);
+    }
+
+    #[test]
+    fn aggregates_come_before_their_uses() {
+        let text = "struct Line { from: Point, to: Point, next: *Line }\nstruct Point { x: i32, y: i32 }\n\
+                    union Bits { word: u32, real: f32 }\n\
+                    fn int(let x: f64) -> u8 { fn int() {} x cast u8 }\n\
+                    let p = Point { x: 1, y: 2 };\n\
+                    let l: mut Line = Line { from: p, to: Point { x: 3, y: 4 }, next: 0 cast *Line };\n\
+                    l.to.y = 5;\nlet b = Bits { real: 1.0 };\nlet w: u32 = b.word cast u32;\n\
+                    let bytes: mut [4 u8] = \"abcd\";\nlet pair: (i32, bool) = uninit;\nbytes[1] = pair.0 cast u8;";
+        let source = c(text);
+        assert_eq!(source.errors, []);
+        let code = &source.code;
+        let position = |text: &str| code.find(text).unwrap_or_else(|| panic!("no `{}` in\n{}", text, code));
+        assert!(position("struct Line;") < position("struct Point {"));
+        assert!(position("struct Point {") < position("struct Line {"));
+        assert!(code.contains("    struct Line *next;\n"));
+        assert!(code.contains("static uint8_t mb_f_int(double _1);\nstatic void mb_f2_int(void);\n"));
+        assert!(code.contains("mb_reinterpret(_6, _5);"));
+        assert!(code.contains("mb_copy(&_7, \"abcd\", 4);"));
+        assert!(code.contains("_7.e[_9] = (uint8_t)_8._0;"));
+        assert!(code.contains("(_1 != _1 ? 0 : _1 <= 0 ? 0 : _1 >= UINT8_MAX ? UINT8_MAX : (uint8_t)_1)"));
+    }
+
+    #[test]
+    fn values_without_a_c_type_are_errors() {
+        let text = "fn f(let a: i32) {}\nfn g(let p: *[i u8]) { let a = *p; }\nf(uninit);";
+        let source = c(text);
+        let codes: Vec<_> = source.errors.iter().map(|error| error.kind.code()).collect();
+        // the local of `a` and the assignment to it
+        assert_eq!(codes, ["E0702", "E0701", "E0701"]);
+    }
+
+    #[test]
+    fn reserved_names_are_avoided() {
+        let text = "struct int { mb_copy: u8, int: u8, mb_r_int: u8 }\nlet s: int = uninit;\n\
+                    fn int32_t() {}\nfn mb_copy() {}\nfn f_2() {}\nfn f() {}\nfn g() { fn f() {} }\n\
+                    extern fn abort();\nextern fn size_t();";
+        let source = c(text);
+        assert!(source.code.contains("struct mb_r_int {\n    uint8_t mb_r_mb_copy;\n    uint8_t mb_r_int;\n"));
+        assert!(source.code.contains("    uint8_t mb_r_mb_r_int;\n"));
+        let prototypes = "static void mb_f_int32_t(void);\nstatic void mb_f_mb_copy(void);\n\
+                          static void mb_f_f_2(void);\nstatic void mb_f_f(void);\nstatic void mb_f_g(void);\n";
+        assert!(source.code.contains(prototypes));
+        // the nested `f` comes after the extern declarations
+        assert!(source.code.contains("static void mb_f8_f(void);\n"));
+        let errors: Vec<_> = source.errors.iter().map(|error| (error.code(), error.span)).collect();
+        assert_eq!(errors, [("E0703", Span::new(156, 161)), ("E0703", Span::new(175, 181))]);
+    }
+
+    #[test]
+    fn aggregates_compare_member_by_member() {
+        let text = "struct P { x: i32, y: (u8, f64), z: [2 u16] }\nunion U { a: u32, b: f32 }\n\
+                    let p: P = uninit;\nlet same = p == p;\nlet bytes = \"ab\";\nlet other = \"ac\" != bytes;\n\
+                    let f: f32 = 5.5;\nlet rem = f % 2.0;\nlet u: U = uninit;\nlet bits = u == u;";
+        let source = c(text);
+        let code = &source.code;
+        let position = |text: &str| code.find(text).unwrap_or_else(|| panic!("no `{}` in\n{}", text, code));
+        assert!(position("static bool mb_eq") < position("static bool mb_eq26(struct P a, struct P b) {"));
+        assert!(code.contains("    return a.x == b.x && mb_eq24(a.y, b.y) && mb_eq25(a.z, b.z);\n"));
+        assert!(code.contains("        if (!(a.e[i] == b.e[i])) return false;\n"));
+        assert!(code.contains("_2 = mb_eq26(_1, _1);"));
+        assert!(code.contains("_4 = !mb_eq27((mb_array27){ { 97, 99 } }, _3);"));
+        assert!(code.contains("_6 = fmodf(_5, 2.0);"));
+        let codes: Vec<_> = source.errors.iter().map(|error| error.kind.code()).collect();
+        assert_eq!(codes, ["E0704"]);
+    }
+
+    /// compiles and runs the emitted code, when there is a C compiler to do it.
+    #[cfg(feature = "std")]
+    #[test]
+    fn emitted_code_compiles_and_runs() {
+        use std::process::Command;
+
+        if Command::new("cc").arg("--version").output().is_err() {
+            return;
+        }
+        let text = "extern fn putchar(let c: i32) -> i32;\n\
+                    struct P { x: i32, y: (u8, f64), z: [2 u16] }\n\
+                    fn show(let b: bool) { putchar(if b { '1' } else { '0' } cast i32); }\n\
+                    let a: mut P = uninit;\na.x = 1; a.y.0 = 2; a.y.1 = 0.5; a.z[0] = 3; a.z[1] = 4;\n\
+                    let b: mut P = a;\nshow(a == b);\nb.z[1] = 5;\nshow(a == b);\nshow(a != b);\n\
+                    let bytes = \"abc\";\nshow(bytes == \"abc\");\nshow(\"abd\" == bytes);\n\
+                    let f: f32 = 5.5;\nshow(f % 2.0 == 1.5);\nshow(-7.5 % 2.0 == -1.5);\n\
+                    fn int(let x: i32) -> i32 { x }\nfn main() {}\nshow(int(1) == 1);\n\
+                    enum E { a, b, c }\nlet all = E::ALL;\nshow(all[2] == E::c);\nshow(E::variant_count == 3);";
+        let source = c(text);
+        assert_eq!(source.errors, []);
+        let root = std::env::temp_dir().join(format!("mumbo_c_emitter_{}", std::process::id()));
+        source.write_to(&mut DirectorySink::new(&root), "prog").unwrap();
+        let status = Command::new("cc")
+            .args(["-std=c99", "-pedantic-errors", "-o"])
+            .arg(root.join("prog"))
+            .arg(root.join("prog.c"))
+            .arg("-lm")
+            .status()
+            .unwrap();
+        let output = Command::new(root.join("prog")).output();
+        std::fs::remove_dir_all(&root).unwrap();
+        assert!(status.success());
+        assert_eq!(output.unwrap().stdout, b"1011011111");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn writes_the_runtime_next_to_the_program() {
+        let source = c("let x: u64 = 18446744073709551615;");
+        assert!(source.code.contains("_1 = 18446744073709551615u;"));
+        let mut sink = MemorySink::new();
+        source.write_to(&mut sink, "prog").unwrap();
+        assert_eq!(sink.get(RUNTIME_HEADER_NAME), Some(RUNTIME_HEADER.as_bytes()));
+        assert_eq!(sink.get("prog.c"), Some(source.code.as_bytes()));
+    }
+}
@@ -0,0 +1,1073 @@
+//! compile-time evaluation: runs the `compiletime { ... }` blocks of a program and replaces
+//! each with the literal it evaluates to, `compiletime { 2 * 3 }` becomes `6`.
+//!
+//! a compiletime block can use integer, float and boolean arithmetic, casts between them,
+//! enum variants, the `const` bindings around it and control flow: `let`, assignments, `if`,
+//! `match` and loops with `break` and `continue`. calls, pointers and `uninit` need a running
+//! program and are rejected. integers are checked against their type, overflowing one is an
+//! error instead of wrapping, while casts truncate. a variant cast to an integer is its
+//! position in the enum, `E::variant_count` counts them and `E::ALL[i]` picks one. strings can
+//! be joined with `+`, compared and measured with `.len`, `compiletime { "ab" + "cd" }` becomes
+//! `"abcd"`.
+//!
+//! the pass runs after type checking, so it doesn't report type errors again, an expression
+//! of the wrong type is just not evaluable.
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::ast::arena::AstArena;
+use crate::ast::visit_mut::{MutVisitor, walk_block_mut, walk_expr_mut, walk_stmt_mut};
+use crate::ast::{
+    BinaryOp, Block, BlockKind, EnumIntrinsic, Expr, ExprKind, FnDecl, Ident, ItemKind, MatchArm, PatternKind,
+    Qualifier, Stmt, StmtKind, TypeExpr, TypeKind, UnaryOp,
+};
+use crate::literal_value::{Literal, decode_char_literal, parse_i128};
+use crate::ty::{FloatTy, IntTy, TyId};
+use crate::types::{Span, Spanned, Token};
+
+/// a value known while compiling.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstValue<'source> {
+    /// `ty` is `None` for an unsuffixed integer, which has no range until it is coerced.
+    Int { value: i128, ty: Option<IntTy> },
+    Float { value: f64, ty: Option<FloatTy> },
+    Bool(bool),
+    /// an enum variant, `index` is its position in the declaration.
+    Variant { name: &'source str, index: u32 },
+    /// the bytes of a string, escapes resolved.
+    Str(&'source [u8]),
+    /// `()`, the value of blocks without a tail.
+    Unit,
+}
+
+impl fmt::Display for ConstValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstValue::Int { value, ty: Some(ty) } => write!(f, "{} cast {}", value, ty.name()),
+            ConstValue::Int { value, ty: None } => write!(f, "{}", value),
+            ConstValue::Float { value, ty: Some(ty) } => write!(f, "{} cast {}", FloatText(*value), ty.name()),
+            ConstValue::Float { value, ty: None } => write!(f, "{}", FloatText(*value)),
+            ConstValue::Bool(value) => write!(f, "{}", value),
+            ConstValue::Variant { name, .. } => f.write_str(name),
+            ConstValue::Str(bytes) => write!(f, "\"{}\"", escape_string(bytes)),
+            ConstValue::Unit => f.write_str("()"),
+        }
+    }
+}
+
+/// `bytes` as the body of a string literal. they are utf-8, since the lexer has no byte
+/// escapes and every string value is joined from literals.
+fn escape_string(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len());
+    for c in String::from_utf8_lossy(bytes).chars() {
+        match c {
+            '\n' => text.push_str("\\n"),
+            '\t' => text.push_str("\\t"),
+            '\r' => text.push_str("\\r"),
+            '\0' => text.push_str("\\0"),
+            '"' | '\\' => {
+                text.push('\\');
+                text.push(c);
+            }
+            c => text.push(c),
+        }
+    }
+    text
+}
+
+/// a float the way a float literal is written, with at least one digit after the point.
+struct FloatText(f64);
+
+impl fmt::Display for FloatText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 % 1.0 == 0.0 { write!(f, "{:.1}", self.0) } else { write!(f, "{}", self.0) }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EvalErrorKind {
+    /// something only a running program can evaluate, like a call or `uninit`.
+    NotConst,
+    /// an integer result outside the range of its type.
+    Overflow,
+    DivisionByZero,
+    /// the block didn't finish within `EvalOptions::step_limit` steps.
+    TooManySteps { limit: u32 },
+    /// a float result that is infinite or not a number, which no literal can spell.
+    NotRepresentable,
+    /// `E::ALL[index]` past the last variant.
+    IndexOutOfBounds { index: i128, len: u32 },
+}
+
+impl EvalErrorKind {
+    /// stable identifier like `ParseErrorKind::code`, evaluation codes start at `E0401`.
+    #[inline]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            EvalErrorKind::NotConst => "E0401",
+            EvalErrorKind::Overflow => "E0402",
+            EvalErrorKind::DivisionByZero => "E0403",
+            EvalErrorKind::TooManySteps { .. } => "E0404",
+            EvalErrorKind::NotRepresentable => "E0405",
+            EvalErrorKind::IndexOutOfBounds { .. } => "E0406",
+        }
+    }
+}
+
+impl fmt::Display for EvalErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalErrorKind::NotConst => f.write_str("this can't be evaluated at compile time"),
+            EvalErrorKind::Overflow => f.write_str("arithmetic overflow in a compiletime block"),
+            EvalErrorKind::DivisionByZero => f.write_str("division by zero in a compiletime block"),
+            EvalErrorKind::TooManySteps { limit } => {
+                write!(f, "compiletime block didn't finish within {} steps", limit)
+            }
+            EvalErrorKind::NotRepresentable => f.write_str("compiletime block evaluates to an infinite float or NaN"),
+            EvalErrorKind::IndexOutOfBounds { index, len } => {
+                write!(f, "index {} is out of bounds for an array of length {}", index, len)
+            }
+        }
+    }
+}
+
+/// the span covers the expression that couldn't be evaluated.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EvalError {
+    pub kind: EvalErrorKind,
+    pub span: Span,
+}
+
+impl EvalError {
+    #[inline]
+    pub const fn new(kind: EvalErrorKind, span: Span) -> Self {
+        EvalError { kind, span }
+    }
+
+    #[inline]
+    pub const fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+}
+
+impl fmt::Display for EvalError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.kind.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EvalOptions {
+    step_limit: u32,
+}
+
+impl EvalOptions {
+    #[inline]
+    pub const fn new() -> Self {
+        EvalOptions { step_limit: 1 << 20 }
+    }
+
+    /// how many expressions one compiletime block may evaluate, which stops endless loops.
+    #[inline]
+    pub const fn step_limit(mut self, limit: u32) -> Self {
+        self.step_limit = limit;
+        self
+    }
+
+    #[inline]
+    pub const fn get_step_limit(&self) -> u32 {
+        self.step_limit
+    }
+}
+
+impl Default for EvalOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// the outcome of `eval`. `values` has the value of every block that was replaced, by the
+/// span of the block, in source order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Evaluated<'source> {
+    pub errors: Vec<EvalError>,
+    pub values: Vec<(Span, ConstValue<'source>)>,
+}
+
+/// evaluates the compiletime blocks of `stmts` and replaces them with their values. the text
+/// of the new literals lives in `arena`. blocks that fail keep their place and an error.
+pub fn eval<'source>(
+    stmts: &mut [Stmt<'source>],
+    arena: &'source AstArena<'source>,
+    options: EvalOptions,
+) -> Evaluated<'source> {
+    let mut evaluator = Evaluator {
+        arena,
+        options,
+        scopes: Vec::new(),
+        steps: 0,
+        out: Evaluated::default(),
+    };
+    evaluator.push_scope(false, stmts);
+    for stmt in stmts {
+        evaluator.visit_stmt(stmt);
+    }
+    evaluator.out
+}
+
+#[derive(Debug, Default)]
+struct Scope<'source> {
+    /// `None` for bindings whose value isn't known at compile time.
+    bindings: Vec<(&'source str, Option<ConstValue<'source>>)>,
+    /// the variants of each enum declared in the scope, for its `EnumIntrinsic`s.
+    enums: Vec<(&'source str, Vec<&'source str>)>,
+    /// the parameters of a function, the scopes outside it only lend their variants.
+    is_fn: bool,
+}
+
+/// how evaluating an expression ended early.
+enum Flow<'source> {
+    Break {
+        label: Option<&'source str>,
+        value: ConstValue<'source>,
+        span: Span,
+    },
+    Continue {
+        label: Option<&'source str>,
+        span: Span,
+    },
+    Error(EvalError),
+}
+
+impl From<EvalError> for Flow<'_> {
+    #[inline]
+    fn from(error: EvalError) -> Self {
+        Flow::Error(error)
+    }
+}
+
+type Eval<'source> = Result<ConstValue<'source>, Flow<'source>>;
+
+#[inline]
+fn not_const<'source, T>(span: Span) -> Result<T, Flow<'source>> {
+    Err(Flow::Error(EvalError::new(EvalErrorKind::NotConst, span)))
+}
+
+struct Evaluator<'source> {
+    arena: &'source AstArena<'source>,
+    options: EvalOptions,
+    scopes: Vec<Scope<'source>>,
+    /// expressions evaluated for the current compiletime block.
+    steps: u32,
+    out: Evaluated<'source>,
+}
+
+impl<'source> Evaluator<'source> {
+    /// a scope for `stmts`, with the variants of the enums declared among them already bound.
+    fn push_scope(&mut self, is_fn: bool, stmts: &[Stmt<'source>]) {
+        let mut scope = Scope {
+            is_fn,
+            ..Scope::default()
+        };
+        for stmt in stmts {
+            if let StmtKind::Item(item) = &stmt.kind
+                && let ItemKind::Enum(decl) = &item.kind
+            {
+                let variants = decl.variants.iter().zip(0..).map(|(variant, index)| {
+                    let name = variant.name.name;
+                    (name, Some(ConstValue::Variant { name, index }))
+                });
+                scope.bindings.extend(variants);
+                let names = decl.variants.iter().map(|variant| variant.name.name).collect();
+                scope.enums.push((decl.name.name, names));
+            }
+        }
+        self.scopes.push(scope);
+    }
+
+    fn bind(&mut self, name: &'source str, value: Option<ConstValue<'source>>) {
+        self.scopes.last_mut().expect("bindings are inside a scope").bindings.push((name, value));
+    }
+
+    /// the binding `name` refers to, `None` when there is none.
+    fn lookup(&mut self, name: &str) -> Option<&mut Option<ConstValue<'source>>> {
+        let mut in_fn = false;
+        for scope in self.scopes.iter_mut().rev() {
+            let visible = |(bound, value): &&mut (&str, Option<ConstValue<'_>>)| {
+                *bound == name && (!in_fn || matches!(value, Some(ConstValue::Variant { .. })))
+            };
+            if let Some((_, value)) = scope.bindings.iter_mut().rev().find(visible) {
+                return Some(value);
+            }
+            in_fn |= scope.is_fn;
+        }
+        None
+    }
+
+    /// the variant names of the enum `name`, enums are visible inside functions too.
+    fn variants_of(&self, name: &str) -> Option<&[&'source str]> {
+        let mut enums = self.scopes.iter().rev().flat_map(|scope| scope.enums.iter().rev());
+        enums.find(|(enum_name, _)| *enum_name == name).map(|(_, variants)| variants.as_slice())
+    }
+
+    /// the variants `E::ALL` holds when `expr` is one, a variant named `ALL` hides it.
+    fn all_variants(&self, expr: &Expr<'source>) -> Option<&[&'source str]> {
+        match expr.kind {
+            ExprKind::Path { enum_name, variant } if EnumIntrinsic::named(variant.name) == Some(EnumIntrinsic::All) => {
+                self.variants_of(enum_name.name).filter(|variants| !variants.contains(&variant.name))
+            }
+            _ => None,
+        }
+    }
+
+    /// evaluates the compiletime block `expr` and replaces it with its value.
+    fn fold(&mut self, expr: &mut Expr<'source>) {
+        let ExprKind::Block(block) = &expr.kind else { unreachable!() };
+        self.steps = 0;
+        let value = match self.block(block) {
+            Ok(ConstValue::Float { value, .. }) if !value.is_finite() => {
+                Err(EvalError::new(EvalErrorKind::NotRepresentable, expr.span))
+            }
+            Ok(value) => Ok(value),
+            Err(Flow::Error(error)) => Err(error),
+            // a `break` or `continue` for a loop outside of the block
+            Err(Flow::Break { span, .. } | Flow::Continue { span, .. }) => {
+                Err(EvalError::new(EvalErrorKind::NotConst, span))
+            }
+        };
+        match value {
+            Ok(value) => {
+                expr.kind = self.literal(value, expr.span);
+                self.out.values.push((expr.span, value));
+            }
+            Err(error) => self.out.errors.push(error),
+        }
+    }
+
+    /// `value` as an expression, a literal cast to its type if it has one.
+    fn literal(&self, value: ConstValue<'source>, span: Span) -> ExprKind<'source> {
+        let (text, token, negative, ty) = match value {
+            ConstValue::Int { value, ty } => {
+                (value.unsigned_abs().to_string(), Token::LitInteger, value < 0, ty.map(IntTy::name))
+            }
+            ConstValue::Float { value, ty } => {
+                (FloatText(value.abs()).to_string(), Token::LitFloat, value < 0.0, ty.map(FloatTy::name))
+            }
+            ConstValue::Bool(value) => return ExprKind::Identifier(if value { "true" } else { "false" }),
+            ConstValue::Variant { name, .. } => return ExprKind::Identifier(name),
+            ConstValue::Str(bytes) => {
+                let raw = self.arena.alloc_str(escape_string(bytes)).as_bytes();
+                return ExprKind::Literal(Literal::new(Token::LitStr, raw));
+            }
+            ConstValue::Unit => {
+                let block = Block {
+                    kind: BlockKind::Plain,
+                    stmts: Vec::new(),
+                    tail: None,
+                    span,
+                };
+                return ExprKind::Block(self.arena.alloc_block(block));
+            }
+        };
+        let raw = self.arena.alloc_str(text).as_bytes();
+        let mut kind = ExprKind::Literal(Literal::new(token, raw));
+        if negative {
+            let expr = self.arena.alloc_expr(Expr::new(kind, span));
+            kind = ExprKind::Unary { op: UnaryOp::Neg, expr };
+        }
+        if let Some(name) = ty {
+            let ty = TypeExpr {
+                qualifiers: Vec::new(),
+                kind: TypeKind::Named(name),
+                span,
+            };
+            let expr = self.arena.alloc_expr(Expr::new(kind, span));
+            kind = ExprKind::Cast {
+                expr,
+                ty: self.arena.alloc_type(ty),
+            };
+        }
+        kind
+    }
+
+    /// counts one step, every expression and loop iteration is one.
+    fn step(&mut self, span: Span) -> Result<(), Flow<'source>> {
+        self.steps += 1;
+        if self.steps > self.options.step_limit {
+            let limit = self.options.step_limit;
+            return Err(EvalError::new(EvalErrorKind::TooManySteps { limit }, span).into());
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, block: &Block<'source>) -> Eval<'source> {
+        if block.kind == BlockKind::Runtime {
+            return not_const(block.span);
+        }
+        self.push_scope(false, &block.stmts);
+        let value = self.block_inner(block);
+        self.scopes.pop();
+        value
+    }
+
+    fn block_inner(&mut self, block: &Block<'source>) -> Eval<'source> {
+        for stmt in &block.stmts {
+            match &stmt.kind {
+                StmtKind::Let { name, ty, value } => {
+                    let value = self.expr(value)?;
+                    let value = match ty {
+                        Some(ty) => coerce(value, ty).ok_or(EvalError::new(EvalErrorKind::Overflow, stmt.span))?,
+                        None => value,
+                    };
+                    self.bind(name.name, Some(value));
+                }
+                StmtKind::Expr(expr) => {
+                    self.expr(expr)?;
+                }
+                // variants are bound with the scope, functions can't be called anyway
+                StmtKind::Item(_) => {}
+            }
+        }
+        match &block.tail {
+            Some(tail) => self.expr(tail),
+            None => Ok(ConstValue::Unit),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr<'source>) -> Eval<'source> {
+        self.step(expr.span)?;
+        let span = expr.span;
+        match &expr.kind {
+            ExprKind::Literal(literal) if literal.token() == Token::LitStr => {
+                let bytes = match literal.cooked() {
+                    Cow::Borrowed(bytes) => bytes,
+                    Cow::Owned(bytes) => self.arena.alloc_bytes(bytes),
+                };
+                Ok(ConstValue::Str(bytes))
+            }
+            ExprKind::Literal(literal) => literal_value(literal, span),
+            ExprKind::Identifier(name) => match self.lookup(name) {
+                Some(Some(value)) => Ok(*value),
+                Some(None) => not_const(span),
+                None if *name == "true" || *name == "false" => Ok(ConstValue::Bool(*name == "true")),
+                None => not_const(span),
+            },
+            ExprKind::Path { enum_name, variant } => match self.lookup(variant.name) {
+                Some(Some(value @ ConstValue::Variant { .. })) => Ok(*value),
+                _ => match (EnumIntrinsic::named(variant.name), self.variants_of(enum_name.name)) {
+                    (Some(EnumIntrinsic::VariantCount), Some(variants)) => Ok(ConstValue::Int {
+                        value: variants.len() as i128,
+                        ty: Some(IntTy::Usize),
+                    }),
+                    // `E::ALL` has no literal, only its elements and length are evaluable
+                    _ => not_const(span),
+                },
+            },
+            ExprKind::Unary { op, expr: operand } => {
+                let value = self.expr(operand)?;
+                unary(*op, value).ok_or_else(|| error_for(value, span))
+            }
+            ExprKind::Binary { op, lhs, rhs } => match (op, self.expr(lhs)?, self.expr(rhs)?) {
+                // the joined bytes need the arena, which `binary` doesn't have
+                (BinaryOp::Add, ConstValue::Str(lhs), ConstValue::Str(rhs)) => {
+                    Ok(ConstValue::Str(self.arena.alloc_bytes([lhs, rhs].concat())))
+                }
+                (op, lhs, rhs) => Ok(binary(*op, lhs, rhs, span)?),
+            },
+            ExprKind::Assign { op, target, value } => {
+                let value = self.expr(value)?;
+                let ExprKind::Identifier(name) = target.kind else { return not_const(target.span) };
+                let Some(Some(current)) = self.lookup(name).copied() else { return not_const(target.span) };
+                let value = match op {
+                    Some(op) => binary(*op, current, value, span)?,
+                    None => value,
+                };
+                let value = coerce_to(value, current).ok_or(EvalError::new(EvalErrorKind::Overflow, span))?;
+                *self.lookup(name).expect("looked up above") = Some(value);
+                Ok(ConstValue::Unit)
+            }
+            ExprKind::Cast { expr: operand, ty } => {
+                let value = self.expr(operand)?;
+                match named_type(ty).and_then(|target| cast(value, target)) {
+                    Some(value) => Ok(value),
+                    None => not_const(span),
+                }
+            }
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => match self.expr(cond)? {
+                ConstValue::Bool(true) => self.block(then_branch),
+                ConstValue::Bool(false) => match else_branch {
+                    Some(else_branch) => self.expr(else_branch),
+                    None => Ok(ConstValue::Unit),
+                },
+                _ => not_const(cond.span),
+            },
+            ExprKind::While { label, cond, body } => {
+                let label = label.map(|label| label.name);
+                loop {
+                    match self.expr(cond)? {
+                        ConstValue::Bool(true) => {}
+                        ConstValue::Bool(false) => return Ok(ConstValue::Unit),
+                        _ => return not_const(cond.span),
+                    }
+                    self.step(span)?;
+                    match self.block(body) {
+                        Ok(_) => {}
+                        Err(Flow::Break { label: target, .. }) if target.is_none() || target == label => {
+                            return Ok(ConstValue::Unit);
+                        }
+                        Err(Flow::Continue { label: target, .. }) if target.is_none() || target == label => {}
+                        Err(flow) => return Err(flow),
+                    }
+                }
+            }
+            ExprKind::Loop { label, body } => {
+                let label = label.map(|label| label.name);
+                loop {
+                    self.step(span)?;
+                    match self.block(body) {
+                        Ok(_) => {}
+                        Err(Flow::Break { label: target, value, .. }) if target.is_none() || target == label => {
+                            return Ok(value);
+                        }
+                        Err(Flow::Continue { label: target, .. }) if target.is_none() || target == label => {}
+                        Err(flow) => return Err(flow),
+                    }
+                }
+            }
+            ExprKind::Break { label, value } => {
+                let value = match value {
+                    Some(value) => self.expr(value)?,
+                    None => ConstValue::Unit,
+                };
+                let label = label.map(|label| label.name);
+                Err(Flow::Break { label, value, span })
+            }
+            ExprKind::Continue { label } => Err(Flow::Continue {
+                label: label.map(|label| label.name),
+                span,
+            }),
+            ExprKind::Match { scrutinee, arms } => {
+                let value = self.expr(scrutinee)?;
+                for arm in arms {
+                    self.scopes.push(Scope::default());
+                    let matches = self.pattern(&arm.pattern.kind, value, arm.pattern.span);
+                    let result = match matches {
+                        Ok(true) => Some(self.expr(&arm.body)),
+                        Ok(false) => None,
+                        Err(flow) => Some(Err(flow)),
+                    };
+                    self.scopes.pop();
+                    if let Some(result) = result {
+                        return result;
+                    }
+                }
+                not_const(span)
+            }
+            ExprKind::Index { expr: base, index } if let Some(variants) = self.all_variants(base) => {
+                let len = variants.len() as u32;
+                let position = match self.expr(index)? {
+                    ConstValue::Int { value, .. } => value,
+                    _ => return not_const(index.span),
+                };
+                let variants = self.all_variants(base).expect("checked above");
+                match usize::try_from(position).ok().and_then(|at| variants.get(at)) {
+                    Some(&name) => Ok(ConstValue::Variant {
+                        name,
+                        index: position as u32,
+                    }),
+                    None => {
+                        let kind = EvalErrorKind::IndexOutOfBounds { index: position, len };
+                        Err(EvalError::new(kind, span).into())
+                    }
+                }
+            }
+            ExprKind::Field { expr: base, field }
+                if field.name == "len"
+                    && let Some(variants) = self.all_variants(base) =>
+            {
+                Ok(ConstValue::Int {
+                    value: variants.len() as i128,
+                    ty: Some(IntTy::Usize),
+                })
+            }
+            ExprKind::Field { expr: base, field } if field.name == "len" => match self.expr(base)? {
+                ConstValue::Str(bytes) => Ok(ConstValue::Int {
+                    value: bytes.len() as i128,
+                    ty: Some(IntTy::Usize),
+                }),
+                _ => not_const(span),
+            },
+            ExprKind::Call { .. }
+            | ExprKind::Struct { .. }
+            | ExprKind::Field { .. }
+            | ExprKind::Index { .. }
+            | ExprKind::Fn(_)
+            | ExprKind::Return(_) => not_const(span),
+        }
+    }
+
+    /// whether `value` matches `pattern`, binding it when the pattern is a name.
+    fn pattern(
+        &mut self,
+        pattern: &PatternKind<'source>,
+        value: ConstValue<'source>,
+        span: Span,
+    ) -> Result<bool, Flow<'source>> {
+        match pattern {
+            PatternKind::Wildcard => Ok(true),
+            PatternKind::Literal(literal) => Ok(same(literal_value(literal, span)?, value)),
+            PatternKind::Identifier(name) => match self.lookup(name) {
+                Some(Some(variant @ ConstValue::Variant { .. })) => Ok(same(*variant, value)),
+                _ => {
+                    self.bind(name, Some(value));
+                    Ok(true)
+                }
+            },
+            PatternKind::Path { variant, .. } => match self.lookup(variant.name) {
+                Some(Some(variant @ ConstValue::Variant { .. })) => Ok(same(*variant, value)),
+                _ => not_const(span),
+            },
+        }
+    }
+}
+
+impl<'source> MutVisitor<'source> for Evaluator<'source> {
+    /// a `const` binding whose value is known can be used by later compiletime blocks.
+    fn visit_stmt(&mut self, stmt: &mut Stmt<'source>) {
+        walk_stmt_mut(self, stmt);
+        if let StmtKind::Let { name, ty, value } = &stmt.kind {
+            let is_const = ty.as_ref().is_some_and(|ty| ty.qualifiers.iter().any(|q| q.value == Qualifier::Const));
+            let known = match ty {
+                Some(ty) if is_const => {
+                    self.steps = 0;
+                    self.expr(value).ok().and_then(|value| coerce(value, ty))
+                }
+                _ => None,
+            };
+            self.bind(name.name, known);
+        }
+    }
+
+    fn visit_fn_decl(&mut self, decl: &mut FnDecl<'source>) {
+        let Some(body) = &mut decl.body else { return };
+        self.push_scope(true, &[]);
+        for param in &decl.params {
+            self.bind(param.name.name, None);
+        }
+        self.visit_block(body);
+        self.scopes.pop();
+    }
+
+    fn visit_block(&mut self, block: &mut Block<'source>) {
+        self.push_scope(false, &block.stmts);
+        walk_block_mut(self, block);
+        self.scopes.pop();
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr<'source>) {
+        match &expr.kind {
+            ExprKind::Block(block) if block.kind == BlockKind::Compiletime => self.fold(expr),
+            _ => walk_expr_mut(self, expr),
+        }
+    }
+
+    /// match arms bind names, which may shadow a `const` binding.
+    fn visit_match_arm(&mut self, arm: &mut MatchArm<'source>) {
+        self.scopes.push(Scope::default());
+        if let PatternKind::Identifier(name) = arm.pattern.kind
+            && !matches!(self.lookup(name), Some(Some(ConstValue::Variant { .. })))
+        {
+            self.bind(name, None);
+        }
+        self.visit_expr(&mut arm.body);
+        self.scopes.pop();
+    }
+
+    fn visit_label(&mut self, _label: &mut Ident<'source>) {}
+}
+
+/// an error for an operator applied to `value`, `Overflow` for an integer and `NotConst` for
+/// operands it doesn't apply to.
+fn error_for<'source>(value: ConstValue<'_>, span: Span) -> Flow<'source> {
+    let kind = match value {
+        ConstValue::Int { .. } => EvalErrorKind::Overflow,
+        _ => EvalErrorKind::NotConst,
+    };
+    Flow::Error(EvalError::new(kind, span))
+}
+
+fn literal_value<'source>(literal: &Literal<'_>, span: Span) -> Eval<'source> {
+    let overflow = || Flow::Error(EvalError::new(EvalErrorKind::Overflow, span));
+    match literal.token() {
+        Token::LitInteger => {
+            let value = parse_i128(Spanned::new(literal.raw(), span), false).map_err(|_| overflow())?;
+            Ok(ConstValue::Int { value, ty: None })
+        }
+        Token::LitFloat => {
+            let text: String = literal.raw().iter().filter(|byte| **byte != b'_').map(|byte| *byte as char).collect();
+            match text.parse() {
+                Ok(value) => Ok(ConstValue::Float { value, ty: None }),
+                Err(_) => not_const(span),
+            }
+        }
+        Token::LitChar => match decode_char_literal(literal.raw()) {
+            Ok(c) => Ok(ConstValue::Int {
+                value: c as i128,
+                ty: None,
+            }),
+            Err(_) => not_const(span),
+        },
+        _ => not_const(span),
+    }
+}
+
+/// the primitive type `ty` names, qualifiers aside.
+fn named_type(ty: &TypeExpr<'_>) -> Option<TyId> {
+    match ty.kind {
+        TypeKind::Named(name) => TyId::primitive(name),
+        _ => None,
+    }
+}
+
+/// `value` as the value of a binding annotated with `ty`, `None` if it doesn't fit. types
+/// that aren't primitives leave it as it is.
+fn coerce<'source>(value: ConstValue<'source>, ty: &TypeExpr<'_>) -> Option<ConstValue<'source>> {
+    let Some(target) = named_type(ty) else { return Some(value) };
+    match (value, target.as_int(), target.as_float()) {
+        (ConstValue::Int { value, ty: None }, Some(int), _) => fit(value, Some(int)).map(|value| ConstValue::Int {
+            value,
+            ty: Some(int),
+        }),
+        (ConstValue::Int { value, ty: None }, _, Some(float)) => Some(float_value(value as f64, Some(float))),
+        (ConstValue::Float { value, ty: None }, _, Some(float)) => Some(float_value(value, Some(float))),
+        _ => Some(value),
+    }
+}
+
+/// `value` as the new value of a binding that holds `current`.
+fn coerce_to<'source>(value: ConstValue<'source>, current: ConstValue<'source>) -> Option<ConstValue<'source>> {
+    match (value, current) {
+        (ConstValue::Int { value, ty: None }, ConstValue::Int { ty: Some(ty), .. }) => {
+            fit(value, Some(ty)).map(|value| ConstValue::Int { value, ty: Some(ty) })
+        }
+        (ConstValue::Int { value, ty: None }, ConstValue::Float { ty, .. }) => Some(float_value(value as f64, ty)),
+        (ConstValue::Float { value, ty: None }, ConstValue::Float { ty, .. }) => Some(float_value(value, ty)),
+        _ => Some(value),
+    }
+}
+
+/// `value` if it is in the range of `ty`, unsuffixed integers take any `i128`.
+fn fit(value: i128, ty: Option<IntTy>) -> Option<i128> {
+    match ty {
+        Some(ty) => ty.contains(value).then_some(value),
+        None => Some(value),
+    }
+}
+
+/// an `f32` is rounded to the precision it has.
+#[inline]
+fn float_value<'source>(value: f64, ty: Option<FloatTy>) -> ConstValue<'source> {
+    let value = if ty == Some(FloatTy::F32) { value as f32 as f64 } else { value };
+    ConstValue::Float { value, ty }
+}
+
+/// whether a `match` on `value` takes an arm for `pattern`.
+fn same(pattern: ConstValue<'_>, value: ConstValue<'_>) -> bool {
+    match (pattern, value) {
+        (ConstValue::Int { value: a, .. }, ConstValue::Int { value: b, .. }) => a == b,
+        (ConstValue::Int { value: a, .. }, ConstValue::Float { value: b, .. })
+        | (ConstValue::Float { value: b, .. }, ConstValue::Int { value: a, .. }) => a as f64 == b,
+        (ConstValue::Float { value: a, .. }, ConstValue::Float { value: b, .. }) => a == b,
+        (ConstValue::Bool(a), ConstValue::Bool(b)) => a == b,
+        (ConstValue::Variant { index: a, name: x }, ConstValue::Variant { index: b, name: y }) => a == b && x == y,
+        (ConstValue::Str(a), ConstValue::Str(b)) => a == b,
+        (ConstValue::Unit, ConstValue::Unit) => true,
+        _ => false,
+    }
+}
+
+fn unary<'source>(op: UnaryOp, value: ConstValue<'source>) -> Option<ConstValue<'source>> {
+    match (op, value) {
+        (UnaryOp::Neg, ConstValue::Int { value, ty }) => {
+            value.checked_neg().and_then(|value| fit(value, ty)).map(|value| ConstValue::Int { value, ty })
+        }
+        (UnaryOp::Neg, ConstValue::Float { value, ty }) => Some(ConstValue::Float { value: -value, ty }),
+        (UnaryOp::Not, ConstValue::Bool(value)) => Some(ConstValue::Bool(!value)),
+        (UnaryOp::BitNot, ConstValue::Int { value, ty }) => {
+            let value = match ty {
+                Some(ty) => ty.wrap(!value),
+                None => !value,
+            };
+            Some(ConstValue::Int { value, ty })
+        }
+        _ => None,
+    }
+}
+
+fn binary<'source>(
+    op: BinaryOp,
+    lhs: ConstValue<'source>,
+    rhs: ConstValue<'source>,
+    span: Span,
+) -> Result<ConstValue<'source>, EvalError> {
+    let error = |kind| EvalError::new(kind, span);
+    let compare = |ordering: Option<core::cmp::Ordering>| {
+        use core::cmp::Ordering;
+        let result = match (op, ordering) {
+            (_, None) => return Err(error(EvalErrorKind::NotConst)),
+            (BinaryOp::Eq, Some(ordering)) => ordering == Ordering::Equal,
+            (BinaryOp::Ne, Some(ordering)) => ordering != Ordering::Equal,
+            (BinaryOp::Lt, Some(ordering)) => ordering == Ordering::Less,
+            (BinaryOp::Le, Some(ordering)) => ordering != Ordering::Greater,
+            (BinaryOp::Gt, Some(ordering)) => ordering == Ordering::Greater,
+            (BinaryOp::Ge, Some(ordering)) => ordering != Ordering::Less,
+            _ => unreachable!("only comparisons compare"),
+        };
+        Ok(ConstValue::Bool(result))
+    };
+    let is_comparison = matches!(
+        op,
+        BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge
+    );
+    match (lhs, rhs) {
+        (ConstValue::Int { value: a, ty: lhs_ty }, ConstValue::Int { value: b, ty: rhs_ty }) => {
+            if is_comparison {
+                return compare(Some(a.cmp(&b)));
+            }
+            // the shift amount doesn't take part in the type of the result
+            let ty = if matches!(op, BinaryOp::Shl | BinaryOp::Shr) { lhs_ty } else { lhs_ty.or(rhs_ty) };
+            let bits = ty.map_or(128, IntTy::bits) as i128;
+            let value = match op {
+                BinaryOp::Add => a.checked_add(b),
+                BinaryOp::Sub => a.checked_sub(b),
+                BinaryOp::Mul => a.checked_mul(b),
+                BinaryOp::Div | BinaryOp::Rem if b == 0 => return Err(error(EvalErrorKind::DivisionByZero)),
+                BinaryOp::Div => a.checked_div(b),
+                BinaryOp::Rem => a.checked_rem(b),
+                BinaryOp::BitAnd => Some(a & b),
+                BinaryOp::BitOr => Some(a | b),
+                BinaryOp::BitXor => Some(a ^ b),
+                BinaryOp::Shl | BinaryOp::Shr if !(0..bits).contains(&b) => None,
+                BinaryOp::Shl => a.checked_mul(1i128.checked_shl(b as u32).unwrap_or(0)).filter(|_| b < 127),
+                BinaryOp::Shr => Some(a >> b),
+                _ => unreachable!("comparisons returned above"),
+            };
+            match value.and_then(|value| fit(value, ty)) {
+                Some(value) => Ok(ConstValue::Int { value, ty }),
+                None => Err(error(EvalErrorKind::Overflow)),
+            }
+        }
+        (ConstValue::Int { .. } | ConstValue::Float { .. }, ConstValue::Int { .. } | ConstValue::Float { .. }) => {
+            let (a, lhs_ty) = as_float(lhs);
+            let (b, rhs_ty) = as_float(rhs);
+            if is_comparison {
+                return compare(a.partial_cmp(&b));
+            }
+            let value = match op {
+                BinaryOp::Add => a + b,
+                BinaryOp::Sub => a - b,
+                BinaryOp::Mul => a * b,
+                BinaryOp::Div => a / b,
+                BinaryOp::Rem => a % b,
+                _ => return Err(error(EvalErrorKind::NotConst)),
+            };
+            Ok(float_value(value, lhs_ty.or(rhs_ty)))
+        }
+        (ConstValue::Bool(a), ConstValue::Bool(b)) => match op {
+            BinaryOp::BitAnd => Ok(ConstValue::Bool(a & b)),
+            BinaryOp::BitOr => Ok(ConstValue::Bool(a | b)),
+            BinaryOp::BitXor => Ok(ConstValue::Bool(a ^ b)),
+            _ if is_comparison => compare(Some(a.cmp(&b))),
+            _ => Err(error(EvalErrorKind::NotConst)),
+        },
+        (ConstValue::Variant { .. }, ConstValue::Variant { .. }) | (ConstValue::Str(_), ConstValue::Str(_))
+            if matches!(op, BinaryOp::Eq | BinaryOp::Ne) =>
+        {
+            Ok(ConstValue::Bool(same(lhs, rhs) == (op == BinaryOp::Eq)))
+        }
+        _ => Err(error(EvalErrorKind::NotConst)),
+    }
+}
+
+/// a numeric value as a float and its float type, integers have none.
+fn as_float(value: ConstValue<'_>) -> (f64, Option<FloatTy>) {
+    match value {
+        ConstValue::Int { value, .. } => (value as f64, None),
+        ConstValue::Float { value, ty } => (value, ty),
+        _ => unreachable!("only called for numbers"),
+    }
+}
+
+/// `value cast target`, `None` for casts that aren't evaluable.
+fn cast<'source>(value: ConstValue<'source>, target: TyId) -> Option<ConstValue<'source>> {
+    if target == TyId::LITERAL {
+        return Some(value);
+    }
+    if let Some(int) = target.as_int() {
+        let value = match value {
+            ConstValue::Int { value, .. } => int.wrap(value),
+            // saturating, like the float to integer conversion of C compilers that don't trap
+            ConstValue::Float { value, .. } if value.is_nan() => 0,
+            ConstValue::Float { value, .. } => (value as i128).clamp(int.min(), int.max() as i128),
+            ConstValue::Bool(value) => value as i128,
+            ConstValue::Variant { index, .. } => int.wrap(index as i128),
+            ConstValue::Str(_) | ConstValue::Unit => return None,
+        };
+        return Some(ConstValue::Int { value, ty: Some(int) });
+    }
+    if let Some(float) = target.as_float() {
+        return match value {
+            ConstValue::Int { value, .. } => Some(float_value(value as f64, Some(float))),
+            ConstValue::Float { value, .. } => Some(float_value(value, Some(float))),
+            _ => None,
+        };
+    }
+    match (value, target) {
+        (ConstValue::Bool(_), TyId::BOOL) => Some(value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::format;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+
+    use super::{ConstValue, EvalError, EvalErrorKind, EvalOptions, Evaluated, eval};
+    use crate::ast::arena::AstArena;
+    use crate::ast::pretty::to_source;
+    use crate::parser::tests::parse_ok;
+    use crate::ty::{FloatTy, IntTy};
+    use crate::types::Span;
+
+    fn evaluated(text: &str) -> (Evaluated<'_>, String) {
+        let stmts = parse_ok(text);
+        // folded nodes go into an arena of their own, it has to live as long as `stmts`
+        let arena = Box::leak(Box::new(AstArena::new()));
+        let evaluated = eval(stmts, arena, EvalOptions::new());
+        (evaluated, to_source(stmts))
+    }
+
+    fn value(text: &str) -> ConstValue<'_> {
+        let (evaluated, _) = evaluated(text);
+        assert_eq!(evaluated.errors, [], "{text}");
+        evaluated.values[0].1
+    }
+
+    #[test]
+    fn strings_are_joined_and_measured() {
+        assert_eq!(value("compiletime { \"ab\" + \"cd\" };"), ConstValue::Str(b"abcd"));
+        let len = ConstValue::Int {
+            value: 3,
+            ty: Some(IntTy::Usize),
+        };
+        assert_eq!(value("compiletime { (\"é\" + \"\\0\").len };"), len);
+        assert_eq!(value("compiletime { \"ab\" + \"c\" == \"a\" + \"bc\" };"), ConstValue::Bool(true));
+
+        let (_, source) = evaluated("let s = compiletime { let t = \"tab\\t\"; t + \"\\\"é\" };");
+        assert_eq!(source, "let s = \"tab\\t\\\"é\";\n");
+        let (evaluated, _) = evaluated("compiletime { \"a\" - \"b\" };");
+        assert_eq!(evaluated.errors, [EvalError::new(EvalErrorKind::NotConst, Span::new(14, 23))]);
+    }
+
+    #[test]
+    fn enum_intrinsics_are_folded() {
+        let count = ConstValue::Int {
+            value: 3,
+            ty: Some(IntTy::Usize),
+        };
+        let enums = "enum E { a, b, c }\n";
+        let text = format!("{enums}compiletime {{ E::variant_count }};");
+        assert_eq!(value(&text), count);
+        let text = format!("{enums}compiletime {{ E::ALL.len }};");
+        assert_eq!(value(&text), count);
+        let text = format!("{enums}fn f() -> E {{ compiletime {{ E::ALL[E::variant_count cast u8 - 1] }} }}");
+        assert_eq!(value(&text), ConstValue::Variant { name: "c", index: 2 });
+
+        let (evaluated, _) = evaluated("enum E { a }\ncompiletime { E::ALL[1] };");
+        let kind = EvalErrorKind::IndexOutOfBounds { index: 1, len: 1 };
+        assert_eq!(evaluated.errors, [EvalError::new(kind, Span::new(27, 36))]);
+        assert_eq!(evaluated.errors[0].to_string(), "index 1 is out of bounds for an array of length 1");
+    }
+
+    #[test]
+    fn arithmetic_and_casts_are_folded() {
+        assert_eq!(value("compiletime { 1 + 2 * 3 };"), ConstValue::Int { value: 7, ty: None });
+        assert_eq!(value("compiletime { 300 cast u8 };"), ConstValue::Int { value: 44, ty: Some(IntTy::U8) });
+        assert_eq!(value("compiletime { -1 cast u16 };"), ConstValue::Int { value: 65535, ty: Some(IntTy::U16) });
+        assert_eq!(value("compiletime { 255 cast i8 };"), ConstValue::Int { value: -1, ty: Some(IntTy::I8) });
+        assert_eq!(value("compiletime { 2.9 cast i32 };"), ConstValue::Int { value: 2, ty: Some(IntTy::I32) });
+        let quarter = ConstValue::Float {
+            value: 0.25,
+            ty: Some(FloatTy::F32),
+        };
+        assert_eq!(value("compiletime { 1 cast f32 / 4.0 };"), quarter);
+        assert_eq!(value("compiletime { 'a' + 1 < 100 };"), ConstValue::Bool(true));
+        assert_eq!(value("compiletime { 1 << 4 | 3 };"), ConstValue::Int { value: 19, ty: None });
+
+        let (_, source) = evaluated("let a: u8 = compiletime { 250 cast u8 / 5 };\nlet b = compiletime { 0.5 - 2.0 };");
+        assert_eq!(source, "let a: u8 = 50 cast u8;\nlet b = -1.5;\n");
+    }
+
+    #[test]
+    fn variants_bindings_and_control_flow() {
+        let text = "enum Thingamabob { __variant1, __variant2, }\n\
+                    let variant: const u8 = compiletime { __variant2 cast u8 };\n\
+                    let twice: const u8 = compiletime { variant * 2 };\n\
+                    let sum = compiletime {\n\
+                        let total: mut u32 = 0;\n\
+                        let i: mut u32 = 0;\n\
+                        while i < 10 { i += 1; if i == 5 { continue; }; total += i; };\n\
+                        total\n\
+                    };\n\
+                    let found = compiletime { let n: mut u8 = 1; loop { n = n * 3; if n > 20 { break n; }; } };\n\
+                    let picked = compiletime { match __variant2 { __variant1 => 10, __variant2 => 20, } };\n\
+                    let qualified = compiletime {\n\
+                        match Thingamabob::__variant1 { Thingamabob::__variant2 => 1, _ => 2, }\n\
+                    };";
+        let (evaluated, _) = evaluated(text);
+        assert_eq!(evaluated.errors, []);
+        let values: Vec<_> = evaluated.values.iter().map(|(_, value)| value.to_string()).collect();
+        assert_eq!(values, ["1 cast u8", "2 cast u8", "50 cast u32", "27 cast u8", "20", "2"]);
+    }
+
+    #[test]
+    fn unevaluable_blocks_are_reported() {
+        let text = "fn f() -> u8 { 1 }\nlet a: mut u8 = 1;\ncompiletime { f() };\ncompiletime { a + 1 };\n\
+                    compiletime { 200 cast u8 + 100 };\ncompiletime { 1 / 0 };\ncompiletime { loop {} };\n\
+                    compiletime { 1.0 / 0.0 };\ncompiletime { runtime { 1 } };";
+        let (evaluated, source) = evaluated(text);
+        let errors: Vec<_> = evaluated.errors.iter().map(|e| (e.kind, e.span)).collect();
+        let limit = EvalOptions::new().get_step_limit();
+        assert_eq!(
+            errors,
+            [
+                (EvalErrorKind::NotConst, Span::new(52, 55)),
+                (EvalErrorKind::NotConst, Span::new(73, 74)),
+                (EvalErrorKind::Overflow, Span::new(96, 113)),
+                (EvalErrorKind::DivisionByZero, Span::new(131, 136)),
+                (EvalErrorKind::TooManySteps { limit }, Span::new(154, 161)),
+                (EvalErrorKind::NotRepresentable, Span::new(165, 190)),
+                (EvalErrorKind::NotConst, Span::new(214, 219)),
+            ]
+        );
+        assert!(source.contains("compiletime {\n    f()\n}"), "{source}");
+        assert_eq!(EvalError::new(EvalErrorKind::Overflow, Span::new(0, 1)).code(), "E0402");
+    }
+}
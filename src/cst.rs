@@ -0,0 +1,685 @@
+//! lossless syntax tree: every byte of the source belongs to exactly one token, including
+//! whitespace, comments and bytes the lexer rejected, and tokens are grouped into nodes
+//! following the shape of the `ast`. concatenating the tokens gives back the source.
+//!
+//! trivia goes to the innermost node that contains it, so whitespace between two statements
+//! belongs to the block around them and whitespace inside `a + b` to the binary expression.
+//! tokens of statements that failed to parse are left directly in the enclosing node.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+use crate::ast::arena::AstArena;
+use crate::ast::{
+    Attribute, Block, Expr, ExprKind, FnDecl, Ident, ItemKind, MatchArm, Pattern, Stmt, StmtKind, TypeExpr, TypeKind,
+};
+use crate::lexer::tokenize;
+use crate::lexer::lexer_impls::skip_whitespace::is_whitespace;
+use crate::parser::{self, Parsed};
+use crate::types::{Span, Token};
+
+/// what a `SyntaxNode` is, mirroring the `ast` node it was built from.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    /// the whole source.
+    Root,
+    LetStmt,
+    ExprStmt,
+    FnDecl,
+    Param,
+    StructDecl,
+    UnionDecl,
+    EnumDecl,
+    ModDecl,
+    ImportDecl,
+    Field,
+    Variant,
+    /// `#[name(args)]`, the first children of the declaration it belongs to.
+    Attribute,
+    /// `@name` in front of a loop or after `break` and `continue`.
+    Label,
+    LiteralExpr,
+    NameExpr,
+    /// `Enum::Variant`
+    PathExpr,
+    UnaryExpr,
+    BinaryExpr,
+    AssignExpr,
+    CallExpr,
+    /// `Name { field: value, ... }`
+    StructExpr,
+    /// `field: value` in a `StructExpr`.
+    FieldInit,
+    FieldExpr,
+    IndexExpr,
+    CastExpr,
+    BlockExpr,
+    IfExpr,
+    WhileExpr,
+    LoopExpr,
+    BreakExpr,
+    ContinueExpr,
+    ReturnExpr,
+    MatchExpr,
+    MatchArm,
+    Type,
+    Pattern,
+}
+
+/// what a `SyntaxToken` is: a lexed token or one of the pieces the lexer skips.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Token(Token),
+    /// a run of spaces, tabs and newlines.
+    Whitespace,
+    /// `// ...` up to but not including the newline.
+    Comment,
+    /// bytes covered by a lexer error.
+    Error,
+}
+
+impl TokenKind {
+    #[inline]
+    pub const fn is_trivia(self) -> bool {
+        matches!(self, TokenKind::Whitespace | TokenKind::Comment)
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SyntaxToken {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+impl SyntaxToken {
+    #[inline]
+    pub const fn new(kind: TokenKind, span: Span) -> Self {
+        SyntaxToken { kind, span }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxElement {
+    #[inline]
+    pub const fn span(&self) -> Span {
+        match self {
+            SyntaxElement::Node(node) => node.span,
+            SyntaxElement::Token(token) => token.span,
+        }
+    }
+}
+
+/// a node and everything written inside of it. the span covers exactly the children, which
+/// are in source order without gaps between them.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SyntaxNode {
+    pub kind: NodeKind,
+    pub span: Span,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    /// the span is taken from the children, `offset` places a node without any.
+    fn new(kind: NodeKind, children: Vec<SyntaxElement>, offset: usize) -> Self {
+        let span = match (children.first(), children.last()) {
+            (Some(first), Some(last)) => first.span().merge(last.span()),
+            _ => Span::new(offset, offset),
+        };
+        SyntaxNode { kind, span, children }
+    }
+
+    /// the direct children that are nodes.
+    #[inline]
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// every token below this node in source order, trivia included.
+    #[inline]
+    pub fn tokens(&self) -> Tokens<'_> {
+        Tokens {
+            stack: alloc::vec![self.children.iter()],
+        }
+    }
+
+    /// the token containing the byte at `offset`.
+    pub fn token_at_offset(&self, offset: usize) -> Option<&SyntaxToken> {
+        self.tokens().find(|token| token.span.start <= offset && offset < token.span.end)
+    }
+}
+
+/// see `SyntaxNode::tokens`.
+#[derive(Debug, Clone)]
+pub struct Tokens<'a> {
+    stack: Vec<core::slice::Iter<'a, SyntaxElement>>,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = &'a SyntaxToken;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.last_mut()?.next() {
+                Some(SyntaxElement::Token(token)) => return Some(token),
+                Some(SyntaxElement::Node(node)) => self.stack.push(node.children.iter()),
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+/// a lossless tree over `source`. `Display` writes the source back out token by token.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cst<'source> {
+    source: &'source str,
+    root: SyntaxNode,
+}
+
+impl<'source> Cst<'source> {
+    /// lexes `source` again, this time keeping the trivia, and groups the tokens into nodes
+    /// following `stmts`, which must have been parsed from `source`.
+    pub fn new(source: &'source str, stmts: &[Stmt<'_>]) -> Self {
+        let mut builder = Builder {
+            tokens: lex_lossless(source),
+            next: 0,
+        };
+        let mut children = Vec::new();
+        for stmt in stmts {
+            builder.stmt(&mut children, stmt);
+        }
+        builder.tokens_until(source.len(), &mut children);
+        Cst {
+            source,
+            root: SyntaxNode::new(NodeKind::Root, children, 0),
+        }
+    }
+
+    #[inline]
+    pub const fn source(&self) -> &'source str {
+        self.source
+    }
+
+    #[inline]
+    pub const fn root(&self) -> &SyntaxNode {
+        &self.root
+    }
+
+    /// the source text of a token or node.
+    #[inline]
+    pub fn text(&self, span: Span) -> &'source str {
+        &self.source[span.start..span.end]
+    }
+
+    /// the tree with one element per line, indented by depth, like
+    /// `LetStmt@0..10` for nodes and `KwLet@0..3 "let"` for tokens.
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        self.write_node(&mut out, &self.root, 0).expect("formatting into a String can't fail");
+        out
+    }
+
+    fn write_node(&self, out: &mut String, node: &SyntaxNode, depth: usize) -> fmt::Result {
+        let Span { start, end } = node.span;
+        writeln!(out, "{:indent$}{:?}@{}..{}", "", node.kind, start, end, indent = depth * 2)?;
+        for child in &node.children {
+            match child {
+                SyntaxElement::Node(node) => self.write_node(out, node, depth + 1)?,
+                SyntaxElement::Token(token) => {
+                    let Span { start, end } = token.span;
+                    write!(out, "{:indent$}", "", indent = (depth + 1) * 2)?;
+                    match token.kind {
+                        TokenKind::Token(kind) => write!(out, "{:?}", kind)?,
+                        kind => write!(out, "{:?}", kind)?,
+                    }
+                    writeln!(out, "@{}..{} {:?}", start, end, self.text(token.span))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Cst<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in self.root.tokens() {
+            f.write_str(self.text(token.span))?;
+        }
+        Ok(())
+    }
+}
+
+/// parses `source` and builds the lossless tree next to the ast.
+pub fn parse<'source>(source: &'source str, arena: &'source AstArena<'source>) -> (Cst<'source>, Parsed<'source>) {
+    let parsed = parser::parse(source, arena);
+    (Cst::new(source, &parsed.stmts), parsed)
+}
+
+/// every byte of `source` as a token, trivia and lexer errors included.
+fn lex_lossless(source: &str) -> Vec<SyntaxToken> {
+    let tokenized = tokenize(source);
+    let mut errors = tokenized.errors.iter().map(|e| e.span).peekable();
+    let mut out = Vec::with_capacity(tokenized.len() * 2);
+    let mut cursor = 0;
+    for token in &tokenized.tokens {
+        while let Some(error) = errors.next_if(|error| error.start < token.span.start) {
+            push_error(source, &mut cursor, error, token.span.start, &mut out);
+        }
+        push_trivia(source, cursor, token.span.start, &mut out);
+        out.push(SyntaxToken::new(TokenKind::Token(token.value), token.span));
+        cursor = token.span.end;
+    }
+    for error in errors {
+        push_error(source, &mut cursor, error, source.len(), &mut out);
+    }
+    push_trivia(source, cursor, source.len(), &mut out);
+    out
+}
+
+/// the part of `error` after `cursor` and before `limit`, preceded by the trivia in front of it.
+fn push_error(source: &str, cursor: &mut usize, error: Span, limit: usize, out: &mut Vec<SyntaxToken>) {
+    let start = error.start.max(*cursor);
+    let mut end = error.end.min(limit);
+    // errors on a non-ascii character only cover its first byte
+    while !source.is_char_boundary(end) {
+        end += 1;
+    }
+    if start >= end {
+        return;
+    }
+    push_trivia(source, *cursor, start, out);
+    out.push(SyntaxToken::new(TokenKind::Error, Span::new(start, end)));
+    *cursor = end;
+}
+
+/// splits `start..end`, which holds no lexed tokens, into whitespace and comments. anything
+/// else in there was skipped by the lexer after an error and becomes an `Error` token.
+fn push_trivia(source: &str, start: usize, end: usize, out: &mut Vec<SyntaxToken>) {
+    let bytes = source.as_bytes();
+    let is_comment = |at: usize| bytes[at] == b'/' && at + 1 < end && bytes[at + 1] == b'/';
+    let mut at = start;
+    while at < end {
+        let piece_start = at;
+        let kind = if is_whitespace(bytes[at]) {
+            while at < end && is_whitespace(bytes[at]) {
+                at += 1;
+            }
+            TokenKind::Whitespace
+        } else if is_comment(at) {
+            while at < end && bytes[at] != b'\n' {
+                at += 1;
+            }
+            TokenKind::Comment
+        } else {
+            while at < end && !is_whitespace(bytes[at]) && !is_comment(at) {
+                at += 1;
+            }
+            // never split a utf-8 sequence
+            while !source.is_char_boundary(at) {
+                at += 1;
+            }
+            TokenKind::Error
+        };
+        out.push(SyntaxToken::new(kind, Span::new(piece_start, at)));
+    }
+}
+
+/// hands out the tokens of `lex_lossless` in order while walking the ast.
+struct Builder {
+    tokens: Vec<SyntaxToken>,
+    next: usize,
+}
+
+impl Builder {
+    /// moves the tokens starting before `offset` into `out`.
+    fn tokens_until(&mut self, offset: usize, out: &mut Vec<SyntaxElement>) {
+        while let Some(token) = self.tokens.get(self.next)
+            && token.span.start < offset
+        {
+            out.push(SyntaxElement::Token(*token));
+            self.next += 1;
+        }
+    }
+
+    /// pushes a node covering `span` to `out`, `build` adds the child nodes.
+    fn node(
+        &mut self,
+        out: &mut Vec<SyntaxElement>,
+        kind: NodeKind,
+        span: Span,
+        build: impl FnOnce(&mut Self, &mut Vec<SyntaxElement>),
+    ) {
+        self.tokens_until(span.start, out);
+        let mut children = Vec::new();
+        build(self, &mut children);
+        self.tokens_until(span.end, &mut children);
+        out.push(SyntaxElement::Node(SyntaxNode::new(kind, children, span.start)));
+    }
+
+    fn stmt(&mut self, out: &mut Vec<SyntaxElement>, stmt: &Stmt<'_>) {
+        match &stmt.kind {
+            StmtKind::Let { ty, value, .. } => self.node(out, NodeKind::LetStmt, stmt.span, |b, out| {
+                if let Some(ty) = ty {
+                    b.ty(out, ty);
+                }
+                b.expr(out, value);
+            }),
+            StmtKind::Expr(expr) => self.node(out, NodeKind::ExprStmt, stmt.span, |b, out| b.expr(out, expr)),
+            StmtKind::Item(item) => match &item.kind {
+                ItemKind::Fn(decl) => self.fn_decl(out, &item.attrs, decl, stmt.span),
+                ItemKind::Struct(adt) | ItemKind::Union(adt) => {
+                    let kind = match item.kind {
+                        ItemKind::Struct(_) => NodeKind::StructDecl,
+                        _ => NodeKind::UnionDecl,
+                    };
+                    self.node(out, kind, stmt.span, |b, out| {
+                        b.attributes(out, &item.attrs);
+                        for field in &adt.fields {
+                            b.node(out, NodeKind::Field, field.span, |b, out| b.ty(out, &field.ty));
+                        }
+                    });
+                }
+                ItemKind::Enum(decl) => self.node(out, NodeKind::EnumDecl, stmt.span, |b, out| {
+                    b.attributes(out, &item.attrs);
+                    for variant in &decl.variants {
+                        b.node(out, NodeKind::Variant, variant.span, |_, _| {});
+                    }
+                }),
+                ItemKind::Mod(_) | ItemKind::Import(_) => {
+                    let kind = match item.kind {
+                        ItemKind::Mod(_) => NodeKind::ModDecl,
+                        _ => NodeKind::ImportDecl,
+                    };
+                    self.node(out, kind, stmt.span, |b, out| b.attributes(out, &item.attrs));
+                }
+            },
+        }
+    }
+
+    fn attributes(&mut self, out: &mut Vec<SyntaxElement>, attrs: &[Attribute<'_>]) {
+        for attr in attrs {
+            self.node(out, NodeKind::Attribute, attr.span, |_, _| {});
+        }
+    }
+
+    fn fn_decl(&mut self, out: &mut Vec<SyntaxElement>, attrs: &[Attribute<'_>], decl: &FnDecl<'_>, span: Span) {
+        self.node(out, NodeKind::FnDecl, span, |b, out| {
+            b.attributes(out, attrs);
+            for param in &decl.params {
+                b.node(out, NodeKind::Param, param.span, |b, out| b.ty(out, &param.ty));
+            }
+            if let Some(ret) = &decl.ret {
+                b.ty(out, ret);
+            }
+            if let Some(body) = &decl.body {
+                b.block(out, body);
+            }
+        });
+    }
+
+    fn block(&mut self, out: &mut Vec<SyntaxElement>, block: &Block<'_>) {
+        self.node(out, NodeKind::BlockExpr, block.span, |b, out| {
+            for stmt in &block.stmts {
+                b.stmt(out, stmt);
+            }
+            if let Some(tail) = &block.tail {
+                b.expr(out, tail);
+            }
+        });
+    }
+
+    fn label(&mut self, out: &mut Vec<SyntaxElement>, label: Option<Ident<'_>>) {
+        if let Some(label) = label {
+            self.node(out, NodeKind::Label, label.span, |_, _| {});
+        }
+    }
+
+    fn expr(&mut self, out: &mut Vec<SyntaxElement>, expr: &Expr<'_>) {
+        let span = expr.span;
+        match &expr.kind {
+            ExprKind::Literal(_) => self.node(out, NodeKind::LiteralExpr, span, |_, _| {}),
+            ExprKind::Identifier(_) => self.node(out, NodeKind::NameExpr, span, |_, _| {}),
+            ExprKind::Path { .. } => self.node(out, NodeKind::PathExpr, span, |_, _| {}),
+            ExprKind::Unary { expr, .. } => self.node(out, NodeKind::UnaryExpr, span, |b, out| b.expr(out, expr)),
+            ExprKind::Binary { lhs, rhs, .. } => self.node(out, NodeKind::BinaryExpr, span, |b, out| {
+                b.expr(out, lhs);
+                b.expr(out, rhs);
+            }),
+            ExprKind::Assign { target, value, .. } => self.node(out, NodeKind::AssignExpr, span, |b, out| {
+                b.expr(out, target);
+                b.expr(out, value);
+            }),
+            ExprKind::Call { callee, args } => self.node(out, NodeKind::CallExpr, span, |b, out| {
+                b.expr(out, callee);
+                for arg in args {
+                    b.expr(out, arg);
+                }
+            }),
+            ExprKind::Struct { fields, .. } => self.node(out, NodeKind::StructExpr, span, |b, out| {
+                for field in fields {
+                    b.node(out, NodeKind::FieldInit, field.span, |b, out| b.expr(out, &field.value));
+                }
+            }),
+            ExprKind::Field { expr, .. } => self.node(out, NodeKind::FieldExpr, span, |b, out| b.expr(out, expr)),
+            ExprKind::Index { expr, index } => self.node(out, NodeKind::IndexExpr, span, |b, out| {
+                b.expr(out, expr);
+                b.expr(out, index);
+            }),
+            ExprKind::Cast { expr, ty } => self.node(out, NodeKind::CastExpr, span, |b, out| {
+                b.expr(out, expr);
+                b.ty(out, ty);
+            }),
+            ExprKind::Block(block) => self.block(out, block),
+            ExprKind::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => self.node(out, NodeKind::IfExpr, span, |b, out| {
+                b.expr(out, cond);
+                b.block(out, then_branch);
+                if let Some(else_branch) = else_branch {
+                    b.expr(out, else_branch);
+                }
+            }),
+            ExprKind::While { label, cond, body } => self.node(out, NodeKind::WhileExpr, span, |b, out| {
+                b.label(out, *label);
+                b.expr(out, cond);
+                b.block(out, body);
+            }),
+            ExprKind::Loop { label, body } => self.node(out, NodeKind::LoopExpr, span, |b, out| {
+                b.label(out, *label);
+                b.block(out, body);
+            }),
+            ExprKind::Break { label, value } => self.node(out, NodeKind::BreakExpr, span, |b, out| {
+                b.label(out, *label);
+                if let Some(value) = value {
+                    b.expr(out, value);
+                }
+            }),
+            ExprKind::Continue { label } => self.node(out, NodeKind::ContinueExpr, span, |b, out| b.label(out, *label)),
+            ExprKind::Return(value) => self.node(out, NodeKind::ReturnExpr, span, |b, out| {
+                if let Some(value) = value {
+                    b.expr(out, value);
+                }
+            }),
+            ExprKind::Match { scrutinee, arms } => self.node(out, NodeKind::MatchExpr, span, |b, out| {
+                b.expr(out, scrutinee);
+                for arm in arms {
+                    b.match_arm(out, arm);
+                }
+            }),
+            ExprKind::Fn(decl) => self.fn_decl(out, &[], decl, span),
+        }
+    }
+
+    fn match_arm(&mut self, out: &mut Vec<SyntaxElement>, arm: &MatchArm<'_>) {
+        self.node(out, NodeKind::MatchArm, arm.span, |b, out| {
+            b.pattern(out, &arm.pattern);
+            b.expr(out, &arm.body);
+        });
+    }
+
+    fn pattern(&mut self, out: &mut Vec<SyntaxElement>, pattern: &Pattern<'_>) {
+        self.node(out, NodeKind::Pattern, pattern.span, |_, _| {});
+    }
+
+    fn ty(&mut self, out: &mut Vec<SyntaxElement>, ty: &TypeExpr<'_>) {
+        self.node(out, NodeKind::Type, ty.span, |b, out| match &ty.kind {
+            TypeKind::Named(_) | TypeKind::Type => {}
+            TypeKind::Pointer(pointee) => b.ty(out, pointee),
+            TypeKind::Array { len, elem } => {
+                b.expr(out, len);
+                b.ty(out, elem);
+            }
+            TypeKind::Tuple(elems) => {
+                for elem in elems {
+                    b.ty(out, elem);
+                }
+            }
+            TypeKind::Fn { params, ret } => {
+                for param in params {
+                    b.ty(out, param);
+                }
+                if let Some(ret) = ret {
+                    b.ty(out, ret);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use super::{NodeKind, TokenKind, parse};
+    use crate::ast::arena::AstArena;
+    use crate::types::{Span, Token};
+
+    const SAMPLES: [&str; 12] = [
+        include_str!("../progs/adt.mumbo"),
+        include_str!("../progs/extern.mumbo"),
+        include_str!("../progs/freeform.mumbo"),
+        include_str!("../progs/functions_basic.mumbo"),
+        include_str!("../progs/generics.mumbo"),
+        include_str!("../progs/let_literals.mumbo"),
+        include_str!("../progs/lexer.mumbo"),
+        include_str!("../progs/modules.mumbo"),
+        include_str!("../progs/pointers_and_arrays.mumbo"),
+        include_str!("../progs/spec_future.mumbo"),
+        include_str!("../progs/std.mumbo"),
+        include_str!("../progs/weird_grammar.mumbo"),
+    ];
+
+    /// the tokens cover the source without gaps and every node spans exactly its children.
+    fn assert_lossless(source: &str) {
+        let arena = AstArena::new();
+        let (cst, _) = parse(source, &arena);
+        assert_eq!(cst.to_string(), source);
+        let mut end = 0;
+        for token in cst.root().tokens() {
+            assert_eq!(token.span.start, end, "{source:?}");
+            end = token.span.end;
+        }
+        assert_eq!(end, source.len());
+        assert_eq!(cst.root().span, Span::new(0, source.len()));
+    }
+
+    #[test]
+    fn sample_programs_are_kept_byte_for_byte() {
+        for source in SAMPLES {
+            assert_lossless(source);
+        }
+        let broken = ["", "  // only a comment", "let x = ;\n} $ é\t", "\"unclosed // string", "a = 1 // no newline"];
+        for source in broken {
+            assert_lossless(source);
+        }
+    }
+
+    #[test]
+    fn trivia_goes_to_the_innermost_node() {
+        let arena = AstArena::new();
+        let (cst, parsed) = parse("// c\nlet x: u8 = a +  1; // after\n", &arena);
+        assert!(parsed.errors.is_empty());
+        assert_eq!(
+            cst.dump(),
+            r#"Root@0..34
+  Comment@0..4 "// c"
+  Whitespace@4..5 "\n"
+  LetStmt@5..24
+    KwLet@5..8 "let"
+    Whitespace@8..9 " "
+    LitIdentifier@9..10 "x"
+    PuncColon@10..11 ":"
+    Whitespace@11..12 " "
+    Type@12..14
+      LitIdentifier@12..14 "u8"
+    Whitespace@14..15 " "
+    PuncEq@15..16 "="
+    Whitespace@16..17 " "
+    BinaryExpr@17..23
+      NameExpr@17..18
+        LitIdentifier@17..18 "a"
+      Whitespace@18..19 " "
+      PuncPlus@19..20 "+"
+      Whitespace@20..22 "  "
+      LiteralExpr@22..23
+        LitInteger@22..23 "1"
+    PuncSemi@23..24 ";"
+  Whitespace@24..25 " "
+  Comment@25..33 "// after"
+  Whitespace@33..34 "\n"
+"#
+        );
+    }
+
+    #[test]
+    fn attributes_are_children_of_their_item() {
+        let arena = AstArena::new();
+        let source = "#[inline] #[extern(\"C\")]\nfn f() {}\n#[x] struct S {}";
+        assert_lossless(source);
+        let (cst, parsed) = parse(source, &arena);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let items: Vec<_> = cst.root().child_nodes().collect();
+        let children: Vec<_> = items[0].child_nodes().map(|node| (node.kind, node.span)).collect();
+        assert_eq!(
+            children,
+            [
+                (NodeKind::Attribute, Span::new(0, 9)),
+                (NodeKind::Attribute, Span::new(10, 24)),
+                (NodeKind::BlockExpr, Span::new(32, 34)),
+            ]
+        );
+        assert_eq!(items[1].child_nodes().next().map(|node| node.kind), Some(NodeKind::Attribute));
+    }
+
+    #[test]
+    fn errors_are_kept_as_tokens() {
+        let arena = AstArena::new();
+        let (cst, parsed) = parse("let a = $;\nb;", &arena);
+        assert_eq!(parsed.lexer_errors.len(), 1);
+        let error = cst.root().token_at_offset(8).unwrap();
+        assert_eq!((error.kind, error.span), (TokenKind::Error, Span::new(8, 9)));
+        let kinds: Vec<NodeKind> = cst.root().child_nodes().map(|node| node.kind).collect();
+        assert_eq!(kinds, [NodeKind::ExprStmt]);
+        // the failed `let` stays in the root as loose tokens
+        assert_eq!(cst.root().children[0].span(), Span::new(0, 3));
+        assert_eq!(cst.root().token_at_offset(0).unwrap().kind, TokenKind::Token(Token::KwLet));
+    }
+}
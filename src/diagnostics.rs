@@ -0,0 +1,571 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+use crate::c_emitter::CError;
+use crate::const_eval::{EvalError, EvalErrorKind};
+#[cfg(feature = "std")]
+use crate::driver::{DriverError, DriverErrorKind};
+use crate::externs::{CallConv, ExternError, ExternErrorKind};
+use crate::lexer::{ErrorKind, LexerError, LexerWarning};
+use crate::lint::{LintKind, LintLevel, LintWarning};
+use crate::parser::{ParseError, ParseErrorKind};
+use crate::resolve::{ResolveError, ResolveErrorKind, ResolveWarning};
+use crate::source_code::{SourceCode, write_underline};
+use crate::ty::{IntTy, Mutability, TyTable};
+use crate::typeck::{TypeError, TypeErrorKind, TypeWarning, TypeWarningKind};
+use crate::types::Span;
+use crate::verify::VerifyError;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const BLUE: &str = "\x1b[1;34m";
+const GREEN: &str = "\x1b[1;32m";
+
+/// how bad a `Diagnostic` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    #[inline]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Severity::Note => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+
+    #[inline]
+    const fn color(self) -> &'static str {
+        match self {
+            Severity::Note => "\x1b[1;32m",
+            Severity::Warning => "\x1b[1;33m",
+            Severity::Error => "\x1b[1;31m",
+        }
+    }
+}
+
+/// a suggested edit: `replacement` goes where `span` is, an empty span inserts it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Fix {
+    pub span: Span,
+    pub replacement: String,
+    pub message: String,
+}
+
+/// a message about some piece of source code, shared by every pass that reports problems.
+///
+/// the first label is the primary one: it decides the reported position and is underlined
+/// with `^`, the others with `-`. see `Diagnostic::render`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// stable identifier like `E0004`, see `ErrorKind::code`.
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub labels: Vec<(Span, String)>,
+    pub notes: Vec<String>,
+    pub fixes: Vec<Fix>,
+}
+
+impl Diagnostic {
+    #[inline]
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity,
+            code: None,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            fixes: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    #[inline]
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    #[inline]
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// adds a label, the message may be empty to only underline the span.
+    #[inline]
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push((span, message.into()));
+        self
+    }
+
+    #[inline]
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// adds a `Fix` explained by `message`, like "add `;` here".
+    #[inline]
+    pub fn with_fix(mut self, span: Span, replacement: impl Into<String>, message: impl Into<String>) -> Self {
+        self.fixes.push(Fix { span, replacement: replacement.into(), message: message.into() });
+        self
+    }
+
+    /// renders the diagnostic like rustc does: a header, the location of the primary label,
+    /// every labeled line with carets below the span, the notes and finally the fixes, each
+    /// showing its line with the edit applied. `color` adds ansi escapes. spans reaching past
+    /// their first line are underlined up to its end.
+    pub fn render(&self, source: &SourceCode<'_>, path: &str, color: bool) -> String {
+        let mut out = String::new();
+        self.write_to(&mut out, source, path, color).expect("formatting into a String can't fail");
+        out
+    }
+
+    /// like `render`, but writes into `out`.
+    pub fn write_to(&self, out: &mut impl Write, source: &SourceCode<'_>, path: &str, color: bool) -> fmt::Result {
+        let paint = |style: &'static str| if color { style } else { "" };
+        let (reset, blue) = (paint(RESET), paint(BLUE));
+
+        write!(out, "{}{}", paint(self.severity.color()), self.severity.as_str())?;
+        if let Some(code) = self.code {
+            write!(out, "[{}]", code)?;
+        }
+        writeln!(out, "{}{}: {}{}", reset, paint(BOLD), self.message, reset)?;
+
+        let lines = source.line_index();
+        let width = self
+            .labels
+            .iter()
+            .map(|(span, _)| span)
+            .chain(self.fixes.iter().map(|fix| &fix.span))
+            .map(|span| lines.line_column(span.start).0.ilog10() as usize + 1)
+            .max()
+            .unwrap_or(0);
+        if let Some((span, _)) = self.labels.first() {
+            let (line, column) = lines.line_column(span.start);
+            writeln!(out, "{:width$}{}-->{} {}:{}:{}", "", blue, reset, path, line, column)?;
+            writeln!(out, "{:width$} {}|{}", "", blue, reset)?;
+        }
+
+        let mut previous_line = None;
+        for (index, (span, label)) in self.labels.iter().enumerate() {
+            let line = source.line_containing(span.start);
+            let number = lines.line_column(line.span.start).0;
+            if previous_line != Some(number) {
+                writeln!(out, "{}{:>width$} |{} {}", blue, number, reset, line.value)?;
+                previous_line = Some(number);
+            }
+
+            write!(out, "{:width$} {}|{} ", "", blue, reset)?;
+            let (marker, style) = match index {
+                0 => ('^', paint(self.severity.color())),
+                _ => ('-', blue),
+            };
+            let start = span.start.saturating_sub(line.span.start);
+            let end = span.end.saturating_sub(line.span.start);
+            // the style goes in front of the indentation, which is only spaces and tabs
+            out.write_str(style)?;
+            write_underline(out, line.value, start, end, marker)?;
+            if !label.is_empty() {
+                write!(out, " {}", label)?;
+            }
+            writeln!(out, "{}", reset)?;
+        }
+
+        for note in &self.notes {
+            writeln!(out, "{:width$} {}={} note: {}", "", blue, reset, note)?;
+        }
+
+        for fix in &self.fixes {
+            writeln!(out, "{:width$} {}={} help: {}", "", blue, reset, fix.message)?;
+            let line = source.line_containing(fix.span.start);
+            let number = lines.line_column(line.span.start).0;
+            let start = fix.span.start.saturating_sub(line.span.start).min(line.value.len());
+            let end = fix.span.end.saturating_sub(line.span.start).clamp(start, line.value.len());
+            let patched = format!("{}{}{}", &line.value[..start], fix.replacement, &line.value[end..]);
+            writeln!(out, "{}{:>width$} |{} {}", blue, number, reset, patched)?;
+            write!(out, "{:width$} {}|{} {}", "", blue, reset, paint(GREEN))?;
+            write_underline(out, &patched, start, start + fix.replacement.len(), '+')?;
+            writeln!(out, "{}", reset)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<LexerError> for Diagnostic {
+    fn from(error: LexerError) -> Self {
+        let diagnostic = Diagnostic::error(error.kind.to_string())
+            .with_code(error.code())
+            .with_label(error.span, "");
+        match error.kind {
+            ErrorKind::EmptyCharLiteral => {
+                diagnostic.with_note("use `'\\0'` for a nul character or a string literal for no characters")
+            }
+            ErrorKind::UnclosedCharLiteral => {
+                diagnostic.with_note("a character literal holds exactly one character, use a string literal for more")
+            }
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(error: ParseError) -> Self {
+        let diagnostic = Diagnostic::error(error.kind.to_string())
+            .with_code(error.code())
+            .with_label(error.span, "");
+        match error.kind {
+            ParseErrorKind::ChainedOperator(_) => diagnostic.with_note("use parentheses to group the operands"),
+            ParseErrorKind::DuplicateField { first } | ParseErrorKind::DuplicateVariant { first } => {
+                diagnostic.with_label(first, "first declared here")
+            }
+            ParseErrorKind::DuplicateFieldInit { first } => diagnostic.with_label(first, "first initialized here"),
+            ParseErrorKind::ConflictingQualifiers { previous, .. } => diagnostic.with_label(previous.span, ""),
+            ParseErrorKind::UnclosedParen { open } => diagnostic.with_label(open, "opened here"),
+            ParseErrorKind::MissingSemicolon => diagnostic.with_fix(error.span, ";", "add `;` here"),
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<ResolveError<'_>> for Diagnostic {
+    fn from(error: ResolveError<'_>) -> Self {
+        let diagnostic = Diagnostic::error(error.to_string())
+            .with_code(error.code())
+            .with_label(error.span, "");
+        match error.kind {
+            ResolveErrorKind::UsedBeforeDeclaration { declared } => diagnostic.with_label(declared, "declared here"),
+            ResolveErrorKind::Undeclared => diagnostic,
+            ResolveErrorKind::Shadowed { previous } => diagnostic.with_label(previous, "previously declared here"),
+            ResolveErrorKind::NotAnEnum { declared } => diagnostic.with_label(declared, "declared here"),
+            ResolveErrorKind::NoVariant { declared } => diagnostic.with_label(declared, "enum declared here"),
+        }
+    }
+}
+
+impl From<ResolveWarning<'_>> for Diagnostic {
+    fn from(warning: ResolveWarning<'_>) -> Self {
+        match warning {
+            ResolveWarning::Shadowed { span, previous, .. } => Diagnostic::warning(warning.to_string())
+                .with_label(span, "")
+                .with_label(previous, "previously declared here"),
+        }
+    }
+}
+
+impl Diagnostic {
+    /// type errors refer to their types by id, `tys` is the table of the `Checked` they come from.
+    pub fn from_type_error(error: &TypeError<'_>, tys: &TyTable<'_>) -> Self {
+        let diagnostic = Diagnostic::error(error.kind.message(tys))
+            .with_code(error.code())
+            .with_label(error.span, "");
+        match error.kind {
+            TypeErrorKind::Mismatch { because: Some(because), .. } => {
+                diagnostic.with_label(because, "expected due to this")
+            }
+            TypeErrorKind::AmbiguousType(_) => diagnostic.with_note("give the binding a type annotation"),
+            TypeErrorKind::AssignToImmutable { declared } => {
+                let diagnostic = diagnostic.with_note("only `mut` and `anymut` places can be assigned");
+                match declared {
+                    Some(declared) => diagnostic.with_label(declared, "declared without `mut` here"),
+                    None => diagnostic,
+                }
+            }
+            TypeErrorKind::AssignToConst { declared: Some(declared) } => {
+                diagnostic.with_label(declared, "declared here")
+            }
+            TypeErrorKind::UnionRead { .. } => {
+                diagnostic.with_note("a union doesn't know which member it holds, `cast` says what to read it as")
+            }
+            TypeErrorKind::PointerMutability { expected: Mutability::Mut, found: Mutability::Anymut } => {
+                diagnostic.with_note("the pointee may be written through other pointers, `*mut` promises it isn't")
+            }
+            TypeErrorKind::LiteralOutOfRange { to, .. } => {
+                diagnostic.with_note(format!("`{}` holds values from {} to {}", to.name(), to.min(), to.max()))
+            }
+            TypeErrorKind::CharOutOfRange { to: IntTy::U8, .. } => {
+                diagnostic.with_note("`u8` only holds ascii characters, a `u32` holds any character")
+            }
+            _ => diagnostic,
+        }
+    }
+
+    /// like `from_type_error`.
+    pub fn from_c_error(error: &CError, tys: &TyTable<'_>) -> Self {
+        Diagnostic::error(error.kind.message(tys))
+            .with_code(error.code())
+            .with_label(error.span, "")
+    }
+
+    /// like `from_type_error`.
+    pub fn from_type_warning(warning: &TypeWarning, tys: &TyTable<'_>) -> Self {
+        let diagnostic = Diagnostic::warning(warning.kind.message(tys)).with_label(warning.span, "");
+        match warning.kind {
+            TypeWarningKind::LossyCast { from, .. } if from.as_float().is_some() => {
+                diagnostic.with_note("the fraction is dropped and values out of range saturate")
+            }
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<ExternError<'_>> for Diagnostic {
+    fn from(error: ExternError<'_>) -> Self {
+        let diagnostic = Diagnostic::error(error.to_string())
+            .with_code(error.code())
+            .with_label(error.span, "");
+        match error.kind {
+            ExternErrorKind::SignatureMismatch { previous } | ExternErrorKind::CallConvMismatch { previous } => {
+                diagnostic.with_label(previous, "first declared here")
+            }
+            ExternErrorKind::DuplicateDefinition { previous } => diagnostic.with_label(previous, "first defined here"),
+            ExternErrorKind::UnknownCallConv => {
+                let names: Vec<_> = CallConv::ALL.iter().map(|conv| format!("`\"{}\"`", conv.name())).collect();
+                diagnostic.with_note(format!("the calling conventions are {}", names.join(", ")))
+            }
+            ExternErrorKind::NotExtern => diagnostic.with_note("declare it with `extern fn`"),
+        }
+    }
+}
+
+/// a denied lint is an error, any other level a warning.
+impl From<LintWarning<'_>> for Diagnostic {
+    fn from(warning: LintWarning<'_>) -> Self {
+        let severity = if warning.level == LintLevel::Deny { Severity::Error } else { Severity::Warning };
+        let mut diagnostic = Diagnostic::new(severity, warning.to_string()).with_label(warning.span, "");
+        if let LintKind::Unreachable { after } = warning.kind {
+            diagnostic = diagnostic.with_label(after, "any code following this is unreachable");
+        }
+        diagnostic.with_note(format!("`#[allow({})]` on an item silences this inside it", warning.kind.lint().name()))
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<DriverError> for Diagnostic {
+    fn from(error: DriverError) -> Self {
+        let diagnostic = Diagnostic::error(error.to_string())
+            .with_code(error.code())
+            .with_label(error.span, "");
+        match error.kind {
+            DriverErrorKind::ModuleNotFound { path } => diagnostic.with_note(format!("looked for `{path}`")),
+            DriverErrorKind::UnknownModule => diagnostic.with_note("modules are declared with `mod name;`"),
+            DriverErrorKind::NotInModule { .. } => diagnostic,
+        }
+    }
+}
+
+impl From<EvalError> for Diagnostic {
+    fn from(error: EvalError) -> Self {
+        let diagnostic = Diagnostic::error(error.to_string())
+            .with_code(error.code())
+            .with_label(error.span, "");
+        match error.kind {
+            EvalErrorKind::NotConst => {
+                diagnostic.with_note("calls, pointers and bindings that aren't `const` need a running program")
+            }
+            EvalErrorKind::TooManySteps { .. } => {
+                diagnostic.with_note("raise the limit with `EvalOptions::step_limit`")
+            }
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<VerifyError> for Diagnostic {
+    fn from(error: VerifyError) -> Self {
+        Diagnostic::error(format!("internal compiler error: {}", error))
+            .with_code(error.code())
+            .with_label(error.span, "")
+            .with_note("this is a bug in the compiler, not in the program")
+    }
+}
+
+impl From<LexerWarning> for Diagnostic {
+    fn from(warning: LexerWarning) -> Self {
+        match warning {
+            LexerWarning::ControlCharacterInLiteral(offset) => {
+                Diagnostic::warning("raw control character in string literal")
+                    .with_label(Span::new(offset, offset + 1), "")
+                    .with_note("escape it as `\\t`, `\\n`, `\\r`, `\\0` or `\\xNN`")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::{Diagnostic, Severity};
+    use crate::ast::arena::AstArena;
+    use crate::const_eval::{EvalOptions, eval};
+    use crate::lexer::{ControlBytePolicy, Lexer};
+    use crate::lint::{Lint, LintLevel, LintOptions, lint};
+    use crate::parser::{Parser, parse};
+    use crate::resolve::{ResolveOptions, resolve};
+    use crate::typeck::check;
+    use crate::source_code::SourceCode;
+    use crate::types::Span;
+
+    #[test]
+    fn renders_labels_under_their_lines() {
+        let source = SourceCode::new("let a = 1;\n\tlet é = $;\n");
+        let diagnostic = Diagnostic::error("something went wrong")
+            .with_code("E9999")
+            .with_label(Span::new(21, 22), "here")
+            .with_label(Span::new(16, 18), "")
+            .with_label(Span::new(4, 5), "declared here")
+            .with_note("a note");
+        assert_eq!(
+            diagnostic.render(&source, "main.mumbo", false),
+            "error[E9999]: something went wrong\n \
+             --> main.mumbo:2:11\n  \
+              |\n\
+             2 | \tlet é = $;\n  \
+              | \t        ^ here\n  \
+              | \t    -\n\
+             1 | let a = 1;\n  \
+              |     - declared here\n  \
+              = note: a note\n"
+        );
+    }
+
+    #[test]
+    fn fixes_show_the_edited_line() {
+        let text = "let y = 3\nlet z = 4;\n";
+        let source = SourceCode::new(text);
+        let arena = AstArena::new();
+        let parsed = parse(text, &arena);
+        let [error] = parsed.errors.as_slice() else { panic!("{:?}", parsed.errors) };
+        assert_eq!(
+            Diagnostic::from(*error).render(&source, "m.mumbo", false),
+            "error[E0109]: expected `;` at the end of the statement\n \
+             --> m.mumbo:1:10\n  \
+              |\n\
+             1 | let y = 3\n  \
+              |          ^\n  \
+              = help: add `;` here\n\
+             1 | let y = 3;\n  \
+              |          +\n"
+        );
+    }
+
+    #[test]
+    fn colors_are_optional() {
+        let source = SourceCode::new("x");
+        let diagnostic = Diagnostic::warning("w").with_label(Span::new(0, 1), "");
+        let plain = diagnostic.render(&source, "f", false);
+        let colored = diagnostic.render(&source, "f", true);
+        assert!(!plain.contains('\x1b'));
+        assert!(colored.contains("\x1b[1;33mwarning"));
+        assert_eq!(Diagnostic::new(Severity::Note, "n").render(&source, "f", false), "note: n\n");
+    }
+
+    #[test]
+    fn lexer_errors_and_warnings_convert() {
+        let source = SourceCode::new("let c = '';");
+        let mut lexer = Lexer::new(source.clone());
+        let error = loop {
+            if let Err(e) = lexer.lex_single_token() {
+                break e;
+            }
+        };
+        let rendered = Diagnostic::from(error).render(&source, "c.mumbo", false);
+        assert!(rendered.starts_with("error[E0006]: empty character literal\n --> c.mumbo:1:9\n"), "{rendered}");
+        assert!(rendered.contains("1 | let c = '';\n  |         ^^\n"), "{rendered}");
+
+        let source = SourceCode::new("\"a\x01\"");
+        let mut lexer = Lexer::new(source.clone()).with_control_byte_policy(ControlBytePolicy::Warn);
+        lexer.lex_single_token().unwrap();
+        let diagnostic = Diagnostic::from(lexer.take_warning().unwrap());
+        assert_eq!(diagnostic.severity, Severity::Warning);
+        assert_eq!(diagnostic.labels[0].0, Span::new(2, 3));
+    }
+
+    #[test]
+    fn parse_errors_convert() {
+        let source = SourceCode::new("a < b < c");
+        let arena = AstArena::new();
+        let error = Parser::new(Lexer::new(source.clone()), &arena).parse_expr().unwrap_err();
+        let rendered = Diagnostic::from(error).render(&source, "p.mumbo", false);
+        assert!(rendered.starts_with("error[E0102]: `<` operators cannot be chained\n"), "{rendered}");
+        assert!(rendered.contains("1 | a < b < c\n  |       ^\n"), "{rendered}");
+        assert!(rendered.ends_with("= note: use parentheses to group the operands\n"), "{rendered}");
+
+        let source = SourceCode::new("(a + b;");
+        let error = Parser::new(Lexer::new(source.clone()), &arena).parse_expr().unwrap_err();
+        let rendered = Diagnostic::from(error).render(&source, "p.mumbo", false);
+        assert!(rendered.starts_with("error[E0107]: `(` is never closed\n"), "{rendered}");
+        assert!(rendered.contains("1 | (a + b;\n  |       ^\n  | - opened here\n"), "{rendered}");
+    }
+
+    #[test]
+    fn resolve_errors_and_warnings_convert() {
+        let source = SourceCode::new("a;\nlet a = 1;\nlet a = 2;");
+        let arena = AstArena::new();
+        let parsed = parse(source.as_str(), &arena);
+        let resolved = resolve(&parsed.stmts, ResolveOptions::new());
+        let rendered = Diagnostic::from(resolved.errors[0]).render(&source, "r.mumbo", false);
+        assert!(rendered.starts_with("error[E0201]: `a` is used before its declaration\n"), "{rendered}");
+        assert!(rendered.contains("1 | a;\n  | ^\n2 | let a = 1;\n  |     - declared here\n"), "{rendered}");
+
+        let diagnostic = Diagnostic::from(resolved.warnings[0]);
+        assert_eq!(diagnostic.severity, Severity::Warning);
+        assert_eq!(diagnostic.labels[1], (Span::new(7, 8), "previously declared here".to_string()));
+    }
+
+    #[test]
+    fn type_errors_convert() {
+        let source = SourceCode::new("let a: u8 = true;");
+        let arena = AstArena::new();
+        let parsed = parse(source.as_str(), &arena);
+        let checked = check(&parsed.stmts);
+        let rendered = Diagnostic::from_type_error(&checked.errors[0], &checked.tys).render(&source, "t.mumbo", false);
+        assert!(rendered.starts_with("error[E0301]: mismatched types: expected `u8`, found `bool`\n"), "{rendered}");
+        assert!(rendered.contains("expected due to this"), "{rendered}");
+
+        let source = SourceCode::new("let b: u8 = 2.5 cast u8;");
+        let parsed = parse(source.as_str(), &arena);
+        let checked = check(&parsed.stmts);
+        let diagnostic = Diagnostic::from_type_warning(&checked.warnings[0], &checked.tys);
+        assert_eq!(diagnostic.severity, Severity::Warning);
+        let rendered = diagnostic.render(&source, "t.mumbo", false);
+        assert!(rendered.starts_with("warning: casting `{float}` to `u8` may change the value\n"), "{rendered}");
+    }
+
+    #[test]
+    fn eval_errors_convert() {
+        let source = SourceCode::new("let a = compiletime { 200 cast u8 + 100 };");
+        let arena = AstArena::new();
+        let mut parsed = parse(source.as_str(), &arena);
+        let evaluated = eval(&mut parsed.stmts, &arena, EvalOptions::new());
+        let rendered = Diagnostic::from(evaluated.errors[0]).render(&source, "c.mumbo", false);
+        assert!(rendered.starts_with("error[E0402]: arithmetic overflow in a compiletime block\n"), "{rendered}");
+    }
+
+    #[test]
+    fn lint_warnings_convert() {
+        let source = SourceCode::new("fn f() { return; f(); }\nf();");
+        let arena = AstArena::new();
+        let parsed = parse(source.as_str(), &arena);
+        let options = LintOptions::new().level(Lint::UnreachableCode, LintLevel::Deny);
+        let diagnostic = Diagnostic::from(lint(&parsed.stmts, options)[0]);
+        assert_eq!(diagnostic.severity, Severity::Error);
+        let rendered = diagnostic.render(&source, "l.mumbo", false);
+        assert!(rendered.starts_with("error: unreachable code\n"), "{rendered}");
+        assert!(rendered.contains("any code following this is unreachable"), "{rendered}");
+        assert!(rendered.contains("`#[allow(unreachable_code)]`"), "{rendered}");
+    }
+}
@@ -0,0 +1,759 @@
+//! the compilation driver: loads a program from disk into a `SourceMap`, lexes, parses,
+//! resolves, type checks, lints and evaluates the compiletime blocks of every file of it and
+//! collects the diagnostics of all of them. a file with errors before type checking isn't type
+//! checked or linted, one with type errors isn't evaluated. the options of every stage come
+//! from a `Session`. a function is linted as never called unless some file imports its name,
+//! a call through an `import` is in another file.
+//!
+//! a `Driver` runs the `Pass`es registered on it after its own stages, on every file without
+//! type errors. they see the syntax tree, the types and the ir of the file and can report
+//! diagnostics or rewrite the ir for the passes after them.
+//!
+//! with `Session::verify` the driver checks what type checking and lowering produce and what
+//! every pass leaves behind, see `verify`. a broken promise is an error at the place it was
+//! found, after a pass with a note naming it. only the first one of a file is looked for.
+//!
+//! the items of every file go into the `SymbolIndex` of the compilation as the file is parsed,
+//! errors or not.
+//!
+//! a program is a root file together with the modules it declares, `mod name;` loads
+//! `name.mumbo` next to the file declaring it, transitively. `compile_dir` takes every
+//! `.mumbo` file of a directory as a root instead. each file is resolved on its own, an
+//! `import a.b.name;` is then checked against the files: `a` has to be a module of the
+//! importing file, `b` a module of `a` and `name` an item at the top level of `b`.
+
+use std::boxed::Box;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::string::String;
+use std::vec::Vec;
+
+use core::fmt;
+
+use crate::ast::arena::AstArena;
+use crate::ast::{ItemKind, Stmt, StmtKind};
+use crate::const_eval::{EvalOptions, eval};
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::ir::{Program, lower};
+use crate::lexer::{Lexer, LexerOptions};
+use crate::lint::{LintKind, LintOptions, lint};
+use crate::parser::Parser;
+use crate::resolve::{ResolveOptions, resolve};
+use crate::source_code::SourceCode;
+use crate::source_map::{FileId, SourceMap, module_path};
+use crate::symbol_index::SymbolIndex;
+use crate::typeck::{Checked, check};
+use crate::types::Span;
+use crate::verify;
+
+/// the configuration of a compilation, the options of every stage the driver runs. the
+/// driver uses no hashing or clock, so compiling the same files with equal sessions gives
+/// the same diagnostics in the same order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Session {
+    lexer: LexerOptions,
+    resolve: ResolveOptions,
+    lint: LintOptions,
+    eval: EvalOptions,
+    verify: bool,
+}
+
+impl Session {
+    /// the default options of every stage.
+    #[inline]
+    pub const fn new() -> Self {
+        Session {
+            lexer: LexerOptions::new(),
+            resolve: ResolveOptions::new(),
+            lint: LintOptions::new(),
+            eval: EvalOptions::new(),
+            verify: cfg!(debug_assertions),
+        }
+    }
+
+    #[inline]
+    pub const fn lexer(mut self, options: LexerOptions) -> Self {
+        self.lexer = options;
+        self
+    }
+
+    #[inline]
+    pub const fn resolve(mut self, options: ResolveOptions) -> Self {
+        self.resolve = options;
+        self
+    }
+
+    #[inline]
+    pub const fn lint(mut self, options: LintOptions) -> Self {
+        self.lint = options;
+        self
+    }
+
+    #[inline]
+    pub const fn eval(mut self, options: EvalOptions) -> Self {
+        self.eval = options;
+        self
+    }
+
+    /// whether the output of the stages is checked, see `verify`. on by default in debug builds.
+    #[inline]
+    pub const fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    #[inline]
+    pub const fn get_lexer(&self) -> LexerOptions {
+        self.lexer
+    }
+
+    #[inline]
+    pub const fn get_resolve(&self) -> ResolveOptions {
+        self.resolve
+    }
+
+    #[inline]
+    pub const fn get_lint(&self) -> LintOptions {
+        self.lint
+    }
+
+    #[inline]
+    pub const fn get_eval(&self) -> EvalOptions {
+        self.eval
+    }
+
+    #[inline]
+    pub const fn get_verify(&self) -> bool {
+        self.verify
+    }
+}
+
+impl Default for Session {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DriverErrorKind {
+    /// the file of `mod name;` couldn't be read, `path` is where it was looked for.
+    ModuleNotFound { path: String },
+    /// a segment of an `import` path that isn't a module of the one before it.
+    UnknownModule,
+    /// the last segment of an `import` path isn't declared in the module.
+    NotInModule { module: String },
+}
+
+impl DriverErrorKind {
+    /// stable identifier like `E0601`.
+    #[inline]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            DriverErrorKind::ModuleNotFound { .. } => "E0601",
+            DriverErrorKind::UnknownModule => "E0602",
+            DriverErrorKind::NotInModule { .. } => "E0603",
+        }
+    }
+}
+
+/// `span` is in `file` and covers `name`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DriverError {
+    pub kind: DriverErrorKind,
+    pub name: String,
+    pub file: FileId,
+    pub span: Span,
+}
+
+impl DriverError {
+    #[inline]
+    pub const fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+}
+
+impl fmt::Display for DriverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            DriverErrorKind::ModuleNotFound { .. } => write!(f, "file not found for module `{}`", self.name),
+            DriverErrorKind::UnknownModule => write!(f, "unresolved module `{}`", self.name),
+            DriverErrorKind::NotInModule { module } => {
+                write!(f, "`{}` is not declared in module `{}`", self.name, module)
+            }
+        }
+    }
+}
+
+/// the outcome of `compile_file` and `compile_dir`.
+#[derive(Debug, Clone, Default)]
+pub struct Compilation {
+    /// every file of the program, roots first.
+    pub files: SourceMap,
+    /// per file in the order of `files`, the diagnostics of a file sorted by position.
+    pub diagnostics: Vec<(FileId, Diagnostic)>,
+    /// the items of every file, see `SymbolIndex`.
+    pub symbols: SymbolIndex,
+}
+
+impl Compilation {
+    #[inline]
+    pub fn error_count(&self) -> usize {
+        self.diagnostics.iter().filter(|(_, diagnostic)| diagnostic.severity == Severity::Error).count()
+    }
+
+    #[inline]
+    pub fn has_errors(&self) -> bool {
+        self.error_count() > 0
+    }
+
+    /// every diagnostic rendered against its file, see `Diagnostic::render`.
+    pub fn render(&self, color: bool) -> String {
+        let mut out = String::new();
+        for (id, diagnostic) in &self.diagnostics {
+            let file = self.files.file(*id);
+            out.push_str(&diagnostic.render(&file.source(), file.path(), color));
+        }
+        out
+    }
+}
+
+/// a stage added to a `Driver` from outside of the compiler, like a lint of its own or an ir
+/// transform. see the module docs for when it runs.
+pub trait Pass {
+    /// unique among the passes of a driver, `after` refers to a pass by it.
+    fn name(&self) -> &str;
+
+    /// the passes that have to run before this one, by name. names no registered pass has
+    /// are ignored, passes in a cycle run in the order they were registered.
+    #[inline]
+    fn after(&self) -> &[&str] {
+        &[]
+    }
+
+    fn run(&self, cx: &mut PassContext<'_, '_>);
+}
+
+/// what a `Pass` sees of the file it runs on.
+pub struct PassContext<'a, 'source> {
+    pub files: &'a SourceMap,
+    pub file: FileId,
+    /// with the compiletime blocks already replaced by their values.
+    pub stmts: &'a [Stmt<'source>],
+    pub checked: &'a Checked<'source>,
+    /// the symbols of this file and the files checked before it, the ones before it in `files`.
+    pub symbols: &'a SymbolIndex,
+    /// the file lowered to the ir, the passes after this one see what it changes.
+    pub program: &'a mut Program<'source>,
+    diagnostics: &'a mut Vec<(FileId, Diagnostic)>,
+}
+
+impl PassContext<'_, '_> {
+    /// adds `diagnostic` to the ones of the file.
+    #[inline]
+    pub fn report(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push((self.file, diagnostic));
+    }
+}
+
+/// compiles programs with the options of a `Session` and the `Pass`es registered on it.
+pub struct Driver {
+    session: Session,
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl Driver {
+    #[inline]
+    pub fn new(session: Session) -> Self {
+        Driver {
+            session,
+            passes: Vec::new(),
+        }
+    }
+
+    /// registers `pass`, see `Pass::after` for the order passes run in.
+    #[inline]
+    pub fn pass(mut self, pass: impl Pass + 'static) -> Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    /// compiles the program rooted at `path`. only a root that can't be read is an `Err`, a
+    /// missing module is a diagnostic.
+    pub fn compile_file(&self, path: impl AsRef<Path>) -> io::Result<Compilation> {
+        let mut files = SourceMap::new();
+        load_file(path.as_ref(), &mut files)?;
+        Ok(self.compile(files))
+    }
+
+    /// compiles every `.mumbo` file in `dir` as a root.
+    pub fn compile_dir(&self, dir: impl AsRef<Path>) -> io::Result<Compilation> {
+        let mut files = SourceMap::new();
+        load_dir(dir.as_ref(), &mut files)?;
+        Ok(self.compile(files))
+    }
+
+    /// the passes in the order they run, see `Pass::after`.
+    fn ordered_passes(&self) -> Vec<&dyn Pass> {
+        let mut ordered: Vec<&dyn Pass> = Vec::with_capacity(self.passes.len());
+        let mut waiting: Vec<&dyn Pass> = self.passes.iter().map(|pass| &**pass).collect();
+        while !waiting.is_empty() {
+            let is_ready = |pass: &&dyn Pass| {
+                pass.after().iter().all(|name| {
+                    let is_waiting = waiting.iter().any(|other| other.name() == *name);
+                    !is_waiting || ordered.iter().any(|done| done.name() == *name)
+                })
+            };
+            // a cycle, the first pass that is waiting goes next
+            let next = waiting.iter().position(is_ready).unwrap_or(0);
+            ordered.push(waiting.remove(next));
+        }
+        ordered
+    }
+
+    fn compile(&self, files: SourceMap) -> Compilation {
+        compile(files, self.session, &self.ordered_passes())
+    }
+}
+
+/// compiles the program rooted at `path` with the default `Session` and no passes.
+#[inline]
+pub fn compile_file(path: impl AsRef<Path>) -> io::Result<Compilation> {
+    Driver::new(Session::new()).compile_file(path)
+}
+
+/// compiles every `.mumbo` file in `dir` as a root, like `compile_file`.
+#[inline]
+pub fn compile_dir(dir: impl AsRef<Path>) -> io::Result<Compilation> {
+    Driver::new(Session::new()).compile_dir(dir)
+}
+
+/// adds the file at `path` to `files` under its path as given.
+pub fn load_file(path: &Path, files: &mut SourceMap) -> io::Result<FileId> {
+    let source = fs::read_to_string(path)?;
+    Ok(files.add(path.to_string_lossy(), source))
+}
+
+/// adds every `.mumbo` file directly in `dir` to `files`, sorted by path.
+pub fn load_dir(dir: &Path, files: &mut SourceMap) -> io::Result<Vec<FileId>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "mumbo") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    paths.iter().map(|path| load_file(path, files)).collect()
+}
+
+/// what the files of a program need to know about each other, by span into their file.
+#[derive(Debug, Default)]
+struct FileSummary {
+    /// the name of every `mod` and the file it is, `None` when it couldn't be loaded.
+    modules: Vec<(Span, Option<FileId>)>,
+    /// the names of the items at the top level.
+    items: Vec<Span>,
+    /// the segments of every `import`, the imported name last.
+    imports: Vec<Vec<Span>>,
+    /// the lint of every function the file never calls, by its name.
+    unused_fns: Vec<(String, Diagnostic)>,
+}
+
+/// checks the files already in `files` and loads the modules they declare.
+fn compile(mut files: SourceMap, session: Session, passes: &[&dyn Pass]) -> Compilation {
+    let mut summaries = Vec::new();
+    let mut diagnostics = Vec::new();
+    let mut errors = Vec::new();
+    let mut symbols = SymbolIndex::new();
+    // modules are added while going, each file is checked once
+    let mut next = 0;
+    while next < files.len() {
+        let id = files.iter().map(|(id, _)| id).nth(next).expect("the file was added");
+        next += 1;
+        let (declared, summary) = check_file(&files, id, session, passes, &mut symbols, &mut diagnostics);
+        let mut modules = Vec::with_capacity(declared.len());
+        for (span, path) in declared {
+            let module = match files.find(&path) {
+                Some(module) => Some(module),
+                None => match load_file(Path::new(&path), &mut files) {
+                    Ok(module) => Some(module),
+                    Err(_) => {
+                        let name = String::from(files.snippet(id, span));
+                        let kind = DriverErrorKind::ModuleNotFound { path };
+                        errors.push(DriverError { kind, name, file: id, span });
+                        None
+                    }
+                },
+            };
+            modules.push((span, module));
+        }
+        summaries.push(FileSummary { modules, ..summary });
+    }
+    check_imports(&files, &summaries, &mut errors);
+    let imported: Vec<_> = files
+        .iter()
+        .flat_map(|(id, _)| summaries[id.index()].imports.iter().filter_map(move |import| Some((id, *import.last()?))))
+        .map(|(id, name)| files.snippet(id, name))
+        .collect();
+    for (id, _) in files.iter() {
+        let unused = core::mem::take(&mut summaries[id.index()].unused_fns);
+        let unused = unused.into_iter().filter(|(name, _)| !imported.contains(&name.as_str()));
+        diagnostics.extend(unused.map(|(_, diagnostic)| (id, diagnostic)));
+    }
+    diagnostics.extend(errors.into_iter().map(|error| (error.file, Diagnostic::from(error))));
+    // stable, the diagnostics of a file stay in the order of their passes where they tie
+    diagnostics.sort_by_key(|(id, diagnostic)| (*id, diagnostic.labels.first().map(|(span, _)| span.start)));
+    Compilation { files, diagnostics, symbols }
+}
+
+/// runs every stage on the file `id`, see the module docs. returns the path of every module it
+/// declares by the span of the name, and what it declares at the top level.
+fn check_file(
+    files: &SourceMap,
+    id: FileId,
+    session: Session,
+    passes: &[&dyn Pass],
+    symbols: &mut SymbolIndex,
+    out: &mut Vec<(FileId, Diagnostic)>,
+) -> (Vec<(Span, String)>, FileSummary) {
+    let file = files.file(id);
+    let arena = AstArena::new();
+    let lexer = Lexer::with_options(SourceCode::new(file.source().as_str()), session.lexer);
+    let mut parsed = Parser::new(lexer, &arena).parse();
+    out.extend(parsed.lexer_errors.iter().map(|error| (id, Diagnostic::from(*error))));
+    out.extend(parsed.errors.iter().map(|error| (id, Diagnostic::from(*error))));
+    symbols.update(id, &parsed.stmts);
+    let resolved = resolve(&parsed.stmts, session.resolve);
+    let mut summary = FileSummary::default();
+    let is_resolved = parsed.lexer_errors.is_empty() && parsed.errors.is_empty() && resolved.errors.is_empty();
+    out.extend(resolved.errors.into_iter().map(|error| (id, Diagnostic::from(error))));
+    out.extend(resolved.warnings.into_iter().map(|warning| (id, Diagnostic::from(warning))));
+    // types of names that don't resolve would only repeat the errors above
+    if is_resolved {
+        let checked = check(&parsed.stmts);
+        out.extend(checked.errors.iter().map(|error| (id, Diagnostic::from_type_error(error, &checked.tys))));
+        let mut verified = true;
+        if session.verify {
+            let errors = verify::expr_types(&parsed.stmts, &checked);
+            verified = errors.is_empty();
+            out.extend(errors.into_iter().map(|error| (id, Diagnostic::from(error))));
+        }
+        out.extend(checked.warnings.iter().map(|warning| (id, Diagnostic::from_type_warning(warning, &checked.tys))));
+        for warning in lint(&parsed.stmts, session.lint) {
+            match warning.kind {
+                LintKind::UnusedFn(name) => summary.unused_fns.push((String::from(name), Diagnostic::from(warning))),
+                _ => out.push((id, Diagnostic::from(warning))),
+            }
+        }
+        if checked.errors.is_empty() {
+            let evaluated = eval(&mut parsed.stmts, &arena, session.eval);
+            out.extend(evaluated.errors.into_iter().map(|error| (id, Diagnostic::from(error))));
+            if !passes.is_empty() || (session.verify && verified) {
+                let mut program = lower(&parsed.stmts, &checked);
+                if session.verify && verified {
+                    let errors = verify::program(&program);
+                    verified = errors.is_empty();
+                    out.extend(errors.into_iter().map(|error| (id, Diagnostic::from(error))));
+                }
+                for pass in passes {
+                    pass.run(&mut PassContext {
+                        files,
+                        file: id,
+                        stmts: &parsed.stmts,
+                        checked: &checked,
+                        symbols,
+                        program: &mut program,
+                        diagnostics: out,
+                    });
+                    if session.verify && verified {
+                        let errors = verify::program(&program);
+                        verified = errors.is_empty();
+                        let note = format!("found after the pass `{}`", pass.name());
+                        let errors = errors.into_iter().map(|error| Diagnostic::from(error).with_note(note.clone()));
+                        out.extend(errors.map(|diagnostic| (id, diagnostic)));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut modules = Vec::new();
+    for stmt in &parsed.stmts {
+        let StmtKind::Item(item) = &stmt.kind else { continue };
+        match &item.kind {
+            ItemKind::Mod(decl) => modules.push((decl.name.span, module_path(file.path(), decl.name.name))),
+            ItemKind::Import(decl) => {
+                let path = decl.path.iter().chain([&decl.name]).map(|segment| segment.span);
+                summary.imports.push(path.collect());
+            }
+            _ => summary.items.push(item.name().span),
+        }
+    }
+    (modules, summary)
+}
+
+/// follows every `import` through the modules of the files, see the module docs.
+fn check_imports(files: &SourceMap, summaries: &[FileSummary], errors: &mut Vec<DriverError>) {
+    for (id, _) in files.iter() {
+        let summary = &summaries[id.index()];
+        'imports: for import in &summary.imports {
+            let (name, path) = import.split_last().expect("an import names something");
+            let mut module = id;
+            for &segment in path {
+                let text = files.snippet(id, segment);
+                let modules = &summaries[module.index()].modules;
+                let declared = modules.iter().find(|(at, _)| files.snippet(module, *at) == text);
+                match declared {
+                    Some(&(_, Some(found))) => module = found,
+                    // reported as `ModuleNotFound` where it is declared
+                    Some((_, None)) => continue 'imports,
+                    None => {
+                        let (kind, name) = (DriverErrorKind::UnknownModule, String::from(text));
+                        errors.push(DriverError { kind, name, file: id, span: segment });
+                        continue 'imports;
+                    }
+                }
+            }
+            let text = files.snippet(id, *name);
+            let target = &summaries[module.index()];
+            let mut declared = target.items.iter().chain(target.modules.iter().map(|(at, _)| at));
+            if !declared.any(|at| files.snippet(module, *at) == text) {
+                let segment = *path.last().expect("imports name a module");
+                let module = String::from(files.snippet(id, segment));
+                errors.push(DriverError {
+                    kind: DriverErrorKind::NotInModule { module },
+                    name: String::from(text),
+                    file: id,
+                    span: *name,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::path::PathBuf;
+    use std::rc::Rc;
+
+    use super::{Compilation, Driver, Pass, PassContext, Session, compile_dir, compile_file};
+    use crate::const_eval::EvalOptions;
+    use crate::diagnostics::{Diagnostic, Severity};
+    use crate::ir::{BlockId, TerminatorKind};
+    use crate::lexer::LexerOptions;
+    use crate::lint::{Lint, LintLevel, LintOptions};
+    use crate::resolve::{ResolveOptions, ShadowPolicy};
+
+    /// a fresh directory holding `files`, removed again by the caller.
+    fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("mumbo_driver_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        for (path, source) in files {
+            std::fs::write(root.join(path), source).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn loads_declared_modules() {
+        let root = project(
+            "modules",
+            &[
+                ("main.mumbo", "mod shapes;\nimport shapes.geometry.point;\nfn origin() -> point { uninit }"),
+                ("shapes.mumbo", "mod geometry;"),
+                ("geometry.mumbo", "struct point { x: i32, y: i32 }"),
+            ],
+        );
+        let compiled = compile_file(root.join("main.mumbo")).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        let paths: Vec<_> = compiled.files.iter().map(|(_, file)| file.path().rsplit(['/', '\\']).next()).collect();
+        assert_eq!(paths, [Some("main.mumbo"), Some("shapes.mumbo"), Some("geometry.mumbo")]);
+        assert!(!compiled.has_errors(), "{}", compiled.render(false));
+        let symbols: Vec<_> =
+            compiled.symbols.iter().map(|symbol| (symbol.file.index(), symbol.name.as_str())).collect();
+        assert_eq!(symbols, [(0, "shapes"), (0, "origin"), (1, "geometry"), (2, "point")]);
+    }
+
+    #[test]
+    fn reports_missing_modules_and_bad_imports() {
+        let root = project(
+            "errors",
+            &[
+                ("main.mumbo", "mod gone;\nmod util;\nimport util.missing;\nimport nowhere.x;\nimport gone.y;"),
+                ("util.mumbo", "fn helper() {}\nlet x = undeclared;"),
+            ],
+        );
+        let compiled = compile_file(root.join("main.mumbo")).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        let codes: Vec<_> = compiled.diagnostics.iter().map(|(id, d)| (id.index(), d.code)).collect();
+        // `gone.y` isn't reported again, the module is already missing
+        assert_eq!(codes, [(0, Some("E0601")), (0, Some("E0603")), (0, Some("E0602")), (1, Some("E0202"))]);
+        assert_eq!(compiled.error_count(), 4);
+        let rendered = compiled.render(false);
+        assert!(rendered.contains("file not found for module `gone`"), "{rendered}");
+        assert!(rendered.contains("`missing` is not declared in module `util`"), "{rendered}");
+        assert!(rendered.contains("unresolved module `nowhere`"), "{rendered}");
+    }
+
+    #[test]
+    fn files_are_type_checked() {
+        let root = project(
+            "types",
+            &[
+                ("main.mumbo", "mod util;\nlet x: u8 = true;\nlet y: bool = 5;\nlet z: u8 = 1000;"),
+                ("util.mumbo", "let w = 1.5 cast u8;\nlet v: u8 = undeclared;"),
+            ],
+        );
+        let compiled = compile_file(root.join("main.mumbo")).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        let codes: Vec<_> = compiled.diagnostics.iter().map(|(id, d)| (id.index(), d.code)).collect();
+        // `util` doesn't resolve, so only the resolve error is reported for it. the lints of
+        // the unused bindings of `main` have no code
+        assert_eq!(
+            codes,
+            [
+                (0, None),
+                (0, Some("E0301")),
+                (0, None),
+                (0, Some("E0301")),
+                (0, None),
+                (0, Some("E0322")),
+                (1, Some("E0202"))
+            ]
+        );
+        assert_eq!(compiled.error_count(), 4);
+
+        let root = project("warnings", &[("main.mumbo", "let w = 1.5 cast u8;\nw;")]);
+        let compiled = compile_file(root.join("main.mumbo")).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        assert!(!compiled.has_errors());
+        assert_eq!(compiled.diagnostics.len(), 1, "{}", compiled.render(false));
+    }
+
+    #[test]
+    fn sessions_configure_every_stage() {
+        let text = "let a = 1;\nlet a = a;\na;\nfn f() {}\nlet b = compiletime { loop {} };\nlet é = b;\né;";
+        let root = project("session", &[("main.mumbo", text)]);
+        let default = compile_file(root.join("main.mumbo")).unwrap();
+        let session = Session::new()
+            .lexer(LexerOptions::new().unicode_identifiers(true))
+            .resolve(ResolveOptions::new().shadowing(ShadowPolicy::Allow))
+            .lint(LintOptions::new().level(Lint::DeadCode, LintLevel::Deny))
+            .eval(EvalOptions::new().step_limit(10));
+        let configured = Driver::new(session).compile_file(root.join("main.mumbo")).unwrap();
+        let again = Driver::new(session).compile_file(root.join("main.mumbo")).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let messages = |compiled: &Compilation| -> Vec<_> {
+            compiled.diagnostics.iter().map(|(_, d)| (d.severity, d.message.clone())).collect()
+        };
+        // `é` is no identifier by default, which stops the file before type checking
+        assert_eq!(
+            messages(&default),
+            [
+                (Severity::Warning, "`a` shadows an earlier declaration".into()),
+                (Severity::Error, "invalid character".into()),
+                (Severity::Error, "expected identifier, found `=`".into()),
+                (Severity::Error, "invalid character".into()),
+                (Severity::Error, "expected an expression, found `;`".into()),
+            ]
+        );
+        assert_eq!(
+            messages(&configured),
+            [
+                (Severity::Error, "function `f` is never called".into()),
+                (Severity::Error, "compiletime block didn't finish within 10 steps".into()),
+            ]
+        );
+        assert_eq!(messages(&again), messages(&configured));
+    }
+
+    #[test]
+    fn directories_are_compiled_as_roots() {
+        let root = project("dir", &[("b.mumbo", "mod a;\nimport a.f;"), ("a.mumbo", "fn f() {}"), ("notes.txt", "x")]);
+        let compiled = compile_dir(&root).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        // `a` is already loaded as a root when `b` declares it, `f` is used through the import
+        assert_eq!(compiled.files.len(), 2);
+        assert!(compiled.diagnostics.is_empty(), "{}", compiled.render(false));
+    }
+
+    struct Logged {
+        name: &'static str,
+        after: &'static [&'static str],
+        log: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Pass for Logged {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn after(&self) -> &[&str] {
+            self.after
+        }
+
+        fn run(&self, cx: &mut PassContext<'_, '_>) {
+            self.log.borrow_mut().push(format!("{} {}", self.name, cx.files.file(cx.file).path().ends_with("b.mumbo")));
+            if self.name == "count" {
+                cx.report(Diagnostic::warning(format!("{} bodies", cx.program.bodies.len())));
+            }
+        }
+    }
+
+    #[test]
+    fn passes_run_in_order() {
+        let root = project("passes", &[("b.mumbo", "fn f() {}
+f();"), ("c.mumbo", "let x: u8 = true;\nx;")]);
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let pass = |name, after| Logged {
+            name,
+            after,
+            log: Rc::clone(&log),
+        };
+        let compiled = Driver::new(Session::new())
+            .pass(pass("count", &["first", "missing"]))
+            .pass(pass("first", &[]))
+            .pass(pass("cycle", &["cycle"]))
+            .compile_dir(&root)
+            .unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        // `c` has a type error, so no pass runs on it
+        assert_eq!(*log.borrow(), ["first true", "count true", "cycle true"]);
+        let messages: Vec<_> = compiled.diagnostics.iter().map(|(_, d)| d.message.as_str()).collect();
+        let mismatch = "mismatched types: expected `u8`, found `bool`";
+        assert_eq!(messages, ["2 bodies", mismatch], "{}", compiled.render(false));
+    }
+
+    /// jumps out of the first function.
+    struct Broken;
+
+    impl Pass for Broken {
+        fn name(&self) -> &str {
+            "broken"
+        }
+
+        fn run(&self, cx: &mut PassContext<'_, '_>) {
+            cx.program.bodies[1].blocks[0].terminator.kind = TerminatorKind::Goto(BlockId::new(99));
+        }
+    }
+
+    #[test]
+    fn passes_are_verified() {
+        let root = project("verify", &[("main.mumbo", "fn f() {}\nf();")]);
+        let verified = Driver::new(Session::new().verify(true)).pass(Broken).compile_dir(&root).unwrap();
+        let unverified = Driver::new(Session::new().verify(false)).pass(Broken).compile_dir(&root).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        let rendered = verified.render(false);
+        assert_eq!(verified.error_count(), 1, "{rendered}");
+        let error = "error[E0703]: internal compiler error: `bb99` is not a block of `fn1`";
+        assert!(rendered.contains(error), "{rendered}");
+        assert!(rendered.contains("= note: found after the pass `broken`"), "{rendered}");
+        assert!(unverified.diagnostics.is_empty(), "{}", unverified.render(false));
+    }
+}
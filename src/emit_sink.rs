@@ -0,0 +1,194 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// destination for generated output files, so backends don't have to know whether
+/// they are writing to disk, to memory or into an archive.
+pub trait EmitSink {
+    /// writes one generated file. `path` is relative and `/`-separated.
+    fn emit_file(&mut self, path: &str, contents: &[u8]) -> io::Result<()>;
+
+    /// called once after the last file has been emitted.
+    #[inline]
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// writes files below a root directory, creating parent directories as needed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DirectorySink {
+    root: PathBuf,
+}
+
+impl DirectorySink {
+    #[inline]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        DirectorySink { root: root.into() }
+    }
+}
+
+impl EmitSink for DirectorySink {
+    fn emit_file(&mut self, path: &str, contents: &[u8]) -> io::Result<()> {
+        let full = self.root.join(path);
+        if let Some(parent) = full.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(full, contents)
+    }
+}
+
+/// keeps every emitted file in memory, in emission order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct MemorySink {
+    pub files: Vec<(String, Vec<u8>)>,
+}
+
+impl MemorySink {
+    #[inline]
+    pub const fn new() -> Self {
+        MemorySink { files: Vec::new() }
+    }
+
+    /// contents of the most recent file emitted under `path`.
+    #[inline]
+    pub fn get(&self, path: &str) -> Option<&[u8]> {
+        self.files.iter().rev().find(|(name, _)| name == path).map(|(_, contents)| &contents[..])
+    }
+}
+
+impl EmitSink for MemorySink {
+    #[inline]
+    fn emit_file(&mut self, path: &str, contents: &[u8]) -> io::Result<()> {
+        self.files.push((path.to_string(), contents.to_vec()));
+        Ok(())
+    }
+}
+
+const TAR_BLOCK: usize = 512;
+
+/// streams files into a ustar archive. entries carry a zero mtime and fixed
+/// ownership so equal inputs produce byte-identical archives.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TarSink<W: Write> {
+    out: W,
+}
+
+impl<W: Write> TarSink<W> {
+    #[inline]
+    pub const fn new(out: W) -> Self {
+        TarSink { out }
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> EmitSink for TarSink<W> {
+    fn emit_file(&mut self, path: &str, contents: &[u8]) -> io::Result<()> {
+        // the plain ustar name field is all we support, no prefix splitting
+        if path.is_empty() || path.len() > 100 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "tar entry names must be between 1 and 100 bytes",
+            ));
+        }
+
+        let mut header = [0u8; TAR_BLOCK];
+        header[..path.len()].copy_from_slice(path.as_bytes());
+        write_octal(&mut header[100..108], 0o644);
+        write_octal(&mut header[108..116], 0);
+        write_octal(&mut header[116..124], 0);
+        write_octal(&mut header[124..136], contents.len() as u64);
+        write_octal(&mut header[136..148], 0);
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+
+        // the checksum is computed with its own field filled with spaces
+        header[148..156].fill(b' ');
+        let checksum: u32 = header.iter().map(|&b| b as u32).sum();
+        write_octal(&mut header[148..155], checksum as u64);
+
+        self.out.write_all(&header)?;
+        self.out.write_all(contents)?;
+        let padding = (TAR_BLOCK - contents.len() % TAR_BLOCK) % TAR_BLOCK;
+        self.out.write_all(&[0u8; TAR_BLOCK][..padding])
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        // end of archive is marked by two zeroed blocks
+        self.out.write_all(&[0u8; TAR_BLOCK * 2])?;
+        self.out.flush()
+    }
+}
+
+/// zero-padded octal digits followed by a nul byte, filling `field`.
+fn write_octal(field: &mut [u8], mut value: u64) {
+    let digits = field.len() - 1;
+    field[digits] = 0;
+    for index in (0..digits).rev() {
+        field[index] = b'0' + (value & 7) as u8;
+        value >>= 3;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DirectorySink, EmitSink, MemorySink, TAR_BLOCK, TarSink};
+
+    fn parse_octal(field: &[u8]) -> u64 {
+        field
+            .iter()
+            .take_while(|&&b| b.is_ascii_digit())
+            .fold(0, |acc, &b| acc * 8 + (b - b'0') as u64)
+    }
+
+    #[test]
+    fn memory_sink_keeps_files() {
+        let mut sink = MemorySink::new();
+        sink.emit_file("main.c", b"int main(void) { return 0; }\n").unwrap();
+        sink.emit_file("mumbo_rt.h", b"#pragma once\n").unwrap();
+        sink.finish().unwrap();
+
+        assert_eq!(sink.files.len(), 2);
+        assert_eq!(sink.get("mumbo_rt.h"), Some(&b"#pragma once\n"[..]));
+        assert_eq!(sink.get("missing.c"), None);
+    }
+
+    #[test]
+    fn tar_sink_writes_valid_ustar() {
+        let mut sink = TarSink::new(Vec::new());
+        sink.emit_file("out/main.c", b"hello").unwrap();
+        sink.finish().unwrap();
+        let archive = sink.into_inner();
+
+        assert_eq!(archive.len(), TAR_BLOCK * 4);
+        let header = &archive[..TAR_BLOCK];
+        assert_eq!(&header[..10], b"out/main.c");
+        assert_eq!(parse_octal(&header[124..136]), 5);
+        assert_eq!(&header[257..263], b"ustar\0");
+
+        let mut unsummed = header.to_vec();
+        unsummed[148..156].fill(b' ');
+        let expected: u64 = unsummed.iter().map(|&b| b as u64).sum();
+        assert_eq!(parse_octal(&header[148..156]), expected);
+
+        assert_eq!(&archive[TAR_BLOCK..TAR_BLOCK + 5], b"hello");
+        assert!(archive[TAR_BLOCK + 5..].iter().all(|&b| b == 0));
+
+        let mut sink = TarSink::new(Vec::new());
+        assert!(sink.emit_file(&"a".repeat(101), b"").is_err());
+    }
+
+    #[test]
+    fn directory_sink_creates_parents() {
+        let root = std::env::temp_dir().join(format!("mumbo_emit_sink_{}", std::process::id()));
+        let mut sink = DirectorySink::new(&root);
+        sink.emit_file("nested/dir/main.c", b"/* c */").unwrap();
+        assert_eq!(std::fs::read(root.join("nested/dir/main.c")).unwrap(), b"/* c */");
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
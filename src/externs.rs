@@ -0,0 +1,332 @@
+//! the registry of `extern fn` declarations: the foreign functions a program calls and the
+//! functions it exports, for the interpreter and code generation to link against.
+//!
+//! the calling convention comes from an `#[extern("C")]` attribute, `C` without one. a
+//! function may be declared any number of times, in any block, as long as every declaration
+//! has the same signature and calling convention. a symbol has one signature no matter where
+//! it is declared. at most one declaration has a body, which defines and exports the function.
+//!
+//! the pass runs after type checking and takes the signatures from `Checked::fn_types`.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::ast::visit::{Visitor, walk_item, walk_stmts};
+use crate::ast::{AttrArg, FnDecl, Ident, Item, ItemKind, Stmt};
+use crate::ty::TyId;
+use crate::typeck::Checked;
+use crate::types::{Span, Token};
+
+/// how arguments and the return value are passed, named by `#[extern("...")]`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CallConv {
+    /// the C convention of the target, `"C"`.
+    #[default]
+    C,
+    /// the convention of the operating system's own interfaces, `"system"`. the same as `C`
+    /// everywhere except 32 bit windows.
+    System,
+}
+
+impl CallConv {
+    pub const ALL: &[CallConv] = &[CallConv::C, CallConv::System];
+
+    /// how the attribute spells it.
+    #[inline]
+    pub const fn name(self) -> &'static str {
+        match self {
+            CallConv::C => "C",
+            CallConv::System => "system",
+        }
+    }
+
+    #[inline]
+    pub fn from_name(name: &[u8]) -> Option<CallConv> {
+        CallConv::ALL.iter().copied().find(|conv| conv.name().as_bytes() == name)
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ExternErrorKind {
+    /// a declaration with another signature than the earlier one at `previous`.
+    SignatureMismatch { previous: Span },
+    /// like `SignatureMismatch`, for the calling convention.
+    CallConvMismatch { previous: Span },
+    /// a second body for the function, `previous` is the first one's declaration.
+    DuplicateDefinition { previous: Span },
+    /// an `#[extern]` attribute that doesn't name one of `CallConv::ALL`.
+    UnknownCallConv,
+    /// an `#[extern]` attribute on a function declared without `extern`.
+    NotExtern,
+}
+
+impl ExternErrorKind {
+    /// stable identifier like `ParseErrorKind::code`, registry codes start at `E0501`.
+    #[inline]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            ExternErrorKind::SignatureMismatch { .. } => "E0501",
+            ExternErrorKind::CallConvMismatch { .. } => "E0502",
+            ExternErrorKind::DuplicateDefinition { .. } => "E0503",
+            ExternErrorKind::UnknownCallConv => "E0504",
+            ExternErrorKind::NotExtern => "E0505",
+        }
+    }
+}
+
+/// `span` covers the name of the declaration, or the attribute for attribute errors.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExternError<'source> {
+    pub kind: ExternErrorKind,
+    pub name: &'source str,
+    pub span: Span,
+}
+
+impl ExternError<'_> {
+    #[inline]
+    pub const fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+}
+
+impl fmt::Display for ExternError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ExternErrorKind::SignatureMismatch { .. } => {
+                write!(f, "`{}` is declared again with a different signature", self.name)
+            }
+            ExternErrorKind::CallConvMismatch { .. } => {
+                write!(f, "`{}` is declared again with a different calling convention", self.name)
+            }
+            ExternErrorKind::DuplicateDefinition { .. } => write!(f, "`{}` is defined more than once", self.name),
+            ExternErrorKind::UnknownCallConv => f.write_str("`extern` takes one calling convention, like `\"C\"`"),
+            ExternErrorKind::NotExtern => write!(f, "`{}` has a calling convention but isn't `extern`", self.name),
+        }
+    }
+}
+
+/// an `extern fn`, merged from all of its declarations.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExternFn<'source> {
+    /// the name of the first declaration.
+    pub name: Ident<'source>,
+    /// a `TyKind::Fn` in the table of the `Checked` the registry was built from.
+    pub ty: TyId,
+    pub conv: CallConv,
+    /// the name of the declaration with a body, `None` for a foreign function.
+    pub definition: Option<Span>,
+}
+
+/// every `extern fn` of a program by name, in the order they were first declared.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExternRegistry<'source> {
+    fns: Vec<ExternFn<'source>>,
+}
+
+impl<'source> ExternRegistry<'source> {
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<&ExternFn<'source>> {
+        self.fns.iter().find(|f| f.name.name == name)
+    }
+
+    #[inline]
+    pub fn iter(&self) -> core::slice::Iter<'_, ExternFn<'source>> {
+        self.fns.iter()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.fns.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.fns.is_empty()
+    }
+
+    /// adds a declaration, merging it into an earlier one of the same name. the earlier one is
+    /// kept as it was when they don't agree.
+    pub fn declare(&mut self, decl: ExternFn<'source>) -> Result<(), ExternErrorKind> {
+        let Some(existing) = self.fns.iter_mut().find(|f| f.name.name == decl.name.name) else {
+            self.fns.push(decl);
+            return Ok(());
+        };
+        let previous = existing.name.span;
+        // an unknown type in either signature has been reported by the type checker
+        if existing.ty != decl.ty && existing.ty != TyId::ERROR && decl.ty != TyId::ERROR {
+            return Err(ExternErrorKind::SignatureMismatch { previous });
+        }
+        if existing.conv != decl.conv {
+            return Err(ExternErrorKind::CallConvMismatch { previous });
+        }
+        match (existing.definition, decl.definition) {
+            (Some(previous), Some(_)) => Err(ExternErrorKind::DuplicateDefinition { previous }),
+            (None, definition) => {
+                existing.definition = definition;
+                Ok(())
+            }
+            (Some(_), None) => Ok(()),
+        }
+    }
+}
+
+impl<'a, 'source> IntoIterator for &'a ExternRegistry<'source> {
+    type Item = &'a ExternFn<'source>;
+    type IntoIter = core::slice::Iter<'a, ExternFn<'source>>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// the outcome of `register`, errors are in source order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Registered<'source> {
+    pub registry: ExternRegistry<'source>,
+    pub errors: Vec<ExternError<'source>>,
+}
+
+/// collects the `extern fn` declarations of a whole program, `checked` is what type checking
+/// returned for it.
+pub fn register<'source>(stmts: &[Stmt<'source>], checked: &Checked<'source>) -> Registered<'source> {
+    let mut registrar = Registrar {
+        checked,
+        out: Registered::default(),
+    };
+    walk_stmts(&mut registrar, stmts);
+    registrar.out
+}
+
+struct Registrar<'c, 'source> {
+    checked: &'c Checked<'source>,
+    out: Registered<'source>,
+}
+
+impl<'source> Registrar<'_, 'source> {
+    /// the calling convention `item` asks for, `None` after reporting a bad attribute.
+    fn conv(&mut self, item: &Item<'source>, name: &'source str) -> Option<CallConv> {
+        let Some(attr) = item.attr("extern") else { return Some(CallConv::default()) };
+        let conv = match attr.args[..] {
+            [AttrArg::Literal(literal)] if literal.value.token() == Token::LitStr => {
+                CallConv::from_name(literal.value.raw())
+            }
+            _ => None,
+        };
+        if conv.is_none() {
+            self.error(ExternErrorKind::UnknownCallConv, name, attr.span);
+        }
+        conv
+    }
+
+    fn declare(&mut self, item: &Item<'source>, decl: &FnDecl<'source>) {
+        let name = decl.name;
+        let Some(conv) = self.conv(item, name.name) else { return };
+        let fn_types = &self.checked.fn_types;
+        let ty = fn_types.iter().find(|(span, _)| *span == name.span).map_or(TyId::ERROR, |&(_, ty)| ty);
+        let definition = decl.body.as_ref().map(|_| name.span);
+        let extern_fn = ExternFn {
+            name,
+            ty,
+            conv,
+            definition,
+        };
+        if let Err(kind) = self.out.registry.declare(extern_fn) {
+            self.error(kind, name.name, name.span);
+        }
+    }
+
+    #[inline]
+    fn error(&mut self, kind: ExternErrorKind, name: &'source str, span: Span) {
+        self.out.errors.push(ExternError { kind, name, span });
+    }
+}
+
+impl<'ast, 'source: 'ast> Visitor<'ast, 'source> for Registrar<'_, 'source> {
+    fn visit_item(&mut self, item: &'ast Item<'source>) {
+        if let ItemKind::Fn(decl) = &item.kind {
+            match item.attr("extern") {
+                _ if decl.is_extern => self.declare(item, decl),
+                Some(attr) => self.error(ExternErrorKind::NotExtern, decl.name.name, attr.span),
+                None => {}
+            }
+        }
+        walk_item(self, item);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use super::{CallConv, ExternError, ExternErrorKind, Registered, register};
+    use crate::parser::tests::parse_ok;
+    use crate::ty::{TyId, TyKind};
+    use crate::typeck::{Checked, check};
+    use crate::types::Span;
+
+    fn registered(text: &str) -> (Registered<'_>, Checked<'_>) {
+        let stmts = parse_ok(text);
+        let checked = check(stmts);
+        (register(stmts, &checked), checked)
+    }
+
+    fn error(kind: ExternErrorKind, name: &str, span: Span) -> ExternError<'_> {
+        ExternError { kind, name, span }
+    }
+
+    #[test]
+    fn declarations_are_collected() {
+        let (registered, checked) = registered(
+            "extern fn puts(let s: *u8) -> i32;\n#[extern(\"system\")] extern fn exit(let code: i32);\n\
+             extern fn hello() -> u8 { 1 }\nfn local() {}\nfn f() { extern fn puts(let s: *u8) -> i32; }",
+        );
+        assert_eq!(registered.errors, []);
+        let names: Vec<_> = registered.registry.iter().map(|f| (f.name.name, f.conv, f.definition)).collect();
+        assert_eq!(
+            names,
+            [
+                ("puts", CallConv::C, None),
+                ("exit", CallConv::System, None),
+                ("hello", CallConv::C, Some(Span::new(96, 101))),
+            ]
+        );
+        let puts = registered.registry.get("puts").unwrap();
+        let TyKind::Fn { params, ret } = checked.tys.kind(puts.ty) else { panic!() };
+        assert!(matches!(checked.tys.kind(params[0]), TyKind::Pointer { pointee: TyId::U8, .. }));
+        assert_eq!(*ret, TyId::I32);
+        assert!(registered.registry.get("local").is_none());
+    }
+
+    #[test]
+    fn conflicting_declarations_are_reported() {
+        let (registered, _) = registered(
+            "extern fn a(let x: u8);\nextern fn a(let x: u16);\n#[extern(\"system\")] extern fn a(let x: u8);\n\
+             extern fn b() {}\nextern fn b();\nextern fn b() {}\n\
+             #[extern(\"pascal\")] extern fn c();\n#[extern(C)] extern fn d();\n#[extern(\"C\")] fn e() {}",
+        );
+        let first = |start| Span::new(start, start + 1);
+        assert_eq!(
+            registered.errors,
+            [
+                error(ExternErrorKind::SignatureMismatch { previous: first(10) }, "a", first(34)),
+                error(ExternErrorKind::CallConvMismatch { previous: first(10) }, "a", first(79)),
+                error(ExternErrorKind::DuplicateDefinition { previous: first(103) }, "b", first(135)),
+                error(ExternErrorKind::UnknownCallConv, "c", Span::new(142, 161)),
+                error(ExternErrorKind::UnknownCallConv, "d", Span::new(177, 189)),
+                error(ExternErrorKind::NotExtern, "e", Span::new(205, 219)),
+            ]
+        );
+        assert_eq!(registered.errors[0].to_string(), "`a` is declared again with a different signature");
+        assert_eq!(registered.errors[0].code(), "E0501");
+        assert_eq!(registered.registry.len(), 2);
+        assert_eq!(registered.registry.get("b").unwrap().definition, Some(first(103)));
+    }
+}
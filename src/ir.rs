@@ -0,0 +1,469 @@
+//! the mid-level intermediate representation: every function as a graph of basic blocks
+//! holding simple statements, lowered from a checked syntax tree by `lower`.
+//!
+//! a `Body` numbers its locals, `_0` holds the return value, the parameters come next and
+//! then `let` bindings, `match` bindings and temporaries. a statement assigns one `Rvalue`,
+//! a single operation on operands that are constants or copies of places, so a nested
+//! expression becomes a sequence of temporaries. a `Place` is a local followed by field,
+//! index and dereference projections.
+//!
+//! control flow is explicit: every block ends in a `Terminator` jumping to other blocks.
+//! `if`, loops, `break`, `continue` and `return` become jumps and branches, a `match` tests
+//! its arms in order by comparing the scrutinee with each pattern.
+//!
+//! types are the `TyId`s of the `Checked` the program was lowered with. a value nothing made
+//! concrete keeps the unsuffixed type of its literal, backends pick the default `typeck` does.
+
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+use crate::ast::{BinaryOp, Ident, UnaryOp};
+use crate::literal_value::Literal;
+use crate::ty::{TyId, TyTable};
+use crate::types::{Span, Token};
+
+pub mod cfg;
+mod lower;
+
+pub use lower::lower;
+
+macro_rules! index_type {
+    ($(#[$attr:meta])* $name:ident, $prefix:literal) => {
+        $(#[$attr])*
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name(u32);
+
+        impl $name {
+            #[inline]
+            pub const fn new(index: usize) -> Self {
+                $name(index as u32)
+            }
+
+            #[inline]
+            pub const fn index(self) -> usize {
+                self.0 as usize
+            }
+        }
+
+        impl fmt::Display for $name {
+            #[inline]
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, concat!($prefix, "{}"), self.0)
+            }
+        }
+    };
+}
+
+index_type!(
+    /// a function of a `Program`, `fn0` is the top-level code.
+    BodyId,
+    "fn"
+);
+index_type!(
+    /// a local of a `Body`, written `_1`.
+    LocalId,
+    "_"
+);
+index_type!(
+    /// a basic block of a `Body`, written `bb1`.
+    BlockId,
+    "bb"
+);
+
+impl BodyId {
+    /// the statements outside of any function, run in order.
+    pub const TOP_LEVEL: BodyId = BodyId(0);
+}
+
+impl LocalId {
+    /// where a body leaves its return value.
+    pub const RETURN: LocalId = LocalId(0);
+}
+
+impl BlockId {
+    /// where a body starts.
+    pub const ENTRY: BlockId = BlockId(0);
+}
+
+/// a lowered program, see the module docs.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program<'source> {
+    /// every function in the order they were declared, nested ones included, after the
+    /// top-level code at `BodyId::TOP_LEVEL`.
+    pub bodies: Vec<Body<'source>>,
+}
+
+impl<'source> Program<'source> {
+    #[inline]
+    pub fn body(&self, id: BodyId) -> &Body<'source> {
+        &self.bodies[id.index()]
+    }
+
+    /// the program as text, one body after the other, for debugging and tests.
+    #[inline]
+    pub fn display<'a>(&'a self, tys: &'a TyTable<'source>) -> ProgramDisplay<'a, 'source> {
+        ProgramDisplay { program: self, tys }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Body<'source> {
+    /// `None` for the top-level code.
+    pub name: Option<Ident<'source>>,
+    pub is_extern: bool,
+    /// the parameters are the locals `_1` to `_n`.
+    pub param_count: usize,
+    pub locals: Vec<LocalDecl<'source>>,
+    /// starts at `BlockId::ENTRY`, empty for a function declared without a body.
+    pub blocks: Vec<BasicBlock<'source>>,
+    pub span: Span,
+}
+
+impl<'source> Body<'source> {
+    #[inline]
+    pub fn local(&self, id: LocalId) -> &LocalDecl<'source> {
+        &self.locals[id.index()]
+    }
+
+    #[inline]
+    pub fn block(&self, id: BlockId) -> &BasicBlock<'source> {
+        &self.blocks[id.index()]
+    }
+
+    #[inline]
+    pub fn params(&self) -> impl Iterator<Item = LocalId> {
+        (1..=self.param_count).map(LocalId::new)
+    }
+
+    #[inline]
+    pub fn ret(&self) -> TyId {
+        self.locals[LocalId::RETURN.index()].ty
+    }
+
+    /// the blocks together with their ids.
+    #[inline]
+    pub fn iter_blocks(&self) -> impl Iterator<Item = (BlockId, &BasicBlock<'source>)> {
+        self.blocks.iter().enumerate().map(|(index, block)| (BlockId::new(index), block))
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LocalDecl<'source> {
+    /// `None` for the return value and temporaries.
+    pub name: Option<&'source str>,
+    pub ty: TyId,
+    pub span: Span,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock<'source> {
+    pub statements: Vec<Statement<'source>>,
+    pub terminator: Terminator<'source>,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement<'source> {
+    pub kind: StatementKind<'source>,
+    pub span: Span,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatementKind<'source> {
+    Assign(Place<'source>, Rvalue<'source>),
+    /// evaluated for its effects, a call whose value isn't used.
+    Eval(Rvalue<'source>),
+}
+
+/// a local or a part of one.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Place<'source> {
+    pub local: LocalId,
+    /// applied in order, `_1.x.*` is the value `_1.x` points to.
+    pub projection: Vec<Projection<'source>>,
+}
+
+impl<'source> Place<'source> {
+    #[inline]
+    pub const fn local(local: LocalId) -> Self {
+        Place {
+            local,
+            projection: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn project(mut self, projection: Projection<'source>) -> Self {
+        self.projection.push(projection);
+        self
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Projection<'source> {
+    /// a struct field, union member or tuple element, `0` for the first.
+    Field(&'source str),
+    /// an array element or the element a pointer is offset to.
+    Index(LocalId),
+    Deref,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand<'source> {
+    Copy(Place<'source>),
+    Const(Constant<'source>),
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Constant<'source> {
+    Literal(Literal<'source>),
+    /// `true` and `false`, which are identifiers rather than literals.
+    Bool(bool),
+    /// an enum variant by its discriminant.
+    Variant { name: &'source str, discriminant: u32 },
+    /// a `usize` the compiler knows, like `E::variant_count` or an index into `E::ALL`.
+    Usize(u64),
+    Fn(BodyId),
+}
+
+/// one operation, see the module docs.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rvalue<'source> {
+    Use(Operand<'source>),
+    /// never `Deref`, which is a projection.
+    Unary(UnaryOp, Operand<'source>),
+    Binary(BinaryOp, Operand<'source>, Operand<'source>),
+    Cast(Operand<'source>, TyId),
+    Call {
+        callee: Operand<'source>,
+        args: Vec<Operand<'source>>,
+    },
+    /// a struct literal, or a union literal with its single member, in the order written.
+    Struct {
+        name: &'source str,
+        fields: Vec<(&'source str, Operand<'source>)>,
+    },
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Terminator<'source> {
+    pub kind: TerminatorKind<'source>,
+    pub span: Span,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum TerminatorKind<'source> {
+    Goto(BlockId),
+    /// `cond` is a `bool`.
+    Branch {
+        cond: Operand<'source>,
+        then_block: BlockId,
+        else_block: BlockId,
+    },
+    /// returns the value in `_0`.
+    Return,
+    /// never reached, like the end of a `match` that no arm matched.
+    Unreachable,
+}
+
+impl TerminatorKind<'_> {
+    /// the blocks control continues in, in order.
+    #[inline]
+    pub fn successors(&self) -> impl Iterator<Item = BlockId> + use<> {
+        let targets = match *self {
+            TerminatorKind::Goto(target) => [Some(target), None],
+            TerminatorKind::Branch {
+                then_block, else_block, ..
+            } => [Some(then_block), Some(else_block)],
+            TerminatorKind::Return | TerminatorKind::Unreachable => [None, None],
+        };
+        targets.into_iter().flatten()
+    }
+}
+
+/// see `Program::display`.
+#[derive(Clone, Copy)]
+pub struct ProgramDisplay<'a, 'source> {
+    program: &'a Program<'source>,
+    tys: &'a TyTable<'source>,
+}
+
+impl fmt::Display for ProgramDisplay<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, body) in self.program.bodies.iter().enumerate() {
+            if index > 0 {
+                f.write_char('\n')?;
+            }
+            self.body(f, body)?;
+        }
+        Ok(())
+    }
+}
+
+impl ProgramDisplay<'_, '_> {
+    fn body(&self, f: &mut fmt::Formatter<'_>, body: &Body<'_>) -> fmt::Result {
+        let name = body.name.map_or("{top level}", |name| name.name);
+        write!(f, "{}fn {}(", if body.is_extern { "extern " } else { "" }, name)?;
+        for (index, param) in body.params().enumerate() {
+            if index > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}: {}", param, self.tys.display(body.local(param).ty))?;
+        }
+        write!(f, ") -> {}", self.tys.display(body.ret()))?;
+        if body.blocks.is_empty() {
+            return f.write_str(";\n");
+        }
+        f.write_str(" {\n")?;
+        for (index, local) in body.locals.iter().enumerate() {
+            if (1..=body.param_count).contains(&index) {
+                continue;
+            }
+            write!(f, "    let {}: {};", LocalId::new(index), self.tys.display(local.ty))?;
+            match local.name {
+                Some(name) => writeln!(f, " // {}", name)?,
+                None => f.write_char('\n')?,
+            }
+        }
+        for (id, block) in body.iter_blocks() {
+            writeln!(f, "{}:", id)?;
+            for statement in &block.statements {
+                f.write_str("    ")?;
+                match &statement.kind {
+                    StatementKind::Assign(place, rvalue) => {
+                        self.place(f, place)?;
+                        f.write_str(" = ")?;
+                        self.rvalue(f, rvalue)?;
+                    }
+                    StatementKind::Eval(rvalue) => self.rvalue(f, rvalue)?,
+                }
+                f.write_str(";\n")?;
+            }
+            f.write_str("    ")?;
+            match &block.terminator.kind {
+                TerminatorKind::Goto(target) => write!(f, "goto {}", target)?,
+                TerminatorKind::Branch {
+                    cond,
+                    then_block,
+                    else_block,
+                } => {
+                    f.write_str("branch ")?;
+                    self.operand(f, cond)?;
+                    write!(f, ", {}, {}", then_block, else_block)?;
+                }
+                TerminatorKind::Return => f.write_str("return")?,
+                TerminatorKind::Unreachable => f.write_str("unreachable")?,
+            }
+            f.write_str(";\n")?;
+        }
+        f.write_str("}\n")
+    }
+
+    fn place(&self, f: &mut fmt::Formatter<'_>, place: &Place<'_>) -> fmt::Result {
+        write!(f, "{}", place.local)?;
+        for projection in &place.projection {
+            match projection {
+                Projection::Field(field) => write!(f, ".{}", field)?,
+                Projection::Index(index) => write!(f, "[{}]", index)?,
+                Projection::Deref => f.write_str(".*")?,
+            }
+        }
+        Ok(())
+    }
+
+    fn operand(&self, f: &mut fmt::Formatter<'_>, operand: &Operand<'_>) -> fmt::Result {
+        match operand {
+            Operand::Copy(place) => self.place(f, place),
+            Operand::Const(Constant::Literal(literal)) => {
+                let raw = core::str::from_utf8(literal.raw()).map_err(|_| fmt::Error)?;
+                match literal.token() {
+                    Token::LitStr => write!(f, "\"{}\"", raw),
+                    Token::LitChar => write!(f, "'{}'", raw),
+                    token if raw.is_empty() => f.write_str(token.source_repr()),
+                    _ => f.write_str(raw),
+                }
+            }
+            Operand::Const(Constant::Bool(value)) => write!(f, "{}", value),
+            Operand::Const(Constant::Variant { name, discriminant }) => write!(f, "{}#{}", name, discriminant),
+            Operand::Const(Constant::Usize(value)) => write!(f, "{}usize", value),
+            Operand::Const(Constant::Fn(id)) => match self.program.body(*id).name {
+                Some(name) => f.write_str(name.name),
+                None => write!(f, "{}", id),
+            },
+        }
+    }
+
+    fn rvalue(&self, f: &mut fmt::Formatter<'_>, rvalue: &Rvalue<'_>) -> fmt::Result {
+        match rvalue {
+            Rvalue::Use(operand) => self.operand(f, operand),
+            Rvalue::Unary(op, operand) => {
+                write!(f, "{}", op)?;
+                self.operand(f, operand)
+            }
+            Rvalue::Binary(op, lhs, rhs) => {
+                self.operand(f, lhs)?;
+                write!(f, " {} ", op)?;
+                self.operand(f, rhs)
+            }
+            Rvalue::Cast(operand, ty) => {
+                self.operand(f, operand)?;
+                write!(f, " cast {}", self.tys.display(*ty))
+            }
+            Rvalue::Call { callee, args } => {
+                self.operand(f, callee)?;
+                f.write_char('(')?;
+                for (index, arg) in args.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    self.operand(f, arg)?;
+                }
+                f.write_char(')')
+            }
+            Rvalue::Struct { name, fields } => {
+                write!(f, "{} {{", name)?;
+                for (index, (field, value)) in fields.iter().enumerate() {
+                    f.write_str(if index > 0 { ", " } else { " " })?;
+                    write!(f, "{}: ", field)?;
+                    self.operand(f, value)?;
+                }
+                f.write_str(if fields.is_empty() { "}" } else { " }" })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use super::{BlockId, BodyId, LocalId, Operand, Place, Projection, TerminatorKind};
+
+    #[test]
+    fn ids_and_successors() {
+        assert_eq!((LocalId::RETURN.to_string(), BlockId::new(3).to_string()), ("_0".into(), "bb3".into()));
+        assert_eq!(BodyId::TOP_LEVEL.index(), 0);
+        let branch = TerminatorKind::Branch {
+            cond: Operand::Copy(Place::local(LocalId::new(1)).project(Projection::Deref)),
+            then_block: BlockId::new(2),
+            else_block: BlockId::ENTRY,
+        };
+        assert_eq!(branch.successors().collect::<Vec<_>>(), [BlockId::new(2), BlockId::ENTRY]);
+        assert_eq!(TerminatorKind::Goto(BlockId::new(1)).successors().count(), 1);
+        assert_eq!(TerminatorKind::Return.successors().count(), 0);
+    }
+}
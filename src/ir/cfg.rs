@@ -0,0 +1,266 @@
+//! the control-flow graph of a `Body`: the successors and predecessors of every block, which
+//! blocks the entry reaches and who dominates whom.
+//!
+//! a block `a` dominates `b` when every path from the entry to `b` goes through `a`, every
+//! block dominates itself. dominators are computed with the iterative algorithm of Cooper,
+//! Harvey and Kennedy over the reverse postorder. blocks the entry doesn't reach, like the
+//! ones after a `return`, have no dominators and are in no dominance frontier.
+
+use alloc::vec::Vec;
+
+use crate::ir::{BlockId, Body};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cfg {
+    successors: Vec<Vec<BlockId>>,
+    predecessors: Vec<Vec<BlockId>>,
+    /// the reachable blocks, every block before its successors except along back edges.
+    reverse_postorder: Vec<BlockId>,
+    /// the immediate dominator of each block, the entry is its own and `None` is unreachable.
+    idoms: Vec<Option<BlockId>>,
+    frontiers: Vec<Vec<BlockId>>,
+}
+
+impl Cfg {
+    pub fn new(body: &Body<'_>) -> Self {
+        let len = body.blocks.len();
+        let successors: Vec<Vec<BlockId>> =
+            body.blocks.iter().map(|block| block.terminator.kind.successors().collect()).collect();
+        let mut predecessors = alloc::vec![Vec::new(); len];
+        for (block, targets) in successors.iter().enumerate() {
+            for target in targets {
+                let from = &mut predecessors[target.index()];
+                // both edges of a branch to the same block count once
+                if from.last() != Some(&BlockId::new(block)) {
+                    from.push(BlockId::new(block));
+                }
+            }
+        }
+        let reverse_postorder = reverse_postorder(&successors);
+        let idoms = dominators(&predecessors, &reverse_postorder, len);
+        let mut cfg = Cfg {
+            successors,
+            predecessors,
+            reverse_postorder,
+            idoms,
+            frontiers: Vec::new(),
+        };
+        cfg.frontiers = cfg.frontiers();
+        cfg
+    }
+
+    /// the number of blocks, reachable or not.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.successors.len()
+    }
+
+    /// only for functions declared without a body.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.successors.is_empty()
+    }
+
+    /// the blocks `block` jumps to, in the order of its terminator.
+    #[inline]
+    pub fn successors(&self, block: BlockId) -> &[BlockId] {
+        &self.successors[block.index()]
+    }
+
+    /// the blocks jumping to `block`, in the order of their ids.
+    #[inline]
+    pub fn predecessors(&self, block: BlockId) -> &[BlockId] {
+        &self.predecessors[block.index()]
+    }
+
+    #[inline]
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        self.idoms[block.index()].is_some()
+    }
+
+    /// the reachable blocks, starting with the entry. a block comes before its successors
+    /// unless the edge between them closes a loop.
+    #[inline]
+    pub fn reverse_postorder(&self) -> &[BlockId] {
+        &self.reverse_postorder
+    }
+
+    /// the closest block dominating `block` other than itself, `None` for the entry and
+    /// unreachable blocks.
+    #[inline]
+    pub fn immediate_dominator(&self, block: BlockId) -> Option<BlockId> {
+        self.idoms[block.index()].filter(|&idom| idom != block)
+    }
+
+    /// whether every path from the entry to `block` goes through `dominator`.
+    pub fn dominates(&self, dominator: BlockId, block: BlockId) -> bool {
+        self.dominators(block).any(|found| found == dominator)
+    }
+
+    /// the blocks dominating `block`, starting with itself and ending with the entry. empty
+    /// for an unreachable block.
+    pub fn dominators(&self, block: BlockId) -> impl Iterator<Item = BlockId> + '_ {
+        let start = self.is_reachable(block).then_some(block);
+        core::iter::successors(start, |&block| self.immediate_dominator(block))
+    }
+
+    /// the blocks where the dominance of `block` ends: the ones it doesn't strictly dominate
+    /// that have a predecessor it dominates. sorted by id.
+    #[inline]
+    pub fn dominance_frontier(&self, block: BlockId) -> &[BlockId] {
+        &self.frontiers[block.index()]
+    }
+
+    /// walks up from the predecessors of every join point, see Cooper, Harvey and Kennedy.
+    fn frontiers(&self) -> Vec<Vec<BlockId>> {
+        let mut frontiers = alloc::vec![Vec::new(); self.len()];
+        for &block in &self.reverse_postorder {
+            let predecessors = self.predecessors(block);
+            if predecessors.len() < 2 {
+                continue;
+            }
+            let idom = self.idoms[block.index()];
+            for &predecessor in predecessors {
+                let mut runner = Some(predecessor).filter(|&p| self.is_reachable(p));
+                while let Some(at) = runner
+                    && Some(at) != idom
+                {
+                    let frontier: &mut Vec<BlockId> = &mut frontiers[at.index()];
+                    if !frontier.contains(&block) {
+                        frontier.push(block);
+                    }
+                    runner = self.immediate_dominator(at);
+                }
+            }
+        }
+        for frontier in &mut frontiers {
+            frontier.sort_unstable();
+        }
+        frontiers
+    }
+}
+
+/// depth first from the entry, without recursion so that long bodies can't overflow the stack.
+fn reverse_postorder(successors: &[Vec<BlockId>]) -> Vec<BlockId> {
+    if successors.is_empty() {
+        return Vec::new();
+    }
+    let mut visited = alloc::vec![false; successors.len()];
+    let mut postorder = Vec::with_capacity(successors.len());
+    // each entry is a block and how many of its successors were visited
+    let mut stack = alloc::vec![(BlockId::ENTRY, 0)];
+    visited[BlockId::ENTRY.index()] = true;
+    while let Some((block, next)) = stack.last_mut() {
+        match successors[block.index()].get(*next) {
+            Some(&successor) => {
+                *next += 1;
+                if !visited[successor.index()] {
+                    visited[successor.index()] = true;
+                    stack.push((successor, 0));
+                }
+            }
+            None => {
+                postorder.push(*block);
+                stack.pop();
+            }
+        }
+    }
+    postorder.reverse();
+    postorder
+}
+
+/// the immediate dominator of every block, the entry is its own.
+fn dominators(predecessors: &[Vec<BlockId>], reverse_postorder: &[BlockId], len: usize) -> Vec<Option<BlockId>> {
+    let mut order = alloc::vec![usize::MAX; len];
+    for (index, block) in reverse_postorder.iter().enumerate() {
+        order[block.index()] = index;
+    }
+    let mut idoms = alloc::vec![None; len];
+    let Some(&entry) = reverse_postorder.first() else { return idoms };
+    idoms[entry.index()] = Some(entry);
+    let intersect = |idoms: &[Option<BlockId>], mut a: BlockId, mut b: BlockId| {
+        while a != b {
+            while order[a.index()] > order[b.index()] {
+                a = idoms[a.index()].expect("processed blocks have a dominator");
+            }
+            while order[b.index()] > order[a.index()] {
+                b = idoms[b.index()].expect("processed blocks have a dominator");
+            }
+        }
+        a
+    };
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &block in &reverse_postorder[1..] {
+            let mut processed = predecessors[block.index()].iter().copied().filter(|p| idoms[p.index()].is_some());
+            let Some(first) = processed.next() else { continue };
+            let idom = processed.fold(first, |idom, predecessor| intersect(&idoms, predecessor, idom));
+            if idoms[block.index()] != Some(idom) {
+                idoms[block.index()] = Some(idom);
+                changed = true;
+            }
+        }
+    }
+    idoms
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::Cfg;
+    use crate::ir::{BlockId, lower};
+    use crate::parser::tests::parse_ok;
+    use crate::typeck::check;
+
+    /// the cfg of the first function of `text`.
+    fn cfg(text: &str) -> Cfg {
+        let stmts = parse_ok(text);
+        let checked = check(stmts);
+        assert!(checked.errors.is_empty(), "{:?}", checked.errors);
+        Cfg::new(&lower(stmts, &checked).bodies[1])
+    }
+
+    fn blocks(ids: &[usize]) -> Vec<BlockId> {
+        ids.iter().map(|&id| BlockId::new(id)).collect()
+    }
+
+    #[test]
+    fn branches_and_joins() {
+        // bb0 branches to bb1 and bb2, which join in bb3
+        let cfg = cfg("fn f(let c: bool) -> u8 { if c { 1 } else { 2 } }");
+        assert_eq!(cfg.len(), 4);
+        assert_eq!(cfg.successors(BlockId::ENTRY), blocks(&[1, 2]));
+        assert_eq!(cfg.predecessors(BlockId::new(3)), blocks(&[1, 2]));
+        assert_eq!(cfg.reverse_postorder(), blocks(&[0, 2, 1, 3]));
+        assert_eq!(cfg.immediate_dominator(BlockId::new(3)), Some(BlockId::ENTRY));
+        assert_eq!(cfg.immediate_dominator(BlockId::ENTRY), None);
+        assert!(cfg.dominates(BlockId::ENTRY, BlockId::new(2)) && cfg.dominates(BlockId::new(2), BlockId::new(2)));
+        assert!(!cfg.dominates(BlockId::new(1), BlockId::new(3)));
+        assert_eq!(cfg.dominance_frontier(BlockId::new(1)), blocks(&[3]));
+        assert_eq!(cfg.dominance_frontier(BlockId::ENTRY), []);
+    }
+
+    #[test]
+    fn loops_and_unreachable_blocks() {
+        // bb1 is the loop header, bb2 the body and bb3 the exit, bb4 follows the `return`
+        let cfg = cfg("fn f(let n: mut u8) { while n > 0 { n -= 1; } return; if n == 0 { n = 1; }; }");
+        assert_eq!(cfg.predecessors(BlockId::new(1)), blocks(&[0, 2]));
+        assert_eq!(cfg.successors(BlockId::new(2)), blocks(&[1]));
+        assert_eq!(cfg.dominators(BlockId::new(2)).collect::<Vec<_>>(), blocks(&[2, 1, 0]));
+        assert_eq!(cfg.dominance_frontier(BlockId::new(2)), blocks(&[1]));
+        assert_eq!(cfg.dominance_frontier(BlockId::new(1)), blocks(&[1]));
+        assert!(cfg.is_reachable(BlockId::new(3)));
+        let unreachable: Vec<_> = (0..cfg.len()).map(BlockId::new).filter(|&b| !cfg.is_reachable(b)).collect();
+        assert_eq!(unreachable, blocks(&[4, 5]));
+        assert_eq!(cfg.dominators(BlockId::new(4)).count(), 0);
+        assert_eq!(cfg.reverse_postorder().len(), 4);
+    }
+
+    #[test]
+    fn declarations_have_no_blocks() {
+        let cfg = cfg("extern fn puts(let s: *u8) -> i32;");
+        assert!(cfg.is_empty() && cfg.reverse_postorder().is_empty());
+    }
+}
@@ -0,0 +1,689 @@
+//! lowering of a checked syntax tree into the `ir`.
+//!
+//! expressions are lowered into a destination place where there is one, `let x = a + b;` is
+//! the single statement `_1 = _2 + _3`, and into temporaries where an operand is needed.
+//! names are looked up like `resolve` does, a function body only sees the functions around it.
+
+use alloc::vec::Vec;
+
+use crate::ast::{
+    BinaryOp, Block, EnumIntrinsic, Expr, ExprKind, FnDecl, Ident, ItemKind, PatternKind, Stmt, StmtKind, UnaryOp,
+};
+use crate::ir::{
+    BasicBlock, BlockId, Body, BodyId, Constant, LocalDecl, LocalId, Operand, Place, Program, Projection, Rvalue,
+    Statement, StatementKind, Terminator, TerminatorKind,
+};
+use crate::ty::{TyId, TyKind};
+use crate::typeck::{Checked, EnumLayout};
+use crate::types::Span;
+
+/// lowers a whole program, `checked` is what `check` returned for it. the program is
+/// expected to have no errors, whatever has one is lowered as far as it goes.
+pub fn lower<'source>(stmts: &[Stmt<'source>], checked: &Checked<'source>) -> Program<'source> {
+    let span = stmts.iter().map(|stmt| stmt.span).reduce(Span::merge).unwrap_or_default();
+    let mut lowerer = Lowerer {
+        checked,
+        bodies: Vec::new(),
+        scopes: Vec::new(),
+        builder: Builder::new(TyId::UNIT, span),
+    };
+    lowerer.bodies.push(placeholder(None, false, span));
+    lowerer.builder.current = Some(lowerer.new_block());
+    lowerer.stmts(stmts, None, None);
+    lowerer.terminate(TerminatorKind::Return, span);
+    lowerer.bodies[BodyId::TOP_LEVEL.index()] = lowerer.builder.finish(None, false, 0);
+    Program {
+        bodies: lowerer.bodies,
+    }
+}
+
+/// a function whose body isn't lowered yet.
+fn placeholder<'source>(name: Option<Ident<'source>>, is_extern: bool, span: Span) -> Body<'source> {
+    Body {
+        name,
+        is_extern,
+        param_count: 0,
+        locals: Vec::new(),
+        blocks: Vec::new(),
+        span,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Name {
+    Local(LocalId),
+    Fn(BodyId),
+}
+
+#[derive(Debug, Default)]
+struct Scope<'source> {
+    names: Vec<(&'source str, Name)>,
+    /// the parameters of a function, locals outside of it aren't visible.
+    is_fn: bool,
+}
+
+/// where `break` and `continue` of a loop go.
+struct LoopTargets<'source> {
+    label: Option<&'source str>,
+    exit: BlockId,
+    next: BlockId,
+    /// where the value of a `break` goes, `None` for `while` and unused values.
+    dest: Option<Place<'source>>,
+}
+
+/// the body being lowered.
+struct Builder<'source> {
+    locals: Vec<LocalDecl<'source>>,
+    blocks: Vec<BasicBlock<'source>>,
+    /// `None` after a jump, the code up to the next block is never run and dropped.
+    current: Option<BlockId>,
+    loops: Vec<LoopTargets<'source>>,
+    span: Span,
+}
+
+impl<'source> Builder<'source> {
+    fn new(ret: TyId, span: Span) -> Self {
+        let ret = LocalDecl { name: None, ty: ret, span };
+        Builder {
+            locals: alloc::vec![ret],
+            blocks: Vec::new(),
+            current: None,
+            loops: Vec::new(),
+            span,
+        }
+    }
+
+    fn finish(self, name: Option<Ident<'source>>, is_extern: bool, param_count: usize) -> Body<'source> {
+        Body {
+            name,
+            is_extern,
+            param_count,
+            locals: self.locals,
+            blocks: self.blocks,
+            span: self.span,
+        }
+    }
+}
+
+struct Lowerer<'c, 'source> {
+    checked: &'c Checked<'source>,
+    bodies: Vec<Body<'source>>,
+    scopes: Vec<Scope<'source>>,
+    builder: Builder<'source>,
+}
+
+impl<'c, 'source> Lowerer<'c, 'source> {
+    fn ty(&self, expr: &Expr<'source>) -> TyId {
+        self.checked.expr_ty(expr.span).unwrap_or(TyId::ERROR)
+    }
+
+    fn local(&mut self, name: Option<&'source str>, ty: TyId, span: Span) -> LocalId {
+        self.builder.locals.push(LocalDecl { name, ty, span });
+        LocalId::new(self.builder.locals.len() - 1)
+    }
+
+    fn temp(&mut self, ty: TyId, span: Span) -> Place<'source> {
+        Place::local(self.local(None, ty, span))
+    }
+
+    /// a block with a placeholder terminator that `terminate` replaces.
+    fn new_block(&mut self) -> BlockId {
+        let terminator = Terminator {
+            kind: TerminatorKind::Unreachable,
+            span: Span::default(),
+        };
+        self.builder.blocks.push(BasicBlock {
+            statements: Vec::new(),
+            terminator,
+        });
+        BlockId::new(self.builder.blocks.len() - 1)
+    }
+
+    fn push(&mut self, kind: StatementKind<'source>, span: Span) {
+        if let Some(current) = self.builder.current {
+            self.builder.blocks[current.index()].statements.push(Statement { kind, span });
+        }
+    }
+
+    fn assign(&mut self, place: Place<'source>, rvalue: Rvalue<'source>, span: Span) {
+        self.push(StatementKind::Assign(place, rvalue), span);
+    }
+
+    /// ends the current block, the code after it is unreachable until the next `enter`.
+    fn terminate(&mut self, kind: TerminatorKind<'source>, span: Span) {
+        if let Some(current) = self.builder.current.take() {
+            self.builder.blocks[current.index()].terminator = Terminator { kind, span };
+        }
+    }
+
+    fn goto(&mut self, target: BlockId, span: Span) {
+        self.terminate(TerminatorKind::Goto(target), span);
+    }
+
+    fn enter(&mut self, block: BlockId) {
+        self.builder.current = Some(block);
+    }
+
+    fn bind(&mut self, name: &'source str, binding: Name) {
+        let scope = self.scopes.last_mut().expect("bindings are inside a scope");
+        scope.names.push((name, binding));
+    }
+
+    /// what `name` refers to, innermost first. locals past a function boundary aren't visible.
+    fn lookup(&self, name: &str) -> Option<Name> {
+        let mut in_fn = false;
+        for scope in self.scopes.iter().rev() {
+            let visible = scope
+                .names
+                .iter()
+                .rev()
+                .find(|(bound, binding)| *bound == name && !(in_fn && matches!(binding, Name::Local(_))));
+            if let Some(&(_, binding)) = visible {
+                return Some(binding);
+            }
+            in_fn |= scope.is_fn;
+        }
+        None
+    }
+
+    fn discriminant(&self, span: Span) -> Option<u32> {
+        self.checked.discriminants.iter().find(|(at, _)| *at == span).map(|&(_, discriminant)| discriminant)
+    }
+
+    /// the enum the `EnumIntrinsic` at `span` is about.
+    fn intrinsic(&self, span: Span) -> Option<&'c EnumLayout<'source>> {
+        let &(_, index) = self.checked.intrinsics.iter().find(|(at, _)| *at == span)?;
+        Some(&self.checked.enums[index])
+    }
+
+    /// lowers the statements of a block and its tail, into `dest` if there is one.
+    fn stmts(&mut self, stmts: &[Stmt<'source>], tail: Option<&Expr<'source>>, dest: Option<Place<'source>>) {
+        self.scopes.push(Scope::default());
+        // functions are visible in their whole block
+        let mut fns = Vec::new();
+        for stmt in stmts {
+            if let StmtKind::Item(item) = &stmt.kind
+                && let ItemKind::Fn(decl) = &item.kind
+            {
+                let id = BodyId::new(self.bodies.len());
+                self.bodies.push(placeholder(Some(decl.name), decl.is_extern, decl.span));
+                self.bind(decl.name.name, Name::Fn(id));
+                fns.push(id);
+            }
+        }
+        let mut fns = fns.into_iter();
+        for stmt in stmts {
+            match &stmt.kind {
+                // the value can't see the name it is bound to
+                StmtKind::Let { name, value, .. } => {
+                    let bound = self.checked.bindings.iter().find(|(span, _)| *span == name.span);
+                    let ty = bound.map_or(TyId::ERROR, |&(_, ty)| ty);
+                    let local = self.local(Some(name.name), ty, name.span);
+                    self.into(Place::local(local), value);
+                    self.bind(name.name, Name::Local(local));
+                }
+                StmtKind::Expr(expr) => self.effect(expr),
+                StmtKind::Item(item) => {
+                    if let ItemKind::Fn(decl) = &item.kind {
+                        let id = fns.next().expect("every function of the block is declared");
+                        let ty = self.checked.fn_types.iter().find(|(span, _)| *span == decl.name.span);
+                        self.fn_decl(decl, id, ty.map_or(TyId::ERROR, |&(_, ty)| ty));
+                    }
+                }
+            }
+        }
+        match (tail, dest) {
+            (Some(tail), Some(dest)) => self.into(dest, tail),
+            (Some(tail), None) => self.effect(tail),
+            (None, _) => {}
+        }
+        self.scopes.pop();
+    }
+
+    fn block(&mut self, block: &Block<'source>, dest: Option<Place<'source>>) {
+        self.stmts(&block.stmts, block.tail.as_deref(), dest);
+    }
+
+    /// lowers `decl` into the body `id`, `ty` is its function type.
+    fn fn_decl(&mut self, decl: &FnDecl<'source>, id: BodyId, ty: TyId) {
+        let (params, ret) = match self.checked.tys.kind(ty) {
+            TyKind::Fn { params, ret } => (params.clone(), *ret),
+            _ => (alloc::vec![TyId::ERROR; decl.params.len()], TyId::ERROR),
+        };
+        let outer = core::mem::replace(&mut self.builder, Builder::new(ret, decl.span));
+        self.scopes.push(Scope {
+            is_fn: true,
+            ..Scope::default()
+        });
+        for (param, ty) in decl.params.iter().zip(params) {
+            let local = self.local(Some(param.name.name), ty, param.name.span);
+            self.bind(param.name.name, Name::Local(local));
+        }
+        if let Some(body) = &decl.body {
+            let entry = self.new_block();
+            self.enter(entry);
+            self.block(body, Some(Place::local(LocalId::RETURN)));
+            self.terminate(TerminatorKind::Return, body.span);
+        }
+        self.scopes.pop();
+        let builder = core::mem::replace(&mut self.builder, outer);
+        self.bodies[id.index()] = builder.finish(Some(decl.name), decl.is_extern, decl.params.len());
+    }
+
+    /// lowers `expr` for its effects, the value is dropped.
+    fn effect(&mut self, expr: &Expr<'source>) {
+        match &expr.kind {
+            ExprKind::Assign { op, target, value } => self.assignment(*op, target, value, expr.span),
+            ExprKind::Call { .. } => {
+                let call = self.rvalue(expr);
+                self.push(StatementKind::Eval(call), expr.span);
+            }
+            ExprKind::Fn(decl) => {
+                self.fn_expr(decl, expr);
+            }
+            _ if is_control_flow(expr) => self.control_flow(expr, None),
+            _ => {
+                self.operand(expr);
+            }
+        }
+    }
+
+    /// lowers `expr` and stores its value in `dest`.
+    fn into(&mut self, dest: Place<'source>, expr: &Expr<'source>) {
+        match &expr.kind {
+            ExprKind::Assign { op, target, value } => self.assignment(*op, target, value, expr.span),
+            ExprKind::Path { variant, .. }
+                if variant.name == EnumIntrinsic::All.name()
+                    && let Some(layout) = self.intrinsic(expr.span) =>
+            {
+                self.all_variants(dest, layout, expr.span);
+            }
+            _ if is_control_flow(expr) => self.control_flow(expr, Some(dest)),
+            _ => {
+                let rvalue = self.rvalue(expr);
+                self.assign(dest, rvalue, expr.span);
+            }
+        }
+    }
+
+    /// `E::ALL` into `dest`, one variant at a time.
+    fn all_variants(&mut self, dest: Place<'source>, layout: &EnumLayout<'source>, span: Span) {
+        for (variant, discriminant) in layout.variants.iter().zip(0..) {
+            let index = self.temp(TyId::USIZE, span);
+            self.assign(index.clone(), Rvalue::Use(Operand::Const(Constant::Usize(discriminant as u64))), span);
+            let name = variant.name;
+            let rvalue = Rvalue::Use(Operand::Const(Constant::Variant { name, discriminant }));
+            self.assign(dest.clone().project(Projection::Index(index.local)), rvalue, span);
+        }
+    }
+
+    /// `expr` as an operand, in a new temporary unless it is a constant or a place.
+    fn operand(&mut self, expr: &Expr<'source>) -> Operand<'source> {
+        match &expr.kind {
+            ExprKind::Literal(literal) => Operand::Const(Constant::Literal(*literal)),
+            ExprKind::Identifier(name) => {
+                if let Some(discriminant) = self.discriminant(expr.span) {
+                    return Operand::Const(Constant::Variant { name, discriminant });
+                }
+                match self.lookup(name) {
+                    Some(Name::Local(local)) => Operand::Copy(Place::local(local)),
+                    Some(Name::Fn(id)) => Operand::Const(Constant::Fn(id)),
+                    None => Operand::Const(Constant::Bool(*name == "true")),
+                }
+            }
+            ExprKind::Path { variant, .. } => match self.intrinsic(expr.span) {
+                Some(layout) if variant.name == EnumIntrinsic::VariantCount.name() => {
+                    Operand::Const(Constant::Usize(layout.variants.len() as u64))
+                }
+                Some(layout) => {
+                    let all = self.temp(self.ty(expr), expr.span);
+                    self.all_variants(all.clone(), layout, expr.span);
+                    Operand::Copy(all)
+                }
+                None => {
+                    let discriminant = self.discriminant(expr.span).unwrap_or_default();
+                    Operand::Const(Constant::Variant {
+                        name: variant.name,
+                        discriminant,
+                    })
+                }
+            },
+            ExprKind::Field { .. }
+            | ExprKind::Index { .. }
+            | ExprKind::Unary {
+                op: UnaryOp::Deref, ..
+            } => Operand::Copy(self.place(expr)),
+            ExprKind::Fn(decl) => Operand::Const(Constant::Fn(self.fn_expr(decl, expr))),
+            _ => {
+                let temp = self.temp(self.ty(expr), expr.span);
+                self.into(temp.clone(), expr);
+                Operand::Copy(temp)
+            }
+        }
+    }
+
+    /// the single operation `expr` is once its operands are lowered.
+    fn rvalue(&mut self, expr: &Expr<'source>) -> Rvalue<'source> {
+        match &expr.kind {
+            ExprKind::Unary { op, expr: operand } if *op != UnaryOp::Deref => Rvalue::Unary(*op, self.operand(operand)),
+            ExprKind::Binary { op, lhs, rhs } => {
+                let lhs = self.operand(lhs);
+                Rvalue::Binary(*op, lhs, self.operand(rhs))
+            }
+            ExprKind::Cast { expr: operand, .. } => Rvalue::Cast(self.operand(operand), self.ty(expr)),
+            ExprKind::Call { callee, args } => {
+                let callee = self.operand(callee);
+                let args = args.iter().map(|arg| self.operand(arg)).collect();
+                Rvalue::Call { callee, args }
+            }
+            ExprKind::Struct { name, fields } => {
+                let fields = fields.iter().map(|init| (init.name.name, self.operand(&init.value))).collect();
+                Rvalue::Struct { name: name.name, fields }
+            }
+            _ => Rvalue::Use(self.operand(expr)),
+        }
+    }
+
+    /// the place `expr` names, a temporary holding its value when it isn't one.
+    fn place(&mut self, expr: &Expr<'source>) -> Place<'source> {
+        match &expr.kind {
+            ExprKind::Identifier(name) if self.discriminant(expr.span).is_none() => match self.lookup(name) {
+                Some(Name::Local(local)) => Place::local(local),
+                _ => self.spill(expr),
+            },
+            ExprKind::Field { expr: base, field } => self.place(base).project(Projection::Field(field.name)),
+            ExprKind::Index { expr: base, index } => {
+                let base = self.place(base);
+                let index = match self.operand(index) {
+                    Operand::Copy(Place { local, projection }) if projection.is_empty() => local,
+                    operand => {
+                        let temp = self.temp(TyId::USIZE, expr.span);
+                        self.assign(temp.clone(), Rvalue::Use(operand), expr.span);
+                        temp.local
+                    }
+                };
+                base.project(Projection::Index(index))
+            }
+            ExprKind::Unary {
+                op: UnaryOp::Deref,
+                expr: pointer,
+            } => self.place(pointer).project(Projection::Deref),
+            _ => self.spill(expr),
+        }
+    }
+
+    /// `expr` in a new temporary.
+    fn spill(&mut self, expr: &Expr<'source>) -> Place<'source> {
+        let temp = self.temp(self.ty(expr), expr.span);
+        let rvalue = Rvalue::Use(self.operand(expr));
+        self.assign(temp.clone(), rvalue, expr.span);
+        temp
+    }
+
+    fn assignment(&mut self, op: Option<BinaryOp>, target: &Expr<'source>, value: &Expr<'source>, span: Span) {
+        let place = self.place(target);
+        match op {
+            Some(op) => {
+                let value = self.operand(value);
+                let rvalue = Rvalue::Binary(op, Operand::Copy(place.clone()), value);
+                self.assign(place, rvalue, span);
+            }
+            None => self.into(place, value),
+        }
+    }
+
+    /// lowers the function expression `decl` into a new body.
+    fn fn_expr(&mut self, decl: &FnDecl<'source>, expr: &Expr<'source>) -> BodyId {
+        let id = BodyId::new(self.bodies.len());
+        self.bodies.push(placeholder(Some(decl.name), decl.is_extern, decl.span));
+        self.fn_decl(decl, id, self.ty(expr));
+        id
+    }
+
+    fn control_flow(&mut self, expr: &Expr<'source>, dest: Option<Place<'source>>) {
+        let span = expr.span;
+        match &expr.kind {
+            ExprKind::Block(block) => self.block(block, dest),
+            ExprKind::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                let cond = self.operand(cond);
+                let then_block = self.new_block();
+                let else_block = else_branch.as_ref().map(|_| self.new_block());
+                let join = self.new_block();
+                let else_block = else_block.unwrap_or(join);
+                self.terminate(TerminatorKind::Branch { cond, then_block, else_block }, span);
+                self.enter(then_block);
+                self.block(then_branch, dest.clone());
+                self.goto(join, span);
+                if let Some(else_branch) = else_branch {
+                    self.enter(else_block);
+                    match dest {
+                        Some(dest) => self.into(dest, else_branch),
+                        None => self.effect(else_branch),
+                    }
+                    self.goto(join, span);
+                }
+                self.enter(join);
+            }
+            ExprKind::While { label, cond, body } => {
+                let header = self.new_block();
+                self.goto(header, span);
+                self.enter(header);
+                let cond = self.operand(cond);
+                let (body_block, exit) = (self.new_block(), self.new_block());
+                let branch = TerminatorKind::Branch {
+                    cond,
+                    then_block: body_block,
+                    else_block: exit,
+                };
+                self.terminate(branch, span);
+                self.enter(body_block);
+                self.loop_body(body, label.map(|label| label.name), exit, header, None);
+                self.enter(exit);
+            }
+            ExprKind::Loop { label, body } => {
+                let (body_block, exit) = (self.new_block(), self.new_block());
+                self.goto(body_block, span);
+                self.enter(body_block);
+                self.loop_body(body, label.map(|label| label.name), exit, body_block, dest);
+                self.enter(exit);
+            }
+            ExprKind::Break { label, value } => {
+                let targets = self.loop_targets(label.map(|label| label.name));
+                let exit = targets.map(|targets| (targets.exit, targets.dest.clone()));
+                match (value, exit.as_ref().and_then(|(_, dest)| dest.clone())) {
+                    (Some(value), Some(dest)) => self.into(dest, value),
+                    (Some(value), None) => self.effect(value),
+                    (None, _) => {}
+                }
+                match exit {
+                    Some((exit, _)) => self.goto(exit, span),
+                    None => self.terminate(TerminatorKind::Unreachable, span),
+                }
+            }
+            ExprKind::Continue { label } => match self.loop_targets(label.map(|label| label.name)) {
+                Some(targets) => {
+                    let next = targets.next;
+                    self.goto(next, span);
+                }
+                None => self.terminate(TerminatorKind::Unreachable, span),
+            },
+            ExprKind::Return(value) => {
+                if let Some(value) = value {
+                    self.into(Place::local(LocalId::RETURN), value);
+                }
+                self.terminate(TerminatorKind::Return, span);
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                let scrutinee_ty = self.ty(scrutinee);
+                let scrutinee = self.operand(scrutinee);
+                let join = self.new_block();
+                for arm in arms {
+                    let pattern = arm.pattern.span;
+                    let constant = match &arm.pattern.kind {
+                        PatternKind::Wildcard => None,
+                        PatternKind::Literal(literal) => Some(Constant::Literal(*literal)),
+                        PatternKind::Identifier(name) => self
+                            .discriminant(pattern)
+                            .map(|discriminant| Constant::Variant { name, discriminant }),
+                        PatternKind::Path { variant, .. } => Some(Constant::Variant {
+                            name: variant.name,
+                            discriminant: self.discriminant(pattern).unwrap_or_default(),
+                        }),
+                    };
+                    self.scopes.push(Scope::default());
+                    let next = match constant {
+                        Some(constant) => {
+                            let test = self.temp(TyId::BOOL, pattern);
+                            let compare = Rvalue::Binary(BinaryOp::Eq, scrutinee.clone(), Operand::Const(constant));
+                            self.assign(test.clone(), compare, pattern);
+                            let (body, next) = (self.new_block(), self.new_block());
+                            let branch = TerminatorKind::Branch {
+                                cond: Operand::Copy(test),
+                                then_block: body,
+                                else_block: next,
+                            };
+                            self.terminate(branch, pattern);
+                            self.enter(body);
+                            Some(next)
+                        }
+                        None => {
+                            if let PatternKind::Identifier(name) = arm.pattern.kind {
+                                let local = self.local(Some(name), scrutinee_ty, pattern);
+                                self.assign(Place::local(local), Rvalue::Use(scrutinee.clone()), pattern);
+                                self.bind(name, Name::Local(local));
+                            }
+                            None
+                        }
+                    };
+                    match &dest {
+                        Some(dest) => self.into(dest.clone(), &arm.body),
+                        None => self.effect(&arm.body),
+                    }
+                    self.goto(join, arm.span);
+                    self.scopes.pop();
+                    // the arms after one that matches everything are never tried
+                    let Some(next) = next else { break };
+                    self.enter(next);
+                }
+                self.terminate(TerminatorKind::Unreachable, span);
+                self.enter(join);
+            }
+            _ => unreachable!("`is_control_flow` is true"),
+        }
+    }
+
+    fn loop_body(
+        &mut self,
+        body: &Block<'source>,
+        label: Option<&'source str>,
+        exit: BlockId,
+        next: BlockId,
+        dest: Option<Place<'source>>,
+    ) {
+        self.builder.loops.push(LoopTargets { label, exit, next, dest });
+        self.block(body, None);
+        self.builder.loops.pop();
+        self.goto(next, body.span);
+    }
+
+    /// the loop `label` names, the innermost one without a label.
+    fn loop_targets(&self, label: Option<&str>) -> Option<&LoopTargets<'source>> {
+        let mut loops = self.builder.loops.iter().rev();
+        match label {
+            Some(label) => loops.find(|targets| targets.label == Some(label)),
+            None => loops.next(),
+        }
+    }
+}
+
+fn is_control_flow(expr: &Expr<'_>) -> bool {
+    matches!(
+        expr.kind,
+        ExprKind::Block(_)
+            | ExprKind::If { .. }
+            | ExprKind::While { .. }
+            | ExprKind::Loop { .. }
+            | ExprKind::Break { .. }
+            | ExprKind::Continue { .. }
+            | ExprKind::Return(_)
+            | ExprKind::Match { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+
+    use super::lower;
+    use crate::parser::tests::parse_ok;
+    use crate::typeck::check;
+
+    fn lowered(text: &str) -> String {
+        let stmts = parse_ok(text);
+        let checked = check(stmts);
+        assert!(checked.errors.is_empty(), "{:?}", checked.errors);
+        lower(stmts, &checked).display(&checked.tys).to_string()
+    }
+
+    #[test]
+    fn expressions_go_through_temporaries() {
+        let text = "fn add(let a: i64, let b: i64) -> i64 { a + b * 2 }\nlet x: i64 = add(1, 2);\n\
+                    struct S { v: u8 }\nlet s = S { v: 3 };\nlet w = -(s.v cast i64) + x;\nadd(w, 1);";
+        assert_eq!(
+            lowered(text),
+            "fn {top level}() -> () {\n    let _0: ();\n    let _1: i64; // x\n    let _2: S; // s\n    \
+             let _3: i64; // w\n    let _4: i64;\n    let _5: i64;\nbb0:\n    _1 = add(1, 2);\n    \
+             _2 = S { v: 3 };\n    _5 = _2.v cast i64;\n    _4 = -_5;\n    _3 = _4 + _1;\n    add(_3, 1);\n    \
+             return;\n}\n\nfn add(_1: i64, _2: i64) -> i64 {\n    let _0: i64;\n    let _3: i64;\nbb0:\n    \
+             _3 = _2 * 2;\n    _0 = _1 + _3;\n    return;\n}\n"
+        );
+    }
+
+    #[test]
+    fn control_flow_becomes_blocks() {
+        let text = "fn f(let p: *mut u8) -> u8 {\n    let y = if *p > 3 { 1 } else { 2 };\n    \
+                    while *p < 10 { *p += y; }\n    return *p;\n    *p = 0;\n}";
+        assert_eq!(
+            lowered(text),
+            "fn {top level}() -> () {\n    let _0: ();\nbb0:\n    return;\n}\n\n\
+             fn f(_1: *mut u8) -> u8 {\n    let _0: u8;\n    let _2: u8; // y\n    let _3: bool;\n    \
+             let _4: bool;\nbb0:\n    _3 = _1.* > 3;\n    branch _3, bb1, bb2;\nbb1:\n    _2 = 1;\n    \
+             goto bb3;\nbb2:\n    _2 = 2;\n    goto bb3;\nbb3:\n    goto bb4;\nbb4:\n    _4 = _1.* < 10;\n    \
+             branch _4, bb5, bb6;\nbb5:\n    _1.* = _1.* + _2;\n    goto bb4;\nbb6:\n    _0 = _1.*;\n    \
+             return;\n}\n"
+        );
+    }
+
+    #[test]
+    fn enum_intrinsics_become_constants() {
+        let text = "enum E { a, b }\nlet n = E::variant_count;\nlet all = E::ALL;";
+        assert_eq!(
+            lowered(text),
+            "fn {top level}() -> () {\n    let _0: ();\n    let _1: usize; // n\n    let _2: [2 E]; // all\n    \
+             let _3: usize;\n    let _4: usize;\nbb0:\n    _1 = 2usize;\n    _3 = 0usize;\n    _2[_3] = a#0;\n    \
+             _4 = 1usize;\n    _2[_4] = b#1;\n    return;\n}\n"
+        );
+    }
+
+    #[test]
+    fn matches_loops_and_functions() {
+        let text = "enum E { a, b }\nfn g(let e: E) -> u8 {\n    fn one() -> u8 { 1 }\n    \
+                    match e { a => one(), other => 2, E::b => 3, }\n}\n\
+                    let n: u8 = @outer loop { loop { if g(E::a) == 1 { break @outer 4; }; continue @outer; }; };";
+        assert_eq!(
+            lowered(text),
+            "fn {top level}() -> () {\n    let _0: ();\n    let _1: u8; // n\n    let _2: bool;\n    \
+             let _3: u8;\nbb0:\n    goto bb1;\nbb1:\n    goto bb3;\nbb2:\n    return;\nbb3:\n    \
+             _3 = g(a#0);\n    _2 = _3 == 1;\n    branch _2, bb5, bb6;\nbb4:\n    goto bb1;\nbb5:\n    \
+             _1 = 4;\n    goto bb2;\nbb6:\n    goto bb1;\n}\n\n\
+             fn g(_1: E) -> u8 {\n    let _0: u8;\n    let _2: bool;\n    let _3: E; // other\nbb0:\n    \
+             _2 = _1 == a#0;\n    branch _2, bb2, bb3;\nbb1:\n    return;\nbb2:\n    _0 = one();\n    \
+             goto bb1;\nbb3:\n    _3 = _1;\n    _0 = 2;\n    goto bb1;\n}\n\n\
+             fn one() -> u8 {\n    let _0: u8;\nbb0:\n    _0 = 1;\n    return;\n}\n"
+        );
+    }
+}
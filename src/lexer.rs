@@ -1,28 +1,163 @@
+#[cfg(feature = "alloc")]
+use crate::literal_value::cook_string;
 use crate::source_code::SourceCode;
-use crate::types::Token;
+use crate::types::{Span, Spanned, Token};
+use core::fmt::Write;
 use core::iter::FusedIterator;
+#[cfg(feature = "alloc")]
+use alloc::{borrow::Cow, string::String};
 
-// N.B.: not all LexerErrors equal themselves as they could be originating from different places.
+// N.B.: not all error kinds equal themselves as they could be originating from different places.
 // therefore we don't implement `Eq` because we aren't reflexive (a != a).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, Copy, PartialEq, Hash)]
 #[non_exhaustive]
-pub enum LexerError {
+pub enum ErrorKind {
     UnexpectedEofWhile(Token),
+    /// only serialized, a message can't be borrowed from the deserializer for `'static`.
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
     WithMessage(&'static str),
     InvalidEscapeSequence,
     InvalidCharacter,
     UnclosedCharLiteral,
+    /// `''`, suggest `'\0'` or a string literal instead.
+    EmptyCharLiteral,
+    /// a raw control byte (below 0x20 or 0x7f) inside a literal that requires it to be escaped.
+    ControlCharacterInLiteral,
     NoLiteralToExtract,
     Eof,
+    /// one of the `LexerLimits` was exceeded. the lexer stops in the middle of the input
+    /// and lexing should not continue.
+    LimitExceeded(Limit),
 
     Internal,
 }
 
+/// which of the `LexerLimits` an input exceeded.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Limit {
+    TokenLength,
+    StringLength,
+    CommentLength,
+    TokenCount,
+}
+
+impl ErrorKind {
+    /// stable identifier for documentation and tooling. codes are never reused, new kinds
+    /// get the next free number.
+    #[inline]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::UnexpectedEofWhile(_) => "E0001",
+            ErrorKind::WithMessage(_) => "E0002",
+            ErrorKind::InvalidEscapeSequence => "E0003",
+            ErrorKind::InvalidCharacter => "E0004",
+            ErrorKind::UnclosedCharLiteral => "E0005",
+            ErrorKind::EmptyCharLiteral => "E0006",
+            ErrorKind::ControlCharacterInLiteral => "E0007",
+            ErrorKind::NoLiteralToExtract => "E0008",
+            ErrorKind::Eof => "E0009",
+            ErrorKind::Internal => "E0010",
+            ErrorKind::LimitExceeded(_) => "E0011",
+        }
+    }
+}
+
+impl core::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ErrorKind::UnexpectedEofWhile(token) => write!(f, "unexpected end of input while lexing {token}"),
+            ErrorKind::WithMessage(message) => f.write_str(message),
+            ErrorKind::InvalidEscapeSequence => f.write_str("invalid escape sequence"),
+            ErrorKind::InvalidCharacter => f.write_str("invalid character"),
+            ErrorKind::UnclosedCharLiteral => f.write_str("unclosed character literal"),
+            ErrorKind::EmptyCharLiteral => f.write_str("empty character literal"),
+            ErrorKind::ControlCharacterInLiteral => f.write_str("raw control character in literal, escape it"),
+            ErrorKind::NoLiteralToExtract => f.write_str("the last token has no literal to extract"),
+            ErrorKind::Eof => f.write_str("end of input"),
+            ErrorKind::LimitExceeded(Limit::TokenLength) => f.write_str("token exceeds the maximum length"),
+            ErrorKind::LimitExceeded(Limit::StringLength) => f.write_str("string literal exceeds the maximum length"),
+            ErrorKind::LimitExceeded(Limit::CommentLength) => f.write_str("comment exceeds the maximum length"),
+            ErrorKind::LimitExceeded(Limit::TokenCount) => f.write_str("source exceeds the maximum number of tokens"),
+            ErrorKind::Internal => f.write_str("internal lexer error"),
+        }
+    }
+}
+
+/// what went wrong and the bytes it happened at. for errors inside a token the span
+/// starts at the token and ends where lexing gave up.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+// the derive for `ErrorKind` is only implemented for `'de: 'static` because of `WithMessage`
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'static")))]
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+pub struct LexerError {
+    pub kind: ErrorKind,
+    pub span: Span,
+}
+
+impl LexerError {
+    #[inline]
+    pub const fn new(kind: ErrorKind, span: Span) -> Self {
+        LexerError { kind, span }
+    }
+
+    /// see `ErrorKind::code`.
+    #[inline]
+    pub const fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+
+    #[inline]
+    pub const fn is_eof(&self) -> bool {
+        matches!(self.kind, ErrorKind::Eof)
+    }
+
+    /// whether lexing should stop here, see `ErrorKind::LimitExceeded`.
+    #[inline]
+    pub const fn is_limit_exceeded(&self) -> bool {
+        matches!(self.kind, ErrorKind::LimitExceeded(_))
+    }
+}
+
+/// `E0004: invalid character`, the span is left to the caller to render.
+impl core::fmt::Display for LexerError {
+    #[inline]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}: {}", self.code(), self.kind)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LexerError {}
+
+/// non-fatal findings about the most recently lexed token, see `Lexer::take_warning`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum LexerWarning {
+    /// byte offset of the first raw control byte inside a string literal.
+    ControlCharacterInLiteral(usize),
+}
+
+/// what to do with raw control bytes (including NUL) inside string literals.
+///
+/// tabs, carriage returns and newlines are not affected as strings may span multiple lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ControlBytePolicy {
+    #[default]
+    Accept,
+    /// accept the literal and record a `LexerWarning::ControlCharacterInLiteral`.
+    Warn,
+    /// fail with `ErrorKind::ControlCharacterInLiteral`.
+    Reject,
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! lexer_error_here {
     ($message: literal) => {{
-        $crate::lexer::LexerError::WithMessage(::core::concat!(
+        $crate::lexer::ErrorKind::WithMessage(::core::concat!(
             "lexer error at ",
             ::core::file!(),
             ":",
@@ -37,6 +172,157 @@ pub use crate::lexer_error_here;
 
 pub type LexerResult<T> = Result<T, LexerError>;
 
+/// upper bounds for untrusted input, exceeding one fails with `ErrorKind::LimitExceeded`.
+/// everything is unlimited by default.
+///
+/// string literals and comments are cut off as soon as they grow past their limit, so a
+/// huge unterminated string isn't scanned to the end. the token length is checked once
+/// the token is complete.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LexerLimits {
+    max_token_len: usize,
+    max_string_len: usize,
+    max_comment_len: usize,
+    max_tokens: usize,
+}
+
+impl LexerLimits {
+    #[inline]
+    pub const fn new() -> Self {
+        LexerLimits {
+            max_token_len: usize::MAX,
+            max_string_len: usize::MAX,
+            max_comment_len: usize::MAX,
+            max_tokens: usize::MAX,
+        }
+    }
+
+    /// bytes per token, including the quotes of literals.
+    #[inline]
+    pub const fn max_token_len(mut self, len: usize) -> Self {
+        self.max_token_len = len;
+        self
+    }
+
+    /// bytes between the quotes of a string literal, escapes count as written.
+    #[inline]
+    pub const fn max_string_len(mut self, len: usize) -> Self {
+        self.max_string_len = len;
+        self
+    }
+
+    /// bytes after the `//` of a line comment.
+    #[inline]
+    pub const fn max_comment_len(mut self, len: usize) -> Self {
+        self.max_comment_len = len;
+        self
+    }
+
+    /// tokens lexed over the lifetime of the lexer, errors don't count.
+    #[inline]
+    pub const fn max_tokens(mut self, count: usize) -> Self {
+        self.max_tokens = count;
+        self
+    }
+
+    #[inline]
+    pub const fn get_max_token_len(&self) -> usize {
+        self.max_token_len
+    }
+
+    #[inline]
+    pub const fn get_max_string_len(&self) -> usize {
+        self.max_string_len
+    }
+
+    #[inline]
+    pub const fn get_max_comment_len(&self) -> usize {
+        self.max_comment_len
+    }
+
+    #[inline]
+    pub const fn get_max_tokens(&self) -> usize {
+        self.max_tokens
+    }
+}
+
+impl Default for LexerLimits {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// lexer configuration, see `Lexer::with_options`. the default is what `Lexer::new` uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct LexerOptions {
+    control_bytes: ControlBytePolicy,
+    error_recovery: bool,
+    unicode_identifiers: bool,
+    limits: LexerLimits,
+}
+
+impl LexerOptions {
+    #[inline]
+    pub const fn new() -> Self {
+        LexerOptions {
+            control_bytes: ControlBytePolicy::Accept,
+            error_recovery: false,
+            unicode_identifiers: false,
+            limits: LexerLimits::new(),
+        }
+    }
+
+    /// see `ControlBytePolicy`.
+    #[inline]
+    pub const fn control_bytes(mut self, policy: ControlBytePolicy) -> Self {
+        self.control_bytes = policy;
+        self
+    }
+
+    /// see `Lexer::with_error_recovery`.
+    #[inline]
+    pub const fn error_recovery(mut self, recover: bool) -> Self {
+        self.error_recovery = recover;
+        self
+    }
+
+    /// accept any non-ascii character in identifiers, including the first one.
+    /// there is no XID classification, everything above the ascii range counts.
+    #[inline]
+    pub const fn unicode_identifiers(mut self, accept: bool) -> Self {
+        self.unicode_identifiers = accept;
+        self
+    }
+
+    /// see `LexerLimits`.
+    #[inline]
+    pub const fn limits(mut self, limits: LexerLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    #[inline]
+    pub const fn get_control_bytes(&self) -> ControlBytePolicy {
+        self.control_bytes
+    }
+
+    #[inline]
+    pub const fn get_error_recovery(&self) -> bool {
+        self.error_recovery
+    }
+
+    #[inline]
+    pub const fn get_unicode_identifiers(&self) -> bool {
+        self.unicode_identifiers
+    }
+
+    #[inline]
+    pub const fn get_limits(&self) -> LexerLimits {
+        self.limits
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Lexer<'source> {
     source: SourceCode<'source>,
@@ -44,46 +330,190 @@ pub struct Lexer<'source> {
     index: usize,
 
     literal: Option<&'source [u8]>,
+    warning: Option<LexerWarning>,
+
+    options: LexerOptions,
+    /// successfully lexed tokens, for `LexerLimits::max_tokens`.
+    token_count: usize,
 
-    // TODO: feature gate these bastards so backtracking and advance doesnt take a billion years
-    line: usize,
-    column: usize,
+    /// latched once `Eof` is returned or the `Iterator` impl stopped at an error.
+    finished: bool,
+}
+
+/// lexer position captured by `Lexer::checkpoint`, restore it with `Lexer::rewind`.
+///
+/// only meaningful for the lexer (or a clone of it) it was taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LexerCheckpoint<'source> {
+    start: usize,
+    index: usize,
+    literal: Option<&'source [u8]>,
+    warning: Option<LexerWarning>,
+    token_count: usize,
+    finished: bool,
+}
+
+/// where the lexer is and what it is holding on to, see `Lexer::state`. displays as a
+/// one-line summary followed by the current line with a caret under `index`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LexerState<'source> {
+    pub start: usize,
+    pub index: usize,
+    /// 1-based, of the byte at `index`.
+    pub line: usize,
+    pub column: usize,
+    /// the literal `extract_literal` would return, without taking it.
+    pub literal: Option<&'source [u8]>,
+    /// the line containing `index`, without its line terminator.
+    pub window: Spanned<&'source str>,
+}
+
+impl LexerState<'_> {
+    /// the first line of the `Display` output.
+    fn write_summary(&self, out: &mut impl Write) -> core::fmt::Result {
+        write!(out, "lexer at {}:{} (index {}..{}), pending literal: ", self.line, self.column, self.start, self.index)?;
+        match self.literal.map(core::str::from_utf8) {
+            Some(Ok(literal)) => write!(out, "{:?}", literal),
+            Some(Err(_)) => write!(out, "{:?}", self.literal.unwrap_or_default()),
+            None => out.write_str("none"),
+        }
+    }
+}
+
+impl core::fmt::Display for LexerState<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.write_summary(f)?;
+        write!(f, "\n{}\n", self.window.value)?;
+        // keep tabs so the caret lines up with the text above
+        let offset = self.index.saturating_sub(self.window.span.start);
+        for c in self.window.value.get(..offset).unwrap_or_default().chars() {
+            f.write_char(if c == '\t' { '\t' } else { ' ' })?;
+        }
+        f.write_char('^')
+    }
 }
 
-mod lexer_impls;
+#[cfg(feature = "alloc")]
+mod incremental;
+pub(crate) mod lexer_impls;
+#[cfg(feature = "std")]
+mod parallel;
+#[cfg(feature = "alloc")]
+mod token_buffer;
+#[cfg(feature = "alloc")]
+mod tokenize;
+
+#[cfg(feature = "alloc")]
+pub use incremental::IncrementalLexer;
+#[cfg(feature = "std")]
+pub use parallel::{FileLexResult, LexStats, lex_files, lex_sources};
+#[cfg(feature = "alloc")]
+pub use token_buffer::{TokenBuffer, TokenCursor};
+#[cfg(feature = "alloc")]
+pub use tokenize::{Tokenized, tokenize};
 
 impl<'source> Lexer<'source> {
     #[inline]
     pub const fn new(source: SourceCode<'source>) -> Self {
+        Self::with_options(source, LexerOptions::new())
+    }
+
+    #[inline]
+    pub const fn with_options(source: SourceCode<'source>, options: LexerOptions) -> Self {
         Lexer {
             source,
             start: 0,
             index: 0,
 
             literal: None,
+            warning: None,
+
+            options,
+            token_count: 0,
+
+            finished: false,
 
-            line: 1,
-            column: 0,
         }
     }
 
+    #[inline]
+    pub const fn with_control_byte_policy(mut self, policy: ControlBytePolicy) -> Self {
+        self.options.control_bytes = policy;
+        self
+    }
+
+    /// in error recovery mode, lexer errors other than `ErrorKind::Eof` and
+    /// `ErrorKind::LimitExceeded` are reported as
+    /// `Ok(Token::Error)` covering the malformed input, so consumers like highlighters
+    /// and formatters always see a complete token stream.
+    #[inline]
+    pub const fn with_error_recovery(mut self, recover: bool) -> Self {
+        self.options.error_recovery = recover;
+        self
+    }
+
+    /// errors span from the start of the offending token to where lexing stopped.
+    ///
     /// After this function returns, you may be at the end.
+    #[inline]
     pub const fn lex_single_token(&mut self) -> LexerResult<Token> {
         self.skip_whitespace();
+        let start = self.index;
+        let result = match self.lex_token_impl() {
+            Ok(_) if self.index - start > self.options.limits.get_max_token_len() => {
+                Err(ErrorKind::LimitExceeded(Limit::TokenLength))
+            }
+            Ok(_) if self.token_count == self.options.limits.get_max_tokens() => {
+                Err(ErrorKind::LimitExceeded(Limit::TokenCount))
+            }
+            result => result,
+        };
+        match result {
+            Ok(tok) => {
+                self.token_count += 1;
+                Ok(tok)
+            }
+            Err(ErrorKind::LimitExceeded(limit)) => {
+                self.literal = None;
+                Err(LexerError::new(ErrorKind::LimitExceeded(limit), Span::new(start, self.index)))
+            }
+            Err(kind) if self.options.error_recovery && !matches!(kind, ErrorKind::Eof) => {
+                self.literal = None;
+                Ok(Token::Error)
+            }
+            Err(kind) => Err(LexerError::new(kind, Span::new(start, self.index))),
+        }
+    }
+
+    /// After this function returns, you may be at the end.
+    const fn lex_token_impl(&mut self) -> Result<Token, ErrorKind> {
+        self.skip_whitespace();
 
         if self.is_at_end() {
-            return Err(LexerError::Eof);
+            self.finished = true;
+            return Err(ErrorKind::Eof);
         }
 
         self.start = self.index;
         self.literal = None;
+        self.warning = None;
 
         let next = unsafe { self.advance_unchecked() };
         let tok = match next {
             b'.' => Token::PuncDot,
             b',' => Token::PuncComma,
             b';' => Token::PuncSemi,
-            b':' => Token::PuncColon,
+            b':' => match self.peek() {
+                Some(b':') => {
+                    unsafe { self.advance_unchecked() };
+                    Token::PuncColonColon
+                }
+                _ => Token::PuncColon,
+            },
+            b'?' => Token::PuncQuestion,
+            b'~' => Token::PuncTilde,
+            b'#' => Token::PuncHash,
+            b'@' => Token::PuncAt,
 
             b'+' => match self.peek() {
                 Some(b'=') => {
@@ -104,6 +534,11 @@ impl<'source> Lexer<'source> {
                     unsafe { self.advance_unchecked() };
                     Token::PuncSlashEq
                 }
+                // only left behind by `skip_whitespace` for comments over the length limit
+                Some(b'/') => {
+                    self.index += 1 + self.options.limits.get_max_comment_len();
+                    return Err(ErrorKind::LimitExceeded(Limit::CommentLength));
+                }
                 _ => Token::PuncSlash,
             },
 
@@ -124,6 +559,10 @@ impl<'source> Lexer<'source> {
                     unsafe { self.advance_unchecked() };
                     Token::PuncEqEq
                 }
+                Some(b'>') => {
+                    unsafe { self.advance_unchecked() };
+                    Token::PuncFatArrow
+                }
                 _ => Token::PuncEq,
             },
 
@@ -266,32 +705,108 @@ impl<'source> Lexer<'source> {
                 unsafe { self.lex_identifier() }
             }
 
+            c if c >= 0x80 && self.options.unicode_identifiers => {
+                // SAFETY: same as above, `lex_identifier` accepts non-ascii heads in this mode
+                unsafe { self.lex_identifier() }
+            }
+
             // always invalid characters:
             //
             // - anything up until the " " character (byte 0x20, decimal 32)
             //   except whitespace such as "\t", "\r", "\n"
-            // - "#"
             // - "$"
-            // - "?"
-            // - "@"
             // - "\" outside of a string escape
             // - "`"
             // - anything outside of the ascii range (outside of strings)
             //   or in other words, values higher than [DEL] (byte 0x7f, decimal 127)
-            _ => return Err(LexerError::InvalidCharacter),
+            c => {
+                // the error covers the whole character, so lexing resumes at a char boundary.
+                // only continuation bytes are taken, in case lexing started inside a character
+                let mut remaining = lexer_impls::chars::utf8_sequence_len(c) - 1;
+                while remaining != 0 && matches!(self.peek(), Some(0x80..=0xbf)) {
+                    unsafe { self.advance_unchecked() };
+                    remaining -= 1;
+                }
+                return Err(ErrorKind::InvalidCharacter);
+            }
         };
 
         Ok(tok)
     }
 
+    /// iterates over the remaining tokens, yielding errors as `Some(Err(_))` and
+    /// continuing after them. iteration only ends at the end of the source.
+    ///
+    /// unlike the `Iterator` impl on `Lexer` itself, an error is distinguishable from the end.
+    #[inline]
+    pub const fn spanned_tokens(&mut self) -> TokenIter<'_, 'source> {
+        TokenIter { lexer: self }
+    }
+
+    /// like `lex_single_token`, but also returns the byte range the token covers.
+    /// for string and char literals, the span includes the quotes.
+    ///
+    /// After this function returns, you may be at the end.
+    #[inline]
+    pub const fn lex_single_token_spanned(&mut self) -> LexerResult<Spanned<Token>> {
+        self.skip_whitespace();
+        let start = self.index;
+        match self.lex_single_token() {
+            Ok(tok) => Ok(Spanned::new(tok, Span::new(start, self.index))),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// the literal of the most recent token. errors carry an empty span at `self.index()`.
     #[inline]
     pub const fn extract_literal(&mut self) -> LexerResult<&'source [u8]> {
         match self.literal.take() {
             Some(t) => Ok(t),
-            None => Err(LexerError::NoLiteralToExtract),
+            None => Err(self.error_here(ErrorKind::NoLiteralToExtract)),
+        }
+    }
+
+    /// like `extract_literal`, but as a `str`.
+    ///
+    /// the source is a `str` and every literal starts and ends at an ascii delimiter or
+    /// spans whole identifier characters, so the slice is always valid utf-8.
+    #[inline]
+    pub const fn extract_literal_str(&mut self) -> LexerResult<&'source str> {
+        match self.extract_literal() {
+            // SAFETY: literals are slices of a `str` that start and end at char boundaries
+            Ok(t) => Ok(unsafe { lexer_impls::raw::to_str(t) }),
+            Err(e) => Err(e),
         }
     }
 
+    /// takes the warning recorded while lexing the most recent token, if any.
+    #[inline]
+    pub const fn take_warning(&mut self) -> Option<LexerWarning> {
+        self.warning.take()
+    }
+
+    /// like `extract_literal`, but decodes the body of a `Token::LitChar` into the
+    /// `char` it denotes, resolving escapes like `'\n'`.
+    #[inline]
+    pub const fn extract_char(&mut self) -> LexerResult<char> {
+        let literal = match self.extract_literal() {
+            Ok(t) => t,
+            Err(e) => return Err(e),
+        };
+        match lexer_impls::chars::decode_char(literal) {
+            Some(c) => Ok(c),
+            None => Err(self.error_here(ErrorKind::WithMessage("literal is not a single character"))),
+        }
+    }
+
+    /// like `extract_literal`, but with the escapes of a string literal resolved, see
+    /// `literal_value::cook_string`. only allocates if there are escapes.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn extract_cooked_string(&mut self) -> LexerResult<Cow<'source, [u8]>> {
+        self.extract_literal().map(cook_string)
+    }
+
     /// # Safety
     ///
     /// more of a correctness requirement: use `extract_literal` instead, or
@@ -300,13 +815,63 @@ impl<'source> Lexer<'source> {
     pub const unsafe fn extract_literal_copy(&self) -> LexerResult<&'source [u8]> {
         match self.literal {
             Some(t) => Ok(t),
-            None => Err(LexerError::NoLiteralToExtract),
+            None => Err(self.error_here(ErrorKind::NoLiteralToExtract)),
         }
     }
 
+    #[inline]
+    const fn error_here(&self, kind: ErrorKind) -> LexerError {
+        LexerError::new(kind, Span::new(self.index, self.index))
+    }
+
+    /// 1-based line and column of the byte at `self.index()`.
+    ///
+    /// the lexer only tracks byte offsets, so this scans the source up to the index.
+    /// build a `LineIndex` once when converting many positions.
     #[inline]
     pub const fn get_line_column(&self) -> (usize, usize) {
-        (self.line, self.column)
+        self.source.line_column(self.index)
+    }
+
+    /// whether lexing has terminated: either `ErrorKind::Eof` was returned, or iterating
+    /// the lexer as an `Iterator` stopped at an error. once set, the `Iterator` impl only
+    /// yields `None`.
+    #[inline]
+    pub const fn finished(&self) -> bool {
+        self.finished
+    }
+
+    #[inline]
+    pub const fn options(&self) -> &LexerOptions {
+        &self.options
+    }
+
+    #[inline]
+    pub const fn source(&self) -> &SourceCode<'source> {
+        &self.source
+    }
+
+    /// the source from `self.index()` on, what is left to lex.
+    #[inline]
+    pub const fn remaining(&self) -> &'source str {
+        self.split_at_index().1
+    }
+
+    /// the source up to `self.index()`, what has been lexed so far.
+    #[inline]
+    pub const fn consumed(&self) -> &'source str {
+        self.split_at_index().0
+    }
+
+    /// errors on non-ascii input can leave the index inside a char, that char then counts
+    /// as consumed.
+    const fn split_at_index(&self) -> (&'source str, &'source str) {
+        let source = self.source.as_str();
+        let mut index = self.index;
+        while index < source.len() && !source.is_char_boundary(index) {
+            index += 1;
+        }
+        source.split_at(index)
     }
 
     #[inline]
@@ -319,26 +884,109 @@ impl<'source> Lexer<'source> {
         self.index
     }
 
+    /// captures the current position so speculative lexing can be undone with `rewind`.
     #[inline]
-    pub fn get_lexer_debug_state(&self) -> String {
+    pub const fn checkpoint(&self) -> LexerCheckpoint<'source> {
+        LexerCheckpoint {
+            start: self.start,
+            index: self.index,
+            literal: self.literal,
+            warning: self.warning,
+            token_count: self.token_count,
+            finished: self.finished,
+        }
+    }
+
+    /// restores the position, pending literal and warning captured by `checkpoint`.
+    /// configuration like the control byte policy is left untouched.
+    #[inline]
+    pub const fn rewind(&mut self, checkpoint: LexerCheckpoint<'source>) {
+        debug_assert!(checkpoint.index <= self.source.len());
+        self.start = checkpoint.start;
+        self.index = checkpoint.index;
+        self.literal = checkpoint.literal;
+        self.warning = checkpoint.warning;
+        self.token_count = checkpoint.token_count;
+        self.finished = checkpoint.finished;
+    }
+
+    /// a snapshot of the position, the pending literal and the current line.
+    pub fn state(&self) -> LexerState<'source> {
         let (line, column) = self.get_line_column();
-        let start = self.start();
-        let index = self.index();
-        // its fine to duplicate here because its just debug string
-        let lit = unsafe { self.extract_literal_copy() };
-        format!(
-            "lexer error at {}:{} (index {}..{}), possible literal: {:?}",
-            line, column, start, index, lit
-        )
+        LexerState {
+            start: self.start,
+            index: self.index,
+            line,
+            column,
+            literal: self.literal,
+            window: self.source.line_containing(self.index),
+        }
+    }
+
+    /// the summary of `self.state()` and the current token underlined in its surrounding
+    /// lines, for assertion messages.
+    #[cfg(feature = "alloc")]
+    pub fn get_lexer_debug_state(&self) -> String {
+        let mut out = String::new();
+        let token = Span::new(self.start.min(self.index), self.index);
+        let _ = self.state().write_summary(&mut out);
+        out.push('\n');
+        let _ = self.source.write_snippet(&mut out, token, 1);
+        out
+    }
+}
+
+/// iterator over spanned tokens that reports lexer errors instead of stopping at them,
+/// see `Lexer::spanned_tokens`.
+#[derive(Debug)]
+pub struct TokenIter<'lexer, 'source> {
+    lexer: &'lexer mut Lexer<'source>,
+}
+
+impl<'lexer, 'source> TokenIter<'lexer, 'source> {
+    #[inline]
+    pub const fn lexer(&self) -> &Lexer<'source> {
+        self.lexer
+    }
+
+    /// the underlying lexer, e.g. to `extract_literal` the token that was just yielded.
+    #[inline]
+    pub const fn lexer_mut(&mut self) -> &mut Lexer<'source> {
+        self.lexer
     }
 }
 
+impl<'lexer, 'source> Iterator for TokenIter<'lexer, 'source> {
+    type Item = LexerResult<Spanned<Token>>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.lexer.lex_single_token_spanned() {
+            Err(e) if e.is_eof() => None,
+            res => Some(res),
+        }
+    }
+}
+
+impl FusedIterator for TokenIter<'_, '_> {}
+
+/// yields tokens until the end or the first error, whichever comes first. use
+/// `Lexer::spanned_tokens` to see the errors and keep going after them.
 impl<'source> Iterator for Lexer<'source> {
     type Item = Token;
 
     #[inline]
     fn next(&mut self) -> Option<Token> {
-        self.lex_single_token().ok()
+        if self.finished {
+            return None;
+        }
+        match self.lex_single_token() {
+            Ok(tok) => Some(tok),
+            Err(_) => {
+                self.finished = true;
+                None
+            }
+        }
     }
 }
 
@@ -346,10 +994,15 @@ impl FusedIterator for Lexer<'_> {}
 
 #[cfg(test)]
 mod tests {
+    use alloc::string::{String, ToString};
+    use alloc::vec;
+    use alloc::vec::Vec;
+
     use crate::{
-        lexer::{Lexer, LexerError, LexerResult},
+        lexer::{ErrorKind, Lexer, LexerError, LexerLimits, LexerOptions, LexerResult, Limit},
         source_code::SourceCode,
-        types::Token,
+        test_util::checked_lexer::CheckedLexer,
+        types::{Span, Spanned, Token},
     };
 
     #[test]
@@ -357,15 +1010,327 @@ mod tests {
         let text = "     \n\tlet freeform() ; = <= + 3 >= != \n";
         let mut lexer = Lexer::new(SourceCode::new(text));
         assert_eq!(lexer.next(), Some(Token::KwLet));
-        assert_eq!(lexer.extract_literal(), Err(LexerError::NoLiteralToExtract));
+        assert_eq!(lexer.extract_literal().map_err(|e| e.kind), Err(ErrorKind::NoLiteralToExtract));
 
         assert_eq!(lexer.next(), Some(Token::LitIdentifier));
         assert_eq!(lexer.extract_literal(), Ok(&b"freeform"[..]));
-        assert_eq!(lexer.extract_literal(), Err(LexerError::NoLiteralToExtract));
+        assert_eq!(lexer.extract_literal().map_err(|e| e.kind), Err(ErrorKind::NoLiteralToExtract));
 
         assert_eq!(lexer.next(), Some(Token::IndentLParen));
     }
 
+    #[test]
+    fn spans_cover_whole_tokens() {
+        let text = "let name = \"quit smoking\"; 'V' 3.14 // trailing\n>>=";
+        let mut lexer = Lexer::new(SourceCode::new(text));
+        let mut spans = vec![];
+        loop {
+            match lexer.lex_single_token_spanned() {
+                Ok(tok) => spans.push((tok.value, &text[tok.span.range()])),
+                Err(e) if e.is_eof() => break,
+                Err(e) => panic!("lexer error: {:?}", e),
+            }
+        }
+
+        assert_eq!(
+            spans,
+            [
+                (Token::KwLet, "let"),
+                (Token::LitIdentifier, "name"),
+                (Token::PuncEq, "="),
+                (Token::LitStr, "\"quit smoking\""),
+                (Token::PuncSemi, ";"),
+                (Token::LitChar, "'V'"),
+                (Token::LitFloat, "3.14"),
+                (Token::PuncShrEq, ">>="),
+            ]
+        );
+    }
+
+    #[test]
+    fn spanned_tokens_surface_errors() {
+        let text = "a $ b `";
+        let mut lexer = Lexer::new(SourceCode::new(text));
+        let mut iter = lexer.spanned_tokens();
+
+        let first = iter.next().unwrap().unwrap();
+        assert_eq!((first.value, first.span), (Token::LitIdentifier, Span::new(0, 1)));
+        assert_eq!(iter.lexer_mut().extract_literal(), Ok(&b"a"[..]));
+        assert_eq!(iter.next(), Some(Err(LexerError::new(ErrorKind::InvalidCharacter, Span::new(2, 3)))));
+        assert_eq!(iter.next(), Some(Ok(Spanned::new(Token::LitIdentifier, Span::new(4, 5)))));
+        assert_eq!(iter.next(), Some(Err(LexerError::new(ErrorKind::InvalidCharacter, Span::new(6, 7)))));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next(), None);
+
+        // the plain iterator can't tell the error apart from the end
+        let mut lexer = Lexer::new(SourceCode::new(text));
+        assert_eq!(lexer.by_ref().count(), 1);
+
+        // a multi-byte character is one error covering all of its bytes
+        let text = "1 é 2 🦀";
+        let mut lexer = Lexer::new(SourceCode::new(text));
+        let spans: Vec<_> = lexer.spanned_tokens().map(|token| token.map_err(|e| e.span)).collect();
+        assert_eq!(
+            spans,
+            [
+                Ok(Spanned::new(Token::LitInteger, Span::new(0, 1))),
+                Err(Span::new(2, 4)),
+                Ok(Spanned::new(Token::LitInteger, Span::new(5, 6))),
+                Err(Span::new(7, 11)),
+            ]
+        );
+        assert_eq!(SourceCode::new(text).snippet(Span::new(2, 4)), "é");
+    }
+
+    #[test]
+    fn error_recovery_emits_error_tokens() {
+        let text = "a $ \"\\m\" '' b";
+        let mut lexer = Lexer::new(SourceCode::new(text)).with_error_recovery(true);
+        let tokens: Vec<_> = lexer.spanned_tokens().map(Result::unwrap).collect();
+        assert_eq!(
+            tokens,
+            [
+                Spanned::new(Token::LitIdentifier, Span::new(0, 1)),
+                Spanned::new(Token::Error, Span::new(2, 3)),
+                Spanned::new(Token::Error, Span::new(4, 8)),
+                Spanned::new(Token::Error, Span::new(9, 11)),
+                Spanned::new(Token::LitIdentifier, Span::new(12, 13)),
+            ]
+        );
+        assert_eq!(lexer.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::Eof));
+
+        let mut lexer = Lexer::new(SourceCode::new("a é b")).with_error_recovery(true);
+        let tokens: Vec<_> = lexer.spanned_tokens().map(Result::unwrap).collect();
+        assert_eq!(tokens[1], Spanned::new(Token::Error, Span::new(2, 4)));
+        assert_eq!(tokens[2], Spanned::new(Token::LitIdentifier, Span::new(5, 6)));
+
+        let mut lexer = Lexer::new(SourceCode::new("\"unterminated")).with_error_recovery(true);
+        assert_eq!(lexer.lex_single_token(), Ok(Token::Error));
+        assert_eq!(lexer.extract_literal().map_err(|e| e.kind), Err(ErrorKind::NoLiteralToExtract));
+        assert_eq!(lexer.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::Eof));
+    }
+
+    #[test]
+    fn iterator_is_fused_after_errors() {
+        let mut lexer = Lexer::new(SourceCode::new("a $ b c"));
+        assert!(!lexer.finished());
+        assert_eq!(lexer.next(), Some(Token::LitIdentifier));
+        assert_eq!(lexer.next(), None);
+        assert!(lexer.finished());
+        // `b` and `c` are lexable, but the iterator already terminated
+        assert_eq!(lexer.next(), None);
+        assert_eq!(lexer.next(), None);
+
+        let mut lexer = Lexer::new(SourceCode::new("a"));
+        assert_eq!(lexer.next(), Some(Token::LitIdentifier));
+        assert!(!lexer.finished());
+        assert_eq!(lexer.next(), None);
+        assert!(lexer.finished());
+
+        let mut lexer = Lexer::new(SourceCode::new(""));
+        assert_eq!(lexer.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::Eof));
+        assert!(lexer.finished());
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn extract_literal_str_keeps_utf8() {
+        let options = LexerOptions::new().unicode_identifiers(true);
+        let mut lexer = Lexer::with_options(SourceCode::new("héllo \"ünï\" 'é' 12"), options);
+        let mut literals = Vec::new();
+        while lexer.lex_single_token().is_ok() {
+            literals.push(lexer.extract_literal_str().unwrap());
+        }
+        assert_eq!(literals, ["héllo", "ünï", "é", "12"]);
+        assert_eq!(lexer.extract_literal_str().map_err(|e| e.kind), Err(ErrorKind::NoLiteralToExtract));
+    }
+
+    #[test]
+    fn errors_display_readably() {
+        assert_eq!(
+            ErrorKind::UnexpectedEofWhile(Token::LitStr).to_string(),
+            "unexpected end of input while lexing {string}"
+        );
+        assert_eq!(ErrorKind::WithMessage("custom").to_string(), "custom");
+        let error = LexerError::new(ErrorKind::InvalidCharacter, Span::new(0, 1));
+        assert_eq!(error.to_string(), "E0004: invalid character");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn errors_are_std_errors() {
+        fn first_error(source: &str) -> Result<(), Box<dyn std::error::Error>> {
+            let mut lexer = Lexer::new(SourceCode::new(source));
+            loop {
+                match lexer.lex_single_token() {
+                    Err(e) if e.is_eof() => return Ok(()),
+                    res => res?,
+                };
+            }
+        }
+        assert!(first_error("let x = 1;").is_ok());
+        assert_eq!(first_error("let x = '").unwrap_err().to_string(), "E0001: unexpected end of input while lexing {char}");
+    }
+
+    #[test]
+    fn errors_carry_spans_and_codes() {
+        let text = "let s = \"open\nx";
+        let mut lexer = Lexer::new(SourceCode::new(text));
+        let error = loop {
+            if let Err(e) = lexer.lex_single_token() {
+                break e;
+            }
+        };
+        assert_eq!(error, LexerError::new(ErrorKind::UnexpectedEofWhile(Token::LitStr), Span::new(8, 15)));
+        assert_eq!(error.code(), "E0001");
+        assert_eq!(lexer.source().line_column(error.span.start), (1, 9));
+
+        let eof = lexer.lex_single_token().unwrap_err();
+        assert!(eof.is_eof());
+        assert_eq!(eof.span, Span::new(15, 15));
+        assert_eq!(lexer.extract_literal().unwrap_err().span, Span::new(15, 15));
+
+        let codes: Vec<&str> = [
+            ErrorKind::UnexpectedEofWhile(Token::LitStr),
+            ErrorKind::WithMessage(""),
+            ErrorKind::InvalidEscapeSequence,
+            ErrorKind::InvalidCharacter,
+            ErrorKind::UnclosedCharLiteral,
+            ErrorKind::EmptyCharLiteral,
+            ErrorKind::ControlCharacterInLiteral,
+            ErrorKind::NoLiteralToExtract,
+            ErrorKind::Eof,
+            ErrorKind::Internal,
+            ErrorKind::LimitExceeded(Limit::TokenCount),
+        ]
+        .iter()
+        .map(ErrorKind::code)
+        .collect();
+        assert_eq!(
+            codes,
+            ["E0001", "E0002", "E0003", "E0004", "E0005", "E0006", "E0007", "E0008", "E0009", "E0010", "E0011"]
+        );
+    }
+
+    #[test]
+    fn limits_stop_pathological_input() {
+        fn first_error(source: &str, limits: LexerLimits) -> LexerError {
+            let options = LexerOptions::new().error_recovery(true).limits(limits);
+            let mut lexer = Lexer::with_options(SourceCode::new(source), options);
+            loop {
+                if let Err(e) = lexer.lex_single_token() {
+                    break e;
+                }
+            }
+        }
+
+        let limits = LexerLimits::new().max_string_len(4);
+        assert!(first_error("\"abcd\" 'x'", limits).is_eof());
+        let string = "let s = \"abcdefgh".repeat(1000);
+        assert_eq!(
+            first_error(&string, limits),
+            LexerError::new(ErrorKind::LimitExceeded(Limit::StringLength), Span::new(8, 14))
+        );
+
+        let limits = LexerLimits::new().max_comment_len(3);
+        assert!(first_error("//abc\nx //a", limits).is_eof());
+        assert_eq!(
+            first_error("x // abcdef\n", limits),
+            LexerError::new(ErrorKind::LimitExceeded(Limit::CommentLength), Span::new(2, 7))
+        );
+
+        let limits = LexerLimits::new().max_token_len(3);
+        assert!(first_error("abc 123 \"a\"", limits).is_eof());
+        assert_eq!(
+            first_error("ab abcd", limits),
+            LexerError::new(ErrorKind::LimitExceeded(Limit::TokenLength), Span::new(3, 7))
+        );
+
+        let limits = LexerLimits::new().max_tokens(2);
+        let mut lexer = Lexer::with_options(SourceCode::new("a b c"), LexerOptions::new().limits(limits));
+        assert_eq!(lexer.lex_single_token(), Ok(Token::LitIdentifier));
+        let checkpoint = lexer.checkpoint();
+        assert_eq!(lexer.lex_single_token(), Ok(Token::LitIdentifier));
+        let error = lexer.lex_single_token().unwrap_err();
+        assert!(error.is_limit_exceeded());
+        assert_eq!(error.span, Span::new(4, 5));
+        assert!(lexer.extract_literal().is_err());
+        lexer.rewind(checkpoint);
+        assert_eq!(lexer.lex_single_token(), Ok(Token::LitIdentifier));
+    }
+
+    #[test]
+    fn state_snapshots_position_and_literal() {
+        let mut lexer = Lexer::new(SourceCode::new("let a;\n\tlet s = \"é\" + bc"));
+        while lexer.index() < 25 {
+            lexer.lex_single_token().unwrap();
+        }
+        let state = lexer.state();
+        assert_eq!((state.start, state.index, state.line, state.column), (23, 25, 2, 19));
+        assert_eq!(state.literal, Some(&b"bc"[..]));
+        assert_eq!(state.window, Spanned::new("\tlet s = \"é\" + bc", Span::new(7, 25)));
+        assert_eq!(
+            state.to_string(),
+            "lexer at 2:19 (index 23..25), pending literal: \"bc\"\n\tlet s = \"é\" + bc\n\t                ^"
+        );
+        // the snapshot doesn't take the literal
+        assert_eq!(lexer.extract_literal(), Ok(&b"bc"[..]));
+        assert!(lexer.state().to_string().contains("pending literal: none"));
+        assert_eq!(
+            lexer.get_lexer_debug_state(),
+            "lexer at 2:19 (index 23..25), pending literal: none\n\
+             1 | let a;\n\
+             2 | \tlet s = \"é\" + bc\n  \
+               | \t              ^^\n"
+        );
+    }
+
+    #[test]
+    fn remaining_and_consumed_split_the_source() {
+        let text = "let é = \"ü\";";
+        let mut lexer = Lexer::new(SourceCode::new(text));
+        assert_eq!((lexer.consumed(), lexer.remaining()), ("", text));
+        lexer.lex_single_token().unwrap();
+        lexer.lex_single_token().unwrap_err();
+        assert_eq!((lexer.consumed(), lexer.remaining()), ("let é", " = \"ü\";"));
+        while !lexer.lex_single_token().is_err_and(|e| e.is_eof()) {}
+        assert_eq!((lexer.consumed(), lexer.remaining()), (text, ""));
+        assert_eq!(lexer.source().as_str(), text);
+
+        let limits = LexerLimits::new().max_comment_len(1);
+        let mut lexer = Lexer::with_options(SourceCode::new("//éé"), LexerOptions::new().limits(limits));
+        assert!(lexer.lex_single_token().unwrap_err().is_limit_exceeded());
+        assert_eq!((lexer.consumed(), lexer.remaining()), ("//é", "é"));
+    }
+
+    #[test]
+    fn rewind_restores_checkpoint() {
+        let mut lexer = Lexer::new(SourceCode::new("let a\n= \"x\";"));
+        assert_eq!(lexer.lex_single_token(), Ok(Token::KwLet));
+        let checkpoint = lexer.checkpoint();
+        let (line, column) = lexer.get_line_column();
+
+        let mut speculated = Vec::new();
+        while let Ok(token) = lexer.lex_single_token() {
+            speculated.push((token, lexer.extract_literal().ok()));
+        }
+        assert!(lexer.finished());
+        assert_eq!(lexer.get_line_column().0, 2);
+
+        lexer.rewind(checkpoint);
+        assert!(!lexer.finished());
+        assert_eq!(lexer.get_line_column(), (line, column));
+        assert_eq!(lexer.extract_literal().map_err(|e| e.kind), Err(ErrorKind::NoLiteralToExtract));
+        assert_eq!(lexer.checkpoint(), checkpoint);
+
+        let mut replayed = Vec::new();
+        while let Ok(token) = lexer.lex_single_token() {
+            replayed.push((token, lexer.extract_literal().ok()));
+        }
+        assert_eq!(speculated, replayed);
+        assert_eq!(replayed.len(), 4);
+    }
+
     #[test]
     fn invalid_characters_test() {
         // [0..=255]
@@ -383,7 +1348,7 @@ mod tests {
         let mut pairs = vec![];
 
         while !l.is_at_end() {
-            let res = l.clone().lex_single_token() == Err(LexerError::InvalidCharacter);
+            let res = l.clone().lex_single_token().is_err_and(|e| e.kind == ErrorKind::InvalidCharacter);
             let byte = lossy_str.as_bytes()[l.index()];
             l.advance();
             pairs.push((res, byte));
@@ -396,7 +1361,7 @@ mod tests {
 
     #[test]
     fn test_operators() {
-        let source = "! - * / + << >> < <= > >= == != = += -= *= /= %= &= |= ^= <<= >>=";
+        let source = "! - * / + << >> < <= > >= == != = += -= *= /= %= &= |= ^= <<= >>= ? ~ # @ => :: :";
         let mut l = Lexer::new(SourceCode::new(source));
 
         let expected = [
@@ -424,6 +1389,13 @@ mod tests {
             Token::PuncXorEq,
             Token::PuncShlEq,
             Token::PuncShrEq,
+            Token::PuncQuestion,
+            Token::PuncTilde,
+            Token::PuncHash,
+            Token::PuncAt,
+            Token::PuncFatArrow,
+            Token::PuncColonColon,
+            Token::PuncColon,
         ];
         let mut index = 0;
 
@@ -433,10 +1405,9 @@ mod tests {
             match val {
                 Ok(tok) => {
                     assert_eq!(expected[index], tok, "Error at {}th, expected {:?}", index, expected[index]);
-                    println!("Matched {:?} with {:?}", tok, expected[index]);
                     index += 1;
                 }
-                Err(LexerError::Eof) => break,
+                Err(e) if e.is_eof() => break,
                 Err(e) => {
                     panic!("lexer error: {:?}\n\t{}", e, l.get_lexer_debug_state());
                 }
@@ -448,7 +1419,7 @@ mod tests {
     #[test]
     fn eof_after_all_tokens_no_ub_for_miri() {
         let fail_sources = &[
-            "2485.", "\"fdf", "\"", "'v", "'", r#""\""#, r#""\"#, r#""\m""#, r#""\\"#, r#"'\'"#, r#"'\\"#, r#"'\"#, r#"'\m'"#,
+            "2485.", "\"fdf", "\"", "'v", "'", r#""\""#, r#""\"#, r#""\m""#, r#""\\"#, r#"'\'"#, r#"'\\"#, r#"'\"#, r#"'\m'"#, "''",
         ];
         let sources = &[
             // ident, eof
@@ -467,6 +1438,8 @@ mod tests {
             "struct",
             "enum",
             "union",
+            "mod",
+            "import",
             "true",
             "false",
             "uninit",
@@ -503,6 +1476,10 @@ mod tests {
             "&",
             "|",
             "^",
+            "?",
+            "~",
+            "#",
+            "@",
             "<<",
             ">>",
             "+=",
@@ -524,18 +1501,18 @@ mod tests {
         ];
 
         for correct in sources {
-            let mut l = Lexer::new(SourceCode::new(correct));
+            let mut l = CheckedLexer::new(SourceCode::new(correct));
             assert!(!l.is_at_end());
             assert!(l.lex_single_token().is_ok());
             assert!(l.is_at_end());
             let index = l.index();
-            assert_eq!(l.lex_single_token(), Err(LexerError::Eof));
+            assert_eq!(l.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::Eof));
             assert!(l.is_at_end());
             assert_eq!(l.index(), index);
 
             let mut new_source = correct.to_string();
             new_source.push('.');
-            let mut l = Lexer::new(SourceCode::new(&new_source));
+            let mut l = CheckedLexer::new(SourceCode::new(&new_source));
             assert!(!l.is_at_end());
             let first = l.lex_single_token();
             if first.is_ok() {
@@ -548,14 +1525,14 @@ mod tests {
                     assert_eq!(second, Ok(Token::PuncDot));
                 }
                 Err(_) => {
-                    assert_eq!(second, Err(LexerError::Eof));
+                    assert_eq!(second.map_err(|e| e.kind), Err(ErrorKind::Eof));
                 }
             }
             assert!(l.is_at_end());
 
             let mut new_source = correct.to_string();
             new_source.push('f');
-            let mut l = Lexer::new(SourceCode::new(&new_source));
+            let mut l = CheckedLexer::new(SourceCode::new(&new_source));
             assert!(!l.is_at_end());
             let first = l.lex_single_token();
             assert!(first.is_ok());
@@ -574,7 +1551,7 @@ mod tests {
             let second = l.lex_single_token();
             match first.unwrap() {
                 Token::LitIdentifier => {
-                    assert_eq!(second, Err(LexerError::Eof));
+                    assert_eq!(second.map_err(|e| e.kind), Err(ErrorKind::Eof));
                 }
                 _ => {
                     assert_eq!(second, Ok(Token::LitIdentifier))
@@ -584,12 +1561,12 @@ mod tests {
         }
 
         for incorrect in fail_sources {
-            let mut l = Lexer::new(SourceCode::new(incorrect));
+            let mut l = CheckedLexer::new(SourceCode::new(incorrect));
             assert!(!l.is_at_end());
             assert!(l.lex_single_token().is_err());
             assert!(l.is_at_end(), "source: \"{}\", {:?}", &incorrect, l.get_lexer_debug_state());
             let index = l.index();
-            assert_eq!(l.lex_single_token(), Err(LexerError::Eof));
+            assert_eq!(l.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::Eof));
             assert!(l.is_at_end());
             assert_eq!(l.index(), index);
         }
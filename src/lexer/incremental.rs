@@ -0,0 +1,221 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::lexer::{Lexer, LexerError, LexerResult};
+use crate::source_code::SourceCode;
+use crate::types::{Span, Spanned, Token};
+
+/// an owned source and its token list, kept up to date across text edits by re-lexing
+/// only the region around each edit.
+///
+/// every entry is either a token or the error that was hit at that spot, in source order,
+/// so the list always equals what lexing the whole source from scratch would produce.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncrementalLexer {
+    source: String,
+    tokens: Vec<Spanned<LexerResult<Token>>>,
+    /// byte range of each token's literal, parallel to `tokens`.
+    literals: Vec<Option<Span>>,
+}
+
+impl IncrementalLexer {
+    pub fn new(source: String) -> Self {
+        let mut lexer = IncrementalLexer {
+            source,
+            tokens: Vec::new(),
+            literals: Vec::new(),
+        };
+        let (tokens, literals, _) = lex_from(&lexer.source, 0, |_| false);
+        lexer.tokens = tokens;
+        lexer.literals = literals;
+        lexer
+    }
+
+    #[inline]
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    #[inline]
+    pub fn tokens(&self) -> &[Spanned<LexerResult<Token>>] {
+        &self.tokens
+    }
+
+    /// the literal of the token at `index`, if it has one.
+    #[inline]
+    pub fn literal(&self, index: usize) -> Option<&str> {
+        let span = self.literals.get(index).copied().flatten()?;
+        Some(&self.source[span.range()])
+    }
+
+    /// replaces the bytes in `range` with `text` and re-lexes the affected tokens.
+    /// returns the indices of the tokens that were replaced in the new token list.
+    ///
+    /// # Panics
+    ///
+    /// if `range` is out of bounds or doesn't lie on char boundaries.
+    pub fn edit(&mut self, range: Range<usize>, text: &str) -> Range<usize> {
+        self.source.replace_range(range.clone(), text);
+        let old_end = range.end;
+        let new_end = range.start + text.len();
+        let shift = |offset: usize| offset - old_end + new_end;
+
+        // the first token touching the edit may grow into it, and the one before that may
+        // have peeked at the byte after itself, so lexing restarts one token earlier. the
+        // end of the token before the restart point is a position the lexer really was in,
+        // unlike the edit start which may be inside a comment
+        let first_touched = self.tokens.partition_point(|t| t.span.end < range.start);
+        let mut restart = first_touched.saturating_sub(1);
+        // errors on non-ascii input advance byte by byte, so a token may end mid-char
+        while restart > 0 && !self.source.is_char_boundary(self.tokens[restart - 1].span.end) {
+            restart -= 1;
+        }
+        let restart_offset = match restart {
+            0 => 0,
+            _ => self.tokens[restart - 1].span.end,
+        };
+
+        // tokens that start after the old edit end are unaffected text, only shifted
+        let first_kept = self.tokens.partition_point(|t| t.span.start < old_end);
+        let kept_starts: Vec<usize> = self.tokens[first_kept..].iter().map(|t| shift(t.span.start)).collect();
+
+        // relex until a token starts where an unaffected one does, from there on the lexer
+        // sees the same text as before and would produce the same tokens
+        let (mut tokens, mut literals, resynced) = lex_from(&self.source, restart_offset, |start| {
+            start >= new_end && kept_starts.binary_search(&start).is_ok()
+        });
+        let rejoin = match resynced {
+            Some(start) => {
+                tokens.pop();
+                literals.pop();
+                first_kept + kept_starts.binary_search(&start).unwrap()
+            }
+            None => self.tokens.len(),
+        };
+
+        for (token, literal) in self.tokens[rejoin..].iter_mut().zip(&mut self.literals[rejoin..]) {
+            token.span = Span::new(shift(token.span.start), shift(token.span.end));
+            if let Err(e) = &mut token.value {
+                e.span = token.span;
+            }
+            if let Some(literal) = literal {
+                *literal = Span::new(shift(literal.start), shift(literal.end));
+            }
+        }
+
+        let replaced = restart..restart + tokens.len();
+        self.tokens.splice(restart..rejoin, tokens);
+        self.literals.splice(restart..rejoin, literals);
+        replaced
+    }
+}
+
+/// lexes `source` starting at the token boundary `offset` until `stop` returns true for the
+/// start of a token. that token is still included as the last entry and its start returned.
+#[allow(clippy::type_complexity)]
+fn lex_from(
+    source: &str,
+    offset: usize,
+    mut stop: impl FnMut(usize) -> bool,
+) -> (Vec<Spanned<LexerResult<Token>>>, Vec<Option<Span>>, Option<usize>) {
+    let rest = &source[offset..];
+    let mut lexer = Lexer::new(SourceCode::new(rest));
+    let (mut tokens, mut literals) = (Vec::new(), Vec::new());
+    loop {
+        lexer.skip_whitespace();
+        let start = lexer.index();
+        let result = lexer.lex_single_token();
+        if result.is_err_and(|e| e.is_eof()) {
+            break;
+        }
+        let literal = lexer.extract_literal().ok().map(|literal| {
+            let literal_start = literal.as_ptr() as usize - rest.as_ptr() as usize + offset;
+            Span::new(literal_start, literal_start + literal.len())
+        });
+        let span = Span::new(start + offset, lexer.index() + offset);
+        tokens.push(Spanned::new(result.map_err(|e| LexerError::new(e.kind, span)), span));
+        literals.push(literal);
+        if stop(start + offset) {
+            return (tokens, literals, Some(start + offset));
+        }
+    }
+    (tokens, literals, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::IncrementalLexer;
+    use crate::types::Token;
+
+    #[track_caller]
+    fn assert_matches_fresh(lexer: &IncrementalLexer) {
+        let fresh = IncrementalLexer::new(lexer.source().to_string());
+        assert_eq!(lexer, &fresh, "source: {:?}", lexer.source());
+    }
+
+    #[test]
+    fn edits_only_relex_locally() {
+        let mut lexer = IncrementalLexer::new("let a = 1;\nlet b = 2;\nlet c = 3;\n".to_string());
+        assert_eq!(lexer.tokens().len(), 15);
+
+        let replaced = lexer.edit(15..16, "bigger");
+        assert_eq!(lexer.source(), "let a = 1;\nlet bigger = 2;\nlet c = 3;\n");
+        assert!(replaced.len() <= 3, "{replaced:?}");
+        assert_eq!(lexer.literal(6), Some("bigger"));
+        assert_eq!(lexer.literal(13), Some("3"));
+        assert_matches_fresh(&lexer);
+    }
+
+    #[test]
+    fn edits_that_change_far_tokens() {
+        let mut lexer = IncrementalLexer::new("let a = 1; // x\nlet b = \"s\";".to_string());
+
+        // opening a string swallows everything up to the next quote
+        lexer.edit(8..8, "\"");
+        assert_eq!(lexer.tokens()[3].value, Ok(Token::LitStr));
+        assert_matches_fresh(&lexer);
+        lexer.edit(8..9, "");
+        assert_matches_fresh(&lexer);
+
+        // commenting out the first line
+        lexer.edit(0..0, "//");
+        assert_eq!(lexer.tokens()[0].value, Ok(Token::KwLet));
+        assert_eq!(lexer.tokens()[0].span.start, 18);
+        assert_matches_fresh(&lexer);
+
+        // merging tokens across the edit
+        lexer.edit(0..2, "");
+        lexer.edit(9..10, "");
+        assert_matches_fresh(&lexer);
+        lexer.edit(4..4, "<");
+        lexer.edit(5..5, "=");
+        assert_matches_fresh(&lexer);
+    }
+
+    #[test]
+    fn random_edits_match_fresh_lexing() {
+        let text = crate::test_util::source_generator::generate_program(Default::default());
+        let mut lexer = IncrementalLexer::new(text[..2000.min(text.len())].to_string());
+        let snippets = ["", "\"", "//", "\n", " ", "x", "1.", "=", "'a'", "é", "fn", "}"];
+        let mut seed = 0x9e37_79b9_u32;
+        for _ in 0..300 {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            let len = lexer.source().len();
+            let mut start = seed as usize % (len + 1);
+            let mut end = (start + (seed >> 8) as usize % 4).min(len);
+            while !lexer.source().is_char_boundary(start) {
+                start -= 1;
+            }
+            while !lexer.source().is_char_boundary(end) {
+                end += 1;
+            }
+            lexer.edit(start..end, snippets[(seed >> 16) as usize % snippets.len()]);
+            assert_matches_fresh(&lexer);
+        }
+    }
+}
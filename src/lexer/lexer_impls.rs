@@ -1,10 +1,11 @@
 use crate::lexer::Lexer;
-use core::hint::assert_unchecked;
-use core::slice;
 
+pub mod chars;
 pub mod high_level;
 pub mod identifiers;
 pub mod numbers;
+pub mod raw;
+pub mod runs;
 pub mod skip_whitespace;
 
 /// byte-level traversal
@@ -21,8 +22,8 @@ impl<'source> Lexer<'source> {
     #[track_caller]
     pub const unsafe fn peek_unchecked(&self) -> u8 {
         unsafe {
-            assert_unchecked(!self.is_at_end());
-            *self.source.as_bytes().as_ptr().add(self.index)
+            raw::assume(!self.is_at_end());
+            raw::byte_at(self.source.as_bytes(), self.index)
         }
     }
 
@@ -53,14 +54,7 @@ impl<'source> Lexer<'source> {
         unsafe {
             let byte = self.peek_unchecked();
             self.index += 1;
-            if byte == b'\n' {
-                self.line += 1;
-                self.column = 1;
-                byte
-            } else {
-                self.column += 1;
-                byte
-            }
+            byte
         }
     }
 
@@ -89,8 +83,8 @@ impl<'source> Lexer<'source> {
     #[track_caller]
     pub const unsafe fn peek_next_unchecked(&self) -> u8 {
         unsafe {
-            assert_unchecked(self.index + 1 < self.source.len());
-            *self.source.as_bytes().as_ptr().add(self.index + 1)
+            raw::assume(self.index + 1 < self.source.len());
+            raw::byte_at(self.source.as_bytes(), self.index + 1)
         }
     }
 
@@ -123,7 +117,7 @@ impl<'source> Lexer<'source> {
     #[track_caller]
     pub const unsafe fn matches_unchecked(&mut self, expected: u8) -> bool {
         unsafe {
-            assert_unchecked(!self.is_at_end());
+            raw::assume(!self.is_at_end());
             let byte = self.peek_unchecked();
             if byte == expected {
                 self.advance_unchecked();
@@ -195,39 +189,30 @@ impl<'source> Lexer<'source> {
     #[inline]
     #[track_caller]
     pub const unsafe fn slice_here(&self) -> &'source [u8] {
-        unsafe {
-            let ptr = self.source.as_bytes().as_ptr().add(self.start);
-            let len = self.index - self.start;
-            slice::from_raw_parts(ptr, len)
-        }
+        unsafe { raw::subslice(self.source.as_bytes(), self.start, self.index) }
     }
 
     /// # Safety
     ///
     /// - `self.index` must be bigger than 0
     /// - `self.index` must be smaller than or equal to self.source.len()
-    /// - `self.line` must be bigger than 0
     #[inline]
     #[track_caller]
     pub const unsafe fn backtrack_unchecked(&mut self) -> u8 {
         unsafe {
-            self.index = self.index.unchecked_sub(1);
-            let byte = self.peek_unchecked();
-            if byte == b'\n' {
-                self.line = self.line.unchecked_sub(1);
-                // TODO DANGER WE HAVE TO BACK TRACK UNTIL THE PREVIOUS NEWLINE OR START OF SOURCE
-                // AND FIX UP self.column FOR THE FUCKING DEBUSF GHBKJL;FSDLGSDL;G
-                self.column = 1;
-            }
-            byte
+            raw::assume(self.index > 0);
+            self.index -= 1;
+            self.peek_unchecked()
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::vec::Vec;
+
     use crate::{
-        lexer::{Lexer, LexerError},
+        lexer::{ErrorKind, Lexer},
         source_code::SourceCode,
         types::Token,
     };
@@ -262,7 +247,7 @@ mod tests {
         assert_eq!(lexer.index(), 0);
         assert_eq!(lexer.start(), lexer.start);
         assert_eq!(lexer.start(), 0);
-        assert_eq!(lexer.get_line_column(), (1, 0));
+        assert_eq!(lexer.get_line_column(), (1, 1));
 
         assert_eq!(lexer.next(), Some(Token::KwLet));
         assert_eq!(lexer.start(), 0);
@@ -274,6 +259,63 @@ mod tests {
         assert_eq!(lexer.extract_literal(), Ok(&b"x"[..]));
     }
 
+    #[test]
+    fn crlf_is_a_single_newline() {
+        let lf = "let a\nlet\n\nb //x\n\tc\n";
+        let crlf = lf.replace('\n', "\r\n");
+        let mixed = "let a\r\nlet\n\r\nb //x\n\tc\r\n";
+
+        let mut expected = Lexer::new(SourceCode::new(lf));
+        let mut crlf = Lexer::new(SourceCode::new(&crlf));
+        let mut mixed = Lexer::new(SourceCode::new(mixed));
+        loop {
+            let token = expected.lex_single_token();
+            assert_eq!(crlf.lex_single_token().map_err(|e| e.kind), token.map_err(|e| e.kind));
+            assert_eq!(mixed.lex_single_token().map_err(|e| e.kind), token.map_err(|e| e.kind));
+            assert_eq!(crlf.get_line_column(), expected.get_line_column());
+            assert_eq!(mixed.get_line_column(), expected.get_line_column());
+            if token.is_err_and(|e| e.is_eof()) {
+                break;
+            }
+        }
+        assert_eq!(expected.get_line_column(), (6, 1));
+
+        // a lone `\r` is not a newline
+        let mut lexer = Lexer::new(SourceCode::new("a\rb"));
+        assert_eq!(lexer.next(), Some(Token::LitIdentifier));
+        assert_eq!(lexer.next(), Some(Token::LitIdentifier));
+        assert_eq!(lexer.get_line_column(), (1, 4));
+    }
+
+    #[test]
+    fn backtracking_over_newlines_restores_columns() {
+        let text = "ab\ncd\r\n\nef";
+        let source = SourceCode::new(text);
+        let mut lexer = Lexer::new(source.clone());
+        while lexer.advance().is_some() {}
+        assert_eq!(lexer.get_line_column(), (4, 3));
+
+        let mut positions = Vec::new();
+        while lexer.index() > 0 {
+            // SAFETY: index is in `1..=len`
+            unsafe { lexer.backtrack_unchecked() };
+            assert_eq!(lexer.get_line_column(), source.line_column(lexer.index()));
+            positions.push(lexer.get_line_column());
+        }
+        positions.reverse();
+        assert_eq!(
+            positions,
+            [(1, 1), (1, 2), (1, 3), (2, 1), (2, 2), (2, 3), (2, 3), (3, 1), (4, 1), (4, 2)]
+        );
+
+        // the closing quote of a multi-line string is found by backtracking
+        let mut lexer = Lexer::new(SourceCode::new("\"a\r\nbc\n\" x"));
+        assert_eq!(lexer.lex_single_token(), Ok(Token::LitStr));
+        assert_eq!(lexer.get_line_column(), (3, 2));
+        assert_eq!(lexer.lex_single_token(), Ok(Token::LitIdentifier));
+        assert_eq!(lexer.get_line_column(), (3, 4));
+    }
+
     #[test]
     fn bytelevel_peek() {
         let source = "hi";
@@ -350,18 +392,30 @@ mod tests {
         assert_eq!(lexer.lex_single_token(), Ok(Token::LitChar));
         assert_eq!(lexer.extract_literal(), Ok(&b"V"[..]));
 
+        let text = "'é' '水' '🦀' '\\n'";
+        let mut lexer = Lexer::new(SourceCode::new(text));
+        for expected in ['é', '水', '🦀', '\n'] {
+            assert_eq!(lexer.lex_single_token(), Ok(Token::LitChar));
+            assert_eq!(lexer.extract_char(), Ok(expected));
+        }
+        assert_eq!(lexer.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::Eof));
+
+        let text = "'éa'";
+        let mut lexer = Lexer::new(SourceCode::new(text));
+        assert_eq!(lexer.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::InvalidCharacter));
+
         let fail1 = r#"
             "unterminated
         "#;
         let mut lexer = Lexer::new(SourceCode::new(fail1));
-        assert_eq!(lexer.lex_single_token(), Err(LexerError::UnexpectedEofWhile(Token::LitStr)));
-        assert_eq!(lexer.extract_literal(), Err(LexerError::NoLiteralToExtract));
+        assert_eq!(lexer.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::UnexpectedEofWhile(Token::LitStr)));
+        assert_eq!(lexer.extract_literal().map_err(|e| e.kind), Err(ErrorKind::NoLiteralToExtract));
 
         let fail2 = r#"
             'v
         "#;
         let mut lexer = Lexer::new(SourceCode::new(fail2));
-        assert_eq!(lexer.lex_single_token(), Err(LexerError::InvalidCharacter));
-        assert_eq!(lexer.extract_literal(), Err(LexerError::NoLiteralToExtract));
+        assert_eq!(lexer.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::InvalidCharacter));
+        assert_eq!(lexer.extract_literal().map_err(|e| e.kind), Err(ErrorKind::NoLiteralToExtract));
     }
 }
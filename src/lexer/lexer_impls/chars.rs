@@ -0,0 +1,86 @@
+/// length of the utf-8 sequence started by `lead`.
+///
+/// `lead` is assumed to be at a character boundary of valid utf-8, which is
+/// always the case for bytes coming out of a `SourceCode`.
+#[inline]
+pub const fn utf8_sequence_len(lead: u8) -> usize {
+    match lead {
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        _ => 4,
+    }
+}
+
+/// decodes the body of a character literal (the bytes between the quotes)
+/// into the `char` it denotes, resolving escapes.
+///
+/// returns `None` if the body is not exactly one escape or one unicode scalar value.
+#[inline]
+pub const fn decode_char(body: &[u8]) -> Option<char> {
+    match body {
+        [b'\\', escaped] => match escaped {
+            b'\'' => Some('\''),
+            b't' => Some('\t'),
+            b'n' => Some('\n'),
+            b'r' => Some('\r'),
+            b'\\' => Some('\\'),
+            b'0' => Some('\0'),
+            _ => None,
+        },
+        [lead, ..] => {
+            if utf8_sequence_len(*lead) != body.len() {
+                return None;
+            }
+            match core::str::from_utf8(body) {
+                Ok(s) => {
+                    let bytes = s.as_bytes();
+                    let scalar = match bytes.len() {
+                        1 => bytes[0] as u32,
+                        2 => ((bytes[0] as u32 & 0x1f) << 6) | (bytes[1] as u32 & 0x3f),
+                        3 => ((bytes[0] as u32 & 0x0f) << 12) | ((bytes[1] as u32 & 0x3f) << 6) | (bytes[2] as u32 & 0x3f),
+                        _ => {
+                            ((bytes[0] as u32 & 0x07) << 18)
+                                | ((bytes[1] as u32 & 0x3f) << 12)
+                                | ((bytes[2] as u32 & 0x3f) << 6)
+                                | (bytes[3] as u32 & 0x3f)
+                        }
+                    };
+                    char::from_u32(scalar)
+                }
+                Err(_) => None,
+            }
+        }
+        [] => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_char, utf8_sequence_len};
+
+    #[test]
+    fn sequence_lengths_match_std() {
+        for c in ['a', '\0', 'é', 'ő', '€', '水', '🦀'] {
+            let mut buf = [0u8; 4];
+            let encoded = c.encode_utf8(&mut buf);
+            assert_eq!(utf8_sequence_len(encoded.as_bytes()[0]), c.len_utf8());
+        }
+    }
+
+    #[test]
+    fn decodes_scalars_and_escapes() {
+        assert_eq!(decode_char(b"V"), Some('V'));
+        assert_eq!(decode_char("é".as_bytes()), Some('é'));
+        assert_eq!(decode_char("€".as_bytes()), Some('€'));
+        assert_eq!(decode_char("🦀".as_bytes()), Some('🦀'));
+        assert_eq!(decode_char(br"\n"), Some('\n'));
+        assert_eq!(decode_char(br"\'"), Some('\''));
+        assert_eq!(decode_char(br"\0"), Some('\0'));
+
+        assert_eq!(decode_char(b""), None);
+        assert_eq!(decode_char(b"ab"), None);
+        assert_eq!(decode_char("éa".as_bytes()), None);
+        assert_eq!(decode_char(br"\m"), None);
+    }
+}
@@ -1,9 +1,11 @@
+use crate::lexer::ControlBytePolicy;
+use crate::lexer::ErrorKind;
 use crate::lexer::Lexer;
-use crate::lexer::LexerError;
-use crate::lexer::LexerResult;
+use crate::lexer::LexerWarning;
+use crate::lexer::Limit;
 use crate::lexer::lexer_impls;
 use crate::lexer::lexer_impls::identifiers::check_identifier_actual_token;
-use crate::lexer::lexer_impls::identifiers::is_valid_identifier_tail;
+use crate::lexer::lexer_impls::runs;
 use crate::lexer::lexer_impls::skip_whitespace::skip_whitespace_impl;
 use crate::types::Token;
 
@@ -23,20 +25,19 @@ impl<'source> Lexer<'source> {
     ///
     /// - `self.start` points to the first character of the identifier
     /// - `self.index` points to one character after `self.start` (may be at the end)
-    /// - character pointed to by `self.start` is `alnum | "_"`
+    /// - character pointed to by `self.start` is `alnum | "_"`, or any non-ascii byte
+    ///   if unicode identifiers are enabled
     ///
     /// After this function returns, you may be at the end.
     pub const unsafe fn lex_identifier(&mut self) -> Token {
-        while !self.is_at_end() {
-            // SAFETY: we are guaranteed to not be at the end here
-
-            let byte = unsafe { self.peek_unchecked() };
-            if is_valid_identifier_tail(byte) {
-                unsafe { self.advance_unchecked() };
-            } else {
-                break;
-            }
-        }
+        let class = if self.options.unicode_identifiers {
+            runs::UNICODE_IDENTIFIER
+        } else {
+            runs::IDENTIFIER
+        };
+        // the source is a `str` and runs only ever stop at an ascii byte, so the
+        // identifier always ends on a char boundary
+        self.index += runs::run_len(self.source.as_bytes(), self.index, class);
 
         // SAFETY: self.index can at most equal the source length here, and that is fine
         let slice = unsafe { self.slice_here() };
@@ -61,12 +62,17 @@ impl<'source> Lexer<'source> {
     ///
     /// After this function returns, you may be at the end.
     #[inline]
-    pub const unsafe fn lex_quoted_string(&mut self) -> LexerResult<Token> {
+    pub const unsafe fn lex_quoted_string(&mut self) -> Result<Token, ErrorKind> {
         if self.is_at_end() {
-            return Err(LexerError::UnexpectedEofWhile(Token::LitStr));
+            return Err(ErrorKind::UnexpectedEofWhile(Token::LitStr));
         }
 
         while !self.is_at_end() {
+            // checked per byte so that huge unterminated strings are not scanned to the end
+            if self.index - self.start - 1 > self.options.limits.get_max_string_len() {
+                return Err(ErrorKind::LimitExceeded(Limit::StringLength));
+            }
+
             // SAFETY: we are guaranteed to not be at the end here
 
             let byte = unsafe { self.advance_unchecked() };
@@ -78,7 +84,7 @@ impl<'source> Lexer<'source> {
                 }
                 b'\\' => {
                     let Some(escaped) = self.advance() else {
-                        return Err(LexerError::UnexpectedEofWhile(Token::LitStr));
+                        return Err(ErrorKind::UnexpectedEofWhile(Token::LitStr));
                     };
 
                     match escaped {
@@ -89,38 +95,34 @@ impl<'source> Lexer<'source> {
                         b'x' => {
                             // byte escape sequence
                             // follow rust: \xNN where n is a hexadecimal character, not shorter, not longer.
-                            return Err(LexerError::WithMessage("byte escape sequences are not implemented yet"));
+                            return Err(ErrorKind::WithMessage("byte escape sequences are not implemented yet"));
                         }
                         _ => {
                             // invalid escape
-                            if self.is_at_end() {
-                                return Err(LexerError::UnexpectedEofWhile(Token::LitStr));
-                            }
                             // "hello world \m\m\" "
                             //                     ^
-                            while !self.is_at_end() {
-                                let byte = unsafe { self.advance_unchecked() };
-                                match byte {
-                                    b'"' => {
-                                        unsafe { self.backtrack_unchecked() };
-                                        break;
-                                    }
-                                    b'\\' => {
-                                        let Some(_) = self.advance() else {
-                                            return Err(LexerError::UnexpectedEofWhile(Token::LitStr));
-                                        };
-                                    }
-                                    _ => continue,
-                                }
-                            }
-                            if self.is_at_end() {
-                                return Err(LexerError::UnexpectedEofWhile(Token::LitStr));
+                            return match self.skip_rest_of_quoted_string() {
+                                Ok(()) => Err(ErrorKind::InvalidEscapeSequence),
+                                Err(e) => Err(e),
+                            };
+                        }
+                    }
+                }
+                // tabs and newlines are fine, multi-line strings are allowed
+                c if c.is_ascii_control() && !lexer_impls::skip_whitespace::is_whitespace(c) => {
+                    match self.options.control_bytes {
+                        ControlBytePolicy::Accept => continue,
+                        ControlBytePolicy::Warn => {
+                            if self.warning.is_none() {
+                                self.warning = Some(LexerWarning::ControlCharacterInLiteral(self.index - 1));
                             }
-                            // the loop exit conditions were to either be at the end, or be at a quote
-                            // we checked if we were at the end above, so we're guaranteed to be at a quote.
-                            unsafe { self.advance_unchecked() };
-
-                            return Err(LexerError::InvalidEscapeSequence);
+                            continue;
+                        }
+                        ControlBytePolicy::Reject => {
+                            return match self.skip_rest_of_quoted_string() {
+                                Ok(()) => Err(ErrorKind::ControlCharacterInLiteral),
+                                Err(e) => Err(e),
+                            };
                         }
                     }
                 }
@@ -132,13 +134,13 @@ impl<'source> Lexer<'source> {
         }
 
         if self.is_at_end() {
-            return Err(LexerError::UnexpectedEofWhile(Token::LitStr));
+            return Err(ErrorKind::UnexpectedEofWhile(Token::LitStr));
         }
 
         unsafe {
             if self.peek_unchecked() != b'"' {
                 self.advance_unchecked();
-                return Err(LexerError::InvalidCharacter);
+                return Err(ErrorKind::InvalidCharacter);
             }
         }
 
@@ -161,6 +163,28 @@ impl<'source> Lexer<'source> {
         Ok(Token::LitStr)
     }
 
+    /// skips to just after the closing quote of the string literal we are inside of,
+    /// honoring escapes. used to resume lexing after an error inside a string.
+    ///
+    /// After this function returns, you may be at the end.
+    #[inline]
+    const fn skip_rest_of_quoted_string(&mut self) -> Result<(), ErrorKind> {
+        while !self.is_at_end() {
+            // SAFETY: we are guaranteed to not be at the end here
+            let byte = unsafe { self.advance_unchecked() };
+            match byte {
+                b'"' => return Ok(()),
+                b'\\' => {
+                    if self.advance().is_none() {
+                        return Err(ErrorKind::UnexpectedEofWhile(Token::LitStr));
+                    }
+                }
+                _ => continue,
+            }
+        }
+        Err(ErrorKind::UnexpectedEofWhile(Token::LitStr))
+    }
+
     /// if this function returns a value matching `Ok(t) if t.is_identifier_extractable()`,
     /// you can extract the specific literal by using `self.extract_literal()` and
     /// unsafely unwrap it **once** before any modification.
@@ -173,18 +197,23 @@ impl<'source> Lexer<'source> {
     ///
     /// After this function returns, you may be at the end.
     #[inline]
-    pub const unsafe fn lex_character_literal(&mut self) -> LexerResult<Token> {
+    pub const unsafe fn lex_character_literal(&mut self) -> Result<Token, ErrorKind> {
         if self.is_at_end() {
-            return Err(LexerError::UnexpectedEofWhile(Token::LitChar));
+            return Err(ErrorKind::UnexpectedEofWhile(Token::LitChar));
         }
 
         let byte = unsafe { self.peek_unchecked() };
 
         match byte {
+            b'\'' => {
+                // consume the closing quote so lexing can resume after the literal
+                unsafe { self.advance_unchecked() };
+                return Err(ErrorKind::EmptyCharLiteral);
+            }
             b'\\' => {
                 let Some(escaped) = self.peek_next() else {
                     unsafe { self.advance_unchecked() };
-                    return Err(LexerError::UnexpectedEofWhile(Token::LitChar));
+                    return Err(ErrorKind::UnexpectedEofWhile(Token::LitChar));
                 };
 
                 match escaped {
@@ -198,7 +227,7 @@ impl<'source> Lexer<'source> {
                     b'x' => {
                         // byte escape sequence
                         // follow rust: \xNN where n is a hexadecimal character, not shorter, not longer.
-                        return Err(LexerError::WithMessage("byte escape sequences are not implemented yet"));
+                        return Err(ErrorKind::WithMessage("byte escape sequences are not implemented yet"));
                     }
                     // '\mf;
                     //    ^
@@ -208,32 +237,47 @@ impl<'source> Lexer<'source> {
                             self.advance_unchecked();
                         }
                         if self.is_at_end() {
-                            return Err(LexerError::UnexpectedEofWhile(Token::LitChar));
+                            return Err(ErrorKind::UnexpectedEofWhile(Token::LitChar));
                         }
 
                         let val = unsafe { self.advance_unchecked() };
                         if val != b'\'' {
-                            return Err(LexerError::UnclosedCharLiteral);
+                            return Err(ErrorKind::UnclosedCharLiteral);
                         }
 
                         // invalid escape
-                        return Err(LexerError::InvalidEscapeSequence);
+                        return Err(ErrorKind::InvalidEscapeSequence);
                     }
                 }
             }
-            _ => unsafe {
-                self.advance_unchecked();
-            },
+            _ if byte.is_ascii_control() => {
+                // control characters have to be written as escapes, e.g. `'\0'` or `'\n'`.
+                // consume the closing quote too, if any, so lexing can resume after the literal
+                unsafe { self.advance_unchecked() };
+                if self.peek_default() == b'\'' {
+                    unsafe { self.advance_unchecked() };
+                }
+                return Err(ErrorKind::ControlCharacterInLiteral);
+            }
+            _ => {
+                // a single unicode scalar value may span multiple bytes. the source is valid
+                // utf-8 and we are at a character boundary, so all of its bytes are present.
+                let mut remaining = lexer_impls::chars::utf8_sequence_len(byte);
+                while remaining != 0 {
+                    unsafe { self.advance_unchecked() };
+                    remaining -= 1;
+                }
+            }
         };
 
         if self.is_at_end() {
-            return Err(LexerError::UnexpectedEofWhile(Token::LitChar));
+            return Err(ErrorKind::UnexpectedEofWhile(Token::LitChar));
         }
 
         unsafe {
             if self.peek_unchecked() != b'\'' {
                 self.advance_unchecked();
-                return Err(LexerError::InvalidCharacter);
+                return Err(ErrorKind::InvalidCharacter);
             }
         }
 
@@ -268,23 +312,13 @@ impl<'source> Lexer<'source> {
     ///
     /// After this function returns, you may be at the end.
     #[inline]
-    pub const unsafe fn lex_ambiguous_number_literal(&mut self) -> LexerResult<Token> {
-        while !self.is_at_end() {
-            // SAFETY: we are guaranteed to not be at the end here
-
-            let byte = unsafe { self.peek_unchecked() };
+    pub const unsafe fn lex_ambiguous_number_literal(&mut self) -> Result<Token, ErrorKind> {
+        self.index += runs::run_len(self.source.as_bytes(), self.index, runs::DIGIT);
 
-            match byte {
-                c if lexer_impls::numbers::is_valid_digit(c) => unsafe { self.advance_unchecked() },
-                b'.' => {
-                    unsafe {
-                        self.advance_unchecked();
-                        return lex_dot_after_integer(self);
-                    };
-                }
-                _ => {
-                    break;
-                }
+        if let Some(b'.') = self.peek() {
+            unsafe {
+                self.advance_unchecked();
+                return lex_dot_after_integer(self);
             };
         }
 
@@ -304,11 +338,11 @@ impl<'source> Lexer<'source> {
 /// - pass `lexer_impls::numbers::is_valid_digit`.
 /// - `lexer.source.as_bytes()[lexer.index - 1]` must be a `.` character. (you should've already consumed the dot)
 #[inline]
-pub const unsafe fn lex_dot_after_integer(lexer: &mut Lexer<'_>) -> LexerResult<Token> {
+pub const unsafe fn lex_dot_after_integer(lexer: &mut Lexer<'_>) -> Result<Token, ErrorKind> {
     if lexer.is_at_end() {
         // TODO:
         // @backtracking:1 = return the lit integer and set index properly for dot
-        return Err(LexerError::UnexpectedEofWhile(Token::LitFloat));
+        return Err(ErrorKind::UnexpectedEofWhile(Token::LitFloat));
     }
 
     // checking part after dot
@@ -345,7 +379,7 @@ pub const unsafe fn lex_dot_after_integer(lexer: &mut Lexer<'_>) -> LexerResult<
             //      10. abs()
             //         ^ known whitespace (comments not allowed here) TODO support comments here
             // @backtracking:2 = return the lit integer and set index properly for dot
-            return Err(LexerError::WithMessage("lexing whitespace after `{integer}.` is todo"));
+            return Err(ErrorKind::WithMessage("lexing whitespace after `{integer}.` is todo"));
         }
         // 10.abs()
         c if lexer_impls::identifiers::is_valid_identifier_head(c) => {
@@ -355,9 +389,9 @@ pub const unsafe fn lex_dot_after_integer(lexer: &mut Lexer<'_>) -> LexerResult<
             // @backtracking:3 = return the lit integer and set index properly for the dot
             //
             // TODO method call on integer literal
-            return Err(LexerError::WithMessage("lexing method calls on integer literals are todo"));
+            return Err(ErrorKind::WithMessage("lexing method calls on integer literals are todo"));
         }
-        _ => return Err(LexerError::UnexpectedEofWhile(Token::LitFloat)),
+        _ => return Err(ErrorKind::UnexpectedEofWhile(Token::LitFloat)),
     }
 
     // SAFETY: self.start is 1 after the start quote, self.index is at the end quote
@@ -372,7 +406,7 @@ pub const unsafe fn lex_dot_after_integer(lexer: &mut Lexer<'_>) -> LexerResult<
 #[cfg(test)]
 mod tests {
     use crate::{
-        lexer::{Lexer, LexerError, lexer_impls},
+        lexer::{ControlBytePolicy, ErrorKind, Lexer, LexerOptions, LexerWarning, lexer_impls},
         source_code::SourceCode,
         types::Token,
     };
@@ -436,25 +470,88 @@ mod tests {
         ));
 
         assert!(lexer.lex_single_token().is_err());
-        assert_eq!(lexer.extract_literal(), Err(LexerError::NoLiteralToExtract));
+        assert_eq!(lexer.extract_literal().map_err(|e| e.kind), Err(ErrorKind::NoLiteralToExtract));
     }
 
     #[test]
     fn litchar_extensive() {
         let text = "'\\mf";
         let mut l = Lexer::new(SourceCode::new(text));
-        assert_eq!(l.lex_single_token(), Err(LexerError::UnclosedCharLiteral));
+        assert_eq!(l.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::UnclosedCharLiteral));
         assert!(l.is_at_end());
 
         let text = "'\\m'";
         let mut l = Lexer::new(SourceCode::new(text));
-        assert_eq!(l.lex_single_token(), Err(LexerError::InvalidEscapeSequence));
+        assert_eq!(l.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::InvalidEscapeSequence));
         assert!(l.is_at_end());
 
         let text = "'\\m";
         let mut l = Lexer::new(SourceCode::new(text));
-        assert_eq!(l.lex_single_token(), Err(LexerError::UnexpectedEofWhile(Token::LitChar)));
+        assert_eq!(l.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::UnexpectedEofWhile(Token::LitChar)));
+        assert!(l.is_at_end());
+
+        for text in ["'\0'", "'\n'", "'\t'", "'\x7f'"] {
+            let mut l = Lexer::new(SourceCode::new(text));
+            assert_eq!(l.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::ControlCharacterInLiteral));
+            assert!(l.is_at_end());
+        }
+
+        let text = "'' x";
+        let mut l = Lexer::new(SourceCode::new(text));
+        assert_eq!(l.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::EmptyCharLiteral));
+        assert_eq!(l.lex_single_token(), Ok(Token::LitIdentifier));
+        assert_eq!(l.extract_literal(), Ok(&b"x"[..]));
+    }
+
+    #[test]
+    fn control_byte_policy() {
+        let text = "\"a\0b\x01\" x \"tab\tand\nnewline\"";
+
+        let mut l = Lexer::new(SourceCode::new(text));
+        assert_eq!(l.lex_single_token(), Ok(Token::LitStr));
+        assert_eq!(l.take_warning(), None);
+        assert_eq!(l.extract_literal(), Ok(&b"a\0b\x01"[..]));
+
+        let mut l = Lexer::new(SourceCode::new(text)).with_control_byte_policy(ControlBytePolicy::Warn);
+        assert_eq!(l.lex_single_token(), Ok(Token::LitStr));
+        assert_eq!(l.take_warning(), Some(LexerWarning::ControlCharacterInLiteral(2)));
+        assert_eq!(l.take_warning(), None);
+        assert_eq!(l.extract_literal(), Ok(&b"a\0b\x01"[..]));
+        assert_eq!(l.lex_single_token(), Ok(Token::LitIdentifier));
+        assert_eq!(l.take_warning(), None);
+
+        let mut l = Lexer::new(SourceCode::new(text)).with_control_byte_policy(ControlBytePolicy::Reject);
+        assert_eq!(l.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::ControlCharacterInLiteral));
+        assert_eq!(l.lex_single_token(), Ok(Token::LitIdentifier));
+        assert_eq!(l.extract_literal(), Ok(&b"x"[..]));
+        // whitespace control bytes are never affected
+        assert_eq!(l.lex_single_token(), Ok(Token::LitStr));
         assert!(l.is_at_end());
+
+        let text = "\"\0 unterminated";
+        let mut l = Lexer::new(SourceCode::new(text)).with_control_byte_policy(ControlBytePolicy::Reject);
+        assert_eq!(l.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::UnexpectedEofWhile(Token::LitStr)));
+        assert!(l.is_at_end());
+    }
+
+    #[test]
+    fn unicode_identifiers_option() {
+        let text = "größe é_1 ünïcödé;";
+
+        let mut l = Lexer::new(SourceCode::new(text));
+        assert_eq!(l.lex_single_token(), Ok(Token::LitIdentifier));
+        assert_eq!(l.extract_literal(), Ok(&b"gr"[..]));
+        assert_eq!(l.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::InvalidCharacter));
+
+        let options = LexerOptions::new().unicode_identifiers(true).error_recovery(true);
+        let mut l = Lexer::with_options(SourceCode::new(text), options);
+        assert_eq!(l.options(), &options);
+        for expected in ["größe", "é_1", "ünïcödé"] {
+            assert_eq!(l.lex_single_token(), Ok(Token::LitIdentifier));
+            assert_eq!(l.extract_literal(), Ok(expected.as_bytes()));
+        }
+        assert_eq!(l.lex_single_token(), Ok(Token::PuncSemi));
+        assert_eq!(l.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::Eof));
     }
 
     #[test]
@@ -462,16 +559,16 @@ mod tests {
         let text = r#"
         "\m\m""#;
         let mut l = Lexer::new(SourceCode::new(text));
-        assert_eq!(l.lex_single_token(), Err(LexerError::InvalidEscapeSequence));
+        assert_eq!(l.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::InvalidEscapeSequence));
         assert!(l.is_at_end());
         let text = r#"
         "\m\"   ""#;
         let mut l = Lexer::new(SourceCode::new(text));
-        assert_eq!(l.lex_single_token(), Err(LexerError::InvalidEscapeSequence));
+        assert_eq!(l.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::InvalidEscapeSequence));
         assert!(l.is_at_end());
         let text = r#""\m\n"#;
         let mut l = Lexer::new(SourceCode::new(text));
-        assert_eq!(l.lex_single_token(), Err(LexerError::UnexpectedEofWhile(Token::LitStr)));
+        assert_eq!(l.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::UnexpectedEofWhile(Token::LitStr)));
         assert!(l.is_at_end(), "source: `{}`, lexer:\n\t{}", text, l.get_lexer_debug_state());
     }
 }
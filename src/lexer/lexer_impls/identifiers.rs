@@ -1,145 +1,124 @@
 use crate::lexer::Lexer;
 use crate::types::Token;
-use core::slice;
 
-#[inline(always)]
-pub const fn const_index(s: &[u8], index: usize) -> Option<u8> {
-    if index >= s.len() {
-        None
-    } else {
-        Some(unsafe { *s.as_ptr().add(index) })
+/// every word that lexes to something other than `Token::LitIdentifier`.
+///
+/// this is the only place a new keyword has to be added, the lookup table below is built
+/// from it at compile time.
+pub const KEYWORDS: &[(&str, Token)] = &[
+    ("let", Token::KwLet),
+    ("fn", Token::KwFn),
+    ("return", Token::KwReturn),
+    ("extern", Token::KwExtern),
+    ("const", Token::KwConst),
+    ("mut", Token::KwMut),
+    ("anymut", Token::KwAnymut),
+    ("compiletime", Token::KwCompiletime),
+    ("runtime", Token::KwRuntime),
+    ("static", Token::KwStatic),
+    ("type", Token::KwType),
+    ("cast", Token::KwCast),
+    ("if", Token::KwIf),
+    ("else", Token::KwElse),
+    ("while", Token::KwWhile),
+    ("loop", Token::KwLoop),
+    ("break", Token::KwBreak),
+    ("continue", Token::KwContinue),
+    ("match", Token::KwMatch),
+    ("mod", Token::KwMod),
+    ("import", Token::KwImport),
+    ("struct", Token::KwAdtStruct),
+    ("enum", Token::KwAdtEnum),
+    ("union", Token::KwAdtUnion),
+    ("uninit", Token::LitUninit),
+];
+
+const MIN_KEYWORD_LEN: usize = keyword_len_bound(false);
+const MAX_KEYWORD_LEN: usize = keyword_len_bound(true);
+
+const KEYWORD_TABLE_BITS: u32 = 6;
+const KEYWORD_TABLE_LEN: usize = 1 << KEYWORD_TABLE_BITS;
+const _: () = assert!(KEYWORDS.len() < u8::MAX as usize && KEYWORDS.len() <= KEYWORD_TABLE_LEN);
+
+/// the first seed for which `keyword_hash` maps every keyword to its own slot.
+const KEYWORD_SEED: u32 = {
+    let mut seed = 1;
+    while build_keyword_table(seed).is_none() {
+        seed += 1;
+        assert!(seed < 100_000, "no perfect hash seed for KEYWORDS, grow KEYWORD_TABLE_BITS");
+    }
+    seed
+};
+
+/// `1 + index into KEYWORDS` per hash slot, `0` for empty slots.
+const KEYWORD_TABLE: [u8; KEYWORD_TABLE_LEN] = match build_keyword_table(KEYWORD_SEED) {
+    Some(table) => table,
+    None => unreachable!(),
+};
+
+const fn keyword_len_bound(max: bool) -> usize {
+    let mut bound = if max { 0 } else { usize::MAX };
+    let mut index = 0;
+    while index < KEYWORDS.len() {
+        let len = KEYWORDS[index].0.len();
+        if (max && len > bound) || (!max && len < bound) {
+            bound = len;
+        }
+        index += 1;
     }
+    bound
 }
 
+/// FNV-1a with a custom offset basis, folded into `KEYWORD_TABLE_BITS` bits.
 #[inline(always)]
-pub const fn const_subslice(s: &[u8], start: usize, end: usize) -> Option<&[u8]> {
-    if start > end || end > s.len() {
-        None
-    } else {
-        Some(unsafe { slice::from_raw_parts(s.as_ptr().add(start), end - start) })
+const fn keyword_hash(s: &[u8], seed: u32) -> usize {
+    let mut hash = 0x811c_9dc5 ^ seed;
+    let mut index = 0;
+    while index < s.len() {
+        hash = (hash ^ s[index] as u32).wrapping_mul(0x0100_0193);
+        index += 1;
     }
+    (hash >> (32 - KEYWORD_TABLE_BITS)) as usize
 }
 
-/// trie implementation
+const fn build_keyword_table(seed: u32) -> Option<[u8; KEYWORD_TABLE_LEN]> {
+    let mut table = [0; KEYWORD_TABLE_LEN];
+    let mut index = 0;
+    while index < KEYWORDS.len() {
+        let slot = keyword_hash(KEYWORDS[index].0.as_bytes(), seed);
+        if table[slot] != 0 {
+            return None;
+        }
+        table[slot] = index as u8 + 1;
+        index += 1;
+    }
+    Some(table)
+}
+
+/// the keyword token spelled by `s`, if any. one hash and at most one comparison.
+#[inline]
+pub const fn keyword_token(s: &[u8]) -> Option<Token> {
+    if s.len() < MIN_KEYWORD_LEN || s.len() > MAX_KEYWORD_LEN {
+        return None;
+    }
+    match KEYWORD_TABLE[keyword_hash(s, KEYWORD_SEED)] {
+        0 => None,
+        slot => {
+            let (keyword, token) = KEYWORDS[slot as usize - 1];
+            if const_slice_eq(s, keyword.as_bytes()) { Some(token) } else { None }
+        }
+    }
+}
+
+/// keyword lookup, see `KEYWORDS`.
 ///
 /// # Safety
 ///
 /// s.len() must be at least 1.
 pub const unsafe fn check_identifier_actual_token<'src>(lexer: &mut Lexer<'src>, s: &'src [u8]) -> Token {
-    let r = match unsafe { *s.as_ptr() } {
-        b'l' => {
-            // let
-            identifier_check_rest(s, 1, b"et", Token::KwLet)
-        }
-        b'f' => {
-            // fn
-            identifier_check_rest(s, 1, b"n", Token::KwFn)
-        }
-        b'r' => {
-            // return, runtime
-            let Some(next) = const_index(s, 1) else {
-                return Token::LitIdentifier;
-            };
-            match next {
-                b'e' => identifier_check_rest(s, 2, b"turn", Token::KwReturn),
-                b'u' => identifier_check_rest(s, 2, b"ntime", Token::KwRuntime),
-                _ => Token::LitIdentifier,
-            }
-        }
-        b'e' => {
-            // extern, enum
-            let Some(next) = const_index(s, 1) else {
-                return Token::LitIdentifier;
-            };
-            match next {
-                b'x' => identifier_check_rest(s, 2, b"tern", Token::KwExtern),
-                b'n' => identifier_check_rest(s, 2, b"um", Token::KwAdtEnum),
-                _ => Token::LitIdentifier,
-            }
-        }
-        b'c' => {
-            // const, compiletime, cast
-            let Some(next) = const_index(s, 1) else {
-                return Token::LitIdentifier;
-            };
-            match next {
-                b'o' => {
-                    let Some(next) = const_index(s, 2) else {
-                        return Token::LitIdentifier;
-                    };
-                    match next {
-                        b'n' => identifier_check_rest(s, 3, b"st", Token::KwConst),
-                        b'm' => identifier_check_rest(s, 3, b"piletime", Token::KwCompiletime),
-                        _ => Token::LitIdentifier,
-                    }
-                }
-                b'a' => identifier_check_rest(s, 2, b"st", Token::KwCast),
-                _ => Token::LitIdentifier,
-            }
-        }
-        b'm' => {
-            // mut
-            identifier_check_rest(s, 1, b"ut", Token::KwMut)
-        }
-        b'a' => {
-            // anymut
-            identifier_check_rest(s, 1, b"nymut", Token::KwAnymut)
-        }
-        b's' => {
-            // static, struct
-            let Some(next) = const_index(s, 1) else {
-                return Token::LitIdentifier;
-            };
-            match next {
-                b't' => {
-                    let Some(next) = const_index(s, 2) else {
-                        return Token::LitIdentifier;
-                    };
-
-                    match next {
-                        b'a' => identifier_check_rest(s, 3, b"tic", Token::KwStatic),
-                        b'r' => identifier_check_rest(s, 3, b"uct", Token::KwAdtStruct),
-                        _ => Token::LitIdentifier,
-                    }
-                }
-                _ => Token::LitIdentifier,
-            }
-        }
-        b't' => {
-            // type
-            identifier_check_rest(s, 1, b"ype", Token::KwType)
-        }
-        b'u' => {
-            // union, uninit
-            let Some(next) = const_index(s, 1) else {
-                return Token::LitIdentifier;
-            };
-
-            match next {
-                b'n' => {
-                    let Some(next) = const_index(s, 2) else {
-                        return Token::LitIdentifier;
-                    };
-
-                    match next {
-                        b'i' => {
-                            let Some(next) = const_index(s, 3) else {
-                                return Token::LitIdentifier;
-                            };
-
-                            match next {
-                                b'n' => identifier_check_rest(s, 4, b"it", Token::LitUninit),
-                                b'o' => identifier_check_rest(s, 4, b"n", Token::KwAdtUnion),
-                                _ => Token::LitIdentifier,
-                            }
-                        }
-                        _ => Token::LitIdentifier,
-                    }
-                }
-                _ => Token::LitIdentifier,
-            }
-        }
-        _ => Token::LitIdentifier,
+    let r = match keyword_token(s) {
+        Some(token) => token,
+        None => Token::LitIdentifier,
     };
 
     if r.is_identifier_extractable() {
@@ -167,23 +146,6 @@ pub const fn const_slice_eq(s1: &[u8], s2: &[u8]) -> bool {
     true
 }
 
-#[inline]
-pub const fn identifier_check_rest(s: &[u8], start: usize, rest: &'static [u8], token: Token) -> Token {
-    let Some(ss) = const_subslice(s, start, s.len()) else {
-        return Token::LitIdentifier;
-    };
-
-    if const_slice_eq(ss, rest) { token } else { Token::LitIdentifier }
-}
-
-#[inline]
-pub const fn is_valid_identifier_tail(byte: u8) -> bool {
-    matches!(
-        byte,
-        b'a'..=b'z' | b'A' ..=b'Z' | b'_' | b'0' ..=b'9'
-    )
-}
-
 #[inline]
 pub const fn is_valid_identifier_head(byte: u8) -> bool {
     matches!(
@@ -191,3 +153,35 @@ pub const fn is_valid_identifier_head(byte: u8) -> bool {
         b'a'..=b'z' | b'A' ..=b'Z' | b'_'
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+
+    use super::{KEYWORDS, keyword_token};
+    use crate::types::Token;
+
+    #[test]
+    fn every_keyword_is_found() {
+        for &(keyword, token) in KEYWORDS {
+            assert_eq!(keyword_token(keyword.as_bytes()), Some(token), "{keyword}");
+            assert_eq!(token.source_repr(), keyword);
+        }
+        let keyword_tokens = Token::ALL.iter().filter(|t| KEYWORDS.iter().any(|(_, k)| k == *t)).count();
+        assert_eq!(keyword_tokens, KEYWORDS.len());
+    }
+
+    #[test]
+    fn near_misses_are_identifiers() {
+        for &(keyword, _) in KEYWORDS {
+            let bytes = keyword.as_bytes();
+            assert_eq!(keyword_token(&bytes[1..]), None, "{keyword}");
+            assert_eq!(keyword_token(&bytes[..bytes.len() - 1]), None, "{keyword}");
+            assert_eq!(keyword_token(format!("{keyword}_").as_bytes()), None, "{keyword}");
+            assert_eq!(keyword_token(keyword.to_uppercase().as_bytes()), None, "{keyword}");
+        }
+        for word in ["", "x", "lets", "uni", "compiletimes", "Fn", "statc"] {
+            assert_eq!(keyword_token(word.as_bytes()), None, "{word}");
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! the only places the lexer touches memory without a check. every `*_unchecked` fast path
+//! bottoms out in one of these.
+//!
+//! with the `safe` feature they keep their signatures but check everything, so a broken
+//! invariant panics instead of reading out of bounds or producing an invalid `str`.
+
+/// # Safety
+///
+/// `index < bytes.len()`
+#[cfg(not(feature = "safe"))]
+#[inline(always)]
+#[track_caller]
+pub const unsafe fn byte_at(bytes: &[u8], index: usize) -> u8 {
+    // SAFETY: upheld by the caller
+    unsafe { *bytes.as_ptr().add(index) }
+}
+
+/// # Safety
+///
+/// `index < bytes.len()`, checked under the `safe` feature.
+#[cfg(feature = "safe")]
+#[inline(always)]
+#[track_caller]
+pub const unsafe fn byte_at(bytes: &[u8], index: usize) -> u8 {
+    bytes[index]
+}
+
+/// `bytes[start..end]`.
+///
+/// # Safety
+///
+/// `start <= end <= bytes.len()`
+#[cfg(not(feature = "safe"))]
+#[inline(always)]
+#[track_caller]
+pub const unsafe fn subslice(bytes: &[u8], start: usize, end: usize) -> &[u8] {
+    // SAFETY: upheld by the caller
+    unsafe { core::slice::from_raw_parts(bytes.as_ptr().add(start), end - start) }
+}
+
+/// `bytes[start..end]`.
+///
+/// # Safety
+///
+/// `start <= end <= bytes.len()`, checked under the `safe` feature.
+#[cfg(feature = "safe")]
+#[inline(always)]
+#[track_caller]
+pub const unsafe fn subslice(bytes: &[u8], start: usize, end: usize) -> &[u8] {
+    bytes.split_at(end).0.split_at(start).1
+}
+
+/// # Safety
+///
+/// `bytes` must be valid utf-8.
+#[cfg(not(feature = "safe"))]
+#[inline(always)]
+#[track_caller]
+pub const unsafe fn to_str(bytes: &[u8]) -> &str {
+    // SAFETY: upheld by the caller
+    unsafe { core::str::from_utf8_unchecked(bytes) }
+}
+
+/// # Safety
+///
+/// `bytes` must be valid utf-8, checked under the `safe` feature.
+#[cfg(feature = "safe")]
+#[inline(always)]
+#[track_caller]
+pub const unsafe fn to_str(bytes: &[u8]) -> &str {
+    match core::str::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(_) => panic!("literal is not valid utf-8"),
+    }
+}
+
+/// tells the optimizer that `condition` holds.
+///
+/// # Safety
+///
+/// `condition` must be true, checked under the `safe` feature.
+#[cfg(not(feature = "safe"))]
+#[inline(always)]
+#[track_caller]
+pub const unsafe fn assume(condition: bool) {
+    // SAFETY: upheld by the caller
+    unsafe { core::hint::assert_unchecked(condition) }
+}
+
+/// tells the optimizer that `condition` holds.
+///
+/// # Safety
+///
+/// `condition` must be true, checked under the `safe` feature.
+#[cfg(feature = "safe")]
+#[inline(always)]
+#[track_caller]
+pub const unsafe fn assume(condition: bool) {
+    assert!(condition, "lexer invariant violated");
+}
+
+#[cfg(all(test, feature = "safe"))]
+mod tests {
+    use crate::lexer::Lexer;
+    use crate::source_code::SourceCode;
+
+    #[test]
+    #[should_panic]
+    fn unchecked_reads_are_checked() {
+        let lexer = Lexer::new(SourceCode::new(""));
+        // SAFETY: not upheld on purpose, the `safe` feature turns this into a panic
+        unsafe { lexer.peek_unchecked() };
+    }
+
+    #[test]
+    #[should_panic]
+    fn unchecked_slices_are_checked() {
+        let mut lexer = Lexer::new(SourceCode::new("ab"));
+        lexer.start = 3;
+        lexer.index = 2;
+        // SAFETY: not upheld on purpose, the `safe` feature turns this into a panic
+        unsafe { lexer.slice_here() };
+    }
+}
@@ -0,0 +1,85 @@
+/// byte class bits. every class is a single bit so that a whole chunk can be tested by
+/// and-ing the classes of its bytes together.
+pub const DIGIT: u8 = 1 << 0;
+/// `alnum | "_"`
+pub const IDENTIFIER: u8 = 1 << 1;
+/// `alnum | "_"` and every non-ascii byte.
+pub const UNICODE_IDENTIFIER: u8 = 1 << 2;
+
+const CLASSES: [u8; 256] = {
+    let mut classes = [0; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let b = byte as u8;
+        if b.is_ascii_digit() {
+            classes[byte] |= DIGIT;
+        }
+        if b.is_ascii_alphanumeric() || b == b'_' {
+            classes[byte] |= IDENTIFIER | UNICODE_IDENTIFIER;
+        }
+        if !b.is_ascii() {
+            classes[byte] |= UNICODE_IDENTIFIER;
+        }
+        byte += 1;
+    }
+    classes
+};
+
+#[inline(always)]
+pub const fn class_of(byte: u8) -> u8 {
+    CLASSES[byte as usize]
+}
+
+/// length of the run of bytes starting at `from` that are all in `class`.
+///
+/// checks eight bytes per step without branching on each byte, and finishes the last
+/// partial chunk byte by byte.
+#[inline]
+pub const fn run_len(bytes: &[u8], from: usize, class: u8) -> usize {
+    let mut index = from;
+    while index + 8 <= bytes.len() {
+        let all = class_of(bytes[index])
+            & class_of(bytes[index + 1])
+            & class_of(bytes[index + 2])
+            & class_of(bytes[index + 3])
+            & class_of(bytes[index + 4])
+            & class_of(bytes[index + 5])
+            & class_of(bytes[index + 6])
+            & class_of(bytes[index + 7]);
+        if all & class == 0 {
+            break;
+        }
+        index += 8;
+    }
+    while index < bytes.len() && class_of(bytes[index]) & class != 0 {
+        index += 1;
+    }
+    index - from
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::{DIGIT, IDENTIFIER, UNICODE_IDENTIFIER, run_len};
+
+    #[test]
+    fn runs_stop_at_first_non_member() {
+        let bytes = b"0123456789012345x";
+        assert_eq!(run_len(bytes, 0, DIGIT), 16);
+        assert_eq!(run_len(bytes, 3, DIGIT), 13);
+        assert_eq!(run_len(bytes, 16, DIGIT), 0);
+        assert_eq!(run_len(bytes, 17, DIGIT), 0);
+        assert_eq!(run_len(bytes, 0, IDENTIFIER), 17);
+
+        for len in 0..20 {
+            let mut text = "a_Z9".repeat(5)[..len].to_string();
+            text.push_str(" tail");
+            assert_eq!(run_len(text.as_bytes(), 0, IDENTIFIER), len);
+        }
+
+        let text = "größe_größe_größe+";
+        assert_eq!(run_len(text.as_bytes(), 0, IDENTIFIER), 2);
+        assert_eq!(run_len(text.as_bytes(), 0, UNICODE_IDENTIFIER), text.len() - 1);
+    }
+}
@@ -15,6 +15,7 @@ pub const fn skip_whitespace_impl(lexer: &mut Lexer<'_>) {
                 if let Some(byte) = lexer.peek_next()
                     && byte == b'/'
                 {
+                    let comment_start = lexer.index;
                     unsafe {
                         lexer.advance_unchecked();
                         lexer.advance_unchecked();
@@ -22,15 +23,20 @@ pub const fn skip_whitespace_impl(lexer: &mut Lexer<'_>) {
 
                     // we could be at end here
 
+                    let limit = lexer.options.limits.get_max_comment_len();
                     while !lexer.is_at_end() {
                         // SAFETY: we are guaranteed to not be at the end here
 
                         let byte = unsafe { lexer.peek_unchecked() };
-                        if byte != b'\n' {
-                            unsafe { lexer.advance_unchecked() };
-                        } else {
+                        if byte == b'\n' {
                             break;
                         }
+                        if lexer.index - comment_start - 2 == limit {
+                            // leave the comment in place, `lex_token_impl` reports it
+                            lexer.index = comment_start;
+                            return;
+                        }
+                        unsafe { lexer.advance_unchecked() };
                     }
                 } else {
                     break;
@@ -0,0 +1,137 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::lexer::{Lexer, LexerError};
+use crate::source_code::SourceCode;
+
+/// token count and errors of one lexed source, see `lex_sources`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LexStats {
+    pub bytes: usize,
+    pub tokens: usize,
+    /// lexing continues after each error.
+    pub errors: Vec<LexerError>,
+}
+
+/// result of lexing one file with `lex_files`.
+#[derive(Debug)]
+pub struct FileLexResult {
+    pub path: PathBuf,
+    /// `Err` if the file couldn't be read.
+    pub stats: io::Result<LexStats>,
+}
+
+impl LexStats {
+    /// lexes `source` to the end, counting tokens without collecting them.
+    pub fn of(source: &str) -> Self {
+        let mut lexer = Lexer::new(SourceCode::new(source));
+        let mut stats = LexStats {
+            bytes: source.len(),
+            ..LexStats::default()
+        };
+        loop {
+            match lexer.lex_single_token() {
+                Ok(_) => stats.tokens += 1,
+                Err(e) if e.is_eof() => break,
+                Err(e) => stats.errors.push(e),
+            }
+        }
+        stats
+    }
+}
+
+/// lexes every source on all available cores. results are in the order of `sources`.
+pub fn lex_sources<S: AsRef<str> + Sync>(sources: &[S]) -> Vec<LexStats> {
+    parallel_map(sources, |source| LexStats::of(source.as_ref()))
+}
+
+/// reads and lexes every file on all available cores. results are in the order of `paths`.
+pub fn lex_files<P: AsRef<Path> + Sync>(paths: &[P]) -> Vec<FileLexResult> {
+    parallel_map(paths, |path| {
+        let path = path.as_ref();
+        FileLexResult {
+            path: path.to_path_buf(),
+            stats: std::fs::read_to_string(path).map(|source| LexStats::of(&source)),
+        }
+    })
+}
+
+/// maps `items` with a scoped thread per core, threads pull the next unclaimed item so a few
+/// large files don't leave the other threads idle.
+fn parallel_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get()).min(items.len());
+    if threads <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<R>> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else { break };
+                        done.push((index, f(item)));
+                    }
+                    done
+                })
+            })
+            .collect();
+
+        let mut results: Vec<Option<R>> = (0..items.len()).map(|_| None).collect();
+        for worker in workers {
+            for (index, result) in worker.join().unwrap() {
+                results[index] = Some(result);
+            }
+        }
+        results
+    });
+    results.iter_mut().map(|result| result.take().unwrap()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LexStats, lex_files, lex_sources};
+    use crate::lexer::{ErrorKind, LexerError};
+    use crate::types::Span;
+
+    #[test]
+    fn stats_count_tokens_and_errors() {
+        let stats = LexStats::of("let x = 1; $ y");
+        assert_eq!(stats.bytes, 14);
+        assert_eq!(stats.tokens, 6);
+        assert_eq!(stats.errors, [LexerError::new(ErrorKind::InvalidCharacter, Span::new(11, 12))]);
+    }
+
+    #[test]
+    fn parallel_results_keep_input_order() {
+        let sources: Vec<String> = (0..50).map(|i| "x ".repeat(i)).collect();
+        let stats = lex_sources(&sources);
+        assert_eq!(stats.len(), 50);
+        for (i, stats) in stats.iter().enumerate() {
+            assert_eq!(stats.tokens, i);
+        }
+        assert!(lex_sources::<&str>(&[]).is_empty());
+    }
+
+    #[test]
+    fn lex_files_reports_unreadable_files() {
+        let mut paths: Vec<_> = std::fs::read_dir("progs").unwrap().map(|e| e.unwrap().path()).collect();
+        paths.sort();
+        paths.push("progs/does_not_exist.mumbo".into());
+        let results = lex_files(&paths);
+        assert_eq!(results.len(), paths.len());
+        for (result, path) in results.iter().zip(&paths) {
+            assert_eq!(&result.path, path);
+        }
+        let (missing, found) = results.split_last().unwrap();
+        assert!(missing.stats.is_err());
+        for result in found {
+            let source = std::fs::read_to_string(&result.path).unwrap();
+            assert_eq!(result.stats.as_ref().unwrap(), &LexStats::of(&source));
+        }
+    }
+}
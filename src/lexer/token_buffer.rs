@@ -0,0 +1,254 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::lexer::tokenize::tokens_to_source;
+use crate::lexer::{Lexer, LexerError};
+use crate::source_code::SourceCode;
+use crate::token_stream::{LexedToken, TokenSource};
+use crate::types::{Span, Spanned, Token};
+
+/// struct-of-arrays token list: kinds, start offsets and lengths in parallel `Vec`s.
+///
+/// literals aren't stored, they are recomputed from the span on demand. offsets are `u32`,
+/// so sources are limited to 4GiB.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenBuffer<'source> {
+    source: SourceCode<'source>,
+    kinds: Vec<Token>,
+    starts: Vec<u32>,
+    lens: Vec<u32>,
+    /// lexing continues after each error, errors are not part of the token arrays.
+    errors: Vec<LexerError>,
+}
+
+impl<'source> TokenBuffer<'source> {
+    #[inline]
+    pub fn new(source: SourceCode<'source>) -> Self {
+        Self::from_lexer(Lexer::new(source))
+    }
+
+    /// lexes the rest of `lexer`. recovered `Token::Error`s are stored like any other token.
+    pub fn from_lexer(mut lexer: Lexer<'source>) -> Self {
+        let source = lexer.source().clone();
+        assert!(source.len() <= u32::MAX as usize, "TokenBuffer sources are limited to 4GiB");
+        // a rough guess to avoid most regrowth, tokens average a few bytes
+        let capacity = source.len() / 4;
+        let mut buffer = TokenBuffer {
+            source,
+            kinds: Vec::with_capacity(capacity),
+            starts: Vec::with_capacity(capacity),
+            lens: Vec::with_capacity(capacity),
+            errors: Vec::new(),
+        };
+        loop {
+            // `Lexer::start` is not the token start for every token (it skips opening quotes)
+            lexer.skip_whitespace();
+            let start = lexer.index();
+            match lexer.lex_single_token() {
+                Ok(token) => {
+                    buffer.kinds.push(token);
+                    buffer.starts.push(start as u32);
+                    buffer.lens.push((lexer.index() - start) as u32);
+                }
+                Err(e) if e.is_eof() => break,
+                Err(e) if e.is_limit_exceeded() => {
+                    buffer.errors.push(e);
+                    break;
+                }
+                Err(e) => buffer.errors.push(e),
+            }
+        }
+        buffer
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.kinds.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty()
+    }
+
+    #[inline]
+    pub fn source(&self) -> &SourceCode<'source> {
+        &self.source
+    }
+
+    #[inline]
+    pub fn kinds(&self) -> &[Token] {
+        &self.kinds
+    }
+
+    #[inline]
+    pub fn kind(&self, index: usize) -> Option<Token> {
+        self.kinds.get(index).copied()
+    }
+
+    #[inline]
+    pub fn span(&self, index: usize) -> Option<Span> {
+        let start = *self.starts.get(index)? as usize;
+        Some(Span::new(start, start + self.lens[index] as usize))
+    }
+
+    /// the literal of the token at `index`, the same slice `Lexer::extract_literal` returns.
+    pub fn literal(&self, index: usize) -> Option<&'source [u8]> {
+        let kind = self.kind(index)?;
+        if !kind.is_identifier_extractable() {
+            return None;
+        }
+        let span = self.span(index)?;
+        let bytes = self.source.as_bytes();
+        match kind {
+            // the quotes are not part of the literal
+            Token::LitStr | Token::LitChar => Some(&bytes[span.start + 1..span.end - 1]),
+            _ => Some(&bytes[span.range()]),
+        }
+    }
+
+    #[inline]
+    pub fn errors(&self) -> &[LexerError] {
+        &self.errors
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = Spanned<Token>> + '_ {
+        (0..self.len()).map(|index| Spanned::new(self.kinds[index], self.span(index).unwrap()))
+    }
+
+    /// the tokens from the start, as a `TokenSource` for the parser.
+    #[inline]
+    pub const fn cursor(&self) -> TokenCursor<'_, 'source> {
+        TokenCursor {
+            buffer: self,
+            index: 0,
+            error: 0,
+        }
+    }
+
+    /// see `Tokenized::to_source`. `Token::Error`s are dropped.
+    pub fn to_source(&self) -> String {
+        tokens_to_source(
+            (0..self.len())
+                .filter(|&index| self.kinds[index] != Token::Error)
+                .map(|index| (self.kinds[index], self.literal(index))),
+        )
+    }
+}
+
+/// reads a `TokenBuffer` front to back. the buffer doesn't record where its errors were
+/// lexed, so they come after the last token.
+#[derive(Debug, Clone)]
+pub struct TokenCursor<'buffer, 'source> {
+    buffer: &'buffer TokenBuffer<'source>,
+    index: usize,
+    /// errors handed out so far.
+    error: usize,
+}
+
+impl<'source> TokenSource<'source> for TokenCursor<'_, 'source> {
+    fn next_token(&mut self) -> Option<Result<LexedToken<'source>, LexerError>> {
+        let index = self.index;
+        if let Some(token) = self.buffer.kind(index) {
+            self.index += 1;
+            return Some(Ok(LexedToken {
+                token,
+                span: self.buffer.span(index)?,
+                literal: self.buffer.literal(index),
+            }));
+        }
+        let error = *self.buffer.errors.get(self.error)?;
+        self.error += 1;
+        Some(Err(error))
+    }
+
+    #[inline]
+    fn text(&self) -> Option<&'source str> {
+        Some(self.buffer.source.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::TokenBuffer;
+    use crate::ast::arena::AstArena;
+    use crate::lexer::{ErrorKind, Lexer, LexerError, tokenize};
+    use crate::parser::Parser;
+    use crate::source_code::SourceCode;
+    use crate::test_util::source_generator::{ProgramShape, generate_program};
+    use crate::token_stream::TokenSource;
+    use crate::types::{Span, Token};
+
+    fn assert_matches_tokenize(text: &str) {
+        let buffer = TokenBuffer::new(SourceCode::new(text));
+        let tokenized = tokenize(text);
+        assert_eq!(buffer.iter().collect::<Vec<_>>(), tokenized.tokens);
+        assert_eq!(buffer.errors(), tokenized.errors);
+        for index in 0..buffer.len() {
+            assert_eq!(buffer.literal(index), tokenized.literal(index), "{index}");
+        }
+        assert_eq!(buffer.to_source(), tokenized.to_source());
+    }
+
+    #[test]
+    fn buffer_matches_tokenize() {
+        assert_matches_tokenize(&generate_program(ProgramShape::new(5, 10)));
+        assert_matches_tokenize("let x = \"s\\\"\"; $ 'é' 1.5 ''");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn buffer_matches_tokenize_on_corpus() {
+        for entry in std::fs::read_dir("progs").unwrap() {
+            assert_matches_tokenize(&std::fs::read_to_string(entry.unwrap().path()).unwrap());
+        }
+    }
+
+    #[test]
+    fn recovered_errors_are_tokens() {
+        let source = SourceCode::new("a $ b");
+        let buffer = TokenBuffer::from_lexer(Lexer::new(source).with_error_recovery(true));
+        assert_eq!(buffer.kinds(), [Token::LitIdentifier, Token::Error, Token::LitIdentifier]);
+        assert!(buffer.errors().is_empty());
+        assert_eq!(buffer.literal(1), None);
+        assert_eq!(buffer.to_source(), "a b");
+        assert_eq!(buffer.kind(3), None);
+        assert_eq!(buffer.span(3), None);
+    }
+
+    #[test]
+    fn cursor_yields_errors_last() {
+        let buffer = TokenBuffer::new(SourceCode::new("a $ 'b"));
+        let mut cursor = buffer.cursor();
+        let a = cursor.next_token().unwrap().unwrap();
+        assert_eq!((a.token, a.span, a.literal), (Token::LitIdentifier, Span::new(0, 1), Some(&b"a"[..])));
+        assert_eq!(cursor.next_token(), Some(Err(LexerError::new(ErrorKind::InvalidCharacter, Span::new(2, 3)))));
+        assert_eq!(cursor.next_token().map(|e| e.unwrap_err().span.start), Some(4));
+        assert_eq!(cursor.next_token(), None);
+    }
+
+    fn assert_parses_like_streamed(text: &str) {
+        let arena = AstArena::new();
+        let streamed = Parser::new(Lexer::new(SourceCode::new(text)), &arena).parse();
+        let buffer = TokenBuffer::new(SourceCode::new(text));
+        let buffered = Parser::new(buffer.cursor(), &arena).parse();
+        assert_eq!(buffered, streamed);
+    }
+
+    #[test]
+    fn buffered_and_streamed_tokens_parse_the_same() {
+        // the missing `;` looks at the text between the tokens
+        assert_parses_like_streamed("let y = 3\nlet z = 4;");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn buffered_and_streamed_tokens_parse_the_corpus_the_same() {
+        for entry in std::fs::read_dir("progs").unwrap() {
+            assert_parses_like_streamed(&std::fs::read_to_string(entry.unwrap().path()).unwrap());
+        }
+    }
+}
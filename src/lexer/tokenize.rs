@@ -0,0 +1,200 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::lexer::lexer_impls::raw;
+use crate::lexer::{Lexer, LexerError};
+use crate::source_code::SourceCode;
+use crate::types::{Spanned, Token};
+
+/// a whole source lexed in one go, see `Lexer::tokenize`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tokenized<'source> {
+    pub tokens: Vec<Spanned<Token>>,
+    /// the extracted literal of each token, parallel to `tokens`.
+    pub literals: Vec<Option<&'source [u8]>>,
+    /// every lexer error, lexing continues after each of them except for exceeded limits.
+    pub errors: Vec<LexerError>,
+}
+
+impl<'source> Tokenized<'source> {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// the literal of the token at `index`, if it has one.
+    #[inline]
+    pub fn literal(&self, index: usize) -> Option<&'source [u8]> {
+        self.literals.get(index).copied().flatten()
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (Spanned<Token>, Option<&'source [u8]>)> + '_ {
+        self.tokens.iter().copied().zip(self.literals.iter().copied())
+    }
+
+    /// reconstructs source text from the tokens and literals alone, with a single space
+    /// between two tokens only where they would otherwise lex differently. lexing the
+    /// result yields the same tokens and literals again.
+    ///
+    /// errors are not part of the token list and are dropped.
+    pub fn to_source(&self) -> String {
+        tokens_to_source(self.iter().map(|(token, literal)| (token.value, literal)))
+    }
+}
+
+/// see `Tokenized::to_source`.
+pub(crate) fn tokens_to_source<'a>(tokens: impl Iterator<Item = (Token, Option<&'a [u8]>)>) -> String {
+    let mut out = String::new();
+    // the text of the previous token followed by the current one, reused for every pair
+    let mut pair = String::new();
+    let mut previous: Option<(Token, Option<&[u8]>, usize)> = None;
+    for (token, literal) in tokens {
+        let Some((prev_token, prev_literal, prev_start)) = previous else {
+            push_token_text(&mut out, token, literal);
+            previous = Some((token, literal, 0));
+            continue;
+        };
+        pair.clear();
+        pair.push_str(&out[prev_start..]);
+        let split = pair.len();
+        push_token_text(&mut pair, token, literal);
+        if !lexes_as_pair(&pair, (prev_token, prev_literal), (token, literal)) {
+            out.push(' ');
+        }
+        previous = Some((token, literal, out.len()));
+        out.push_str(&pair[split..]);
+    }
+    out
+}
+
+fn push_token_text(out: &mut String, token: Token, literal: Option<&[u8]>) {
+    let Some(literal) = literal else {
+        out.push_str(token.source_repr());
+        return;
+    };
+    // SAFETY: literals are slices of a `str` that start and end at token boundaries
+    let literal = unsafe { raw::to_str(literal) };
+    match token {
+        Token::LitStr => {
+            out.push('"');
+            out.push_str(literal);
+            out.push('"');
+        }
+        Token::LitChar => {
+            out.push('\'');
+            out.push_str(literal);
+            out.push('\'');
+        }
+        _ => out.push_str(literal),
+    }
+}
+
+/// whether `joined`, the text of `first` immediately followed by the text of `second`, lexes
+/// back into exactly those two tokens.
+fn lexes_as_pair(joined: &str, first: (Token, Option<&[u8]>), second: (Token, Option<&[u8]>)) -> bool {
+    let mut lexer = Lexer::new(SourceCode::new(joined));
+    [first, second].into_iter().all(|(expected, expected_literal)| {
+        lexer.lex_single_token_spanned().is_ok_and(|token| token.value == expected)
+            && lexer.extract_literal().ok() == expected_literal
+    }) && lexer.lex_single_token_spanned().is_err_and(|e| e.is_eof())
+}
+
+impl<'source> Lexer<'source> {
+    /// lexes the rest of the source, extracting literals and collecting errors along the way.
+    pub fn tokenize(mut self) -> Tokenized<'source> {
+        let mut out = Tokenized::default();
+        loop {
+            match self.lex_single_token_spanned() {
+                Ok(token) => {
+                    out.tokens.push(token);
+                    out.literals.push(self.extract_literal().ok());
+                }
+                Err(e) if e.is_eof() => break,
+                Err(e) if e.is_limit_exceeded() => {
+                    out.errors.push(e);
+                    break;
+                }
+                Err(e) => out.errors.push(e),
+            }
+        }
+        out
+    }
+}
+
+/// shorthand for `Lexer::new(SourceCode::new(source)).tokenize()`.
+#[inline]
+pub fn tokenize(source: &str) -> Tokenized<'_> {
+    Lexer::new(SourceCode::new(source)).tokenize()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{Tokenized, tokenize};
+    use crate::lexer::{ErrorKind, LexerError};
+    use crate::test_util::source_generator::{ProgramShape, generate_program};
+    use crate::types::{Span, Token};
+
+    #[test]
+    fn tokenize_collects_tokens_literals_and_errors() {
+        let tokenized = tokenize("let x = \"s\"; $ 'é'");
+        let tokens: Vec<_> = tokenized.iter().map(|(t, lit)| (t.value, lit)).collect();
+        assert_eq!(
+            tokens,
+            [
+                (Token::KwLet, None),
+                (Token::LitIdentifier, Some(&b"x"[..])),
+                (Token::PuncEq, None),
+                (Token::LitStr, Some(&b"s"[..])),
+                (Token::PuncSemi, None),
+                (Token::LitChar, Some("é".as_bytes())),
+            ]
+        );
+        assert_eq!(tokenized.errors, [LexerError::new(ErrorKind::InvalidCharacter, Span::new(13, 14))]);
+        assert_eq!(tokenized.tokens[3].span, Span::new(8, 11));
+        assert_eq!(tokenized.literal(1), Some(&b"x"[..]));
+        assert_eq!(tokenized.literal(0), None);
+        assert_eq!(tokenized.literal(100), None);
+        assert_eq!(tokenized.len(), 6);
+
+        assert!(tokenize("  // nothing\n").is_empty());
+    }
+
+    fn assert_round_trips(text: &str) {
+        let tokenized = tokenize(text);
+        let printed = tokenized.to_source();
+        let relexed = tokenize(&printed);
+        assert!(relexed.errors.is_empty(), "{printed:?} has errors: {:?}", relexed.errors);
+        let tokens = |t: &Tokenized<'_>| t.iter().map(|(t, lit)| (t.value, lit.map(<[u8]>::to_vec))).collect::<Vec<_>>();
+        assert_eq!(tokens(&tokenized), tokens(&relexed), "{printed:?}");
+    }
+
+    #[test]
+    fn to_source_inserts_minimal_separators() {
+        let tokenized = tokenize("let   x :const u8=a- >b < = 'c' / /z;\n// comment\nfn f ( ) { return \"s\\\" t\"; }");
+        assert_eq!(tokenized.to_source(), "let x:const u8=a- >b< ='c'/ /z;fn f(){return\"s\\\" t\";}");
+    }
+
+    #[test]
+    fn to_source_round_trips() {
+        assert_round_trips(&generate_program(ProgramShape::new(5, 10)));
+        assert_round_trips("1 . 5 1.5 1. .5 a.b 10.x <<= < <= >>= > >= -> - > == = = !=");
+        let all: Vec<&str> = Token::ALL.iter().map(|t| t.source_repr()).filter(|r| !r.starts_with('{')).collect();
+        assert_round_trips(&all.join(" "));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn to_source_round_trips_on_corpus() {
+        for entry in std::fs::read_dir("progs").unwrap() {
+            assert_round_trips(&std::fs::read_to_string(entry.unwrap().path()).unwrap());
+        }
+    }
+}
@@ -0,0 +1,58 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod alloc_profiling;
+#[cfg(feature = "alloc")]
+pub mod ast;
+#[cfg(feature = "alloc")]
+pub mod c_emitter;
+#[cfg(feature = "alloc")]
+pub mod const_eval;
+#[cfg(feature = "alloc")]
+pub mod cst;
+#[cfg(feature = "alloc")]
+pub mod diagnostics;
+#[cfg(feature = "std")]
+pub mod driver;
+#[cfg(feature = "std")]
+pub mod emit_sink;
+#[cfg(feature = "alloc")]
+pub mod externs;
+pub mod lexer;
+#[cfg(feature = "alloc")]
+pub mod ir;
+#[cfg(feature = "alloc")]
+pub mod lint;
+pub mod literal_value;
+#[cfg(feature = "alloc")]
+pub mod parser;
+#[cfg(feature = "alloc")]
+pub mod resolve;
+#[cfg(feature = "alloc")]
+pub mod similarity;
+pub mod source_code;
+#[cfg(feature = "alloc")]
+pub mod source_map;
+#[cfg(feature = "alloc")]
+pub mod symbol_index;
+#[cfg(feature = "alloc")]
+pub mod test_util;
+#[cfg(feature = "std")]
+pub mod token_emitter;
+#[cfg(feature = "alloc")]
+pub mod token_stream;
+#[cfg(feature = "alloc")]
+pub mod ty;
+#[cfg(feature = "alloc")]
+pub mod typeck;
+pub mod types;
+#[cfg(feature = "alloc")]
+pub mod verify;
+#[cfg(feature = "std")]
+pub mod version;
+
+#[cfg(feature = "std")]
+pub use version::version_info;
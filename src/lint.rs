@@ -0,0 +1,481 @@
+//! lints: warnings about code that is valid but most likely not what was meant, `let`
+//! bindings that are never read, functions that are never called and statements that can't
+//! run because of a `return`, `break` or `continue` before them.
+//!
+//! each lint has a `LintLevel` in `LintOptions`, `Warn` by default. `#[allow(lint)]` on an
+//! item turns the lint off for the item and everything inside it, `#[allow(dead_code)] fn
+//! f() {}` keeps `f` from being reported. names starting with `_` are never reported as
+//! unused, neither are `extern` functions, which are called from outside, and `main`.
+//!
+//! names are looked up the way `resolve` does, the pass expects a program that resolves.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::ast::visit::{Visitor, walk_block, walk_expr, walk_item, walk_stmt, walk_stmts};
+use crate::ast::{AttrArg, Block, Expr, ExprKind, FnDecl, Item, ItemKind, MatchArm, PatternKind, Stmt, StmtKind};
+use crate::types::Span;
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// `let` bindings that are never read.
+    UnusedBindings,
+    /// functions that are never called.
+    DeadCode,
+    /// statements after a `return`, `break` or `continue`.
+    UnreachableCode,
+}
+
+impl Lint {
+    pub const ALL: &[Lint] = &[Lint::UnusedBindings, Lint::DeadCode, Lint::UnreachableCode];
+
+    /// the name `#[allow]` takes.
+    #[inline]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedBindings => "unused_bindings",
+            Lint::DeadCode => "dead_code",
+            Lint::UnreachableCode => "unreachable_code",
+        }
+    }
+
+    #[inline]
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.iter().copied().find(|lint| lint.name() == name)
+    }
+}
+
+/// what happens to the findings of a lint.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LintLevel {
+    /// not reported.
+    Allow,
+    #[default]
+    Warn,
+    /// reported as an error.
+    Deny,
+}
+
+/// lint configuration, see `lint`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct LintOptions {
+    levels: [LintLevel; Lint::ALL.len()],
+}
+
+impl LintOptions {
+    #[inline]
+    pub const fn new() -> Self {
+        LintOptions {
+            levels: [LintLevel::Warn; Lint::ALL.len()],
+        }
+    }
+
+    /// the level of `lint` where no `#[allow]` says otherwise.
+    #[inline]
+    pub const fn level(mut self, lint: Lint, level: LintLevel) -> Self {
+        self.levels[lint as usize] = level;
+        self
+    }
+
+    #[inline]
+    pub const fn get_level(&self, lint: Lint) -> LintLevel {
+        self.levels[lint as usize]
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintKind<'source> {
+    UnusedBinding(&'source str),
+    UnusedFn(&'source str),
+    /// `after` is the jump that ends the reachable code.
+    Unreachable { after: Span },
+}
+
+impl LintKind<'_> {
+    #[inline]
+    pub const fn lint(&self) -> Lint {
+        match self {
+            LintKind::UnusedBinding(_) => Lint::UnusedBindings,
+            LintKind::UnusedFn(_) => Lint::DeadCode,
+            LintKind::Unreachable { .. } => Lint::UnreachableCode,
+        }
+    }
+}
+
+/// `span` covers the unused name or the unreachable statements. `level` is `Warn` or `Deny`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LintWarning<'source> {
+    pub kind: LintKind<'source>,
+    pub level: LintLevel,
+    pub span: Span,
+}
+
+impl fmt::Display for LintWarning<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            LintKind::UnusedBinding(name) => write!(f, "`{}` is never read", name),
+            LintKind::UnusedFn(name) => write!(f, "function `{}` is never called", name),
+            LintKind::Unreachable { .. } => f.write_str("unreachable code"),
+        }
+    }
+}
+
+/// lints a whole program, the findings are sorted by position.
+pub fn lint<'source>(stmts: &[Stmt<'source>], options: LintOptions) -> Vec<LintWarning<'source>> {
+    let mut linter = Linter {
+        options,
+        scopes: Vec::new(),
+        fns: Vec::new(),
+        allowed: [0; Lint::ALL.len()],
+        out: Vec::new(),
+    };
+    linter.push_scope(false);
+    linter.declare_items(stmts);
+    walk_stmts(&mut linter, stmts);
+    linter.unreachable(stmts, None);
+    linter.pop_scope();
+    linter.out.sort_by_key(|warning| warning.span.start);
+    linter.out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BindingKind {
+    Let,
+    /// a function that is reported if it is never called.
+    Fn,
+    /// parameters, pattern bindings and functions that are never reported.
+    Other,
+    /// items other than functions, visible inside the functions of their block.
+    Item,
+    Variant,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Binding<'source> {
+    name: &'source str,
+    span: Span,
+    kind: BindingKind,
+    /// the level of the lint for this binding, decided where it is declared.
+    level: LintLevel,
+    used: bool,
+}
+
+impl Binding<'_> {
+    #[inline]
+    const fn is_item(&self) -> bool {
+        matches!(self.kind, BindingKind::Fn | BindingKind::Item | BindingKind::Variant)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Scope<'source> {
+    bindings: Vec<Binding<'source>>,
+    /// the parameters of a function, see `resolve`.
+    is_fn: bool,
+}
+
+struct Linter<'source> {
+    options: LintOptions,
+    scopes: Vec<Scope<'source>>,
+    /// the names of the functions whose bodies are being linted, calls from inside a function
+    /// to itself don't count as uses.
+    fns: Vec<Span>,
+    /// how many of the items around the current node `#[allow]` each lint.
+    allowed: [u32; Lint::ALL.len()],
+    out: Vec<LintWarning<'source>>,
+}
+
+impl<'source> Linter<'source> {
+    fn push_scope(&mut self, is_fn: bool) {
+        self.scopes.push(Scope {
+            is_fn,
+            ..Scope::default()
+        });
+    }
+
+    /// reports the bindings of the scope that were never used.
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().expect("scopes are balanced");
+        for binding in scope.bindings {
+            if binding.used || binding.name.starts_with('_') {
+                continue;
+            }
+            let kind = match binding.kind {
+                BindingKind::Let => LintKind::UnusedBinding(binding.name),
+                BindingKind::Fn => LintKind::UnusedFn(binding.name),
+                BindingKind::Other | BindingKind::Item | BindingKind::Variant => continue,
+            };
+            self.report(kind, binding.level, binding.span);
+        }
+    }
+
+    fn level(&self, lint: Lint) -> LintLevel {
+        if self.allowed[lint as usize] > 0 { LintLevel::Allow } else { self.options.get_level(lint) }
+    }
+
+    fn report(&mut self, kind: LintKind<'source>, level: LintLevel, span: Span) {
+        if level != LintLevel::Allow {
+            self.out.push(LintWarning { kind, level, span });
+        }
+    }
+
+    fn declare(&mut self, name: &'source str, span: Span, kind: BindingKind, level: LintLevel) {
+        let scope = self.scopes.last_mut().expect("declarations are inside a scope");
+        scope.bindings.push(Binding {
+            name,
+            span,
+            kind,
+            level,
+            used: false,
+        });
+    }
+
+    /// items are visible in their whole block, a function's own `#[allow]` applies to it.
+    fn declare_items(&mut self, stmts: &[Stmt<'source>]) {
+        for stmt in stmts {
+            let StmtKind::Item(item) = &stmt.kind else { continue };
+            match &item.kind {
+                ItemKind::Fn(decl) => {
+                    let exempt = decl.is_extern || decl.name.name == "main";
+                    let kind = if exempt { BindingKind::Other } else { BindingKind::Fn };
+                    self.allow(item, 1);
+                    let level = self.level(Lint::DeadCode);
+                    self.allow(item, -1);
+                    self.declare(decl.name.name, decl.name.span, kind, level);
+                }
+                ItemKind::Enum(decl) => {
+                    self.declare(decl.name.name, decl.name.span, BindingKind::Item, LintLevel::Allow);
+                    for variant in &decl.variants {
+                        self.declare(variant.name.name, variant.name.span, BindingKind::Variant, LintLevel::Allow);
+                    }
+                }
+                _ => {
+                    let name = item.name();
+                    self.declare(name.name, name.span, BindingKind::Item, LintLevel::Allow);
+                }
+            }
+        }
+    }
+
+    /// adds `delta` to the count of every lint an `#[allow]` of `item` names.
+    fn allow(&mut self, item: &Item<'source>, delta: i32) {
+        let names = item.attrs.iter().filter(|attr| attr.name.name == "allow").flat_map(|attr| &attr.args);
+        for arg in names {
+            if let AttrArg::Ident(ident) = arg
+                && let Some(lint) = Lint::from_name(ident.name)
+            {
+                let count = &mut self.allowed[lint as usize];
+                *count = count.wrapping_add_signed(delta);
+            }
+        }
+    }
+
+    /// the binding `name` refers to, innermost first, like `resolve`.
+    fn lookup(&mut self, name: &str) -> Option<&mut Binding<'source>> {
+        let mut in_fn = false;
+        for scope in self.scopes.iter_mut().rev() {
+            let visible = |binding: &&mut Binding<'_>| binding.name == name && (!in_fn || binding.is_item());
+            if let Some(binding) = scope.bindings.iter_mut().rev().find(visible) {
+                return Some(binding);
+            }
+            in_fn |= scope.is_fn;
+        }
+        None
+    }
+
+    fn use_name(&mut self, name: &str) {
+        let fns = core::mem::take(&mut self.fns);
+        if let Some(binding) = self.lookup(name)
+            && !(binding.kind == BindingKind::Fn && fns.contains(&binding.span))
+        {
+            binding.used = true;
+        }
+        self.fns = fns;
+    }
+
+    /// reports the statements and the tail after the first one that always jumps away.
+    fn unreachable(&mut self, stmts: &[Stmt<'source>], tail: Option<&Expr<'source>>) {
+        let jump = stmts.iter().position(|stmt| {
+            matches!(
+                &stmt.kind,
+                StmtKind::Expr(Expr {
+                    kind: ExprKind::Return(_) | ExprKind::Break { .. } | ExprKind::Continue { .. },
+                    ..
+                })
+            )
+        });
+        let Some(jump) = jump else { return };
+        let rest = stmts[jump + 1..].iter().map(|stmt| stmt.span).chain(tail.map(|tail| tail.span));
+        let Some(span) = rest.reduce(Span::merge) else { return };
+        let after = stmts[jump].span;
+        self.report(LintKind::Unreachable { after }, self.level(Lint::UnreachableCode), span);
+    }
+}
+
+impl<'ast, 'source: 'ast> Visitor<'ast, 'source> for Linter<'source> {
+    fn visit_stmt(&mut self, stmt: &'ast Stmt<'source>) {
+        match &stmt.kind {
+            // the value can't see the name it is bound to
+            StmtKind::Let { name, ty, value } => {
+                if let Some(ty) = ty {
+                    self.visit_type(ty);
+                }
+                self.visit_expr(value);
+                let level = self.level(Lint::UnusedBindings);
+                self.declare(name.name, name.span, BindingKind::Let, level);
+            }
+            _ => walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_item(&mut self, item: &'ast Item<'source>) {
+        self.allow(item, 1);
+        walk_item(self, item);
+        self.allow(item, -1);
+    }
+
+    fn visit_fn_decl(&mut self, decl: &'ast FnDecl<'source>) {
+        for param in &decl.params {
+            self.visit_type(&param.ty);
+        }
+        if let Some(ret) = &decl.ret {
+            self.visit_type(ret);
+        }
+        self.push_scope(true);
+        for param in &decl.params {
+            self.declare(param.name.name, param.name.span, BindingKind::Other, LintLevel::Allow);
+        }
+        if let Some(body) = &decl.body {
+            self.fns.push(decl.name.span);
+            self.visit_block(body);
+            self.fns.pop();
+        }
+        self.pop_scope();
+    }
+
+    fn visit_block(&mut self, block: &'ast Block<'source>) {
+        self.push_scope(false);
+        self.declare_items(&block.stmts);
+        walk_block(self, block);
+        self.unreachable(&block.stmts, block.tail.as_deref());
+        self.pop_scope();
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr<'source>) {
+        match &expr.kind {
+            ExprKind::Identifier(name) => self.use_name(name),
+            ExprKind::Path { enum_name, .. } => self.use_name(enum_name.name),
+            // a plain assignment writes the binding without reading it
+            ExprKind::Assign {
+                op: None,
+                target:
+                    Expr {
+                        kind: ExprKind::Identifier(_),
+                        ..
+                    },
+                value,
+            } => self.visit_expr(value),
+            ExprKind::Struct { name, .. } => {
+                self.use_name(name.name);
+                walk_expr(self, expr);
+            }
+            _ => walk_expr(self, expr),
+        }
+    }
+
+    /// an identifier pattern naming a variant matches it, any other binds the value.
+    fn visit_match_arm(&mut self, arm: &'ast MatchArm<'source>) {
+        self.push_scope(false);
+        match arm.pattern.kind {
+            PatternKind::Identifier(name) => {
+                let is_variant = self.lookup(name).is_some_and(|binding| binding.kind == BindingKind::Variant);
+                if !is_variant {
+                    self.declare(name, arm.pattern.span, BindingKind::Other, LintLevel::Allow);
+                }
+            }
+            PatternKind::Path { enum_name, .. } => self.use_name(enum_name.name),
+            PatternKind::Wildcard | PatternKind::Literal(_) => {}
+        }
+        self.visit_expr(&arm.body);
+        self.pop_scope();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use super::{Lint, LintKind, LintLevel, LintOptions, LintWarning, lint};
+    use crate::parser::tests::parse_ok;
+    use crate::types::Span;
+
+    fn lint_with(text: &str, options: LintOptions) -> Vec<LintWarning<'_>> {
+        lint(parse_ok(text), options)
+    }
+
+    fn kinds(text: &str) -> Vec<LintKind<'_>> {
+        lint_with(text, LintOptions::new()).iter().map(|warning| warning.kind).collect()
+    }
+
+    #[test]
+    fn used_code_is_not_reported() {
+        let text = "fn add(let a: u8, let b: u8) -> u8 { a + b }\nlet x = 1;\nlet y: mut u8 = add(x, 2);\n\
+                    y += 1;\nenum E { v }\nlet e = E::v;\nmatch e { v => 1, other => 2, };\n\
+                    fn main() { let _ignored = 3; }\nextern fn exported() {}\nif y == 2 { return; };";
+        assert_eq!(kinds(text), []);
+    }
+
+    #[test]
+    fn unused_bindings_and_functions() {
+        let text = "let a = 1;\nlet b = 2;\nb = 3;\nlet a = a;\nfn f() { let a = 1; f(); }\n\
+                    fn g() -> u8 { 1 }\nfn h() { g(); }\nlet s = S { v: 1 };\nstruct S { v: u8 }";
+        let warnings = lint_with(text, LintOptions::new());
+        let found: Vec<_> = warnings.iter().map(|warning| (warning.kind, warning.span)).collect();
+        assert_eq!(
+            found,
+            [
+                (LintKind::UnusedBinding("b"), Span::new(15, 16)),
+                (LintKind::UnusedBinding("a"), Span::new(33, 34)),
+                (LintKind::UnusedFn("f"), Span::new(43, 44)),
+                (LintKind::UnusedBinding("a"), Span::new(53, 54)),
+                (LintKind::UnusedFn("h"), Span::new(89, 90)),
+                (LintKind::UnusedBinding("s"), Span::new(106, 107)),
+            ]
+        );
+        assert_eq!(warnings[2].to_string(), "function `f` is never called");
+        assert_eq!(warnings[0].to_string(), "`b` is never read");
+    }
+
+    #[test]
+    fn code_after_a_jump_is_unreachable() {
+        let text = "fn f() -> u8 {\n    return 1;\n    let x = 2;\n    x\n}\nf();\n\
+                    loop { break; continue; };\nwhile true { continue; }";
+        let warnings = lint_with(text, LintOptions::new());
+        assert_eq!(warnings.len(), 2, "{warnings:?}");
+        assert_eq!(warnings[0].kind, LintKind::Unreachable { after: Span::new(19, 28) });
+        assert_eq!(warnings[0].span, Span::new(33, 49));
+        assert_eq!(warnings[1].span, Span::new(71, 80));
+        assert_eq!(warnings[1].to_string(), "unreachable code");
+    }
+
+    #[test]
+    fn levels_and_allow_attributes() {
+        let text = "#[allow(unused_bindings)]\nfn f() { let a = 1; fn inner() {} }\n\
+                    #[allow(dead_code, unreachable_code)] fn g() { return; g(); }\nf();\nlet b = 2;";
+        assert_eq!(kinds(text), [LintKind::UnusedFn("inner"), LintKind::UnusedBinding("b")]);
+
+        let options = LintOptions::new()
+            .level(Lint::UnusedBindings, LintLevel::Deny)
+            .level(Lint::DeadCode, LintLevel::Allow);
+        assert_eq!(options.get_level(Lint::UnreachableCode), LintLevel::Warn);
+        let warnings = lint_with("let a = 1;\nfn f() {}", options);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!((warnings[0].kind, warnings[0].level), (LintKind::UnusedBinding("a"), LintLevel::Deny));
+        assert_eq!(Lint::from_name("dead_code"), Some(Lint::DeadCode));
+    }
+}
@@ -0,0 +1,353 @@
+#[cfg(feature = "alloc")]
+use alloc::{borrow::Cow, vec::Vec};
+
+use crate::lexer::lexer_impls::chars;
+use crate::types::{Spanned, Token};
+
+/// a literal token paired with the raw source bytes `Lexer::extract_literal` returned for it.
+///
+/// string and char literals are raw in the sense that their escapes are not resolved yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Literal<'source> {
+    token: Token,
+    raw: &'source [u8],
+}
+
+impl<'source> Literal<'source> {
+    #[inline]
+    pub const fn new(token: Token, raw: &'source [u8]) -> Self {
+        Literal { token, raw }
+    }
+
+    #[inline(always)]
+    pub const fn token(&self) -> Token {
+        self.token
+    }
+
+    #[inline(always)]
+    pub const fn raw(&self) -> &'source [u8] {
+        self.raw
+    }
+
+    /// length in bytes of the literal's value once escapes are resolved, computed without
+    /// allocating. for string literals this is the size of the byte array they evaluate to.
+    ///
+    /// tokens other than `LitStr` and `LitChar` have no escapes, their decoded length is the raw length.
+    #[inline]
+    pub const fn decoded_len(&self) -> usize {
+        match self.token {
+            Token::LitStr => decoded_string_len(self.raw),
+            Token::LitChar => match chars::decode_char(self.raw) {
+                Some(c) => c.len_utf8(),
+                None => self.raw.len(),
+            },
+            _ => self.raw.len(),
+        }
+    }
+
+    /// the bytes a string literal evaluates to, see `cook_string`. other tokens have no
+    /// escapes and are returned as-is.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn cooked(&self) -> Cow<'source, [u8]> {
+        match self.token {
+            Token::LitStr => cook_string(self.raw),
+            _ => Cow::Borrowed(self.raw),
+        }
+    }
+}
+
+/// resolves the escapes of a lexed string literal body into the bytes they stand for.
+///
+/// borrows when there is nothing to resolve. `\xNN` produces the byte `0xNN`. malformed
+/// escapes, which the lexer never lets through, are kept verbatim.
+#[cfg(feature = "alloc")]
+pub fn cook_string(raw: &[u8]) -> Cow<'_, [u8]> {
+    if !raw.contains(&b'\\') {
+        return Cow::Borrowed(raw);
+    }
+    let mut cooked = Vec::with_capacity(decoded_string_len(raw));
+    let mut index = 0;
+    while index < raw.len() {
+        let byte = raw[index];
+        index += 1;
+        if byte != b'\\' || index == raw.len() {
+            cooked.push(byte);
+            continue;
+        }
+        let escaped = raw[index];
+        index += 1;
+        match escaped {
+            b'n' => cooked.push(b'\n'),
+            b't' => cooked.push(b'\t'),
+            b'r' => cooked.push(b'\r'),
+            b'0' => cooked.push(b'\0'),
+            b'"' | b'\\' => cooked.push(escaped),
+            b'x' if let Some(&[high, low]) = raw.get(index..index + 2)
+                && let (Some(high), Some(low)) = (hex_value(high), hex_value(low)) =>
+            {
+                cooked.push(high << 4 | low);
+                index += 2;
+            }
+            _ => cooked.extend_from_slice(&[b'\\', escaped]),
+        }
+    }
+    Cow::Owned(cooked)
+}
+
+/// `{"token":"LitStr","raw":"a\\n"}`, the raw bytes as a string since they are sliced from one.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Literal<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeStruct};
+
+        let raw = core::str::from_utf8(self.raw).map_err(|_| S::Error::custom("literal is not valid utf-8"))?;
+        let mut state = serializer.serialize_struct("Literal", 2)?;
+        state.serialize_field("token", &self.token)?;
+        state.serialize_field("raw", raw)?;
+        state.end()
+    }
+}
+
+/// why a literal couldn't be turned into a value.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum LiteralError {
+    /// the value doesn't fit the requested integer type.
+    IntegerOverflow,
+    /// a byte that is neither a digit of the literal's radix nor a `_` separator.
+    InvalidDigit(u8),
+    /// only a radix prefix and separators, like `0x` or `0b__`.
+    NoDigits,
+    /// `''`.
+    EmptyChar,
+    /// more than one escape or unicode scalar between the quotes.
+    MultipleChars,
+    /// the byte after a backslash doesn't start a known escape.
+    InvalidEscape(u8),
+    /// not valid utf-8, which can't happen for bytes lexed from a `SourceCode`.
+    InvalidUtf8,
+}
+
+/// the `char` a char literal body (the bytes between the quotes, as returned by
+/// `Lexer::extract_literal`) denotes, resolving the escapes `\'` `\t` `\n` `\r` `\\` `\0`.
+pub const fn decode_char_literal(body: &[u8]) -> Result<char, LiteralError> {
+    let len = match body {
+        [] => return Err(LiteralError::EmptyChar),
+        [b'\\'] => return Err(LiteralError::InvalidEscape(b'\\')),
+        [b'\\', ..] => 2,
+        [lead, ..] => chars::utf8_sequence_len(*lead),
+    };
+    if body.len() < len {
+        return Err(LiteralError::InvalidUtf8);
+    }
+    let (first, rest) = body.split_at(len);
+    match chars::decode_char(first) {
+        Some(_) if !rest.is_empty() => Err(LiteralError::MultipleChars),
+        Some(c) => Ok(c),
+        None if first[0] == b'\\' => Err(LiteralError::InvalidEscape(first[1])),
+        None => Err(LiteralError::InvalidUtf8),
+    }
+}
+
+/// the value of an integer literal. `0x`, `0o` and `0b` prefixes select the radix, and `_`
+/// may separate digits anywhere after the prefix. errors carry the literal's span.
+pub const fn parse_u128(literal: Spanned<&[u8]>) -> Result<u128, Spanned<LiteralError>> {
+    let (radix, digits) = match literal.value {
+        [b'0', b'x' | b'X', rest @ ..] => (16, rest),
+        [b'0', b'o' | b'O', rest @ ..] => (8, rest),
+        [b'0', b'b' | b'B', rest @ ..] => (2, rest),
+        digits => (10, digits),
+    };
+
+    let mut value: u128 = 0;
+    let mut any_digits = false;
+    let mut index = 0;
+    while index < digits.len() {
+        let byte = digits[index];
+        index += 1;
+        if byte == b'_' {
+            continue;
+        }
+        let digit = match hex_value(byte) {
+            Some(digit) if digit < radix => digit,
+            _ => return Err(Spanned::new(LiteralError::InvalidDigit(byte), literal.span)),
+        };
+        any_digits = true;
+        value = match value.checked_mul(radix as u128) {
+            Some(value) => match value.checked_add(digit as u128) {
+                Some(value) => value,
+                None => return Err(Spanned::new(LiteralError::IntegerOverflow, literal.span)),
+            },
+            None => return Err(Spanned::new(LiteralError::IntegerOverflow, literal.span)),
+        };
+    }
+    if !any_digits {
+        return Err(Spanned::new(LiteralError::NoDigits, literal.span));
+    }
+    Ok(value)
+}
+
+/// like `parse_u128`, but for a literal preceded by a unary minus when `negative` is set,
+/// so that `-170141183460469231731687303715884105728` (`i128::MIN`) still fits.
+pub const fn parse_i128(literal: Spanned<&[u8]>, negative: bool) -> Result<i128, Spanned<LiteralError>> {
+    let magnitude = match parse_u128(literal) {
+        Ok(magnitude) => magnitude,
+        Err(e) => return Err(e),
+    };
+    if negative && magnitude == i128::MIN.unsigned_abs() {
+        Ok(i128::MIN)
+    } else if magnitude <= i128::MAX as u128 {
+        let value = magnitude as i128;
+        Ok(if negative { -value } else { value })
+    } else {
+        Err(Spanned::new(LiteralError::IntegerOverflow, literal.span))
+    }
+}
+
+#[inline]
+const fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// length of a lexed string literal body after resolving escapes.
+///
+/// every escape decodes to a single byte: two-character escapes like `\n` and
+/// four-character byte escapes like `\x7f`.
+#[inline]
+pub const fn decoded_string_len(raw: &[u8]) -> usize {
+    let mut len = 0;
+    let mut index = 0;
+    while index < raw.len() {
+        index += match raw[index] {
+            b'\\' if index + 1 < raw.len() && raw[index + 1] == b'x' => 4,
+            b'\\' => 2,
+            _ => 1,
+        };
+        len += 1;
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::borrow::Cow;
+    use alloc::string::ToString;
+
+    use super::{
+        Literal, LiteralError, cook_string, decode_char_literal, decoded_string_len, parse_i128, parse_u128,
+    };
+    use crate::{
+        lexer::Lexer,
+        source_code::SourceCode,
+        types::{Span, Spanned, Token},
+    };
+
+    #[test]
+    fn decoded_len_resolves_escapes() {
+        let text = r#""10 string \" ends here ->" "\t\r\n\\\0" "plain" "" 'é' '\n' 1234 ident"#;
+        let expected = [
+            (Token::LitStr, 24),
+            (Token::LitStr, 5),
+            (Token::LitStr, 5),
+            (Token::LitStr, 0),
+            (Token::LitChar, 2),
+            (Token::LitChar, 1),
+            (Token::LitInteger, 4),
+            (Token::LitIdentifier, 5),
+        ];
+
+        let mut lexer = Lexer::new(SourceCode::new(text));
+        for (token, len) in expected {
+            assert_eq!(lexer.lex_single_token(), Ok(token));
+            let literal = Literal::new(token, lexer.extract_literal().unwrap());
+            assert_eq!(literal.decoded_len(), len, "{:?}", literal);
+        }
+    }
+
+    #[test]
+    fn byte_escapes_count_as_one_byte() {
+        assert_eq!(decoded_string_len(br"\x7f"), 1);
+        assert_eq!(decoded_string_len(br"a\x00b"), 3);
+    }
+
+    #[test]
+    fn cooking_resolves_escapes() {
+        let text = r#""10 string \" ends here ->" "\t\r\n\\\0" "plain""#;
+        let mut lexer = Lexer::new(SourceCode::new(text));
+
+        assert_eq!(lexer.lex_single_token(), Ok(Token::LitStr));
+        let cooked = lexer.extract_cooked_string().unwrap();
+        assert_eq!(&*cooked, b"10 string \" ends here ->");
+        assert_eq!(cooked.len(), Literal::new(Token::LitStr, br#"10 string \" ends here ->"#).decoded_len());
+
+        assert_eq!(lexer.lex_single_token(), Ok(Token::LitStr));
+        assert_eq!(&*lexer.extract_cooked_string().unwrap(), b"\t\r\n\\\0");
+
+        assert_eq!(lexer.lex_single_token(), Ok(Token::LitStr));
+        assert!(matches!(lexer.extract_cooked_string(), Ok(Cow::Borrowed(b"plain"))));
+    }
+
+    #[test]
+    fn cooking_byte_escapes_and_malformed_input() {
+        assert_eq!(&*cook_string(br"\x7f\x00A\xFf"), b"\x7f\0A\xff");
+        assert_eq!(&*cook_string(br"\xg0 \m \"), br"\xg0 \m \");
+        assert_eq!(&*Literal::new(Token::LitIdentifier, br"a\n").cooked(), br"a\n");
+    }
+
+    #[test]
+    fn integer_values_and_overflow() {
+        let span = Span::new(4, 9);
+        let parse = |raw: &str| parse_u128(Spanned::new(raw.as_bytes(), span)).map_err(|e| e.value);
+        assert_eq!(parse("0"), Ok(0));
+        assert_eq!(parse("1234"), Ok(1234));
+        assert_eq!(parse("1_000_000"), Ok(1_000_000));
+        assert_eq!(parse("0xfF"), Ok(255));
+        assert_eq!(parse("0o17"), Ok(15));
+        assert_eq!(parse("0b1010_1010"), Ok(0xaa));
+        assert_eq!(parse(&u128::MAX.to_string()), Ok(u128::MAX));
+        assert_eq!(parse("340282366920938463463374607431768211456"), Err(LiteralError::IntegerOverflow));
+        assert_eq!(parse("0b102"), Err(LiteralError::InvalidDigit(b'2')));
+        assert_eq!(parse("12a"), Err(LiteralError::InvalidDigit(b'a')));
+        assert_eq!(parse("0x__"), Err(LiteralError::NoDigits));
+        assert_eq!(parse(""), Err(LiteralError::NoDigits));
+
+        assert_eq!(
+            parse_u128(Spanned::new(b"0x1_0000_0000_0000_0000_0000_0000_0000_0000", span)),
+            Err(Spanned::new(LiteralError::IntegerOverflow, span))
+        );
+
+        let signed = |raw: &str, negative| parse_i128(Spanned::new(raw.as_bytes(), span), negative).map_err(|e| e.value);
+        let min = i128::MIN.unsigned_abs().to_string();
+        assert_eq!(signed(&min, true), Ok(i128::MIN));
+        assert_eq!(signed(&min, false), Err(LiteralError::IntegerOverflow));
+        assert_eq!(signed(&i128::MAX.to_string(), false), Ok(i128::MAX));
+        assert_eq!(signed("42", true), Ok(-42));
+        assert_eq!(signed("0", true), Ok(0));
+    }
+
+    #[test]
+    fn char_literals_decode_to_one_scalar() {
+        let text = r"'V' '\n' '\'' 'é' '🦀' '\\'";
+        let mut lexer = Lexer::new(SourceCode::new(text));
+        for expected in ['V', '\n', '\'', 'é', '🦀', '\\'] {
+            assert_eq!(lexer.lex_single_token(), Ok(Token::LitChar));
+            assert_eq!(decode_char_literal(lexer.extract_literal().unwrap()), Ok(expected));
+        }
+
+        assert_eq!(decode_char_literal(b""), Err(LiteralError::EmptyChar));
+        assert_eq!(decode_char_literal(b"ab"), Err(LiteralError::MultipleChars));
+        assert_eq!(decode_char_literal("éa".as_bytes()), Err(LiteralError::MultipleChars));
+        assert_eq!(decode_char_literal(br"\n\n"), Err(LiteralError::MultipleChars));
+        assert_eq!(decode_char_literal(br"\m"), Err(LiteralError::InvalidEscape(b'm')));
+        assert_eq!(decode_char_literal(br"\"), Err(LiteralError::InvalidEscape(b'\\')));
+        assert_eq!(decode_char_literal(&[0xc3]), Err(LiteralError::InvalidUtf8));
+        assert_eq!(decode_char_literal(&[0xed, 0xa0, 0x80]), Err(LiteralError::InvalidUtf8));
+    }
+}
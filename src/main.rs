@@ -1,17 +1,36 @@
-use std::{fmt::Display, fs, time::Duration};
+use std::{
+    fmt::Display,
+    fs,
+    io::{IsTerminal, Write},
+    path::Path,
+    time::Duration,
+};
 
 use voxell_rng::prelude::RngCoreExtension;
 use voxell_timer::{power_toys::ScopedTimer, time_fn};
 
-use crate::{
-    lexer::{Lexer, LexerError, LexerResult},
+use mumbo_lang::{
+    alloc_profiling::{self, AllocationStats},
+    ast::arena::AstArena,
+    c_emitter::{self, CSource},
+    diagnostics::Diagnostic,
+    driver::{self, Driver, Session},
+    emit_sink::DirectorySink,
+    ir,
+    lexer::{Lexer, LexerResult, TokenBuffer, lex_sources},
+    parser::{self, Parser},
+    similarity::{Fingerprint, similar_pairs},
     source_code::SourceCode,
+    source_map::SourceMap,
+    test_util::source_generator::{ProgramShape, generate_program},
+    token_emitter, typeck,
     types::Token,
+    version_info,
 };
 
-pub mod lexer;
-pub mod source_code;
-pub mod types;
+#[cfg(feature = "alloc-profiling")]
+#[global_allocator]
+static GLOBAL: alloc_profiling::CountingAllocator = alloc_profiling::CountingAllocator;
 
 #[derive(Clone, PartialEq, Eq)]
 struct TimerThing {
@@ -32,48 +51,315 @@ impl Display for TimerThing {
 }
 
 fn main() {
-    let folder = fs::read_dir("progs").unwrap();
-    let mut pairs = vec![];
-    for entry in folder {
-        let entry = entry.unwrap();
-        let path = entry.path();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--version" || arg == "-V") {
+        if args.iter().any(|arg| arg == "--verbose" || arg == "-v") {
+            println!("{:#}", version_info());
+        } else {
+            println!("{}", version_info());
+        }
+        return;
+    }
+
+    match args.first().map(String::as_str) {
+        Some("check") => check_command(&args[1..]),
+        Some("emit-c") => emit_c_command(&args[1..]),
+        Some("lex") => lex_command(&args[1..]),
+        Some("similar") => similar_command(&args[1..]),
+        Some("symbols") => symbols_command(&args[1..]),
+        Some(other) => {
+            eprintln!(
+                "unknown command `{}`, expected `check`, `emit-c`, `lex`, `similar`, `symbols` or no arguments \
+                 to run the benchmark",
+                other
+            );
+            std::process::exit(2);
+        }
+        None => benchmark(),
+    }
+}
+
+/// `mumbo check [--verify] <file|dir>`, `--verify` checks the compiler itself like a debug
+/// build does.
+fn check_command(args: &[String]) {
+    let (path, verify) = match args {
+        [path] => (path, false),
+        [flag, path] if flag == "--verify" => (path, true),
+        _ => {
+            eprintln!("usage: mumbo check [--verify] <file|dir>");
+            std::process::exit(2);
+        }
+    };
+
+    let session = if verify { Session::new().verify(true) } else { Session::new() };
+    let driver = Driver::new(session);
+    let compiled = if fs::metadata(path).is_ok_and(|meta| meta.is_dir()) {
+        driver.compile_dir(path)
+    } else {
+        driver.compile_file(path)
+    };
+    let compiled = match compiled {
+        Ok(compiled) => compiled,
+        Err(e) => {
+            eprintln!("could not read {}: {}", path, e);
+            std::process::exit(1);
+        }
+    };
+
+    eprint!("{}", compiled.render(std::io::stderr().is_terminal()));
+    if compiled.has_errors() {
+        eprintln!("checked {} files, found {} errors", compiled.files.len(), compiled.error_count());
+        std::process::exit(1);
+    }
+}
+
+/// `mumbo emit-c [--out=dir] [--verify] <file>`, writes `<file stem>.c` and the runtime
+/// header to `dir`.
+fn emit_c_command(args: &[String]) {
+    let mut path = None;
+    let mut out = ".";
+    let mut session = Session::new();
+    for arg in args {
+        match arg.as_str() {
+            dir if dir.starts_with("--out=") => out = &dir["--out=".len()..],
+            "--verify" => session = session.verify(true),
+            file => path = Some(file),
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("usage: mumbo emit-c [--out=dir] [--verify] <file>");
+        std::process::exit(2);
+    };
+
+    let color = std::io::stderr().is_terminal();
+    let compiled = match Driver::new(session).compile_file(path) {
+        Ok(compiled) => compiled,
+        Err(e) => {
+            eprintln!("could not read {}: {}", path, e);
+            std::process::exit(1);
+        }
+    };
+    if compiled.has_errors() {
+        eprint!("{}", compiled.render(color));
+        std::process::exit(1);
+    }
+
+    // the root is read again, the driver only keeps diagnostics
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("could not read {}: {}", path, e);
+            std::process::exit(1);
+        }
+    };
+    let (emitted, diagnostics) = emit_c(&source, path, color);
+    eprint!("{}", diagnostics);
+    let Some(emitted) = emitted else { std::process::exit(1) };
+
+    let name = Path::new(path).file_stem().and_then(|stem| stem.to_str()).unwrap_or("main");
+    if let Err(e) = emitted.write_to(&mut DirectorySink::new(out), name) {
+        eprintln!("could not write to {}: {}", out, e);
+        std::process::exit(1);
+    }
+}
 
-        let dat = fs::read_to_string(&path).unwrap().repeat(15000);
-        pairs.push((dat, path));
+/// type checks `source` and lowers it to C. returns the rendered warnings and errors, and the
+/// C only if none of them is an error.
+fn emit_c(source: &str, path: &str, color: bool) -> (Option<CSource>, String) {
+    let code = SourceCode::new(source);
+    let arena = AstArena::new();
+    let parsed = parser::parse(source, &arena);
+    let checked = typeck::check(&parsed.stmts);
+    let mut diagnostics = String::new();
+    for warning in &checked.warnings {
+        diagnostics += &Diagnostic::from_type_warning(warning, &checked.tys).render(&code, path, color);
+    }
+    for error in &checked.errors {
+        diagnostics += &Diagnostic::from_type_error(error, &checked.tys).render(&code, path, color);
+    }
+    if !checked.errors.is_empty() {
+        return (None, diagnostics);
+    }
+    let emitted = c_emitter::emit(&ir::lower(&parsed.stmts, &checked), &checked);
+    for error in &emitted.errors {
+        diagnostics += &Diagnostic::from_c_error(error, &checked.tys).render(&code, path, color);
     }
+    (emitted.errors.is_empty().then_some(emitted), diagnostics)
+}
 
+/// `mumbo lex [--format=jsonl] <file>`
+fn lex_command(args: &[String]) {
+    let mut path = None;
+    for arg in args {
+        match arg.as_str() {
+            "--format=jsonl" => {}
+            format if format.starts_with("--format=") => {
+                eprintln!("unsupported format `{}`, only `jsonl` is available", &format["--format=".len()..]);
+                std::process::exit(2);
+            }
+            file => path = Some(file),
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("usage: mumbo lex [--format=jsonl] <file>");
+        std::process::exit(2);
+    };
+
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("could not read {}: {}", path, e);
+            std::process::exit(1);
+        }
+    };
+
+    let stdout = std::io::stdout().lock();
+    match token_emitter::emit_jsonl(SourceCode::new(&source), std::io::BufWriter::new(stdout)) {
+        Ok(summary) if summary.errors == 0 => {}
+        Ok(_) => std::process::exit(1),
+        // most likely a closed pipe, nothing left to report to
+        Err(_) => std::process::exit(1),
+    }
+}
+
+/// `mumbo similar [--threshold=0.8] <dir>`
+fn similar_command(args: &[String]) {
+    let usage = || -> ! {
+        eprintln!("usage: mumbo similar [--threshold=0.8] <dir>");
+        std::process::exit(2);
+    };
+    let mut threshold = 0.8;
+    let mut dir = None;
+    for arg in args {
+        match arg.strip_prefix("--threshold=") {
+            Some(value) => match value.parse::<f64>() {
+                Ok(value) if (0.0..=1.0).contains(&value) => threshold = value,
+                _ => usage(),
+            },
+            None => dir = Some(arg),
+        }
+    }
+    let Some(dir) = dir else { usage() };
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("could not read {}: {}", dir, e);
+            std::process::exit(1);
+        }
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "mumbo"))
+        .collect();
+    paths.sort();
+
+    let mut fingerprints = Vec::with_capacity(paths.len());
+    for path in &paths {
+        match fs::read_to_string(path) {
+            Ok(source) => fingerprints.push(Fingerprint::new(&source, 5, 4)),
+            Err(e) => {
+                eprintln!("could not read {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    for pair in similar_pairs(&fingerprints, threshold) {
+        let line = writeln!(
+            stdout,
+            "{:.2} {} {}",
+            pair.score,
+            paths[pair.first].display(),
+            paths[pair.second].display()
+        );
+        // most likely a closed pipe, nothing left to report to
+        if line.is_err() {
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `mumbo symbols [--query <name>] <file|dir>`, lists the symbols matching the query best
+/// first, every symbol without one.
+fn symbols_command(args: &[String]) {
+    let usage = || -> ! {
+        eprintln!("usage: mumbo symbols [--query <name>] <file|dir>");
+        std::process::exit(2);
+    };
+    let mut query = "";
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--query" => query = args.next().unwrap_or_else(|| usage()),
+            file => path = Some(file),
+        }
+    }
+    let Some(path) = path else { usage() };
+
+    let compiled = if fs::metadata(path).is_ok_and(|meta| meta.is_dir()) {
+        driver::compile_dir(path)
+    } else {
+        driver::compile_file(path)
+    };
+    let compiled = match compiled {
+        Ok(compiled) => compiled,
+        Err(e) => {
+            eprintln!("could not read {}: {}", path, e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut stdout = std::io::stdout().lock();
+    for symbol in compiled.symbols.query(query) {
+        let at = compiled.files.resolve(symbol.file, symbol.span);
+        // most likely a closed pipe, nothing left to report to
+        if writeln!(stdout, "{} {} {}", at, symbol.kind, symbol.name).is_err() {
+            std::process::exit(1);
+        }
+    }
+}
+
+fn benchmark() {
+    let allocs = alloc_profiling::snapshot();
+    let mut progs = SourceMap::new();
+    driver::load_dir(Path::new("progs"), &mut progs).unwrap();
+    let mut files = SourceMap::new();
+    for (_, file) in progs.iter() {
+        files.add(file.path(), file.source().as_str().repeat(15000));
+    }
+    report_allocations("loading progs", allocs);
+
+    let allocs = alloc_profiling::snapshot();
     let mut sum = 0;
     let mut total_source = 0;
     let mut st = ScopedTimer::new(TimerThing::new(0, "main".to_string()));
 
     let mut f1 = st.fork(TimerThing::new(1, "main".to_string()));
-    for (i, (source, path)) in pairs.into_iter().enumerate() {
+    for (id, file) in files.iter() {
+        let source = file.source();
         let mut f2 = f1.fork(TimerThing::new(
-            i as i32,
-            format!("file {}, {:.1}MB", path.to_string_lossy(), source.len() as f64 / 1000000.0),
+            id.index() as i32,
+            format!("file {}, {:.1}MB", file.path(), source.len() as f64 / 1000000.0),
         ));
-        let mut lexer = Lexer::new(SourceCode::new(&source));
+        let mut lexer = Lexer::new(source.clone());
         let mut val;
         'tokens: loop {
             val = lexer.lex_single_token();
-            if val == Err(LexerError::Eof) {
-                total_source += source.len();
-                break 'tokens;
-            }
             match val {
                 Ok(_t) => {
                     sum += 1;
                 }
+                Err(e) if e.is_eof() => {
+                    total_source += source.len();
+                    break 'tokens;
+                }
                 Err(e) => {
-                    let (line, col) = lexer.get_line_column();
-                    let maybe_lit: LexerResult<&[u8]> = lexer.extract_literal();
-                    let start = lexer.start();
-                    let index = lexer.index();
-                    eprintln!(
-                        "lexer error at {:?}:{}:{} (index {}-{}): {:?}, maybe_lit: {:?}",
-                        path, line, col, start, index, e, maybe_lit
-                    );
-                    total_source += lexer.start();
+                    let color = std::io::stderr().is_terminal();
+                    eprint!("{}", Diagnostic::from(e).render(lexer.source(), file.path(), color));
+                    total_source += e.span.start;
                     break 'tokens;
                 }
             }
@@ -81,6 +367,7 @@ fn main() {
         f2.join();
     }
     f1.join();
+    report_allocations("lexing progs", allocs);
 
     let dur = st
         .clone()
@@ -90,12 +377,98 @@ fn main() {
         .fold(Duration::ZERO, |acc, next| acc + next);
     println!("{}", st.join_and_finish_pretty());
     println!(
-        "Finished {} bytes in {:?} ({:.2} MB/s)",
+        "Finished {} bytes ({} tokens) in {:?} ({:.2} MB/s)",
         total_source,
+        sum,
         dur,
         total_source as f64 / dur.as_secs_f64() / 1000000.0
     );
 
+    let allocs = alloc_profiling::snapshot();
+    let sources: Vec<&str> = files.iter().map(|(_, file)| file.source().as_str()).collect();
+    let (stats, dur) = time_fn(|| lex_sources(&sources));
+    report_allocations("lexing progs in parallel", allocs);
+    let bytes: usize = stats.iter().map(|stats| stats.bytes).sum();
+    println!(
+        "Finished {} bytes ({} tokens, {} errors) on all cores in {:?} ({:.2} MB/s)",
+        bytes,
+        stats.iter().map(|stats| stats.tokens).sum::<usize>(),
+        stats.iter().map(|stats| stats.errors.len()).sum::<usize>(),
+        dur,
+        bytes as f64 / dur.as_secs_f64() / 1000000.0
+    );
+
+    let allocs = alloc_profiling::snapshot();
+    let ((nodes, errors), dur) = time_fn(|| {
+        let (mut nodes, mut errors) = (0, 0);
+        for source in &sources {
+            let arena = AstArena::new();
+            let parsed = parser::parse(source, &arena);
+            nodes += parsed.stmts.len() + arena.len();
+            errors += parsed.errors.len() + parsed.lexer_errors.len();
+        }
+        (nodes, errors)
+    });
+    report_allocations("parsing progs", allocs);
+    println!(
+        "Parsed {} bytes ({} nodes, {} errors) in {:?} ({:.2} MB/s)",
+        bytes,
+        nodes,
+        errors,
+        dur,
+        bytes as f64 / dur.as_secs_f64() / 1000000.0
+    );
+
+    let allocs = alloc_profiling::snapshot();
+    let (buffers, lex_dur) = time_fn(|| {
+        sources
+            .iter()
+            .map(|source| TokenBuffer::new(SourceCode::new(source)))
+            .collect::<Vec<_>>()
+    });
+    let (errors, parse_dur) = time_fn(|| {
+        let mut errors = 0;
+        for buffer in &buffers {
+            let arena = AstArena::new();
+            let parsed = Parser::new(buffer.cursor(), &arena).parse();
+            errors += parsed.errors.len() + parsed.lexer_errors.len();
+        }
+        errors
+    });
+    report_allocations("parsing progs from token buffers", allocs);
+    println!(
+        "Parsed {} bytes ({} errors) from token buffers in {:?} + {:?} ({:.2} MB/s)",
+        bytes,
+        errors,
+        lex_dur,
+        parse_dur,
+        bytes as f64 / (lex_dur + parse_dur).as_secs_f64() / 1000000.0
+    );
+
+    println!("starting generated program benchmark");
+    println!("genning program");
+    let allocs = alloc_profiling::snapshot();
+    let program = generate_program(ProgramShape::new(20_000, 40_000));
+    let mut lexer = Lexer::new(SourceCode::new(&program));
+    let mut tokens = 0;
+    let (lexed, dur) = time_fn(|| {
+        loop {
+            match lexer.lex_single_token() {
+                Ok(_) => tokens += 1,
+                Err(e) => break e,
+            }
+        }
+    });
+    assert!(lexed.is_eof(), "{}: {}", lexed, lexer.get_lexer_debug_state());
+    report_allocations("generating and lexing program", allocs);
+    println!(
+        "generated program of {} bytes ({} tokens) lexed in {:?} ({:.2} MB/s)",
+        program.len(),
+        tokens,
+        dur,
+        program.len() as f64 / dur.as_secs_f64() / 1000000.0
+    );
+
     println!("starting quoted string bruh benchmark");
     println!("genning strings");
     let mut s = String::new();
@@ -116,12 +489,13 @@ fn main() {
                     continue;
                 }
                 Ok(t) => panic!("got {:?}", t),
-                Err(LexerError::Eof) => break,
+                Err(e) if e.is_eof() => break,
                 Err(_) => panic!(),
             }
         }
         val
     });
+    assert!(lexed.is_err_and(|e| e.is_eof()));
     println!("total {} lexed in {:?}", len, dur);
 
     println!("starting quoted string bruh benchmark");
@@ -135,6 +509,14 @@ fn main() {
     println!("string of length {} lexed in {:?}", literal.len(), dur);
 }
 
+/// prints the allocations made since `since` under the name `stage`, with the
+/// `alloc-profiling` feature.
+fn report_allocations(stage: &str, since: AllocationStats) {
+    if cfg!(feature = "alloc-profiling") {
+        println!("allocations during {}: {}", stage, alloc_profiling::snapshot() - since);
+    }
+}
+
 fn get_quoted_string(len: usize) -> String {
     static ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789`~!@#$%^&*()_+{}[]|;:',./<>?-=\n\t\r\0";
     let mut rng = voxell_rng::rng::XoRoShiRo128::default();
@@ -154,9 +536,24 @@ fn get_quoted_string(len: usize) -> String {
 
 #[cfg(test)]
 mod tests {
-    use crate::lexer::{Lexer, LexerResult};
-    use crate::source_code::SourceCode;
-    use crate::types::Token;
+    use mumbo_lang::lexer::{Lexer, LexerResult};
+    use mumbo_lang::source_code::SourceCode;
+    use mumbo_lang::types::Token;
+
+    use super::emit_c;
+
+    #[test]
+    fn ill_typed_programs_emit_no_c() {
+        let text = "let x: u8 = true;\nlet y: bool = 5;\nlet z: u8 = 1000;";
+        let (emitted, diagnostics) = emit_c(text, "t.mumbo", false);
+        assert!(emitted.is_none());
+        let errors: Vec<_> = diagnostics.lines().filter(|line| line.starts_with("error")).collect();
+        assert_eq!(errors.len(), 3, "{}", diagnostics);
+        assert!(diagnostics.contains("E0301") && diagnostics.contains("E0322"), "{}", diagnostics);
+
+        let (emitted, diagnostics) = emit_c("let x: u8 = 1;", "t.mumbo", false);
+        assert!(emitted.is_some_and(|emitted| emitted.code.contains("_1 = 1;")), "{}", diagnostics);
+    }
 
     #[test]
     fn general_test() {
@@ -208,16 +605,14 @@ mod tests {
         let mut val: LexerResult<Token>;
         loop {
             val = lexer.lex_single_token();
-            if val == Err(crate::lexer::LexerError::Eof) {
+            if val.is_err_and(|e| e.is_eof()) {
                 break;
             }
 
             match val {
                 Ok(t) => {
                     if t.is_identifier_extractable() {
-                        let ident = lexer.extract_literal().unwrap();
-                        let str = str::from_utf8(ident).unwrap();
-                        print!("{} ", str);
+                        print!("{} ", lexer.extract_literal_str().unwrap());
                     } else {
                         print!("{} ", t.source_repr());
                     }
@@ -0,0 +1,360 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::ast::arena::AstArena;
+use crate::ast::{BinaryOp, Ident, Qualifier, Stmt};
+use crate::lexer::lexer_impls::raw;
+use crate::lexer::{Lexer, LexerError};
+use crate::source_code::SourceCode;
+use crate::token_stream::{LexedToken, TokenSource, TokenStream};
+use crate::types::{Span, Spanned, Token};
+
+mod expr;
+mod item;
+mod pattern;
+pub(crate) mod precedence;
+mod stmt;
+mod ty;
+
+/// what the parser was looking for when it found something else.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Expected {
+    Token(Token),
+    Expression,
+    Type,
+    /// `while` or `loop` after a label.
+    Loop,
+    Pattern,
+    /// a declaration after attributes.
+    Item,
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Token(token) => write_token(f, *token),
+            Expected::Expression => f.write_str("an expression"),
+            Expected::Type => f.write_str("a type"),
+            Expected::Loop => f.write_str("`while` or `loop`"),
+            Expected::Pattern => f.write_str("a pattern"),
+            Expected::Item => f.write_str("an item"),
+        }
+    }
+}
+
+/// `` `;` `` for punctuation and keywords, `identifier` for the tokens written as `{identifier}`.
+fn write_token(f: &mut fmt::Formatter<'_>, token: Token) -> fmt::Result {
+    let repr = token.source_repr();
+    match repr.strip_prefix('{').and_then(|r| r.strip_suffix('}')) {
+        Some(kind) => f.write_str(kind),
+        None => write!(f, "`{}`", repr),
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    /// `found` is `None` at the end of input.
+    Unexpected { expected: Expected, found: Option<Token> },
+    /// `a == b == c`, comparisons don't associate and need parentheses.
+    ChainedOperator(BinaryOp),
+    /// the left side of `=` or `op=` is not something that can be assigned to.
+    InvalidAssignmentTarget,
+    /// a struct or union field with the same name as an earlier one, `first` is the span
+    /// of that earlier name.
+    DuplicateField { first: Span },
+    /// like `DuplicateField`, for enum variants.
+    DuplicateVariant { first: Span },
+    /// like `DuplicateField`, for a field given twice in a struct literal.
+    DuplicateFieldInit { first: Span },
+    /// a `(` without its `)`, the error is where the `)` was expected.
+    UnclosedParen { open: Span },
+    /// a statement without its `;` at the end of a line, the error is an empty span right
+    /// after the statement.
+    MissingSemicolon,
+    /// `mut const u8`, `static static u8` or `compiletime static u8`, see `Qualifier::conflicts_with`.
+    ConflictingQualifiers {
+        qualifier: Qualifier,
+        previous: Spanned<Qualifier>,
+    },
+}
+
+impl ParseErrorKind {
+    /// stable identifier like `ErrorKind::code`. parser codes start at `E0101`, leaving the
+    /// lower ones to the lexer.
+    #[inline]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            ParseErrorKind::Unexpected { .. } => "E0101",
+            ParseErrorKind::ChainedOperator(_) => "E0102",
+            ParseErrorKind::InvalidAssignmentTarget => "E0103",
+            ParseErrorKind::DuplicateField { .. } => "E0104",
+            ParseErrorKind::DuplicateVariant { .. } => "E0105",
+            ParseErrorKind::ConflictingQualifiers { .. } => "E0106",
+            ParseErrorKind::UnclosedParen { .. } => "E0107",
+            ParseErrorKind::DuplicateFieldInit { .. } => "E0108",
+            ParseErrorKind::MissingSemicolon => "E0109",
+        }
+    }
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::Unexpected { expected, found } => {
+                write!(f, "expected {}, found ", expected)?;
+                match found {
+                    Some(token) => write_token(f, *token),
+                    None => f.write_str("end of input"),
+                }
+            }
+            ParseErrorKind::ChainedOperator(op) => write!(f, "`{}` operators cannot be chained", op),
+            ParseErrorKind::InvalidAssignmentTarget => f.write_str("invalid left-hand side of assignment"),
+            ParseErrorKind::DuplicateField { .. } => f.write_str("field is declared more than once"),
+            ParseErrorKind::DuplicateVariant { .. } => f.write_str("variant is declared more than once"),
+            ParseErrorKind::DuplicateFieldInit { .. } => f.write_str("field is initialized more than once"),
+            ParseErrorKind::UnclosedParen { .. } => f.write_str("`(` is never closed"),
+            ParseErrorKind::MissingSemicolon => f.write_str("expected `;` at the end of the statement"),
+            ParseErrorKind::ConflictingQualifiers { qualifier, previous } if *qualifier == previous.value => {
+                write!(f, "`{}` is given twice", qualifier)
+            }
+            ParseErrorKind::ConflictingQualifiers { qualifier, previous } => {
+                write!(f, "`{}` conflicts with `{}`", qualifier, previous.value)
+            }
+        }
+    }
+}
+
+/// like `LexerError`, the span covers the offending token or expression.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: Span,
+}
+
+impl ParseError {
+    #[inline]
+    pub const fn new(kind: ParseErrorKind, span: Span) -> Self {
+        ParseError { kind, span }
+    }
+
+    #[inline]
+    pub const fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+}
+
+impl fmt::Display for ParseError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code(), self.kind)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
+/// a whole source parsed in one go, see `Parser::parse`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Default, PartialEq)]
+pub struct Parsed<'source> {
+    pub stmts: Vec<Stmt<'source>>,
+    /// every parse error, parsing continues after the statement each of them is in.
+    pub errors: Vec<ParseError>,
+    pub lexer_errors: Vec<LexerError>,
+}
+
+/// recursive descent parser over a `TokenStream`. lexer errors are collected by the stream
+/// and can be taken with `take_lexer_errors`, the parser only sees well-formed tokens.
+///
+/// the tokens come from any `TokenSource`, a `Lexer` to lex while parsing or the `cursor` of
+/// a `TokenBuffer` to parse tokens that were lexed in one go.
+#[derive(Debug, Clone)]
+pub struct Parser<'source, S = Lexer<'source>> {
+    tokens: TokenStream<'source, S>,
+    /// where the nodes that other nodes point to go.
+    arena: &'source AstArena<'source>,
+    /// errors that didn't stop the statement they are in, like duplicate field names.
+    errors: Vec<ParseError>,
+    /// number of `{` consumed and not closed yet, see `recover_to_semi`.
+    depth: usize,
+    /// set while parsing an `if` or `while` condition or a `match` scrutinee, where the `{`
+    /// after `name` opens the body instead of a struct literal.
+    no_struct_literal: bool,
+}
+
+impl<'source, S: TokenSource<'source>> Parser<'source, S> {
+    #[inline]
+    pub fn new(tokens: S, arena: &'source AstArena<'source>) -> Self {
+        Parser {
+            tokens: TokenStream::new(tokens),
+            arena,
+            errors: Vec::new(),
+            depth: 0,
+            no_struct_literal: false,
+        }
+    }
+
+    #[inline]
+    pub fn is_at_end(&mut self) -> bool {
+        self.tokens.is_at_end()
+    }
+
+    #[inline]
+    pub fn lexer_errors(&self) -> &[LexerError] {
+        self.tokens.errors()
+    }
+
+    #[inline]
+    pub fn take_lexer_errors(&mut self) -> Vec<LexerError> {
+        self.tokens.take_errors()
+    }
+
+    /// errors reported without failing the statement they were found in, the statement
+    /// was still parsed.
+    #[inline]
+    pub fn take_errors(&mut self) -> Vec<ParseError> {
+        core::mem::take(&mut self.errors)
+    }
+
+    /// parses statements until the end of input, skipping over the ones that fail to parse.
+    pub fn parse(mut self) -> Parsed<'source> {
+        let mut out = Parsed::default();
+        while !self.is_at_end() {
+            match self.parse_stmt() {
+                Ok(stmt) => out.stmts.push(stmt),
+                Err(e) => {
+                    // recovery stops in front of a `}` without a matching `{`, skip it once it
+                    // has been reported
+                    if e.span == self.tokens.peek_span() {
+                        self.eat(Token::IndentRBrace);
+                    }
+                    self.errors.push(e);
+                }
+            }
+        }
+        out.errors = self.take_errors();
+        out.lexer_errors = self.take_lexer_errors();
+        out
+    }
+
+    /// every token is consumed through here to keep track of `depth`.
+    #[inline]
+    fn next(&mut self) -> Option<LexedToken<'source>> {
+        let token = self.tokens.next()?;
+        match token.token {
+            Token::IndentLBrace => self.depth += 1,
+            Token::IndentRBrace => self.depth = self.depth.saturating_sub(1),
+            _ => {}
+        }
+        Some(token)
+    }
+
+    /// consumes the next token if it is `token`.
+    #[inline]
+    fn eat(&mut self, token: Token) -> Option<LexedToken<'source>> {
+        if self.tokens.at(token) { self.next() } else { None }
+    }
+
+    /// consumes the next token, failing without consuming if it isn't `token`.
+    fn expect(&mut self, token: Token) -> Result<LexedToken<'source>, ParseError> {
+        match self.eat(token) {
+            Some(t) => Ok(t),
+            None => Err(self.unexpected(Expected::Token(token))),
+        }
+    }
+
+    /// parses `item, item, ...` up to and including `close`, allowing a trailing comma.
+    fn parse_comma_separated<T>(
+        &mut self,
+        close: Token,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut items = Vec::new();
+        while self.eat(close).is_none() {
+            items.push(item(self)?);
+            if self.eat(Token::PuncComma).is_none() {
+                self.expect(close)?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    /// runs `parse` with struct literals allowed or not, restoring the previous setting.
+    fn with_struct_literals<T>(
+        &mut self,
+        allowed: bool,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        let outer = core::mem::replace(&mut self.no_struct_literal, !allowed);
+        let result = parse(self);
+        self.no_struct_literal = outer;
+        result
+    }
+
+    /// reports every name that was already declared earlier in `names`.
+    fn check_duplicates(&mut self, names: impl Iterator<Item = Ident<'source>>, kind: fn(Span) -> ParseErrorKind) {
+        let mut seen: Vec<Ident<'source>> = Vec::new();
+        for name in names {
+            match seen.iter().find(|s| s.name == name.name) {
+                Some(first) => self.errors.push(ParseError::new(kind(first.span), name.span)),
+                None => seen.push(name),
+            }
+        }
+    }
+
+    fn parse_ident(&mut self) -> Result<Ident<'source>, ParseError> {
+        let token = self.expect(Token::LitIdentifier)?;
+        Ok(Ident::new(identifier_str(&token), token.span))
+    }
+
+    /// `Enum::Variant`, the enum and the variant.
+    fn parse_path(&mut self) -> Result<(Ident<'source>, Ident<'source>), ParseError> {
+        let enum_name = self.parse_ident()?;
+        self.expect(Token::PuncColonColon)?;
+        Ok((enum_name, self.parse_ident()?))
+    }
+
+    /// an error for the next token, or for the end of input if there is none.
+    fn unexpected(&mut self, expected: Expected) -> ParseError {
+        let found = self.tokens.peek_token(0);
+        ParseError::new(ParseErrorKind::Unexpected { expected, found }, self.tokens.peek_span())
+    }
+}
+
+/// shorthand for `Parser::new(Lexer::new(SourceCode::new(source)), arena).parse()`.
+#[inline]
+pub fn parse<'source>(source: &'source str, arena: &'source AstArena<'source>) -> Parsed<'source> {
+    Parser::new(Lexer::new(SourceCode::new(source)), arena).parse()
+}
+
+/// the text of an identifier token.
+#[inline]
+fn identifier_str<'source>(token: &LexedToken<'source>) -> &'source str {
+    let literal = token.literal.unwrap_or_default();
+    // SAFETY: literals are slices of a `str` that start and end at token boundaries
+    unsafe { raw::to_str(literal) }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use alloc::boxed::Box;
+
+    use super::parse;
+    use crate::ast::Stmt;
+    use crate::ast::arena::AstArena;
+
+    /// the statements of `text`, which has to parse without errors. the arena is leaked so
+    /// that the passes under test can borrow the statements for as long as `text`.
+    pub(crate) fn parse_ok(text: &str) -> &mut [Stmt<'_>] {
+        let arena = Box::leak(Box::new(AstArena::new()));
+        let parsed = parse(text, arena);
+        assert!(parsed.lexer_errors.is_empty(), "{:?}", parsed.lexer_errors);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        Box::leak(parsed.stmts.into_boxed_slice())
+    }
+}
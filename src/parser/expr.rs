@@ -0,0 +1,728 @@
+use alloc::vec::Vec;
+
+use crate::ast::{BlockKind, Expr, ExprKind, FieldInit, Ident, MatchArm, UnaryOp};
+use crate::literal_value::Literal;
+use crate::parser::precedence::{Associativity, Infix, InfixOperator, Precedence, infix_operator};
+use crate::parser::stmt::is_block_like;
+use crate::parser::{Expected, ParseError, ParseErrorKind, Parser, identifier_str};
+use crate::token_stream::TokenSource;
+use crate::types::{Span, Token};
+
+/// whether `expr` may appear on the left of an assignment.
+#[inline]
+const fn is_place(expr: &Expr<'_>) -> bool {
+    matches!(
+        expr.kind,
+        ExprKind::Identifier(_)
+            | ExprKind::Field { .. }
+            | ExprKind::Index { .. }
+            | ExprKind::Unary {
+                op: UnaryOp::Deref,
+                ..
+            }
+    )
+}
+
+impl<'source, S: TokenSource<'source>> Parser<'source, S> {
+    /// parses one expression, leaving whatever follows it in the stream.
+    ///
+    /// from weakest to strongest: assignments `= += -= ...` (right associative), `== !=`,
+    /// `< <= > >=`, `|`, `^`, `&`, `<< >>`, `+ -`, `* / %`, `cast`, the prefix `- ! ~ *` and
+    /// the postfix calls, field accesses and indexing. comparisons don't chain, all other binary
+    /// operators are left associative.
+    pub fn parse_expr(&mut self) -> Result<Expr<'source>, ParseError> {
+        self.parse_binary(Precedence::Assignment)
+    }
+
+    /// precedence climbing: parses operands joined by operators that bind at least as
+    /// tightly as `min`.
+    fn parse_binary(&mut self, min: Precedence) -> Result<Expr<'source>, ParseError> {
+        let mut lhs = self.parse_cast()?;
+        // precedence of the last non-associative operator applied at this level
+        let mut chained = None;
+        while let Some(next) = self.tokens.peek(0).copied() {
+            let Some(InfixOperator {
+                infix,
+                precedence,
+                associativity,
+            }) = infix_operator(next.token)
+            else {
+                break;
+            };
+            if precedence < min {
+                break;
+            }
+            if chained == Some(precedence)
+                && let Infix::Binary(op) = infix
+            {
+                return Err(ParseError::new(ParseErrorKind::ChainedOperator(op), next.span));
+            }
+            self.next();
+
+            let rhs = match associativity {
+                Associativity::Right => self.parse_binary(precedence)?,
+                Associativity::Left | Associativity::None => self.parse_above(precedence)?,
+            };
+            let span = lhs.span.merge(rhs.span);
+            let kind = match infix {
+                Infix::Binary(op) => ExprKind::Binary {
+                    op,
+                    lhs: self.arena.alloc_expr(lhs),
+                    rhs: self.arena.alloc_expr(rhs),
+                },
+                Infix::Assign(op) => {
+                    if !is_place(&lhs) {
+                        return Err(ParseError::new(ParseErrorKind::InvalidAssignmentTarget, lhs.span));
+                    }
+                    ExprKind::Assign {
+                        op,
+                        target: self.arena.alloc_expr(lhs),
+                        value: self.arena.alloc_expr(rhs),
+                    }
+                }
+            };
+            lhs = Expr::new(kind, span);
+            chained = (associativity == Associativity::None).then_some(precedence);
+        }
+        Ok(lhs)
+    }
+
+    /// operands of an operator at `precedence`, only containing tighter operators.
+    #[inline]
+    fn parse_above(&mut self, precedence: Precedence) -> Result<Expr<'source>, ParseError> {
+        match precedence.tighter() {
+            Some(tighter) => self.parse_binary(tighter),
+            None => self.parse_cast(),
+        }
+    }
+
+    /// `expr cast ty`, binding tighter than binary operators and looser than prefix ones,
+    /// `-a cast u8` casts `-a`. casts chain left to right.
+    fn parse_cast(&mut self) -> Result<Expr<'source>, ParseError> {
+        let mut expr = self.parse_unary()?;
+        while self.eat(Token::KwCast).is_some() {
+            let ty = self.parse_type()?;
+            let span = expr.span.merge(ty.span);
+            let kind = ExprKind::Cast {
+                expr: self.arena.alloc_expr(expr),
+                ty: self.arena.alloc_type(ty),
+            };
+            expr = Expr::new(kind, span);
+        }
+        Ok(expr)
+    }
+
+    /// `-x`, `!x`, `~x` or `*x`. prefix operators bind tighter than binary ones and looser
+    /// than postfix ones, `-a.b` negates `a.b`.
+    fn parse_unary(&mut self) -> Result<Expr<'source>, ParseError> {
+        let Some(op) = self.tokens.peek_token(0).and_then(UnaryOp::from_token) else {
+            return self.parse_postfix();
+        };
+        let start = self.tokens.peek_span();
+        self.next();
+        let expr = self.parse_unary()?;
+        let span = start.merge(expr.span);
+        let kind = ExprKind::Unary {
+            op,
+            expr: self.arena.alloc_expr(expr),
+        };
+        Ok(Expr::new(kind, span))
+    }
+
+    /// a primary expression followed by any number of `(args)`, `.field` and `[index]`,
+    /// applied left to right. tuple fields are numbers, `t.0`, the lexer doesn't split
+    /// `t.0.1` or `t.0.x` into fields yet.
+    ///
+    /// expressions ending in a block take none unless they are in parentheses, so that a
+    /// statement like `if c { ... }` isn't continued by what comes after it.
+    fn parse_postfix(&mut self) -> Result<Expr<'source>, ParseError> {
+        let grouped = self.tokens.at(Token::IndentLParen);
+        let mut expr = self.parse_primary()?;
+        if !grouped && is_block_like(&expr) {
+            return Ok(expr);
+        }
+        loop {
+            let start = expr.span.start;
+            let kind = match self.tokens.peek_token(0) {
+                Some(Token::IndentLParen) => {
+                    self.next();
+                    let args = self.with_struct_literals(true, |p| {
+                        p.parse_comma_separated(Token::IndentRParen, Self::parse_expr)
+                    })?;
+                    ExprKind::Call {
+                        callee: self.arena.alloc_expr(expr),
+                        args,
+                    }
+                }
+                Some(Token::PuncDot) => {
+                    self.next();
+                    let field = match self.eat(Token::LitInteger) {
+                        Some(index) => Ident::new(identifier_str(&index), index.span),
+                        None => self.parse_ident()?,
+                    };
+                    ExprKind::Field {
+                        expr: self.arena.alloc_expr(expr),
+                        field,
+                    }
+                }
+                Some(Token::IndentLBracket) => {
+                    self.next();
+                    let index = self.with_struct_literals(true, Self::parse_expr)?;
+                    let index = self.arena.alloc_expr(index);
+                    self.expect(Token::IndentRBracket)?;
+                    ExprKind::Index {
+                        expr: self.arena.alloc_expr(expr),
+                        index,
+                    }
+                }
+                _ => return Ok(expr),
+            };
+            expr = Expr::new(kind, Span::new(start, self.tokens.last_end()));
+        }
+    }
+
+    pub(super) fn parse_primary(&mut self) -> Result<Expr<'source>, ParseError> {
+        let Some(next) = self.tokens.peek(0).copied() else {
+            return Err(self.unexpected(Expected::Expression));
+        };
+        let kind = match next.token {
+            Token::LitIdentifier if self.tokens.peek_token(1) == Some(Token::PuncColonColon) => {
+                let (enum_name, variant) = self.parse_path()?;
+                let span = enum_name.span.merge(variant.span);
+                return Ok(Expr::new(ExprKind::Path { enum_name, variant }, span));
+            }
+            Token::LitIdentifier
+                if !self.no_struct_literal && self.tokens.peek_token(1) == Some(Token::IndentLBrace) =>
+            {
+                return self.parse_struct_literal();
+            }
+            Token::LitIdentifier => ExprKind::Identifier(identifier_str(&next)),
+            Token::LitInteger
+            | Token::LitFloat
+            | Token::LitStr
+            | Token::LitChar
+            | Token::LitBool
+            | Token::LitUninit => ExprKind::Literal(Literal::new(next.token, next.literal.unwrap_or_default())),
+            Token::KwIf => return self.parse_if(),
+            Token::KwMatch => return self.parse_match(),
+            Token::KwWhile | Token::KwLoop | Token::PuncAt => return self.parse_loop(),
+            Token::KwBreak | Token::KwContinue | Token::KwReturn => return self.parse_jump(),
+            Token::IndentLBrace => return self.parse_block_expr(BlockKind::Plain),
+            Token::IndentLParen => return self.parse_group(),
+            Token::KwCompiletime | Token::KwRuntime if self.tokens.peek_token(1) == Some(Token::IndentLBrace) => {
+                self.next();
+                let kind = match next.token {
+                    Token::KwCompiletime => BlockKind::Compiletime,
+                    _ => BlockKind::Runtime,
+                };
+                let mut block = self.parse_block_expr(kind)?;
+                block.span.start = next.span.start;
+                return Ok(block);
+            }
+            _ => return Err(self.unexpected(Expected::Expression)),
+        };
+        self.next();
+        Ok(Expr::new(kind, next.span))
+    }
+
+    /// `(expr)`. the expression keeps its kind, its span grows to cover the parentheses.
+    fn parse_group(&mut self) -> Result<Expr<'source>, ParseError> {
+        let open = self.expect(Token::IndentLParen)?.span;
+        let mut expr = self.with_struct_literals(true, Self::parse_expr)?;
+        if self.eat(Token::IndentRParen).is_none() {
+            let span = self.tokens.peek_span();
+            return Err(ParseError::new(ParseErrorKind::UnclosedParen { open }, span));
+        }
+        expr.span = Span::new(open.start, self.tokens.last_end());
+        Ok(expr)
+    }
+
+    /// `Name { field: value, ... }`, allowing a trailing comma. a field given twice is
+    /// reported without failing the literal.
+    fn parse_struct_literal(&mut self) -> Result<Expr<'source>, ParseError> {
+        let name = self.parse_ident()?;
+        self.expect(Token::IndentLBrace)?;
+        let fields = self.with_struct_literals(true, |p| {
+            p.parse_comma_separated(Token::IndentRBrace, |p| {
+                let name = p.parse_ident()?;
+                p.expect(Token::PuncColon)?;
+                let value = p.parse_expr()?;
+                let span = name.span.merge(value.span);
+                Ok(FieldInit { name, value, span })
+            })
+        })?;
+        self.check_duplicates(fields.iter().map(|f| f.name), |first| ParseErrorKind::DuplicateFieldInit { first });
+        let span = Span::new(name.span.start, self.tokens.last_end());
+        Ok(Expr::new(ExprKind::Struct { name, fields }, span))
+    }
+
+    fn parse_block_expr(&mut self, kind: BlockKind) -> Result<Expr<'source>, ParseError> {
+        let block = self.parse_block(kind)?;
+        let span = block.span;
+        Ok(Expr::new(ExprKind::Block(self.arena.alloc_block(block)), span))
+    }
+
+    /// `if cond { ... }`, optionally followed by `else { ... }` or `else if ...`.
+    fn parse_if(&mut self) -> Result<Expr<'source>, ParseError> {
+        let start = self.expect(Token::KwIf)?.span;
+        let cond = self.arena.alloc_expr(self.with_struct_literals(false, Self::parse_expr)?);
+        let then_branch = self.arena.alloc_block(self.parse_block(BlockKind::Plain)?);
+        let else_branch = match self.eat(Token::KwElse) {
+            Some(_) if self.tokens.at(Token::KwIf) => Some(self.arena.alloc_expr(self.parse_if()?)),
+            Some(_) => Some(self.arena.alloc_expr(self.parse_block_expr(BlockKind::Plain)?)),
+            None => None,
+        };
+        let kind = ExprKind::If {
+            cond,
+            then_branch,
+            else_branch,
+        };
+        Ok(Expr::new(kind, Span::new(start.start, self.tokens.last_end())))
+    }
+
+    /// `match scrutinee { pattern => body, ... }`. the comma after an arm is optional when its
+    /// body ends in a block and after the last arm.
+    fn parse_match(&mut self) -> Result<Expr<'source>, ParseError> {
+        let start = self.expect(Token::KwMatch)?.span;
+        let scrutinee = self.arena.alloc_expr(self.with_struct_literals(false, Self::parse_expr)?);
+        self.expect(Token::IndentLBrace)?;
+        let mut arms = Vec::new();
+        while self.eat(Token::IndentRBrace).is_none() {
+            let pattern = self.parse_pattern()?;
+            self.expect(Token::PuncFatArrow)?;
+            let body = self.parse_expr()?;
+            let span = pattern.span.merge(body.span);
+            let needs_comma = !is_block_like(&body);
+            arms.push(MatchArm { pattern, body, span });
+            if self.eat(Token::PuncComma).is_none() && needs_comma {
+                self.expect(Token::IndentRBrace)?;
+                break;
+            }
+        }
+        let kind = ExprKind::Match { scrutinee, arms };
+        Ok(Expr::new(kind, Span::new(start.start, self.tokens.last_end())))
+    }
+
+    /// `while cond { ... }` or `loop { ... }`, optionally labeled with `@label`.
+    fn parse_loop(&mut self) -> Result<Expr<'source>, ParseError> {
+        let start = self.tokens.peek_span();
+        let label = self.parse_label()?;
+        let kind = match self.tokens.peek_token(0) {
+            Some(Token::KwWhile) => {
+                self.next();
+                let cond = self.arena.alloc_expr(self.with_struct_literals(false, Self::parse_expr)?);
+                let body = self.arena.alloc_block(self.parse_block(BlockKind::Plain)?);
+                ExprKind::While { label, cond, body }
+            }
+            Some(Token::KwLoop) => {
+                self.next();
+                let body = self.arena.alloc_block(self.parse_block(BlockKind::Plain)?);
+                ExprKind::Loop { label, body }
+            }
+            _ => return Err(self.unexpected(Expected::Loop)),
+        };
+        Ok(Expr::new(kind, Span::new(start.start, self.tokens.last_end())))
+    }
+
+    /// `break @label value`, `continue @label` or `return value`.
+    fn parse_jump(&mut self) -> Result<Expr<'source>, ParseError> {
+        let Some(keyword) = self.next() else {
+            return Err(self.unexpected(Expected::Expression));
+        };
+        let label = match keyword.token {
+            Token::KwBreak | Token::KwContinue => self.parse_label()?,
+            _ => None,
+        };
+        let value = match self.tokens.peek_token(0) {
+            _ if keyword.token == Token::KwContinue => None,
+            None | Some(Token::PuncSemi | Token::PuncComma | Token::IndentRBrace | Token::IndentRParen) => None,
+            Some(_) => Some(self.arena.alloc_expr(self.parse_expr()?)),
+        };
+        let kind = match keyword.token {
+            Token::KwBreak => ExprKind::Break { label, value },
+            Token::KwContinue => ExprKind::Continue { label },
+            _ => ExprKind::Return(value),
+        };
+        Ok(Expr::new(kind, Span::new(keyword.span.start, self.tokens.last_end())))
+    }
+
+    /// `@label`, if there is one.
+    fn parse_label(&mut self) -> Result<Option<Ident<'source>>, ParseError> {
+        let Some(at) = self.eat(Token::PuncAt) else {
+            return Ok(None);
+        };
+        let name = self.parse_ident()?;
+        Ok(Some(Ident::new(name.name, at.span.merge(name.span))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::format;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use alloc::string::String;
+    use core::fmt::Write;
+
+    use crate::ast::arena::AstArena;
+    use crate::ast::{BinaryOp, BlockKind, Expr, ExprKind, StmtKind};
+    use crate::lexer::Lexer;
+    use crate::parser::{Expected, ParseError, ParseErrorKind, Parser, parse};
+    use crate::source_code::SourceCode;
+    use crate::types::{Span, Token};
+
+    /// fully parenthesized prefix form, `(+ a (* b c))`.
+    fn sexpr(expr: &Expr<'_>) -> String {
+        let mut out = String::new();
+        write_sexpr(&mut out, expr);
+        out
+    }
+
+    fn write_sexpr(out: &mut String, expr: &Expr<'_>) {
+        match &expr.kind {
+            ExprKind::Literal(literal) => out.push_str(core::str::from_utf8(literal.raw()).unwrap()),
+            ExprKind::Identifier(name) => out.push_str(name),
+            ExprKind::Path { enum_name, variant } => write!(out, "{}::{}", enum_name.name, variant.name).unwrap(),
+            ExprKind::Binary { op, lhs, rhs } => {
+                write!(out, "({op} ").unwrap();
+                write_sexpr(out, lhs);
+                out.push(' ');
+                write_sexpr(out, rhs);
+                out.push(')');
+            }
+            ExprKind::Assign { op, target, value } => {
+                match op {
+                    Some(op) => write!(out, "({op}= ").unwrap(),
+                    None => out.push_str("(= "),
+                }
+                write_sexpr(out, target);
+                out.push(' ');
+                write_sexpr(out, value);
+                out.push(')');
+            }
+            ExprKind::Unary { op, expr } => {
+                write!(out, "({op}").unwrap();
+                write_sexpr(out, expr);
+                out.push(')');
+            }
+            ExprKind::Call { callee, args } => {
+                out.push_str("(call ");
+                write_sexpr(out, callee);
+                for arg in args {
+                    out.push(' ');
+                    write_sexpr(out, arg);
+                }
+                out.push(')');
+            }
+            ExprKind::Field { expr, field } => {
+                out.push_str("(. ");
+                write_sexpr(out, expr);
+                write!(out, " {})", field.name).unwrap();
+            }
+            ExprKind::Index { expr, index } => {
+                out.push_str("([] ");
+                write_sexpr(out, expr);
+                out.push(' ');
+                write_sexpr(out, index);
+                out.push(')');
+            }
+            ExprKind::Cast { expr, ty } => {
+                out.push_str("(cast ");
+                write_sexpr(out, expr);
+                write!(out, " {ty})").unwrap();
+            }
+            ExprKind::Struct { name, fields } => {
+                write!(out, "(struct {}", name.name).unwrap();
+                for field in fields {
+                    write!(out, " {}:", field.name.name).unwrap();
+                    write_sexpr(out, &field.value);
+                }
+                out.push(')');
+            }
+            other => panic!("no s-expression for {other:?}"),
+        }
+    }
+
+    /// the arena is leaked so the tree can outlive the helper.
+    fn parse_expr(text: &str) -> Result<Expr<'_>, ParseError> {
+        let mut parser = Parser::new(Lexer::new(SourceCode::new(text)), Box::leak(Box::new(AstArena::new())));
+        let expr = parser.parse_expr()?;
+        assert!(parser.is_at_end(), "{text:?} was not fully parsed");
+        assert!(parser.lexer_errors().is_empty());
+        Ok(expr)
+    }
+
+    fn parse_ok(text: &str) -> Expr<'_> {
+        parse_expr(text).unwrap_or_else(|e| panic!("{text:?} failed to parse: {e}"))
+    }
+
+    fn assert_parses(text: &str, expected: &str) {
+        match parse_expr(text) {
+            Ok(expr) => assert_eq!(sexpr(&expr), expected, "{text:?}"),
+            Err(e) => panic!("{text:?} failed to parse: {e}"),
+        }
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_parses("1 + 2 * 3", "(+ 1 (* 2 3))");
+        assert_parses("1 * 2 + 3 % 4", "(+ (* 1 2) (% 3 4))");
+        assert_parses("a - b - c", "(- (- a b) c)");
+        assert_parses("a / b * c", "(* (/ a b) c)");
+        assert_parses("a << 1 + 2", "(<< a (+ 1 2))");
+        assert_parses("a & b | c ^ d & e", "(| (& a b) (^ c (& d e)))");
+        assert_parses("a | b | c", "(| (| a b) c)");
+        assert_parses("a & 1 == 0", "(== (& a 1) 0)");
+        assert_parses("a < b == c >= d", "(== (< a b) (>= c d))");
+        assert_parses("x = y = 1 + 2", "(= x (= y (+ 1 2)))");
+        assert_parses("x += y <<= 2 * z", "(+= x (<<= y (* 2 z)))");
+        assert_parses("mask ^= a >> 3 != b", "(^= mask (!= (>> a 3) b))");
+    }
+
+    #[test]
+    fn every_operator_is_parsed() {
+        for op in BinaryOp::ALL {
+            assert_parses(&format!("a {op} b"), &format!("({op} a b)"));
+            if let Some(token) = op.assign_token() {
+                assert_parses(&format!("a {token} b"), &format!("({token} a b)"));
+            }
+        }
+    }
+
+    #[test]
+    fn literals_and_spans() {
+        let expr = parse_expr("\"s\" + 'c' * 1.5").unwrap();
+        assert_eq!(expr.span, Span::new(0, 15));
+        let ExprKind::Binary { lhs, rhs, .. } = expr.kind else { panic!() };
+        assert_eq!(lhs.span, Span::new(0, 3));
+        assert!(matches!(lhs.kind, ExprKind::Literal(l) if l.token() == Token::LitStr && l.raw() == b"s"));
+        assert_eq!(rhs.span, Span::new(6, 15));
+        assert_parses("v = uninit", "(= v uninit)");
+    }
+
+    #[test]
+    fn non_associative_operators_do_not_chain() {
+        let error = parse_expr("a == b != c").unwrap_err();
+        assert_eq!(error, ParseError::new(ParseErrorKind::ChainedOperator(BinaryOp::Ne), Span::new(7, 9)));
+        assert_eq!(error.to_string(), "E0102: `!=` operators cannot be chained");
+        assert!(parse_expr("a < b < c").is_err());
+        assert_parses("a == b < c", "(== a (< b c))");
+        // shifts associate left, like in C
+        assert_parses("a >> b << c", "(<< (>> a b) c)");
+    }
+
+    #[test]
+    fn reports_missing_operands_and_bad_targets() {
+        let error = parse_expr("a + ;").unwrap_err();
+        let kind = ParseErrorKind::Unexpected {
+            expected: Expected::Expression,
+            found: Some(Token::PuncSemi),
+        };
+        assert_eq!(error, ParseError::new(kind, Span::new(4, 5)));
+        assert_eq!(error.to_string(), "E0101: expected an expression, found `;`");
+
+        let error = parse_expr("a *").unwrap_err();
+        assert_eq!(error.span, Span::new(3, 3));
+        assert_eq!(error.kind.to_string(), "expected an expression, found end of input");
+
+        let error = parse_expr("a + b = c").unwrap_err();
+        assert_eq!(error, ParseError::new(ParseErrorKind::InvalidAssignmentTarget, Span::new(0, 5)));
+        assert!(parse_expr("1 += 2").is_err());
+    }
+
+    #[test]
+    fn postfix_expressions() {
+        assert_parses("bob()", "(call bob)");
+        assert_parses("f(a, b + 1,)", "(call f a (+ b 1))");
+        assert_parses("obj.method(x)", "(call (. obj method) x)");
+        assert_parses("a.b.c[i][j](k)", "(call ([] ([] (. (. a b) c) i) j) k)");
+        assert_parses("f(g(x))[0] * 2 + s.len", "(+ (* ([] (call f (call g x)) 0) 2) (. s len))");
+        assert_parses("arr[i] = v.x += 1", "(= ([] arr i) (+= (. v x) 1))");
+
+        let expr = parse_ok("__secret()  .field [ 2 ]");
+        assert_eq!(expr.span, Span::new(0, 24));
+        let ExprKind::Index { expr: base, index } = &expr.kind else { panic!() };
+        assert_eq!((base.span, index.span), (Span::new(0, 18), Span::new(21, 22)));
+
+        let error = parse_expr("f(a b)").unwrap_err();
+        let kind = ParseErrorKind::Unexpected {
+            expected: Expected::Token(Token::IndentRParen),
+            found: Some(Token::LitIdentifier),
+        };
+        assert_eq!(error, ParseError::new(kind, Span::new(4, 5)));
+        assert_parses("t.0[1]", "([] (. t 0) 1)");
+        assert!(parse_expr("a[]").is_err());
+        assert!(parse_expr("f() = 1").is_err());
+    }
+
+    #[test]
+    fn grouping_and_unary_operators() {
+        assert_parses("(a + b) * c", "(* (+ a b) c)");
+        assert_parses("a - (b - c)", "(- a (- b c))");
+        assert_parses("(a == b) == (c < d)", "(== (== a b) (< c d))");
+        assert_parses("-a * -b.c", "(* (-a) (-(. b c)))");
+        assert_parses("!~*p - -1", "(- (!(~(*p))) (-1))");
+        assert_parses("*p = (x) = 1", "(= (*p) (= x 1))");
+        assert_parses("(f)(x)[0]", "([] (call f x) 0)");
+        assert_parses("-(a + b).c", "(-(. (+ a b) c))");
+
+        let expr = parse_ok("( a + b )");
+        assert!(matches!(expr.kind, ExprKind::Binary { op: BinaryOp::Add, .. }));
+        assert_eq!(expr.span, Span::new(0, 9));
+        let expr = parse_ok("(if a { f } else { g })(x)");
+        let ExprKind::Call { callee, .. } = &expr.kind else { panic!() };
+        assert_eq!((callee.span, expr.span), (Span::new(0, 23), Span::new(0, 26)));
+
+        let unclosed = |open| ParseErrorKind::UnclosedParen { open };
+        let error = parse_expr("(a + b").unwrap_err();
+        assert_eq!(error, ParseError::new(unclosed(Span::new(0, 1)), Span::new(6, 6)));
+        let error = parse_expr("x * ((a) b)").unwrap_err();
+        assert_eq!(error, ParseError::new(unclosed(Span::new(4, 5)), Span::new(9, 10)));
+        assert_eq!(error.to_string(), "E0107: `(` is never closed");
+        assert!(parse_expr("-").is_err());
+        assert!(parse_expr("-a = 1").is_err());
+    }
+
+    #[test]
+    fn casts() {
+        assert_parses("__variant1 cast u8", "(cast __variant1 u8)");
+        assert_parses("Token::EqEq cast u16 + 1", "(+ (cast Token::EqEq u16) 1)");
+        assert_parses("-a.b cast i8 * 2", "(* (cast (-(. a b)) i8) 2)");
+        assert_parses("x cast u16 cast *const u8", "(cast (cast x u16) *const u8)");
+        assert_parses("a + b cast f32 < c", "(< (+ a (cast b f32)) c)");
+
+        let expr = parse_ok("v  cast  u8");
+        assert_eq!(expr.span, Span::new(0, 11));
+        assert!(parse_expr("v cast").is_err());
+    }
+
+    #[test]
+    fn struct_literals() {
+        assert_parses("Point { x: 1, y: a + 1, }", "(struct Point x:1 y:(+ a 1))");
+        assert_parses("Empty {}", "(struct Empty)");
+        assert_parses(
+            "P { inner: Q { v: f(R { a: 0 }) } }.inner.v",
+            "(. (. (struct P inner:(struct Q v:(call f (struct R a:0)))) inner) v)",
+        );
+        assert_eq!(parse_ok("P { a: 1 }").span, Span::new(0, 10));
+
+        // `{` after the name of a condition opens the body
+        let expr = parse_ok("if a == b { x } else { y }");
+        let ExprKind::If { cond, .. } = &expr.kind else { panic!() };
+        assert_eq!(sexpr(cond), "(== a b)");
+        let expr = parse_ok("while (P { x: 1 }).x == f(Q { y: 2 }) { x }");
+        let ExprKind::While { cond, .. } = &expr.kind else { panic!() };
+        assert_eq!(sexpr(cond), "(== (. (struct P x:1) x) (call f (struct Q y:2)))");
+        let expr = parse_ok("match v { _ => P { a: 1 } }");
+        let ExprKind::Match { arms, .. } = &expr.kind else { panic!() };
+        assert_eq!(sexpr(&arms[0].body), "(struct P a:1)");
+
+        let arena = AstArena::new();
+        let parsed = parse("let p = P { a: 1, b: 2, a: 3 };", &arena);
+        let kind = ParseErrorKind::DuplicateFieldInit { first: Span::new(12, 13) };
+        assert_eq!(parsed.errors, [ParseError::new(kind, Span::new(24, 25))]);
+        assert_eq!(parsed.errors[0].to_string(), "E0108: field is initialized more than once");
+        assert!(parse_expr("P { a }").is_err());
+        assert!(parse_expr("P { a: 1 b: 2 }").is_err());
+    }
+
+    #[test]
+    fn if_else_chains() {
+        let expr = parse_ok("if a == b { x = 1; } else if c { x } else { 2 }");
+        let ExprKind::If { cond, then_branch, else_branch: Some(else_branch) } = expr.kind else { panic!() };
+        assert_eq!(sexpr(cond), "(== a b)");
+        assert_eq!((then_branch.stmts.len(), then_branch.tail.is_none()), (1, true));
+        assert_eq!(else_branch.span, Span::new(26, 47));
+        let ExprKind::If { then_branch, else_branch: Some(last), .. } = &else_branch.kind else { panic!() };
+        assert!(then_branch.tail.is_some());
+        assert!(matches!(&last.kind, ExprKind::Block(block) if block.kind == BlockKind::Plain && block.tail.is_some()));
+        assert_eq!(expr.span, Span::new(0, 47));
+    }
+
+    #[test]
+    fn if_in_statement_and_expression_position() {
+        let arena = AstArena::new();
+        let parsed = parse(
+            "let cond: mut bool = false;\n\
+             if cond == true {\n    mynum = mynum + 1;\n} else {\n    mynum = mynum - 1;\n};\n\
+             let v = if c { 1 } else { 2 };\nif c { a = 1; }\nlet w = compiletime { 3 };",
+            &arena,
+        );
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let kinds: Vec<_> = parsed
+            .stmts
+            .iter()
+            .map(|stmt| match &stmt.kind {
+                StmtKind::Let { value, .. } => ("let", core::mem::discriminant(&value.kind)),
+                StmtKind::Expr(expr) => ("expr", core::mem::discriminant(&expr.kind)),
+                StmtKind::Item(_) => panic!(),
+            })
+            .collect();
+        let if_ = core::mem::discriminant(&parse_ok("if a {}").kind);
+        let block = core::mem::discriminant(&parse_ok("{}").kind);
+        assert_eq!(kinds[1..], [("expr", if_), ("let", if_), ("expr", if_), ("let", block)]);
+        let StmtKind::Let { value, .. } = &parsed.stmts[4].kind else { panic!() };
+        assert!(matches!(&value.kind, ExprKind::Block(block) if block.kind == BlockKind::Compiletime));
+        assert_eq!(value.span, Span::new(159, 176));
+    }
+
+    #[test]
+    fn missing_braces_are_recovered() {
+        let arena = AstArena::new();
+        let parsed = parse("if cond\n    mynum = 1;\n} else {\n    mynum = 2;\n};\nlet after = 1;", &arena);
+        let kind = ParseErrorKind::Unexpected {
+            expected: Expected::Token(Token::IndentLBrace),
+            found: Some(Token::LitIdentifier),
+        };
+        assert_eq!(parsed.errors, [ParseError::new(kind, Span::new(12, 17))]);
+        assert_eq!(parsed.stmts.len(), 2);
+
+        let arena = AstArena::new();
+        let parsed = parse("if c { a = 1;", &arena);
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].kind.to_string(), "expected `}`, found end of input");
+    }
+
+    #[test]
+    fn loops_and_jumps() {
+        let arena = AstArena::new();
+        let parsed = parse(
+            "@outer while i < 10 {\n    loop { break @outer; }\n    i += 1;\n    continue;\n}\n\
+             let v = loop { break 4 * 2; };\nreturn;\nreturn a + 1;",
+            &arena,
+        );
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        assert_eq!(parsed.stmts.len(), 4);
+
+        let StmtKind::Expr(expr) = &parsed.stmts[0].kind else { panic!() };
+        let ExprKind::While { label: Some(label), cond, body } = &expr.kind else { panic!("{expr:?}") };
+        assert_eq!((label.name, label.span), ("outer", Span::new(0, 6)));
+        assert_eq!(sexpr(cond), "(< i 10)");
+        assert_eq!(body.stmts.len(), 3);
+        let StmtKind::Expr(inner) = &body.stmts[0].kind else { panic!() };
+        let ExprKind::Loop { label: None, body } = &inner.kind else { panic!("{inner:?}") };
+        let StmtKind::Expr(brk) = &body.stmts[0].kind else { panic!() };
+        assert!(matches!(&brk.kind, ExprKind::Break { label: Some(l), value: None } if l.name == "outer"));
+        assert_eq!(expr.span, Span::new(0, 76));
+
+        let StmtKind::Let { value, .. } = &parsed.stmts[1].kind else { panic!() };
+        let ExprKind::Loop { body, .. } = &value.kind else { panic!() };
+        let StmtKind::Expr(brk) = &body.stmts[0].kind else { panic!() };
+        let ExprKind::Break { value: Some(value), .. } = &brk.kind else { panic!() };
+        assert_eq!(sexpr(value), "(* 4 2)");
+
+        assert!(matches!(&parsed.stmts[2].kind, StmtKind::Expr(e) if matches!(e.kind, ExprKind::Return(None))));
+        assert!(matches!(&parsed.stmts[3].kind, StmtKind::Expr(e) if matches!(e.kind, ExprKind::Return(Some(_)))));
+    }
+
+    #[test]
+    fn labels_need_a_loop() {
+        let arena = AstArena::new();
+        let parsed = parse("@l if a {};", &arena);
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].kind.to_string(), "expected `while` or `loop`, found `if`");
+        assert_eq!(parsed.errors[0].span, Span::new(3, 5));
+    }
+}
@@ -0,0 +1,356 @@
+use alloc::vec::Vec;
+
+use crate::ast::{
+    AdtDecl, AttrArg, Attribute, BlockKind, EnumDecl, Field, FnDecl, Ident, ImportDecl, Item, ItemKind, ModDecl, Param,
+    Variant,
+};
+use crate::literal_value::Literal;
+use crate::parser::{Expected, ParseError, ParseErrorKind, Parser, identifier_str};
+use crate::token_stream::TokenSource;
+use crate::types::{Span, Spanned, Token};
+
+impl<'source, S: TokenSource<'source>> Parser<'source, S> {
+    /// parses a declaration if the next token starts one, `Ok(None)` means it doesn't.
+    /// attributes have to be followed by a declaration.
+    pub fn parse_item(&mut self) -> Result<Option<Item<'source>>, ParseError> {
+        let start = self.tokens.peek_span();
+        let mut attrs = Vec::new();
+        while self.tokens.at(Token::PuncHash) {
+            attrs.push(self.parse_attribute()?);
+        }
+        let kind = match self.tokens.peek_token(0) {
+            Some(Token::KwFn | Token::KwExtern) => ItemKind::Fn(self.parse_fn()?),
+            Some(Token::KwAdtStruct) => ItemKind::Struct(self.parse_adt(Token::KwAdtStruct)?),
+            Some(Token::KwAdtUnion) => ItemKind::Union(self.parse_adt(Token::KwAdtUnion)?),
+            Some(Token::KwAdtEnum) => ItemKind::Enum(self.parse_enum()?),
+            Some(Token::KwMod) => ItemKind::Mod(self.parse_mod()?),
+            Some(Token::KwImport) => ItemKind::Import(self.parse_import()?),
+            _ if !attrs.is_empty() => return Err(self.unexpected(Expected::Item)),
+            _ => return Ok(None),
+        };
+        Ok(Some(Item::new(attrs, kind, Span::new(start.start, self.tokens.last_end()))))
+    }
+
+    /// `#[name]` or `#[name(arg, ...)]`, the arguments are literals and names.
+    fn parse_attribute(&mut self) -> Result<Attribute<'source>, ParseError> {
+        let start = self.expect(Token::PuncHash)?.span;
+        self.expect(Token::IndentLBracket)?;
+        let name = match self.tokens.peek(0).copied() {
+            Some(token) if token.token.is_keyword() => {
+                self.next();
+                Ident::new(token.token.source_repr(), token.span)
+            }
+            _ => self.parse_ident()?,
+        };
+        let args = match self.eat(Token::IndentLParen) {
+            Some(_) => self.parse_comma_separated(Token::IndentRParen, Self::parse_attr_arg)?,
+            None => Vec::new(),
+        };
+        self.expect(Token::IndentRBracket)?;
+        Ok(Attribute {
+            name,
+            args,
+            span: Span::new(start.start, self.tokens.last_end()),
+        })
+    }
+
+    fn parse_attr_arg(&mut self) -> Result<AttrArg<'source>, ParseError> {
+        let Some(next) = self.tokens.peek(0).copied() else {
+            return Err(self.unexpected(Expected::Expression));
+        };
+        let arg = match next.token {
+            Token::LitIdentifier => AttrArg::Ident(Ident::new(identifier_str(&next), next.span)),
+            Token::LitInteger | Token::LitFloat | Token::LitStr | Token::LitChar | Token::LitBool => {
+                let literal = Literal::new(next.token, next.literal.unwrap_or_default());
+                AttrArg::Literal(Spanned::new(literal, next.span))
+            }
+            _ => return Err(self.unexpected(Expected::Expression)),
+        };
+        self.next();
+        Ok(arg)
+    }
+
+    /// `extern fn name(let a: ty, ...) -> ty { ... }`. without a body the declaration ends in
+    /// front of the `;`, which is left to the statement.
+    fn parse_fn(&mut self) -> Result<FnDecl<'source>, ParseError> {
+        let start = self.tokens.peek_span();
+        let is_extern = self.eat(Token::KwExtern).is_some();
+        self.expect(Token::KwFn)?;
+        let name = self.parse_ident()?;
+        self.expect(Token::IndentLParen)?;
+        let params = self.parse_comma_separated(Token::IndentRParen, |parser| {
+            let start = parser.expect(Token::KwLet)?.span;
+            let name = parser.parse_ident()?;
+            parser.expect(Token::PuncColon)?;
+            let ty = parser.parse_type()?;
+            let span = start.merge(ty.span);
+            Ok(Param { name, ty, span })
+        })?;
+        let ret = match self.eat(Token::PuncArrowRight) {
+            Some(_) => Some(self.parse_type()?),
+            None => None,
+        };
+        let body = if self.tokens.at(Token::PuncSemi) { None } else { Some(self.parse_block(BlockKind::Plain)?) };
+        Ok(FnDecl {
+            is_extern,
+            name,
+            params,
+            ret,
+            body,
+            span: Span::new(start.start, self.tokens.last_end()),
+        })
+    }
+
+    /// `keyword name { field: ty, ... }`, duplicate field names are reported without failing.
+    fn parse_adt(&mut self, keyword: Token) -> Result<AdtDecl<'source>, ParseError> {
+        let start = self.expect(keyword)?.span;
+        let name = self.parse_ident()?;
+        self.expect(Token::IndentLBrace)?;
+        let fields = self.parse_comma_separated(Token::IndentRBrace, |parser| {
+            let name = parser.parse_ident()?;
+            parser.expect(Token::PuncColon)?;
+            let ty = parser.parse_type()?;
+            let span = name.span.merge(ty.span);
+            Ok(Field { name, ty, span })
+        })?;
+        self.check_duplicates(fields.iter().map(|f| f.name), |first| ParseErrorKind::DuplicateField { first });
+        Ok(AdtDecl {
+            name,
+            fields,
+            span: Span::new(start.start, self.tokens.last_end()),
+        })
+    }
+
+    /// `enum name { variant, ... }`, duplicate variant names are reported without failing.
+    fn parse_enum(&mut self) -> Result<EnumDecl<'source>, ParseError> {
+        let start = self.expect(Token::KwAdtEnum)?.span;
+        let name = self.parse_ident()?;
+        self.expect(Token::IndentLBrace)?;
+        let variants = self.parse_comma_separated(Token::IndentRBrace, |parser| {
+            let name = parser.parse_ident()?;
+            Ok(Variant { name, span: name.span })
+        })?;
+        self.check_duplicates(variants.iter().map(|v| v.name), |first| ParseErrorKind::DuplicateVariant { first });
+        Ok(EnumDecl {
+            name,
+            variants,
+            span: Span::new(start.start, self.tokens.last_end()),
+        })
+    }
+
+    /// `mod name`, the `;` is left to the statement.
+    fn parse_mod(&mut self) -> Result<ModDecl<'source>, ParseError> {
+        let start = self.expect(Token::KwMod)?.span;
+        let name = self.parse_ident()?;
+        Ok(ModDecl {
+            name,
+            span: start.merge(name.span),
+        })
+    }
+
+    /// `import a.b.name`, at least one module has to lead to the name.
+    fn parse_import(&mut self) -> Result<ImportDecl<'source>, ParseError> {
+        let start = self.expect(Token::KwImport)?.span;
+        let mut path = Vec::new();
+        let mut name = self.parse_ident()?;
+        self.expect(Token::PuncDot)?;
+        loop {
+            path.push(name);
+            name = self.parse_ident()?;
+            if self.eat(Token::PuncDot).is_none() {
+                break;
+            }
+        }
+        Ok(ImportDecl {
+            path,
+            name,
+            span: start.merge(name.span),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use crate::ast::arena::AstArena;
+    use crate::ast::{AttrArg, ItemKind, StmtKind, TypeKind};
+    use crate::parser::{Expected, ParseError, ParseErrorKind, parse};
+    use crate::types::{Span, Token};
+
+    #[test]
+    fn struct_declarations() {
+        let arena = AstArena::new();
+        let parsed = parse(
+            "struct Lol {\n    ty: type,\n    un: myunion,\n};\nstruct Empty {}\nstruct P { x: u8 }",
+            &arena,
+        );
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        assert_eq!(parsed.stmts.len(), 3);
+
+        let StmtKind::Item(item) = &parsed.stmts[0].kind else { panic!() };
+        assert_eq!((item.name().name, item.span), ("Lol", Span::new(0, 45)));
+        assert_eq!(parsed.stmts[0].span, Span::new(0, 46));
+        let ItemKind::Struct(adt) = &item.kind else { panic!() };
+        let fields: Vec<_> = adt.fields.iter().map(|f| (f.name.name, &f.ty.kind, f.span)).collect();
+        assert_eq!(
+            fields,
+            [
+                ("ty", &TypeKind::Type, Span::new(17, 25)),
+                ("un", &TypeKind::Named("myunion"), Span::new(31, 42)),
+            ]
+        );
+
+        let StmtKind::Item(item) = &parsed.stmts[1].kind else { panic!() };
+        assert!(matches!(&item.kind, ItemKind::Struct(adt) if adt.fields.is_empty()));
+        let StmtKind::Item(item) = &parsed.stmts[2].kind else { panic!() };
+        assert!(matches!(&item.kind, ItemKind::Struct(adt) if adt.fields.len() == 1));
+    }
+
+    #[test]
+    fn duplicate_fields_are_reported() {
+        let arena = AstArena::new();
+        let parsed = parse("struct S { a: u8, b: u8, a: u16, a: u32 };", &arena);
+        let duplicate = |span| ParseError::new(ParseErrorKind::DuplicateField { first: Span::new(11, 12) }, span);
+        assert_eq!(parsed.errors, [duplicate(Span::new(25, 26)), duplicate(Span::new(33, 34))]);
+        assert_eq!(parsed.stmts.len(), 1);
+    }
+
+    #[test]
+    fn unions_are_not_structs() {
+        let arena = AstArena::new();
+        let parsed = parse("union myunion {\n    num64: u64,\n    num32: u32,\n};\nunion U { a: u8, a: u8 }", &arena);
+        assert_eq!(parsed.errors.len(), 1);
+        assert!(matches!(parsed.errors[0].kind, ParseErrorKind::DuplicateField { .. }));
+        let StmtKind::Item(item) = &parsed.stmts[0].kind else { panic!() };
+        let ItemKind::Union(adt) = &item.kind else { panic!("{:?}", item.kind) };
+        let fields: Vec<_> = adt.fields.iter().map(|f| (f.name.name, &f.ty.kind)).collect();
+        assert_eq!(fields, [("num64", &TypeKind::Named("u64")), ("num32", &TypeKind::Named("u32"))]);
+        assert_eq!((item.name().name, item.span), ("myunion", Span::new(0, 49)));
+    }
+
+    #[test]
+    fn enum_declarations() {
+        let arena = AstArena::new();
+        let parsed = parse("enum Thingamabob {\n    __variant1,\n    __variant2,\n};\nenum E { a, b, a }", &arena);
+        assert_eq!(
+            parsed.errors,
+            [ParseError::new(ParseErrorKind::DuplicateVariant { first: Span::new(63, 64) }, Span::new(69, 70))]
+        );
+        let StmtKind::Item(item) = &parsed.stmts[0].kind else { panic!() };
+        let ItemKind::Enum(decl) = &item.kind else { panic!() };
+        assert_eq!(decl.name.name, "Thingamabob");
+        let variants: Vec<_> = decl.variants.iter().map(|v| (v.name.name, v.span)).collect();
+        assert_eq!(variants, [("__variant1", Span::new(23, 33)), ("__variant2", Span::new(39, 49))]);
+        assert_eq!(decl.span, Span::new(0, 52));
+        assert_eq!(parsed.stmts.len(), 2);
+    }
+
+    #[test]
+    fn errors_inside_the_body_skip_the_whole_declaration() {
+        let arena = AstArena::new();
+        let parsed = parse("struct S { a: , b: u8 };\nlet x = 1;\n} let y = 2;", &arena);
+        assert_eq!(parsed.errors.len(), 2, "{:?}", parsed.errors);
+        assert_eq!(parsed.errors[0].span, Span::new(14, 15));
+        assert_eq!(parsed.errors[1].span, Span::new(36, 37));
+        assert_eq!(parsed.stmts.len(), 2);
+    }
+
+    #[test]
+    fn fn_declarations() {
+        let arena = AstArena::new();
+        let source = "extern fn printf(let format: static const *const u8);\n\
+                      fn zero() -> const u32 { 0 }\nfn f(let a: u8, let b: u8,) {}";
+        let parsed = parse(source, &arena);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        assert_eq!(parsed.stmts.len(), 3);
+
+        let StmtKind::Item(item) = &parsed.stmts[0].kind else { panic!() };
+        let ItemKind::Fn(decl) = &item.kind else { panic!() };
+        assert!(decl.is_extern && decl.body.is_none() && decl.ret.is_none());
+        assert_eq!((decl.name.name, decl.span), ("printf", Span::new(0, 52)));
+        assert_eq!((decl.params[0].name.name, decl.params[0].span), ("format", Span::new(17, 51)));
+        assert_eq!(parsed.stmts[0].span, Span::new(0, 53));
+
+        let StmtKind::Item(item) = &parsed.stmts[1].kind else { panic!() };
+        let ItemKind::Fn(decl) = &item.kind else { panic!() };
+        assert!(!decl.is_extern && decl.params.is_empty());
+        assert_eq!(decl.ret.as_ref().unwrap().kind, TypeKind::Named("u32"));
+        assert!(decl.body.as_ref().unwrap().tail.is_some());
+
+        let StmtKind::Item(item) = &parsed.stmts[2].kind else { panic!() };
+        let ItemKind::Fn(decl) = &item.kind else { panic!() };
+        let params: Vec<_> = decl.params.iter().map(|p| p.name.name).collect();
+        assert_eq!(params, ["a", "b"]);
+    }
+
+    #[test]
+    fn attributes_attach_to_the_next_item() {
+        let arena = AstArena::new();
+        let parsed = parse("#[extern(\"C\")]\n#[inline] fn f();\n#[repr(u8, 2)] enum E { a }", &arena);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+
+        let StmtKind::Item(item) = &parsed.stmts[0].kind else { panic!() };
+        assert_eq!(item.span, Span::new(0, 31));
+        let names: Vec<_> = item.attrs.iter().map(|attr| (attr.name.name, attr.span)).collect();
+        assert_eq!(names, [("extern", Span::new(0, 14)), ("inline", Span::new(15, 24))]);
+        let AttrArg::Literal(abi) = item.attr("extern").unwrap().args[0] else { panic!() };
+        assert_eq!((abi.value.token(), abi.value.raw(), abi.span), (Token::LitStr, &b"C"[..], Span::new(9, 12)));
+        assert!(item.attr("inline").unwrap().args.is_empty());
+        assert!(item.attr("cold").is_none());
+
+        let StmtKind::Item(item) = &parsed.stmts[1].kind else { panic!() };
+        let args = &item.attr("repr").unwrap().args;
+        assert!(matches!(args[0], AttrArg::Ident(ident) if ident.name == "u8"));
+        assert!(matches!(args[1], AttrArg::Literal(literal) if literal.value.token() == Token::LitInteger));
+    }
+
+    #[test]
+    fn attributes_need_an_item() {
+        let arena = AstArena::new();
+        let parsed = parse("#[inline] let x = 1;\n#[a(+)] fn f();\nlet y = 2;", &arena);
+        let expected = |expected, found, span| {
+            let found = Some(found);
+            ParseError::new(ParseErrorKind::Unexpected { expected, found }, span)
+        };
+        assert_eq!(
+            parsed.errors,
+            [
+                expected(Expected::Item, Token::KwLet, Span::new(10, 13)),
+                expected(Expected::Expression, Token::PuncPlus, Span::new(25, 26)),
+            ]
+        );
+        assert_eq!(parsed.stmts.len(), 1);
+    }
+
+    #[test]
+    fn mod_and_import_declarations() {
+        let arena = AstArena::new();
+        let parsed = parse(include_str!("../../progs/modules.mumbo"), &arena);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+
+        let StmtKind::Item(item) = &parsed.stmts[0].kind else { panic!() };
+        assert!(matches!(item.kind, ItemKind::Mod(_)));
+        assert_eq!((item.name().name, item.span), ("adt", Span::new(52, 59)));
+        let StmtKind::Item(item) = &parsed.stmts[1].kind else { panic!() };
+        let ItemKind::Import(decl) = &item.kind else { panic!() };
+        assert_eq!((decl.path[0].name, decl.path.len()), ("adt", 1));
+        assert_eq!((item.name().name, item.span), ("numtuple", Span::new(61, 80)));
+
+        let parsed = parse("import a.b.c;\nimport single;\nmod;", &arena);
+        let StmtKind::Item(item) = &parsed.stmts[0].kind else { panic!() };
+        let ItemKind::Import(decl) = &item.kind else { panic!() };
+        let path: Vec<_> = decl.path.iter().map(|module| module.name).collect();
+        assert_eq!((path, decl.name.name), (vec!["a", "b"], "c"));
+        let found = |expected, found| ParseErrorKind::Unexpected { expected, found: Some(found) };
+        let errors: Vec<_> = parsed.errors.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            errors,
+            [
+                found(Expected::Token(Token::PuncDot), Token::PuncSemi),
+                found(Expected::Token(Token::LitIdentifier), Token::PuncSemi),
+            ]
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use crate::ast::{Pattern, PatternKind};
+use crate::literal_value::Literal;
+use crate::parser::{Expected, ParseError, Parser, identifier_str};
+use crate::token_stream::TokenSource;
+use crate::types::Token;
+
+impl<'source, S: TokenSource<'source>> Parser<'source, S> {
+    /// parses a `match` pattern: `_`, a literal, an identifier binding the value or naming
+    /// an enum variant, or a variant named through its enum, `Enum::Variant`.
+    pub fn parse_pattern(&mut self) -> Result<Pattern<'source>, ParseError> {
+        let Some(next) = self.tokens.peek(0).copied() else {
+            return Err(self.unexpected(Expected::Pattern));
+        };
+        let kind = match next.token {
+            Token::LitIdentifier if self.tokens.peek_token(1) == Some(Token::PuncColonColon) => {
+                let (enum_name, variant) = self.parse_path()?;
+                let span = enum_name.span.merge(variant.span);
+                return Ok(Pattern {
+                    kind: PatternKind::Path { enum_name, variant },
+                    span,
+                });
+            }
+            Token::LitIdentifier => match identifier_str(&next) {
+                "_" => PatternKind::Wildcard,
+                name => PatternKind::Identifier(name),
+            },
+            Token::LitInteger | Token::LitFloat | Token::LitStr | Token::LitChar | Token::LitBool => {
+                PatternKind::Literal(Literal::new(next.token, next.literal.unwrap_or_default()))
+            }
+            _ => return Err(self.unexpected(Expected::Pattern)),
+        };
+        self.next();
+        Ok(Pattern { kind, span: next.span })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::ast::arena::AstArena;
+    use crate::ast::{ExprKind, PatternKind, StmtKind};
+    use crate::parser::{Expected, ParseError, ParseErrorKind, parse};
+    use crate::types::{Span, Token};
+
+    #[test]
+    fn match_arms_and_patterns() {
+        let arena = AstArena::new();
+        let parsed = parse(
+            "let byte = match variant {\n    __variant1 => 1,\n    'c' => { 2 }\n    \
+             other => other * 2,\n    Tag::some => 3,\n    _ => 0\n};",
+            &arena,
+        );
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let StmtKind::Let { value, .. } = &parsed.stmts[0].kind else { panic!() };
+        let ExprKind::Match { scrutinee, arms } = &value.kind else { panic!("{value:?}") };
+        assert_eq!(scrutinee.kind, ExprKind::Identifier("variant"));
+        let patterns: Vec<_> = arms.iter().map(|arm| &arm.pattern.kind).collect();
+        assert!(matches!(
+            patterns[..],
+            [
+                PatternKind::Identifier("__variant1"),
+                PatternKind::Literal(c),
+                PatternKind::Identifier("other"),
+                PatternKind::Path { enum_name, variant },
+                PatternKind::Wildcard,
+            ] if c.token() == Token::LitChar && enum_name.name == "Tag" && variant.name == "some"
+        ));
+        assert_eq!(arms[0].span, Span::new(31, 46));
+        assert!(matches!(arms[1].body.kind, ExprKind::Block(_)));
+        assert_eq!(value.span, Span::new(11, 121));
+    }
+
+    #[test]
+    fn arms_need_commas_and_patterns() {
+        let arena = AstArena::new();
+        let parsed = parse("match a { 1 => x 2 => y };\nmatch a { + => x };", &arena);
+        let unexpected = |expected, found, span| ParseError::new(ParseErrorKind::Unexpected { expected, found }, span);
+        assert_eq!(
+            parsed.errors,
+            [
+                unexpected(Expected::Token(Token::IndentRBrace), Some(Token::LitInteger), Span::new(17, 18)),
+                unexpected(Expected::Pattern, Some(Token::PuncPlus), Span::new(37, 38)),
+            ]
+        );
+    }
+}
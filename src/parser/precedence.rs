@@ -0,0 +1,172 @@
+//! operator precedence and associativity, kept as data so that a new operator only needs a
+//! table entry and the `BinaryOp` it produces.
+
+use crate::ast::BinaryOp;
+use crate::types::Token;
+
+/// how tightly a binary operator binds, weakest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Precedence {
+    Assignment,
+    Equality,
+    Comparison,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
+    Term,
+    Factor,
+}
+
+impl Precedence {
+    const ALL: [Precedence; 9] = [
+        Precedence::Assignment,
+        Precedence::Equality,
+        Precedence::Comparison,
+        Precedence::BitOr,
+        Precedence::BitXor,
+        Precedence::BitAnd,
+        Precedence::Shift,
+        Precedence::Term,
+        Precedence::Factor,
+    ];
+
+    /// the next tighter level, `None` for the tightest.
+    #[inline]
+    pub(crate) const fn tighter(self) -> Option<Precedence> {
+        let index = self as usize + 1;
+        if index < Self::ALL.len() { Some(Self::ALL[index]) } else { None }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Associativity {
+    Left,
+    Right,
+    /// `a < b < c` is an error, see `ParseErrorKind::ChainedOperator`.
+    None,
+}
+
+/// what an infix operator builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Infix {
+    Binary(BinaryOp),
+    /// `=` for `None`, `op=` otherwise.
+    Assign(Option<BinaryOp>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct InfixOperator {
+    pub infix: Infix,
+    pub precedence: Precedence,
+    pub associativity: Associativity,
+}
+
+const fn entry(
+    token: Token,
+    infix: Infix,
+    precedence: Precedence,
+    associativity: Associativity,
+) -> (Token, InfixOperator) {
+    let operator = InfixOperator {
+        infix,
+        precedence,
+        associativity,
+    };
+    (token, operator)
+}
+
+const fn binary(op: BinaryOp, precedence: Precedence, associativity: Associativity) -> (Token, InfixOperator) {
+    entry(op.token(), Infix::Binary(op), precedence, associativity)
+}
+
+const fn compound_assign(op: BinaryOp) -> (Token, InfixOperator) {
+    let Some(token) = op.assign_token() else {
+        panic!("operator has no compound assignment");
+    };
+    entry(token, Infix::Assign(Some(op)), Precedence::Assignment, Associativity::Right)
+}
+
+/// every infix operator. `INFIX_TABLE` is built from this at compile time.
+#[rustfmt::skip]
+const INFIX_OPERATORS: &[(Token, InfixOperator)] = &[
+    entry(Token::PuncEq, Infix::Assign(None), Precedence::Assignment, Associativity::Right),
+    compound_assign(BinaryOp::Add),
+    compound_assign(BinaryOp::Sub),
+    compound_assign(BinaryOp::Mul),
+    compound_assign(BinaryOp::Div),
+    compound_assign(BinaryOp::Rem),
+    compound_assign(BinaryOp::BitAnd),
+    compound_assign(BinaryOp::BitOr),
+    compound_assign(BinaryOp::BitXor),
+    compound_assign(BinaryOp::Shl),
+    compound_assign(BinaryOp::Shr),
+
+    binary(BinaryOp::Eq, Precedence::Equality, Associativity::None),
+    binary(BinaryOp::Ne, Precedence::Equality, Associativity::None),
+    binary(BinaryOp::Lt, Precedence::Comparison, Associativity::None),
+    binary(BinaryOp::Le, Precedence::Comparison, Associativity::None),
+    binary(BinaryOp::Gt, Precedence::Comparison, Associativity::None),
+    binary(BinaryOp::Ge, Precedence::Comparison, Associativity::None),
+    binary(BinaryOp::BitOr, Precedence::BitOr, Associativity::Left),
+    binary(BinaryOp::BitXor, Precedence::BitXor, Associativity::Left),
+    binary(BinaryOp::BitAnd, Precedence::BitAnd, Associativity::Left),
+    binary(BinaryOp::Shl, Precedence::Shift, Associativity::Left),
+    binary(BinaryOp::Shr, Precedence::Shift, Associativity::Left),
+    binary(BinaryOp::Add, Precedence::Term, Associativity::Left),
+    binary(BinaryOp::Sub, Precedence::Term, Associativity::Left),
+    binary(BinaryOp::Mul, Precedence::Factor, Associativity::Left),
+    binary(BinaryOp::Div, Precedence::Factor, Associativity::Left),
+    binary(BinaryOp::Rem, Precedence::Factor, Associativity::Left),
+];
+
+/// `INFIX_OPERATORS` indexed by `Token as usize`.
+const INFIX_TABLE: [Option<InfixOperator>; Token::ALL.len()] = {
+    let mut table = [None; Token::ALL.len()];
+    let mut index = 0;
+    while index < INFIX_OPERATORS.len() {
+        let (token, operator) = INFIX_OPERATORS[index];
+        assert!(table[token as usize].is_none(), "token listed twice in INFIX_OPERATORS");
+        table[token as usize] = Some(operator);
+        index += 1;
+    }
+    table
+};
+
+/// the infix operator `token` stands for, if any.
+#[inline]
+pub(crate) const fn infix_operator(token: Token) -> Option<InfixOperator> {
+    INFIX_TABLE[token as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Infix, Precedence, infix_operator};
+    use crate::ast::BinaryOp;
+    use crate::types::Token;
+
+    #[test]
+    fn every_operator_token_has_an_entry() {
+        for &token in Token::ALL {
+            let expected = match (BinaryOp::from_token(token), BinaryOp::from_assign_token(token)) {
+                (Some(op), _) => Some(Infix::Binary(op)),
+                (_, Some(op)) => Some(Infix::Assign(Some(op))),
+                _ if token == Token::PuncEq => Some(Infix::Assign(None)),
+                _ => None,
+            };
+            assert_eq!(infix_operator(token).map(|o| o.infix), expected, "{token:?}");
+        }
+    }
+
+    #[test]
+    fn levels_go_from_weakest_to_tightest() {
+        let mut level = Some(Precedence::Assignment);
+        let mut count = 0;
+        while let Some(current) = level {
+            level = current.tighter();
+            assert!(level.is_none_or(|next| next > current));
+            count += 1;
+        }
+        assert_eq!(count, Precedence::ALL.len());
+    }
+}
@@ -0,0 +1,229 @@
+use alloc::vec::Vec;
+
+use crate::ast::{Block, BlockKind, Expr, ExprKind, Stmt, StmtKind};
+use crate::parser::{Expected, ParseError, ParseErrorKind, Parser};
+use crate::token_stream::TokenSource;
+use crate::types::{Span, Token};
+
+/// a statement, or the expression ending a block without a `;`.
+enum BlockEntry<'source, S = Stmt<'source>> {
+    Stmt(S),
+    Tail(Expr<'source>),
+}
+
+/// expressions ending in a block don't need a `;` to be a statement, `if c { a } else { b }`.
+#[inline]
+pub(super) const fn is_block_like(expr: &Expr<'_>) -> bool {
+    matches!(
+        expr.kind,
+        ExprKind::Block(_)
+            | ExprKind::If { .. }
+            | ExprKind::While { .. }
+            | ExprKind::Loop { .. }
+            | ExprKind::Match { .. }
+    )
+}
+
+impl<'source, S: TokenSource<'source>> Parser<'source, S> {
+    /// parses `let name: ty = value;` or an expression followed by `;`, assignments
+    /// like `v2 = 1;` included. items and expressions ending in a block, like `if`, don't
+    /// need the `;`.
+    ///
+    /// on error everything up to and including the next `;` is skipped, so that parsing can
+    /// continue with the following statement.
+    pub fn parse_stmt(&mut self) -> Result<Stmt<'source>, ParseError> {
+        match self.parse_block_entry(false)? {
+            BlockEntry::Stmt(stmt) => Ok(stmt),
+            BlockEntry::Tail(_) => unreachable!("tails are only parsed inside blocks"),
+        }
+    }
+
+    /// parses `{ stmt* tail? }`. a missing `{` is reported and the block parsed as if it
+    /// was there, statements that fail to parse are reported and skipped.
+    pub fn parse_block(&mut self, kind: BlockKind) -> Result<Block<'source>, ParseError> {
+        let start = self.tokens.peek_span();
+        if self.eat(Token::IndentLBrace).is_none() {
+            let error = self.unexpected(Expected::Token(Token::IndentLBrace));
+            self.errors.push(error);
+            // as if it had been consumed, so that the `}` closes this block during recovery
+            self.depth += 1;
+        }
+
+        let mut stmts = Vec::new();
+        let mut tail = None;
+        loop {
+            match self.tokens.peek_token(0) {
+                Some(Token::IndentRBrace) => {
+                    self.next();
+                    break;
+                }
+                None => return Err(self.unexpected(Expected::Token(Token::IndentRBrace))),
+                Some(_) => {}
+            }
+            match self.with_struct_literals(true, |p| p.parse_block_entry(true)) {
+                Ok(BlockEntry::Stmt(stmt)) => stmts.push(stmt),
+                Ok(BlockEntry::Tail(expr)) => tail = Some(self.arena.alloc_expr(expr)),
+                Err(e) => self.errors.push(e),
+            }
+        }
+        Ok(Block {
+            kind,
+            stmts,
+            tail,
+            span: Span::new(start.start, self.tokens.last_end()),
+        })
+    }
+
+    fn parse_block_entry(&mut self, in_block: bool) -> Result<BlockEntry<'source>, ParseError> {
+        let start = self.tokens.peek_span().start;
+        let depth = self.depth;
+        let result = self.parse_stmt_kind(in_block).map(|entry| match entry {
+            BlockEntry::Stmt(kind) => BlockEntry::Stmt(Stmt::new(kind, Span::new(start, self.tokens.last_end()))),
+            BlockEntry::Tail(expr) => BlockEntry::Tail(expr),
+        });
+        if result.is_err() {
+            self.recover_to_semi(depth);
+        }
+        result
+    }
+
+    fn parse_stmt_kind(&mut self, in_block: bool) -> Result<BlockEntry<'source, StmtKind<'source>>, ParseError> {
+        if let Some(item) = self.parse_item()? {
+            // the `;` after an item is optional
+            self.eat(Token::PuncSemi);
+            return Ok(BlockEntry::Stmt(StmtKind::Item(item)));
+        }
+        let kind = match self.tokens.peek_token(0) {
+            Some(Token::KwLet) => {
+                self.next();
+                let name = self.parse_ident()?;
+                let ty = match self.eat(Token::PuncColon) {
+                    Some(_) => Some(self.parse_type()?),
+                    None => None,
+                };
+                self.expect(Token::PuncEq)?;
+                let value = self.parse_expr()?;
+                StmtKind::Let { name, ty, value }
+            }
+            _ => {
+                let expr = self.parse_expr()?;
+                if in_block && self.tokens.at(Token::IndentRBrace) {
+                    return Ok(BlockEntry::Tail(expr));
+                }
+                if is_block_like(&expr) {
+                    self.eat(Token::PuncSemi);
+                    return Ok(BlockEntry::Stmt(StmtKind::Expr(expr)));
+                }
+                StmtKind::Expr(expr)
+            }
+        };
+        self.expect_semi()?;
+        Ok(BlockEntry::Stmt(kind))
+    }
+
+    /// the `;` ending a statement. when the statement ends a line and the next one starts with
+    /// something that can't continue it, the `;` was most likely forgotten: the error goes
+    /// right after the statement and parsing goes on as if the `;` was there.
+    fn expect_semi(&mut self) -> Result<(), ParseError> {
+        if self.eat(Token::PuncSemi).is_some() {
+            return Ok(());
+        }
+        if !self.tokens.at_line_start() {
+            return Err(self.unexpected(Expected::Token(Token::PuncSemi)));
+        }
+        let end = self.tokens.last_end();
+        self.errors.push(ParseError::new(ParseErrorKind::MissingSemicolon, Span::new(end, end)));
+        Ok(())
+    }
+
+    /// skips past the next `;` of the statement that started at `depth`, closing the braces
+    /// it opened. stops in front of a `}` closing the block the statement is in.
+    fn recover_to_semi(&mut self, depth: usize) {
+        while let Some(token) = self.tokens.peek_token(0) {
+            match token {
+                Token::PuncSemi if self.depth <= depth => {
+                    self.next();
+                    return;
+                }
+                Token::IndentRBrace if self.depth <= depth => return,
+                _ => {}
+            }
+            self.next();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::arena::AstArena;
+    use crate::ast::{ExprKind, StmtKind, TypeKind};
+    use crate::parser::{Expected, ParseError, ParseErrorKind, parse};
+    use crate::types::{Span, Token};
+
+    #[test]
+    fn let_assignment_and_expression_statements() {
+        let arena = AstArena::new();
+        let parsed = parse("let v2: mut u8 = 0;\nv2 = 1;\nv2 + 1;\nlet floatlt = 3.14159;", &arena);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        assert_eq!(parsed.stmts.len(), 4);
+
+        let StmtKind::Let { name, ty, value } = &parsed.stmts[0].kind else { panic!() };
+        assert_eq!((name.name, name.span), ("v2", Span::new(4, 6)));
+        assert_eq!(ty.as_ref().unwrap().kind, TypeKind::Named("u8"));
+        assert!(matches!(value.kind, ExprKind::Literal(_)));
+        assert_eq!(parsed.stmts[0].span, Span::new(0, 19));
+
+        let StmtKind::Expr(assign) = &parsed.stmts[1].kind else { panic!() };
+        assert!(matches!(assign.kind, ExprKind::Assign { op: None, .. }));
+        assert_eq!(parsed.stmts[1].span, Span::new(20, 27));
+        assert!(matches!(&parsed.stmts[2].kind, StmtKind::Expr(e) if matches!(e.kind, ExprKind::Binary { .. })));
+        assert!(matches!(&parsed.stmts[3].kind, StmtKind::Let { ty: None, .. }));
+    }
+
+    #[test]
+    fn semicolons_missing_at_the_end_of_a_line() {
+        let arena = AstArena::new();
+        let parsed = parse("let y = 3\nlet z = 4;\nf(y)\n  + z;\ng(z)\n", &arena);
+        // `+ z` continues the call on the next line, the end of input isn't a new line
+        assert_eq!(
+            parsed.errors,
+            [
+                ParseError::new(ParseErrorKind::MissingSemicolon, Span::new(9, 9)),
+                ParseError::new(
+                    ParseErrorKind::Unexpected {
+                        expected: Expected::Token(Token::PuncSemi),
+                        found: None
+                    },
+                    Span::new(37, 37)
+                ),
+            ]
+        );
+        assert_eq!(parsed.stmts.len(), 3);
+        assert!(matches!(&parsed.stmts[1].kind, StmtKind::Let { name, .. } if name.name == "z"));
+
+        // on the same line it is still whatever comes next that is unexpected
+        let parsed = parse("let y = 3 let z = 4;", &arena);
+        assert!(matches!(parsed.errors[0].kind, ParseErrorKind::Unexpected { .. }));
+    }
+
+    #[test]
+    fn errors_skip_to_the_next_semicolon() {
+        let arena = AstArena::new();
+        let parsed = parse("let = 1; a + ; let b: = 2 { ; } c; d = 4;\nlet x = 1", &arena);
+        let unexpected = |expected, found, span| {
+            ParseError::new(ParseErrorKind::Unexpected { expected, found }, span)
+        };
+        assert_eq!(
+            parsed.errors,
+            [
+                unexpected(Expected::Token(Token::LitIdentifier), Some(Token::PuncEq), Span::new(4, 5)),
+                unexpected(Expected::Expression, Some(Token::PuncSemi), Span::new(13, 14)),
+                unexpected(Expected::Type, Some(Token::PuncEq), Span::new(22, 23)),
+                unexpected(Expected::Token(Token::PuncSemi), None, Span::new(51, 51)),
+            ]
+        );
+        assert_eq!(parsed.stmts.len(), 1);
+        assert!(matches!(&parsed.stmts[0].kind, StmtKind::Expr(e) if matches!(e.kind, ExprKind::Assign { .. })));
+        assert!(parsed.lexer_errors.is_empty());
+    }
+}
@@ -0,0 +1,177 @@
+use alloc::vec::Vec;
+
+use crate::ast::{Qualifier, TypeExpr, TypeKind};
+use crate::parser::{Expected, ParseError, ParseErrorKind, Parser, identifier_str};
+use crate::token_stream::TokenSource;
+use crate::types::{Span, Spanned, Token};
+
+impl<'source, S: TokenSource<'source>> Parser<'source, S> {
+    /// parses a type annotation: qualifiers like `anymut static` followed by a type name,
+    /// `type`, `*ty`, `[len ty]`, `(ty, ...)` or `fn(ty, ...) -> ty`.
+    ///
+    /// conflicting qualifiers are reported without failing and stay in the chain.
+    pub fn parse_type(&mut self) -> Result<TypeExpr<'source>, ParseError> {
+        let start = self.tokens.peek_span();
+        let mut qualifiers: Vec<Spanned<Qualifier>> = Vec::new();
+        while let Some(next) = self.tokens.peek(0).copied()
+            && let Some(qualifier) = Qualifier::from_token(next.token)
+        {
+            self.next();
+            if let Some(previous) = qualifiers.iter().find(|q| q.value.conflicts_with(qualifier)) {
+                let kind = ParseErrorKind::ConflictingQualifiers {
+                    qualifier,
+                    previous: *previous,
+                };
+                self.errors.push(ParseError::new(kind, next.span));
+            }
+            qualifiers.push(Spanned::new(qualifier, next.span));
+        }
+
+        let Some(next) = self.tokens.peek(0).copied() else {
+            return Err(self.unexpected(Expected::Type));
+        };
+        let kind = match next.token {
+            Token::LitIdentifier => {
+                self.next();
+                TypeKind::Named(identifier_str(&next))
+            }
+            Token::KwType => {
+                self.next();
+                TypeKind::Type
+            }
+            Token::PuncStar => {
+                self.next();
+                TypeKind::Pointer(self.arena.alloc_type(self.parse_type()?))
+            }
+            Token::IndentLBracket => {
+                self.next();
+                let len = self.arena.alloc_expr(self.parse_primary()?);
+                let elem = self.arena.alloc_type(self.parse_type()?);
+                self.expect(Token::IndentRBracket)?;
+                TypeKind::Array { len, elem }
+            }
+            Token::IndentLParen => {
+                self.next();
+                TypeKind::Tuple(self.parse_comma_separated(Token::IndentRParen, Self::parse_type)?)
+            }
+            Token::KwFn => {
+                self.next();
+                self.expect(Token::IndentLParen)?;
+                let params = self.parse_comma_separated(Token::IndentRParen, Self::parse_type)?;
+                let ret = match self.eat(Token::PuncArrowRight) {
+                    Some(_) => Some(self.arena.alloc_type(self.parse_type()?)),
+                    None => None,
+                };
+                TypeKind::Fn { params, ret }
+            }
+            _ => return Err(self.unexpected(Expected::Type)),
+        };
+        Ok(TypeExpr {
+            qualifiers,
+            kind,
+            span: Span::new(start.start, self.tokens.last_end()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use crate::ast::arena::AstArena;
+    use crate::ast::{ExprKind, Qualifier, TypeExpr, TypeKind};
+    use crate::lexer::Lexer;
+    use crate::parser::{Expected, ParseError, ParseErrorKind, Parser};
+    use crate::source_code::SourceCode;
+    use crate::types::{Span, Spanned, Token};
+
+    /// the arena is leaked so the tree can outlive the helper.
+    fn parse_type(text: &str) -> (TypeExpr<'_>, Vec<ParseError>) {
+        let mut parser = Parser::new(Lexer::new(SourceCode::new(text)), Box::leak(Box::new(AstArena::new())));
+        let ty = parser.parse_type().unwrap();
+        assert!(parser.is_at_end(), "{text:?} was not fully parsed");
+        (ty, parser.take_errors())
+    }
+
+    fn qualifiers(ty: &TypeExpr<'_>) -> Vec<Qualifier> {
+        ty.qualifiers.iter().map(|q| q.value).collect()
+    }
+
+    #[test]
+    fn qualifiers_are_kept_in_order() {
+        let arena = AstArena::new();
+        let mut parser = Parser::new(Lexer::new(SourceCode::new("anymut static u8 type")), &arena);
+        let ty = parser.parse_type().unwrap();
+        let qualifiers: Vec<Qualifier> = ty.qualifiers.iter().map(|q| q.value).collect();
+        assert_eq!(qualifiers, [Qualifier::Anymut, Qualifier::Static]);
+        assert_eq!(ty.qualifiers[1].span, Span::new(7, 13));
+        assert_eq!((ty.kind, ty.span), (TypeKind::Named("u8"), Span::new(0, 16)));
+
+        let ty = parser.parse_type().unwrap();
+        assert_eq!((ty.kind, ty.qualifiers.len(), ty.span), (TypeKind::Type, 0, Span::new(17, 21)));
+
+        let error = Parser::new(Lexer::new(SourceCode::new("mut ;")), &arena).parse_type().unwrap_err();
+        let kind = ParseErrorKind::Unexpected {
+            expected: Expected::Type,
+            found: Some(Token::PuncSemi),
+        };
+        assert_eq!((error.kind, error.span), (kind, Span::new(4, 5)));
+    }
+
+    #[test]
+    fn qualifiers_of_the_sample_programs() {
+        for (text, expected, name) in [
+            ("const u8", &[Qualifier::Const][..], "u8"),
+            ("mut literal", &[Qualifier::Mut], "literal"),
+            ("anymut static u8", &[Qualifier::Anymut, Qualifier::Static], "u8"),
+            ("runtime static const usize", &[Qualifier::Runtime, Qualifier::Static, Qualifier::Const], "usize"),
+            ("compiletime anymut u8", &[Qualifier::Compiletime, Qualifier::Anymut], "u8"),
+        ] {
+            let (ty, errors) = parse_type(text);
+            assert!(errors.is_empty(), "{text:?}: {errors:?}");
+            assert_eq!(qualifiers(&ty), expected, "{text:?}");
+            assert_eq!(ty.kind, TypeKind::Named(name));
+        }
+    }
+
+    #[test]
+    fn compound_types() {
+        let (ty, _) = parse_type("anymut *anymut u8");
+        let TypeKind::Pointer(pointee) = &ty.kind else { panic!("{ty:?}") };
+        assert_eq!((qualifiers(&ty), qualifiers(pointee)), (vec![Qualifier::Anymut], vec![Qualifier::Anymut]));
+        assert_eq!((ty.span, pointee.span), (Span::new(0, 17), Span::new(8, 17)));
+
+        let (ty, _) = parse_type("compiletime anymut [5 u8]");
+        let TypeKind::Array { len, elem } = &ty.kind else { panic!("{ty:?}") };
+        assert!(matches!(len.kind, ExprKind::Literal(l) if l.raw() == b"5"));
+        assert_eq!(elem.kind, TypeKind::Named("u8"));
+
+        let (ty, _) = parse_type("(const usize, mut u64,)");
+        assert!(matches!(&ty.kind, TypeKind::Tuple(elems) if elems.len() == 2));
+        let (ty, _) = parse_type("fn(u8, *u8) -> fn()");
+        let TypeKind::Fn { params, ret: Some(ret) } = &ty.kind else { panic!("{ty:?}") };
+        assert_eq!(params.len(), 2);
+        assert_eq!(ret.kind, TypeKind::Fn { params: vec![], ret: None });
+    }
+
+    #[test]
+    fn conflicting_qualifiers_are_reported() {
+        let conflict = |qualifier, previous, previous_span, span| {
+            let previous = Spanned::new(previous, previous_span);
+            ParseError::new(ParseErrorKind::ConflictingQualifiers { qualifier, previous }, span)
+        };
+        let (ty, errors) = parse_type("mut const u8");
+        assert_eq!(errors, [conflict(Qualifier::Const, Qualifier::Mut, Span::new(0, 3), Span::new(4, 9))]);
+        assert_eq!(errors[0].kind.to_string(), "`const` conflicts with `mut`");
+        assert_eq!(qualifiers(&ty).len(), 2);
+
+        let (_, errors) = parse_type("static static u8");
+        assert_eq!(errors[0].kind.to_string(), "`static` is given twice");
+        let (_, errors) = parse_type("compiletime static anymut runtime u8");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[1].span, Span::new(26, 33));
+    }
+}
@@ -0,0 +1,515 @@
+//! name resolution: checks that every name an expression uses is declared where it is used.
+//!
+//! items are visible in their whole block, so they can be used before they are declared.
+//! `let` bindings only from the next statement on, and function bodies only see the items
+//! around them, not the locals. a `let` may rebind a name that is already visible, which is
+//! reported according to `ShadowPolicy`.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::ast::visit::{Visitor, walk_block, walk_expr, walk_stmt, walk_stmts};
+use crate::ast::{Block, EnumIntrinsic, Expr, ExprKind, FnDecl, Ident, ItemKind, MatchArm, PatternKind, Stmt, StmtKind};
+use crate::types::Span;
+
+/// names that are always in scope.
+const BUILTINS: &[&str] = &["true", "false"];
+
+/// what to do with a `let` that hides an earlier binding of the same name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ShadowPolicy {
+    Allow,
+    /// accept the `let` and record a `ResolveWarning::Shadowed`.
+    #[default]
+    Warn,
+    /// report `ResolveErrorKind::Shadowed`.
+    Deny,
+}
+
+/// resolver configuration, see `resolve`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ResolveOptions {
+    shadowing: ShadowPolicy,
+}
+
+impl ResolveOptions {
+    #[inline]
+    pub const fn new() -> Self {
+        ResolveOptions {
+            shadowing: ShadowPolicy::Warn,
+        }
+    }
+
+    /// see `ShadowPolicy`.
+    #[inline]
+    pub const fn shadowing(mut self, policy: ShadowPolicy) -> Self {
+        self.shadowing = policy;
+        self
+    }
+
+    #[inline]
+    pub const fn get_shadowing(&self) -> ShadowPolicy {
+        self.shadowing
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ResolveErrorKind {
+    /// the name is declared by a later `let` of the block, `declared` is that declaration.
+    UsedBeforeDeclaration { declared: Span },
+    /// nothing by that name is in scope, now or later.
+    Undeclared,
+    /// a `let` hiding `previous` under `ShadowPolicy::Deny`.
+    Shadowed { previous: Span },
+    /// the `Enum` of `Enum::Variant` names something else, declared at `declared`.
+    NotAnEnum { declared: Span },
+    /// the `Variant` of `Enum::Variant` isn't one of the variants of the enum at `declared`.
+    NoVariant { declared: Span },
+}
+
+impl ResolveErrorKind {
+    /// stable identifier like `ParseErrorKind::code`, resolver codes start at `E0201`.
+    #[inline]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            ResolveErrorKind::UsedBeforeDeclaration { .. } => "E0201",
+            ResolveErrorKind::Undeclared => "E0202",
+            ResolveErrorKind::Shadowed { .. } => "E0203",
+            ResolveErrorKind::NotAnEnum { .. } => "E0204",
+            ResolveErrorKind::NoVariant { .. } => "E0205",
+        }
+    }
+}
+
+/// `span` covers the name the error is about.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResolveError<'source> {
+    pub kind: ResolveErrorKind,
+    pub name: &'source str,
+    pub span: Span,
+}
+
+impl ResolveError<'_> {
+    #[inline]
+    pub const fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+}
+
+impl fmt::Display for ResolveError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ResolveErrorKind::UsedBeforeDeclaration { .. } => {
+                write!(f, "`{}` is used before its declaration", self.name)
+            }
+            ResolveErrorKind::Undeclared => write!(f, "cannot find `{}` in this scope", self.name),
+            ResolveErrorKind::Shadowed { .. } => write!(f, "`{}` shadows an earlier declaration", self.name),
+            ResolveErrorKind::NotAnEnum { .. } => write!(f, "`{}` is not an enum", self.name),
+            ResolveErrorKind::NoVariant { .. } => write!(f, "the enum has no variant `{}`", self.name),
+        }
+    }
+}
+
+/// findings that don't stop the program from being resolved.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ResolveWarning<'source> {
+    /// a `let` hiding `previous` under `ShadowPolicy::Warn`, `span` is the new name.
+    Shadowed {
+        name: &'source str,
+        span: Span,
+        previous: Span,
+    },
+}
+
+impl fmt::Display for ResolveWarning<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveWarning::Shadowed { name, .. } => write!(f, "`{}` shadows an earlier declaration", name),
+        }
+    }
+}
+
+/// the outcome of `resolve`, both lists are sorted by position.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Resolved<'source> {
+    pub errors: Vec<ResolveError<'source>>,
+    pub warnings: Vec<ResolveWarning<'source>>,
+}
+
+/// resolves every name used in `stmts`, a whole program.
+pub fn resolve<'source>(stmts: &[Stmt<'source>], options: ResolveOptions) -> Resolved<'source> {
+    let mut resolver = Resolver {
+        options,
+        scopes: Vec::new(),
+        out: Resolved::default(),
+    };
+    resolver.push_scope(false);
+    resolver.declare_items(stmts);
+    walk_stmts(&mut resolver, stmts);
+    resolver.pop_scope();
+    resolver.out.errors.sort_by_key(|error| error.span.start);
+    resolver.out.warnings.sort_by_key(|ResolveWarning::Shadowed { span, .. }| span.start);
+    resolver.out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BindingKind {
+    Let,
+    Param,
+    /// a binding introduced by a match pattern.
+    Pattern,
+    Item,
+    Enum,
+    /// enum variants are in scope unqualified, next to their enum declared at `of`.
+    Variant { of: Span },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Binding<'source> {
+    name: &'source str,
+    span: Span,
+    kind: BindingKind,
+}
+
+#[derive(Debug, Default)]
+struct Scope<'source> {
+    bindings: Vec<Binding<'source>>,
+    /// names used in this scope, or in a block nested in it, that weren't found. a later
+    /// `let` of this scope turns them into `UsedBeforeDeclaration`.
+    unresolved: Vec<(&'source str, Span)>,
+    /// the parameters of a function, the scopes outside it only lend their items.
+    is_fn: bool,
+}
+
+struct Resolver<'source> {
+    options: ResolveOptions,
+    scopes: Vec<Scope<'source>>,
+    out: Resolved<'source>,
+}
+
+impl<'source> Resolver<'source> {
+    fn push_scope(&mut self, is_fn: bool) {
+        self.scopes.push(Scope {
+            is_fn,
+            ..Scope::default()
+        });
+    }
+
+    /// names still unresolved move on to the enclosing scope, unless the scope ends a function
+    /// or the program.
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().expect("scopes are balanced");
+        match self.scopes.last_mut() {
+            Some(parent) if !scope.is_fn => parent.unresolved.extend(scope.unresolved),
+            _ => {
+                let undeclared = scope.unresolved.into_iter().map(|(name, span)| ResolveError {
+                    kind: ResolveErrorKind::Undeclared,
+                    name,
+                    span,
+                });
+                self.out.errors.extend(undeclared);
+            }
+        }
+    }
+
+    /// the binding `name` refers to, innermost first.
+    fn lookup(&self, name: &str) -> Option<Binding<'source>> {
+        let mut in_fn = false;
+        for scope in self.scopes.iter().rev() {
+            let visible = |binding: &&Binding<'_>| {
+                let is_item =
+                    matches!(binding.kind, BindingKind::Item | BindingKind::Enum | BindingKind::Variant { .. });
+                binding.name == name && (!in_fn || is_item)
+            };
+            if let Some(binding) = scope.bindings.iter().rev().find(visible) {
+                return Some(*binding);
+            }
+            in_fn |= scope.is_fn;
+        }
+        None
+    }
+
+    fn use_name(&mut self, name: &'source str, span: Span) {
+        if self.lookup(name).is_none() && !BUILTINS.contains(&name) {
+            let scope = self.scopes.last_mut().expect("uses are inside a scope");
+            scope.unresolved.push((name, span));
+        }
+    }
+
+    fn declare(&mut self, name: &'source str, span: Span, kind: BindingKind) {
+        let scope = self.scopes.last_mut().expect("declarations are inside a scope");
+        scope.bindings.push(Binding { name, span, kind });
+    }
+
+    /// `enum_name::variant`, the variant has to be declared by the enum `enum_name` names. an
+    /// expression may name an `EnumIntrinsic` instead, a pattern can't.
+    fn use_path(&mut self, enum_name: Ident<'source>, variant: Ident<'source>, in_pattern: bool) {
+        let Some(binding) = self.lookup(enum_name.name) else {
+            return self.use_name(enum_name.name, enum_name.span);
+        };
+        let error = match binding.kind {
+            BindingKind::Enum => {
+                let declared = self.scopes.iter().flat_map(|scope| &scope.bindings).any(|candidate| {
+                    candidate.name == variant.name && candidate.kind == BindingKind::Variant { of: binding.span }
+                });
+                if declared || (!in_pattern && EnumIntrinsic::named(variant.name).is_some()) {
+                    return;
+                }
+                ResolveError {
+                    kind: ResolveErrorKind::NoVariant { declared: binding.span },
+                    name: variant.name,
+                    span: variant.span,
+                }
+            }
+            _ => ResolveError {
+                kind: ResolveErrorKind::NotAnEnum { declared: binding.span },
+                name: enum_name.name,
+                span: enum_name.span,
+            },
+        };
+        self.out.errors.push(error);
+    }
+
+    /// a `let` binding, checked against earlier uses and bindings of its name.
+    fn declare_let(&mut self, name: &'source str, span: Span) {
+        if let Some(previous) = self.lookup(name) {
+            match self.options.shadowing {
+                ShadowPolicy::Allow => {}
+                ShadowPolicy::Warn => {
+                    let previous = previous.span;
+                    self.out.warnings.push(ResolveWarning::Shadowed { name, span, previous });
+                }
+                ShadowPolicy::Deny => self.out.errors.push(ResolveError {
+                    kind: ResolveErrorKind::Shadowed { previous: previous.span },
+                    name,
+                    span,
+                }),
+            }
+        }
+        let scope = self.scopes.last_mut().expect("declarations are inside a scope");
+        let out = &mut self.out;
+        scope.unresolved.retain(|&(used, used_span)| {
+            if used != name {
+                return true;
+            }
+            out.errors.push(ResolveError {
+                kind: ResolveErrorKind::UsedBeforeDeclaration { declared: span },
+                name,
+                span: used_span,
+            });
+            false
+        });
+        self.declare(name, span, BindingKind::Let);
+    }
+
+    /// items are visible in their whole block, before and after their declaration.
+    fn declare_items(&mut self, stmts: &[Stmt<'source>]) {
+        for stmt in stmts {
+            let StmtKind::Item(item) = &stmt.kind else { continue };
+            let name = item.name();
+            match &item.kind {
+                ItemKind::Enum(decl) => {
+                    self.declare(name.name, name.span, BindingKind::Enum);
+                    for variant in &decl.variants {
+                        let kind = BindingKind::Variant { of: name.span };
+                        self.declare(variant.name.name, variant.name.span, kind);
+                    }
+                }
+                _ => self.declare(name.name, name.span, BindingKind::Item),
+            }
+        }
+    }
+}
+
+impl<'ast, 'source: 'ast> Visitor<'ast, 'source> for Resolver<'source> {
+    fn visit_stmt(&mut self, stmt: &'ast Stmt<'source>) {
+        match &stmt.kind {
+            // the value can't see the name it is bound to
+            StmtKind::Let { name, ty, value } => {
+                if let Some(ty) = ty {
+                    self.visit_type(ty);
+                }
+                self.visit_expr(value);
+                self.declare_let(name.name, name.span);
+            }
+            _ => walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_fn_decl(&mut self, decl: &'ast FnDecl<'source>) {
+        for param in &decl.params {
+            self.visit_type(&param.ty);
+        }
+        if let Some(ret) = &decl.ret {
+            self.visit_type(ret);
+        }
+        self.push_scope(true);
+        for param in &decl.params {
+            self.declare(param.name.name, param.name.span, BindingKind::Param);
+        }
+        if let Some(body) = &decl.body {
+            self.visit_block(body);
+        }
+        self.pop_scope();
+    }
+
+    fn visit_block(&mut self, block: &'ast Block<'source>) {
+        self.push_scope(false);
+        self.declare_items(&block.stmts);
+        walk_block(self, block);
+        self.pop_scope();
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr<'source>) {
+        match expr.kind {
+            ExprKind::Identifier(name) => self.use_name(name, expr.span),
+            ExprKind::Path { enum_name, variant } => self.use_path(enum_name, variant, false),
+            ExprKind::Struct { name, .. } => {
+                self.use_name(name.name, name.span);
+                walk_expr(self, expr);
+            }
+            _ => walk_expr(self, expr),
+        }
+    }
+
+    /// an identifier pattern naming a variant matches it, any other binds the value.
+    fn visit_match_arm(&mut self, arm: &'ast MatchArm<'source>) {
+        self.push_scope(false);
+        match arm.pattern.kind {
+            PatternKind::Identifier(name) => {
+                let is_variant =
+                    self.lookup(name).is_some_and(|binding| matches!(binding.kind, BindingKind::Variant { .. }));
+                if !is_variant {
+                    self.declare(name, arm.pattern.span, BindingKind::Pattern);
+                }
+            }
+            PatternKind::Path { enum_name, variant } => self.use_path(enum_name, variant, true),
+            PatternKind::Wildcard | PatternKind::Literal(_) => {}
+        }
+        self.visit_expr(&arm.body);
+        self.pop_scope();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use super::{ResolveError, ResolveErrorKind, ResolveOptions, ResolveWarning, Resolved, ShadowPolicy, resolve};
+    use crate::parser::tests::parse_ok;
+    use crate::types::Span;
+
+    fn resolve_with(text: &str, options: ResolveOptions) -> Resolved<'_> {
+        resolve(parse_ok(text), options)
+    }
+
+    fn error(kind: ResolveErrorKind, name: &str, span: Span) -> ResolveError<'_> {
+        ResolveError { kind, name, span }
+    }
+
+    #[test]
+    fn names_in_scope_resolve() {
+        let resolved = resolve_with(
+            "let a = 1;\nlet b = a + f(a);\nfn f(let x: u8) -> u8 { let y = x; g(y) }\nfn g(let x: u8) -> u8 { x }\n\
+             enum E { v }\nmatch b { v => 1, other => other + a, };\nif true { let c = b; c };",
+            ResolveOptions::new(),
+        );
+        assert_eq!(resolved, Resolved::default());
+    }
+
+    #[test]
+    fn uses_before_a_let_are_reported() {
+        let resolved = resolve_with(
+            "x + 1;\nlet y = { x };\nlet x = 2;\nlet z = z;\nfn f() { x }\nmissing;",
+            ResolveOptions::new(),
+        );
+        let declared = |start| ResolveErrorKind::UsedBeforeDeclaration { declared: Span::new(start, start + 1) };
+        assert_eq!(
+            resolved.errors,
+            [
+                error(declared(26), "x", Span::new(0, 1)),
+                error(declared(26), "x", Span::new(17, 18)),
+                error(declared(37), "z", Span::new(41, 42)),
+                error(ResolveErrorKind::Undeclared, "x", Span::new(53, 54)),
+                error(ResolveErrorKind::Undeclared, "missing", Span::new(57, 64)),
+            ]
+        );
+        assert!(resolved.warnings.is_empty());
+
+        let resolved = resolve_with("let p = P { a: q };\nstruct Q {}\nlet r = Q {};", ResolveOptions::new());
+        let undeclared = |name, span| error(ResolveErrorKind::Undeclared, name, span);
+        assert_eq!(resolved.errors, [undeclared("P", Span::new(8, 9)), undeclared("q", Span::new(15, 16))]);
+    }
+
+    #[test]
+    fn shadowing_follows_the_policy() {
+        let text = "let a = 1;\nlet a = a;\nfn f(let p: u8) { let p = 1; }\nmatch a { a => a, };";
+        let warnings = resolve_with(text, ResolveOptions::new()).warnings;
+        assert_eq!(
+            warnings,
+            [
+                ResolveWarning::Shadowed {
+                    name: "a",
+                    span: Span::new(15, 16),
+                    previous: Span::new(4, 5),
+                },
+                ResolveWarning::Shadowed {
+                    name: "p",
+                    span: Span::new(44, 45),
+                    previous: Span::new(31, 32),
+                },
+            ]
+        );
+
+        let allowed = resolve_with(text, ResolveOptions::new().shadowing(ShadowPolicy::Allow));
+        assert_eq!(allowed, Resolved::default());
+
+        let denied = resolve_with(text, ResolveOptions::new().shadowing(ShadowPolicy::Deny));
+        assert!(denied.warnings.is_empty());
+        let kinds: Vec<_> = denied.errors.iter().map(|e| (e.kind, e.code())).collect();
+        assert_eq!(
+            kinds,
+            [
+                (ResolveErrorKind::Shadowed { previous: Span::new(4, 5) }, "E0203"),
+                (ResolveErrorKind::Shadowed { previous: Span::new(31, 32) }, "E0203"),
+            ]
+        );
+        assert_eq!(denied.errors[0].to_string(), "`a` shadows an earlier declaration");
+    }
+
+    #[test]
+    fn variant_paths_name_a_variant_of_their_enum() {
+        let resolved = resolve_with(
+            "enum Tag { some, none }\nlet a = Tag::some;\nfn f() { Tag::none }\nmatch a { Tag::none => 1, _ => 2, };\n\
+             Tag::other;\na::some;\nMissing::some;\nfn g() { enum Inner { x } Inner::x; Tag::x; }",
+            ResolveOptions::new(),
+        );
+        let no_variant = ResolveErrorKind::NoVariant { declared: Span::new(5, 8) };
+        assert_eq!(
+            resolved.errors,
+            [
+                error(no_variant, "other", Span::new(106, 111)),
+                error(ResolveErrorKind::NotAnEnum { declared: Span::new(28, 29) }, "a", Span::new(113, 114)),
+                error(ResolveErrorKind::Undeclared, "Missing", Span::new(122, 129)),
+                error(no_variant, "x", Span::new(178, 179)),
+            ]
+        );
+        assert_eq!(resolved.errors[0].to_string(), "the enum has no variant `other`");
+        assert_eq!(resolved.errors[1].code(), "E0204");
+    }
+
+    #[test]
+    fn enum_intrinsics_are_expressions() {
+        let text = "enum Tag { a }\nlet n = Tag::variant_count;\nlet all = Tag::ALL;\n\
+                    match a { Tag::ALL => 1, _ => 2, };";
+        let resolved = resolve_with(text, ResolveOptions::new());
+        let no_variant = ResolveErrorKind::NoVariant { declared: Span::new(5, 8) };
+        assert_eq!(resolved.errors, [error(no_variant, "ALL", Span::new(78, 81))]);
+    }
+}
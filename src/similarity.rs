@@ -0,0 +1,150 @@
+use alloc::vec::Vec;
+
+use crate::lexer::TokenBuffer;
+use crate::source_code::SourceCode;
+use crate::types::Token;
+
+/// winnowed token k-gram fingerprint of a source file, used to find near-duplicate files.
+///
+/// only token kinds are hashed, so renaming identifiers or changing literal values
+/// doesn't hide a copy.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    /// sorted and deduplicated.
+    hashes: Vec<u64>,
+}
+
+/// two entries of the slice passed to `similar_pairs` and their similarity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimilarPair {
+    pub first: usize,
+    pub second: usize,
+    /// jaccard index of the fingerprints, between 0.0 and 1.0.
+    pub score: f64,
+}
+
+impl Fingerprint {
+    /// hashes every run of `k` tokens and keeps the minimum of every `window`
+    /// consecutive hashes. lexer errors are skipped.
+    pub fn new(source: &str, k: usize, window: usize) -> Self {
+        assert!(k > 0 && window > 0, "k and window must be positive");
+        let buffer = TokenBuffer::new(SourceCode::new(source));
+        let tokens = buffer.kinds();
+        if tokens.len() < k {
+            return Fingerprint::default();
+        }
+
+        let grams: Vec<u64> = tokens.windows(k).map(hash_tokens).collect();
+        let mut hashes: Vec<u64> = if grams.len() <= window {
+            grams.iter().copied().min().into_iter().collect()
+        } else {
+            grams.windows(window).map(|w| w.iter().copied().min().unwrap()).collect()
+        };
+        hashes.sort_unstable();
+        hashes.dedup();
+        Fingerprint { hashes }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// jaccard index of the two fingerprints. two empty fingerprints are not similar.
+    pub fn similarity(&self, other: &Fingerprint) -> f64 {
+        let (mut a, mut b) = (0, 0);
+        let mut shared = 0;
+        while a < self.hashes.len() && b < other.hashes.len() {
+            match self.hashes[a].cmp(&other.hashes[b]) {
+                core::cmp::Ordering::Less => a += 1,
+                core::cmp::Ordering::Greater => b += 1,
+                core::cmp::Ordering::Equal => {
+                    shared += 1;
+                    a += 1;
+                    b += 1;
+                }
+            }
+        }
+        let union = self.hashes.len() + other.hashes.len() - shared;
+        if union == 0 { 0.0 } else { shared as f64 / union as f64 }
+    }
+}
+
+/// every pair of fingerprints with a similarity of at least `threshold`, most similar first.
+pub fn similar_pairs(fingerprints: &[Fingerprint], threshold: f64) -> Vec<SimilarPair> {
+    let mut pairs = Vec::new();
+    for (first, a) in fingerprints.iter().enumerate() {
+        for (second, b) in fingerprints.iter().enumerate().skip(first + 1) {
+            let score = a.similarity(b);
+            if score >= threshold {
+                pairs.push(SimilarPair { first, second, score });
+            }
+        }
+    }
+    pairs.sort_by(|a, b| b.score.total_cmp(&a.score));
+    pairs
+}
+
+/// FNV-1a over the token kinds.
+fn hash_tokens(tokens: &[Token]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    for &token in tokens {
+        hash ^= token as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::vec::Vec;
+
+    use super::{Fingerprint, similar_pairs};
+
+    const ORIGINAL: &str = "
+        fn add(let a: const u32, let b: const u32) -> u32 {
+            let sum: const u32 = a + b;
+            return sum * 2;
+        }
+    ";
+
+    #[test]
+    fn renamed_copy_is_identical() {
+        let renamed = ORIGINAL.replace("add", "plus").replace("sum", "total").replace('2', "7");
+        let a = Fingerprint::new(ORIGINAL, 5, 4);
+        let b = Fingerprint::new(&renamed, 5, 4);
+        assert!(!a.is_empty());
+        assert_eq!(a, b);
+        assert_eq!(a.similarity(&b), 1.0);
+    }
+
+    #[test]
+    fn unrelated_sources_score_low() {
+        let a = Fingerprint::new(ORIGINAL, 5, 4);
+        let b = Fingerprint::new("struct S { x: u8, y: u8, } let s: S = uninit;", 5, 4);
+        assert!(a.similarity(&b) < 0.2);
+        assert_eq!(Fingerprint::new("", 5, 4).similarity(&Fingerprint::default()), 0.0);
+    }
+
+    #[test]
+    fn pairs_are_sorted_by_score() {
+        let extended = format!("{ORIGINAL} let x = 1; let y = 2;");
+        let prints = [
+            Fingerprint::new(ORIGINAL, 5, 4),
+            Fingerprint::new("let x = \"unrelated\";", 5, 4),
+            Fingerprint::new(&extended, 5, 4),
+            Fingerprint::new(ORIGINAL, 5, 4),
+        ];
+        let pairs = similar_pairs(&prints, 0.5);
+        let found: Vec<_> = pairs.iter().map(|p| (p.first, p.second)).collect();
+        assert_eq!(found, [(0, 3), (0, 2), (2, 3)]);
+        assert_eq!(pairs[0].score, 1.0);
+        assert!(pairs[1].score < 1.0);
+    }
+}
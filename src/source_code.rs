@@ -1,3 +1,10 @@
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
+#[cfg(feature = "alloc")]
+use core::fmt;
+
+use crate::types::{Span, Spanned};
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct SourceCode<'source> {
     code: &'source str,
@@ -28,11 +35,416 @@ impl<'source> SourceCode<'source> {
     pub const fn as_bytes(&self) -> &'source [u8] {
         self.code.as_bytes()
     }
+
+    /// 1-based line and column of the byte at `offset`, columns count bytes.
+    /// `\r\n` counts as a single newline.
+    ///
+    /// this scans the source up to `offset`. offsets past the end resolve to
+    /// the position just after the last byte.
+    pub const fn line_column(&self, offset: usize) -> (usize, usize) {
+        let bytes = self.as_bytes();
+        let end = if offset < bytes.len() {
+            offset
+        } else {
+            bytes.len()
+        };
+        let mut line = 1;
+        let mut line_start = 0;
+        let mut index = 0;
+        while index < end {
+            if bytes[index] == b'\n' {
+                line += 1;
+                line_start = index + 1;
+            }
+            index += 1;
+        }
+        // the `\r` of a `\r\n` pair does not take up a column
+        let mut column = end - line_start + 1;
+        if end > line_start && end < bytes.len() && bytes[end] == b'\n' && bytes[end - 1] == b'\r' {
+            column -= 1;
+        }
+        (line, column)
+    }
+
+    /// builds the line table, see `LineIndex`. every call builds a new one, callers converting
+    /// many offsets keep it around. the files of a `SourceMap` build theirs once, on first use,
+    /// see `SourceFile::offset_to_position`.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn line_index(&self) -> LineIndex {
+        LineIndex::new(self)
+    }
+
+    /// the source text covered by `span`, clamped to the end of the source.
+    /// spans that don't fall on char boundaries yield an empty string.
+    #[inline]
+    pub fn snippet(&self, span: Span) -> &'source str {
+        let end = span.end.min(self.len());
+        let start = span.start.min(end);
+        self.code.get(start..end).unwrap_or("")
+    }
+
+    /// the line that contains the byte at `offset`, without its line terminator.
+    /// offsets past the end resolve to the last line.
+    pub fn line_containing(&self, offset: usize) -> Spanned<&'source str> {
+        let bytes = self.as_bytes();
+        let offset = offset.min(bytes.len());
+        let start = match bytes[..offset].iter().rposition(|&b| b == b'\n') {
+            Some(newline) => newline + 1,
+            None => 0,
+        };
+        let mut end = match bytes[offset..].iter().position(|&b| b == b'\n') {
+            Some(newline) => offset + newline,
+            None => bytes.len(),
+        };
+        if end > start && bytes[end - 1] == b'\r' {
+            end -= 1;
+        }
+        let span = Span::new(start, end);
+        Spanned::new(self.snippet(span), span)
+    }
+
+    /// the lines touched by `span` plus up to `n` lines before and after it,
+    /// each paired with its 1-based line number.
+    #[cfg(feature = "alloc")]
+    pub fn context_lines(&self, span: Span, n: usize) -> Vec<(usize, Spanned<&'source str>)> {
+        let first = self.line_containing(span.start);
+        let last = self.line_containing(span.end.max(span.start));
+        let (first_line, _) = self.line_column(first.span.start);
+
+        let mut before = Vec::new();
+        let mut cursor = first.span.start;
+        while before.len() < n && cursor > 0 {
+            let line = self.line_containing(cursor - 1);
+            cursor = line.span.start;
+            before.push(line);
+        }
+
+        let mut lines = Vec::new();
+        let mut number = first_line - before.len();
+        for line in before.into_iter().rev() {
+            lines.push((number, line));
+            number += 1;
+        }
+
+        let mut line = first;
+        loop {
+            lines.push((number, line));
+            number += 1;
+            let next = self.next_line_start(line.span.end);
+            if line.span.start >= last.span.start || next.is_none() {
+                break;
+            }
+            line = self.line_containing(next.unwrap());
+        }
+
+        let mut after = 0;
+        let mut next = self.next_line_start(line.span.end);
+        while after < n
+            && let Some(start) = next
+        {
+            let line = self.line_containing(start);
+            lines.push((number, line));
+            number += 1;
+            after += 1;
+            next = self.next_line_start(line.span.end);
+        }
+        lines
+    }
+
+    /// the lines around `span` with a gutter of line numbers and a caret underline below
+    /// every part of a line the span covers, for error output:
+    ///
+    /// ```text
+    /// 1 | let a = 1;
+    /// 2 | let b = $;
+    ///   |         ^
+    /// 3 | let c = 3;
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn render_snippet(&self, span: Span, context_lines: usize) -> String {
+        let mut out = String::new();
+        self.write_snippet(&mut out, span, context_lines).expect("formatting into a String can't fail");
+        out
+    }
+
+    /// like `render_snippet`, but writes into `out`.
+    #[cfg(feature = "alloc")]
+    pub fn write_snippet(&self, out: &mut impl fmt::Write, span: Span, context_lines: usize) -> fmt::Result {
+        let lines = self.context_lines(span, context_lines);
+        let width = lines.last().map_or(1, |(number, _)| number.ilog10() as usize + 1);
+        let last = span.start.max(span.end.saturating_sub(1));
+        for (number, line) in lines {
+            writeln!(out, "{:>width$} | {}", number, line.value)?;
+            if line.span.start <= last && span.start <= line.span.end {
+                write!(out, "{:width$} | ", "")?;
+                let start = span.start.saturating_sub(line.span.start);
+                let end = span.end.min(line.span.end).saturating_sub(line.span.start);
+                write_underline(out, line.value, start, end, '^')?;
+                out.write_char('\n')?;
+            }
+        }
+        Ok(())
+    }
+
+    /// start of the line following a line that ends at `line_end`, if there is one.
+    #[cfg(feature = "alloc")]
+    fn next_line_start(&self, line_end: usize) -> Option<usize> {
+        let bytes = self.as_bytes();
+        match bytes.get(line_end..)? {
+            [b'\r', b'\n', ..] => Some(line_end + 2),
+            [b'\n', ..] => Some(line_end + 1),
+            _ => None,
+        }
+    }
+}
+
+/// writes `marker` below the bytes `start..end` of `line`, at least once, after whitespace
+/// that keeps tabs so the markers line up with the text above. offsets inside a char are
+/// widened to the whole char.
+#[cfg(feature = "alloc")]
+pub(crate) fn write_underline(
+    out: &mut impl fmt::Write,
+    line: &str,
+    start: usize,
+    end: usize,
+    marker: char,
+) -> fmt::Result {
+    let start = floor_char_boundary(line, start);
+    let end = ceil_char_boundary(line, end.max(start));
+    for c in line[..start].chars() {
+        out.write_char(if c == '\t' { '\t' } else { ' ' })?;
+    }
+    for _ in 0..line[start..end].chars().count().max(1) {
+        out.write_char(marker)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "alloc")]
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(feature = "alloc")]
+fn ceil_char_boundary(text: &str, mut index: usize) -> usize {
+    index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
+
+/// source text that owns its storage, for sources that have to outlive the scope they were
+/// loaded in. clones share the text instead of copying it.
+///
+/// lexing borrows it through `as_source_code`.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SourceCodeOwned {
+    code: Arc<str>,
+}
+
+#[cfg(feature = "alloc")]
+impl SourceCodeOwned {
+    #[inline]
+    pub fn new(code: impl Into<Arc<str>>) -> Self {
+        SourceCodeOwned { code: code.into() }
+    }
+
+    #[inline]
+    pub fn as_source_code(&self) -> SourceCode<'_> {
+        SourceCode::new(&self.code)
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.code
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.code.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.code.is_empty()
+    }
+
+    /// the shared storage, for handing the text to other owners without a copy.
+    #[inline]
+    pub fn as_arc(&self) -> &Arc<str> {
+        &self.code
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<&str> for SourceCodeOwned {
+    #[inline]
+    fn from(code: &str) -> Self {
+        Self::new(code)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<String> for SourceCodeOwned {
+    #[inline]
+    fn from(code: String) -> Self {
+        Self::new(code)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<Box<str>> for SourceCodeOwned {
+    #[inline]
+    fn from(code: Box<str>) -> Self {
+        Self::new(code)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<Arc<str>> for SourceCodeOwned {
+    #[inline]
+    fn from(code: Arc<str>) -> Self {
+        SourceCodeOwned { code }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<&SourceCode<'_>> for SourceCodeOwned {
+    #[inline]
+    fn from(source: &SourceCode<'_>) -> Self {
+        Self::new(source.as_str())
+    }
+}
+
+/// a source file mapped into memory instead of read into a `String`, see `MappedSource::open`.
+#[cfg(feature = "mmap")]
+#[derive(Debug)]
+pub struct MappedSource {
+    // `None` for empty files, which can't be mapped on every platform
+    map: Option<memmap2::Mmap>,
+}
+
+#[cfg(feature = "mmap")]
+impl MappedSource {
+    /// maps the file at `path` and checks that it is utf-8, failing with
+    /// `io::ErrorKind::InvalidData` if it isn't.
+    ///
+    /// # Safety
+    ///
+    /// nothing, in this process or any other, may write to or truncate the file while the
+    /// returned `MappedSource` or anything borrowed from it is alive. the contents are
+    /// validated once, a later write can leave invalid utf-8 behind a `&str` and a truncation
+    /// faults on access.
+    pub unsafe fn open(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        if file.metadata()?.len() == 0 {
+            return Ok(MappedSource { map: None });
+        }
+        // SAFETY: the file is only read, the caller rules out writes while it is mapped
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        if let Err(e) = core::str::from_utf8(&map) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e));
+        }
+        Ok(MappedSource { map: Some(map) })
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        match &self.map {
+            // SAFETY: validated in `open`
+            Some(map) => unsafe { core::str::from_utf8_unchecked(map) },
+            None => "",
+        }
+    }
+
+    #[inline]
+    pub fn as_source_code(&self) -> SourceCode<'_> {
+        SourceCode::new(self.as_str())
+    }
+}
+
+/// sorted table of line spans that converts byte offsets to line and column in O(log n),
+/// with the same results as `SourceCode::line_column`.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LineIndex {
+    /// every line without its `\n` or `\r\n` terminator, the last line ends at the source end.
+    lines: Vec<Span>,
+    len: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl LineIndex {
+    pub fn new(source: &SourceCode<'_>) -> Self {
+        let bytes = source.as_bytes();
+        let mut lines = Vec::new();
+        let mut start = 0;
+        for (index, &byte) in bytes.iter().enumerate() {
+            if byte == b'\n' {
+                let end = if index > start && bytes[index - 1] == b'\r' { index - 1 } else { index };
+                lines.push(Span::new(start, end));
+                start = index + 1;
+            }
+        }
+        lines.push(Span::new(start, bytes.len()));
+        LineIndex { lines, len: bytes.len() }
+    }
+
+    /// 1-based line and column of the byte at `offset`, see `SourceCode::line_column`.
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.len);
+        // the first line starts at 0, so there is always at least one line before `offset`
+        let line = self.lines.partition_point(|line| line.start <= offset) - 1;
+        let span = self.lines[line];
+        // both bytes of a `\r\n` terminator share the column right after the line
+        (line + 1, offset.min(span.end) - span.start + 1)
+    }
+
+    /// byte offset of the start of every line, the first one is always 0.
+    #[inline]
+    pub fn line_starts(&self) -> impl ExactSizeIterator<Item = usize> + '_ {
+        self.lines.iter().map(|line| line.start)
+    }
+
+    /// number of lines, a trailing newline starts an empty last line.
+    #[inline]
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// span of the 1-based `line` without its terminator.
+    #[inline]
+    pub fn line_span(&self, line: usize) -> Option<Span> {
+        self.lines.get(line.checked_sub(1)?).copied()
+    }
+
+    /// byte offset of a 1-based `line` and `column`, the inverse of `line_column`. the column
+    /// may point one past the last byte of the line, at its terminator, anything further is
+    /// `None`.
+    #[inline]
+    pub fn offset(&self, line: usize, column: usize) -> Option<usize> {
+        let span = self.line_span(line)?;
+        let offset = span.start + column.checked_sub(1)?;
+        (offset <= span.end).then_some(offset)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::SourceCode;
+    use alloc::format;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    use super::{LineIndex, SourceCode, SourceCodeOwned};
+    use crate::lexer::Lexer;
+    use crate::types::{Span, Spanned, Token};
 
     #[test]
     fn source_code_accessors_work() {
@@ -43,4 +455,177 @@ mod tests {
         assert!(!source_code.is_empty());
         assert_eq!(source_code.as_bytes(), text.as_bytes());
     }
+
+    #[test]
+    fn owned_sources_outlive_their_loading_scope() {
+        let owned = {
+            let loaded = String::from("let x = 1;");
+            SourceCodeOwned::from(loaded)
+        };
+        let shared = owned.clone();
+        assert!(alloc::sync::Arc::ptr_eq(owned.as_arc(), shared.as_arc()));
+        assert_eq!(shared.as_str(), "let x = 1;");
+
+        let tokens: Vec<Token> = Lexer::new(shared.as_source_code()).collect();
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(SourceCodeOwned::from(&SourceCode::new("let x = 1;")), owned);
+    }
+
+    #[test]
+    fn positions_convert_both_ways() {
+        let text = "ab\ncd\r\n\nef";
+        let source_code = SourceCode::new(text);
+        let lines = source_code.line_index();
+        assert_eq!(lines.line_starts().collect::<Vec<_>>(), [0, 3, 7, 8]);
+        for offset in 0..=text.len() + 2 {
+            let (line, column) = lines.line_column(offset);
+            assert_eq!((line, column), source_code.line_column(offset), "{offset}");
+            // the `\n` of a `\r\n` shares its column with the `\r`
+            if offset <= text.len() && offset != 6 {
+                assert_eq!(lines.offset(line, column), Some(offset), "{offset}");
+            }
+        }
+        assert_eq!(lines.offset(2, 3), Some(5));
+        assert_eq!(lines.offset(2, 4), None);
+        assert_eq!(lines.offset(0, 1), None);
+        assert_eq!(lines.offset(1, 0), None);
+        assert_eq!(lines.offset(5, 1), None);
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mapped_sources_lex_like_read_ones() {
+        use super::MappedSource;
+
+        for entry in std::fs::read_dir("progs").unwrap() {
+            let path = entry.unwrap().path();
+            // SAFETY: nothing writes to the test programs while the tests run
+            let mapped = unsafe { MappedSource::open(&path) }.unwrap();
+            let read = std::fs::read_to_string(&path).unwrap();
+            assert_eq!(mapped.as_str(), read);
+            let tokens: Vec<Token> = Lexer::new(mapped.as_source_code()).collect();
+            assert_eq!(tokens, Lexer::new(SourceCode::new(&read)).collect::<Vec<_>>());
+        }
+
+        let dir = std::env::temp_dir();
+        let empty = dir.join(format!("mumbo-empty-{}.mumbo", std::process::id()));
+        std::fs::write(&empty, "").unwrap();
+        // SAFETY: the files are private to this test
+        assert_eq!(unsafe { MappedSource::open(&empty) }.unwrap().as_str(), "");
+        let invalid = dir.join(format!("mumbo-invalid-{}.mumbo", std::process::id()));
+        std::fs::write(&invalid, b"let \xff;").unwrap();
+        let error = unsafe { MappedSource::open(&invalid) }.unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        let _ = (std::fs::remove_file(empty), std::fs::remove_file(invalid));
+    }
+
+    #[test]
+    fn line_column_is_one_based() {
+        let source_code = SourceCode::new("ab\ncd\r\n\nef");
+        assert_eq!(source_code.line_column(0), (1, 1));
+        assert_eq!(source_code.line_column(1), (1, 2));
+        assert_eq!(source_code.line_column(2), (1, 3));
+        assert_eq!(source_code.line_column(3), (2, 1));
+        assert_eq!(source_code.line_column(5), (2, 3));
+        assert_eq!(source_code.line_column(6), (2, 3));
+        assert_eq!(source_code.line_column(7), (3, 1));
+        assert_eq!(source_code.line_column(8), (4, 1));
+        assert_eq!(source_code.line_column(10), (4, 3));
+        assert_eq!(source_code.line_column(100), (4, 3));
+    }
+
+    #[test]
+    fn snippets_and_lines() {
+        let source_code = SourceCode::new("let a = 1;\r\nlet é = 2;\n\nfn f() {}");
+        assert_eq!(source_code.snippet(Span::new(4, 5)), "a");
+        assert_eq!(source_code.snippet(Span::new(32, 100)), "{}");
+        assert_eq!(source_code.snippet(Span::new(17, 18)), "");
+        assert_eq!(
+            source_code.line_containing(0),
+            Spanned::new("let a = 1;", Span::new(0, 10))
+        );
+        assert_eq!(
+            source_code.line_containing(10),
+            Spanned::new("let a = 1;", Span::new(0, 10))
+        );
+        assert_eq!(
+            source_code.line_containing(12),
+            Spanned::new("let é = 2;", Span::new(12, 23))
+        );
+        assert_eq!(
+            source_code.line_containing(24),
+            Spanned::new("", Span::new(24, 24))
+        );
+        assert_eq!(
+            source_code.line_containing(100),
+            Spanned::new("fn f() {}", Span::new(25, 34))
+        );
+    }
+
+    #[test]
+    fn snippets_render_with_carets() {
+        let lines: Vec<String> = (1..=10).map(|n| format!("line {n};")).collect();
+        let text = lines.join("\n") + "\n\tlet é = $;";
+        let source_code = SourceCode::new(&text);
+        assert_eq!(
+            source_code.render_snippet(Span::new(10, 11), 1),
+            "1 | line 1;\n2 | line 2;\n  |   ^\n3 | line 3;\n"
+        );
+        // spans over several lines are underlined on each of them
+        assert_eq!(
+            source_code.render_snippet(Span::new(69, 83), 0),
+            " 9 | line 9;\n   |      ^^\n10 | line 10;\n   | ^^^^^^^^\n11 | \tlet é = $;\n   | ^^\n"
+        );
+        assert_eq!(
+            source_code.render_snippet(Span::new(91, 92), 2),
+            " 9 | line 9;\n10 | line 10;\n11 | \tlet é = $;\n   | \t        ^\n"
+        );
+    }
+
+    #[test]
+    fn context_lines_surround_span() {
+        let source_code = SourceCode::new("a\nb\r\nc\nd\ne");
+        fn text(lines: Vec<(usize, Spanned<&str>)>) -> Vec<(usize, &str)> {
+            lines.into_iter().map(|(n, l)| (n, l.value)).collect()
+        }
+        assert_eq!(
+            text(source_code.context_lines(Span::new(5, 6), 0)),
+            [(3, "c")]
+        );
+        assert_eq!(
+            text(source_code.context_lines(Span::new(5, 6), 1)),
+            [(2, "b"), (3, "c"), (4, "d")]
+        );
+        assert_eq!(
+            text(source_code.context_lines(Span::new(2, 8), 5)),
+            [(1, "a"), (2, "b"), (3, "c"), (4, "d"), (5, "e")]
+        );
+        assert_eq!(
+            text(source_code.context_lines(Span::new(0, 0), 1)),
+            [(1, "a"), (2, "b")]
+        );
+        assert_eq!(
+            text(source_code.context_lines(Span::new(10, 11), 1)),
+            [(4, "d"), (5, "e")]
+        );
+    }
+
+    #[test]
+    fn line_index_agrees_with_scan() {
+        for text in ["", "\n", "ab\ncd\r\n\nef", "a\rb\r\n\r\r\n", "é\n🦀x\r\n"] {
+            let source_code = SourceCode::new(text);
+            let index = LineIndex::new(&source_code);
+            for offset in 0..=text.len() + 2 {
+                assert_eq!(index.line_column(offset), source_code.line_column(offset), "{text:?} at {offset}");
+            }
+        }
+
+        let index = SourceCode::new("ab\ncd\r\n\nef").line_index();
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.line_span(0), None);
+        assert_eq!(index.line_span(2), Some(Span::new(3, 5)));
+        assert_eq!(index.line_span(3), Some(Span::new(7, 7)));
+        assert_eq!(index.line_span(4), Some(Span::new(8, 10)));
+        assert_eq!(index.line_span(5), None);
+    }
 }
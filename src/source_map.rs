@@ -0,0 +1,248 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::OnceCell;
+use core::fmt;
+
+use crate::source_code::{LineIndex, SourceCode, SourceCodeOwned};
+use crate::types::Span;
+
+/// a file registered in a `SourceMap`. ids are only meaningful for the map that handed
+/// them out.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FileId(u32);
+
+impl FileId {
+    /// position of the file in registration order.
+    #[inline]
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// a source file and its line table, see `SourceMap::add`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    path: String,
+    source: SourceCodeOwned,
+    /// built on the first position conversion, most files never need one.
+    line_index: OnceCell<LineIndex>,
+}
+
+impl SourceFile {
+    #[inline]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    #[inline]
+    pub fn source(&self) -> SourceCode<'_> {
+        self.source.as_source_code()
+    }
+
+    #[inline]
+    pub fn source_owned(&self) -> &SourceCodeOwned {
+        &self.source
+    }
+
+    /// the line table, built on the first call and kept for later ones.
+    #[inline]
+    pub fn line_index(&self) -> &LineIndex {
+        self.line_index.get_or_init(|| self.source().line_index())
+    }
+
+    /// byte offset of the start of every line, see `LineIndex::line_starts`.
+    #[inline]
+    pub fn line_starts(&self) -> impl ExactSizeIterator<Item = usize> + '_ {
+        self.line_index().line_starts()
+    }
+
+    /// 1-based line and column of the byte at `offset`, see `LineIndex::line_column`.
+    #[inline]
+    pub fn offset_to_position(&self, offset: usize) -> (usize, usize) {
+        self.line_index().line_column(offset)
+    }
+
+    /// the byte offset of a 1-based line and column, the inverse of `offset_to_position`.
+    /// see `LineIndex::offset`.
+    #[inline]
+    pub fn position_to_offset(&self, line: usize, column: usize) -> Option<usize> {
+        self.line_index().offset(line, column)
+    }
+}
+
+/// where a span starts, displays as `path:line:column`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Location<'map> {
+    pub path: &'map str,
+    /// 1-based, columns count bytes like `SourceCode::line_column`.
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.path, self.line, self.column)
+    }
+}
+
+/// where `mod name;` in the file at `parent` looks for the module: `name.mumbo` in the same
+/// directory.
+pub fn module_path(parent: &str, name: &str) -> String {
+    let dir = match parent.rfind(['/', '\\']) {
+        Some(index) => &parent[..=index],
+        None => "",
+    };
+    format!("{}{}.mumbo", dir, name)
+}
+
+/// every file of a project, so that a `(FileId, Span)` pair identifies source text
+/// across files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// registers a file, its line table is built once a position in it is needed. paths are
+    /// not deduplicated, adding the same path twice gives two ids.
+    pub fn add(&mut self, path: impl Into<String>, source: impl Into<SourceCodeOwned>) -> FileId {
+        let id = FileId(u32::try_from(self.files.len()).expect("more than u32::MAX source files"));
+        self.files.push(SourceFile {
+            path: path.into(),
+            source: source.into(),
+            line_index: OnceCell::new(),
+        });
+        id
+    }
+
+    #[inline]
+    pub fn get(&self, id: FileId) -> Option<&SourceFile> {
+        self.files.get(id.index())
+    }
+
+    /// like `get`, but panics for ids of another map.
+    #[inline]
+    #[track_caller]
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.index()]
+    }
+
+    /// the first file registered under `path`.
+    pub fn find(&self, path: &str) -> Option<FileId> {
+        self.files.iter().position(|file| file.path == path).map(|index| FileId(index as u32))
+    }
+
+    /// the file `mod name;` in `parent` declares, if it was added, see `module_path`.
+    pub fn find_module(&self, parent: FileId, name: &str) -> Option<FileId> {
+        self.find(&module_path(self.file(parent).path(), name))
+    }
+
+    /// the file, line and column `span` starts at.
+    #[track_caller]
+    pub fn resolve(&self, id: FileId, span: Span) -> Location<'_> {
+        let file = self.file(id);
+        let (line, column) = file.offset_to_position(span.start);
+        Location {
+            path: &file.path,
+            line,
+            column,
+        }
+    }
+
+    /// the source text covered by `span`, see `SourceCode::snippet`.
+    #[track_caller]
+    pub fn snippet(&self, id: FileId, span: Span) -> &str {
+        self.file(id).source().snippet(span)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files.iter().enumerate().map(|(index, file)| (FileId(index as u32), file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+
+    use super::{SourceMap, module_path};
+    use crate::lexer::Lexer;
+    use crate::types::Span;
+
+    #[test]
+    fn spans_resolve_per_file() {
+        let mut map = SourceMap::new();
+        let main = map.add("src/main.mumbo", "fn main() {\n\treturn helper();\n}");
+        let helper = map.add("src/helper.mumbo", String::from("fn helper() {}"));
+        assert_eq!((main.index(), helper.index(), map.len()), (0, 1, 2));
+
+        let location = map.resolve(main, Span::new(20, 26));
+        assert_eq!((location.line, location.column), (2, 9));
+        assert_eq!(location.to_string(), "src/main.mumbo:2:9");
+        assert_eq!(map.resolve(helper, Span::new(3, 9)).to_string(), "src/helper.mumbo:1:4");
+        assert_eq!(map.snippet(main, Span::new(20, 26)), "helper");
+
+        assert_eq!(map.find("src/helper.mumbo"), Some(helper));
+        assert_eq!(map.find("src/missing.mumbo"), None);
+        assert!(map.get(helper).is_some());
+        let paths: Vec<&str> = map.iter().map(|(_, file)| file.path()).collect();
+        assert_eq!(paths, ["src/main.mumbo", "src/helper.mumbo"]);
+
+        let tokens = Lexer::new(map.file(helper).source()).count();
+        assert_eq!(tokens, 6);
+    }
+
+    #[test]
+    fn files_convert_positions_through_one_table() {
+        let mut map = SourceMap::new();
+        let id = map.add("a.mumbo", "ab\ncd\r\n\nef");
+        let file = map.file(id);
+        assert_eq!(file.line_starts().collect::<Vec<_>>(), [0, 3, 7, 8]);
+        assert_eq!(file.offset_to_position(4), (2, 2));
+        assert_eq!(file.offset_to_position(6), (2, 3));
+        assert_eq!(file.position_to_offset(4, 2), Some(9));
+        assert_eq!(file.position_to_offset(2, 4), None);
+        // built once, later conversions share it
+        assert!(core::ptr::eq(file.line_index(), file.line_index()));
+    }
+
+    #[test]
+    fn modules_are_siblings_of_their_parent() {
+        let mut map = SourceMap::new();
+        let main = map.add("progs/modules.mumbo", "mod adt;");
+        let adt = map.add("progs/adt.mumbo", "");
+        let top = map.add("top.mumbo", "");
+        assert_eq!(map.find_module(main, "adt"), Some(adt));
+        assert_eq!(map.find_module(main, "missing"), None);
+        assert_eq!(map.find_module(adt, "modules"), Some(main));
+        assert_eq!(module_path("top.mumbo", "x"), "x.mumbo");
+        assert_eq!(module_path("a\\b.mumbo", "x"), "a\\x.mumbo");
+        assert_eq!(map.find_module(top, "top"), Some(top));
+    }
+
+    #[test]
+    #[should_panic]
+    fn foreign_ids_panic() {
+        let mut other = SourceMap::new();
+        let id = other.add("a", "");
+        SourceMap::new().resolve(id, Span::new(0, 0));
+    }
+}
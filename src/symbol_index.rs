@@ -0,0 +1,226 @@
+//! the items the files of a program declare, searched by name, see `SymbolIndex`.
+//!
+//! only items at the top level of a file and the variants of its enums are indexed, a function
+//! declared in a block can't be named outside of it. imports aren't, the item they name is
+//! already indexed in its own file.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::ast::{ItemKind, Stmt, StmtKind};
+use crate::source_map::FileId;
+use crate::types::Span;
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    Fn,
+    Struct,
+    Union,
+    Enum,
+    Variant,
+    Mod,
+}
+
+impl SymbolKind {
+    /// the keyword declaring the symbol, `variant` for enum variants.
+    #[inline]
+    pub const fn name(self) -> &'static str {
+        match self {
+            SymbolKind::Fn => "fn",
+            SymbolKind::Struct => "struct",
+            SymbolKind::Union => "union",
+            SymbolKind::Enum => "enum",
+            SymbolKind::Variant => "variant",
+            SymbolKind::Mod => "mod",
+        }
+    }
+}
+
+impl fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Symbol {
+    /// variants are named after their enum, `E::a`.
+    pub name: String,
+    pub kind: SymbolKind,
+    pub file: FileId,
+    /// the name where it is declared.
+    pub span: Span,
+}
+
+/// the symbols of every file, by file id and in declaration order within a file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolIndex {
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolIndex {
+    #[inline]
+    pub const fn new() -> Self {
+        SymbolIndex { symbols: Vec::new() }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.iter()
+    }
+
+    /// replaces the symbols of `file` with the ones `stmts` declare, the other files keep
+    /// theirs.
+    pub fn update(&mut self, file: FileId, stmts: &[Stmt<'_>]) {
+        self.remove(file);
+        let mut symbols = Vec::new();
+        for stmt in stmts {
+            let StmtKind::Item(item) = &stmt.kind else { continue };
+            let kind = match &item.kind {
+                ItemKind::Fn(_) => SymbolKind::Fn,
+                ItemKind::Struct(_) => SymbolKind::Struct,
+                ItemKind::Union(_) => SymbolKind::Union,
+                ItemKind::Enum(_) => SymbolKind::Enum,
+                ItemKind::Mod(_) => SymbolKind::Mod,
+                ItemKind::Import(_) => continue,
+            };
+            let name = item.name();
+            symbols.push(Symbol { name: String::from(name.name), kind, file, span: name.span });
+            if let ItemKind::Enum(decl) = &item.kind {
+                symbols.extend(decl.variants.iter().map(|variant| Symbol {
+                    name: format!("{}::{}", name.name, variant.name.name),
+                    kind: SymbolKind::Variant,
+                    file,
+                    span: variant.name.span,
+                }));
+            }
+        }
+        let at = self.symbols.partition_point(|symbol| symbol.file < file);
+        self.symbols.splice(at..at, symbols);
+    }
+
+    /// forgets the symbols of `file`.
+    #[inline]
+    pub fn remove(&mut self, file: FileId) {
+        self.symbols.retain(|symbol| symbol.file != file);
+    }
+
+    /// every symbol called exactly `name`.
+    #[inline]
+    pub fn get<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Symbol> + 'a {
+        self.symbols.iter().filter(move |symbol| symbol.name == name)
+    }
+
+    /// every symbol whose name has the characters of `query` in order, ignoring ascii case, the
+    /// best matches first: equal names, then names starting with `query`, names containing
+    /// it and names only containing its characters. an empty query matches every symbol.
+    pub fn query(&self, query: &str) -> Vec<&Symbol> {
+        let mut found: Vec<_> = self
+            .symbols
+            .iter()
+            .filter_map(|symbol| Some((score(&symbol.name, query)?, symbol)))
+            .collect();
+        // stable, ties stay in the order of the files
+        found.sort_by_key(|(score, symbol)| (*score, symbol.name.len()));
+        found.into_iter().map(|(_, symbol)| symbol).collect()
+    }
+}
+
+/// how well `name` matches `query`, lower is better.
+fn score(name: &str, query: &str) -> Option<u8> {
+    if name == query {
+        return Some(0);
+    }
+    let (name, query) = (name.to_ascii_lowercase(), query.to_ascii_lowercase());
+    if name == query {
+        Some(1)
+    } else if name.starts_with(&query) {
+        Some(2)
+    } else if name.contains(&query) {
+        Some(3)
+    } else {
+        let mut rest = name.chars();
+        query.chars().all(|c| rest.any(|n| n == c)).then_some(4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use super::{SymbolIndex, SymbolKind};
+    use crate::parser::tests::parse_ok;
+    use crate::source_map::SourceMap;
+
+    fn index(sources: &[&str]) -> (SourceMap, SymbolIndex) {
+        let mut files = SourceMap::new();
+        let mut index = SymbolIndex::new();
+        for (n, source) in sources.iter().enumerate() {
+            let id = files.add(format!("{n}.mumbo"), *source);
+            index.update(id, parse_ok(source));
+        }
+        (files, index)
+    }
+
+    #[test]
+    fn indexes_top_level_items() {
+        let (files, index) = index(&[
+            "mod util;\nimport util.helper;\nfn main() { fn nested() {} }\nenum Color { red, green }",
+            "struct point { x: i32 }\nunion bits { a: u8 }",
+        ]);
+        let symbols: Vec<_> =
+            index.iter().map(|symbol| (symbol.name.as_str(), symbol.kind, symbol.file.index())).collect();
+        assert_eq!(
+            symbols,
+            [
+                ("util", SymbolKind::Mod, 0),
+                ("main", SymbolKind::Fn, 0),
+                ("Color", SymbolKind::Enum, 0),
+                ("Color::red", SymbolKind::Variant, 0),
+                ("Color::green", SymbolKind::Variant, 0),
+                ("point", SymbolKind::Struct, 1),
+                ("bits", SymbolKind::Union, 1),
+            ]
+        );
+        let point = index.get("point").next().unwrap();
+        assert_eq!(files.snippet(point.file, point.span), "point");
+        assert_eq!(files.resolve(point.file, point.span).to_string(), "1.mumbo:1:8");
+    }
+
+    #[test]
+    fn queries_rank_closer_names_first() {
+        let (_, index) = index(&["fn parse_item() {}\nfn parse() {}\nfn Parse() {}\nfn reparse() {}\nfn pairs() {}"]);
+        let names = |query| -> Vec<_> { index.query(query).iter().map(|symbol| symbol.name.as_str()).collect() };
+        assert_eq!(names("parse"), ["parse", "Parse", "parse_item", "reparse"]);
+        assert_eq!(names("prs"), ["parse", "Parse", "pairs", "reparse", "parse_item"]);
+        assert_eq!(names("xyz"), [""; 0]);
+        assert_eq!(names("").len(), 5);
+    }
+
+    #[test]
+    fn updates_replace_the_symbols_of_a_file() {
+        let (files, mut index) = index(&["fn a() {}", "fn b() {}"]);
+        let first = files.iter().next().unwrap().0;
+        index.update(first, parse_ok("fn c() {}\nfn d() {}"));
+        let names: Vec<_> = index.iter().map(|symbol| symbol.name.as_str()).collect();
+        assert_eq!(names, ["c", "d", "b"]);
+        index.remove(first);
+        assert_eq!(index.len(), 1);
+    }
+}
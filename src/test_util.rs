@@ -0,0 +1,2 @@
+pub mod checked_lexer;
+pub mod source_generator;
@@ -0,0 +1,156 @@
+use core::ops::Deref;
+
+use crate::lexer::{Lexer, LexerResult};
+use crate::source_code::{LineIndex, SourceCode};
+use crate::types::Token;
+
+/// wraps a `Lexer` and asserts its position invariants after every token:
+///
+/// - `start <= index <= source.len()`
+/// - `index` never moves backwards, and errors other than `Eof` always make progress
+/// - `get_line_column()` agrees with the `LineIndex` of the source
+/// - extracted literals lie within `start..index`
+///
+/// everything else is reachable through `Deref`.
+#[derive(Debug, Clone)]
+pub struct CheckedLexer<'source> {
+    lexer: Lexer<'source>,
+    source: SourceCode<'source>,
+    line_index: LineIndex,
+    previous_index: usize,
+}
+
+impl<'source> CheckedLexer<'source> {
+    #[inline]
+    pub fn new(source: SourceCode<'source>) -> Self {
+        Self::from_lexer(Lexer::new(source.clone()), source)
+    }
+
+    /// `lexer` must have been created from `source` and not advanced yet.
+    #[inline]
+    pub fn from_lexer(lexer: Lexer<'source>, source: SourceCode<'source>) -> Self {
+        CheckedLexer {
+            lexer,
+            line_index: source.line_index(),
+            source,
+            previous_index: 0,
+        }
+    }
+
+    #[track_caller]
+    pub fn lex_single_token(&mut self) -> LexerResult<Token> {
+        let res = self.lexer.lex_single_token();
+        self.check_invariants();
+        match res {
+            Err(e) if e.is_eof() => {}
+            Err(e) => assert!(
+                self.lexer.index() > self.previous_index,
+                "{:?} made no progress\n\t{}",
+                e,
+                self.lexer.get_lexer_debug_state()
+            ),
+            Ok(_) => {}
+        }
+        self.previous_index = self.lexer.index();
+        res
+    }
+
+    #[track_caller]
+    pub fn extract_literal(&mut self) -> LexerResult<&'source [u8]> {
+        let literal = self.lexer.extract_literal()?;
+        let offset = literal.as_ptr() as usize - self.source.as_bytes().as_ptr() as usize;
+        assert!(
+            self.lexer.start() <= offset && offset + literal.len() <= self.lexer.index(),
+            "literal {}..{} outside of the token\n\t{}",
+            offset,
+            offset + literal.len(),
+            self.lexer.get_lexer_debug_state()
+        );
+        Ok(literal)
+    }
+
+    #[track_caller]
+    pub fn check_invariants(&self) {
+        let (start, index) = (self.lexer.start(), self.lexer.index());
+        assert!(
+            start <= index && index <= self.source.len(),
+            "start {} index {} len {}",
+            start,
+            index,
+            self.source.len()
+        );
+        assert!(
+            index >= self.previous_index,
+            "index moved backwards from {} to {}",
+            self.previous_index,
+            index
+        );
+        assert_eq!(
+            self.lexer.get_line_column(),
+            self.line_index.line_column(index),
+            "line/column out of sync at index {}",
+            index
+        );
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> Lexer<'source> {
+        self.lexer
+    }
+}
+
+impl<'source> Deref for CheckedLexer<'source> {
+    type Target = Lexer<'source>;
+
+    #[inline]
+    fn deref(&self) -> &Lexer<'source> {
+        &self.lexer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    use super::CheckedLexer;
+    use crate::source_code::SourceCode;
+    use crate::test_util::source_generator::{ProgramShape, generate_program};
+
+    fn lex_everything(text: &str) {
+        let mut lexer = CheckedLexer::new(SourceCode::new(text));
+        loop {
+            match lexer.lex_single_token() {
+                Ok(t) if t.is_identifier_extractable() => {
+                    lexer.extract_literal().unwrap();
+                }
+                Ok(_) => {}
+                Err(e) if e.is_eof() => break,
+                Err(_) => {}
+            }
+        }
+        assert!(lexer.is_at_end());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn invariants_hold_on_corpus() {
+        for entry in std::fs::read_dir("progs").unwrap() {
+            let text = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            lex_everything(&text);
+            lex_everything(&text.replace('\n', "\r\n"));
+        }
+    }
+
+    #[test]
+    fn invariants_hold_on_generated_programs() {
+        lex_everything(&generate_program(ProgramShape::new(5, 10)));
+    }
+
+    #[test]
+    fn invariants_hold_on_garbage() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        lex_everything(&String::from_utf8_lossy(&bytes));
+        lex_everything("\"\\m\\\" '\\m' '' 10. 10.x \"\0\" 'é' \r\n\r\r\n");
+    }
+}
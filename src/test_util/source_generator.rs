@@ -0,0 +1,337 @@
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::lexer::lexer_impls::identifiers::keyword_token;
+
+static PRIMITIVE_TYPES: &[&str] = &["u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64", "f32", "f64"];
+static QUALIFIERS: &[&str] = &["", "const ", "mut ", "anymut ", "static const ", "runtime mut "];
+static BINARY_OPERATORS: &[&str] = &["+", "-", "*", "/", "%", "&", "|", "^", "<<", ">>"];
+static COMPOUND_OPERATORS: &[&str] = &["=", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "<<=", ">>="];
+static STRING_WORDS: &[&str] = &["hello", "mumbo", "lexer", "benchmark", "quit smoking", "nyan", "rodata", "größe"];
+static ESCAPES: &[&str] = &["\\n", "\\t", "\\r", "\\0", "\\\\", "\\\""];
+static IDENTIFIER_SYLLABLES: &[&str] = &["ba", "ko", "ri", "len", "ptr", "buf", "idx", "tmp", "val", "node", "x", "_"];
+static COMMENTS: &[&str] = &["TODO: handle overflow", "safety: checked above", "", "see `function0`", "ünïcode"];
+
+/// describes the shape of a program produced by [`generate_program`].
+///
+/// the defaults produce a program of roughly 100KB. scale `structs` and
+/// `functions` up for throughput benchmarks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProgramShape {
+    pub structs: usize,
+    pub fields_per_struct: usize,
+    pub functions: usize,
+    pub params_per_function: usize,
+    pub statements_per_block: usize,
+    /// how many `{ ... };` blocks deep function bodies go. 0 means flat bodies.
+    pub block_depth: usize,
+    /// different seeds give different programs of the same shape.
+    pub seed: u64,
+}
+
+impl ProgramShape {
+    #[inline]
+    pub const fn new(structs: usize, functions: usize) -> Self {
+        ProgramShape {
+            structs,
+            fields_per_struct: 6,
+            functions,
+            params_per_function: 3,
+            statements_per_block: 8,
+            block_depth: 2,
+            seed: 0,
+        }
+    }
+
+    #[inline]
+    pub const fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+impl Default for ProgramShape {
+    #[inline]
+    fn default() -> Self {
+        ProgramShape::new(50, 100)
+    }
+}
+
+/// generates a program made of `shape.structs` struct declarations followed by
+/// `shape.functions` functions with bodies, nested blocks, comments and mixed literals.
+/// braces and parentheses are balanced, locals get random identifiers and string and
+/// character literals contain escapes and non-ascii text.
+///
+/// the output only uses syntax the lexer accepts and is deterministic for a given shape,
+/// including its seed.
+pub fn generate_program(shape: ProgramShape) -> String {
+    let mut generator = ProgramGenerator {
+        rng: SplitMix64(shape.seed),
+        out: String::new(),
+        shape,
+    };
+
+    for index in 0..shape.structs {
+        generator.struct_decl(index);
+    }
+
+    for index in 0..shape.functions {
+        generator.fn_decl(index);
+    }
+
+    generator.out
+}
+
+/// small and stable across versions, unlike an external rng, so a seed always means the
+/// same program.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+struct ProgramGenerator {
+    rng: SplitMix64,
+    out: String,
+    shape: ProgramShape,
+}
+
+impl ProgramGenerator {
+    fn below(&mut self, max: usize) -> usize {
+        (self.rng.next_u64() % max as u64) as usize
+    }
+
+    /// one or more syllables, retried until it is not a keyword.
+    fn identifier(&mut self) {
+        loop {
+            let start = self.out.len();
+            for _ in 0..1 + self.below(3) {
+                let syllable = self.pick(IDENTIFIER_SYLLABLES);
+                self.out.push_str(syllable);
+            }
+            if self.below(3) == 0 {
+                let suffix = self.below(100);
+                let _ = write!(self.out, "{}", suffix);
+            }
+            if keyword_token(&self.out.as_bytes()[start..]).is_none() {
+                return;
+            }
+            self.out.truncate(start);
+        }
+    }
+
+    fn string_literal(&mut self) {
+        self.out.push('"');
+        for word in 0..1 + self.below(4) {
+            if word != 0 {
+                self.out.push(' ');
+            }
+            let word = self.pick(STRING_WORDS);
+            self.out.push_str(word);
+            if self.below(3) == 0 {
+                let escape = self.pick(ESCAPES);
+                self.out.push_str(escape);
+            }
+        }
+        self.out.push('"');
+    }
+
+    fn char_literal(&mut self) {
+        match self.below(6) {
+            0 => {
+                let escape = self.pick(&["\\n", "\\t", "\\0", "\\\\", "\\'"]);
+                let _ = write!(self.out, "'{}'", escape);
+            }
+            1 => self.out.push_str("'é'"),
+            _ => {
+                let byte = b'a' + self.below(26) as u8;
+                let _ = write!(self.out, "'{}'", byte as char);
+            }
+        }
+    }
+
+    fn pick(&mut self, items: &[&'static str]) -> &'static str {
+        items[self.below(items.len())]
+    }
+
+    fn indent(&mut self, depth: usize) {
+        for _ in 0..depth {
+            self.out.push_str("    ");
+        }
+    }
+
+    fn struct_decl(&mut self, index: usize) {
+        let _ = writeln!(self.out, "struct Struct{} {{", index);
+        for field in 0..self.shape.fields_per_struct {
+            let ty = self.pick(PRIMITIVE_TYPES);
+            let _ = writeln!(self.out, "    field{}: {},", field, ty);
+        }
+        self.out.push_str("};\n\n");
+    }
+
+    fn fn_decl(&mut self, index: usize) {
+        let _ = write!(self.out, "fn function{}(", index);
+        for param in 0..self.shape.params_per_function {
+            if param != 0 {
+                self.out.push_str(", ");
+            }
+            let qualifier = self.pick(&QUALIFIERS[..3]);
+            let ty = self.pick(PRIMITIVE_TYPES);
+            let _ = write!(self.out, "let param{}: {}{}", param, qualifier, ty);
+        }
+        let ty = self.pick(PRIMITIVE_TYPES);
+        let _ = writeln!(self.out, ") -> const {} {{", ty);
+
+        self.block_body(1, self.shape.block_depth);
+
+        self.indent(1);
+        self.out.push_str("return ");
+        self.expr(2);
+        self.out.push_str(";\n}\n\n");
+    }
+
+    fn block_body(&mut self, depth: usize, nesting_left: usize) {
+        for _ in 0..self.shape.statements_per_block {
+            self.indent(depth);
+            if self.below(8) == 0 {
+                let comment = self.pick(COMMENTS);
+                let _ = writeln!(self.out, "// {}", comment);
+                self.indent(depth);
+            }
+            match self.below(4) {
+                0 => {
+                    self.identifier();
+                    let op = self.pick(COMPOUND_OPERATORS);
+                    let _ = write!(self.out, " {} ", op);
+                    self.expr(2);
+                }
+                1 => {
+                    self.out.push_str("let ");
+                    self.identifier();
+                    self.out.push_str(": const *static const u8 = ");
+                    self.string_literal();
+                }
+                _ => {
+                    self.out.push_str("let ");
+                    self.identifier();
+                    let qualifier = self.pick(QUALIFIERS);
+                    let ty = self.pick(PRIMITIVE_TYPES);
+                    let _ = write!(self.out, ": {}{} = ", qualifier, ty);
+                    self.expr(3);
+                }
+            }
+            self.out.push_str(";\n");
+        }
+
+        if nesting_left != 0 {
+            self.indent(depth);
+            self.out.push_str("{\n");
+            self.block_body(depth + 1, nesting_left - 1);
+            self.indent(depth);
+            self.out.push_str("};\n");
+        }
+    }
+
+    fn expr(&mut self, terms: usize) {
+        for term in 0..terms {
+            if term != 0 {
+                let op = self.pick(BINARY_OPERATORS);
+                let _ = write!(self.out, " {} ", op);
+            }
+            match self.below(7) {
+                0 => {
+                    let param = self.below(self.shape.params_per_function.max(1));
+                    let _ = write!(self.out, "param{}", param);
+                }
+                1 => {
+                    let (whole, fraction) = (self.below(1000), self.below(100000));
+                    let _ = write!(self.out, "{}.{}", whole, fraction);
+                }
+                2 => self.char_literal(),
+                3 => self.identifier(),
+                // nesting stays shallow since every level has fewer terms
+                4 if terms > 1 => {
+                    self.out.push('(');
+                    self.expr(terms - 1);
+                    self.out.push(')');
+                }
+                _ => {
+                    let integer = self.below(100000);
+                    let _ = write!(self.out, "{}", integer);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ProgramShape, generate_program};
+    use crate::{
+        ast::arena::AstArena,
+        lexer::Lexer,
+        parser::parse,
+        source_code::SourceCode,
+        types::Token,
+    };
+
+    #[test]
+    fn generated_program_lexes_cleanly() {
+        let program = generate_program(ProgramShape::default());
+        let mut lexer = Lexer::new(SourceCode::new(&program));
+        let mut structs = 0;
+        let mut functions = 0;
+        loop {
+            match lexer.lex_single_token() {
+                Ok(Token::KwAdtStruct) => structs += 1,
+                Ok(Token::KwFn) => functions += 1,
+                Ok(_) => {}
+                Err(e) if e.is_eof() => break,
+                Err(e) => panic!("lexer error: {:?}\n\t{}", e, lexer.get_lexer_debug_state()),
+            }
+        }
+
+        assert_eq!(structs, ProgramShape::default().structs);
+        assert_eq!(functions, ProgramShape::default().functions);
+    }
+
+    #[test]
+    fn generated_programs_parse_cleanly() {
+        for seed in 0..50 {
+            let program = generate_program(ProgramShape::new(5, 10).with_seed(seed));
+            let arena = AstArena::new();
+            let parsed = parse(&program, &arena);
+            assert!(parsed.lexer_errors.is_empty(), "seed {}: {:?}", seed, parsed.lexer_errors);
+            assert!(parsed.errors.is_empty(), "seed {}: {:?}", seed, parsed.errors);
+        }
+    }
+
+    #[test]
+    fn generation_is_deterministic() {
+        let shape = ProgramShape {
+            block_depth: 4,
+            ..ProgramShape::new(3, 5)
+        };
+        assert_eq!(generate_program(shape), generate_program(shape));
+        assert_ne!(generate_program(shape), generate_program(ProgramShape::new(3, 6)));
+        assert_eq!(generate_program(shape.with_seed(7)), generate_program(shape.with_seed(7)));
+        assert_ne!(generate_program(shape), generate_program(shape.with_seed(7)));
+    }
+
+    #[test]
+    fn programs_cover_escapes_comments_and_nesting() {
+        let program = generate_program(ProgramShape::new(0, 20).with_seed(3));
+        for needle in ["\\n", "\\\"", "'\\", "'é'", "// ", "(", "{\n"] {
+            assert!(program.contains(needle), "{needle:?} missing");
+        }
+        let count = |c| program.matches(c).count();
+        assert_eq!(count('('), count(')'));
+        assert_eq!(count('{'), count('}'));
+    }
+}
@@ -0,0 +1,114 @@
+use std::io::{self, Write};
+
+use crate::lexer::Lexer;
+use crate::source_code::SourceCode;
+use crate::types::{Span, Spanned};
+
+/// what a finished emitter run saw.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct EmitSummary {
+    pub tokens: usize,
+    pub errors: usize,
+}
+
+/// lexes `source` and writes one JSON object per token to `out` as it goes, never
+/// holding more than the current token in memory.
+///
+/// tokens look like `{"token":"LitStr","start":4,"end":9,"literal":"abc"}`, where
+/// `literal` is only present for literal tokens. lexer errors are written as
+/// `{"error":"InvalidCharacter","code":"E0004","start":4,"end":5}` and lexing continues after them.
+pub fn emit_jsonl<W: Write>(source: SourceCode<'_>, mut out: W) -> io::Result<EmitSummary> {
+    let mut lexer = Lexer::new(source);
+    let mut summary = EmitSummary::default();
+
+    loop {
+        match lexer.lex_single_token_spanned() {
+            Ok(Spanned { value: token, span }) => {
+                summary.tokens += 1;
+                write!(out, "{{\"token\":\"{:?}\",\"start\":{},\"end\":{}", token, span.start, span.end)?;
+                if let Ok(literal) = lexer.extract_literal() {
+                    out.write_all(b",\"literal\":")?;
+                    write_json_string(&mut out, literal)?;
+                }
+                out.write_all(b"}\n")?;
+            }
+            Err(e) if e.is_eof() => break,
+            Err(e) => {
+                summary.errors += 1;
+                out.write_all(b"{\"error\":")?;
+                write_json_string(&mut out, format!("{:?}", e.kind).as_bytes())?;
+                let Span { start, end } = e.span;
+                writeln!(out, ",\"code\":\"{}\",\"start\":{},\"end\":{}}}", e.code(), start, end)?;
+            }
+        }
+    }
+
+    out.flush()?;
+    Ok(summary)
+}
+
+/// writes `bytes` as a quoted JSON string. bytes come from a `&str`, so only
+/// quotes, backslashes and control characters need escaping.
+fn write_json_string<W: Write>(out: &mut W, bytes: &[u8]) -> io::Result<()> {
+    out.write_all(b"\"")?;
+    let mut clean_from = 0;
+    for (index, &byte) in bytes.iter().enumerate() {
+        let escaped: &[u8] = match byte {
+            b'"' => b"\\\"",
+            b'\\' => b"\\\\",
+            b'\n' => b"\\n",
+            b'\r' => b"\\r",
+            b'\t' => b"\\t",
+            c if c < 0x20 || c == 0x7f => {
+                out.write_all(&bytes[clean_from..index])?;
+                write!(out, "\\u{:04x}", c)?;
+                clean_from = index + 1;
+                continue;
+            }
+            _ => continue,
+        };
+        out.write_all(&bytes[clean_from..index])?;
+        out.write_all(escaped)?;
+        clean_from = index + 1;
+    }
+    out.write_all(&bytes[clean_from..])?;
+    out.write_all(b"\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EmitSummary, emit_jsonl};
+    use crate::source_code::SourceCode;
+
+    #[test]
+    fn emits_one_line_per_token() {
+        let mut out = vec![];
+        let summary = emit_jsonl(SourceCode::new("let s = \"a\\\"\\\\b\";\n$ x"), &mut out).unwrap();
+        assert_eq!(summary, EmitSummary { tokens: 6, errors: 1 });
+
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                r#"{"token":"KwLet","start":0,"end":3}"#,
+                r#"{"token":"LitIdentifier","start":4,"end":5,"literal":"s"}"#,
+                r#"{"token":"PuncEq","start":6,"end":7}"#,
+                r#"{"token":"LitStr","start":8,"end":16,"literal":"a\\\"\\\\b"}"#,
+                r#"{"token":"PuncSemi","start":16,"end":17}"#,
+                r#"{"error":"InvalidCharacter","code":"E0004","start":18,"end":19}"#,
+                r#"{"token":"LitIdentifier","start":20,"end":21,"literal":"x"}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes_control_characters() {
+        let mut out = vec![];
+        emit_jsonl(SourceCode::new("\"\x01\t\""), &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"token\":\"LitStr\",\"start\":0,\"end\":4,\"literal\":\"\\u0001\\t\"}\n"
+        );
+    }
+}
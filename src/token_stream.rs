@@ -0,0 +1,243 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use crate::lexer::{Lexer, LexerError};
+use crate::types::{Span, Spanned, Token};
+
+/// a token together with its span and, for literal tokens, the extracted literal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LexedToken<'source> {
+    pub token: Token,
+    pub span: Span,
+    pub literal: Option<&'source [u8]>,
+}
+
+/// where a `TokenStream` gets its tokens from: the streaming `Lexer`, which lexes as the parser
+/// asks for tokens, or a `TokenCursor` over a `TokenBuffer` lexed up front.
+pub trait TokenSource<'source> {
+    /// the next token or lexer error, `None` at the end of input.
+    fn next_token(&mut self) -> Option<Result<LexedToken<'source>, LexerError>>;
+
+    /// the text the spans of the tokens point into, if there is one. the parser looks at
+    /// what is between tokens with it, like whether a line ends there.
+    #[inline]
+    fn text(&self) -> Option<&'source str> {
+        None
+    }
+}
+
+impl<'source> TokenSource<'source> for Lexer<'source> {
+    #[inline]
+    fn next_token(&mut self) -> Option<Result<LexedToken<'source>, LexerError>> {
+        match self.lex_single_token_spanned() {
+            Ok(Spanned { value, span }) => Some(Ok(LexedToken {
+                token: value,
+                span,
+                literal: self.extract_literal().ok(),
+            })),
+            Err(e) if e.is_eof() => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    #[inline]
+    fn text(&self) -> Option<&'source str> {
+        Some(self.source().as_str())
+    }
+}
+
+/// `TokenStream::expect` found something else. `found` is `None` at the end of input.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UnexpectedToken {
+    pub expected: Token,
+    pub found: Option<Spanned<Token>>,
+}
+
+/// buffered token stream over a `TokenSource` with arbitrary lookahead.
+///
+/// lexer errors are skipped over and collected into `errors()`, so the stream only
+/// ever contains well-formed tokens.
+#[derive(Debug, Clone)]
+pub struct TokenStream<'source, S = Lexer<'source>> {
+    source: S,
+    buffer: VecDeque<LexedToken<'source>>,
+    errors: Vec<LexerError>,
+    at_eof: bool,
+    /// end of the last token handed out by `next`, used to place end-of-input spans.
+    last_end: usize,
+}
+
+impl<'source, S: TokenSource<'source>> TokenStream<'source, S> {
+    #[inline]
+    pub fn new(source: S) -> Self {
+        TokenStream {
+            source,
+            buffer: VecDeque::new(),
+            errors: Vec::new(),
+            at_eof: false,
+            last_end: 0,
+        }
+    }
+
+    /// lexes until `n + 1` tokens are buffered or the end is reached.
+    fn fill(&mut self, n: usize) {
+        while self.buffer.len() <= n && !self.at_eof {
+            match self.source.next_token() {
+                Some(Ok(token)) => self.buffer.push_back(token),
+                Some(Err(e)) if e.is_limit_exceeded() => {
+                    self.errors.push(e);
+                    self.at_eof = true;
+                }
+                Some(Err(e)) => self.errors.push(e),
+                None => self.at_eof = true,
+            }
+        }
+    }
+
+    /// the token `n` positions ahead without consuming anything, `peek(0)` is the next token.
+    #[inline]
+    pub fn peek(&mut self, n: usize) -> Option<&LexedToken<'source>> {
+        self.fill(n);
+        self.buffer.get(n)
+    }
+
+    /// kind of the token `n` positions ahead.
+    #[inline]
+    pub fn peek_token(&mut self, n: usize) -> Option<Token> {
+        self.peek(n).map(|t| t.token)
+    }
+
+    #[inline]
+    pub fn at(&mut self, token: Token) -> bool {
+        self.peek_token(0) == Some(token)
+    }
+
+    #[inline]
+    pub fn is_at_end(&mut self) -> bool {
+        self.peek(0).is_none()
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    #[inline]
+    pub fn next(&mut self) -> Option<LexedToken<'source>> {
+        self.fill(0);
+        let token = self.buffer.pop_front()?;
+        self.last_end = token.span.end;
+        Some(token)
+    }
+
+    /// consumes the next token if it is `token`.
+    #[inline]
+    pub fn eat(&mut self, token: Token) -> Option<LexedToken<'source>> {
+        if self.at(token) { self.next() } else { None }
+    }
+
+    /// consumes the next token, failing without consuming if it isn't `token`.
+    #[inline]
+    pub fn expect(&mut self, token: Token) -> Result<LexedToken<'source>, UnexpectedToken> {
+        match self.peek(0) {
+            Some(t) if t.token == token => Ok(self.next().unwrap()),
+            found => Err(UnexpectedToken {
+                expected: token,
+                found: found.map(|t| Spanned::new(t.token, t.span)),
+            }),
+        }
+    }
+
+    /// span of the next token, or an empty span just after the last consumed token at the end.
+    #[inline]
+    pub fn peek_span(&mut self) -> Span {
+        let last_end = self.last_end;
+        match self.peek(0) {
+            Some(t) => t.span,
+            None => Span::new(last_end, last_end),
+        }
+    }
+
+    /// whether the text between the last consumed token and the next one has a line break.
+    /// `false` at the end of input and for sources without text, see `TokenSource::text`.
+    pub fn at_line_start(&mut self) -> bool {
+        let start = self.last_end;
+        let Some(end) = self.peek(0).map(|t| t.span.start) else { return false };
+        let gap = self.source.text().and_then(|text| text.get(start..end));
+        gap.is_some_and(|gap| gap.contains('\n'))
+    }
+
+    /// end of the most recently consumed token.
+    #[inline]
+    pub const fn last_end(&self) -> usize {
+        self.last_end
+    }
+
+    #[inline]
+    pub fn errors(&self) -> &[LexerError] {
+        &self.errors
+    }
+
+    #[inline]
+    pub fn take_errors(&mut self) -> Vec<LexerError> {
+        core::mem::take(&mut self.errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TokenStream, UnexpectedToken};
+    use crate::lexer::{ErrorKind, Lexer, LexerError};
+    use crate::source_code::SourceCode;
+    use crate::types::{Span, Spanned, Token};
+
+    fn stream(text: &str) -> TokenStream<'_> {
+        TokenStream::new(Lexer::new(SourceCode::new(text)))
+    }
+
+    #[test]
+    fn lookahead_does_not_consume() {
+        let mut s = stream("let x = 10;");
+        assert_eq!(s.peek_token(3), Some(Token::LitInteger));
+        assert_eq!(s.peek(3).unwrap().literal, Some(&b"10"[..]));
+        assert_eq!(s.peek_token(0), Some(Token::KwLet));
+        assert_eq!(s.peek_token(5), None);
+
+        assert_eq!(s.next().unwrap().token, Token::KwLet);
+        assert_eq!(s.peek(0).unwrap().literal, Some(&b"x"[..]));
+        assert_eq!(s.peek_token(2), Some(Token::LitInteger));
+    }
+
+    #[test]
+    fn expect_and_eat() {
+        let mut s = stream("fn f");
+        assert!(s.eat(Token::KwLet).is_none());
+        assert!(s.eat(Token::KwFn).is_some());
+        assert_eq!(
+            s.expect(Token::IndentLParen),
+            Err(UnexpectedToken {
+                expected: Token::IndentLParen,
+                found: Some(Spanned::new(Token::LitIdentifier, Span::new(3, 4))),
+            })
+        );
+        assert_eq!(s.expect(Token::LitIdentifier).unwrap().literal, Some(&b"f"[..]));
+        assert_eq!(s.peek_span(), Span::new(4, 4));
+        assert_eq!(
+            s.expect(Token::PuncSemi),
+            Err(UnexpectedToken {
+                expected: Token::PuncSemi,
+                found: None,
+            })
+        );
+        assert!(s.is_at_end());
+    }
+
+    #[test]
+    fn errors_are_collected_and_skipped() {
+        let mut s = stream("a $ b");
+        assert_eq!(s.peek_token(1), Some(Token::LitIdentifier));
+        assert_eq!(s.errors(), [LexerError::new(ErrorKind::InvalidCharacter, Span::new(2, 3))]);
+        assert_eq!(s.next().unwrap().literal, Some(&b"a"[..]));
+        assert_eq!(s.next().unwrap().literal, Some(&b"b"[..]));
+        assert!(s.next().is_none());
+        assert_eq!(s.take_errors().len(), 1);
+        assert!(s.errors().is_empty());
+    }
+}
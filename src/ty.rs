@@ -0,0 +1,599 @@
+//! the type universe shared by type checking, constant evaluation and code generation.
+//!
+//! every type is interned into a `TyTable` and referred to by its `TyId` from then on, two ids
+//! are equal exactly when their types are. the built-in types are interned by `TyTable::new`
+//! in a fixed order, so they have the same id in every table and are available as constants
+//! like `TyId::U8`.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::types::Token;
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntTy {
+    U8,
+    U16,
+    U32,
+    U64,
+    Usize,
+    I8,
+    I16,
+    I32,
+    I64,
+    Isize,
+}
+
+impl IntTy {
+    pub const ALL: &[IntTy] = &[
+        IntTy::U8,
+        IntTy::U16,
+        IntTy::U32,
+        IntTy::U64,
+        IntTy::Usize,
+        IntTy::I8,
+        IntTy::I16,
+        IntTy::I32,
+        IntTy::I64,
+        IntTy::Isize,
+    ];
+
+    #[inline]
+    pub const fn name(self) -> &'static str {
+        match self {
+            IntTy::U8 => "u8",
+            IntTy::U16 => "u16",
+            IntTy::U32 => "u32",
+            IntTy::U64 => "u64",
+            IntTy::Usize => "usize",
+            IntTy::I8 => "i8",
+            IntTy::I16 => "i16",
+            IntTy::I32 => "i32",
+            IntTy::I64 => "i64",
+            IntTy::Isize => "isize",
+        }
+    }
+
+    #[inline]
+    pub const fn is_signed(self) -> bool {
+        matches!(self, IntTy::I8 | IntTy::I16 | IntTy::I32 | IntTy::I64 | IntTy::Isize)
+    }
+
+    /// the width in bits, `usize` and `isize` are 64 bits wide.
+    #[inline]
+    pub const fn bits(self) -> u32 {
+        match self {
+            IntTy::U8 | IntTy::I8 => 8,
+            IntTy::U16 | IntTy::I16 => 16,
+            IntTy::U32 | IntTy::I32 => 32,
+            IntTy::U64 | IntTy::I64 | IntTy::Usize | IntTy::Isize => 64,
+        }
+    }
+
+    /// the largest value of the type.
+    #[inline]
+    pub const fn max(self) -> u128 {
+        let bits = if self.is_signed() { self.bits() - 1 } else { self.bits() };
+        (1 << bits) - 1
+    }
+
+    /// the smallest value of the type.
+    #[inline]
+    pub const fn min(self) -> i128 {
+        if self.is_signed() { -(1 << (self.bits() - 1)) } else { 0 }
+    }
+
+    #[inline]
+    pub const fn contains(self, value: i128) -> bool {
+        self.min() <= value && value <= self.max() as i128
+    }
+
+    /// whether every value of `other` is a value of `self`.
+    #[inline]
+    pub const fn holds(self, other: IntTy) -> bool {
+        self.min() <= other.min() && self.max() >= other.max()
+    }
+
+    /// `value` truncated to the bits of the type, the way a cast converts integers.
+    #[inline]
+    pub const fn wrap(self, value: i128) -> i128 {
+        let bits = self.bits();
+        let truncated = (value as u128) & ((1u128 << bits) - 1);
+        if self.is_signed() && truncated >> (bits - 1) == 1 {
+            truncated as i128 - (1i128 << bits)
+        } else {
+            truncated as i128
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FloatTy {
+    F32,
+    F64,
+}
+
+impl FloatTy {
+    #[inline]
+    pub const fn name(self) -> &'static str {
+        match self {
+            FloatTy::F32 => "f32",
+            FloatTy::F64 => "f64",
+        }
+    }
+
+    /// the bits of precision, every integer up to `2^digits` converts exactly.
+    #[inline]
+    pub const fn digits(self) -> u32 {
+        match self {
+            FloatTy::F32 => 24,
+            FloatTy::F64 => 53,
+        }
+    }
+}
+
+/// how a place may be written, from its `mut`, `const` or `anymut` qualifier.
+///
+/// `mut` and `anymut` places can both be assigned, they differ in aliasing. a `mut` place only
+/// changes through itself or a `*mut` pointer to it, an `anymut` one may be changed through
+/// any number of pointers at any time, so nothing can assume it kept its value between two
+/// reads. a `*anymut` pointer can't become a `*mut` one for that reason, it would promise
+/// exclusive access nobody checked. every pointer can give up write access, `*mut u8` is
+/// usable as `*u8`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mutability {
+    /// no qualifier, the initializer is the only write.
+    Immutable,
+    Const,
+    Mut,
+    Anymut,
+}
+
+impl Mutability {
+    #[inline]
+    pub const fn is_writable(self) -> bool {
+        matches!(self, Mutability::Mut | Mutability::Anymut)
+    }
+
+    /// whether a pointer to a place of this mutability can be used as a pointer to `target`.
+    #[inline]
+    pub const fn coerces_to(self, target: Mutability) -> bool {
+        match target {
+            Mutability::Immutable | Mutability::Const => true,
+            Mutability::Mut => matches!(self, Mutability::Mut),
+            Mutability::Anymut => self.is_writable(),
+        }
+    }
+
+    #[inline]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Mutability::Immutable => "immutable",
+            Mutability::Const => "const",
+            Mutability::Mut => "mut",
+            Mutability::Anymut => "anymut",
+        }
+    }
+
+    /// the qualifier as written in source, empty for `Immutable`.
+    #[inline]
+    pub const fn prefix(self) -> &'static str {
+        match self {
+            Mutability::Immutable => "",
+            Mutability::Const => "const ",
+            Mutability::Mut => "mut ",
+            Mutability::Anymut => "anymut ",
+        }
+    }
+}
+
+/// a type interned into a `TyTable`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TyId(u32);
+
+impl TyId {
+    pub const U8: TyId = TyId::int(IntTy::U8);
+    pub const U16: TyId = TyId::int(IntTy::U16);
+    pub const U32: TyId = TyId::int(IntTy::U32);
+    pub const U64: TyId = TyId::int(IntTy::U64);
+    pub const USIZE: TyId = TyId::int(IntTy::Usize);
+    pub const I8: TyId = TyId::int(IntTy::I8);
+    pub const I16: TyId = TyId::int(IntTy::I16);
+    pub const I32: TyId = TyId::int(IntTy::I32);
+    pub const I64: TyId = TyId::int(IntTy::I64);
+    pub const ISIZE: TyId = TyId::int(IntTy::Isize);
+    pub const F32: TyId = TyId::float(FloatTy::F32);
+    pub const F64: TyId = TyId::float(FloatTy::F64);
+    pub const BOOL: TyId = TyId(12);
+    pub const LITERAL: TyId = TyId(13);
+    pub const TYPE: TyId = TyId(14);
+    /// `()`, what statements and blocks without a value evaluate to.
+    pub const UNIT: TyId = TyId(15);
+    pub const NEVER: TyId = TyId(16);
+    pub const ERROR: TyId = TyId(17);
+    pub const LIT_INTEGER: TyId = TyId(18);
+    pub const LIT_FLOAT: TyId = TyId(19);
+    pub const LIT_STR: TyId = TyId(20);
+    pub const LIT_CHAR: TyId = TyId(21);
+    pub const LIT_BOOL: TyId = TyId(22);
+    pub const LIT_UNINIT: TyId = TyId(23);
+
+    /// how many types `TyTable::new` interns.
+    const BUILTINS: u32 = 24;
+
+    #[inline]
+    pub const fn int(int: IntTy) -> TyId {
+        TyId(int as u32)
+    }
+
+    #[inline]
+    pub const fn float(float: FloatTy) -> TyId {
+        TyId(IntTy::ALL.len() as u32 + float as u32)
+    }
+
+    /// the integer type `self` is, `None` for every other type.
+    #[inline]
+    pub const fn as_int(self) -> Option<IntTy> {
+        if self.0 < IntTy::ALL.len() as u32 { Some(IntTy::ALL[self.0 as usize]) } else { None }
+    }
+
+    #[inline]
+    pub const fn as_float(self) -> Option<FloatTy> {
+        match self {
+            TyId::F32 => Some(FloatTy::F32),
+            TyId::F64 => Some(FloatTy::F64),
+            _ => None,
+        }
+    }
+
+    /// the type of a literal written with `token`, `None` for tokens that aren't literals.
+    #[inline]
+    pub const fn unsuffixed(token: Token) -> Option<TyId> {
+        let id = match token {
+            Token::LitInteger => TyId::LIT_INTEGER,
+            Token::LitFloat => TyId::LIT_FLOAT,
+            Token::LitStr => TyId::LIT_STR,
+            Token::LitChar => TyId::LIT_CHAR,
+            Token::LitBool => TyId::LIT_BOOL,
+            Token::LitUninit => TyId::LIT_UNINIT,
+            _ => return None,
+        };
+        Some(id)
+    }
+
+    /// `name` as a built-in type, `None` for anything else.
+    pub fn primitive(name: &str) -> Option<TyId> {
+        if let Some(int) = IntTy::ALL.iter().find(|int| int.name() == name) {
+            return Some(TyId::int(*int));
+        }
+        let id = match name {
+            "f32" => TyId::F32,
+            "f64" => TyId::F64,
+            "bool" => TyId::BOOL,
+            "literal" => TyId::LITERAL,
+            _ => return None,
+        };
+        Some(id)
+    }
+
+    #[inline]
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    #[inline]
+    pub const fn is_builtin(self) -> bool {
+        self.0 < TyId::BUILTINS
+    }
+
+    /// the numeric types don't depend on the table, they are all built in.
+    #[inline]
+    pub const fn is_numeric(self) -> bool {
+        self.0 <= TyId::F64.0 || matches!(self, TyId::LIT_INTEGER | TyId::LIT_FLOAT | TyId::LIT_CHAR | TyId::ERROR)
+    }
+
+    #[inline]
+    pub const fn is_integer(self) -> bool {
+        self.0 <= TyId::ISIZE.0 || matches!(self, TyId::LIT_INTEGER | TyId::LIT_CHAR | TyId::ERROR)
+    }
+
+    /// the type of a literal that hasn't been coerced to a concrete type yet.
+    #[inline]
+    pub const fn is_unsuffixed(self) -> bool {
+        self.0 >= TyId::LIT_INTEGER.0 && self.0 <= TyId::LIT_UNINIT.0
+    }
+}
+
+/// what a `TyId` stands for. qualifiers like `mut` are not part of a type.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TyKind<'source> {
+    Int(IntTy),
+    Float(FloatTy),
+    Bool,
+    /// the `literal` meta type, any literal can be stored in it.
+    Literal,
+    /// the type of types.
+    Type,
+    /// the type of a literal that hasn't been coerced yet, the token says which kind it is.
+    /// `uninit` coerces into anything.
+    Unsuffixed(Token),
+    /// `mutability` is the one of the places the pointer reaches.
+    Pointer { mutability: Mutability, pointee: TyId },
+    /// `len` is `None` when it isn't known.
+    Array { len: Option<u64>, elem: TyId },
+    /// `()` is the unit type.
+    Tuple(Vec<TyId>),
+    Fn { params: Vec<TyId>, ret: TyId },
+    /// a struct, union or enum by name.
+    Adt(&'source str),
+    /// the type of `return`, `break` and `continue`, which never produce a value.
+    Never,
+    /// something that was already reported, agrees with every other type.
+    Error,
+    /// the type of an unannotated binding that the checker hasn't fixed yet. `index` tells the
+    /// variables apart, `literal` is the unsuffixed type of the initializer.
+    Infer { index: u32, literal: TyId },
+}
+
+/// the interned types of one program, see the module docs.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TyTable<'source> {
+    kinds: Vec<TyKind<'source>>,
+}
+
+impl Default for TyTable<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'source> TyTable<'source> {
+    /// a table holding only the built-in types.
+    pub fn new() -> Self {
+        let mut kinds = Vec::with_capacity(TyId::BUILTINS as usize);
+        kinds.extend(IntTy::ALL.iter().map(|int| TyKind::Int(*int)));
+        kinds.extend([TyKind::Float(FloatTy::F32), TyKind::Float(FloatTy::F64)]);
+        kinds.extend([TyKind::Bool, TyKind::Literal, TyKind::Type, TyKind::Tuple(Vec::new())]);
+        kinds.extend([TyKind::Never, TyKind::Error]);
+        kinds.extend(
+            [Token::LitInteger, Token::LitFloat, Token::LitStr, Token::LitChar, Token::LitBool, Token::LitUninit]
+                .map(TyKind::Unsuffixed),
+        );
+        debug_assert_eq!(kinds.len(), TyId::BUILTINS as usize);
+        Self { kinds }
+    }
+
+    /// the id of `kind`, adding it to the table if it isn't there yet.
+    pub fn intern(&mut self, kind: TyKind<'source>) -> TyId {
+        // tables stay small enough for a linear search, there is a handful of types per program
+        match self.kinds.iter().position(|known| *known == kind) {
+            Some(index) => TyId(index as u32),
+            None => {
+                self.kinds.push(kind);
+                TyId(self.kinds.len() as u32 - 1)
+            }
+        }
+    }
+
+    #[inline]
+    pub fn pointer(&mut self, mutability: Mutability, pointee: TyId) -> TyId {
+        self.intern(TyKind::Pointer { mutability, pointee })
+    }
+
+    /// panics if `id` comes from a different table.
+    #[inline]
+    pub fn kind(&self, id: TyId) -> &TyKind<'source> {
+        &self.kinds[id.index()]
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.kinds.len()
+    }
+
+    /// never true, the built-in types are always there.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty()
+    }
+
+    /// whether a value of type `from` can be used where `to` is expected.
+    pub fn coerces_to(&self, from: TyId, to: TyId) -> bool {
+        if from == to {
+            return true;
+        }
+        match (self.kind(from), self.kind(to)) {
+            (TyKind::Error | TyKind::Never, _) | (_, TyKind::Error) => true,
+            (TyKind::Unsuffixed(_), TyKind::Literal) => true,
+            (TyKind::Unsuffixed(Token::LitUninit), _) => true,
+            (TyKind::Unsuffixed(Token::LitInteger), TyKind::Int(_) | TyKind::Float(_)) => true,
+            (TyKind::Unsuffixed(Token::LitFloat), TyKind::Float(_)) => true,
+            (TyKind::Unsuffixed(Token::LitChar), TyKind::Int(_)) => true,
+            (TyKind::Unsuffixed(Token::LitBool), TyKind::Bool) => true,
+            // a string literal is the bytes it is made of, which can't be written
+            (TyKind::Unsuffixed(Token::LitStr), TyKind::Array { elem, .. }) => matches!(*elem, TyId::U8 | TyId::ERROR),
+            (TyKind::Unsuffixed(Token::LitStr), TyKind::Pointer { mutability, pointee }) => {
+                !mutability.is_writable() && matches!(*pointee, TyId::U8 | TyId::ERROR)
+            }
+            (
+                TyKind::Pointer { mutability: from_mut, pointee: from },
+                TyKind::Pointer { mutability: to_mut, pointee: to },
+            ) => from_mut.coerces_to(*to_mut) && self.coerces_to(*from, *to),
+            (TyKind::Array { len: from_len, elem: from }, TyKind::Array { len: to_len, elem: to }) => {
+                (from_len.is_none() || to_len.is_none() || from_len == to_len) && self.coerces_to(*from, *to)
+            }
+            (TyKind::Tuple(from), TyKind::Tuple(to)) => {
+                from.len() == to.len() && from.iter().zip(to).all(|(from, to)| self.coerces_to(*from, *to))
+            }
+            _ => false,
+        }
+    }
+
+    /// the type both operands of an arithmetic or comparison operator take, an unsuffixed
+    /// literal adopts the type of the other side.
+    pub fn unify(&self, lhs: TyId, rhs: TyId) -> Option<TyId> {
+        match (lhs, rhs) {
+            _ if lhs == rhs => Some(lhs),
+            (TyId::ERROR | TyId::NEVER, other) | (other, TyId::ERROR | TyId::NEVER) => Some(other),
+            (literal, other) | (other, literal) if literal.is_unsuffixed() && self.coerces_to(literal, other) => {
+                Some(other)
+            }
+            _ if !lhs.is_unsuffixed() || !rhs.is_unsuffixed() => None,
+            (TyId::LIT_FLOAT, _) | (_, TyId::LIT_FLOAT) if lhs.is_numeric() && rhs.is_numeric() => {
+                Some(TyId::LIT_FLOAT)
+            }
+            _ if lhs.is_integer() && rhs.is_integer() => Some(TyId::LIT_INTEGER),
+            _ => None,
+        }
+    }
+
+    /// `id` the way it is written in source, for messages.
+    #[inline]
+    pub fn display(&self, id: TyId) -> TyDisplay<'_, 'source> {
+        TyDisplay { tys: self, id }
+    }
+}
+
+/// see `TyTable::display`.
+#[derive(Clone, Copy)]
+pub struct TyDisplay<'a, 'source> {
+    tys: &'a TyTable<'source>,
+    id: TyId,
+}
+
+impl TyDisplay<'_, '_> {
+    fn list(&self, f: &mut fmt::Formatter<'_>, ids: &[TyId]) -> fmt::Result {
+        for (index, id) in ids.iter().enumerate() {
+            if index > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", self.tys.display(*id))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TyDisplay<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tys = self.tys;
+        match tys.kind(self.id) {
+            TyKind::Int(int) => f.write_str(int.name()),
+            TyKind::Float(float) => f.write_str(float.name()),
+            TyKind::Bool => f.write_str("bool"),
+            TyKind::Literal => f.write_str("literal"),
+            TyKind::Type => f.write_str("type"),
+            TyKind::Unsuffixed(token) => f.write_str(token.source_repr()),
+            TyKind::Pointer { mutability, pointee } => write!(f, "*{}{}", mutability.prefix(), tys.display(*pointee)),
+            TyKind::Array { len: Some(len), elem } => write!(f, "[{} {}]", len, tys.display(*elem)),
+            TyKind::Array { len: None, elem } => write!(f, "[_ {}]", tys.display(*elem)),
+            TyKind::Tuple(elems) => {
+                f.write_str("(")?;
+                self.list(f, elems)?;
+                f.write_str(")")
+            }
+            TyKind::Fn { params, ret } => {
+                f.write_str("fn(")?;
+                self.list(f, params)?;
+                write!(f, ") -> {}", tys.display(*ret))
+            }
+            TyKind::Adt(name) => f.write_str(name),
+            TyKind::Never => f.write_str("!"),
+            TyKind::Error => f.write_str("{error}"),
+            TyKind::Infer { literal, .. } => write!(f, "{}", tys.display(*literal)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::{FloatTy, IntTy, Mutability, TyId, TyKind, TyTable};
+    use crate::types::Token;
+
+    #[test]
+    fn builtins_have_fixed_ids() {
+        let tys = TyTable::new();
+        for int in IntTy::ALL {
+            assert_eq!(tys.kind(TyId::int(*int)), &TyKind::Int(*int));
+            assert_eq!(TyId::primitive(int.name()), Some(TyId::int(*int)));
+            assert_eq!(TyId::int(*int).as_int(), Some(*int));
+        }
+        assert_eq!((IntTy::U8.max(), IntTy::I16.max(), IntTy::Usize.max()), (255, 32767, u64::MAX as u128));
+        assert_eq!((TyId::F32.as_float(), TyId::F32.as_int()), (Some(FloatTy::F32), None));
+        assert_eq!(tys.kind(TyId::F64), &TyKind::Float(FloatTy::F64));
+        assert_eq!(tys.kind(TyId::UNIT), &TyKind::Tuple(Vec::new()));
+        assert_eq!(tys.kind(TyId::ERROR), &TyKind::Error);
+        assert_eq!(tys.kind(TyId::LIT_UNINIT), &TyKind::Unsuffixed(Token::LitUninit));
+        assert_eq!(TyId::unsuffixed(Token::LitChar), Some(TyId::LIT_CHAR));
+        assert_eq!(TyId::unsuffixed(Token::KwLet), None);
+        assert_eq!(TyId::primitive("literal"), Some(TyId::LITERAL));
+        assert_eq!(TyId::primitive("type"), None);
+        assert!(TyId::LIT_UNINIT.is_builtin() && TyId::LIT_UNINIT.is_unsuffixed());
+        assert!(TyId::I64.is_integer() && !TyId::F32.is_integer() && TyId::F32.is_numeric());
+        assert!(!TyId::BOOL.is_numeric() && !TyId::UNIT.is_unsuffixed());
+    }
+
+    #[test]
+    fn integer_ranges() {
+        assert_eq!((IntTy::I8.min(), IntTy::I8.max(), IntTy::U16.min()), (-128, 127, 0));
+        assert!(IntTy::I16.holds(IntTy::U8) && IntTy::U64.holds(IntTy::Usize));
+        assert!(!IntTy::U64.holds(IntTy::I8) && !IntTy::I32.holds(IntTy::U32));
+        assert!(IntTy::I8.contains(-128) && !IntTy::U8.contains(256));
+        assert_eq!((IntTy::U8.wrap(300), IntTy::I8.wrap(200), IntTy::U16.wrap(-1)), (44, -56, 65535));
+    }
+
+    #[test]
+    fn types_are_interned_once() {
+        let mut tys = TyTable::new();
+        let len = tys.len();
+        assert_eq!(tys.intern(TyKind::Bool), TyId::BOOL);
+        let pointer = tys.pointer(Mutability::Immutable, TyId::U8);
+        assert!(!pointer.is_builtin());
+        assert_eq!(tys.intern(TyKind::Pointer { mutability: Mutability::Immutable, pointee: TyId::U8 }), pointer);
+        let array = tys.intern(TyKind::Array { len: None, elem: TyId::BOOL });
+        let fn_ty = tys.intern(TyKind::Fn { params: vec![array, pointer], ret: TyId::UNIT });
+        assert_eq!(tys.len(), len + 3);
+        assert_eq!(tys.display(fn_ty).to_string(), "fn([_ bool], *u8) -> ()");
+        assert_eq!(tys.display(TyId::LIT_FLOAT).to_string(), "{float}");
+    }
+
+    #[test]
+    fn literals_coerce_and_unify() {
+        let mut tys = TyTable::new();
+        assert!(tys.coerces_to(TyId::LIT_INTEGER, TyId::F32));
+        assert!(!tys.coerces_to(TyId::LIT_FLOAT, TyId::U8));
+        assert!(tys.coerces_to(TyId::LIT_CHAR, TyId::U8));
+        assert!(tys.coerces_to(TyId::LIT_UNINIT, TyId::UNIT));
+        assert!(tys.coerces_to(TyId::NEVER, TyId::BOOL));
+        let bytes = tys.intern(TyKind::Array { len: Some(3), elem: TyId::U8 });
+        let words = tys.intern(TyKind::Array { len: Some(3), elem: TyId::U16 });
+        assert!(tys.coerces_to(TyId::LIT_STR, bytes));
+        assert!(!tys.coerces_to(TyId::LIT_STR, words));
+        let unknown = tys.intern(TyKind::Array { len: None, elem: TyId::U8 });
+        assert!(tys.coerces_to(bytes, unknown));
+        let [read, write, shared] =
+            [Mutability::Const, Mutability::Mut, Mutability::Anymut].map(|access| tys.pointer(access, TyId::U8));
+        assert!(tys.coerces_to(TyId::LIT_STR, read) && !tys.coerces_to(TyId::LIT_STR, write));
+        assert!(tys.coerces_to(write, shared) && tys.coerces_to(shared, read));
+        assert!(!tys.coerces_to(shared, write) && !tys.coerces_to(read, shared));
+        assert_eq!(tys.display(shared).to_string(), "*anymut u8");
+
+        assert_eq!(tys.unify(TyId::U8, TyId::LIT_INTEGER), Some(TyId::U8));
+        assert_eq!(tys.unify(TyId::LIT_FLOAT, TyId::F64), Some(TyId::F64));
+        assert_eq!(tys.unify(TyId::LIT_CHAR, TyId::LIT_FLOAT), Some(TyId::LIT_FLOAT));
+        assert_eq!(tys.unify(TyId::LIT_CHAR, TyId::LIT_INTEGER), Some(TyId::LIT_INTEGER));
+        assert_eq!(tys.unify(TyId::ERROR, TyId::BOOL), Some(TyId::BOOL));
+        assert_eq!(tys.unify(TyId::U8, TyId::U16), None);
+        assert_eq!(tys.unify(TyId::U8, TyId::LIT_FLOAT), None);
+    }
+}
@@ -0,0 +1,1839 @@
+//! type checking: infers the type of every expression and checks it against the annotations,
+//! parameters and return types it has to agree with.
+//!
+//! names are looked up like `resolve` does, a name it can't find has the type `{error}` here,
+//! which agrees with everything so that it is only reported once. an imported name is `{error}`
+//! too, as a value and as a type: each file is checked on its own. literals have a type of
+//! their own that coerces into the primitives it can be written as, an integer literal only
+//! into the integer types that hold its value. types are interned into the `TyTable` that
+//! `check` returns, see `ty`.
+//!
+//! an unannotated `let` takes the type of its initializer. when that is a literal, the binding
+//! gets an inference variable that the first use needing a concrete type fixes, `let x = 1;
+//! let y: u8 = x;` makes `x` a `u8`. variables nothing fixes fall back to a default, `i32`,
+//! `f64`, `u8` for ascii characters and `u32` for the others and a byte array for strings,
+//! `uninit` has none and is reported as ambiguous. a binding annotated with `literal` stays
+//! polymorphic instead: every use coerces the literal on its own.
+//!
+//! every enum gets an integer type for its discriminants, the one its `#[repr(ty)]` names or
+//! else the smallest unsigned type that holds them all. a variant's discriminant is its
+//! position in the declaration, and casting an enum to an integer type is only allowed when
+//! the type holds the discriminant. `E::variant_count` is a `usize` and `E::ALL` a `[n E]`,
+//! see `EnumIntrinsic`.
+//!
+//! other casts convert between the integer and float types in any direction, from `bool` to an
+//! integer type, between pointers and between a pointer and `usize` or `isize`. a cast that
+//! may change the value, to a narrower integer type, from a float to an integer or from an
+//! integer wider than the precision of the float, is a `TypeWarning`. everything else, like
+//! an integer to `bool` or to an enum, is an error.
+//!
+//! assignments need a `mut` or `anymut` place, see `Mutability`. a binding without a
+//! qualifier is only written by its initializer, a field without one is as writable as the
+//! value it is part of and a place behind a pointer is as writable as the pointer says.
+//!
+//! strings only exist as literals at run time, so joining them with `+` and measuring them
+//! with `.len` is only allowed inside a `compiletime` block, which `const_eval` folds.
+//!
+//! a struct literal gives every field of the struct, a union literal exactly one member. a
+//! union member is only read as the operand of a `cast`, `u.bits cast u32`, which says what
+//! its bytes are taken as. writing a member, or a place inside one, needs no cast.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::ast::{
+    AdtDecl, AttrArg, BinaryOp, Block, BlockKind, EnumDecl, EnumIntrinsic, Expr, ExprKind, FieldInit, FnDecl, Ident,
+    Item, ItemKind, MatchArm, PatternKind, Qualifier, Stmt, StmtKind, TypeExpr, TypeKind, UnaryOp,
+};
+use crate::literal_value::{Literal, decode_char_literal, parse_u128};
+use crate::ty::{IntTy, Mutability, TyId, TyKind, TyTable};
+use crate::types::{Span, Spanned, Token};
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TypeErrorKind<'source> {
+    /// `because` is the annotation or signature `expected` comes from, if there is one.
+    Mismatch {
+        expected: TyId,
+        found: TyId,
+        because: Option<Span>,
+    },
+    /// a type name that is neither a primitive nor declared.
+    UnknownType(&'source str),
+    /// an operator applied to operands it isn't defined for.
+    InvalidOperands { op: &'static str, lhs: TyId, rhs: Option<TyId> },
+    NotCallable(TyId),
+    WrongArgumentCount { expected: usize, found: usize },
+    NoField { ty: TyId, field: &'source str },
+    NotIndexable(TyId),
+    /// a function with a return type whose body ends without a value.
+    MissingReturnValue { ret: TyId },
+    /// an unannotated binding whose type nothing decides.
+    AmbiguousType(&'source str),
+    /// an assignment to a place without `mut` or `anymut`. `declared` is the binding or field
+    /// the place gets that from, `None` behind a pointer.
+    AssignToImmutable { declared: Option<Span> },
+    /// an assignment to a `const` place, `declared` like for `AssignToImmutable`.
+    AssignToConst { declared: Option<Span> },
+    /// a pointer used where one with more access to its pointee is expected.
+    PointerMutability { expected: Mutability, found: Mutability },
+    /// a `#[repr]` that doesn't name one integer type.
+    InvalidRepr,
+    /// an enum with more variants than its `#[repr]` type can count.
+    TooManyVariants { repr: IntTy, count: usize },
+    /// an enum cast to something other than an integer type.
+    InvalidEnumCast { to: TyId },
+    /// an enum cast to an integer type too small for the discriminant, or for the largest one
+    /// when the variant isn't known.
+    DiscriminantTruncated { discriminant: u32, to: IntTy },
+    /// a struct literal without some of the fields of the struct, in order of declaration.
+    MissingFields { ty: TyId, fields: Vec<&'source str> },
+    /// a union literal that doesn't set exactly one member.
+    UnionLiteralMembers { ty: TyId, count: usize },
+    /// a union member read outside of a `cast`, see the module docs.
+    UnionRead { field: &'source str },
+    /// `name { ... }` where `name` is neither a struct nor a union.
+    NotAStruct(&'source str),
+    /// a cast between types that don't convert into each other, see the module docs.
+    InvalidCast { from: TyId, to: TyId },
+    /// an integer literal used where an integer type that can't hold it is expected.
+    LiteralOutOfRange { value: i128, to: IntTy },
+    /// a character literal used where an integer type that can't hold it is expected, `u8`
+    /// only holds ascii.
+    CharOutOfRange { value: char, to: IntTy },
+}
+
+impl TypeErrorKind<'_> {
+    /// stable identifier like `ParseErrorKind::code`, type checker codes start at `E0301`.
+    #[inline]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            TypeErrorKind::Mismatch { .. } => "E0301",
+            TypeErrorKind::UnknownType(_) => "E0302",
+            TypeErrorKind::InvalidOperands { .. } => "E0303",
+            TypeErrorKind::NotCallable(_) => "E0304",
+            TypeErrorKind::WrongArgumentCount { .. } => "E0305",
+            TypeErrorKind::NoField { .. } => "E0306",
+            TypeErrorKind::NotIndexable(_) => "E0307",
+            TypeErrorKind::MissingReturnValue { .. } => "E0308",
+            TypeErrorKind::AmbiguousType(_) => "E0309",
+            TypeErrorKind::AssignToImmutable { .. } => "E0310",
+            TypeErrorKind::AssignToConst { .. } => "E0311",
+            TypeErrorKind::PointerMutability { .. } => "E0312",
+            TypeErrorKind::InvalidRepr => "E0313",
+            TypeErrorKind::TooManyVariants { .. } => "E0314",
+            TypeErrorKind::InvalidEnumCast { .. } => "E0315",
+            TypeErrorKind::DiscriminantTruncated { .. } => "E0316",
+            TypeErrorKind::MissingFields { .. } => "E0317",
+            TypeErrorKind::UnionLiteralMembers { .. } => "E0318",
+            TypeErrorKind::UnionRead { .. } => "E0319",
+            TypeErrorKind::NotAStruct(_) => "E0320",
+            TypeErrorKind::InvalidCast { .. } => "E0321",
+            TypeErrorKind::LiteralOutOfRange { .. } => "E0322",
+            TypeErrorKind::CharOutOfRange { .. } => "E0323",
+        }
+    }
+
+    /// the error as a sentence, `tys` is the table of the `Checked` it comes from.
+    pub fn message(&self, tys: &TyTable<'_>) -> String {
+        match self {
+            TypeErrorKind::Mismatch { expected, found, .. } => {
+                format!("mismatched types: expected `{}`, found `{}`", tys.display(*expected), tys.display(*found))
+            }
+            TypeErrorKind::UnknownType(name) => format!("cannot find type `{}`", name),
+            TypeErrorKind::InvalidOperands { op, lhs, rhs: Some(rhs) } => {
+                format!("`{}` can't be applied to `{}` and `{}`", op, tys.display(*lhs), tys.display(*rhs))
+            }
+            TypeErrorKind::InvalidOperands { op, lhs, rhs: None } => {
+                format!("`{}` can't be applied to `{}`", op, tys.display(*lhs))
+            }
+            TypeErrorKind::NotCallable(ty) => format!("`{}` is not a function", tys.display(*ty)),
+            TypeErrorKind::WrongArgumentCount { expected, found } => {
+                format!("expected {} arguments, found {}", expected, found)
+            }
+            TypeErrorKind::NoField { ty, field } => format!("`{}` has no field `{}`", tys.display(*ty), field),
+            TypeErrorKind::NotIndexable(ty) => format!("`{}` can't be indexed", tys.display(*ty)),
+            TypeErrorKind::MissingReturnValue { ret } => {
+                format!("function returning `{}` ends without a value", tys.display(*ret))
+            }
+            TypeErrorKind::AmbiguousType(name) => format!("type annotations needed for `{}`", name),
+            TypeErrorKind::AssignToImmutable { .. } => "cannot assign to a place that isn't `mut` or `anymut`".into(),
+            TypeErrorKind::AssignToConst { .. } => "cannot assign to a `const` place".into(),
+            TypeErrorKind::PointerMutability { expected, found } => {
+                format!("mismatched pointer access: expected `{}`, found `{}`", expected.name(), found.name())
+            }
+            TypeErrorKind::InvalidRepr => "`repr` takes one integer type, like `#[repr(u8)]`".into(),
+            TypeErrorKind::TooManyVariants { repr, count } => {
+                format!("`{}` can't hold the discriminants of {} variants", repr.name(), count)
+            }
+            TypeErrorKind::InvalidEnumCast { to } => {
+                format!("an enum can only be cast to an integer type, not `{}`", tys.display(*to))
+            }
+            TypeErrorKind::DiscriminantTruncated { discriminant, to } => {
+                format!("discriminant {} doesn't fit in `{}`", discriminant, to.name())
+            }
+            TypeErrorKind::MissingFields { ty, fields } => {
+                let plural = if fields.len() == 1 { "" } else { "s" };
+                let fields = fields.iter().map(|field| format!("`{}`", field)).collect::<Vec<_>>().join(", ");
+                format!("missing field{} {} in literal of `{}`", plural, fields, tys.display(*ty))
+            }
+            TypeErrorKind::UnionLiteralMembers { ty, count } => {
+                format!("a literal of `{}` sets exactly one member, found {}", tys.display(*ty), count)
+            }
+            TypeErrorKind::UnionRead { field } => format!("reading union member `{}` needs an explicit `cast`", field),
+            TypeErrorKind::NotAStruct(name) => format!("`{}` is not a struct or union", name),
+            TypeErrorKind::InvalidCast { from, to } => {
+                format!("`{}` can't be cast to `{}`", tys.display(*from), tys.display(*to))
+            }
+            TypeErrorKind::LiteralOutOfRange { value, to } => {
+                format!("literal `{}` doesn't fit in `{}`", value, to.name())
+            }
+            TypeErrorKind::CharOutOfRange { value, to } => {
+                format!("character `{}` doesn't fit in `{}`", value.escape_debug(), to.name())
+            }
+        }
+    }
+}
+
+/// the span covers the expression or type that is wrong.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TypeError<'source> {
+    pub kind: TypeErrorKind<'source>,
+    pub span: Span,
+}
+
+impl TypeError<'_> {
+    #[inline]
+    pub const fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TypeWarningKind {
+    /// a cast that may change the value, see the module docs.
+    LossyCast { from: TyId, to: TyId },
+    /// an integer literal cast to a type that doesn't hold it, `wrapped` is what it becomes.
+    LiteralTruncated { value: i128, to: IntTy, wrapped: i128 },
+}
+
+impl TypeWarningKind {
+    /// like `TypeErrorKind::message`.
+    pub fn message(&self, tys: &TyTable<'_>) -> String {
+        match self {
+            TypeWarningKind::LossyCast { from, to } => {
+                format!("casting `{}` to `{}` may change the value", tys.display(*from), tys.display(*to))
+            }
+            TypeWarningKind::LiteralTruncated { value, to, wrapped } => {
+                format!("literal `{}` doesn't fit in `{}`, the cast makes it `{}`", value, to.name(), wrapped)
+            }
+        }
+    }
+}
+
+/// like `TypeError`, for code that is allowed but likely wrong.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TypeWarning {
+    pub kind: TypeWarningKind,
+    pub span: Span,
+}
+
+/// the outcome of `check`, errors are in the order they were found. `tys` holds every type
+/// the errors and bindings refer to.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Checked<'source> {
+    pub errors: Vec<TypeError<'source>>,
+    pub warnings: Vec<TypeWarning>,
+    /// the type of every `let` by the span of its name, in the order they were checked.
+    /// inferred types are already fixed or defaulted, an ambiguous one is `{error}`.
+    pub bindings: Vec<(Span, TyId)>,
+    /// the type of every function item by the span of its name.
+    pub fn_types: Vec<(Span, TyId)>,
+    /// the type of every expression by its span, sorted by span, see `expr_ty`. a literal keeps
+    /// its unsuffixed type, the coercion to where it is used isn't recorded.
+    pub expr_types: Vec<(Span, TyId)>,
+    /// every struct and union in the order of declaration.
+    pub structs: Vec<StructLayout<'source>>,
+    /// every enum in the order of declaration.
+    pub enums: Vec<EnumLayout<'source>>,
+    /// the discriminant of every variant used in an expression or pattern, by its span.
+    pub discriminants: Vec<(Span, u32)>,
+    /// every `EnumIntrinsic` by the span of its path, with the index into `enums` of the enum
+    /// it is about.
+    pub intrinsics: Vec<(Span, usize)>,
+    pub tys: TyTable<'source>,
+}
+
+/// the fields of a struct or the members of a union.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StructLayout<'source> {
+    pub name: Ident<'source>,
+    pub is_union: bool,
+    /// in order of declaration.
+    pub fields: Vec<(Ident<'source>, TyId)>,
+}
+
+/// how the values of an enum are represented.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EnumLayout<'source> {
+    pub name: Ident<'source>,
+    /// the type of the discriminants, see the module docs.
+    pub repr: IntTy,
+    /// in order of declaration, the discriminant of a variant is its index.
+    pub variants: Vec<Ident<'source>>,
+}
+
+impl Checked<'_> {
+    /// the type of the expression at `span`, `None` for spans that aren't an expression.
+    #[inline]
+    pub fn expr_ty(&self, span: Span) -> Option<TyId> {
+        let index = self.expr_types.binary_search_by_key(&span, |&(span, _)| span).ok()?;
+        Some(self.expr_types[index].1)
+    }
+}
+
+impl StructLayout<'_> {
+    /// the type of the field `name`.
+    #[inline]
+    pub fn field(&self, name: &str) -> Option<TyId> {
+        self.fields.iter().find(|(field, _)| field.name == name).map(|&(_, ty)| ty)
+    }
+}
+
+impl EnumLayout<'_> {
+    /// the discriminant of the variant `name`.
+    #[inline]
+    pub fn discriminant(&self, name: &str) -> Option<u32> {
+        self.variants.iter().position(|variant| variant.name == name).map(|index| index as u32)
+    }
+}
+
+/// type checks a whole program.
+pub fn check<'source>(stmts: &[Stmt<'source>]) -> Checked<'source> {
+    let mut checker = Checker {
+        tys: TyTable::new(),
+        scopes: Vec::new(),
+        adts: Vec::new(),
+        structs: Vec::new(),
+        enums: Vec::new(),
+        imports: Vec::new(),
+        discriminants: Vec::new(),
+        intrinsics: Vec::new(),
+        fns: Vec::new(),
+        loops: Vec::new(),
+        compiletime: 0,
+        vars: Vec::new(),
+        bindings: Vec::new(),
+        fn_types: Vec::new(),
+        expr_types: Vec::new(),
+        errors: Vec::new(),
+        warnings: Vec::new(),
+    };
+    checker.stmts(stmts);
+    checker.default_vars();
+    let bindings = checker.bindings.iter().map(|&(span, ty)| (span, checker.shallow(ty))).collect();
+    let mut expr_types: Vec<_> = checker.expr_types.iter().map(|&(span, ty)| (span, checker.shallow(ty))).collect();
+    expr_types.sort_by_key(|&(span, _)| span);
+    Checked {
+        errors: checker.errors,
+        warnings: checker.warnings,
+        bindings,
+        fn_types: checker.fn_types,
+        expr_types,
+        structs: checker.structs,
+        enums: checker.enums,
+        discriminants: checker.discriminants,
+        intrinsics: checker.intrinsics,
+        tys: checker.tys,
+    }
+}
+
+/// the type of an unannotated `let` with a literal initializer, see the module docs.
+struct InferVar<'source> {
+    name: &'source str,
+    span: Span,
+    /// what the variable becomes if nothing fixes it, `None` for `uninit`.
+    default: Option<TyId>,
+    fixed: Option<TyId>,
+}
+
+/// a name in scope, `span` is where it is declared.
+#[derive(Debug, Clone, Copy)]
+struct Binding<'source> {
+    name: &'source str,
+    ty: TyId,
+    mutability: Mutability,
+    span: Span,
+}
+
+/// what an assignment target is, `declared` like for `TypeErrorKind::AssignToImmutable`.
+struct Place {
+    ty: TyId,
+    mutability: Mutability,
+    declared: Option<Span>,
+}
+
+/// a struct or union in scope.
+#[derive(Clone, Copy)]
+struct Adt<'ast, 'source> {
+    decl: &'ast AdtDecl<'source>,
+    is_union: bool,
+}
+
+/// where a `return` jumps to, and the type it has to return.
+struct FnContext {
+    ret: TyId,
+    because: Option<Span>,
+}
+
+struct Checker<'ast, 'source> {
+    tys: TyTable<'source>,
+    /// the bindings of each block, innermost last. a function body starts a new list.
+    scopes: Vec<Vec<Binding<'source>>>,
+    /// structs and unions of the blocks entered so far.
+    adts: Vec<Adt<'ast, 'source>>,
+    structs: Vec<StructLayout<'source>>,
+    /// the enums of the blocks entered so far.
+    enums: Vec<EnumLayout<'source>>,
+    /// the names of every `import`, what they are is only known to the driver.
+    imports: Vec<&'source str>,
+    discriminants: Vec<(Span, u32)>,
+    intrinsics: Vec<(Span, usize)>,
+    fns: Vec<FnContext>,
+    /// the label of each enclosing loop and the type of its `break` values, innermost last. the
+    /// type of a `loop` is `None` until a `break` is seen.
+    loops: Vec<(Option<&'source str>, Option<TyId>)>,
+    /// how many `compiletime` blocks the checked expression is in.
+    compiletime: u32,
+    vars: Vec<InferVar<'source>>,
+    bindings: Vec<(Span, TyId)>,
+    fn_types: Vec<(Span, TyId)>,
+    expr_types: Vec<(Span, TyId)>,
+    errors: Vec<TypeError<'source>>,
+    warnings: Vec<TypeWarning>,
+}
+
+impl<'ast, 'source> Checker<'ast, 'source> {
+    /// the same type is lowered once for the signature and once for the body check, an
+    /// error is only kept the first time.
+    fn error(&mut self, kind: TypeErrorKind<'source>, span: Span) {
+        let error = TypeError { kind, span };
+        if !self.errors.contains(&error) {
+            self.errors.push(error);
+        }
+    }
+
+    fn warn(&mut self, kind: TypeWarningKind, span: Span) {
+        let warning = TypeWarning { kind, span };
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    /// reports `found` unless it coerces to `expected`, an inference variable on either side
+    /// is fixed to the other one.
+    fn expect(&mut self, found: TyId, expected: TyId, span: Span, because: Option<Span>) {
+        let (found, expected) = (self.shallow(found), self.shallow(expected));
+        let coerces = match (self.unfixed(found), self.unfixed(expected)) {
+            (Some((index, literal)), None) => self.fix(index, literal, expected),
+            (None, Some((index, literal))) => self.tys.coerces_to(found, literal) || self.fix(index, literal, found),
+            (Some((_, from)), Some((_, to))) => self.tys.coerces_to(from, to),
+            (None, None) => self.tys.coerces_to(found, expected),
+        };
+        if coerces {
+            return;
+        }
+        // tell a pointer that only lacks access apart from an unrelated type
+        if let TyKind::Pointer { mutability: from, pointee } = *self.tys.kind(found)
+            && let TyKind::Pointer { mutability: to, .. } = *self.tys.kind(expected)
+            && !from.coerces_to(to)
+        {
+            let relaxed = self.tys.pointer(to, pointee);
+            if self.tys.coerces_to(relaxed, expected) {
+                self.error(TypeErrorKind::PointerMutability { expected: to, found: from }, span);
+                return;
+            }
+        }
+        self.error(TypeErrorKind::Mismatch { expected, found, because }, span);
+    }
+
+    /// `expect` for the expression `value`, which also has to fit if it is an integer literal.
+    fn expect_value(&mut self, value: &Expr<'source>, found: TyId, expected: TyId, because: Option<Span>) {
+        self.expect(found, expected, value.span, because);
+        self.literal_fits(value, expected);
+    }
+
+    /// reports `value` if it is an integer or character literal that the integer type `ty`
+    /// can't hold.
+    fn literal_fits(&mut self, value: &Expr<'source>, ty: TyId) {
+        let Some(to) = self.shallow(ty).as_int() else { return };
+        if let Some(literal) = literal_char(value) {
+            if !char_fits(literal, to) {
+                self.error(TypeErrorKind::CharOutOfRange { value: literal, to }, value.span);
+            }
+        } else if let Some(literal) = literal_int(value)
+            && !to.contains(literal)
+        {
+            self.error(TypeErrorKind::LiteralOutOfRange { value: literal, to }, value.span);
+        }
+    }
+
+    /// a new inference variable for the binding `name` of `value`, which has the unsuffixed
+    /// type `literal`.
+    fn infer_var(&mut self, name: &'source str, span: Span, literal: TyId, value: &Expr<'source>) -> TyId {
+        let default = match literal {
+            TyId::LIT_INTEGER => Some(TyId::I32),
+            TyId::LIT_FLOAT => Some(TyId::F64),
+            // a `u32` holds any character, a `u8` only ascii
+            TyId::LIT_CHAR if literal_char(value).is_some_and(|c| !c.is_ascii()) => Some(TyId::U32),
+            TyId::LIT_CHAR => Some(TyId::U8),
+            TyId::LIT_BOOL => Some(TyId::BOOL),
+            TyId::LIT_STR => {
+                let len = string_len(value);
+                Some(self.tys.intern(TyKind::Array { len, elem: TyId::U8 }))
+            }
+            _ => None,
+        };
+        let index = self.vars.len() as u32;
+        self.vars.push(InferVar {
+            name,
+            span,
+            default,
+            fixed: None,
+        });
+        self.tys.intern(TyKind::Infer { index, literal })
+    }
+
+    /// `ty` with a fixed inference variable replaced by what it was fixed to.
+    fn shallow(&self, ty: TyId) -> TyId {
+        match *self.tys.kind(ty) {
+            TyKind::Infer { index, .. } => self.vars[index as usize].fixed.unwrap_or(ty),
+            _ => ty,
+        }
+    }
+
+    /// like `shallow`, but an unfixed variable is replaced by its literal type. for questions
+    /// that shouldn't fix anything, like whether `ty` is numeric.
+    fn probe(&self, ty: TyId) -> TyId {
+        match *self.tys.kind(ty) {
+            TyKind::Infer { index, literal } => self.vars[index as usize].fixed.unwrap_or(literal),
+            _ => ty,
+        }
+    }
+
+    /// the index and literal type of the inference variable `ty` if it isn't fixed yet.
+    fn unfixed(&self, ty: TyId) -> Option<(usize, TyId)> {
+        match *self.tys.kind(ty) {
+            TyKind::Infer { index, literal } if self.vars[index as usize].fixed.is_none() => {
+                Some((index as usize, literal))
+            }
+            _ => None,
+        }
+    }
+
+    /// fixes the variable `index` to `target` if its literal coerces to it. targets that are
+    /// no concrete type themselves, like another literal or `{error}`, leave it open.
+    fn fix(&mut self, index: usize, literal: TyId, target: TyId) -> bool {
+        if !self.tys.coerces_to(literal, target) {
+            return false;
+        }
+        if !target.is_unsuffixed() && !matches!(target, TyId::LITERAL | TyId::ERROR | TyId::NEVER) {
+            self.vars[index].fixed = Some(target);
+        }
+        true
+    }
+
+    /// `TyTable::unify` that fixes an inference variable meeting a concrete type.
+    fn unify(&mut self, lhs: TyId, rhs: TyId) -> Option<TyId> {
+        let (lhs, rhs) = (self.shallow(lhs), self.shallow(rhs));
+        match (self.unfixed(lhs), self.unfixed(rhs)) {
+            _ if lhs == rhs => Some(lhs),
+            (Some((index, literal)), None) => self.unify_var(lhs, index, literal, rhs),
+            (None, Some((index, literal))) => self.unify_var(rhs, index, literal, lhs),
+            (Some((_, lhs)), Some((_, rhs))) => self.tys.unify(lhs, rhs),
+            (None, None) => self.tys.unify(lhs, rhs),
+        }
+    }
+
+    fn unify_var(&mut self, var: TyId, index: usize, literal: TyId, other: TyId) -> Option<TyId> {
+        let ty = self.tys.unify(literal, other)?;
+        if ty == literal {
+            // the other side is a literal of the same kind, the variable still stands for both
+            return Some(var);
+        }
+        self.fix(index, literal, ty);
+        Some(ty)
+    }
+
+    /// defaults the inference variables nothing fixed, the ones without a default are ambiguous.
+    fn default_vars(&mut self) {
+        for index in 0..self.vars.len() {
+            let InferVar { name, span, default, fixed } = self.vars[index];
+            if fixed.is_some() {
+                continue;
+            }
+            if default.is_none() {
+                self.error(TypeErrorKind::AmbiguousType(name), span);
+            }
+            self.vars[index].fixed = Some(default.unwrap_or(TyId::ERROR));
+        }
+    }
+
+    /// the index in `loops` of the loop `label` names, the innermost one without a label.
+    fn loop_target(&self, label: Option<&str>) -> Option<usize> {
+        match label {
+            Some(label) => self.loops.iter().rposition(|(name, _)| *name == Some(label)),
+            None => self.loops.len().checked_sub(1),
+        }
+    }
+
+    fn is_declared_type(&self, name: &str) -> bool {
+        self.adt_named(name).is_some() || self.enum_named(name).is_some()
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Binding<'source>> {
+        self.scopes.iter().rev().flat_map(|scope| scope.iter().rev()).find(|binding| binding.name == name)
+    }
+
+    fn bind(&mut self, name: &'source str, span: Span, ty: TyId, mutability: Mutability) {
+        let binding = Binding {
+            name,
+            ty,
+            mutability,
+            span,
+        };
+        self.scopes.last_mut().expect("bindings are inside a scope").push(binding);
+    }
+
+    fn lower_type(&mut self, ty: &TypeExpr<'source>) -> TyId {
+        match &ty.kind {
+            TypeKind::Named(name) => match TyId::primitive(name) {
+                Some(primitive) => primitive,
+                None if self.is_declared_type(name) => self.tys.intern(TyKind::Adt(name)),
+                None if self.imports.contains(name) => TyId::ERROR,
+                None => {
+                    self.error(TypeErrorKind::UnknownType(name), ty.span);
+                    TyId::ERROR
+                }
+            },
+            TypeKind::Type => TyId::TYPE,
+            TypeKind::Pointer(pointee_ty) => {
+                let pointee = self.lower_type(pointee_ty);
+                self.tys.pointer(mutability(pointee_ty), pointee)
+            }
+            TypeKind::Array { len, elem } => {
+                let len = match len.kind {
+                    ExprKind::Literal(literal) if literal.token() == Token::LitInteger => {
+                        parse_u128(Spanned::new(literal.raw(), len.span)).ok().and_then(|len| u64::try_from(len).ok())
+                    }
+                    _ => None,
+                };
+                let elem = self.lower_type(elem);
+                self.tys.intern(TyKind::Array { len, elem })
+            }
+            TypeKind::Tuple(elems) => {
+                let elems = elems.iter().map(|elem| self.lower_type(elem)).collect();
+                self.tys.intern(TyKind::Tuple(elems))
+            }
+            TypeKind::Fn { params, ret } => {
+                let params = params.iter().map(|param| self.lower_type(param)).collect();
+                let ret = match ret {
+                    Some(ret) => self.lower_type(ret),
+                    None => TyId::UNIT,
+                };
+                self.tys.intern(TyKind::Fn { params, ret })
+            }
+        }
+    }
+
+    /// the parameter and return types of `decl`.
+    fn signature(&mut self, decl: &FnDecl<'source>) -> (Vec<TyId>, TyId) {
+        let params = decl.params.iter().map(|param| self.lower_type(&param.ty)).collect();
+        let ret = match &decl.ret {
+            Some(ret) => self.lower_type(ret),
+            None => TyId::UNIT,
+        };
+        (params, ret)
+    }
+
+    fn fn_ty(&mut self, decl: &FnDecl<'source>) -> TyId {
+        let (params, ret) = self.signature(decl);
+        self.tys.intern(TyKind::Fn { params, ret })
+    }
+
+    /// checks `stmts` in a new scope, items are bound before any statement is checked.
+    fn stmts(&mut self, stmts: &'ast [Stmt<'source>]) {
+        self.scopes.push(Vec::new());
+        self.bind_items(stmts);
+        for stmt in stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    /// types first, so that the signatures can use the types declared after them.
+    fn bind_items(&mut self, stmts: &'ast [Stmt<'source>]) {
+        let items = || {
+            stmts.iter().filter_map(|stmt| match &stmt.kind {
+                StmtKind::Item(item) => Some(item),
+                _ => None,
+            })
+        };
+        for item in items() {
+            match &item.kind {
+                ItemKind::Struct(decl) | ItemKind::Union(decl) => {
+                    let is_union = matches!(item.kind, ItemKind::Union(_));
+                    self.adts.push(Adt { decl, is_union });
+                    self.bind(decl.name.name, decl.name.span, TyId::TYPE, Mutability::Const);
+                }
+                ItemKind::Enum(decl) => {
+                    self.bind(decl.name.name, decl.name.span, TyId::TYPE, Mutability::Const);
+                    let ty = self.tys.intern(TyKind::Adt(decl.name.name));
+                    for variant in &decl.variants {
+                        self.bind(variant.name.name, variant.name.span, ty, Mutability::Const);
+                    }
+                    let repr = self.repr(item, decl);
+                    self.enums.push(EnumLayout {
+                        name: decl.name,
+                        repr,
+                        variants: decl.variants.iter().map(|variant| variant.name).collect(),
+                    });
+                }
+                // whatever the module declares is unknown here
+                ItemKind::Mod(decl) => self.bind(decl.name.name, decl.name.span, TyId::ERROR, Mutability::Const),
+                ItemKind::Import(decl) => {
+                    self.imports.push(decl.name.name);
+                    self.bind(decl.name.name, decl.name.span, TyId::ERROR, Mutability::Const);
+                }
+                ItemKind::Fn(_) => {}
+            }
+        }
+        for item in items() {
+            if let ItemKind::Fn(decl) = &item.kind {
+                let ty = self.fn_ty(decl);
+                self.fn_types.push((decl.name.span, ty));
+                self.bind(decl.name.name, decl.name.span, ty, Mutability::Const);
+            }
+        }
+    }
+
+    /// the discriminant type of the enum `decl`, see the module docs.
+    fn repr(&mut self, item: &Item<'source>, decl: &EnumDecl<'source>) -> IntTy {
+        let count = decl.variants.len();
+        let largest = count.saturating_sub(1) as u128;
+        let Some(attr) = item.attr("repr") else {
+            let fits = [IntTy::U8, IntTy::U16, IntTy::U32].into_iter().find(|int| int.max() >= largest);
+            return fits.unwrap_or(IntTy::U64);
+        };
+        let repr = match attr.args[..] {
+            [AttrArg::Ident(ident)] => TyId::primitive(ident.name).and_then(TyId::as_int),
+            _ => None,
+        };
+        let Some(repr) = repr else {
+            self.error(TypeErrorKind::InvalidRepr, attr.span);
+            return IntTy::U64;
+        };
+        if repr.max() < largest {
+            self.error(TypeErrorKind::TooManyVariants { repr, count }, attr.span);
+        }
+        repr
+    }
+
+    /// the enum a value of type `ty` belongs to, `None` if it isn't one.
+    fn enum_of(&self, ty: TyId) -> Option<&EnumLayout<'source>> {
+        match *self.tys.kind(self.probe(ty)) {
+            TyKind::Adt(name) => self.enum_named(name),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn adt_named(&self, name: &str) -> Option<Adt<'ast, 'source>> {
+        self.adts.iter().rev().find(|adt| adt.decl.name.name == name).copied()
+    }
+
+    /// the struct or union a value of type `ty` is, `None` if it isn't one.
+    fn adt_of(&self, ty: TyId) -> Option<Adt<'ast, 'source>> {
+        match *self.tys.kind(self.probe(ty)) {
+            TyKind::Adt(name) => self.adt_named(name),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn enum_named(&self, name: &str) -> Option<&EnumLayout<'source>> {
+        self.enums.iter().rev().find(|layout| layout.name.name == name)
+    }
+
+    /// the enum and discriminant of the variant a binding declared at `declared` is.
+    fn variant_at(&self, declared: Span) -> Option<(&'source str, u32)> {
+        self.enums.iter().rev().find_map(|layout| {
+            let index = layout.variants.iter().position(|variant| variant.span == declared)?;
+            Some((layout.name.name, index as u32))
+        })
+    }
+
+    /// the type of `enum_name::variant`, recording the discriminant for `span`.
+    /// the type of `enum_name::variant`, which may be an `EnumIntrinsic` unless it is a pattern.
+    fn path(&mut self, enum_name: Ident<'source>, variant: Ident<'source>, span: Span, in_pattern: bool) -> TyId {
+        // a missing enum or variant is reported by `resolve`
+        let Some(index) = self.enums.iter().rposition(|layout| layout.name.name == enum_name.name) else {
+            return TyId::ERROR;
+        };
+        let layout = &self.enums[index];
+        let ty = self.tys.intern(TyKind::Adt(enum_name.name));
+        if let Some(discriminant) = layout.discriminant(variant.name) {
+            self.discriminants.push((span, discriminant));
+            return ty;
+        }
+        let len = layout.variants.len() as u64;
+        match EnumIntrinsic::named(variant.name) {
+            Some(_) if in_pattern => TyId::ERROR,
+            Some(intrinsic) => {
+                self.intrinsics.push((span, index));
+                match intrinsic {
+                    EnumIntrinsic::VariantCount => TyId::USIZE,
+                    EnumIntrinsic::All => self.tys.intern(TyKind::Array { len: Some(len), elem: ty }),
+                }
+            }
+            None => TyId::ERROR,
+        }
+    }
+
+    /// checks `operand cast target`, see the module docs.
+    fn cast(&mut self, operand: &Expr<'source>, operand_ty: TyId, target: TyId, span: Span) {
+        if self.enum_of(operand_ty).is_some() {
+            return self.enum_cast(operand, operand_ty, target, span);
+        }
+        let from = self.probe(operand_ty);
+        let lossless = match from {
+            _ if from == target => Some(true),
+            // reported already, or the cast is a no-op
+            TyId::ERROR | TyId::NEVER | TyId::LIT_UNINIT => Some(true),
+            _ if matches!(target, TyId::ERROR | TyId::LITERAL) => Some(true),
+            TyId::BOOL | TyId::LIT_BOOL => (target == TyId::BOOL || target.as_int().is_some()).then_some(true),
+            // unlike an integer, a character that doesn't fit isn't wrapped into some other one
+            TyId::LIT_CHAR if let (Some(value), Some(to)) = (literal_char(operand), target.as_int()) => {
+                if !char_fits(value, to) {
+                    self.error(TypeErrorKind::CharOutOfRange { value, to }, span);
+                }
+                Some(true)
+            }
+            TyId::LIT_INTEGER | TyId::LIT_CHAR if target.is_numeric() => {
+                if let (Some(value), Some(to)) = (literal_int(operand), target.as_int())
+                    && !to.contains(value)
+                {
+                    let wrapped = to.wrap(value);
+                    self.warn(TypeWarningKind::LiteralTruncated { value, to, wrapped }, span);
+                }
+                Some(true)
+            }
+            TyId::LIT_FLOAT if target.is_numeric() => Some(target.as_float().is_some()),
+            _ => conversion(&self.tys, from, target),
+        };
+        match lossless {
+            Some(true) => {}
+            Some(false) => self.warn(TypeWarningKind::LossyCast { from, to: target }, span),
+            None => self.error(TypeErrorKind::InvalidCast { from, to: target }, span),
+        }
+    }
+
+    /// checks `operand cast target` when `operand` is an enum.
+    fn enum_cast(&mut self, operand: &Expr<'source>, operand_ty: TyId, target: TyId, span: Span) {
+        let Some(layout) = self.enum_of(operand_ty) else { return };
+        let largest = layout.variants.len().saturating_sub(1) as u32;
+        let Some(to) = target.as_int() else {
+            if target != TyId::ERROR {
+                self.error(TypeErrorKind::InvalidEnumCast { to: target }, span);
+            }
+            return;
+        };
+        // a variant named directly has its own discriminant, any other value may be the largest
+        let known = self.discriminants.iter().rev().find(|(at, _)| *at == operand.span);
+        let discriminant = known.map_or(largest, |&(_, discriminant)| discriminant);
+        if (discriminant as u128) > to.max() {
+            self.error(TypeErrorKind::DiscriminantTruncated { discriminant, to }, span);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &'ast Stmt<'source>) {
+        match &stmt.kind {
+            StmtKind::Let { name, ty: annotation, value } => {
+                let found = self.expr(value);
+                let ty = match annotation {
+                    Some(annotation) => {
+                        let ty = self.lower_type(annotation);
+                        self.expect_value(value, found, ty, Some(annotation.span));
+                        // a `literal` binding keeps the literal's own type, see the module docs
+                        match self.probe(found) {
+                            literal if ty == TyId::LITERAL && literal.is_unsuffixed() => literal,
+                            _ => ty,
+                        }
+                    }
+                    None if found.is_unsuffixed() => self.infer_var(name.name, name.span, found, value),
+                    None => found,
+                };
+                self.bindings.push((name.span, ty));
+                let mutability = annotation.as_ref().map_or(Mutability::Immutable, |annotation| mutability(annotation));
+                self.bind(name.name, name.span, ty, mutability);
+            }
+            StmtKind::Expr(expr) => {
+                self.expr(expr);
+            }
+            StmtKind::Item(item) => match &item.kind {
+                ItemKind::Fn(decl) => self.fn_decl(decl),
+                ItemKind::Struct(adt) | ItemKind::Union(adt) => {
+                    let fields = adt.fields.iter().map(|field| (field.name, self.lower_type(&field.ty))).collect();
+                    self.structs.push(StructLayout {
+                        name: adt.name,
+                        is_union: matches!(item.kind, ItemKind::Union(_)),
+                        fields,
+                    });
+                }
+                ItemKind::Enum(_) | ItemKind::Mod(_) | ItemKind::Import(_) => {}
+            },
+        }
+    }
+
+    /// whether a binding of type `ty` is an item, the only bindings a function body sees.
+    fn is_item(&self, ty: TyId) -> bool {
+        matches!(self.tys.kind(ty), TyKind::Fn { .. } | TyKind::Type | TyKind::Adt(_) | TyKind::Error)
+    }
+
+    fn fn_decl(&mut self, decl: &'ast FnDecl<'source>) {
+        let (params, ret) = self.signature(decl);
+        let Some(body) = &decl.body else { return };
+        // a body only sees the items around it, see `resolve`
+        let outer = core::mem::take(&mut self.scopes);
+        let items = outer.iter().flatten().filter(|binding| self.is_item(binding.ty)).copied().collect();
+        self.scopes.push(items);
+        let params = decl.params.iter().zip(params).map(|(param, ty)| Binding {
+            name: param.name.name,
+            ty,
+            mutability: mutability(&param.ty),
+            span: param.name.span,
+        });
+        self.scopes.push(params.collect());
+        let because = decl.ret.as_ref().map(|ret| ret.span);
+        self.fns.push(FnContext { ret, because });
+        let loops = core::mem::take(&mut self.loops);
+
+        let found = self.block(body);
+        match &body.tail {
+            Some(tail) => self.expect_value(tail, found, ret, because),
+            None if found != TyId::NEVER && !self.tys.coerces_to(TyId::UNIT, ret) => {
+                self.error(TypeErrorKind::MissingReturnValue { ret }, decl.name.span);
+            }
+            None => {}
+        }
+
+        self.loops = loops;
+        self.fns.pop();
+        self.scopes = outer;
+    }
+
+    /// the type of the tail, `()` without one or `!` when a statement always diverges.
+    fn block(&mut self, block: &'ast Block<'source>) -> TyId {
+        self.stmts(&block.stmts);
+        let ty = match &block.tail {
+            Some(tail) => self.expr(tail),
+            None => {
+                let diverges = block.stmts.iter().any(|stmt| match &stmt.kind {
+                    StmtKind::Expr(expr) => matches!(expr.kind, ExprKind::Return(_) | ExprKind::Break { .. }),
+                    _ => false,
+                });
+                if diverges { TyId::NEVER } else { TyId::UNIT }
+            }
+        };
+        self.scopes.pop();
+        ty
+    }
+
+    fn expr(&mut self, expr: &'ast Expr<'source>) -> TyId {
+        let ty = self.infer(expr);
+        self.expr_types.push((expr.span, ty));
+        ty
+    }
+
+    fn infer(&mut self, expr: &'ast Expr<'source>) -> TyId {
+        match &expr.kind {
+            ExprKind::Literal(literal) => literal_ty(literal),
+            ExprKind::Identifier(name @ ("true" | "false")) if self.lookup(name).is_none() => TyId::BOOL,
+            ExprKind::Identifier(name) => match self.lookup(name).copied() {
+                Some(binding) => {
+                    if let Some((_, discriminant)) = self.variant_at(binding.span) {
+                        self.discriminants.push((expr.span, discriminant));
+                    }
+                    binding.ty
+                }
+                None => TyId::ERROR,
+            },
+            ExprKind::Path { enum_name, variant } => self.path(*enum_name, *variant, expr.span, false),
+            ExprKind::Unary { op, expr: operand } => {
+                let ty = self.expr(operand);
+                self.unary(*op, ty, expr.span)
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let (lhs_ty, rhs_ty) = (self.expr(lhs), self.expr(rhs));
+                // a literal operand takes the type of the other one, except for a shift amount
+                if !matches!(op, BinaryOp::Shl | BinaryOp::Shr) {
+                    self.literal_fits(lhs, rhs_ty);
+                    self.literal_fits(rhs, lhs_ty);
+                }
+                self.binary(*op, lhs_ty, rhs_ty, expr.span)
+            }
+            ExprKind::Assign { op, target, value } => {
+                let place = self.place(target);
+                let value_ty = self.expr(value);
+                match op {
+                    Some(op) => {
+                        if !matches!(op, BinaryOp::Shl | BinaryOp::Shr) {
+                            self.literal_fits(value, place.ty);
+                        }
+                        let result = self.binary(*op, place.ty, value_ty, expr.span);
+                        self.expect(result, place.ty, value.span, Some(target.span));
+                    }
+                    None => self.expect_value(value, value_ty, place.ty, Some(target.span)),
+                }
+                let declared = place.declared;
+                match place.mutability {
+                    Mutability::Immutable => self.error(TypeErrorKind::AssignToImmutable { declared }, target.span),
+                    Mutability::Const => self.error(TypeErrorKind::AssignToConst { declared }, target.span),
+                    Mutability::Mut | Mutability::Anymut => {}
+                }
+                TyId::UNIT
+            }
+            ExprKind::Call { callee, args } => {
+                let callee_ty = self.expr(callee);
+                let arg_tys: Vec<_> = args.iter().map(|arg| self.expr(arg)).collect();
+                match self.tys.kind(self.probe(callee_ty)).clone() {
+                    TyKind::Fn { params, ret } => {
+                        if params.len() != args.len() {
+                            let count = TypeErrorKind::WrongArgumentCount {
+                                expected: params.len(),
+                                found: args.len(),
+                            };
+                            self.error(count, expr.span);
+                        }
+                        for ((arg, found), expected) in args.iter().zip(arg_tys).zip(params) {
+                            self.expect_value(arg, found, expected, None);
+                        }
+                        ret
+                    }
+                    TyKind::Error => TyId::ERROR,
+                    _ => {
+                        self.error(TypeErrorKind::NotCallable(callee_ty), callee.span);
+                        TyId::ERROR
+                    }
+                }
+            }
+            ExprKind::Struct { name, fields } => self.struct_literal(*name, fields, expr.span),
+            ExprKind::Field { expr: base, field } => self.read_field(base, *field, false),
+            ExprKind::Index { expr: base, index } => {
+                let ty = self.expr(base);
+                self.index(ty, base, index)
+            }
+            ExprKind::Cast { expr: operand, ty } => {
+                let operand_ty = match &operand.kind {
+                    ExprKind::Field { expr: base, field } => {
+                        let ty = self.read_field(base, *field, true);
+                        self.expr_types.push((operand.span, ty));
+                        ty
+                    }
+                    _ => self.expr(operand),
+                };
+                let target = self.lower_type(ty);
+                self.cast(operand, operand_ty, target, expr.span);
+                target
+            }
+            ExprKind::Block(block) if block.kind == BlockKind::Compiletime => {
+                self.compiletime += 1;
+                let ty = self.block(block);
+                self.compiletime -= 1;
+                ty
+            }
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                self.condition(cond);
+                let then_ty = self.block(then_branch);
+                match else_branch {
+                    Some(else_branch) => {
+                        let else_ty = self.expr(else_branch);
+                        self.join(then_ty, else_ty, else_branch.span, then_branch.tail.as_ref().map(|t| t.span))
+                    }
+                    None => TyId::UNIT,
+                }
+            }
+            ExprKind::While { label, cond, body } => {
+                self.condition(cond);
+                self.loops.push((label.map(|label| label.name), Some(TyId::UNIT)));
+                self.block(body);
+                self.loops.pop();
+                TyId::UNIT
+            }
+            ExprKind::Loop { label, body } => {
+                self.loops.push((label.map(|label| label.name), None));
+                self.block(body);
+                // without a `break` the loop never ends
+                self.loops.pop().and_then(|(_, ty)| ty).unwrap_or(TyId::NEVER)
+            }
+            ExprKind::Break { label, value } => {
+                let ty = match value {
+                    Some(value) => self.expr(value),
+                    None => TyId::UNIT,
+                };
+                // a `break` outside of a loop, or to a label no loop has, isn't checked here
+                let target = self.loop_target(label.map(|label| label.name));
+                match target.map(|index| self.loops[index].1) {
+                    Some(Some(expected)) => {
+                        match value {
+                            Some(value) => self.expect_value(value, ty, expected, None),
+                            None => self.expect(ty, expected, expr.span, None),
+                        }
+                    }
+                    Some(None) => self.loops[target.unwrap()].1 = Some(ty),
+                    None => {}
+                }
+                TyId::NEVER
+            }
+            ExprKind::Continue { .. } => TyId::NEVER,
+            ExprKind::Match { scrutinee, arms } => {
+                let scrutinee_ty = self.expr(scrutinee);
+                let mut result: Option<(TyId, Span)> = None;
+                for arm in arms {
+                    let ty = self.arm(arm, scrutinee_ty);
+                    result = Some(match result {
+                        Some((expected, span)) => (self.join(expected, ty, arm.body.span, Some(span)), span),
+                        None => (ty, arm.body.span),
+                    });
+                }
+                result.map_or(TyId::NEVER, |(ty, _)| ty)
+            }
+            ExprKind::Fn(decl) => {
+                self.fn_decl(decl);
+                self.fn_ty(decl)
+            }
+            ExprKind::Return(value) => {
+                let ty = match value {
+                    Some(value) => self.expr(value),
+                    None => TyId::UNIT,
+                };
+                if let Some(&FnContext { ret, because }) = self.fns.last() {
+                    match value {
+                        Some(value) => self.expect_value(value, ty, ret, because),
+                        None => self.expect(ty, ret, expr.span, because),
+                    }
+                }
+                TyId::NEVER
+            }
+        }
+    }
+
+    /// the type and mutability of an assignment target, the parser only allows places there.
+    fn place(&mut self, expr: &'ast Expr<'source>) -> Place {
+        let ty = |ty| Place {
+            ty,
+            mutability: Mutability::Anymut,
+            declared: None,
+        };
+        match &expr.kind {
+            ExprKind::Identifier(name) => match self.lookup(name) {
+                Some(binding) => Place {
+                    ty: binding.ty,
+                    mutability: binding.mutability,
+                    declared: Some(binding.span),
+                },
+                // reported by `resolve`
+                None => ty(TyId::ERROR),
+            },
+            ExprKind::Field { expr: base, field } => {
+                let place = self.place(base);
+                let field_ty = self.field(place.ty, field.name, field.span);
+                let adt = self.adt_of(place.ty);
+                let decl = adt.and_then(|adt| adt.decl.fields.iter().find(|f| f.name.name == field.name));
+                match decl.filter(|decl| decl.ty.qualifiers.iter().any(|q| q.value.is_mutability())) {
+                    Some(decl) => Place {
+                        ty: field_ty,
+                        mutability: mutability(&decl.ty),
+                        declared: Some(decl.name.span),
+                    },
+                    None => Place { ty: field_ty, ..place },
+                }
+            }
+            ExprKind::Index { expr: base, index } => {
+                let place = self.place(base);
+                let elem = self.index(place.ty, base, index);
+                match *self.tys.kind(self.probe(place.ty)) {
+                    TyKind::Pointer { mutability, .. } => Place {
+                        ty: elem,
+                        mutability,
+                        declared: None,
+                    },
+                    _ => Place { ty: elem, ..place },
+                }
+            }
+            ExprKind::Unary {
+                op: UnaryOp::Deref,
+                expr: pointer,
+            } => {
+                let pointer_ty = self.expr(pointer);
+                let pointee = self.unary(UnaryOp::Deref, pointer_ty, expr.span);
+                match *self.tys.kind(self.probe(pointer_ty)) {
+                    TyKind::Pointer { mutability, .. } => Place {
+                        ty: pointee,
+                        mutability,
+                        declared: None,
+                    },
+                    _ => ty(pointee),
+                }
+            }
+            _ => {
+                let found = self.expr(expr);
+                ty(found)
+            }
+        }
+    }
+
+    fn unary(&mut self, op: UnaryOp, ty: TyId, span: Span) -> TyId {
+        let probe = self.probe(ty);
+        let result = match op {
+            UnaryOp::Neg if probe.is_numeric() => Some(ty),
+            UnaryOp::Not if matches!(probe, TyId::BOOL | TyId::LIT_BOOL | TyId::ERROR) => Some(ty),
+            UnaryOp::BitNot if probe.is_integer() => Some(ty),
+            UnaryOp::Deref => match *self.tys.kind(probe) {
+                TyKind::Pointer { pointee, .. } => Some(pointee),
+                TyKind::Error => Some(TyId::ERROR),
+                _ => None,
+            },
+            _ => None,
+        };
+        result.unwrap_or_else(|| {
+            let op = op.token().source_repr();
+            self.error(TypeErrorKind::InvalidOperands { op, lhs: ty, rhs: None }, span);
+            TyId::ERROR
+        })
+    }
+
+    /// the element type of `base[index]`, `ty` is the type of `base`.
+    fn index(&mut self, ty: TyId, base: &'ast Expr<'source>, index: &'ast Expr<'source>) -> TyId {
+        let index_ty = self.expr(index);
+        self.expect(index_ty, TyId::USIZE, index.span, None);
+        match *self.tys.kind(self.probe(ty)) {
+            TyKind::Array { elem, .. } | TyKind::Pointer { pointee: elem, .. } => elem,
+            TyKind::Error => TyId::ERROR,
+            _ => {
+                self.error(TypeErrorKind::NotIndexable(ty), base.span);
+                TyId::ERROR
+            }
+        }
+    }
+
+    fn condition(&mut self, cond: &'ast Expr<'source>) {
+        let ty = self.expr(cond);
+        self.expect(ty, TyId::BOOL, cond.span, None);
+    }
+
+    /// the type of two branches that produce the same value, `found` is checked against
+    /// `expected` from the first branch.
+    fn join(&mut self, expected: TyId, found: TyId, span: Span, because: Option<Span>) -> TyId {
+        match self.unify(expected, found) {
+            Some(ty) => ty,
+            None => {
+                self.error(TypeErrorKind::Mismatch { expected, found, because }, span);
+                expected
+            }
+        }
+    }
+
+    fn arm(&mut self, arm: &'ast MatchArm<'source>, scrutinee: TyId) -> TyId {
+        self.scopes.push(Vec::new());
+        match &arm.pattern.kind {
+            PatternKind::Wildcard => {}
+            PatternKind::Literal(literal) => self.expect(literal_ty(literal), scrutinee, arm.pattern.span, None),
+            PatternKind::Identifier(name) => match self.lookup(name).and_then(|binding| self.variant_at(binding.span)) {
+                Some((enum_name, discriminant)) => {
+                    self.discriminants.push((arm.pattern.span, discriminant));
+                    let ty = self.tys.intern(TyKind::Adt(enum_name));
+                    self.expect(ty, scrutinee, arm.pattern.span, None);
+                }
+                None => self.bind(name, arm.pattern.span, scrutinee, Mutability::Immutable),
+            },
+            PatternKind::Path { enum_name, variant } => {
+                let ty = self.path(*enum_name, *variant, arm.pattern.span, true);
+                self.expect(ty, scrutinee, arm.pattern.span, None);
+            }
+        }
+        let ty = self.expr(&arm.body);
+        self.scopes.pop();
+        ty
+    }
+
+    fn binary(&mut self, op: BinaryOp, lhs: TyId, rhs: TyId, span: Span) -> TyId {
+        let operand = self.unify(lhs, rhs).map(|ty| (ty, self.probe(ty)));
+        let result = match (op, operand) {
+            (BinaryOp::Eq | BinaryOp::Ne, Some(_)) => Some(TyId::BOOL),
+            (BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge, Some((_, probe))) if probe.is_numeric() => {
+                Some(TyId::BOOL)
+            }
+            (BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem, Some((ty, probe)))
+                if probe.is_numeric() =>
+            {
+                Some(ty)
+            }
+            (BinaryOp::Add, Some((ty, TyId::LIT_STR))) if self.compiletime > 0 => Some(ty),
+            (BinaryOp::BitAnd | BinaryOp::BitOr | BinaryOp::BitXor, Some((ty, probe)))
+                if probe.is_integer() || probe == TyId::BOOL =>
+            {
+                Some(ty)
+            }
+            // the shift amount doesn't have to match the shifted value
+            (BinaryOp::Shl | BinaryOp::Shr, _) if self.probe(lhs).is_integer() && self.probe(rhs).is_integer() => {
+                Some(lhs)
+            }
+            _ => None,
+        };
+        result.unwrap_or_else(|| {
+            let op = op.token().source_repr();
+            self.error(TypeErrorKind::InvalidOperands { op, lhs, rhs: Some(rhs) }, span);
+            TyId::ERROR
+        })
+    }
+
+    /// the type of `base.field` as a value, `in_cast` when it is the operand of a `cast`.
+    fn read_field(&mut self, base: &'ast Expr<'source>, field: Ident<'source>, in_cast: bool) -> TyId {
+        let ty = self.expr(base);
+        if !in_cast && self.adt_of(ty).is_some_and(|adt| adt.is_union) {
+            self.error(TypeErrorKind::UnionRead { field: field.name }, field.span);
+        }
+        self.field(ty, field.name, field.span)
+    }
+
+    /// checks `name { fields }` against the declaration of `name`, see the module docs.
+    fn struct_literal(&mut self, name: Ident<'source>, fields: &'ast [FieldInit<'source>], span: Span) -> TyId {
+        let adt = self.adt_named(name.name);
+        if adt.is_none() && self.lookup(name.name).is_some() && !self.imports.contains(&name.name) {
+            // an unknown name is reported by `resolve`
+            self.error(TypeErrorKind::NotAStruct(name.name), name.span);
+        }
+        let ty = match adt {
+            Some(adt) => self.tys.intern(TyKind::Adt(adt.decl.name.name)),
+            None => TyId::ERROR,
+        };
+        for init in fields {
+            let found = self.expr(&init.value);
+            let Some(adt) = adt else { continue };
+            match adt.decl.fields.iter().find(|field| field.name.name == init.name.name) {
+                Some(field) => {
+                    let expected = self.lower_type(&field.ty);
+                    self.expect_value(&init.value, found, expected, Some(field.ty.span));
+                }
+                None => self.error(TypeErrorKind::NoField { ty, field: init.name.name }, init.name.span),
+            }
+        }
+        let Some(adt) = adt else { return ty };
+        if adt.is_union {
+            if fields.len() != 1 {
+                self.error(TypeErrorKind::UnionLiteralMembers { ty, count: fields.len() }, span);
+            }
+            return ty;
+        }
+        let missing: Vec<_> = adt
+            .decl
+            .fields
+            .iter()
+            .map(|field| field.name.name)
+            .filter(|field| !fields.iter().any(|init| init.name.name == *field))
+            .collect();
+        if !missing.is_empty() {
+            self.error(TypeErrorKind::MissingFields { ty, fields: missing }, span);
+        }
+        ty
+    }
+
+    fn field(&mut self, ty: TyId, field: &'source str, span: Span) -> TyId {
+        let found = match self.tys.kind(self.probe(ty)).clone() {
+            TyKind::Tuple(elems) => field.parse::<usize>().ok().and_then(|index| elems.get(index).copied()),
+            TyKind::Adt(name) => {
+                let adt = self.adt_named(name);
+                let field = adt.and_then(|adt| adt.decl.fields.iter().find(|f| f.name.name == field));
+                field.map(|field| self.lower_type(&field.ty))
+            }
+            TyKind::Unsuffixed(Token::LitStr) if field == "len" && self.compiletime > 0 => Some(TyId::USIZE),
+            TyKind::Error => Some(TyId::ERROR),
+            _ => None,
+        };
+        found.unwrap_or_else(|| {
+            self.error(TypeErrorKind::NoField { ty, field }, span);
+            TyId::ERROR
+        })
+    }
+}
+
+/// the mutability qualifier of `ty`, `Immutable` without one.
+fn mutability(ty: &TypeExpr<'_>) -> Mutability {
+    let qualifier = ty.qualifiers.iter().find(|qualifier| qualifier.value.is_mutability());
+    match qualifier.map(|qualifier| qualifier.value) {
+        Some(Qualifier::Mut) => Mutability::Mut,
+        Some(Qualifier::Anymut) => Mutability::Anymut,
+        Some(Qualifier::Const) => Mutability::Const,
+        _ => Mutability::Immutable,
+    }
+}
+
+/// the value of an integer literal, possibly negated, or the scalar value of a character
+/// literal. `None` for any other expression.
+fn literal_int(expr: &Expr<'_>) -> Option<i128> {
+    match &expr.kind {
+        ExprKind::Literal(literal) if literal.token() == Token::LitInteger => {
+            parse_u128(Spanned::new(literal.raw(), expr.span)).ok().and_then(|value| i128::try_from(value).ok())
+        }
+        ExprKind::Literal(literal) if literal.token() == Token::LitChar => literal_char(expr).map(|c| c as i128),
+        ExprKind::Unary { op: UnaryOp::Neg, expr } => literal_int(expr).map(|value| -value),
+        _ => None,
+    }
+}
+
+/// the value of a character literal, `None` for any other expression.
+fn literal_char(expr: &Expr<'_>) -> Option<char> {
+    match &expr.kind {
+        ExprKind::Literal(literal) if literal.token() == Token::LitChar => decode_char_literal(literal.raw()).ok(),
+        _ => None,
+    }
+}
+
+/// whether the integer type `to` holds `value`, `u8` only holds ascii so that a latin-1 byte
+/// can't stand in for a character.
+fn char_fits(value: char, to: IntTy) -> bool {
+    match to {
+        IntTy::U8 => value.is_ascii(),
+        _ => to.contains(value as i128),
+    }
+}
+
+/// whether a cast between the numeric or pointer types `from` and `to` keeps every value,
+/// `None` if it isn't allowed.
+fn conversion(tys: &TyTable<'_>, from: TyId, to: TyId) -> Option<bool> {
+    let lossless = match (from.as_int(), from.as_float(), to.as_int(), to.as_float()) {
+        (Some(from), _, Some(to), _) => to.holds(from),
+        (Some(from), _, _, Some(to)) => from.bits() - from.is_signed() as u32 <= to.digits(),
+        (_, Some(_), Some(_), _) => false,
+        (_, Some(from), _, Some(to)) => to.digits() >= from.digits(),
+        _ => {
+            let is_pointer = |ty| matches!(tys.kind(ty), TyKind::Pointer { .. });
+            let is_address = |ty| matches!(ty, TyId::USIZE | TyId::ISIZE | TyId::LIT_INTEGER) || is_pointer(ty);
+            return (is_pointer(from) && is_address(to) || is_address(from) && is_pointer(to)).then_some(true);
+        }
+    };
+    Some(lossless)
+}
+
+#[inline]
+/// the length of the string `expr` evaluates to, for a literal and for literals joined in a
+/// `compiletime` block.
+fn string_len(expr: &Expr<'_>) -> Option<u64> {
+    match &expr.kind {
+        ExprKind::Literal(literal) => Some(literal.decoded_len() as u64),
+        ExprKind::Binary { op: BinaryOp::Add, lhs, rhs } => Some(string_len(lhs)? + string_len(rhs)?),
+        ExprKind::Block(block) if block.kind == BlockKind::Compiletime && block.stmts.is_empty() => {
+            string_len(block.tail.as_ref()?)
+        }
+        _ => None,
+    }
+}
+
+fn literal_ty(literal: &Literal<'_>) -> TyId {
+    TyId::unsuffixed(literal.token()).expect("literals have a literal token")
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::{Checked, TypeError, TypeErrorKind, check};
+    use crate::parser::tests::parse_ok;
+    use crate::ty::{IntTy, Mutability, TyId, TyKind};
+    use crate::types::Span;
+
+    fn checked(text: &str) -> Checked<'_> {
+        check(parse_ok(text))
+    }
+
+    fn errors(text: &str) -> Vec<TypeError<'_>> {
+        checked(text).errors
+    }
+
+    fn mismatch<'a>(expected: TyId, found: TyId, because: Option<Span>, span: Span) -> TypeError<'a> {
+        let kind = TypeErrorKind::Mismatch { expected, found, because };
+        TypeError { kind, span }
+    }
+
+    #[test]
+    fn sample_programs_check() {
+        for text in [
+            include_str!("../progs/let_literals.mumbo"),
+            include_str!("../progs/functions_basic.mumbo"),
+        ] {
+            assert_eq!(errors(text), [], "{text}");
+        }
+        let text = "let byte: u8 = '5';\nlet cond: mut bool = false;\nlet sum: u32 = 1 + 2 * 3;\n\
+                    struct P { x: i32, y: (u8, f64) }\nlet p: P = uninit;\nlet x: i32 = p.x + 1;\n\
+                    let y: f64 = p.y.1 * 2.5;\nlet big = if cond { sum } else { 7 };\nlet again: u32 = big;\n\
+                    enum E { a, b }\nlet e: E = a;\nlet n: u8 = match e { a => 1, b => byte, };\n\
+                    fn add(let l: i64, let r: i64) -> i64 { return l + r; }\nlet total: i64 = add(1, 2);\n\
+                    let found = loop { break 3.0; };\nlet f: f32 = found;";
+        assert_eq!(errors(text), []);
+    }
+
+    #[test]
+    fn characters_fit_their_type() {
+        let text = "let a = 'é';\nlet b = 'x';\nlet c: u8 = 'é';\nlet d: u8 = '€';\nlet e: u16 = '€';\n\
+                    let f: u16 = '🦀';\nlet g: u32 = '🦀';";
+        let checked = checked(text);
+        let tys: Vec<_> = checked.bindings.iter().map(|(_, ty)| checked.tys.display(*ty).to_string()).collect();
+        assert_eq!(tys, ["u32", "u8", "u8", "u8", "u16", "u16", "u32"]);
+        let kinds: Vec<_> = checked.errors.iter().map(|e| e.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            [
+                TypeErrorKind::CharOutOfRange { value: 'é', to: IntTy::U8 },
+                TypeErrorKind::CharOutOfRange { value: '€', to: IntTy::U8 },
+                TypeErrorKind::CharOutOfRange { value: '🦀', to: IntTy::U16 },
+            ]
+        );
+        assert_eq!(checked.errors[0].kind.message(&checked.tys), "character `é` doesn't fit in `u8`");
+    }
+
+    #[test]
+    fn labeled_breaks_leave_their_loop() {
+        let text = "let r: i32 = @outer loop { while true { break @outer 5; } };\n\
+                    let s = @a loop { loop { break @a 6; } };\nlet t: u8 = s;\n\
+                    let u: bool = @b loop { @c while true { break @c; } break @b 1; };";
+        let checked = checked(text);
+        // only the value of the outer loop disagrees with its annotation
+        let loop_value = mismatch(TyId::BOOL, TyId::LIT_INTEGER, Some(Span::new(125, 129)), Span::new(132, 183));
+        assert_eq!(checked.errors, [loop_value]);
+        assert_eq!(checked.bindings[1].1, TyId::U8);
+    }
+
+    #[test]
+    fn annotations_are_checked() {
+        let text = "let a: u8 = 1.5;\nlet b: bool = 1;\nlet c: [4 u8] = \"str\";\nlet d: *u16 = \"s\";\nlet e: u8 = a;";
+        let checked = checked(text);
+        assert_eq!(
+            checked.errors[..2],
+            [
+                mismatch(TyId::U8, TyId::LIT_FLOAT, Some(Span::new(7, 9)), Span::new(12, 15)),
+                mismatch(TyId::BOOL, TyId::LIT_INTEGER, Some(Span::new(24, 28)), Span::new(31, 32)),
+            ]
+        );
+        assert_eq!(checked.errors.len(), 3);
+        let TypeErrorKind::Mismatch { expected, found, .. } = checked.errors[2].kind else {
+            panic!("{:?}", checked.errors[2]);
+        };
+        let pointer = TyKind::Pointer {
+            mutability: Mutability::Immutable,
+            pointee: TyId::U16,
+        };
+        assert_eq!((checked.tys.kind(expected), found), (&pointer, TyId::LIT_STR));
+        assert_eq!(checked.errors[2].span, Span::new(71, 74));
+        assert_eq!(
+            errors("let t: (u8, missing) = uninit;")[0].kind,
+            TypeErrorKind::UnknownType("missing")
+        );
+    }
+
+    #[test]
+    fn unannotated_lets_are_inferred() {
+        let text = "let a = 1;\nlet b: u8 = a;\nlet c = 2.5;\nlet d = 'x';\nlet e = \"four\";\nlet g = a + 1;\n\
+                    let l: literal = 7;\nlet m: u16 = l;\nlet n: f32 = l;\nlet f = uninit;\nlet h = f;\n\
+                    let i = 3;\nlet j = i * 2;\nlet k: i64 = j;";
+        let inferred = checked(text);
+        let ambiguous = TypeError { kind: TypeErrorKind::AmbiguousType("f"), span: Span::new(139, 140) };
+        assert_eq!(inferred.errors, [ambiguous]);
+        let tys: Vec<_> = inferred.bindings.iter().map(|(_, ty)| inferred.tys.display(*ty).to_string()).collect();
+        assert_eq!(
+            tys,
+            [
+                "u8", "u8", "f64", "u8", "[4 u8]", "u8", "{integer}", "u16", "f32", "{error}", "{error}", "i64", "i64",
+                "i64",
+            ]
+        );
+        assert_eq!(inferred.bindings[0].0, Span::new(4, 5));
+        // expressions have the type their variable was fixed to, literals keep their own
+        let sum = text.find("a + 1").unwrap();
+        assert_eq!(inferred.expr_ty(Span::new(sum, sum + 5)), Some(TyId::U8));
+        assert_eq!(inferred.expr_ty(Span::new(sum + 4, sum + 5)), Some(TyId::LIT_INTEGER));
+        assert_eq!(inferred.expr_ty(Span::new(0, 3)), None);
+
+        let conflicting = checked("let x = 1;\nlet y: u8 = x;\nlet z: u16 = x;\nlet q = 1.5;\nlet r: u8 = q;");
+        let messages: Vec<_> = conflicting.errors.iter().map(|e| e.kind.message(&conflicting.tys)).collect();
+        assert_eq!(
+            messages,
+            [
+                "mismatched types: expected `u16`, found `u8`",
+                "mismatched types: expected `u8`, found `{float}`",
+            ]
+        );
+    }
+
+    #[test]
+    fn assignments_need_writable_places() {
+        let text = "let v1: u8 = 0;\nv1 = 1;\nlet v2: mut u8 = 0;\nv2 = 1;\nlet v3: anymut u8 = 0;\nv3 += 1;\n\
+                    let c: const u8 = 0;\nc = 1;\nlet p: *mut u8 = uninit;\n*p = 1;\np[1] = 2;\nlet q: *u8 = p;\n\
+                    *q = 2;\nlet r: *anymut u8 = p;\nlet s: *mut u8 = r;\nstruct S { a: u8, b: const u8, c: mut u8 }\n\
+                    let x: S = uninit;\nx.a = 1;\nx.c = 2;\nlet y: mut S = uninit;\ny.a = 1;\ny.b = 2;\n\
+                    fn f(let n: mut u8, let m: u8) { n = 1; m = 2; }";
+        let checked = checked(text);
+        let kinds: Vec<_> = checked.errors.iter().map(|e| (e.kind.clone(), e.span)).collect();
+        assert_eq!(
+            kinds,
+            [
+                (TypeErrorKind::AssignToImmutable { declared: Some(Span::new(4, 6)) }, Span::new(16, 18)),
+                (TypeErrorKind::AssignToConst { declared: Some(Span::new(88, 89)) }, Span::new(105, 106)),
+                (TypeErrorKind::AssignToImmutable { declared: None }, Span::new(171, 173)),
+                (
+                    TypeErrorKind::PointerMutability {
+                        expected: Mutability::Mut,
+                        found: Mutability::Anymut,
+                    },
+                    Span::new(219, 220)
+                ),
+                (TypeErrorKind::AssignToImmutable { declared: Some(Span::new(269, 270)) }, Span::new(284, 287)),
+                (TypeErrorKind::AssignToConst { declared: Some(Span::new(240, 241)) }, Span::new(334, 337)),
+                (TypeErrorKind::AssignToImmutable { declared: Some(Span::new(367, 368)) }, Span::new(383, 384)),
+            ]
+        );
+        let message = checked.errors[3].kind.message(&checked.tys);
+        assert_eq!(message, "mismatched pointer access: expected `mut`, found `anymut`");
+    }
+
+    #[test]
+    fn integer_literals_have_to_fit() {
+        let text = "let z: u8 = 300;\nlet n: i8 = -128;\nlet m: u8 = -1;\n\
+                    fn f(let a: u16) -> i8 { let b: i8 = 1; b + 200 }\nf(65536);\nlet s: mut u8 = 0;\ns += 256;\n\
+                    let w: u8 = 300 cast u8;\nlet big: u64 = 18446744073709551615;";
+        let checked = checked(text);
+        let out_of_range =
+            |value, to, start, end| (TypeErrorKind::LiteralOutOfRange { value, to }, Span::new(start, end));
+        let kinds: Vec<_> = checked.errors.iter().map(|e| (e.kind.clone(), e.span)).collect();
+        assert_eq!(
+            kinds,
+            [
+                out_of_range(300, IntTy::U8, 12, 15),
+                out_of_range(-1, IntTy::U8, 47, 49),
+                out_of_range(200, IntTy::I8, 95, 98),
+                out_of_range(65536, IntTy::U16, 103, 108),
+                out_of_range(256, IntTy::U8, 135, 138),
+            ]
+        );
+        // a cast wraps on purpose and only warns
+        assert_eq!(checked.warnings.len(), 1);
+        let message = checked.errors[0].kind.message(&checked.tys);
+        assert_eq!(message, "literal `300` doesn't fit in `u8`");
+    }
+
+    #[test]
+    fn signatures_and_returns_are_checked() {
+        let text = "fn f(let a: u8) -> bool { a }\nfn g() -> u32 { let x = 1; }\nfn h() -> u8 { return 1.0; }\n\
+                    fn k() -> u8 { return 1; }\nf(1, 2);\nf(true);\nlet n: u8 = 5;\nn(1);";
+        let kinds: Vec<_> = errors(text).into_iter().map(|e| (e.kind.code(), e.span)).collect();
+        assert_eq!(
+            kinds,
+            [
+                ("E0301", Span::new(26, 27)),
+                ("E0308", Span::new(33, 34)),
+                ("E0301", Span::new(81, 84)),
+                ("E0305", Span::new(115, 122)),
+                ("E0301", Span::new(126, 130)),
+                ("E0304", Span::new(148, 149)),
+            ]
+        );
+    }
+
+    #[test]
+    fn operators_need_matching_operands() {
+        let text = "let a: u8 = 1;\nlet b: u16 = 2;\na + b;\na < 1.5;\n!1.0;\n!a;\n\
+                    let c: f32 = 1;\nc << 1;\nlet d: u8 = a << 9;";
+        let checked = checked(text);
+        let messages: Vec<_> = checked.errors.iter().map(|e| e.kind.message(&checked.tys)).collect();
+        assert_eq!(
+            messages,
+            [
+                "`+` can't be applied to `u8` and `u16`",
+                "`<` can't be applied to `u8` and `{float}`",
+                "`!` can't be applied to `{float}`",
+                "`!` can't be applied to `u8`",
+                "`<<` can't be applied to `f32` and `{integer}`",
+            ]
+        );
+    }
+
+    #[test]
+    fn strings_are_joined_at_compile_time() {
+        let text = "let s = compiletime { \"ab\" + \"c\\n\" };\nlet n: usize = compiletime { (\"ab\" + \"c\").len };";
+        let checked = checked(text);
+        assert_eq!(checked.errors, []);
+        let tys: Vec<_> = checked.bindings.iter().map(|(_, ty)| checked.tys.display(*ty).to_string()).collect();
+        assert_eq!(tys, ["[4 u8]", "usize"]);
+
+        let errors = errors("let s = \"ab\" + \"cd\";\nlet n = \"ab\".len;");
+        let codes: Vec<_> = errors.iter().map(TypeError::code).collect();
+        assert_eq!(codes, ["E0303", "E0306"]);
+    }
+
+    #[test]
+    fn enums_have_intrinsics() {
+        let text = "enum E { a, b, c }\nlet n = E::variant_count;\nlet all = E::ALL;\nlet b: E = all[1];";
+        let checked = checked(text);
+        assert_eq!(checked.errors, []);
+        let tys: Vec<_> = checked.bindings.iter().map(|(_, ty)| checked.tys.display(*ty).to_string()).collect();
+        assert_eq!(tys, ["usize", "[3 E]", "E"]);
+        let intrinsics: Vec<_> = checked.intrinsics.iter().map(|&(_, index)| index).collect();
+        assert_eq!(intrinsics, [0, 0]);
+    }
+
+    #[test]
+    fn imported_names_are_unknown() {
+        let text = "import shapes.point;\nfn origin() -> point { point { x: 0 } }\nlet p: *point = uninit;";
+        assert_eq!(errors(text), []);
+        assert_eq!(errors("let p: point = uninit;")[0].kind, TypeErrorKind::UnknownType("point"));
+    }
+
+    #[test]
+    fn enums_have_discriminants_and_representations() {
+        let text = "#[repr(u16)] enum Wide { a, b }\nenum Tag { none, some, other }\n#[repr(f32)] enum Bad { x }\n\
+                    let t: Tag = Tag::some;\nlet n: u8 = Tag::other cast u8;\nlet m: u16 = none cast u16;\n\
+                    let w: f32 = t cast f32;\nmatch t { Tag::none => 1, some => 2, _ => 3, };";
+        let small = checked(text);
+        let reprs: Vec<_> = small.enums.iter().map(|layout| (layout.name.name, layout.repr)).collect();
+        assert_eq!(reprs, [("Wide", IntTy::U16), ("Tag", IntTy::U8), ("Bad", IntTy::U64)]);
+        assert_eq!(small.enums[1].discriminant("other"), Some(2));
+        let discriminants: Vec<_> = small.discriminants.iter().map(|&(_, discriminant)| discriminant).collect();
+        assert_eq!(discriminants, [1, 2, 0, 0, 1]);
+        let messages: Vec<_> = small.errors.iter().map(|e| (e.code(), e.kind.message(&small.tys))).collect();
+        assert_eq!(
+            messages,
+            [
+                ("E0313", "`repr` takes one integer type, like `#[repr(u8)]`".into()),
+                ("E0315", "an enum can only be cast to an integer type, not `f32`".into()),
+            ]
+        );
+
+        let variants: Vec<_> = (0..300).map(|index| format!("v{index}")).collect();
+        let text = format!(
+            "enum Big {{ {} }}\n#[repr(u8)] enum Small {{ {} }}\n\
+             let x = Big::v299 cast u8;\nlet y = Big::v255 cast u8;\nfn f(let big: Big) -> i8 {{ big cast i8 }}",
+            variants.join(", "),
+            variants.join(", "),
+        );
+        let big = checked(&text);
+        assert_eq!(big.enums[0].repr, IntTy::U16);
+        let kinds: Vec<_> = big.errors.iter().map(|e| e.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            [
+                TypeErrorKind::TooManyVariants {
+                    repr: IntTy::U8,
+                    count: 300,
+                },
+                TypeErrorKind::DiscriminantTruncated {
+                    discriminant: 299,
+                    to: IntTy::U8,
+                },
+                TypeErrorKind::DiscriminantTruncated {
+                    discriminant: 299,
+                    to: IntTy::I8,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn struct_literals_and_union_members() {
+        let text = "struct P { x: i32, y: u8 }\nunion U { bits: u32, f: f32 }\nenum E { a }\n\
+                    let p = P { y: 1, x: -2 };\nlet sum: i32 = p.x + 1;\nlet u: mut U = U { f: 1.5 };\n\
+                    u.bits = 3;\nlet b: u32 = u.bits cast u32;";
+        let result = checked(text);
+        assert_eq!(result.errors, []);
+        let layouts: Vec<_> = result.structs.iter().map(|s| (s.name.name, s.is_union, s.fields.len())).collect();
+        assert_eq!(layouts, [("P", false, 2), ("U", true, 2)]);
+        assert_eq!((result.structs[0].field("y"), result.structs[1].field("f")), (Some(TyId::U8), Some(TyId::F32)));
+        assert_eq!(result.structs[0].field("z"), None);
+
+        let text = "struct P { x: i32, y: u8, z: bool }\nunion U { bits: u32, f: f32 }\nenum E { a }\n\
+                    let p = P { x: true, w: 1 };\nlet u = U { bits: 1, f: 2.0 };\nlet e = E { a: 1 };\n\
+                    let v = U {};\nlet r: u32 = u.bits + 1;";
+        let result = checked(text);
+        let messages: Vec<_> = result.errors.iter().map(|e| (e.code(), e.kind.message(&result.tys))).collect();
+        assert_eq!(
+            messages,
+            [
+                ("E0301", "mismatched types: expected `i32`, found `bool`".into()),
+                ("E0306", "`P` has no field `w`".into()),
+                ("E0317", "missing fields `y`, `z` in literal of `P`".into()),
+                ("E0318", "a literal of `U` sets exactly one member, found 2".into()),
+                ("E0320", "`E` is not a struct or union".into()),
+                ("E0318", "a literal of `U` sets exactly one member, found 0".into()),
+                ("E0319", "reading union member `bits` needs an explicit `cast`".into()),
+            ]
+        );
+        let spans: Vec<_> = result.errors.iter().map(|e| e.span).collect();
+        assert_eq!(spans[..3], [Span::new(94, 98), Span::new(100, 101), Span::new(87, 106)]);
+    }
+
+    #[test]
+    fn casts_follow_the_conversion_rules() {
+        let text = "fn f(let i: i32, let u: u8, let x: f32, let b: bool, let p: *u8) {\n\
+                    i cast i64; u cast i16; u cast f32; x cast f64; b cast u8; 1 cast f32; -128 cast i8;\n\
+                    p cast *mut u16; p cast usize; 0 cast *u8; b cast bool;\n\
+                    'x' cast u8; 'é' cast u32; '€' cast f32;\n}";
+        let result = checked(text);
+        assert_eq!((result.errors, result.warnings), (vec![], vec![]));
+
+        let text = "enum E { a }\nfn f(let i: i32, let x: f64, let b: bool, let p: *u8) {\n\
+                    i cast u8; i cast f32; x cast f32; x cast i64; 2.5 cast u8; 300 cast u8; -1 cast u32;\n\
+                    i cast bool; b cast f32; i cast E; p cast u8; x cast *u8; 'é' cast u8; '🦀' cast u16;\n}";
+        let result = checked(text);
+        let warnings: Vec<_> = result.warnings.iter().map(|w| w.kind.message(&result.tys)).collect();
+        assert_eq!(
+            warnings,
+            [
+                "casting `i32` to `u8` may change the value",
+                "casting `i32` to `f32` may change the value",
+                "casting `f64` to `f32` may change the value",
+                "casting `f64` to `i64` may change the value",
+                "casting `{float}` to `u8` may change the value",
+                "literal `300` doesn't fit in `u8`, the cast makes it `44`",
+                "literal `-1` doesn't fit in `u32`, the cast makes it `4294967295`",
+            ]
+        );
+        let errors: Vec<_> = result.errors.iter().map(|e| (e.code(), e.kind.message(&result.tys))).collect();
+        assert_eq!(
+            errors,
+            [
+                ("E0321", "`i32` can't be cast to `bool`".into()),
+                ("E0321", "`bool` can't be cast to `f32`".into()),
+                ("E0321", "`i32` can't be cast to `E`".into()),
+                ("E0321", "`*u8` can't be cast to `u8`".into()),
+                ("E0321", "`f64` can't be cast to `*u8`".into()),
+                ("E0323", "character `é` doesn't fit in `u8`".into()),
+                ("E0323", "character `🦀` doesn't fit in `u16`".into()),
+            ]
+        );
+        assert_eq!(result.warnings[0].span, Span::new(69, 78));
+    }
+}
@@ -1,3 +1,8 @@
+use core::fmt::Display;
+
+use crate::source_code::SourceCode;
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Token {
     KwLet,
@@ -12,6 +17,15 @@ pub enum Token {
     KwStatic,
     KwType,
     KwCast,
+    KwIf,
+    KwElse,
+    KwWhile,
+    KwLoop,
+    KwBreak,
+    KwContinue,
+    KwMatch,
+    KwMod,
+    KwImport,
 
     KwAdtStruct,
     KwAdtEnum,
@@ -29,7 +43,12 @@ pub enum Token {
     PuncComma,
     PuncSemi,
     PuncColon,
+    PuncColonColon,
     PuncArrowRight,
+    PuncFatArrow,
+    PuncQuestion,
+    PuncHash,
+    PuncAt,
 
     PuncEq,
     PuncEqEq,
@@ -49,6 +68,7 @@ pub enum Token {
     PuncAnd,
     PuncOr,
     PuncXor,
+    PuncTilde,
 
     PuncShl,
     PuncShr,
@@ -70,6 +90,9 @@ pub enum Token {
     IndentRBrace,
     IndentLBracket,
     IndentRBracket,
+
+    /// stands in for malformed input when the lexer runs in error recovery mode.
+    Error,
 }
 
 impl Token {
@@ -86,6 +109,15 @@ impl Token {
         Token::KwStatic,
         Token::KwType,
         Token::KwCast,
+        Token::KwIf,
+        Token::KwElse,
+        Token::KwWhile,
+        Token::KwLoop,
+        Token::KwBreak,
+        Token::KwContinue,
+        Token::KwMatch,
+        Token::KwMod,
+        Token::KwImport,
         Token::KwAdtStruct,
         Token::KwAdtEnum,
         Token::KwAdtUnion,
@@ -100,7 +132,12 @@ impl Token {
         Token::PuncComma,
         Token::PuncSemi,
         Token::PuncColon,
+        Token::PuncColonColon,
         Token::PuncArrowRight,
+        Token::PuncFatArrow,
+        Token::PuncQuestion,
+        Token::PuncHash,
+        Token::PuncAt,
         Token::PuncEq,
         Token::PuncEqEq,
         Token::PuncBang,
@@ -117,14 +154,26 @@ impl Token {
         Token::PuncAnd,
         Token::PuncOr,
         Token::PuncXor,
+        Token::PuncTilde,
         Token::PuncShl,
         Token::PuncShr,
+        Token::PuncPlusEq,
+        Token::PuncMinusEq,
+        Token::PuncStarEq,
+        Token::PuncSlashEq,
+        Token::PuncModuloEq,
+        Token::PuncAndEq,
+        Token::PuncOrEq,
+        Token::PuncXorEq,
+        Token::PuncShlEq,
+        Token::PuncShrEq,
         Token::IndentLParen,
         Token::IndentRParen,
         Token::IndentLBrace,
         Token::IndentRBrace,
         Token::IndentLBracket,
         Token::IndentRBracket,
+        Token::Error,
     ];
 
     #[rustfmt::skip]
@@ -142,6 +191,40 @@ impl Token {
         )
     }
 
+    /// the reserved words, which never lex as identifiers.
+    #[inline]
+    pub const fn is_keyword(self) -> bool {
+        matches!(
+            self,
+            Token::KwLet
+                | Token::KwFn
+                | Token::KwReturn
+                | Token::KwExtern
+                | Token::KwConst
+                | Token::KwMut
+                | Token::KwAnymut
+                | Token::KwCompiletime
+                | Token::KwRuntime
+                | Token::KwStatic
+                | Token::KwType
+                | Token::KwCast
+                | Token::KwIf
+                | Token::KwElse
+                | Token::KwWhile
+                | Token::KwLoop
+                | Token::KwBreak
+                | Token::KwContinue
+                | Token::KwMatch
+                | Token::KwMod
+                | Token::KwImport
+                | Token::KwAdtStruct
+                | Token::KwAdtEnum
+                | Token::KwAdtUnion
+        )
+    }
+
+    /// the text of the token. lexing it yields the token back, except for the literals
+    /// that carry a value (and `Error`), which are written as `{kind}` instead.
     #[inline]
     pub const fn source_repr(self) -> &'static str {
         match self {
@@ -157,6 +240,15 @@ impl Token {
             Token::KwStatic => "static",
             Token::KwType => "type",
             Token::KwCast => "cast",
+            Token::KwIf => "if",
+            Token::KwElse => "else",
+            Token::KwWhile => "while",
+            Token::KwLoop => "loop",
+            Token::KwBreak => "break",
+            Token::KwContinue => "continue",
+            Token::KwMatch => "match",
+            Token::KwMod => "mod",
+            Token::KwImport => "import",
             Token::KwAdtStruct => "struct",
             Token::KwAdtEnum => "enum",
             Token::KwAdtUnion => "union",
@@ -171,7 +263,12 @@ impl Token {
             Token::PuncComma => ",",
             Token::PuncSemi => ";",
             Token::PuncColon => ":",
+            Token::PuncColonColon => "::",
             Token::PuncArrowRight => "->",
+            Token::PuncFatArrow => "=>",
+            Token::PuncQuestion => "?",
+            Token::PuncHash => "#",
+            Token::PuncAt => "@",
             Token::PuncEq => "=",
             Token::PuncEqEq => "==",
             Token::PuncBang => "!",
@@ -188,6 +285,7 @@ impl Token {
             Token::PuncAnd => "&",
             Token::PuncOr => "|",
             Token::PuncXor => "^",
+            Token::PuncTilde => "~",
             Token::PuncShl => "<<",
             Token::PuncShr => ">>",
             Token::PuncPlusEq => "+=",
@@ -206,6 +304,154 @@ impl Token {
             Token::IndentRBrace => "}",
             Token::IndentLBracket => "[",
             Token::IndentRBracket => "]",
+            Token::Error => "{error}",
+        }
+    }
+}
+
+/// writes `source_repr`.
+impl Display for Token {
+    #[inline]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.source_repr())
+    }
+}
+
+/// half-open byte range `start..end` into a `SourceCode`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    #[inline]
+    pub const fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    #[inline(always)]
+    pub const fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    #[inline(always)]
+    pub const fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    #[inline]
+    pub const fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+
+    #[inline]
+    pub const fn range(&self) -> core::ops::Range<usize> {
+        self.start..self.end
+    }
+
+    /// smallest span covering both `self` and `other`, including anything in between.
+    #[inline]
+    pub const fn merge(self, other: Span) -> Span {
+        Span {
+            start: if self.start < other.start { self.start } else { other.start },
+            end: if self.end > other.end { self.end } else { other.end },
+        }
+    }
+}
+
+/// a value paired with the byte range of source it came from.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    #[inline]
+    pub const fn new(value: T, span: Span) -> Self {
+        Spanned { value, span }
+    }
+
+    #[inline]
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Spanned<U> {
+        Spanned {
+            value: f(self.value),
+            span: self.span,
+        }
+    }
+
+    #[inline]
+    pub const fn as_ref(&self) -> Spanned<&T> {
+        Spanned {
+            value: &self.value,
+            span: self.span,
+        }
+    }
+
+    /// 1-based line and column of the start of the span, see `SourceCode::line_column`.
+    #[inline]
+    pub const fn line_column(&self, source: &SourceCode<'_>) -> (usize, usize) {
+        source.line_column(self.span.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::{Span, Spanned, Token};
+    use crate::lexer::{ErrorKind, Lexer};
+    use crate::source_code::SourceCode;
+
+    #[test]
+    fn source_repr_round_trips() {
+        for (index, &token) in Token::ALL.iter().enumerate() {
+            assert!(!Token::ALL[..index].contains(&token), "{token:?} listed twice");
+            let repr = token.source_repr();
+            assert_eq!(token.to_string(), repr);
+            if repr.starts_with('{') {
+                continue;
+            }
+            let mut lexer = Lexer::new(SourceCode::new(repr));
+            assert_eq!(lexer.lex_single_token(), Ok(token), "{repr:?}");
+            assert_eq!(lexer.lex_single_token().map_err(|e| e.kind), Err(ErrorKind::Eof), "{repr:?}");
         }
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn tokens_deserialize_from_variant_names() {
+        use serde::Deserialize;
+        use serde::de::IntoDeserializer;
+        use serde::de::value::{Error, StrDeserializer};
+
+        for &token in Token::ALL {
+            let name = format!("{token:?}");
+            let deserializer: StrDeserializer<'_, Error> = name.as_str().into_deserializer();
+            assert_eq!(Token::deserialize(deserializer), Ok(token));
+        }
+    }
+
+    #[test]
+    fn span_merge_covers_both() {
+        let a = Span::new(4, 7);
+        let b = Span::new(10, 12);
+        assert_eq!(a.merge(b), Span::new(4, 12));
+        assert_eq!(b.merge(a), Span::new(4, 12));
+        assert_eq!(a.merge(Span::new(5, 6)), a);
+        assert_eq!(a.merge(b).len(), 8);
+    }
+
+    #[test]
+    fn spanned_map_keeps_span_and_resolves_position() {
+        let source = SourceCode::new("let\n  x");
+        let spanned = Spanned::new("x", Span::new(6, 7));
+        let mapped = spanned.map(str::len);
+        assert_eq!(mapped, Spanned::new(1, Span::new(6, 7)));
+        assert_eq!(mapped.line_column(&source), (2, 3));
+        assert_eq!(spanned.as_ref().value, &"x");
+    }
 }
@@ -0,0 +1,277 @@
+//! checks of what one stage promises the next, run by the driver in debug builds or when the
+//! `Session` asks for them. a `VerifyError` is a bug in the compiler, not in the program, it is
+//! reported like any other error so the stage that broke the promise is found where it did.
+//!
+//! `expr_types` checks that type checking gave every expression a type, `program` that every
+//! local, block and function the ir refers to exists.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::ast::visit::{Visitor, walk_expr, walk_stmts};
+use crate::ast::{Expr, Stmt, TypeExpr};
+use crate::ir::{BlockId, Body, BodyId, Constant, LocalId, Operand, Place, Program, Projection, Rvalue};
+use crate::ir::{StatementKind, TerminatorKind};
+use crate::typeck::Checked;
+use crate::types::Span;
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VerifyErrorKind {
+    /// `Checked::expr_ty` has nothing for the expression.
+    UntypedExpr,
+    /// a place or an index refers to a local `body` doesn't have.
+    LocalOutOfBounds { body: BodyId, local: LocalId },
+    /// a terminator jumps to a block `body` doesn't have.
+    BlockOutOfBounds { body: BodyId, block: BlockId },
+    /// a constant refers to a function the program doesn't have.
+    BodyOutOfBounds { body: BodyId, callee: BodyId },
+}
+
+impl VerifyErrorKind {
+    /// stable identifier like `ParseErrorKind::code`, verification codes start at `E0701`.
+    #[inline]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            VerifyErrorKind::UntypedExpr => "E0701",
+            VerifyErrorKind::LocalOutOfBounds { .. } => "E0702",
+            VerifyErrorKind::BlockOutOfBounds { .. } => "E0703",
+            VerifyErrorKind::BodyOutOfBounds { .. } => "E0704",
+        }
+    }
+}
+
+impl fmt::Display for VerifyErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyErrorKind::UntypedExpr => f.write_str("expression has no type after type checking"),
+            VerifyErrorKind::LocalOutOfBounds { body, local } => write!(f, "`{}` is not a local of `{}`", local, body),
+            VerifyErrorKind::BlockOutOfBounds { body, block } => write!(f, "`{}` is not a block of `{}`", block, body),
+            VerifyErrorKind::BodyOutOfBounds { body, callee } => {
+                write!(f, "`{}` refers to `{}`, which is not in the program", body, callee)
+            }
+        }
+    }
+}
+
+/// the span covers the expression, statement or terminator that broke the check.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VerifyError {
+    pub kind: VerifyErrorKind,
+    pub span: Span,
+}
+
+impl VerifyError {
+    #[inline]
+    pub const fn new(kind: VerifyErrorKind, span: Span) -> Self {
+        VerifyError { kind, span }
+    }
+
+    #[inline]
+    pub const fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+}
+
+impl fmt::Display for VerifyError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.kind.fmt(f)
+    }
+}
+
+/// every expression of `stmts` without a type in `checked`, which has to be the result of
+/// checking them.
+pub fn expr_types(stmts: &[Stmt<'_>], checked: &Checked<'_>) -> Vec<VerifyError> {
+    let mut verifier = ExprTypes {
+        checked,
+        errors: Vec::new(),
+    };
+    walk_stmts(&mut verifier, stmts);
+    verifier.errors
+}
+
+struct ExprTypes<'c, 'tys> {
+    checked: &'c Checked<'tys>,
+    errors: Vec<VerifyError>,
+}
+
+impl<'ast, 'source: 'ast> Visitor<'ast, 'source> for ExprTypes<'_, '_> {
+    fn visit_expr(&mut self, expr: &'ast Expr<'source>) {
+        if self.checked.expr_ty(expr.span).is_none() {
+            self.errors.push(VerifyError::new(VerifyErrorKind::UntypedExpr, expr.span));
+        }
+        walk_expr(self, expr);
+    }
+
+    /// the length of an array type is read as a literal, it is no expression of the program.
+    #[inline]
+    fn visit_type(&mut self, _ty: &'ast TypeExpr<'source>) {}
+}
+
+/// every local, block and function `program` refers to but doesn't have.
+pub fn program(program: &Program<'_>) -> Vec<VerifyError> {
+    let mut errors = Vec::new();
+    for (index, body) in program.bodies.iter().enumerate() {
+        let mut verifier = BodyVerifier {
+            id: BodyId::new(index),
+            body,
+            body_count: program.bodies.len(),
+            errors: &mut errors,
+        };
+        verifier.body();
+    }
+    errors
+}
+
+struct BodyVerifier<'a, 'source> {
+    id: BodyId,
+    body: &'a Body<'source>,
+    body_count: usize,
+    errors: &'a mut Vec<VerifyError>,
+}
+
+impl BodyVerifier<'_, '_> {
+    fn body(&mut self) {
+        if self.body.locals.len() <= self.body.param_count {
+            let local = LocalId::new(self.body.param_count);
+            self.error(VerifyErrorKind::LocalOutOfBounds { body: self.id, local }, self.body.span);
+        }
+        for block in &self.body.blocks {
+            for statement in &block.statements {
+                match &statement.kind {
+                    StatementKind::Assign(place, rvalue) => {
+                        self.place(place, statement.span);
+                        self.rvalue(rvalue, statement.span);
+                    }
+                    StatementKind::Eval(rvalue) => self.rvalue(rvalue, statement.span),
+                }
+            }
+            let span = block.terminator.span;
+            if let TerminatorKind::Branch { cond, .. } = &block.terminator.kind {
+                self.operand(cond, span);
+            }
+            for target in block.terminator.kind.successors() {
+                if target.index() >= self.body.blocks.len() {
+                    self.error(VerifyErrorKind::BlockOutOfBounds { body: self.id, block: target }, span);
+                }
+            }
+        }
+    }
+
+    fn rvalue(&mut self, rvalue: &Rvalue<'_>, span: Span) {
+        match rvalue {
+            Rvalue::Use(operand) | Rvalue::Unary(_, operand) | Rvalue::Cast(operand, _) => self.operand(operand, span),
+            Rvalue::Binary(_, lhs, rhs) => {
+                self.operand(lhs, span);
+                self.operand(rhs, span);
+            }
+            Rvalue::Call { callee, args } => {
+                self.operand(callee, span);
+                for arg in args {
+                    self.operand(arg, span);
+                }
+            }
+            Rvalue::Struct { fields, .. } => {
+                for (_, value) in fields {
+                    self.operand(value, span);
+                }
+            }
+        }
+    }
+
+    fn operand(&mut self, operand: &Operand<'_>, span: Span) {
+        match operand {
+            Operand::Copy(place) => self.place(place, span),
+            Operand::Const(Constant::Fn(callee)) if callee.index() >= self.body_count => {
+                self.error(VerifyErrorKind::BodyOutOfBounds { body: self.id, callee: *callee }, span);
+            }
+            Operand::Const(_) => {}
+        }
+    }
+
+    fn place(&mut self, place: &Place<'_>, span: Span) {
+        let indices = place.projection.iter().filter_map(|projection| match projection {
+            Projection::Index(local) => Some(*local),
+            Projection::Field(_) | Projection::Deref => None,
+        });
+        for local in [place.local].into_iter().chain(indices) {
+            if local.index() >= self.body.locals.len() {
+                self.error(VerifyErrorKind::LocalOutOfBounds { body: self.id, local }, span);
+            }
+        }
+    }
+
+    #[inline]
+    fn error(&mut self, kind: VerifyErrorKind, span: Span) {
+        self.errors.push(VerifyError::new(kind, span));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::string::ToString;
+
+    use super::{VerifyError, VerifyErrorKind, expr_types, program};
+    use crate::ir::{BlockId, BodyId, Constant, LocalId, Operand, Place, Rvalue, StatementKind, TerminatorKind, lower};
+    use crate::parser::tests::parse_ok;
+    use crate::typeck::check;
+    use crate::types::Span;
+
+    const PROGRAM: &str = "\
+        fn f(let a: [4 u8], let i: usize) -> u8 { let b = a[i]; if b > 2 { return b; } b + 1 }\n\
+        struct point { x: i32, y: i32 }\n\
+        let p = point { x: 1, y: 2 };\n\
+        let n = f(uninit, 3) cast i32 + p.x;\n\
+        let m = match n { 0 => 1, _ => compiletime { 2 * 3 } };";
+
+    #[test]
+    fn checked_programs_verify() {
+        let stmts = parse_ok(PROGRAM);
+        let checked = check(stmts);
+        assert!(checked.errors.is_empty(), "{:?}", checked.errors);
+        assert_eq!(expr_types(stmts, &checked), []);
+        assert_eq!(program(&lower(stmts, &checked)), []);
+    }
+
+    #[test]
+    fn missing_types_are_reported() {
+        let stmts = parse_ok("let a = 1 + 2;");
+        let mut checked = check(stmts);
+        checked.expr_types.retain(|&(span, _)| span != Span::new(12, 13));
+        let errors = expr_types(stmts, &checked);
+        assert_eq!(errors, [VerifyError::new(VerifyErrorKind::UntypedExpr, Span::new(12, 13))]);
+        assert_eq!(errors[0].code(), "E0701");
+    }
+
+    #[test]
+    fn out_of_bounds_ids_are_reported() {
+        let stmts = parse_ok(PROGRAM);
+        let checked = check(stmts);
+        let mut lowered = lower(stmts, &checked);
+        let body = &mut lowered.bodies[1];
+        let (locals, blocks) = (LocalId::new(body.locals.len()), BlockId::new(body.blocks.len()));
+        let block = &mut body.blocks[0];
+        let span = block.terminator.span;
+        block.terminator.kind = TerminatorKind::Goto(blocks);
+        let missing = Operand::Const(Constant::Fn(BodyId::new(9)));
+        block.statements[0].kind = StatementKind::Assign(Place::local(locals), Rvalue::Use(missing));
+        let statement = block.statements[0].span;
+
+        let fn1 = BodyId::new(1);
+        assert_eq!(
+            program(&lowered),
+            [
+                VerifyError::new(VerifyErrorKind::LocalOutOfBounds { body: fn1, local: locals }, statement),
+                VerifyError::new(VerifyErrorKind::BodyOutOfBounds { body: fn1, callee: BodyId::new(9) }, statement),
+                VerifyError::new(VerifyErrorKind::BlockOutOfBounds { body: fn1, block: blocks }, span),
+            ]
+        );
+        assert_eq!(
+            VerifyErrorKind::BlockOutOfBounds { body: fn1, block: blocks }.to_string(),
+            format!("`{}` is not a block of `fn1`", blocks)
+        );
+    }
+}
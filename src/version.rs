@@ -0,0 +1,93 @@
+use core::fmt::Display;
+
+/// cargo features that change the behavior of the crate, paired with whether
+/// they were enabled for this build.
+pub const FEATURES: &[(&str, bool)] = &[
+    ("alloc-profiling", cfg!(feature = "alloc-profiling")),
+    ("serde", cfg!(feature = "serde")),
+    ("safe", cfg!(feature = "safe")),
+    ("mmap", cfg!(feature = "mmap")),
+];
+
+/// language editions this build of the compiler understands.
+///
+/// the spec is still a draft, so there is only one.
+pub const LANGUAGE_EDITIONS: &[&str] = &["draft"];
+
+/// everything a bug report or a tooling handshake needs to know about this build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VersionInfo {
+    pub version: &'static str,
+    pub features: &'static [(&'static str, bool)],
+    pub language_editions: &'static [&'static str],
+    pub target_arch: &'static str,
+    pub target_os: &'static str,
+    /// width of `usize`/`isize` after monomorphization when targeting the host
+    pub target_pointer_width: u32,
+}
+
+impl VersionInfo {
+    #[inline]
+    pub fn enabled_features(&self) -> impl Iterator<Item = &'static str> {
+        self.features.iter().filter(|(_, enabled)| *enabled).map(|(name, _)| *name)
+    }
+}
+
+#[inline]
+pub const fn version_info() -> VersionInfo {
+    VersionInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        features: FEATURES,
+        language_editions: LANGUAGE_EDITIONS,
+        target_arch: std::env::consts::ARCH,
+        target_os: std::env::consts::OS,
+        target_pointer_width: usize::BITS,
+    }
+}
+
+/// `{}` prints the short `mumbo x.y.z` line, `{:#}` adds one `key: value` line per field.
+impl Display for VersionInfo {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "mumbo {}", self.version)?;
+        if !f.alternate() {
+            return Ok(());
+        }
+
+        write!(f, "\nfeatures:")?;
+        let mut any = false;
+        for feature in self.enabled_features() {
+            write!(f, " {}", feature)?;
+            any = true;
+        }
+        if !any {
+            write!(f, " (none)")?;
+        }
+        write!(f, "\nlanguage editions: {}", self.language_editions.join(", "))?;
+        write!(f, "\ntarget: {}-{}", self.target_arch, self.target_os)?;
+        write!(f, "\ntarget pointer width: {}", self.target_pointer_width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FEATURES, version_info};
+
+    #[test]
+    fn version_matches_manifest() {
+        let info = version_info();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(info.to_string(), format!("mumbo {}", env!("CARGO_PKG_VERSION")));
+    }
+
+    #[test]
+    fn verbose_output_lists_enabled_features() {
+        let info = version_info();
+        let verbose = format!("{:#}", info);
+        assert!(verbose.starts_with(&info.to_string()));
+        assert!(verbose.contains("language editions: draft"));
+        assert!(verbose.contains(&format!("target pointer width: {}", usize::BITS)));
+        for (name, enabled) in FEATURES {
+            assert_eq!(info.enabled_features().any(|f| f == *name), *enabled);
+        }
+    }
+}